@@ -15,6 +15,9 @@ const SENSITIVE_FIELDS = new Set([
   "programKeypairJson",
   "security_answer",
   "totp_code",
+  "passphrase",
+  "current_passphrase",
+  "new_passphrase",
 ]);
 
 function walk(dir) {
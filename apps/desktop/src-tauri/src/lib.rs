@@ -4,6 +4,9 @@ use aes_gcm::{
 };
 use aws_lc_rs::rsa::{OaepPublicEncryptingKey, PublicEncryptingKey, OAEP_SHA256_MGF1SHA256};
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+#[cfg(not(target_os = "macos"))]
+use fnzero_safe_app_services::credential_store::platform_credential_store;
+use fnzero_safe_app_services::credential_store::{credential_store_policy, CredentialStore};
 use rand::{rngs::OsRng, RngCore};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
@@ -32,7 +35,6 @@ const DAPP_TAB_TITLE_EVENT: &str = "dapp://tab-title";
 const DAPP_NEW_WINDOW_EVENT: &str = "dapp://new-window";
 const DAPP_REQUEST_TTL_MS: u64 = 3 * 60 * 1000;
 const DAPP_WALLET_NAME: &str = "FnzeroSafe";
-#[cfg(target_os = "macos")]
 const BIOMETRIC_WALLET_PASSWORD_SERVICE: &str = "dev.fnzero-safe.wallet.password.v3";
 
 #[derive(Clone)]
@@ -156,6 +158,7 @@ struct BiometricWalletStatus {
     supported: bool,
     configured: bool,
     reason: Option<String>,
+    backend: Option<&'static str>,
 }
 
 fn biometric_wallet_account(wallet_id: &str, public_key: &str) -> Result<String, String> {
@@ -213,7 +216,10 @@ fn biometric_touch_id_available() -> Result<(), String> {
 
 #[cfg(target_os = "macos")]
 mod biometric_wallet_keychain {
-    use super::{biometric_error_message, BIOMETRIC_WALLET_PASSWORD_SERVICE};
+    use super::{
+        biometric_error_message, biometric_touch_id_available, BIOMETRIC_WALLET_PASSWORD_SERVICE,
+    };
+    use fnzero_safe_app_services::credential_store::CredentialStore;
     use security_framework::{
         access_control::{ProtectionMode, SecAccessControl},
         item::{ItemClass, ItemSearchOptions},
@@ -222,6 +228,7 @@ mod biometric_wallet_keychain {
             AccessControlOptions, PasswordOptions,
         },
     };
+    use zeroize::Zeroizing;
 
     const ERR_SEC_ITEM_NOT_FOUND: i32 = -25300;
     const LEGACY_SERVICES: &[&str] = &[
@@ -295,74 +302,98 @@ mod biometric_wallet_keychain {
         }
         Ok(())
     }
+
+    /// Keychain items guarded by the current Touch ID enrollment
+    pub struct TouchIdKeychain;
+
+    impl CredentialStore for TouchIdKeychain {
+        fn backend(&self) -> &'static str {
+            "Touch ID"
+        }
+
+        fn available(&self) -> Result<(), String> {
+            biometric_touch_id_available()
+        }
+
+        fn contains(&self, account: &str) -> Result<bool, String> {
+            configured(account)
+        }
+
+        fn store(&self, account: &str, secret: &str) -> Result<(), String> {
+            store(account, secret)
+        }
+
+        fn load(&self, account: &str) -> Result<Zeroizing<String>, String> {
+            load(account).map(Zeroizing::new)
+        }
+
+        fn delete(&self, account: &str) -> Result<(), String> {
+            delete(account)
+        }
+    }
 }
 
-#[tauri::command]
-fn biometric_wallet_status(req: BiometricWalletRequest) -> Result<BiometricWalletStatus, String> {
-    let account = biometric_wallet_account(&req.wallet_id, &req.public_key)?;
+/// Touch ID on macOS, Secret Service on Linux; other platforms report the reason
+fn biometric_credential_store() -> Result<Box<dyn CredentialStore>, String> {
     #[cfg(target_os = "macos")]
     {
-        let supported = biometric_touch_id_available();
-        let configured = biometric_wallet_keychain::configured(&account)?;
-        Ok(BiometricWalletStatus {
-            supported: supported.is_ok(),
-            configured: configured && supported.is_ok(),
-            reason: supported.err(),
-        })
+        Ok(Box::new(biometric_wallet_keychain::TouchIdKeychain))
     }
     #[cfg(not(target_os = "macos"))]
     {
-        let _ = account;
-        Ok(BiometricWalletStatus {
-            supported: false,
-            configured: false,
-            reason: Some("Touch ID 只支持 macOS 桌面客户端".to_string()),
-        })
+        platform_credential_store(BIOMETRIC_WALLET_PASSWORD_SERVICE)
+            .map_err(|_| "快速解锁只支持 macOS 与 Linux 桌面客户端".to_string())
     }
 }
 
+#[tauri::command]
+fn biometric_wallet_status(req: BiometricWalletRequest) -> Result<BiometricWalletStatus, String> {
+    let account = biometric_wallet_account(&req.wallet_id, &req.public_key)?;
+    let store = match biometric_credential_store() {
+        Ok(store) => store,
+        Err(reason) => {
+            return Ok(BiometricWalletStatus {
+                supported: false,
+                configured: false,
+                reason: Some(reason),
+                backend: None,
+            })
+        }
+    };
+    let policy = credential_store_policy(store.as_ref(), &account)?;
+    Ok(BiometricWalletStatus {
+        supported: policy.supported,
+        configured: policy.configured,
+        reason: policy.reason,
+        backend: Some(store.backend()),
+    })
+}
+
 #[tauri::command]
 fn biometric_wallet_store_password(req: BiometricWalletStoreRequest) -> Result<(), String> {
     let account = biometric_wallet_account(&req.wallet_id, &req.public_key)?;
     if req.password.is_empty() {
         return Err("wallet password is required".to_string());
     }
-    #[cfg(target_os = "macos")]
-    {
-        biometric_wallet_keychain::store(&account, &req.password)
-    }
-    #[cfg(not(target_os = "macos"))]
-    {
-        let _ = account;
-        Err("Touch ID 只支持 macOS 桌面客户端".to_string())
-    }
+    let store = biometric_credential_store()?;
+    store.available()?;
+    store.store(&account, &req.password)
 }
 
 #[tauri::command]
 fn biometric_wallet_get_password(req: BiometricWalletRequest) -> Result<String, String> {
     let account = biometric_wallet_account(&req.wallet_id, &req.public_key)?;
-    #[cfg(target_os = "macos")]
-    {
-        biometric_wallet_keychain::load(&account)
-    }
-    #[cfg(not(target_os = "macos"))]
-    {
-        let _ = account;
-        Err("Touch ID 只支持 macOS 桌面客户端".to_string())
-    }
+    let password = biometric_credential_store()?.load(&account)?;
+    Ok(password.to_string())
 }
 
 #[tauri::command]
 fn biometric_wallet_delete_password(req: BiometricWalletRequest) -> Result<(), String> {
     let account = biometric_wallet_account(&req.wallet_id, &req.public_key)?;
-    #[cfg(target_os = "macos")]
-    {
-        biometric_wallet_keychain::delete(&account)
-    }
-    #[cfg(not(target_os = "macos"))]
-    {
-        let _ = account;
-        Ok(())
+    match biometric_credential_store() {
+        Ok(store) => store.delete(&account),
+        // Nothing can have been stored on a platform without a credential store
+        Err(_) => Ok(()),
     }
 }
 
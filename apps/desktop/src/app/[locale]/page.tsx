@@ -981,6 +981,7 @@ interface BiometricWalletStatus {
   supported: boolean;
   configured: boolean;
   reason?: string | null;
+  backend?: string | null;
 }
 
 type DappId =
@@ -1070,6 +1071,47 @@ interface DappTransactionPreview {
   warnings: string[];
 }
 
+interface RemoteSignPairing {
+  id: string;
+  name: string;
+  relay_url: string;
+  topic: string;
+  public_key: string;
+  client_public_key?: string | null;
+  client_fingerprint?: string | null;
+  created_at: number;
+  last_seen_at?: number | null;
+  uri: string;
+}
+
+interface RemoteSignRequest {
+  id: string;
+  pairing_id: string;
+  pairing_name?: string | null;
+  client_fingerprint?: string | null;
+  request_id: string;
+  status: "pending" | "signing" | "approved" | "rejected" | "expired" | string;
+  delivered: boolean;
+  received_at: number;
+  created_at: number;
+  expires_at?: number | null;
+  note?: string | null;
+  kind: "transaction" | "message";
+  required_signer: string;
+  transaction_base64?: string;
+  transaction_format?: string;
+  network?: string;
+  submit?: boolean;
+  expected_genesis_hash?: string;
+  recent_blockhash?: string;
+  last_valid_block_height?: number;
+  message_base64?: string;
+  preview?: DappTransactionPreview;
+  preview_error?: string;
+}
+
+const REMOTE_SIGN_REFRESH_INTERVAL_MS = 5_000;
+
 const DAPP_CATEGORIES: DappCategoryId[] = ["trend", "defi", "trading", "nft", "staking"];
 const DAPP_CATALOG: DappCatalogItem[] = [
   {
@@ -2068,6 +2110,13 @@ export default function Home() {
   const [dappTransactionPreviewError, setDappTransactionPreviewError] = useState<string | null>(null);
   const [dappTransactionPreviewLoading, setDappTransactionPreviewLoading] = useState(false);
   const [dappPreviewDetailsOpen, setDappPreviewDetailsOpen] = useState(false);
+  const [remoteSignPairings, setRemoteSignPairings] = useState<RemoteSignPairing[]>([]);
+  const [remoteSignRequests, setRemoteSignRequests] = useState<RemoteSignRequest[]>([]);
+  const [remoteSignDefaultRelay, setRemoteSignDefaultRelay] = useState("");
+  const [remoteSignPairingName, setRemoteSignPairingName] = useState("");
+  const [remoteSignRelayUrl, setRemoteSignRelayUrl] = useState("");
+  const [remoteSignQrUri, setRemoteSignQrUri] = useState<string | null>(null);
+  const [remoteSignBusy, setRemoteSignBusy] = useState(false);
   const [biometricStatuses, setBiometricStatuses] = useState<Record<string, BiometricWalletStatus>>({});
   const [biometricBusyWalletId, setBiometricBusyWalletId] = useState<string | null>(null);
   const [savePasswordToBiometric, setSavePasswordToBiometric] = useState(false);
@@ -2139,6 +2188,7 @@ export default function Home() {
   const programDeploymentJournalRef = useRef<ProgramDeploymentJournalState>(emptyProgramDeploymentJournalState());
   const programDeploymentLogPanelRef = useRef<HTMLDivElement | null>(null);
   const privateKeyQrCanvasRef = useRef<HTMLCanvasElement | null>(null);
+  const remoteSignQrCanvasRef = useRef<HTMLCanvasElement | null>(null);
   const dappBrowserShellRef = useRef<HTMLDivElement | null>(null);
   const dappBrowserTabBarRef = useRef<HTMLDivElement | null>(null);
   const dappBrowserAddressBarRef = useRef<HTMLDivElement | null>(null);
@@ -5026,6 +5076,145 @@ export default function Home() {
     toast.success(t("features.external-sign.requestImported"));
   };
 
+  const loadRemoteSign = useCallback(async () => {
+    try {
+      const [pairingsResponse, requestsResponse] = await Promise.all([
+        apiFetch("remote-sign/pairings"),
+        apiFetch("remote-sign/requests", {
+          method: "POST",
+          headers: { "Content-Type": "application/json" },
+          body: JSON.stringify({ status: "pending" }),
+        }),
+      ]);
+      const pairingsData = await pairingsResponse.json();
+      const requestsData = await requestsResponse.json();
+      if (pairingsResponse.ok) {
+        setRemoteSignPairings(Array.isArray(pairingsData.pairings) ? pairingsData.pairings : []);
+        setRemoteSignDefaultRelay(String(pairingsData.default_relay_url || ""));
+      }
+      if (requestsResponse.ok && Array.isArray(requestsData)) {
+        setRemoteSignRequests(requestsData);
+      }
+    } catch (error) {
+      console.warn("Failed to load remote sign state", error);
+    }
+  }, []);
+
+  useEffect(() => {
+    if (selectedForm !== "external-sign") return;
+    void loadRemoteSign();
+    const timer = window.setInterval(() => void loadRemoteSign(), REMOTE_SIGN_REFRESH_INTERVAL_MS);
+    return () => window.clearInterval(timer);
+  }, [loadRemoteSign, selectedForm]);
+
+  useEffect(() => {
+    const canvas = remoteSignQrCanvasRef.current;
+    if (!canvas || !remoteSignQrUri) return;
+    void QRCode.toCanvas(canvas, remoteSignQrUri, {
+      width: 240,
+      margin: 2,
+      errorCorrectionLevel: "M",
+      color: {
+        dark: "#0f172a",
+        light: "#ffffff",
+      },
+    }).catch(() => toast.error(t("features.external-sign.remotePairingQrFailed")));
+  }, [remoteSignQrUri, t]);
+
+  const createRemoteSignPairing = async () => {
+    const name = remoteSignPairingName.trim();
+    const relayUrl = (remoteSignRelayUrl || remoteSignDefaultRelay).trim();
+    if (!name || !relayUrl) {
+      toast.error(t("features.external-sign.remotePairingFillFields"));
+      return;
+    }
+    setRemoteSignBusy(true);
+    try {
+      const response = await apiFetch("remote-sign/pairings/create", {
+        method: "POST",
+        headers: { "Content-Type": "application/json" },
+        body: JSON.stringify({ name, relay_url: relayUrl }),
+      });
+      const data = await response.json();
+      if (!response.ok) {
+        toast.error(data.error || t("features.external-sign.remoteActionFailed"));
+        return;
+      }
+      setRemoteSignPairingName("");
+      setRemoteSignQrUri(String(data.uri || "") || null);
+      toast.success(t("features.external-sign.remotePairingCreated"));
+      await loadRemoteSign();
+    } catch (error) {
+      toast.error(error instanceof Error ? error.message : t("features.external-sign.remoteActionFailed"));
+    } finally {
+      setRemoteSignBusy(false);
+    }
+  };
+
+  const postRemoteSignAction = async (path: string, body: Record<string, unknown>, success: string) => {
+    setRemoteSignBusy(true);
+    try {
+      const response = await apiFetch(path, {
+        method: "POST",
+        headers: { "Content-Type": "application/json" },
+        body: JSON.stringify(body),
+      });
+      const data = await response.json();
+      if (!response.ok) {
+        toast.error(data.error || t("features.external-sign.remoteActionFailed"));
+        return false;
+      }
+      toast.success(success);
+      await loadRemoteSign();
+      return true;
+    } catch (error) {
+      toast.error(error instanceof Error ? error.message : t("features.external-sign.remoteActionFailed"));
+      return false;
+    } finally {
+      setRemoteSignBusy(false);
+    }
+  };
+
+  const deleteRemoteSignPairing = async (pairing: RemoteSignPairing) => {
+    if (!window.confirm(t("features.external-sign.remotePairingDeleteConfirm", { name: pairing.name }))) return;
+    if (await postRemoteSignAction(
+      "remote-sign/pairings/delete",
+      { id: pairing.id },
+      t("features.external-sign.remotePairingDeleted"),
+    )) {
+      if (remoteSignQrUri === pairing.uri) setRemoteSignQrUri(null);
+    }
+  };
+
+  const rejectRemoteSignRequest = async (request: RemoteSignRequest) => {
+    if (await postRemoteSignAction(
+      "remote-sign/requests/reject",
+      { id: request.id },
+      t("features.external-sign.remoteRequestRejected"),
+    )) {
+      if (formData.remoteSignId === request.id) {
+        setFormData((prev) => ({ ...prev, remoteSignId: undefined }));
+      }
+    }
+  };
+
+  const reviewRemoteSignRequest = (request: RemoteSignRequest) => {
+    const matchingWallet = wallets.find((wallet) => wallet.public_key === request.required_signer);
+    setFormData((prev) => ({
+      ...prev,
+      remoteSignId: request.id,
+      requestId: request.request_id,
+      requiredSigner: request.required_signer,
+      transactionBase64: request.transaction_base64 || "",
+      expiresAt: request.expires_at ? String(request.expires_at) : "",
+      expectedGenesisHash: request.expected_genesis_hash || "",
+      recentBlockhash: request.recent_blockhash || "",
+      lastValidBlockHeight: request.last_valid_block_height ? String(request.last_valid_block_height) : "",
+      ...(request.network ? { network: request.network } : {}),
+      ...(matchingWallet ? { wallet_id: matchingWallet.id, keystoreJson: undefined } : {}),
+    }));
+  };
+
   const handleSellPercentShortcut = async (
     formId: "pumpfun-sell" | "pumpswap-sell",
     percent: number,
@@ -7136,7 +7325,9 @@ export default function Home() {
       case "external-sign": {
         const requiredSigner = String(nextFormData.requiredSigner || "").trim();
         const transactionBase64 = String(nextFormData.transactionBase64 || "").trim();
-        if (!requiredSigner || !transactionBase64) {
+        const remoteSignRequest = remoteSignRequests.find((request) => request.id === nextFormData.remoteSignId);
+        const signsMessage = remoteSignRequest?.kind === "message";
+        if (!requiredSigner || (!transactionBase64 && !signsMessage)) {
           return fail(t("features.external-sign.fillAllFields"));
         }
         if (!isLikelySolanaPublicKey(requiredSigner)) {
@@ -7225,10 +7416,6 @@ export default function Home() {
         return nextFormData.mint && hasValidSellAmount(nextFormData)
           ? true
           : fail(t("features.pumpswap-sell.fillAllFields"));
-      case "setup-2fa":
-        return nextFormData.hardware_fingerprint
-          ? true
-          : fail(t("features.setup-2fa.fillAllFields"));
       case "create-tfa": {
         const method = walletAuth("create-tfa");
         const hasWalletMaterial =
@@ -7244,7 +7431,6 @@ export default function Home() {
         if (
           !hasWalletMaterial ||
           !nextFormData.totp_secret ||
-          !nextFormData.hardware_fingerprint ||
           nextFormData.question_index === undefined ||
           !nextFormData.security_answer
         ) {
@@ -7256,7 +7442,7 @@ export default function Home() {
       }
       case "unlock-tfa":
         return nextFormData.encrypted_wallet &&
-          nextFormData.hardware_fingerprint &&
+          (nextFormData.device_factor || nextFormData.hardware_fingerprint) &&
           nextFormData.security_answer &&
           nextFormData.totp_code
           ? true
@@ -9561,6 +9747,32 @@ export default function Home() {
             setLoading(false);
             return;
           }
+          const remoteSignId = String(formData.remoteSignId || "").trim();
+          if (remoteSignId) {
+            const approveBody: ApiRequestBody = { id: remoteSignId };
+            applyWalletAuth(approveBody, m, formData, "private_key");
+            const response = await apiFetch("remote-sign/requests/approve", {
+              method: "POST",
+              headers: { "Content-Type": "application/json" },
+              body: JSON.stringify(approveBody),
+            });
+            const data = await response.json();
+            if (response.ok) {
+              toast.success(t("features.external-sign.remoteRequestApproved"));
+              const result = (data.response?.result ?? {}) as Record<string, unknown>;
+              setFormData((prev) => ({
+                ...prev,
+                remoteSignId: undefined,
+                status: typeof result.status === "string" ? result.status : data.status,
+                signature: typeof result.signature === "string" ? result.signature : prev.signature,
+                signedBy: typeof result.signed_by === "string" ? result.signed_by : data.required_signer,
+              }));
+              void loadRemoteSign();
+            } else {
+              toast.error(data.error || t("features.external-sign.error"));
+            }
+            break;
+          }
           const requestBody: ApiRequestBody = {
             required_signer: String(formData.requiredSigner || "").trim(),
             transaction_base64: String(formData.transactionBase64 || "").trim(),
@@ -10370,7 +10582,7 @@ export default function Home() {
 
         case "setup-2fa": {
           const masterPassword = String(formData.master_password || "");
-          if (!formData.hardware_fingerprint || !masterPassword) {
+          if (!masterPassword) {
             toast.error(t("features.setup-2fa.fillAllFields"));
             setLoading(false);
             return;
@@ -10380,7 +10592,8 @@ export default function Home() {
             method: "POST",
             headers: { "Content-Type": "application/json" },
             body: JSON.stringify({
-              hardware_fingerprint: formData.hardware_fingerprint,
+              // The server falls back to this machine's hardware
+              hardware_fingerprint: formData.hardware_fingerprint || "",
               master_password: masterPassword,
               account: formData.account || "fnzero-safe",
               issuer: formData.issuer || "FnzeroSafe",
@@ -10406,7 +10619,6 @@ export default function Home() {
           const masterPassword = String(formData.master_password || "");
           if (
             !formData.totp_secret ||
-            !formData.hardware_fingerprint ||
             !masterPassword ||
             formData.question_index === undefined ||
             !formData.security_answer
@@ -10432,8 +10644,8 @@ export default function Home() {
 
           const requestBody: ApiRequestBody = {
             totp_secret: formData.totp_secret,
-            hardware_fingerprint: formData.hardware_fingerprint,
             master_password: masterPassword,
+            device_factor: String(formData.device_factor_kind || "fingerprint"),
             question_index: questionIndex,
             security_answer: formData.security_answer,
           };
@@ -10451,7 +10663,9 @@ export default function Home() {
             setFormData((prev) => ({
               ...prev,
               encrypted_wallet: data.encrypted_wallet,
+              device_factor: JSON.stringify(data.device_factor),
               publicKey: data.public_key,
+              backup_codes: (data.backup_codes || []).join("\n"),
             }));
           } else {
             toast.error(data.error || t("features.create-tfa.error"));
@@ -10461,19 +10675,30 @@ export default function Home() {
 
         case "unlock-tfa": {
           const masterPassword = String(formData.master_password || "");
-          if (!formData.encrypted_wallet || !formData.hardware_fingerprint || !masterPassword ||
-              !formData.security_answer || !formData.totp_code) {
+          if (!formData.encrypted_wallet || !(formData.device_factor || formData.hardware_fingerprint) ||
+              !masterPassword || !formData.security_answer || !formData.totp_code) {
             toast.error(t("features.unlock-tfa.fillAllFields"));
             setLoading(false);
             return;
           }
+          let deviceFactor: unknown;
+          if (formData.device_factor) {
+            try {
+              deviceFactor = JSON.parse(String(formData.device_factor));
+            } catch {
+              toast.error(t("features.unlock-tfa.fillAllFields"));
+              setLoading(false);
+              return;
+            }
+          }
 
           const response = await apiFetch("2fa/unlock-tfa", {
             method: "POST",
             headers: { "Content-Type": "application/json" },
             body: JSON.stringify({
               encrypted_wallet: formData.encrypted_wallet,
-              hardware_fingerprint: formData.hardware_fingerprint,
+              device_factor: deviceFactor,
+              hardware_fingerprint: formData.hardware_fingerprint || "",
               master_password: masterPassword,
               security_answer: formData.security_answer,
               totp_code: formData.totp_code,
@@ -10483,9 +10708,19 @@ export default function Home() {
 
           if (response.ok) {
             toast.success(t("features.unlock-tfa.success"));
+            if (data.device_notice) {
+              toast.warning(data.device_notice);
+            }
+            if (data.encrypted_wallet) {
+              toast.warning(
+                t("features.unlock-tfa.backupCodeUsed", { remaining: data.backup_codes_remaining }),
+              );
+            }
             setFormData((prev) => {
               const next: FormState = {
                 ...prev,
+                // A spent backup code rewrites the envelope; the old one still holds it
+                encrypted_wallet: data.encrypted_wallet || prev.encrypted_wallet,
                 publicKey: data.public_key,
                 unlocked: data.unlocked ? "true" : undefined,
               };
@@ -10898,7 +11133,7 @@ export default function Home() {
                           {biometricConfiguredFor(wallet) && (
                             <span className="inline-flex items-center gap-1 rounded-full bg-sky-400/15 px-2 py-0.5 text-xs text-sky-100">
                               <Fingerprint className="h-3 w-3" />
-                              {t("features.biometric.touchId")}
+                              {biometricStatusFor(wallet)?.backend ?? t("features.biometric.touchId")}
                             </span>
                           )}
                         </div>
@@ -17053,6 +17288,167 @@ export default function Home() {
               </div>
             )}
 
+            <section className="space-y-3 rounded-lg border border-white/10 bg-white/[0.03] p-3">
+              <div className="flex items-center justify-between gap-2">
+                <h3 className="text-sm font-semibold">{t("features.external-sign.remoteTitle")}</h3>
+                <button
+                  type="button"
+                  onClick={() => void loadRemoteSign()}
+                  className="rounded bg-white/10 px-2 py-1 text-xs text-gray-200 hover:bg-white/20"
+                >
+                  {t("features.external-sign.remoteRefresh")}
+                </button>
+              </div>
+              <p className="text-xs text-gray-400">{t("features.external-sign.remoteHint")}</p>
+
+              {remoteSignRequests.length === 0 ? (
+                <p className="text-xs text-gray-500">{t("features.external-sign.remoteNoRequests")}</p>
+              ) : (
+                <div className="space-y-2">
+                  {remoteSignRequests.map((request) => {
+                    const reviewing = formData.remoteSignId === request.id;
+                    return (
+                      <div
+                        key={request.id}
+                        className={`space-y-2 rounded-lg border p-3 text-xs ${
+                          reviewing ? "border-purple-400/60 bg-purple-500/10" : "border-white/10 bg-white/5"
+                        }`}
+                      >
+                        <div className="flex flex-wrap items-center justify-between gap-2">
+                          <span className="font-semibold">
+                            {request.pairing_name || "-"} · {request.request_id}
+                          </span>
+                          <span className="text-gray-400">
+                            {request.kind === "message"
+                              ? t("features.external-sign.remoteKindMessage")
+                              : request.submit
+                                ? t("features.external-sign.remoteKindSubmit")
+                                : t("features.external-sign.remoteKindSign")}
+                          </span>
+                        </div>
+                        {request.note && <p className="text-gray-200 break-words">{request.note}</p>}
+                        <p className="font-mono text-gray-400 break-all">
+                          {t("features.external-sign.requiredSigner")}: {request.required_signer}
+                        </p>
+                        {request.client_fingerprint && (
+                          <p className="font-mono text-gray-500">
+                            {t("features.external-sign.remoteClient")}: {request.client_fingerprint}
+                          </p>
+                        )}
+                        {request.expires_at && (
+                          <p className="text-gray-500">
+                            {t("features.external-sign.expiresAt")}: {new Date(request.expires_at * 1000).toLocaleString()}
+                          </p>
+                        )}
+                        {request.preview && (
+                          <div className="space-y-1">
+                            <p className="text-gray-300">
+                              {t("features.external-sign.remotePrograms")}: {request.preview.programs.join(", ") || "-"}
+                            </p>
+                            {!request.preview.required_signer_present && (
+                              <p className="text-red-200">{t("features.external-sign.remoteSignerMissing")}</p>
+                            )}
+                            {request.preview.warnings.map((warning) => (
+                              <p key={warning} className="text-yellow-300">{warning}</p>
+                            ))}
+                          </div>
+                        )}
+                        {request.preview_error && <p className="text-red-200">{request.preview_error}</p>}
+                        {request.kind === "message" && request.message_base64 && (
+                          <p className="font-mono text-gray-400 break-all">{request.message_base64}</p>
+                        )}
+                        <div className="grid grid-cols-2 gap-2">
+                          <button
+                            type="button"
+                            onClick={() => reviewRemoteSignRequest(request)}
+                            disabled={remoteSignBusy}
+                            className="rounded-lg bg-white/10 px-3 py-1.5 font-semibold hover:bg-white/20 disabled:opacity-40"
+                          >
+                            {t("features.external-sign.remoteReview")}
+                          </button>
+                          <button
+                            type="button"
+                            onClick={() => void rejectRemoteSignRequest(request)}
+                            disabled={remoteSignBusy}
+                            className="rounded-lg bg-red-500/20 px-3 py-1.5 font-semibold text-red-100 hover:bg-red-500/30 disabled:opacity-40"
+                          >
+                            {t("features.external-sign.remoteReject")}
+                          </button>
+                        </div>
+                      </div>
+                    );
+                  })}
+                </div>
+              )}
+
+              <div className="space-y-2 border-t border-white/10 pt-3">
+                <h4 className="text-xs font-semibold text-gray-300">{t("features.external-sign.remotePairings")}</h4>
+                {remoteSignPairings.map((pairing) => (
+                  <div key={pairing.id} className="flex flex-wrap items-center justify-between gap-2 text-xs">
+                    <span>
+                      {pairing.name}
+                      <span className="ml-2 font-mono text-gray-500">
+                        {pairing.client_fingerprint || t("features.external-sign.remoteNotPaired")}
+                      </span>
+                    </span>
+                    <span className="flex gap-2">
+                      <button
+                        type="button"
+                        onClick={() => setRemoteSignQrUri(remoteSignQrUri === pairing.uri ? null : pairing.uri)}
+                        className="rounded bg-white/10 px-2 py-1 hover:bg-white/20"
+                      >
+                        {t("features.external-sign.remotePairingShow")}
+                      </button>
+                      <button
+                        type="button"
+                        onClick={() => void deleteRemoteSignPairing(pairing)}
+                        disabled={remoteSignBusy}
+                        className="rounded bg-red-500/20 px-2 py-1 text-red-100 hover:bg-red-500/30 disabled:opacity-40"
+                      >
+                        {t("features.external-sign.remotePairingDelete")}
+                      </button>
+                    </span>
+                  </div>
+                ))}
+                {remoteSignQrUri && (
+                  <div className="flex flex-col items-center gap-2 rounded-lg bg-white/5 p-3">
+                    <canvas ref={remoteSignQrCanvasRef} className="rounded bg-white" />
+                    <button
+                      type="button"
+                      onClick={() => void copyToClipboard(remoteSignQrUri, "remote-sign-uri")}
+                      className="w-full break-all rounded bg-white/5 px-2 py-1 text-left font-mono text-[11px] text-gray-300 hover:bg-white/10"
+                    >
+                      {remoteSignQrUri}
+                    </button>
+                  </div>
+                )}
+                <div className="grid grid-cols-1 gap-2 md:grid-cols-[1fr_1fr_auto]">
+                  <input
+                    type="text"
+                    value={remoteSignPairingName}
+                    onChange={(e) => setRemoteSignPairingName(e.target.value)}
+                    className="px-3 py-1.5 bg-white/5 border border-white/10 rounded-lg focus:outline-none focus:ring-2 focus:ring-white/20 text-white text-xs"
+                    placeholder={t("features.external-sign.remotePairingNamePlaceholder")}
+                  />
+                  <input
+                    type="text"
+                    value={remoteSignRelayUrl}
+                    onChange={(e) => setRemoteSignRelayUrl(e.target.value.trim())}
+                    className="px-3 py-1.5 bg-white/5 border border-white/10 rounded-lg focus:outline-none focus:ring-2 focus:ring-white/20 text-white font-mono text-xs"
+                    placeholder={remoteSignDefaultRelay || t("features.external-sign.remoteRelayPlaceholder")}
+                  />
+                  <button
+                    type="button"
+                    onClick={() => void createRemoteSignPairing()}
+                    disabled={remoteSignBusy}
+                    className="rounded-lg bg-white/10 px-3 py-1.5 text-xs font-semibold hover:bg-white/20 disabled:opacity-40"
+                  >
+                    {t("features.external-sign.remotePairingCreate")}
+                  </button>
+                </div>
+              </div>
+            </section>
+
             <section className="space-y-3 rounded-lg border border-white/10 bg-white/[0.03] p-3">
               <div>
                 <label className="block text-sm font-medium mb-2">{t("features.external-sign.requestJson")}</label>
@@ -17157,7 +17553,11 @@ export default function Home() {
               disabled={loading}
               className="w-full py-3 bg-gradient-to-r from-purple-500 to-pink-500 rounded-lg font-semibold hover:from-purple-600 hover:to-pink-600 transition-all disabled:opacity-50"
             >
-              {loading ? t("features.external-sign.submitting") : t("features.external-sign.submitButton")}
+              {loading
+                ? t("features.external-sign.submitting")
+                : formData.remoteSignId
+                  ? t("features.external-sign.remoteApproveButton")
+                  : t("features.external-sign.submitButton")}
             </button>
 
             {formData.signature && (
@@ -18052,14 +18452,16 @@ export default function Home() {
               />
             </div>
             <div>
-              <label className="block text-sm font-medium mb-2">{t("features.create-tfa.hardwareFingerprint")}</label>
-              <input
-                type="text"
-                value={formData.hardware_fingerprint || ""}
-                onChange={(e) => handleFormChange("hardware_fingerprint", e.target.value)}
+              <label className="block text-sm font-medium mb-2">{t("features.create-tfa.deviceFactorKind")}</label>
+              <select
+                value={String(formData.device_factor_kind ?? "fingerprint")}
+                onChange={(e) => handleFormChange("device_factor_kind", e.target.value)}
                 className="w-full px-4 py-2 bg-white/5 border border-white/10 rounded-lg focus:outline-none focus:ring-2 focus:ring-white/20 text-white"
-                placeholder={t("features.create-tfa.fingerprintPlaceholder")}
-              />
+              >
+                <option value="fingerprint">{t("features.create-tfa.deviceFactorFingerprint")}</option>
+                <option value="device-key">{t("features.create-tfa.deviceFactorKeyFile")}</option>
+                <option value="tpm">{t("features.create-tfa.deviceFactorTpm")}</option>
+              </select>
             </div>
             <div>
               <label className="block text-sm font-medium mb-2">{t("features.create-tfa.questionIndex")}</label>
@@ -18130,6 +18532,40 @@ export default function Home() {
                     </button>
                   </div>
                 </div>
+                {formData.device_factor && (
+                  <div>
+                    <label className="block text-sm font-medium mb-2">{t("features.create-tfa.deviceFactor")}</label>
+                    <p className="text-xs text-gray-400">{t("features.create-tfa.deviceFactorHint")}</p>
+                    <div className="flex gap-2">
+                      <code className="flex-1 px-3 py-2 bg-black/30 rounded text-xs break-all max-h-32 overflow-y-auto">
+                        {formData.device_factor}
+                      </code>
+                      <button type="button"
+                        onClick={() => copyToClipboard(formData.device_factor as string, "tfa-device")}
+                        className="px-3 py-2 bg-white/10 rounded hover:bg-white/20 transition-colors"
+                      >
+                        {copied === "tfa-device" ? <Check className="w-4 h-4" /> : <Copy className="w-4 h-4" />}
+                      </button>
+                    </div>
+                  </div>
+                )}
+                {formData.backup_codes && (
+                  <div>
+                    <label className="block text-sm font-medium mb-2">{t("features.create-tfa.backupCodes")}</label>
+                    <p className="text-xs text-gray-400">{t("features.create-tfa.backupCodesHint")}</p>
+                    <div className="flex gap-2">
+                      <pre className="flex-1 px-3 py-2 bg-black/30 rounded text-xs whitespace-pre-wrap">
+                        {formData.backup_codes}
+                      </pre>
+                      <button type="button"
+                        onClick={() => copyToClipboard(formData.backup_codes as string, "tfa-backup")}
+                        className="px-3 py-2 bg-white/10 rounded hover:bg-white/20 transition-colors"
+                      >
+                        {copied === "tfa-backup" ? <Check className="w-4 h-4" /> : <Copy className="w-4 h-4" />}
+                      </button>
+                    </div>
+                  </div>
+                )}
               </div>
             )}
           </div>
@@ -18148,6 +18584,15 @@ export default function Home() {
                 placeholder={t("features.unlock-tfa.walletPlaceholder")}
               />
             </div>
+            <div>
+              <label className="block text-sm font-medium mb-2">{t("features.unlock-tfa.deviceFactor")}</label>
+              <textarea
+                value={formData.device_factor || ""}
+                onChange={(e) => handleFormChange("device_factor", e.target.value)}
+                className="w-full px-4 py-2 bg-white/5 border border-white/10 rounded-lg focus:outline-none focus:ring-2 focus:ring-white/20 text-white min-h-[80px]"
+                placeholder={t("features.unlock-tfa.deviceFactorPlaceholder")}
+              />
+            </div>
             <div>
               <label className="block text-sm font-medium mb-2">{t("features.unlock-tfa.hardwareFingerprint")}</label>
               <input
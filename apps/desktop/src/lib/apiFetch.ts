@@ -16,7 +16,11 @@ const PLAINTEXT_SECRET_KEYS = new Set([
   "program_keypair_json",
   "master_password",
   "security_answer",
+  "security_answers",
   "totp_code",
+  "passphrase",
+  "current_passphrase",
+  "new_passphrase",
 ]);
 const SECURE_ENVELOPE_KEYS = new Set(["version", "encrypted_key", "iv", "ciphertext"]);
 
@@ -0,0 +1,425 @@
+//! Platform credential stores for desktop quick unlock
+//!
+//! Quick unlock keeps a wallet password in an OS credential store and hands it back
+//! after the user proves presence. The Tauri shell implements [`CredentialStore`] for
+//! the macOS Keychain with Touch ID itself; this module provides the Linux backend
+//! on top of the freedesktop Secret Service (gnome-keyring, KWallet) over D-Bus.
+//!
+//! Secret Service releases secrets to any process in an unlocked session, so reads can
+//! additionally require a [`PresenceCheck`] chosen with `FNZERO_SAFE_QUICK_UNLOCK_CONFIRM`:
+//!
+//! - `none` (default): the unlocked login keyring is the only gate
+//! - `polkit`: `pkcheck` against the [`POLKIT_ACTION_ID`] action, which prompts for the
+//!   user's password through the session's polkit agent
+//! - `fprintd`: `fprintd-verify` must report a fingerprint match
+
+use crate::BiometricPolicy;
+use zeroize::Zeroizing;
+
+pub const QUICK_UNLOCK_CONFIRM_ENV: &str = "FNZERO_SAFE_QUICK_UNLOCK_CONFIRM";
+/// Installed by the Linux packages as `/usr/share/polkit-1/actions/<id>.policy`
+pub const POLKIT_ACTION_ID: &str = "dev.fnzero-safe.quick-unlock";
+
+/// An OS store holding one secret per account
+///
+/// Implementations run any user-presence check inside [`CredentialStore::load`].
+pub trait CredentialStore: Send + Sync {
+    /// Short name shown next to wallets with quick unlock, e.g. "Touch ID"
+    fn backend(&self) -> &'static str;
+    /// `Err(reason)` when quick unlock cannot be used on this machine right now
+    fn available(&self) -> Result<(), String>;
+    fn contains(&self, account: &str) -> Result<bool, String>;
+    fn store(&self, account: &str, secret: &str) -> Result<(), String>;
+    fn load(&self, account: &str) -> Result<Zeroizing<String>, String>;
+    /// Removing an account that has no secret is not an error
+    fn delete(&self, account: &str) -> Result<(), String>;
+}
+
+/// Quick-unlock status of `account`; an unavailable store reports its reason
+pub fn credential_store_policy(
+    store: &dyn CredentialStore,
+    account: &str,
+) -> Result<BiometricPolicy, String> {
+    if let Err(reason) = store.available() {
+        return Ok(BiometricPolicy {
+            supported: false,
+            configured: false,
+            reason: Some(reason),
+        });
+    }
+    Ok(BiometricPolicy {
+        supported: true,
+        configured: store.contains(account)?,
+        reason: None,
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PresenceCheck {
+    None,
+    Polkit,
+    Fprintd,
+}
+
+/// Start time of this process in clock ticks since boot, as `pkcheck --process` expects
+fn process_start_time() -> Result<u64, String> {
+    let stat = std::fs::read_to_string("/proc/self/stat")
+        .map_err(|error| format!("failed to read /proc/self/stat: {error}"))?;
+    start_time_from_stat(&stat).ok_or_else(|| "failed to parse /proc/self/stat".to_string())
+}
+
+/// Field 22 of `/proc/<pid>/stat`; the command name in field 2 may contain spaces or `)`
+fn start_time_from_stat(stat: &str) -> Option<u64> {
+    let (_, after_comm) = stat.rsplit_once(')')?;
+    after_comm.split_whitespace().nth(19)?.parse().ok()
+}
+
+impl PresenceCheck {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "" | "none" => Ok(Self::None),
+            "polkit" => Ok(Self::Polkit),
+            "fprintd" => Ok(Self::Fprintd),
+            other => Err(format!(
+                "{QUICK_UNLOCK_CONFIRM_ENV} must be none, polkit or fprintd, got {other:?}"
+            )),
+        }
+    }
+
+    pub fn from_env() -> Result<Self, String> {
+        Self::parse(&std::env::var(QUICK_UNLOCK_CONFIRM_ENV).unwrap_or_default())
+    }
+
+    fn program(self) -> Option<&'static str> {
+        match self {
+            Self::None => None,
+            Self::Polkit => Some("pkcheck"),
+            Self::Fprintd => Some("fprintd-verify"),
+        }
+    }
+
+    /// `Err` when the confirmation tool is missing from `PATH`
+    pub fn available(self) -> Result<(), String> {
+        let Some(program) = self.program() else {
+            return Ok(());
+        };
+        let found = std::env::var_os("PATH").is_some_and(|path| {
+            std::env::split_paths(&path).any(|dir| dir.join(program).is_file())
+        });
+        if found {
+            Ok(())
+        } else {
+            Err(format!(
+                "{program} is not installed; quick unlock confirmation is unavailable"
+            ))
+        }
+    }
+
+    pub fn confirm(self) -> Result<(), String> {
+        match self {
+            Self::None => Ok(()),
+            Self::Polkit => {
+                // pid alone is racy: polkit wants the start time to rule out pid reuse
+                let subject = format!("{},{}", std::process::id(), process_start_time()?);
+                let status = std::process::Command::new("pkcheck")
+                    .args(["--action-id", POLKIT_ACTION_ID, "--process"])
+                    .arg(subject)
+                    .arg("--allow-user-interaction")
+                    .stdout(std::process::Stdio::null())
+                    .stderr(std::process::Stdio::null())
+                    .status()
+                    .map_err(|error| format!("failed to run pkcheck: {error}"))?;
+                if status.success() {
+                    Ok(())
+                } else {
+                    Err("polkit confirmation was denied or cancelled".to_string())
+                }
+            }
+            Self::Fprintd => {
+                let output = std::process::Command::new("fprintd-verify")
+                    .output()
+                    .map_err(|error| format!("failed to run fprintd-verify: {error}"))?;
+                // fprintd-verify exits 0 on a non-matching finger in some versions
+                let stdout = String::from_utf8_lossy(&output.stdout);
+                if stdout.contains("verify-match") && !stdout.contains("verify-no-match") {
+                    Ok(())
+                } else {
+                    Err("fingerprint verification failed".to_string())
+                }
+            }
+        }
+    }
+}
+
+/// The credential store for this platform, if this module has one
+///
+/// `service` namespaces the stored items so different apps and versions do not collide.
+pub fn platform_credential_store(service: &str) -> Result<Box<dyn CredentialStore>, String> {
+    #[cfg(target_os = "linux")]
+    {
+        Ok(Box::new(SecretServiceStore::new(
+            service,
+            PresenceCheck::from_env()?,
+        )))
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = service;
+        Err("quick unlock is not supported on this platform".to_string())
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub use secret_service_store::SecretServiceStore;
+
+#[cfg(target_os = "linux")]
+mod secret_service_store {
+    use super::{CredentialStore, PresenceCheck};
+    use secret_service::blocking::{Item, SecretService};
+    use secret_service::EncryptionType;
+    use std::collections::HashMap;
+    use zeroize::Zeroizing;
+
+    const APPLICATION: &str = "fnzero-safe";
+
+    /// Wallet secrets in the default Secret Service collection
+    pub struct SecretServiceStore {
+        service: String,
+        presence: PresenceCheck,
+    }
+
+    impl SecretServiceStore {
+        pub fn new(service: &str, presence: PresenceCheck) -> Self {
+            Self {
+                service: service.to_string(),
+                presence,
+            }
+        }
+
+        fn attributes<'a>(&'a self, account: &'a str) -> HashMap<&'a str, &'a str> {
+            HashMap::from([
+                ("application", APPLICATION),
+                ("service", self.service.as_str()),
+                ("account", account),
+            ])
+        }
+
+        fn items<'a>(
+            &self,
+            secret_service: &'a SecretService<'_>,
+            account: &str,
+        ) -> Result<Vec<Item<'a>>, secret_service::Error> {
+            let found = secret_service.search_items(self.attributes(account))?;
+            Ok(found.unlocked.into_iter().chain(found.locked).collect())
+        }
+    }
+
+    /// Run `task` against a fresh connection on its own thread
+    ///
+    /// The blocking client drives zbus on a private tokio runtime, which must not be
+    /// entered from the Tauri main thread or from inside another runtime.
+    fn with_secret_service<T, F>(task: F) -> Result<T, String>
+    where
+        T: Send,
+        F: FnOnce(&SecretService<'_>) -> Result<T, secret_service::Error> + Send,
+    {
+        std::thread::scope(|scope| {
+            scope
+                .spawn(|| {
+                    let secret_service = SecretService::connect(EncryptionType::Dh)?;
+                    task(&secret_service)
+                })
+                .join()
+        })
+        .map_err(|_| "Secret Service worker thread panicked".to_string())?
+        .map_err(secret_service_error)
+    }
+
+    fn secret_service_error(error: secret_service::Error) -> String {
+        match error {
+            secret_service::Error::Prompt => "keyring unlock was cancelled".to_string(),
+            secret_service::Error::Locked => "the login keyring is locked".to_string(),
+            other => format!("Secret Service error: {other}"),
+        }
+    }
+
+    impl CredentialStore for SecretServiceStore {
+        fn backend(&self) -> &'static str {
+            "Secret Service"
+        }
+
+        fn available(&self) -> Result<(), String> {
+            self.presence.available()?;
+            with_secret_service(|secret_service| {
+                secret_service.get_default_collection().map(|_| ())
+            })
+            .map_err(|error| format!("{error} (is gnome-keyring or KWallet running?)"))
+        }
+
+        fn contains(&self, account: &str) -> Result<bool, String> {
+            with_secret_service(|secret_service| {
+                Ok(!self.items(secret_service, account)?.is_empty())
+            })
+        }
+
+        fn store(&self, account: &str, secret: &str) -> Result<(), String> {
+            with_secret_service(|secret_service| {
+                let collection = secret_service.get_default_collection()?;
+                if collection.is_locked()? {
+                    collection.unlock()?;
+                }
+                collection.create_item(
+                    &format!("FnzeroSafe wallet password ({account})"),
+                    self.attributes(account),
+                    secret.as_bytes(),
+                    true,
+                    "text/plain",
+                )?;
+                Ok(())
+            })
+        }
+
+        fn load(&self, account: &str) -> Result<Zeroizing<String>, String> {
+            if !self.contains(account)? {
+                return Err("quick unlock is not enabled for this wallet".to_string());
+            }
+            self.presence.confirm()?;
+            let secret = with_secret_service(|secret_service| {
+                let items = self.items(secret_service, account)?;
+                let item = items.first().ok_or(secret_service::Error::NoResult)?;
+                if item.is_locked()? {
+                    item.unlock()?;
+                }
+                item.get_secret().map(Zeroizing::new)
+            })?;
+            String::from_utf8(secret.to_vec())
+                .map(Zeroizing::new)
+                .map_err(|_| "stored wallet password is not valid UTF-8".to_string())
+        }
+
+        fn delete(&self, account: &str) -> Result<(), String> {
+            with_secret_service(|secret_service| {
+                for item in self.items(secret_service, account)? {
+                    item.delete()?;
+                }
+                Ok(())
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct MemoryStore {
+        unavailable: Option<&'static str>,
+        secrets: Mutex<HashMap<String, String>>,
+    }
+
+    impl CredentialStore for MemoryStore {
+        fn backend(&self) -> &'static str {
+            "memory"
+        }
+
+        fn available(&self) -> Result<(), String> {
+            self.unavailable
+                .map_or(Ok(()), |reason| Err(reason.to_string()))
+        }
+
+        fn contains(&self, account: &str) -> Result<bool, String> {
+            Ok(self.secrets.lock().unwrap().contains_key(account))
+        }
+
+        fn store(&self, account: &str, secret: &str) -> Result<(), String> {
+            self.secrets
+                .lock()
+                .unwrap()
+                .insert(account.to_string(), secret.to_string());
+            Ok(())
+        }
+
+        fn load(&self, account: &str) -> Result<Zeroizing<String>, String> {
+            self.secrets
+                .lock()
+                .unwrap()
+                .get(account)
+                .cloned()
+                .map(Zeroizing::new)
+                .ok_or_else(|| "missing".to_string())
+        }
+
+        fn delete(&self, account: &str) -> Result<(), String> {
+            self.secrets.lock().unwrap().remove(account);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn policy_reports_unavailable_store_without_querying_it() {
+        let store = MemoryStore {
+            unavailable: Some("no keyring"),
+            ..MemoryStore::default()
+        };
+        let policy = credential_store_policy(&store, "wallet").unwrap();
+        assert!(!policy.supported && !policy.configured);
+        assert_eq!(policy.reason.as_deref(), Some("no keyring"));
+
+        let store = MemoryStore::default();
+        store.store("wallet", "pw").unwrap();
+        let policy = credential_store_policy(&store, "wallet").unwrap();
+        assert!(policy.supported && policy.configured);
+        assert!(!credential_store_policy(&store, "other").unwrap().configured);
+    }
+
+    #[test]
+    fn presence_check_parses_env_values() {
+        assert_eq!(PresenceCheck::parse("").unwrap(), PresenceCheck::None);
+        assert_eq!(
+            PresenceCheck::parse(" Polkit ").unwrap(),
+            PresenceCheck::Polkit
+        );
+        assert_eq!(
+            PresenceCheck::parse("fprintd").unwrap(),
+            PresenceCheck::Fprintd
+        );
+        assert!(PresenceCheck::parse("faceid").is_err());
+        assert!(PresenceCheck::None.confirm().is_ok());
+    }
+
+    #[test]
+    fn start_time_is_read_after_the_command_name() {
+        let stat = "4242 (odd) name) S 1 4242 4242 0 -1 4194560 1 0 0 0 0 0 0 0 20 0 1 0 987654 \
+                    1000 100 18446744073709551615";
+        assert_eq!(start_time_from_stat(stat), Some(987654));
+        assert_eq!(start_time_from_stat("4242 (short) S 1"), None);
+        #[cfg(target_os = "linux")]
+        assert!(process_start_time().unwrap() > 0);
+    }
+
+    /// Runs with `--features secret-service-tests` and needs a session bus with an unlocked
+    /// Secret Service, e.g. `dbus-run-session -- sh -c 'echo -n test | gnome-keyring-daemon
+    /// --unlock --components=secrets && cargo test -p fnzero-safe-app-services --features
+    /// secret-service-tests --lib credential_store'`
+    #[cfg(target_os = "linux")]
+    #[test]
+    #[cfg_attr(
+        not(feature = "secret-service-tests"),
+        ignore = "needs a Secret Service; enable the secret-service-tests feature"
+    )]
+    fn secret_service_round_trips_against_a_local_keyring() {
+        let store = SecretServiceStore::new("dev.fnzero-safe.test", PresenceCheck::None);
+        store.available().unwrap();
+        let account = format!("test:{}", uuid::Uuid::new_v4());
+        assert!(!store.contains(&account).unwrap());
+        store.store(&account, "first").unwrap();
+        store.store(&account, "second").unwrap();
+        assert_eq!(store.load(&account).unwrap().as_str(), "second");
+        store.delete(&account).unwrap();
+        assert!(!store.contains(&account).unwrap());
+        store.delete(&account).unwrap();
+    }
+}
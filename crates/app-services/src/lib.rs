@@ -6,8 +6,13 @@
 
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
 use bip39::{Language, Mnemonic};
+use fnzero_safe::solana_utils::token_extensions;
+use fnzero_safe::totp_guard::{
+    self, MemoryTotpStateStore, TotpGuardError, TotpGuardState, TotpStateStore,
+};
 use fnzero_safe::{KeyManager, Keypair, Pubkey, Signer};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use solana_account_decoder_client_types::UiAccountData;
 use solana_client::rpc_client::RpcClient;
 use solana_commitment_config::CommitmentConfig;
@@ -15,6 +20,7 @@ use solana_derivation_path::DerivationPath;
 use solana_rpc_client_types::request::TokenAccountsFilter;
 use solana_sdk::{
     instruction::Instruction,
+    message::AddressLookupTableAccount,
     sanitize::Sanitize,
     signature::Signature,
     signer::keypair::{
@@ -23,11 +29,15 @@ use solana_sdk::{
     transaction::{Transaction, VersionedTransaction},
 };
 use std::str::FromStr;
+use std::sync::{Mutex, OnceLock, PoisonError};
 use std::time::{SystemTime, UNIX_EPOCH};
 use thiserror::Error;
 use uuid::Uuid;
 
-mod squads_v4;
+use fnzero_safe_squads as squads_v4;
+
+#[cfg(feature = "credential-store")]
+pub mod credential_store;
 
 pub mod capabilities {
     pub const WALLET_MANAGEMENT: &str = "wallet_management";
@@ -40,7 +50,9 @@ pub mod capabilities {
 }
 
 const TOKEN_PROGRAM_ID: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
-const TOKEN_2022_PROGRAM_ID: &str = "TokenzQdBNbLqP5VEhdkAS6EPFQYJYYo8M2ee9VhgC3Q";
+const TOKEN_2022_PROGRAM_ID: &str = "TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb";
+const MAX_SQUADS_CLEANUP_CLOSES: usize = 40;
+const SQUADS_CLOSES_PER_TRANSACTION: usize = 4;
 const DEFAULT_MNEMONIC_DERIVATION_PATH: &str = "m/44'/501'/0'/0'";
 const SOLANA_TRANSACTION_PACKET_DATA_BYTES: usize = 1232;
 
@@ -234,6 +246,12 @@ pub struct AssetSummary {
     pub raw_amount: String,
     pub decimals: u8,
     pub logo_uri: Option<String>,
+    /// UI amount including accrued interest, for Token-2022 interest-bearing mints
+    #[serde(default)]
+    pub interest_bearing_amount: Option<String>,
+    /// Current interest rate in basis points, for Token-2022 interest-bearing mints
+    #[serde(default)]
+    pub interest_rate_bps: Option<i16>,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -269,6 +287,55 @@ pub struct PaymentPreviewRequest {
     pub mint: Option<String>,
     pub amount: String,
     pub memo: Option<String>,
+    /// Saved recipients; the preview shows the matching label and flags lookalikes
+    #[serde(default)]
+    pub address_book: Vec<AddressBookEntry>,
+    /// Addresses this wallet has paid before, e.g. from its transaction history
+    #[serde(default)]
+    pub previous_recipients: Vec<String>,
+}
+
+/// A saved recipient. Frontends own the list and pass it back to every call.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AddressBookEntry {
+    /// Empty for a new entry; `address_book_save` assigns one
+    #[serde(default)]
+    pub id: String,
+    pub network: AppNetwork,
+    pub address: String,
+    pub label: String,
+    #[serde(default)]
+    pub notes: Option<String>,
+    /// Mint the address should only receive, e.g. an exchange deposit address
+    #[serde(default)]
+    pub expected_mint: Option<String>,
+    #[serde(default)]
+    pub send_count: u64,
+    #[serde(default)]
+    pub last_sent_at_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecipientCheckRequest {
+    pub network: AppNetwork,
+    pub recipient: String,
+    /// Token being sent; SOL when omitted
+    pub mint: Option<String>,
+    pub address_book: Vec<AddressBookEntry>,
+    #[serde(default)]
+    pub previous_recipients: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecipientCheck {
+    pub label: Option<String>,
+    pub notes: Option<String>,
+    pub expected_mint: Option<String>,
+    pub mint_mismatch: bool,
+    pub first_send: bool,
+    /// Saved entries the recipient imitates, as `label (address)`
+    pub lookalikes: Vec<String>,
+    pub warnings: Vec<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -305,6 +372,9 @@ pub struct PaymentSubmitRequest {
     pub recipient: Option<String>,
     pub mint: Option<String>,
     pub amount_base_units: u64,
+    /// SPL Memo attached to SOL and token transfers (e.g. an exchange deposit tag)
+    #[serde(default)]
+    pub memo: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -326,6 +396,19 @@ pub struct PumpPreviewRequest {
     pub venue: String,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PumpBuyPreviewRequest {
+    pub network: AppNetwork,
+    pub wallet_public_key: String,
+    pub mint: String,
+    pub amount_lamports: u64,
+    /// `SOL` or `WSOL`.
+    pub input_token: String,
+    pub slippage_bps: u32,
+    pub create_ata: bool,
+    pub venue: String,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct DappSignPreviewRequest {
     pub network: AppNetwork,
@@ -383,6 +466,43 @@ pub struct SquadsProposalSummary {
     pub approved: Vec<String>,
     pub rejected: Vec<String>,
     pub cancelled: Vec<String>,
+    pub decoded: Option<SquadsTransactionPreview>,
+    pub decode_error: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SquadsTransactionPreview {
+    pub transaction: String,
+    pub vault: String,
+    pub instructions: Vec<SquadsInstructionPreview>,
+    pub outflows: Vec<SquadsVaultOutflow>,
+    pub program_upgrades: Vec<SquadsProgramUpgradePreview>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SquadsInstructionPreview {
+    pub program_id: String,
+    pub program: String,
+    pub name: String,
+    pub fields: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SquadsVaultOutflow {
+    pub asset: String,
+    pub mint: Option<String>,
+    pub source: String,
+    pub destination: String,
+    pub amount: u64,
+    pub decimals: Option<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SquadsProgramUpgradePreview {
+    pub program_id: String,
+    pub buffer: String,
+    pub buffer_len: Option<u64>,
+    pub buffer_sha256: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -511,6 +631,45 @@ pub struct SquadsExecuteSubmitRequest {
     pub transaction_index: u64,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SquadsCloseSubmitRequest {
+    pub approved: bool,
+    pub network: AppNetwork,
+    pub rpc_url: Option<String>,
+    pub keystore_json: String,
+    pub password: String,
+    pub multisig: String,
+    pub transaction_index: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SquadsCleanupSubmitRequest {
+    pub approved: bool,
+    pub network: AppNetwork,
+    pub rpc_url: Option<String>,
+    pub keystore_json: String,
+    pub password: String,
+    pub multisig: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SquadsCleanupSkipped {
+    pub transaction_index: u64,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SquadsCleanupSubmitResult {
+    pub multisig: String,
+    pub rent_collector: String,
+    pub closed: Vec<u64>,
+    pub skipped: Vec<SquadsCleanupSkipped>,
+    pub transactions: Vec<TransactionSubmitResult>,
+    pub reclaimed_lamports: u64,
+    pub remaining: u64,
+    pub network: AppNetwork,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct TotpSetup {
     pub secret: String,
@@ -524,6 +683,53 @@ pub struct TotpVerifyRequest {
     pub code: String,
 }
 
+/// 2FA replay and lockout state the app persists per TOTP secret
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TotpAttemptState {
+    pub last_accepted_step: Option<u64>,
+    pub failed_attempts: u32,
+    pub locked_until: u64,
+}
+
+impl From<TotpGuardState> for TotpAttemptState {
+    fn from(value: TotpGuardState) -> Self {
+        Self {
+            last_accepted_step: value.last_accepted_step,
+            failed_attempts: value.failed_attempts,
+            locked_until: value.locked_until,
+        }
+    }
+}
+
+impl From<TotpAttemptState> for TotpGuardState {
+    fn from(value: TotpAttemptState) -> Self {
+        Self {
+            last_accepted_step: value.last_accepted_step,
+            failed_attempts: value.failed_attempts,
+            locked_until: value.locked_until,
+            ..Self::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TotpStatefulVerifyRequest {
+    pub secret: String,
+    pub code: String,
+    /// State returned by the previous verification of this secret
+    #[serde(default)]
+    pub state: Option<TotpAttemptState>,
+}
+
+/// Outcome of a stateful verification; `state` must be persisted whether or not it passed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TotpVerification {
+    pub verified: bool,
+    pub state: TotpAttemptState,
+    pub error: Option<MobileError>,
+    pub retry_after_secs: Option<u64>,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct BiometricPolicy {
     pub supported: bool,
@@ -552,6 +758,11 @@ fn require_pubkey(value: &str, field: &'static str) -> AppServiceResult<Pubkey>
     })
 }
 
+fn require_memo(memo: Option<&str>) -> AppServiceResult<Option<String>> {
+    fnzero_safe::solana_utils::normalize_memo(memo)
+        .map_err(|e| AppServiceError::mobile(MobileErrorCode::InvalidInput, e.to_string()))
+}
+
 fn now_ms() -> u64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
@@ -617,9 +828,45 @@ fn token_account_from_json(pubkey: String, parsed: &serde_json::Value) -> Option
         raw_amount,
         decimals,
         logo_uri: None,
+        interest_bearing_amount: None,
+        interest_rate_bps: None,
     })
 }
 
+/// Fill interest-bearing amounts for Token-2022 assets from their mint extensions
+fn apply_interest_bearing_amounts(client: &RpcClient, tokens: &mut [AssetSummary]) {
+    let mut mints = tokens
+        .iter()
+        .filter_map(|token| Pubkey::from_str(&token.mint).ok())
+        .collect::<Vec<_>>();
+    mints.sort();
+    mints.dedup();
+    let now_secs = (now_ms() / 1000) as i64;
+
+    for chunk in mints.chunks(100) {
+        let Ok(accounts) = client.get_multiple_accounts(chunk) else {
+            continue;
+        };
+        for (mint, account) in chunk.iter().zip(accounts) {
+            let Some(interest) = account
+                .and_then(|account| token_extensions::parse_mint(account.owner, &account.data).ok())
+                .and_then(|info| info.interest_bearing)
+            else {
+                continue;
+            };
+            let mint = mint.to_string();
+            for token in tokens.iter_mut().filter(|token| token.mint == mint) {
+                let Ok(raw_amount) = token.raw_amount.parse::<u64>() else {
+                    continue;
+                };
+                token.interest_bearing_amount =
+                    Some(interest.ui_amount_string(raw_amount, token.decimals, now_secs));
+                token.interest_rate_bps = Some(interest.current_rate);
+            }
+        }
+    }
+}
+
 fn token_accounts_for_program(
     client: &RpcClient,
     wallet_pubkey: &Pubkey,
@@ -632,13 +879,17 @@ fn token_accounts_for_program(
         .get_token_accounts_by_owner(wallet_pubkey, TokenAccountsFilter::ProgramId(token_program))
         .map_err(map_rpc_error)?;
 
-    Ok(accounts
+    let mut tokens = accounts
         .into_iter()
         .filter_map(|account| match account.account.data {
             UiAccountData::Json(parsed) => token_account_from_json(account.pubkey, &parsed.parsed),
             _ => None,
         })
-        .collect())
+        .collect::<Vec<_>>();
+    if token_program == token_extensions::TOKEN_2022_PROGRAM_ID {
+        apply_interest_bearing_amounts(client, &mut tokens);
+    }
+    Ok(tokens)
 }
 
 fn map_rpc_error(message: impl ToString) -> AppServiceError {
@@ -740,6 +991,23 @@ fn load_squads_vault_transaction(
         .map_err(|message| AppServiceError::mobile(MobileErrorCode::InvalidInput, message))
 }
 
+fn load_squads_message_lookup_tables(
+    client: &RpcClient,
+    message: &squads_v4::TransactionMessage,
+) -> AppServiceResult<Vec<AddressLookupTableAccount>> {
+    message
+        .address_table_lookups
+        .iter()
+        .map(|lookup| {
+            let account = client
+                .get_account(&lookup.account_key)
+                .map_err(map_rpc_error)?;
+            squads_v4::parse_address_lookup_table(&lookup.account_key, &account.data)
+                .map_err(|message| AppServiceError::mobile(MobileErrorCode::InvalidInput, message))
+        })
+        .collect()
+}
+
 fn load_squads_program_config(client: &RpcClient) -> AppServiceResult<squads_v4::ProgramConfig> {
     let program_config = squads_v4::program_config_pda();
     let account = client.get_account(&program_config).map_err(map_rpc_error)?;
@@ -747,6 +1015,50 @@ fn load_squads_program_config(client: &RpcClient) -> AppServiceResult<squads_v4:
         .map_err(|message| AppServiceError::mobile(MobileErrorCode::InvalidInput, message))
 }
 
+/// Builds close instructions for the given transaction indexes, in order.
+/// `None` marks accounts that are already closed; the others carry either the
+/// instruction with the rent it frees or the reason the program would refuse.
+#[allow(clippy::type_complexity)]
+fn squads_close_candidates(
+    client: &RpcClient,
+    multisig_key: &Pubkey,
+    multisig: &squads_v4::Multisig,
+    indexes: &[u64],
+) -> AppServiceResult<Vec<Option<Result<(Instruction, u64), String>>>> {
+    let keys = indexes
+        .iter()
+        .flat_map(|index| {
+            [
+                squads_v4::transaction_pda(multisig_key, *index),
+                squads_v4::proposal_pda(multisig_key, *index),
+            ]
+        })
+        .collect::<Vec<_>>();
+    let accounts = client.get_multiple_accounts(&keys).map_err(map_rpc_error)?;
+    Ok(indexes
+        .iter()
+        .zip(accounts.chunks(2))
+        .map(|(index, pair)| {
+            let transaction = pair.first()?.as_ref()?;
+            let Some(proposal) = pair.get(1).and_then(Option::as_ref) else {
+                return Some(Err(format!(
+                    "Squads transaction #{index} has no proposal account"
+                )));
+            };
+            Some(
+                squads_v4::transaction_accounts_close_ix(
+                    multisig_key,
+                    multisig,
+                    *index,
+                    &transaction.data,
+                    &proposal.data,
+                )
+                .map(|(_, ix)| (ix, transaction.lamports.saturating_add(proposal.lamports))),
+            )
+        })
+        .collect())
+}
+
 fn next_squads_transaction_index(multisig: &squads_v4::Multisig) -> AppServiceResult<u64> {
     multisig.transaction_index.checked_add(1).ok_or_else(|| {
         AppServiceError::mobile(
@@ -775,9 +1087,19 @@ fn squads_member_summary(member: squads_v4::Member) -> SquadsMemberSummary {
 }
 
 fn squads_proposal_summary(
+    client: &RpcClient,
+    multisig: &Pubkey,
     address: Pubkey,
     proposal: squads_v4::Proposal,
 ) -> SquadsProposalSummary {
+    let (decoded, decode_error) = if proposal.status.is_pending() {
+        match squads_transaction_preview(client, multisig, proposal.transaction_index) {
+            Ok(decoded) => (Some(decoded), None),
+            Err(error) => (None, Some(error.to_string())),
+        }
+    } else {
+        (None, None)
+    };
     SquadsProposalSummary {
         address: address.to_string(),
         transaction_index: proposal.transaction_index,
@@ -797,9 +1119,87 @@ fn squads_proposal_summary(
             .into_iter()
             .map(|key| key.to_string())
             .collect(),
+        decoded,
+        decode_error,
     }
 }
 
+fn squads_transaction_preview(
+    client: &RpcClient,
+    multisig: &Pubkey,
+    transaction_index: u64,
+) -> AppServiceResult<SquadsTransactionPreview> {
+    let transaction_key = squads_v4::transaction_pda(multisig, transaction_index);
+    let transaction = load_squads_vault_transaction(client, &transaction_key)?;
+    let vault = squads_v4::vault_pda(multisig, transaction.vault_index);
+    let lookup_tables = load_squads_message_lookup_tables(client, &transaction.message)?;
+    let message = squads_v4::decode_transaction_message(&transaction.message, &lookup_tables)
+        .map_err(|message| AppServiceError::mobile(MobileErrorCode::InvalidInput, message))?;
+
+    let instructions = message
+        .instructions
+        .iter()
+        .map(|ix| {
+            let summary = squads_v4::summarize_instruction(ix);
+            SquadsInstructionPreview {
+                program_id: ix.program_id.to_string(),
+                program: summary.program,
+                name: summary.name,
+                fields: summary.fields,
+            }
+        })
+        .collect();
+    let outflows = squads_v4::vault_outflows(&vault, &message.instructions)
+        .into_iter()
+        .map(|outflow| {
+            let (asset, mint, decimals) = match outflow.asset {
+                squads_v4::OutflowAsset::Sol => ("SOL".to_string(), None, Some(9)),
+                squads_v4::OutflowAsset::Token { mint, decimals, .. } => (
+                    "token".to_string(),
+                    mint.map(|mint| mint.to_string()),
+                    decimals,
+                ),
+            };
+            SquadsVaultOutflow {
+                asset,
+                mint,
+                source: outflow.source.to_string(),
+                destination: outflow.destination.to_string(),
+                amount: outflow.amount,
+                decimals,
+            }
+        })
+        .collect();
+    let program_upgrades = squads_v4::program_upgrade_targets(&message.instructions)
+        .into_iter()
+        .map(|target| {
+            let buffer = client.get_account(&target.buffer).ok().and_then(|account| {
+                squads_v4::upgrade_buffer_program_bytes(&account.data)
+                    .ok()
+                    .map(|bytes| (bytes.len() as u64, hex_lower(&Sha256::digest(bytes))))
+            });
+            SquadsProgramUpgradePreview {
+                program_id: target.program.to_string(),
+                buffer: target.buffer.to_string(),
+                buffer_len: buffer.as_ref().map(|(len, _)| *len),
+                buffer_sha256: buffer.map(|(_, sha256)| sha256),
+            }
+        })
+        .collect();
+
+    Ok(SquadsTransactionPreview {
+        transaction: transaction_key.to_string(),
+        vault: vault.to_string(),
+        instructions,
+        outflows,
+        program_upgrades,
+    })
+}
+
+fn hex_lower(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
 fn transaction_result(
     signature: Signature,
     network: AppNetwork,
@@ -1421,7 +1821,41 @@ pub fn preview_payment(req: PaymentPreviewRequest) -> AppServiceResult<SigningPr
     require_non_empty(&req.wallet_public_key, "wallet public key")?;
     require_non_empty(&req.recipient, "recipient")?;
     require_non_empty(&req.amount, "amount")?;
+    let memo = require_memo(req.memo.as_deref())?;
+
+    // Without an address book or send history there is nothing to compare against
+    let recipient_check = if req.address_book.is_empty() && req.previous_recipients.is_empty() {
+        None
+    } else {
+        Some(check_recipient(RecipientCheckRequest {
+            network: req.network,
+            recipient: req.recipient.clone(),
+            mint: req.mint.clone(),
+            address_book: req.address_book,
+            previous_recipients: req.previous_recipients,
+        })?)
+    };
 
+    let mut summary = match recipient_check
+        .as_ref()
+        .and_then(|check| check.label.as_deref())
+    {
+        Some(label) => format!("Send {} to {} ({})", req.amount, label, req.recipient),
+        None => format!("Send {} to {}", req.amount, req.recipient),
+    };
+    if let Some(memo) = &memo {
+        summary.push_str(&format!(" with memo \"{}\"", memo));
+    }
+    let mut warnings = recipient_check
+        .map(|check| check.warnings)
+        .unwrap_or_default();
+    warnings.push("Review the recipient and network before signing.".to_string());
+    if memo.is_some() {
+        warnings.push(
+            "Exchanges credit deposits by memo; check it matches the one they gave you."
+                .to_string(),
+        );
+    }
     Ok(SigningPreview {
         id: Uuid::new_v4().to_string(),
         title: if req.mint.is_some() {
@@ -1431,12 +1865,161 @@ pub fn preview_payment(req: PaymentPreviewRequest) -> AppServiceResult<SigningPr
         },
         network: req.network,
         wallet_public_key: req.wallet_public_key,
-        summary: format!("Send {} to {}", req.amount, req.recipient),
-        warnings: vec!["Review the recipient and network before signing.".to_string()],
+        summary,
+        warnings,
         requires_user_confirmation: true,
     })
 }
 
+/// Add or update an entry, keeping one entry per network and address
+pub fn address_book_save(
+    mut book: Vec<AddressBookEntry>,
+    mut entry: AddressBookEntry,
+) -> AppServiceResult<Vec<AddressBookEntry>> {
+    entry.address = require_pubkey(&entry.address, "address")?.to_string();
+    entry.label = require_non_empty(&entry.label, "label")?;
+    entry.notes = entry
+        .notes
+        .map(|notes| notes.trim().to_string())
+        .filter(|notes| !notes.is_empty());
+    entry.expected_mint = match entry.expected_mint.as_deref().map(str::trim) {
+        Some(mint) if !mint.is_empty() => Some(require_pubkey(mint, "expected mint")?.to_string()),
+        _ => None,
+    };
+    if book.iter().any(|existing| {
+        existing.id != entry.id
+            && existing.network == entry.network
+            && existing.address == entry.address
+    }) {
+        return Err(AppServiceError::mobile(
+            MobileErrorCode::InvalidInput,
+            "This address is already in the address book for this network",
+        ));
+    }
+
+    if entry.id.trim().is_empty() {
+        entry.id = Uuid::new_v4().to_string();
+        entry.send_count = 0;
+        entry.last_sent_at_ms = None;
+        book.push(entry);
+    } else {
+        let existing = book
+            .iter_mut()
+            .find(|existing| existing.id == entry.id)
+            .ok_or_else(|| {
+                AppServiceError::mobile(
+                    MobileErrorCode::InvalidInput,
+                    "Address book entry not found",
+                )
+            })?;
+        entry.send_count = existing.send_count;
+        entry.last_sent_at_ms = existing.last_sent_at_ms;
+        *existing = entry;
+    }
+    Ok(book)
+}
+
+pub fn address_book_delete(
+    mut book: Vec<AddressBookEntry>,
+    id: &str,
+) -> AppServiceResult<Vec<AddressBookEntry>> {
+    let before = book.len();
+    book.retain(|entry| entry.id != id);
+    if book.len() == before {
+        return Err(AppServiceError::mobile(
+            MobileErrorCode::InvalidInput,
+            "Address book entry not found",
+        ));
+    }
+    Ok(book)
+}
+
+/// Count a confirmed send on the matching entry, if the recipient is saved
+pub fn address_book_record_send(
+    mut book: Vec<AddressBookEntry>,
+    network: AppNetwork,
+    recipient: &str,
+) -> Vec<AddressBookEntry> {
+    let recipient = recipient.trim();
+    if let Some(entry) = book
+        .iter_mut()
+        .find(|entry| entry.network == network && entry.address == recipient)
+    {
+        entry.send_count += 1;
+        entry.last_sent_at_ms = Some(now_ms());
+    }
+    book
+}
+
+/// Match a recipient against the address book before a payment is signed
+pub fn check_recipient(req: RecipientCheckRequest) -> AppServiceResult<RecipientCheck> {
+    let recipient = require_pubkey(&req.recipient, "recipient")?.to_string();
+    let mint = match req.mint.as_deref().map(str::trim) {
+        Some(mint) if !mint.is_empty() => Some(require_pubkey(mint, "token mint")?.to_string()),
+        _ => None,
+    };
+    let book: Vec<&AddressBookEntry> = req
+        .address_book
+        .iter()
+        .filter(|entry| entry.network == req.network)
+        .collect();
+    let check = fnzero_safe::address_book::check_recipient(
+        &recipient,
+        mint.as_deref(),
+        book.iter()
+            .map(|entry| fnzero_safe::address_book::KnownAddress {
+                address: &entry.address,
+                label: &entry.label,
+                expected_mint: entry.expected_mint.as_deref(),
+            }),
+    );
+    let saved = book.iter().find(|entry| entry.address == recipient);
+    let first_send = saved.is_none_or(|entry| entry.send_count == 0)
+        && !req
+            .previous_recipients
+            .iter()
+            .any(|previous| previous.trim() == recipient);
+
+    let mut warnings = Vec::new();
+    for lookalike in &check.lookalikes {
+        warnings.push(format!(
+            "DANGER: this address starts and ends like \"{}\" ({}) but is a different address. \
+             It may be address poisoning; compare every character.",
+            lookalike.label, lookalike.address
+        ));
+    }
+    if check.mint_mismatch {
+        warnings.push(format!(
+            "\"{}\" only accepts {}; this payment sends a different asset.",
+            check.label.as_deref().unwrap_or_default(),
+            check.expected_mint.as_deref().unwrap_or_default()
+        ));
+    }
+    if first_send {
+        warnings.push(if check.label.is_some() {
+            "You have never paid this saved address before. Confirm the entry is correct."
+                .to_string()
+        } else {
+            "First payment to an address that is not in your address book. Check the full address."
+                .to_string()
+        });
+    }
+
+    Ok(RecipientCheck {
+        label: check.label,
+        notes: saved.and_then(|entry| entry.notes.clone()),
+        expected_mint: check.expected_mint,
+        mint_mismatch: check.mint_mismatch,
+        first_send,
+        lookalikes: check
+            .lookalikes
+            .into_iter()
+            .map(|lookalike| format!("{} ({})", lookalike.label, lookalike.address))
+            .collect(),
+        warnings,
+    })
+}
+
 pub fn submit_payment(req: PaymentSubmitRequest) -> AppServiceResult<TransactionSubmitResult> {
     require_non_empty(&req.preview_id, "preview id")?;
     if !req.approved {
@@ -1464,6 +2047,18 @@ pub fn submit_payment(req: PaymentSubmitRequest) -> AppServiceResult<Transaction
         req.network,
         req.rpc_url,
     )?);
+    let memo = require_memo(req.memo.as_deref())?;
+    if memo.is_some()
+        && !matches!(
+            req.operation,
+            PaymentOperation::SolTransfer | PaymentOperation::SplTokenTransfer
+        )
+    {
+        return Err(AppServiceError::mobile(
+            MobileErrorCode::InvalidInput,
+            "A memo can only be attached to SOL and token transfers",
+        ));
+    }
 
     let signature = match req.operation {
         PaymentOperation::SolTransfer => {
@@ -1471,7 +2066,12 @@ pub fn submit_payment(req: PaymentSubmitRequest) -> AppServiceResult<Transaction
             let recipient =
                 require_pubkey(req.recipient.as_deref().unwrap_or_default(), "recipient")?;
             client
-                .transfer_sol(&keypair, &recipient, req.amount_base_units)
+                .transfer_sol_with_memo(
+                    &keypair,
+                    &recipient,
+                    req.amount_base_units,
+                    memo.as_deref(),
+                )
                 .map_err(map_rpc_error)?
         }
         PaymentOperation::SplTokenTransfer => {
@@ -1479,8 +2079,12 @@ pub fn submit_payment(req: PaymentSubmitRequest) -> AppServiceResult<Transaction
             let recipient =
                 require_pubkey(req.recipient.as_deref().unwrap_or_default(), "recipient")?;
             let mint = require_pubkey(req.mint.as_deref().unwrap_or_default(), "token mint")?;
+            let plan = client
+                .plan_token_transfer(&keypair.pubkey(), &recipient, &mint, req.amount_base_units)
+                .and_then(|plan| plan.with_memo(memo.as_deref()))
+                .map_err(map_rpc_error)?;
             client
-                .transfer_token(&keypair, &recipient, &mint, req.amount_base_units)
+                .send_token_transfer(&keypair, &plan)
                 .map_err(map_rpc_error)?
         }
         PaymentOperation::WsolWrap => {
@@ -1523,6 +2127,47 @@ pub fn preview_pump_trade(req: PumpPreviewRequest) -> AppServiceResult<SigningPr
     })
 }
 
+pub fn preview_pump_buy(req: PumpBuyPreviewRequest) -> AppServiceResult<SigningPreview> {
+    require_non_empty(&req.wallet_public_key, "wallet public key")?;
+    require_non_empty(&req.mint, "token mint")?;
+    require_non_empty(&req.venue, "venue")?;
+    require_positive_amount(req.amount_lamports, "buy amount")?;
+    let input_token = req.input_token.trim().to_ascii_uppercase();
+    if input_token != "SOL" && input_token != "WSOL" {
+        return Err(AppServiceError::mobile(
+            MobileErrorCode::InvalidInput,
+            "Buy input token must be SOL or WSOL",
+        ));
+    }
+    if req.slippage_bps > 10_000 {
+        return Err(AppServiceError::mobile(
+            MobileErrorCode::InvalidInput,
+            "Slippage cannot exceed 100%",
+        ));
+    }
+
+    let mut warnings =
+        vec!["Pump trades can move quickly; confirm slippage before signing.".to_string()];
+    if req.create_ata {
+        warnings.push("The token account will be created if missing and costs rent.".to_string());
+    }
+    Ok(SigningPreview {
+        id: Uuid::new_v4().to_string(),
+        title: format!("{} Buy", req.venue),
+        network: req.network,
+        wallet_public_key: req.wallet_public_key,
+        summary: format!(
+            "Buy {} with {} {} and {} bps slippage",
+            req.mint,
+            fnzero_safe::solana_utils::lamports_to_sol(req.amount_lamports),
+            input_token,
+            req.slippage_bps
+        ),
+        warnings,
+        requires_user_confirmation: true,
+    })
+}
+
 pub fn preview_dapp_signing(req: DappSignPreviewRequest) -> AppServiceResult<SigningPreview> {
     require_non_empty(&req.wallet_public_key, "wallet public key")?;
     require_non_empty(&req.app_name, "dApp name")?;
@@ -1705,6 +2350,8 @@ pub fn squads_info(req: SquadsInfoRequest) -> AppServiceResult<SquadsInfoRespons
     {
         let proposal_key = require_pubkey(proposal, "Squads proposal")?;
         Some(squads_proposal_summary(
+            &client,
+            &multisig_key,
             proposal_key,
             load_squads_proposal(&client, &proposal_key)?,
         ))
@@ -1745,7 +2392,12 @@ pub fn squads_proposals(req: SquadsProposalsRequest) -> AppServiceResult<SquadsP
     for index in (end..=start).rev() {
         let proposal_key = squads_v4::proposal_pda(&multisig_key, index);
         if let Ok(proposal) = load_squads_proposal(&client, &proposal_key) {
-            proposals.push(squads_proposal_summary(proposal_key, proposal));
+            proposals.push(squads_proposal_summary(
+                &client,
+                &multisig_key,
+                proposal_key,
+                proposal,
+            ));
         }
     }
 
@@ -1934,6 +2586,7 @@ pub fn squads_transfer_proposal_submit(
         transaction_index,
         0,
         &inner_instructions,
+        &[],
         req.memo,
     )
     .map_err(|message| AppServiceError::mobile(MobileErrorCode::InvalidInput, message))?;
@@ -2007,6 +2660,174 @@ pub fn squads_reject_submit(
     Ok(transaction_result(signature, req.network, "confirmed"))
 }
 
+pub fn squads_cancel_submit(
+    req: SquadsVoteSubmitRequest,
+) -> AppServiceResult<TransactionSubmitResult> {
+    if !req.approved {
+        return Err(AppServiceError::mobile(
+            MobileErrorCode::UserRejected,
+            "User rejected the Squads cancel request",
+        ));
+    }
+    let signer = keypair_from_mobile_keystore(&req.keystore_json, &req.password)?;
+    let multisig = require_pubkey(&req.multisig, "Squads multisig")?;
+    let proposal = require_pubkey(&req.proposal, "Squads proposal")?;
+    let client = RpcClient::new_with_commitment(
+        rpc_url(req.network, req.rpc_url)?,
+        CommitmentConfig::confirmed(),
+    );
+    let state = load_squads_multisig(&client, &multisig)?;
+    require_squads_member(&state, &signer.pubkey())?;
+    let ix = squads_v4::proposal_cancel_ix(&multisig, &proposal, &signer.pubkey(), req.memo)
+        .map_err(|message| AppServiceError::mobile(MobileErrorCode::InvalidInput, message))?;
+    let signature = sign_and_send_single(&client, ix, &signer)?;
+    Ok(transaction_result(signature, req.network, "confirmed"))
+}
+
+/// Activates a draft proposal. The request memo is not used; activation
+/// takes no arguments on chain.
+pub fn squads_activate_submit(
+    req: SquadsVoteSubmitRequest,
+) -> AppServiceResult<TransactionSubmitResult> {
+    if !req.approved {
+        return Err(AppServiceError::mobile(
+            MobileErrorCode::UserRejected,
+            "User rejected the Squads activate request",
+        ));
+    }
+    let signer = keypair_from_mobile_keystore(&req.keystore_json, &req.password)?;
+    let multisig = require_pubkey(&req.multisig, "Squads multisig")?;
+    let proposal = require_pubkey(&req.proposal, "Squads proposal")?;
+    let client = RpcClient::new_with_commitment(
+        rpc_url(req.network, req.rpc_url)?,
+        CommitmentConfig::confirmed(),
+    );
+    let state = load_squads_multisig(&client, &multisig)?;
+    require_squads_member(&state, &signer.pubkey())?;
+    let proposal_state = load_squads_proposal(&client, &proposal)?;
+    if !matches!(
+        proposal_state.status,
+        squads_v4::ProposalStatus::Draft { .. }
+    ) {
+        return Err(AppServiceError::mobile(
+            MobileErrorCode::InvalidInput,
+            format!(
+                "Only draft proposals can be activated; this one is {}",
+                proposal_state.status.label()
+            ),
+        ));
+    }
+    let ix = squads_v4::proposal_activate_ix(&multisig, &proposal, &signer.pubkey());
+    let signature = sign_and_send_single(&client, ix, &signer)?;
+    Ok(transaction_result(signature, req.network, "confirmed"))
+}
+
+pub fn squads_close_submit(
+    req: SquadsCloseSubmitRequest,
+) -> AppServiceResult<TransactionSubmitResult> {
+    if !req.approved {
+        return Err(AppServiceError::mobile(
+            MobileErrorCode::UserRejected,
+            "User rejected the Squads close request",
+        ));
+    }
+    let signer = keypair_from_mobile_keystore(&req.keystore_json, &req.password)?;
+    let multisig_key = require_pubkey(&req.multisig, "Squads multisig")?;
+    let client = RpcClient::new_with_commitment(
+        rpc_url(req.network, req.rpc_url)?,
+        CommitmentConfig::confirmed(),
+    );
+    let multisig = load_squads_multisig(&client, &multisig_key)?;
+    let (ix, _) =
+        squads_close_candidates(&client, &multisig_key, &multisig, &[req.transaction_index])?
+            .into_iter()
+            .next()
+            .flatten()
+            .ok_or_else(|| {
+                AppServiceError::mobile(
+                    MobileErrorCode::InvalidInput,
+                    "Squads transaction accounts are already closed",
+                )
+            })?
+            .map_err(|message| AppServiceError::mobile(MobileErrorCode::InvalidInput, message))?;
+    let signature = sign_and_send_single(&client, ix, &signer)?;
+    Ok(transaction_result(signature, req.network, "confirmed"))
+}
+
+/// Closes every executed, rejected, cancelled or stale transaction of a
+/// multisig, up to `MAX_SQUADS_CLEANUP_CLOSES` per call, returning the rent
+/// to the multisig's rent collector.
+pub fn squads_cleanup_submit(
+    req: SquadsCleanupSubmitRequest,
+) -> AppServiceResult<SquadsCleanupSubmitResult> {
+    if !req.approved {
+        return Err(AppServiceError::mobile(
+            MobileErrorCode::UserRejected,
+            "User rejected the Squads cleanup request",
+        ));
+    }
+    let signer = keypair_from_mobile_keystore(&req.keystore_json, &req.password)?;
+    let multisig_key = require_pubkey(&req.multisig, "Squads multisig")?;
+    let client = RpcClient::new_with_commitment(
+        rpc_url(req.network, req.rpc_url)?,
+        CommitmentConfig::confirmed(),
+    );
+    let multisig = load_squads_multisig(&client, &multisig_key)?;
+    let rent_collector = multisig.rent_collector.ok_or_else(|| {
+        AppServiceError::mobile(
+            MobileErrorCode::InvalidInput,
+            "Squads multisig has no rent collector",
+        )
+    })?;
+
+    let mut closable = Vec::new();
+    let mut skipped = Vec::new();
+    let indexes = (1..=multisig.transaction_index).collect::<Vec<_>>();
+    for chunk in indexes.chunks(50) {
+        let candidates = squads_close_candidates(&client, &multisig_key, &multisig, chunk)?;
+        for (index, candidate) in chunk.iter().zip(candidates) {
+            match candidate {
+                Some(Ok((ix, lamports))) => closable.push((*index, ix, lamports)),
+                Some(Err(reason)) => skipped.push(SquadsCleanupSkipped {
+                    transaction_index: *index,
+                    reason,
+                }),
+                None => {}
+            }
+        }
+    }
+
+    let remaining = closable.len().saturating_sub(MAX_SQUADS_CLEANUP_CLOSES) as u64;
+    closable.truncate(MAX_SQUADS_CLEANUP_CLOSES);
+    let mut transactions = Vec::new();
+    let mut closed = Vec::new();
+    let mut reclaimed_lamports = 0_u64;
+    for batch in closable.chunks(SQUADS_CLOSES_PER_TRANSACTION) {
+        let signature = sign_and_send_instructions(
+            &client,
+            batch.iter().map(|(_, ix, _)| ix.clone()).collect(),
+            &[&signer],
+            &signer.pubkey(),
+        )?;
+        transactions.push(transaction_result(signature, req.network, "confirmed"));
+        for (index, _, lamports) in batch {
+            closed.push(*index);
+            reclaimed_lamports = reclaimed_lamports.saturating_add(*lamports);
+        }
+    }
+
+    Ok(SquadsCleanupSubmitResult {
+        multisig: multisig_key.to_string(),
+        rent_collector: rent_collector.to_string(),
+        closed,
+        skipped,
+        transactions,
+        reclaimed_lamports,
+        remaining,
+        network: req.network,
+    })
+}
+
 pub fn squads_execute_submit(
     req: SquadsExecuteSubmitRequest,
 ) -> AppServiceResult<TransactionSubmitResult> {
@@ -2033,6 +2854,8 @@ pub fn squads_execute_submit(
             "Squads transaction account does not match the request",
         ));
     }
+    let address_lookup_tables =
+        load_squads_message_lookup_tables(&client, &vault_transaction.message)?;
     let ix = squads_v4::vault_transaction_execute_ix(
         &multisig,
         &transaction,
@@ -2046,6 +2869,7 @@ pub fn squads_execute_submit(
                 "Squads ephemeral signer count is out of range",
             )
         })?,
+        &address_lookup_tables,
     )
     .map_err(|message| AppServiceError::mobile(MobileErrorCode::InvalidInput, message))?;
     let signature = sign_and_send_single(&client, ix, &signer)?;
@@ -2062,29 +2886,82 @@ pub fn setup_totp(account: String) -> AppServiceResult<TotpSetup> {
     })
 }
 
+/// Replay and lockout state for this process, keyed by a hash of the TOTP secret
+fn totp_state_store() -> &'static Mutex<MemoryTotpStateStore> {
+    static STORE: OnceLock<Mutex<MemoryTotpStateStore>> = OnceLock::new();
+    STORE.get_or_init(|| Mutex::new(MemoryTotpStateStore::default()))
+}
+
+fn totp_guard_error(error: TotpGuardError) -> AppServiceError {
+    let message = match error {
+        TotpGuardError::Locked { retry_after_secs } => format!(
+            "Too many failed 2FA attempts; try again in {} seconds",
+            retry_after_secs
+        ),
+        TotpGuardError::InvalidCode { remaining_attempts } => format!(
+            "Invalid TOTP code; {} attempt(s) left before lockout",
+            remaining_attempts
+        ),
+        TotpGuardError::Replayed => {
+            "This TOTP code has already been used; wait for the next one".to_string()
+        }
+        TotpGuardError::Unlock(_) | TotpGuardError::Config(_) => "Invalid TOTP code".to_string(),
+    };
+    AppServiceError::mobile(MobileErrorCode::TotpInvalid, message)
+}
+
+/// Verify through the core 2FA guard, merging in state the app persisted
+fn verify_totp_guarded(
+    secret: &str,
+    code: &str,
+    persisted: Option<TotpAttemptState>,
+) -> (Result<u64, TotpGuardError>, TotpGuardState) {
+    let key = totp_guard::totp_wallet_key(secret);
+    let mut store = totp_state_store()
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    if let Some(persisted) = persisted {
+        let mut state = store.load(&key).unwrap_or_default();
+        state.merge(&persisted.into());
+        let _ = store.save(&key, &state);
+    }
+    let result = totp_guard::verify_totp(&mut *store, &key, secret, code);
+    let state = store.load(&key).unwrap_or_default();
+    (result, state)
+}
+
+/// Check a code once; a code is refused after it has been accepted and repeated failures
+/// lock the secret out for the lifetime of the process. Use [`verify_totp_with_state`] to
+/// keep that state across restarts.
 pub fn verify_totp(req: TotpVerifyRequest) -> AppServiceResult<bool> {
     let secret = require_non_empty(&req.secret, "TOTP secret")?;
     let code = require_non_empty(&req.code, "TOTP code")?;
 
-    let manager = fnzero_safe::totp::TOTPManager::new(fnzero_safe::totp::TOTPConfig {
-        secret,
-        issuer: "FnzeroSafe".to_string(),
-        account: "mobile-wallet".to_string(),
-        algorithm: "SHA1".to_string(),
-        digits: 6,
-        step: 30,
-    });
-
-    let verified = manager
-        .verify_code(&code)
-        .map_err(|_| AppServiceError::mobile(MobileErrorCode::TotpInvalid, "Invalid TOTP code"))?;
-    if !verified {
-        return Err(AppServiceError::mobile(
-            MobileErrorCode::TotpInvalid,
-            "Invalid TOTP code",
-        ));
-    }
-    Ok(true)
+    verify_totp_guarded(&secret, &code, None)
+        .0
+        .map(|_| true)
+        .map_err(totp_guard_error)
+}
+
+pub fn verify_totp_with_state(
+    req: TotpStatefulVerifyRequest,
+) -> AppServiceResult<TotpVerification> {
+    let secret = require_non_empty(&req.secret, "TOTP secret")?;
+    let code = require_non_empty(&req.code, "TOTP code")?;
+
+    let (result, state) = verify_totp_guarded(&secret, &code, req.state);
+    let retry_after_secs = match &result {
+        Err(TotpGuardError::Locked { retry_after_secs }) => Some(*retry_after_secs),
+        _ => None,
+    };
+    Ok(TotpVerification {
+        verified: result.is_ok(),
+        state: state.into(),
+        error: result
+            .err()
+            .map(|error| totp_guard_error(error).to_mobile_error()),
+        retry_after_secs,
+    })
 }
 
 pub fn biometric_policy_stub() -> BiometricPolicy {
@@ -2204,11 +3081,115 @@ mod tests {
             mint: None,
             amount: "0.1 SOL".to_string(),
             memo: None,
+            address_book: Vec::new(),
+            previous_recipients: Vec::new(),
         })
         .unwrap();
 
         assert!(preview.requires_user_confirmation);
         assert_eq!(preview.title, "SOL Payment");
+
+        let preview = preview_payment(PaymentPreviewRequest {
+            network: AppNetwork::Devnet,
+            wallet_public_key: "Wallet1111111111111111111111111111111111".to_string(),
+            recipient: "Recipient111111111111111111111111111111".to_string(),
+            mint: None,
+            amount: "0.1 SOL".to_string(),
+            memo: Some(" 104857 ".to_string()),
+            address_book: Vec::new(),
+            previous_recipients: Vec::new(),
+        })
+        .unwrap();
+        assert!(preview.summary.ends_with("with memo \"104857\""));
+        assert_eq!(preview.warnings.len(), 2);
+
+        let error = preview_payment(PaymentPreviewRequest {
+            network: AppNetwork::Devnet,
+            wallet_public_key: "Wallet1111111111111111111111111111111111".to_string(),
+            recipient: "Recipient111111111111111111111111111111".to_string(),
+            mint: None,
+            amount: "0.1 SOL".to_string(),
+            memo: Some("x".repeat(300)),
+            address_book: Vec::new(),
+            previous_recipients: Vec::new(),
+        })
+        .unwrap_err();
+        assert_eq!(error.to_mobile_error().code, MobileErrorCode::InvalidInput);
+    }
+
+    #[test]
+    fn address_book_labels_recipients_and_flags_lookalikes() {
+        let treasury = "9xQeWvG816bUx9EPjHmaT23yvVM2ZWbrrpZb9PusVFin".to_string();
+        let entry = AddressBookEntry {
+            id: String::new(),
+            network: AppNetwork::Mainnet,
+            address: format!(" {treasury} "),
+            label: "Treasury".to_string(),
+            notes: None,
+            expected_mint: None,
+            send_count: 7,
+            last_sent_at_ms: None,
+        };
+        let book = address_book_save(Vec::new(), entry.clone()).unwrap();
+        assert_eq!(book[0].address, treasury);
+        assert_eq!(book[0].send_count, 0);
+        assert!(address_book_save(book.clone(), entry).is_err());
+
+        let preview = |recipient: &str, book: Vec<AddressBookEntry>| {
+            preview_payment(PaymentPreviewRequest {
+                network: AppNetwork::Mainnet,
+                wallet_public_key: "Wallet1111111111111111111111111111111111".to_string(),
+                recipient: recipient.to_string(),
+                mint: None,
+                amount: "1 SOL".to_string(),
+                memo: None,
+                address_book: book,
+                previous_recipients: Vec::new(),
+            })
+            .unwrap()
+        };
+        let first = preview(&treasury, book.clone());
+        assert_eq!(
+            first.summary,
+            format!("Send 1 SOL to Treasury ({treasury})")
+        );
+        assert!(first.warnings[0].contains("never paid"));
+
+        let book = address_book_record_send(book, AppNetwork::Mainnet, &treasury);
+        assert_eq!(book[0].send_count, 1);
+        assert_eq!(preview(&treasury, book.clone()).warnings.len(), 1);
+
+        let poisoned = preview("9xQeZZZZZZZZZZZZZZZZZZZZZZZZZZZZZZZZZZZZsFin", book.clone());
+        assert!(poisoned.warnings[0].starts_with("DANGER"));
+        assert!(poisoned.warnings[1].starts_with("First payment"));
+
+        let id = book[0].id.clone();
+        assert!(address_book_delete(book, &id).unwrap().is_empty());
+    }
+
+    #[test]
+    fn pump_buy_preview_validates_input_token() {
+        let request = PumpBuyPreviewRequest {
+            network: AppNetwork::Mainnet,
+            wallet_public_key: "Wallet1111111111111111111111111111111111".to_string(),
+            mint: "Mint111111111111111111111111111111111111".to_string(),
+            amount_lamports: 250_000_000,
+            input_token: "sol".to_string(),
+            slippage_bps: 500,
+            create_ata: true,
+            venue: "Pump.fun".to_string(),
+        };
+        let preview = preview_pump_buy(request.clone()).unwrap();
+        assert_eq!(preview.title, "Pump.fun Buy");
+        assert!(preview.summary.contains("0.25 SOL"));
+        assert_eq!(preview.warnings.len(), 2);
+
+        let error = preview_pump_buy(PumpBuyPreviewRequest {
+            input_token: "USDC".to_string(),
+            ..request
+        })
+        .unwrap_err();
+        assert_eq!(error.to_mobile_error().code, MobileErrorCode::InvalidInput);
     }
 
     #[test]
@@ -2225,6 +3206,7 @@ mod tests {
             recipient: Some("11111111111111111111111111111111".to_string()),
             mint: None,
             amount_base_units: 1,
+            memo: None,
         })
         .unwrap_err();
 
@@ -2251,6 +3233,7 @@ mod tests {
             recipient: Some("11111111111111111111111111111111".to_string()),
             mint: None,
             amount_base_units: 1,
+            memo: None,
         })
         .unwrap_err();
 
@@ -2278,6 +3261,7 @@ mod tests {
             recipient: Some("11111111111111111111111111111111".to_string()),
             mint: None,
             amount_base_units: 0,
+            memo: None,
         })
         .unwrap_err();
 
@@ -2304,6 +3288,7 @@ mod tests {
             recipient: None,
             mint: None,
             amount_base_units: 0,
+            memo: None,
         })
         .unwrap_err();
 
@@ -2482,4 +3467,41 @@ mod tests {
 
         assert_eq!(error.to_mobile_error().code, MobileErrorCode::TotpInvalid);
     }
+
+    #[test]
+    fn stateful_totp_refuses_replayed_codes() {
+        let secret = setup_totp("mobile-wallet".to_string()).unwrap().secret;
+        let code = fnzero_safe::totp::TOTPManager::new(fnzero_safe::totp::TOTPConfig {
+            secret: secret.clone(),
+            ..Default::default()
+        })
+        .generate_current_code()
+        .unwrap();
+
+        let first = verify_totp_with_state(TotpStatefulVerifyRequest {
+            secret: secret.clone(),
+            code: code.clone(),
+            state: None,
+        })
+        .unwrap();
+        assert!(first.verified);
+        assert!(first.state.last_accepted_step.is_some());
+
+        let replay = verify_totp(TotpVerifyRequest {
+            secret: secret.clone(),
+            code: code.clone(),
+        })
+        .unwrap_err();
+        assert_eq!(replay.to_mobile_error().code, MobileErrorCode::TotpInvalid);
+
+        let replay = verify_totp_with_state(TotpStatefulVerifyRequest {
+            secret,
+            code,
+            state: Some(first.state),
+        })
+        .unwrap();
+        assert!(!replay.verified);
+        assert_eq!(replay.state.failed_attempts, 2);
+        assert!(replay.error.is_some());
+    }
 }
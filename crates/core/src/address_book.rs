@@ -0,0 +1,157 @@
+//! Recipient checks against an address book.
+//!
+//! Address poisoning plants an address that shares the first and last characters of
+//! one the victim already pays, counting on truncated displays (`AbCd…wXyZ`) and copy
+//! from history. A recipient that matches a known address at both ends without being
+//! identical to it is reported as a lookalike.
+
+use serde::Serialize;
+
+/// Characters that must match at each end for a lookalike
+pub const LOOKALIKE_MIN_EDGE_CHARS: usize = 3;
+/// Characters that must match at both ends together for a lookalike
+pub const LOOKALIKE_MIN_MATCHED_CHARS: usize = 7;
+
+/// An address-book entry (or own wallet) a recipient is checked against
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KnownAddress<'a> {
+    pub address: &'a str,
+    pub label: &'a str,
+    /// Mint this address is meant to receive, e.g. an exchange's USDC deposit address
+    pub expected_mint: Option<&'a str>,
+}
+
+/// A known address the recipient imitates
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Lookalike {
+    pub address: String,
+    pub label: String,
+}
+
+/// What the address book says about a recipient
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct RecipientCheck {
+    /// Label of the entry equal to the recipient
+    pub label: Option<String>,
+    pub expected_mint: Option<String>,
+    /// The entry expects a different mint than the one being sent (`None` = SOL)
+    pub mint_mismatch: bool,
+    pub lookalikes: Vec<Lookalike>,
+}
+
+/// Whether `candidate` differs from `known` but shares enough of both ends to pass for it
+pub fn is_lookalike(candidate: &str, known: &str) -> bool {
+    if candidate == known {
+        return false;
+    }
+    let prefix = candidate
+        .chars()
+        .zip(known.chars())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let suffix = candidate
+        .chars()
+        .rev()
+        .zip(known.chars().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    prefix >= LOOKALIKE_MIN_EDGE_CHARS
+        && suffix >= LOOKALIKE_MIN_EDGE_CHARS
+        && prefix + suffix >= LOOKALIKE_MIN_MATCHED_CHARS
+}
+
+/// Look `recipient` up in `known` for a transfer of `mint` (`None` = SOL)
+pub fn check_recipient<'a>(
+    recipient: &str,
+    mint: Option<&str>,
+    known: impl IntoIterator<Item = KnownAddress<'a>>,
+) -> RecipientCheck {
+    let recipient = recipient.trim();
+    let mut check = RecipientCheck::default();
+    for entry in known {
+        if entry.address == recipient {
+            if check.label.is_none() {
+                check.label = Some(entry.label.to_string());
+            }
+            if let Some(expected) = entry.expected_mint {
+                check.mint_mismatch |= mint != Some(expected);
+                check.expected_mint = Some(expected.to_string());
+            }
+        } else if is_lookalike(recipient, entry.address)
+            && !check
+                .lookalikes
+                .iter()
+                .any(|lookalike| lookalike.address == entry.address)
+        {
+            check.lookalikes.push(Lookalike {
+                address: entry.address.to_string(),
+                label: entry.label.to_string(),
+            });
+        }
+    }
+    check
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TREASURY: &str = "9xQeWvG816bUx9EPjHmaT23yvVM2ZWbrrpZb9PusVFin";
+    const USDC: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+
+    #[test]
+    fn flags_addresses_matching_both_ends() {
+        assert!(is_lookalike(
+            "9xQeAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAFin",
+            TREASURY
+        ));
+        assert!(is_lookalike(
+            "9xQAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAVFin",
+            TREASURY
+        ));
+        assert!(!is_lookalike(TREASURY, TREASURY));
+        // One end only is ordinary coincidence
+        assert!(!is_lookalike(
+            "9xQeWvG8AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA",
+            TREASURY
+        ));
+        assert!(!is_lookalike(
+            "9xQAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAFin",
+            TREASURY
+        ));
+    }
+
+    #[test]
+    fn checks_labels_lookalikes_and_expected_mints() {
+        let book = [
+            KnownAddress {
+                address: TREASURY,
+                label: "Treasury",
+                expected_mint: None,
+            },
+            KnownAddress {
+                address: "BinanceDeposit1111111111111111111111111111",
+                label: "Exchange USDC",
+                expected_mint: Some(USDC),
+            },
+        ];
+
+        let check = check_recipient(TREASURY, None, book);
+        assert_eq!(check.label.as_deref(), Some("Treasury"));
+        assert!(!check.mint_mismatch && check.lookalikes.is_empty());
+
+        let check = check_recipient("BinanceDeposit1111111111111111111111111111", None, book);
+        assert!(check.mint_mismatch);
+        let check = check_recipient(
+            "BinanceDeposit1111111111111111111111111111",
+            Some(USDC),
+            book,
+        );
+        assert!(!check.mint_mismatch);
+
+        let check = check_recipient("9xQeZZZZZZZZZZZZZZZZZZZZZZZZZZZZZZZZZZZZsFin", None, book);
+        assert_eq!(check.label, None);
+        assert_eq!(check.lookalikes.len(), 1);
+        assert_eq!(check.lookalikes[0].label, "Treasury");
+    }
+}
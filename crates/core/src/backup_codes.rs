@@ -0,0 +1,140 @@
+//! One-time recovery codes for 2FA wallets.
+//!
+//! Codes are shown once at setup and only their Argon2 hashes are kept, inside the
+//! encrypted triple-factor envelope. A code stands in for a TOTP code when the phone is
+//! lost; redeeming it removes its hash, so the envelope has to be re-encrypted and saved
+//! in place of the old one.
+
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use rand::Rng;
+use ring::rand::{SecureRandom, SystemRandom};
+
+/// Codes issued per set
+pub const BACKUP_CODE_COUNT: usize = 10;
+/// Digits per code, shown as two groups of four
+pub const BACKUP_CODE_DIGITS: usize = 8;
+
+/// A freshly generated set: plain codes for the user, hashes for the envelope
+pub struct BackupCodeSet {
+    pub codes: Vec<String>,
+    pub hashes: Vec<String>,
+}
+
+/// Generate `count` codes formatted as `1234-5678`
+pub fn new_backup_codes(count: usize) -> Vec<String> {
+    let mut rng = rand::rngs::OsRng;
+    (0..count)
+        .map(|_| {
+            let digits: String = (0..BACKUP_CODE_DIGITS)
+                .map(|_| char::from(b'0' + rng.gen_range(0..10u8)))
+                .collect();
+            format!("{}-{}", &digits[..4], &digits[4..])
+        })
+        .collect()
+}
+
+/// Generate a full set of [`BACKUP_CODE_COUNT`] codes and hash them
+pub fn generate_backup_codes() -> Result<BackupCodeSet, String> {
+    let codes = new_backup_codes(BACKUP_CODE_COUNT);
+    let hashes = codes
+        .iter()
+        .map(|code| hash_backup_code(code))
+        .collect::<Result<_, _>>()?;
+    Ok(BackupCodeSet { codes, hashes })
+}
+
+/// Digits of `code` without separators, or `None` if it is not shaped like a backup code
+pub fn normalize_backup_code(code: &str) -> Option<String> {
+    let digits: String = code
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .collect();
+    (digits.len() == BACKUP_CODE_DIGITS && digits.bytes().all(|b| b.is_ascii_digit()))
+        .then_some(digits)
+}
+
+/// Argon2id PHC string for `code`
+pub fn hash_backup_code(code: &str) -> Result<String, String> {
+    let digits = normalize_backup_code(code).ok_or("备用恢复码格式无效")?;
+    let mut salt = [0u8; 16];
+    SystemRandom::new()
+        .fill(&mut salt)
+        .map_err(|_| "生成随机盐失败".to_string())?;
+    let salt = SaltString::encode_b64(&salt).map_err(|e| format!("生成随机盐失败: {}", e))?;
+    Argon2::default()
+        .hash_password(digits.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| format!("备用恢复码哈希失败: {}", e))
+}
+
+/// Position of the hash in `hashes` that `code` matches
+pub fn find_backup_code(hashes: &[String], code: &str) -> Option<usize> {
+    let digits = normalize_backup_code(code)?;
+    hashes.iter().position(|hash| {
+        PasswordHash::new(hash).is_ok_and(|parsed| {
+            Argon2::default()
+                .verify_password(digits.as_bytes(), &parsed)
+                .is_ok()
+        })
+    })
+}
+
+/// Store a re-sealed envelope in a CLI wallet file, then commit its generation
+///
+/// If the file write fails nothing is committed, so the envelope still on disk keeps working.
+pub fn save_resealed_wallet_file(
+    wallet_file: &str,
+    reseal: crate::TripleFactorReseal,
+    totp_state: &mut dyn crate::totp_guard::TotpStateStore,
+) -> Result<(), String> {
+    save_wallet_file_envelope(wallet_file, &reseal.encrypted_data)?;
+    reseal.commit(totp_state)
+}
+
+/// Replace the envelope in a CLI wallet file after a code was spent or the set regenerated
+pub fn save_wallet_file_envelope(wallet_file: &str, encrypted_data: &str) -> Result<(), String> {
+    update_wallet_file(wallet_file, |data| {
+        data["encrypted_private_key"] = serde_json::Value::from(encrypted_data);
+    })
+}
+
+/// Rewrite a CLI wallet file's JSON through a temporary file
+pub(crate) fn update_wallet_file(
+    wallet_file: &str,
+    update: impl FnOnce(&mut serde_json::Value),
+) -> Result<(), String> {
+    let content =
+        std::fs::read_to_string(wallet_file).map_err(|e| format!("读取钱包文件失败: {}", e))?;
+    let mut data: serde_json::Value =
+        serde_json::from_str(&content).map_err(|e| format!("钱包文件格式错误: {}", e))?;
+    update(&mut data);
+    let json = serde_json::to_string_pretty(&data).map_err(|e| format!("序列化失败: {}", e))?;
+    let tmp = format!("{}.tmp", wallet_file);
+    std::fs::write(&tmp, json).map_err(|e| format!("保存钱包文件失败: {}", e))?;
+    std::fs::rename(&tmp, wallet_file).map_err(|e| format!("保存钱包文件失败: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn codes_match_their_own_hash_only() {
+        let codes = new_backup_codes(2);
+        assert!(codes
+            .iter()
+            .all(|code| code.len() == 9 && normalize_backup_code(code).is_some()));
+        let hashes = codes
+            .iter()
+            .map(|code| hash_backup_code(code).unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(find_backup_code(&hashes, &codes[1]), Some(1));
+        let spaced = codes[0].replace('-', " ");
+        assert_eq!(find_backup_code(&hashes, &spaced), Some(0));
+        // TOTP-shaped codes never reach the hashes
+        assert_eq!(normalize_backup_code("123456"), None);
+        assert_eq!(find_backup_code(&hashes, "123456"), None);
+    }
+}
@@ -11,6 +11,10 @@
 //!
 //! let keypair = bot_helper::ensure_wallet_ready("wallet.json").unwrap();
 //! ```
+//!
+//! With the `sol-trade-sdk` feature, the unlocked keypair can be handed to
+//! [`solana_utils::position_watcher::PositionWatcher`](crate::solana_utils::position_watcher::PositionWatcher)
+//! to sell a Pump position on take-profit, stop-loss or trailing-stop.
 
 use crate::{interactive, KeyManager};
 use serde_json::Value;
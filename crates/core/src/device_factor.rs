@@ -0,0 +1,467 @@
+//! Providers for the device factor of triple-factor wallets.
+//!
+//! The envelope key mixes a device secret with the master password and security answer
+//! (see [`crate::generate_triple_factor_key`]). A [`DeviceFactor`] produces that secret
+//! when a wallet is created and recovers it from what the wallet file keeps:
+//!
+//! - [`FingerprintFactor`]: majority match over hardware components. Anyone who can read
+//!   the same system files can reproduce it.
+//! - [`DeviceKeyFileFactor`]: a random key in a 0600 file under the user data directory.
+//! - [`TpmFactor`]: a random secret sealed to the TPM 2.0 owner hierarchy, so it never
+//!   leaves the chip in a form another machine can use.
+
+use crate::hardware_fingerprint::{HardwareBinding, HardwareFingerprint};
+use base64::{engine::general_purpose, Engine};
+use ring::rand::{SecureRandom, SystemRandom};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use zeroize::Zeroizing;
+
+/// Path of the device key file, overriding the user data directory
+pub const DEVICE_KEY_FILE_ENV: &str = "FNZERO_SAFE_DEVICE_KEY_FILE";
+/// TCTI passed to tpm2-tools, e.g. `swtpm:host=127.0.0.1,port=2321` for the simulator
+pub const TPM_TCTI_ENV: &str = "FNZERO_SAFE_TPM_TCTI";
+
+/// The secret a provider hands to the triple-factor key derivation
+pub struct DeviceSecret {
+    pub secret: Zeroizing<String>,
+    /// Something the user should act on, e.g. hardware drift before it locks them out
+    pub notice: Option<String>,
+}
+
+impl DeviceSecret {
+    fn new(secret: String) -> Self {
+        Self {
+            secret: Zeroizing::new(secret),
+            notice: None,
+        }
+    }
+}
+
+/// Source of the device factor
+pub trait DeviceFactor {
+    /// Name stored in the wallet file as `device_factor.kind`
+    fn kind(&self) -> &'static str;
+    /// Create a secret for a new wallet; the returned JSON is kept in the wallet file
+    fn enroll(&self) -> Result<(DeviceSecret, Value), String>;
+    /// Recover the secret from what [`DeviceFactor::enroll`] returned
+    fn unlock(&self, stored: &Value) -> Result<DeviceSecret, String>;
+}
+
+/// Provider for a `device_factor.kind` (or CLI `--device-factor` value)
+pub fn device_factor_by_kind(kind: &str) -> Result<Box<dyn DeviceFactor>, String> {
+    match kind {
+        FingerprintFactor::KIND => Ok(Box::new(FingerprintFactor)),
+        DeviceKeyFileFactor::KIND => Ok(Box::new(DeviceKeyFileFactor::from_env()?)),
+        TpmFactor::KIND => Ok(Box::new(TpmFactor::from_env())),
+        other => Err(format!(
+            "未知的设备因子类型: {}（可选 fingerprint、device-key、tpm）",
+            other
+        )),
+    }
+}
+
+/// Enroll with `factor` and return the secret and the wallet file's `device_factor` value
+pub fn enroll_device_factor(factor: &dyn DeviceFactor) -> Result<(DeviceSecret, Value), String> {
+    let (secret, mut stored) = factor.enroll()?;
+    stored["kind"] = Value::from(factor.kind());
+    Ok((secret, stored))
+}
+
+/// Recover the device secret of a CLI wallet file's JSON
+///
+/// Files without `device_factor` predate providers and use the hardware fingerprint:
+/// with a top-level `hardware_binding`, or the legacy combined fingerprint.
+pub fn unlock_wallet_device_factor(wallet: &Value) -> Result<DeviceSecret, String> {
+    let stored = &wallet["device_factor"];
+    if stored.is_null() {
+        return FingerprintFactor.unlock(&json!({ "binding": wallet["hardware_binding"] }));
+    }
+    let kind = stored["kind"].as_str().ok_or("钱包文件缺少设备因子类型")?;
+    device_factor_by_kind(kind)?.unlock(stored)
+}
+
+/// Name of the provider a CLI wallet file uses
+pub fn wallet_device_factor_kind(wallet: &Value) -> &str {
+    wallet["device_factor"]["kind"]
+        .as_str()
+        .unwrap_or(FingerprintFactor::KIND)
+}
+
+/// Write a re-bound envelope and its `device_factor` back to a CLI wallet file
+pub fn save_wallet_file_device_factor(
+    wallet_file: &str,
+    encrypted_data: &str,
+    device_factor: Value,
+) -> Result<(), String> {
+    crate::backup_codes::update_wallet_file(wallet_file, |data| {
+        data["encrypted_private_key"] = Value::from(encrypted_data);
+        data["device_factor"] = device_factor;
+        if let Some(fields) = data.as_object_mut() {
+            fields.remove("hardware_binding");
+        }
+    })
+}
+
+fn random_hex_secret() -> Result<String, String> {
+    let mut bytes = Zeroizing::new([0u8; 32]);
+    SystemRandom::new()
+        .fill(bytes.as_mut())
+        .map_err(|_| "生成随机数失败".to_string())?;
+    Ok(hex::encode(bytes.as_ref()))
+}
+
+fn key_id(secret: &str) -> String {
+    let digest = ring::digest::digest(&ring::digest::SHA256, secret.as_bytes());
+    hex::encode(&digest.as_ref()[..8])
+}
+
+/// Hardware components with a majority match ([`HardwareBinding`])
+pub struct FingerprintFactor;
+
+impl FingerprintFactor {
+    pub const KIND: &'static str = "fingerprint";
+}
+
+impl DeviceFactor for FingerprintFactor {
+    fn kind(&self) -> &'static str {
+        Self::KIND
+    }
+
+    fn enroll(&self) -> Result<(DeviceSecret, Value), String> {
+        let fingerprint = HardwareFingerprint::collect()?;
+        let (binding, device_key) = HardwareBinding::create(&fingerprint.components)?;
+        let stored = json!({
+            "binding": serde_json::to_value(&binding).map_err(|e| format!("序列化失败: {}", e))?,
+        });
+        Ok((DeviceSecret::new(device_key), stored))
+    }
+
+    fn unlock(&self, stored: &Value) -> Result<DeviceSecret, String> {
+        let fingerprint = HardwareFingerprint::collect()?;
+        if stored["binding"].is_null() {
+            return Ok(DeviceSecret {
+                secret: Zeroizing::new(fingerprint.fingerprint),
+                notice: Some(
+                    "该钱包使用旧版硬件指纹，任何硬件变化都会导致无法解锁，建议重新绑定"
+                        .to_string(),
+                ),
+            });
+        }
+        let binding: HardwareBinding = serde_json::from_value(stored["binding"].clone())
+            .map_err(|e| format!("设备绑定数据无效: {}", e))?;
+        let (device_key, binding_match) = binding.unlock(&fingerprint.components)?;
+        Ok(DeviceSecret {
+            secret: Zeroizing::new(device_key),
+            notice: binding_match.drifted().then(|| {
+                format!(
+                    "仅 {}/{} 项硬件信息一致，建议重新绑定",
+                    binding_match.matched, binding_match.total
+                )
+            }),
+        })
+    }
+}
+
+/// Random key in a file only the user can read
+pub struct DeviceKeyFileFactor {
+    path: PathBuf,
+}
+
+impl DeviceKeyFileFactor {
+    pub const KIND: &'static str = "device-key";
+
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// `$FNZERO_SAFE_DEVICE_KEY_FILE`, else `<user data dir>/fnzero-safe/device.key`
+    pub fn from_env() -> Result<Self, String> {
+        if let Some(path) = std::env::var_os(DEVICE_KEY_FILE_ENV) {
+            return Ok(Self::new(path));
+        }
+        let data_dir = dirs::data_dir().ok_or("无法确定用户数据目录")?;
+        Ok(Self::new(data_dir.join("fnzero-safe").join("device.key")))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn read_key(&self) -> Result<Option<String>, String> {
+        match std::fs::read_to_string(&self.path) {
+            Ok(key) => Ok(Some(key.trim().to_string())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(format!("读取设备密钥失败: {}", e)),
+        }
+    }
+
+    fn create_key(&self) -> Result<String, String> {
+        let key = random_hex_secret()?;
+        if let Some(parent) = self.path.parent() {
+            let mut builder = std::fs::DirBuilder::new();
+            builder.recursive(true);
+            #[cfg(unix)]
+            std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+            builder
+                .create(parent)
+                .map_err(|e| format!("创建数据目录失败: {}", e))?;
+        }
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options
+            .open(&self.path)
+            .map_err(|e| format!("创建设备密钥失败: {}", e))?;
+        std::io::Write::write_all(&mut file, key.as_bytes())
+            .map_err(|e| format!("写入设备密钥失败: {}", e))?;
+        Ok(key)
+    }
+}
+
+impl DeviceFactor for DeviceKeyFileFactor {
+    fn kind(&self) -> &'static str {
+        Self::KIND
+    }
+
+    /// Reuses the key file if it exists, so every wallet on the device shares one key
+    fn enroll(&self) -> Result<(DeviceSecret, Value), String> {
+        let key = match self.read_key()? {
+            Some(key) => key,
+            None => self.create_key()?,
+        };
+        let stored = json!({ "key_id": key_id(&key) });
+        Ok((DeviceSecret::new(key), stored))
+    }
+
+    fn unlock(&self, stored: &Value) -> Result<DeviceSecret, String> {
+        let key = self
+            .read_key()?
+            .ok_or_else(|| format!("设备密钥文件不存在: {}", self.path.display()))?;
+        if stored["key_id"].as_str() != Some(key_id(&key).as_str()) {
+            return Err(format!(
+                "设备密钥 {} 与创建钱包时使用的不一致",
+                self.path.display()
+            ));
+        }
+        Ok(DeviceSecret::new(key))
+    }
+}
+
+/// Secret sealed to the TPM 2.0 owner hierarchy through tpm2-tools
+///
+/// tpm2-tools is used instead of linking libtss2, so builds need no TPM libraries and
+/// only machines that use this provider need the tools installed.
+pub struct TpmFactor {
+    tcti: Option<String>,
+}
+
+impl TpmFactor {
+    pub const KIND: &'static str = "tpm";
+
+    /// `tcti` selects the TPM, e.g. `device:/dev/tpmrm0` or the swtpm simulator
+    pub fn new(tcti: Option<String>) -> Self {
+        Self { tcti }
+    }
+
+    /// TCTI from `$FNZERO_SAFE_TPM_TCTI`, else tpm2-tools' own default
+    pub fn from_env() -> Self {
+        Self::new(std::env::var(TPM_TCTI_ENV).ok())
+    }
+
+    fn run(&self, args: &[&str], stdin: Option<&[u8]>) -> Result<Vec<u8>, String> {
+        let (tool, args) = args.split_first().ok_or("空的 TPM 命令")?;
+        let mut command = Command::new(tool);
+        if let Some(tcti) = &self.tcti {
+            command.args(["-T", tcti]);
+        }
+        let mut child = command
+            .args(args)
+            .stdin(if stdin.is_some() {
+                Stdio::piped()
+            } else {
+                Stdio::null()
+            })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("运行 {} 失败（需要安装 tpm2-tools）: {}", tool, e))?;
+        if let (Some(input), Some(mut pipe)) = (stdin, child.stdin.take()) {
+            std::io::Write::write_all(&mut pipe, input)
+                .map_err(|e| format!("写入 {} 失败: {}", tool, e))?;
+        }
+        let output = child
+            .wait_with_output()
+            .map_err(|e| format!("运行 {} 失败: {}", tool, e))?;
+        if !output.status.success() {
+            return Err(format!(
+                "{} 失败: {}",
+                tool,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        Ok(output.stdout)
+    }
+
+    /// Recreate the owner-hierarchy primary key; the same template always yields the same key
+    fn create_primary(&self, dir: &Path) -> Result<String, String> {
+        let primary = dir.join("primary.ctx").to_string_lossy().into_owned();
+        self.run(
+            &["tpm2_createprimary", "-Q", "-C", "o", "-c", &primary],
+            None,
+        )?;
+        Ok(primary)
+    }
+}
+
+/// Scratch directory for TPM object files, removed on drop
+struct TpmWorkDir(PathBuf);
+
+impl TpmWorkDir {
+    fn new() -> Result<Self, String> {
+        let nonce = key_id(&random_hex_secret()?);
+        let path = std::env::temp_dir().join(format!("fnzero-tpm-{}", nonce));
+        let mut builder = std::fs::DirBuilder::new();
+        #[cfg(unix)]
+        std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+        builder
+            .create(&path)
+            .map_err(|e| format!("创建临时目录失败: {}", e))?;
+        Ok(Self(path))
+    }
+
+    fn file(&self, name: &str) -> String {
+        self.0.join(name).to_string_lossy().into_owned()
+    }
+}
+
+impl Drop for TpmWorkDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+impl DeviceFactor for TpmFactor {
+    fn kind(&self) -> &'static str {
+        Self::KIND
+    }
+
+    fn enroll(&self) -> Result<(DeviceSecret, Value), String> {
+        let dir = TpmWorkDir::new()?;
+        let primary = self.create_primary(&dir.0)?;
+        let secret = random_hex_secret()?;
+        let (public, private) = (dir.file("seal.pub"), dir.file("seal.priv"));
+        self.run(
+            &[
+                "tpm2_create",
+                "-Q",
+                "-C",
+                &primary,
+                "-i",
+                "-",
+                "-u",
+                &public,
+                "-r",
+                &private,
+            ],
+            Some(secret.as_bytes()),
+        )?;
+        let read = |path: &str| {
+            std::fs::read(path)
+                .map(|bytes| general_purpose::STANDARD.encode(bytes))
+                .map_err(|e| format!("读取 TPM 封装数据失败: {}", e))
+        };
+        let stored = json!({
+            "sealed_public": read(&public)?,
+            "sealed_private": read(&private)?,
+        });
+        Ok((DeviceSecret::new(secret), stored))
+    }
+
+    fn unlock(&self, stored: &Value) -> Result<DeviceSecret, String> {
+        let dir = TpmWorkDir::new()?;
+        let (public, private, sealed) = (
+            dir.file("seal.pub"),
+            dir.file("seal.priv"),
+            dir.file("seal.ctx"),
+        );
+        for (path, field) in [(&public, "sealed_public"), (&private, "sealed_private")] {
+            let bytes = stored[field]
+                .as_str()
+                .and_then(|encoded| general_purpose::STANDARD.decode(encoded).ok())
+                .ok_or("钱包文件中的 TPM 封装数据无效")?;
+            std::fs::write(path, bytes).map_err(|e| format!("写入临时文件失败: {}", e))?;
+        }
+        let primary = self.create_primary(&dir.0)?;
+        self.run(
+            &[
+                "tpm2_load",
+                "-Q",
+                "-C",
+                &primary,
+                "-u",
+                &public,
+                "-r",
+                &private,
+                "-c",
+                &sealed,
+            ],
+            None,
+        )
+        .map_err(|e| format!("TPM 无法加载封装数据（是否为创建钱包的设备？）: {}", e))?;
+        let secret = self.run(&["tpm2_unseal", "-c", &sealed], None)?;
+        let secret = String::from_utf8(secret).map_err(|_| "TPM 返回的数据无效".to_string())?;
+        Ok(DeviceSecret::new(secret))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn device_key_file_is_private_and_must_match() {
+        let dir = std::env::temp_dir().join(format!("device-factor-{}", std::process::id()));
+        let factor = DeviceKeyFileFactor::new(dir.join("nested").join("device.key"));
+
+        let (secret, stored) = factor.enroll().unwrap();
+        assert_eq!(factor.unlock(&stored).unwrap().secret, secret.secret);
+        // A second wallet on the same device reuses the key
+        assert_eq!(factor.enroll().unwrap().0.secret, secret.secret);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(factor.path())
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        let other = DeviceKeyFileFactor::new(dir.join("other.key"));
+        other.enroll().unwrap();
+        assert!(other.unlock(&stored).is_err());
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    /// Runs with `--features tpm-tests` and needs tpm2-tools and a TPM, e.g. `swtpm socket
+    /// --tpm2 --server type=tcp,port=2321 --ctrl type=tcp,port=2322 --tpmstate dir=/tmp/swtpm
+    /// --flags startup-clear` and `FNZERO_SAFE_TPM_TCTI=swtpm:host=127.0.0.1,port=2321`
+    #[test]
+    #[cfg_attr(
+        not(feature = "tpm-tests"),
+        ignore = "needs a TPM; enable the tpm-tests feature"
+    )]
+    fn tpm_sealed_secret_round_trips() {
+        let factor = TpmFactor::from_env();
+        let (secret, stored) = factor.enroll().unwrap();
+        assert_eq!(factor.unlock(&stored).unwrap().secret, secret.secret);
+
+        let mut tampered = stored.clone();
+        tampered["sealed_private"] = Value::from(general_purpose::STANDARD.encode([0u8; 64]));
+        assert!(factor.unlock(&tampered).is_err());
+    }
+}
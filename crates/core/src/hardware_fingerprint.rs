@@ -1,16 +1,93 @@
+//! 硬件指纹与可容忍漂移的设备绑定。
+//!
+//! `fingerprint` 是旧版三因子钱包使用的组合哈希，任何一项硬件变化都会改变它。新钱包改用
+//! [`HardwareBinding`]：每项硬件信息先经 Argon2id 拉伸，随机设备密钥按“多数项一致”的每种组合
+//! 分别用 AES-GCM 加密保存，因此更换网卡或迁移虚拟机后仍可解锁。绑定记录不含单项硬件信息的
+//! 承诺，离线猜测时必须同时猜中一整组硬件信息。
+
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+    Aes256Gcm,
+};
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::{engine::general_purpose, Engine};
 use ring::digest;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::{Deserialize, Serialize};
 #[cfg(any(target_os = "macos", target_os = "linux"))]
 use std::process::Command;
+use zeroize::Zeroizing;
+
+/// 当前的设备绑定格式版本
+pub const HARDWARE_BINDING_VERSION: u32 = 2;
+
+/// 拉伸单项硬件信息的 Argon2id 参数（19 MiB、2 轮）
+const COMPONENT_ARGON2_MEMORY_KIB: u32 = 19 * 1024;
+const COMPONENT_ARGON2_ITERATIONS: u32 = 2;
+
+type ComponentSource = fn() -> Result<String, String>;
 
 /// 硬件指纹结构
 #[derive(Debug, Clone)]
 pub struct HardwareFingerprint {
+    /// 旧版组合指纹（全部硬件信息的 SHA256）
     pub fingerprint: String,
+    /// 用于设备绑定的各项硬件信息，优先读取稳定的系统文件
+    pub components: Vec<HardwareComponent>,
+}
+
+/// 单项硬件信息
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HardwareComponent {
+    pub kind: &'static str,
+    pub value: String,
 }
 
 impl HardwareFingerprint {
     /// 收集硬件指纹
     pub fn collect() -> Result<Self, String> {
+        let components = Self::collect_components();
+        let fingerprint = match Self::legacy_fingerprint() {
+            Some(fingerprint) => fingerprint,
+            None if !components.is_empty() => {
+                let combined = components
+                    .iter()
+                    .map(|component| format!("{}:{}", component.kind, component.value))
+                    .collect::<Vec<_>>()
+                    .join("|");
+                hex::encode(digest::digest(&digest::SHA256, combined.as_bytes()).as_ref())
+            }
+            None => return Err("无法收集硬件指纹信息".to_string()),
+        };
+        Ok(Self {
+            fingerprint,
+            components,
+        })
+    }
+
+    /// 设备绑定使用的硬件信息
+    fn collect_components() -> Vec<HardwareComponent> {
+        let sources: [(&'static str, ComponentSource); 5] = [
+            ("MACHINE_ID", Self::get_machine_id),
+            ("CPU", Self::get_cpu_info),
+            ("SERIAL", Self::get_system_serial),
+            ("MAC", Self::get_stable_mac_address),
+            ("DISK", Self::get_root_disk_uuid),
+        ];
+        sources
+            .into_iter()
+            .filter_map(|(kind, source)| {
+                source()
+                    .ok()
+                    .map(|value| value.trim().to_string())
+                    .filter(|value| !value.is_empty())
+                    .map(|value| HardwareComponent { kind, value })
+            })
+            .collect()
+    }
+
+    /// 旧版组合指纹；计算方式必须保持不变，否则旧钱包无法解锁
+    fn legacy_fingerprint() -> Option<String> {
         let mut components = Vec::new();
 
         // 1. CPU 信息
@@ -34,15 +111,110 @@ impl HardwareFingerprint {
         }
 
         if components.is_empty() {
-            return Err("无法收集硬件指纹信息".to_string());
+            return None;
         }
 
         // 组合所有硬件信息并生成指纹
         let combined = components.join("|");
         let hash = digest::digest(&digest::SHA256, combined.as_bytes());
-        let fingerprint = hex::encode(hash.as_ref());
+        Some(hex::encode(hash.as_ref()))
+    }
+
+    /// 获取机器 ID（Linux 读取 /etc/machine-id，macOS 读取 IOPlatformUUID）
+    fn get_machine_id() -> Result<String, String> {
+        #[cfg(target_os = "macos")]
+        {
+            let output = Command::new("ioreg")
+                .args(["-rd1", "-c", "IOPlatformExpertDevice"])
+                .output()
+                .map_err(|e| format!("获取机器ID失败: {}", e))?;
+
+            if output.status.success() {
+                let content = String::from_utf8_lossy(&output.stdout);
+                for line in content.lines() {
+                    if line.contains("IOPlatformUUID") {
+                        if let Some(uuid) = line.split('=').nth(1) {
+                            return Ok(uuid.trim().trim_matches('"').to_string());
+                        }
+                    }
+                }
+            }
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            for path in ["/etc/machine-id", "/var/lib/dbus/machine-id"] {
+                if let Ok(content) = std::fs::read_to_string(path) {
+                    let id = content.trim().to_string();
+                    if !id.is_empty() && id != "uninitialized" {
+                        return Ok(id);
+                    }
+                }
+            }
+        }
+
+        Err("无法获取机器ID".to_string())
+    }
+
+    /// 获取物理网卡 MAC 地址（Linux 跳过虚拟网卡，不依赖网卡名称）
+    fn get_stable_mac_address() -> Result<String, String> {
+        #[cfg(target_os = "linux")]
+        {
+            let mut interfaces = std::fs::read_dir("/sys/class/net")
+                .map_err(|e| format!("获取MAC地址失败: {}", e))?
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.join("device").exists())
+                .collect::<Vec<_>>();
+            interfaces.sort();
+            for interface in interfaces {
+                if let Ok(content) = std::fs::read_to_string(interface.join("address")) {
+                    let mac = content.trim().to_string();
+                    if !mac.is_empty() && mac != "00:00:00:00:00:00" {
+                        return Ok(mac);
+                    }
+                }
+            }
+        }
+
+        Self::get_mac_address()
+    }
+
+    /// 获取根文件系统 UUID（Linux 读取 /proc 与 /dev/disk/by-uuid）
+    fn get_root_disk_uuid() -> Result<String, String> {
+        #[cfg(target_os = "linux")]
+        {
+            let mountinfo = std::fs::read_to_string("/proc/self/mountinfo")
+                .map_err(|e| format!("获取硬盘UUID失败: {}", e))?;
+            // 字段: id parent major:minor root mount_point ...
+            let device = mountinfo
+                .lines()
+                .map(|line| line.split_whitespace().collect::<Vec<_>>())
+                .find(|fields| fields.get(4) == Some(&"/"))
+                .and_then(|fields| fields.get(2).map(|device| device.to_string()))
+                .ok_or("未找到根文件系统")?;
+            let uevent = std::fs::read_to_string(format!("/sys/dev/block/{}/uevent", device))
+                .map_err(|e| format!("获取硬盘UUID失败: {}", e))?;
+            let devname = uevent
+                .lines()
+                .find_map(|line| line.strip_prefix("DEVNAME="))
+                .ok_or("未找到根文件系统设备")?;
+            let entries = std::fs::read_dir("/dev/disk/by-uuid")
+                .map_err(|e| format!("获取硬盘UUID失败: {}", e))?;
+            for entry in entries.flatten() {
+                let target = std::fs::read_link(entry.path()).ok();
+                if target
+                    .as_deref()
+                    .and_then(|target| target.file_name())
+                    .is_some_and(|name| name == devname)
+                {
+                    return Ok(entry.file_name().to_string_lossy().into_owned());
+                }
+            }
+            Err("无法获取硬盘UUID".to_string())
+        }
 
-        Ok(Self { fingerprint })
+        #[cfg(not(target_os = "linux"))]
+        Self::get_disk_serial()
     }
 
     /// 获取 CPU 信息
@@ -192,6 +364,247 @@ impl HardwareFingerprint {
     }
 }
 
+/// 当前设备与绑定记录的一致程度
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BindingMatch {
+    pub matched: usize,
+    pub total: usize,
+}
+
+impl BindingMatch {
+    /// 部分硬件已变化，建议重新绑定以免继续漂移后无法解锁
+    pub fn drifted(&self) -> bool {
+        self.matched < self.total
+    }
+}
+
+/// 保存在钱包文件中的设备绑定
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HardwareBinding {
+    pub version: u32,
+    /// 拉伸与包装密钥使用的随机盐（hex）
+    pub salt: String,
+    /// 至少需要一致的硬件信息项数
+    pub threshold: usize,
+    pub components: Vec<ComponentCommitment>,
+    /// 设备密钥按每个 `threshold` 项组合分别加密
+    pub wrapped_keys: Vec<WrappedDeviceKey>,
+}
+
+/// 绑定的一项硬件信息
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ComponentCommitment {
+    pub kind: String,
+    /// 拉伸后硬件信息的 SHA-256 承诺（hex），猜测每项都要付出一次 Argon2id 的代价
+    pub commitment: String,
+}
+
+/// 用一组硬件信息加密的设备密钥
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WrappedDeviceKey {
+    /// `components` 中的下标
+    pub components: Vec<usize>,
+    pub ciphertext: String,
+}
+
+/// `total` 项硬件信息中至少需要一致的项数（多数）
+pub fn binding_threshold(total: usize) -> usize {
+    (total / 2 + 1).min(total)
+}
+
+impl HardwareBinding {
+    /// 绑定到 `components`，返回绑定记录和作为硬件因子的随机设备密钥
+    pub fn create(components: &[HardwareComponent]) -> Result<(Self, String), String> {
+        if components.is_empty() {
+            return Err("无法收集硬件指纹信息".to_string());
+        }
+        let rng = SystemRandom::new();
+        let mut salt = [0u8; 16];
+        let mut device_key = [0u8; 32];
+        rng.fill(&mut salt)
+            .and_then(|()| rng.fill(&mut device_key))
+            .map_err(|_| "生成随机数失败".to_string())?;
+        let salt = hex::encode(salt);
+        let device_key = hex::encode(device_key);
+
+        let threshold = binding_threshold(components.len());
+        let mut commitments = Vec::with_capacity(components.len());
+        let mut stretched = Vec::with_capacity(components.len());
+        for component in components {
+            let value = stretch_component(&salt, component)?;
+            commitments.push(ComponentCommitment {
+                kind: component.kind.to_string(),
+                commitment: component_commitment(&salt, component.kind, &value),
+            });
+            stretched.push(Some(value));
+        }
+        let mut wrapped_keys = Vec::new();
+        for subset in combinations(components.len(), threshold) {
+            let key = wrapping_key(&salt, &subset, &stretched).ok_or("生成包装密钥失败")?;
+            wrapped_keys.push(WrappedDeviceKey {
+                ciphertext: seal_device_key(&device_key, &key)?,
+                components: subset,
+            });
+        }
+
+        let binding = Self {
+            version: HARDWARE_BINDING_VERSION,
+            components: commitments,
+            salt,
+            threshold,
+            wrapped_keys,
+        };
+        Ok((binding, device_key))
+    }
+
+    /// 用当前硬件信息解出设备密钥
+    pub fn unlock(&self, current: &[HardwareComponent]) -> Result<(String, BindingMatch), String> {
+        if self.version != HARDWARE_BINDING_VERSION {
+            return Err(format!("不支持的设备绑定版本: {}", self.version));
+        }
+        // 与承诺一致的项保留拉伸值，其余为 `None`
+        let stretched = self
+            .components
+            .iter()
+            .map(|bound| {
+                let Some(component) = current
+                    .iter()
+                    .find(|component| component.kind == bound.kind)
+                else {
+                    return Ok(None);
+                };
+                let stretched = stretch_component(&self.salt, component)?;
+                let commitment = component_commitment(&self.salt, &bound.kind, &stretched);
+                Ok((commitment == bound.commitment).then_some(stretched))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        let binding_match = BindingMatch {
+            matched: stretched.iter().flatten().count(),
+            total: self.components.len(),
+        };
+        if binding_match.matched < self.threshold {
+            return Err(format!(
+                "硬件指纹不匹配：{}/{} 项硬件信息一致，至少需要 {} 项",
+                binding_match.matched, binding_match.total, self.threshold
+            ));
+        }
+
+        let wrapped = self
+            .wrapped_keys
+            .iter()
+            .find(|wrapped| {
+                wrapped
+                    .components
+                    .iter()
+                    .all(|&index| stretched.get(index).is_some_and(Option::is_some))
+            })
+            .ok_or("设备绑定数据不完整")?;
+        let key = wrapping_key(&self.salt, &wrapped.components, &stretched)
+            .ok_or("设备绑定数据不完整")?;
+        let device_key = open_device_key(&wrapped.ciphertext, &key).ok_or("设备绑定数据已损坏")?;
+        Ok((device_key.to_string(), binding_match))
+    }
+}
+
+/// 单项硬件信息经 Argon2id 拉伸后的值；盐中包含类型，不同项不能互相复用
+fn stretch_component(
+    salt: &str,
+    component: &HardwareComponent,
+) -> Result<Zeroizing<[u8; 32]>, String> {
+    let params = Params::new(
+        COMPONENT_ARGON2_MEMORY_KIB,
+        COMPONENT_ARGON2_ITERATIONS,
+        1,
+        Some(32),
+    )
+    .map_err(|_| "Argon2id 参数无效".to_string())?;
+    let salt = format!("fnzero-hw|{}|{}", salt, component.kind);
+    let mut stretched = Zeroizing::new([0u8; 32]);
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(
+            component.value.as_bytes(),
+            salt.as_bytes(),
+            stretched.as_mut(),
+        )
+        .map_err(|_| "拉伸硬件信息失败".to_string())?;
+    Ok(stretched)
+}
+
+/// 拉伸值的承诺；类型与盐参与计算，不能跨项或跨绑定比对
+fn component_commitment(salt: &str, kind: &str, stretched: &[u8; 32]) -> String {
+    let mut context = digest::Context::new(&digest::SHA256);
+    context.update(format!("fnzero-hw-commit-v2|{}|{}|", salt, kind).as_bytes());
+    context.update(stretched);
+    hex::encode(context.finish().as_ref())
+}
+
+/// 组合 `subset` 的包装密钥；其中有当前缺少的项时为 `None`
+fn wrapping_key(
+    salt: &str,
+    subset: &[usize],
+    stretched: &[Option<Zeroizing<[u8; 32]>>],
+) -> Option<Zeroizing<[u8; 32]>> {
+    let mut context = digest::Context::new(&digest::SHA256);
+    context.update(format!("fnzero-hw-wrap-v2|{}", salt).as_bytes());
+    for &index in subset {
+        context.update(format!("|{}:", index).as_bytes());
+        context.update(stretched.get(index)?.as_ref()?.as_ref());
+    }
+    let mut key = Zeroizing::new([0u8; 32]);
+    key.copy_from_slice(context.finish().as_ref());
+    Some(key)
+}
+
+fn seal_device_key(device_key: &str, key: &[u8; 32]) -> Result<String, String> {
+    let cipher =
+        Aes256Gcm::new_from_slice(key).map_err(|_| "AES-256-GCM 初始化失败".to_string())?;
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, device_key.as_bytes())
+        .map_err(|_| "加密设备密钥失败".to_string())?;
+    let mut sealed = nonce.to_vec();
+    sealed.extend_from_slice(&ciphertext);
+    Ok(general_purpose::STANDARD.encode(sealed))
+}
+
+fn open_device_key(sealed: &str, key: &[u8; 32]) -> Option<Zeroizing<String>> {
+    let sealed = general_purpose::STANDARD.decode(sealed).ok()?;
+    if sealed.len() < 12 {
+        return None;
+    }
+    let (nonce, ciphertext) = sealed.split_at(12);
+    let cipher = Aes256Gcm::new_from_slice(key).ok()?;
+    let plaintext = cipher
+        .decrypt(aes_gcm::Nonce::from_slice(nonce), ciphertext)
+        .ok()?;
+    String::from_utf8(plaintext).ok().map(Zeroizing::new)
+}
+
+/// 从 `0..n` 中选 `k` 个下标的全部组合（按字典序）
+pub(crate) fn combinations(n: usize, k: usize) -> Vec<Vec<usize>> {
+    let mut result = Vec::new();
+    let mut current = Vec::with_capacity(k);
+    fn extend(
+        start: usize,
+        n: usize,
+        k: usize,
+        current: &mut Vec<usize>,
+        out: &mut Vec<Vec<usize>>,
+    ) {
+        if current.len() == k {
+            out.push(current.clone());
+            return;
+        }
+        for index in start..n {
+            current.push(index);
+            extend(index + 1, n, k, current, out);
+            current.pop();
+        }
+    }
+    extend(0, n, k, &mut current, &mut result);
+    result
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -210,6 +623,74 @@ mod tests {
         }
     }
 
+    fn component(kind: &'static str, value: &str) -> HardwareComponent {
+        HardwareComponent {
+            kind,
+            value: value.to_string(),
+        }
+    }
+
+    #[test]
+    fn binding_tolerates_minority_drift() {
+        let original = vec![
+            component("MACHINE_ID", "4f1c0e"),
+            component("CPU", "AMD EPYC 7763"),
+            component("SERIAL", "VM-1234"),
+            component("MAC", "02:42:ac:11:00:02"),
+            component("DISK", "9b2d-11aa"),
+        ];
+        let (binding, device_key) = HardwareBinding::create(&original).unwrap();
+        assert_eq!(binding.threshold, 3);
+        assert_eq!(binding.wrapped_keys.len(), 10);
+        assert_eq!(
+            binding.unlock(&original).unwrap(),
+            (
+                device_key.clone(),
+                BindingMatch {
+                    matched: 5,
+                    total: 5
+                }
+            )
+        );
+
+        // A host migration changes the NIC and serial; the rest still carries the key
+        let mut migrated = original.clone();
+        migrated[2].value = "VM-9999".to_string();
+        migrated[3].value = "02:42:ac:11:00:09".to_string();
+        let (key, binding_match) = binding.unlock(&migrated).unwrap();
+        assert_eq!(key, device_key);
+        assert!(binding_match.drifted());
+
+        migrated[1].value = "Intel Xeon".to_string();
+        assert!(binding.unlock(&migrated).is_err());
+    }
+
+    #[test]
+    fn commitments_cover_only_the_stretched_components() {
+        let components = vec![
+            component("MACHINE_ID", "4f1c0e"),
+            component("CPU", "AMD EPYC 7763"),
+            component("DISK", "9b2d-11aa"),
+        ];
+        let (binding, _) = HardwareBinding::create(&components).unwrap();
+        let stored = serde_json::to_string(&binding).unwrap();
+        assert!(components
+            .iter()
+            .all(|component| !stored.contains(&component.value)));
+        for (bound, component) in binding.components.iter().zip(&components) {
+            let stretched = stretch_component(&binding.salt, component).unwrap();
+            assert_eq!(
+                bound.commitment,
+                component_commitment(&binding.salt, component.kind, &stretched)
+            );
+            // A bare SHA-256 of the value does not match
+            let plain = digest::digest(&digest::SHA256, component.value.as_bytes());
+            assert_ne!(bound.commitment, hex::encode(plain.as_ref()));
+        }
+        let missing = serde_json::json!({ "kind": "CPU" });
+        assert!(serde_json::from_value::<ComponentCommitment>(missing).is_err());
+    }
+
     #[test]
     fn test_fingerprint_consistency() {
         let fp1 = HardwareFingerprint::collect();
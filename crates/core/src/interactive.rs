@@ -585,6 +585,26 @@ pub fn show_main_menu() -> Result<(), String> {
                         "PumpSwap Cashback (View & Claim)"
                     }
                 );
+                #[cfg(feature = "sol-trade-sdk")]
+                println!(
+                    "  {}  {}",
+                    "19.".bright_magenta().bold(),
+                    if lang == Language::Chinese {
+                        "Pump.fun 买入代币"
+                    } else {
+                        "Pump.fun Buy Tokens"
+                    }
+                );
+                #[cfg(feature = "sol-trade-sdk")]
+                println!(
+                    "  {}  {}",
+                    "20.".bright_magenta().bold(),
+                    if lang == Language::Chinese {
+                        "PumpSwap 买入代币"
+                    } else {
+                        "PumpSwap Buy Tokens"
+                    }
+                );
             }
             #[cfg(not(feature = "2fa"))]
             {
@@ -701,6 +721,26 @@ pub fn show_main_menu() -> Result<(), String> {
                         "PumpSwap Cashback (View & Claim)"
                     }
                 );
+                #[cfg(feature = "sol-trade-sdk")]
+                println!(
+                    "  {}  {}",
+                    "16.".bright_magenta().bold(),
+                    if lang == Language::Chinese {
+                        "Pump.fun 买入代币"
+                    } else {
+                        "Pump.fun Buy Tokens"
+                    }
+                );
+                #[cfg(feature = "sol-trade-sdk")]
+                println!(
+                    "  {}  {}",
+                    "17.".bright_magenta().bold(),
+                    if lang == Language::Chinese {
+                        "PumpSwap 买入代币"
+                    } else {
+                        "PumpSwap Buy Tokens"
+                    }
+                );
             }
         }
 
@@ -773,13 +813,15 @@ pub fn show_main_menu() -> Result<(), String> {
 
             // Solana operations
             #[cfg(all(feature = "solana-ops", feature = "2fa"))]
-            "7" | "8" | "9" | "10" | "11" | "12" | "13" | "14" | "15" | "16" | "17" | "18" => {
+            "7" | "8" | "9" | "10" | "11" | "12" | "13" | "14" | "15" | "16" | "17" | "18"
+            | "19" | "20" => {
                 if let Err(e) = handle_solana_operation(choice, lang, &mut session) {
                     eprintln!("❌ {}", e);
                 }
             }
             #[cfg(all(feature = "solana-ops", not(feature = "2fa")))]
-            "4" | "5" | "6" | "7" | "8" | "9" | "10" | "11" | "12" | "13" | "14" | "15" => {
+            "4" | "5" | "6" | "7" | "8" | "9" | "10" | "11" | "12" | "13" | "14" | "15" | "16"
+            | "17" => {
                 if let Err(e) = handle_solana_operation(choice, lang, &mut session) {
                     eprintln!("❌ {}", e);
                 }
@@ -1318,6 +1360,10 @@ fn handle_solana_operation(
         "17" => crate::operations::pumpfun_cashback_interactive(keypair, ops_language),
         #[cfg(feature = "sol-trade-sdk")]
         "18" => crate::operations::pumpswap_cashback_interactive(keypair, ops_language),
+        #[cfg(feature = "sol-trade-sdk")]
+        "19" => crate::operations::pumpfun_buy_interactive(keypair, ops_language),
+        #[cfg(feature = "sol-trade-sdk")]
+        "20" => crate::operations::pumpswap_buy_interactive(keypair, ops_language),
         _ => Err("Invalid operation".to_string()),
     };
 
@@ -1339,6 +1385,10 @@ fn handle_solana_operation(
         "14" => crate::operations::pumpfun_cashback_interactive(keypair, ops_language),
         #[cfg(feature = "sol-trade-sdk")]
         "15" => crate::operations::pumpswap_cashback_interactive(keypair, ops_language),
+        #[cfg(feature = "sol-trade-sdk")]
+        "16" => crate::operations::pumpfun_buy_interactive(keypair, ops_language),
+        #[cfg(feature = "sol-trade-sdk")]
+        "17" => crate::operations::pumpswap_buy_interactive(keypair, ops_language),
         _ => Err("Invalid operation".to_string()),
     };
 
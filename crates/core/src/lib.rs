@@ -49,9 +49,21 @@ pub mod totp;
 #[cfg(feature = "2fa")]
 pub mod secure_totp;
 
+// TOTP verification with replay protection and lockout for every 2FA unlock
+#[cfg(feature = "2fa")]
+pub mod totp_guard;
+
+// One-time backup codes stored as hashes in the triple-factor envelope
+#[cfg(feature = "2fa")]
+pub mod backup_codes;
+
 #[cfg(feature = "2fa")]
 pub mod hardware_fingerprint;
 
+// Device factor providers: hardware fingerprint, device key file, TPM-sealed secret
+#[cfg(feature = "2fa")]
+pub mod device_factor;
+
 #[cfg(feature = "2fa")]
 pub mod security_question;
 
@@ -64,6 +76,12 @@ pub mod bot_helper;
 // Solana operations interactive menu
 pub mod operations;
 
+// Address book lookups and address-poisoning detection
+pub mod address_book;
+
+// Verifiable-build comparison for deployed programs
+pub mod program_verify;
+
 // Solana utilities for token operations
 #[cfg(any(
     feature = "solana-ops",
@@ -823,29 +841,6 @@ pub fn derive_totp_secret_from_hardware_and_password(
     Ok(BASE32_NOPAD.encode(&secret))
 }
 
-#[cfg(feature = "2fa")]
-/// Verify a TOTP code
-fn verify_current_totp_code(totp_secret: &str, current_code: &str) -> Result<(), String> {
-    use crate::totp::{TOTPConfig, TOTPManager};
-
-    let config = TOTPConfig {
-        secret: totp_secret.to_string(),
-        account: "wallet".to_string(),
-        issuer: "FnzeroSafe".to_string(),
-        algorithm: "SHA1".to_string(),
-        digits: 6,
-        step: 30,
-    };
-
-    let totp_manager = TOTPManager::new(config);
-
-    match totp_manager.verify_code(current_code) {
-        Ok(true) => Ok(()),
-        Ok(false) => Err("验证失败，请检查主密码、安全问题答案或2FA验证码".to_string()),
-        Err(e) => Err(format!("验证失败: {}", e)),
-    }
-}
-
 // ============================================================================
 // Triple-Factor Encryption (only available with "2fa" feature)
 // ============================================================================
@@ -853,9 +848,10 @@ fn verify_current_totp_code(totp_secret: &str, current_code: &str) -> Result<(),
 #[cfg(feature = "2fa")]
 /// Generate a triple-factor encryption key
 ///
-/// Combines hardware fingerprint + master password + security answer
+/// Combines the device secret + master password + security answer. The device secret comes
+/// from a [`device_factor::DeviceFactor`]; legacy wallets pass the hardware fingerprint.
 pub fn generate_triple_factor_key(
-    hardware_fingerprint: &str,
+    device_secret: &str,
     master_password: &str,
     security_answer: &str,
 ) -> [u8; 32] {
@@ -864,7 +860,7 @@ pub fn generate_triple_factor_key(
 
     let key_material = format!(
         "HW:{}|PASS:{}|QA:{}",
-        hardware_fingerprint,
+        device_secret,
         master_password,
         security_answer.trim().to_lowercase()
     );
@@ -887,7 +883,9 @@ pub fn generate_triple_factor_key(
 #[cfg(feature = "2fa")]
 /// Encrypt with triple-factor authentication
 ///
-/// Used by CLI for maximum security with device binding.
+/// Used by CLI for maximum security with device binding. `backup_code_hashes` come from
+/// [`backup_codes::generate_backup_codes`]; the plain codes are never stored. The envelope
+/// is `tfa2:<wallet id>:<ciphertext>`; the random wallet id keys its 2FA state.
 pub fn encrypt_with_triple_factor(
     private_key: &str,
     twofa_secret: &str,
@@ -895,6 +893,7 @@ pub fn encrypt_with_triple_factor(
     master_password: &str,
     question_index: usize,
     security_answer: &str,
+    backup_code_hashes: &[String],
 ) -> Result<String, String> {
     use serde_json::json;
 
@@ -905,6 +904,7 @@ pub fn encrypt_with_triple_factor(
         "private_key": private_key,
         "twofa_secret": twofa_secret,
         "question_index": question_index,
+        "backup_codes": backup_code_hashes,
         "version": "triple_factor_v1",
         "created_at": std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
@@ -912,48 +912,395 @@ pub fn encrypt_with_triple_factor(
             .as_secs()
     });
 
-    let package_str = data_package.to_string();
-    let encrypted = encrypt_key(&package_str, &encryption_key)?;
+    TripleFactorPackage {
+        key: Zeroizing::new(encryption_key),
+        data: data_package,
+        wallet_id: new_triple_factor_wallet_id()?,
+    }
+    .seal()
+}
 
-    Ok(encrypted)
+#[cfg(feature = "2fa")]
+/// A triple-factor wallet opened by [`decrypt_with_triple_factor_and_2fa`]
+#[derive(Debug, Clone)]
+pub struct TripleFactorUnlock {
+    pub private_key: String,
+    pub twofa_secret: String,
+    pub question_index: usize,
+    /// Unused backup codes left in the envelope
+    pub backup_codes_remaining: usize,
+    /// Set when a backup code was spent: the envelope without it, which the caller must
+    /// store in place of the one it passed in and then commit
+    pub reseal: Option<TripleFactorReseal>,
+}
+
+#[cfg(feature = "2fa")]
+/// A re-sealed triple-factor envelope whose generation is not recorded yet
+///
+/// Store `encrypted_data` in place of the old envelope, then call [`Self::commit`]. Until
+/// then `totp_state` still accepts the old envelope, so a failed write loses nothing.
+#[derive(Debug, Clone)]
+#[must_use = "store the envelope, then commit it"]
+pub struct TripleFactorReseal {
+    pub encrypted_data: String,
+    wallet_id: String,
+    state_key: String,
+    generation: u64,
+}
+
+#[cfg(feature = "2fa")]
+impl TripleFactorReseal {
+    /// Make `totp_state` refuse every envelope older than this one
+    ///
+    /// A legacy envelope has no wallet id; its state moves to the new id and the old key
+    /// is retired.
+    pub fn commit(self, totp_state: &mut dyn totp_guard::TotpStateStore) -> Result<(), String> {
+        let mut state = totp_state.load(&self.state_key)?;
+        state.generation = state.generation.max(self.generation);
+        totp_state.save(&self.wallet_id, &state)?;
+        if self.state_key != self.wallet_id {
+            state.generation = totp_guard::RETIRED_GENERATION;
+            totp_state.save(&self.state_key, &state)?;
+        }
+        Ok(())
+    }
 }
 
 #[cfg(feature = "2fa")]
 /// Decrypt with triple-factor authentication and verify 2FA code
 ///
-/// Used by CLI for unlocking triple-factor encrypted wallets.
+/// The code goes through [`totp_guard`], so it is accepted once per time-step and failed
+/// attempts, including wrong passwords or answers, count towards the wallet's lockout.
+/// An unused backup code is accepted in place of the TOTP code, once: the re-sealed
+/// envelope gets a new generation and, once committed, `totp_state` refuses the one passed
+/// in.
 pub fn decrypt_with_triple_factor_and_2fa(
+    totp_state: &mut dyn totp_guard::TotpStateStore,
     encrypted_data: &str,
     hardware_fingerprint: &str,
     master_password: &str,
     security_answer: &str,
     twofa_code: &str,
-) -> Result<(String, String, usize), String> {
-    let decryption_key =
-        generate_triple_factor_key(hardware_fingerprint, master_password, security_answer);
+) -> Result<TripleFactorUnlock, String> {
+    let (package, factor) = open_triple_factor_with_code(
+        totp_state,
+        encrypted_data,
+        hardware_fingerprint,
+        master_password,
+        security_answer,
+        twofa_code,
+    )?;
+    let mut unlocked = package.unlocked()?;
+    if factor == totp_guard::SecondFactor::BackupCode {
+        unlocked.reseal = Some(reseal_triple_factor(encrypted_data, package)?);
+    }
+    Ok(unlocked)
+}
+
+#[cfg(feature = "2fa")]
+/// Replace a triple-factor wallet's backup codes with a new set
+///
+/// Needs every factor, with either a TOTP code or a backup code. Returns the new envelope and
+/// the new codes to show the user; once the envelope is committed, `totp_state` refuses every
+/// earlier envelope, and so every earlier code.
+pub fn regenerate_triple_factor_backup_codes(
+    totp_state: &mut dyn totp_guard::TotpStateStore,
+    encrypted_data: &str,
+    hardware_fingerprint: &str,
+    master_password: &str,
+    security_answer: &str,
+    twofa_code: &str,
+) -> Result<(TripleFactorReseal, Vec<String>), String> {
+    let (mut package, _) = open_triple_factor_with_code(
+        totp_state,
+        encrypted_data,
+        hardware_fingerprint,
+        master_password,
+        security_answer,
+        twofa_code,
+    )?;
+    let codes = backup_codes::generate_backup_codes()?;
+    package.data["backup_codes"] = serde_json::json!(codes.hashes);
+    let reseal = reseal_triple_factor(encrypted_data, package)?;
+    Ok((reseal, codes.codes))
+}
 
-    let decrypted = decrypt_key(encrypted_data, &decryption_key)
-        .map_err(|_| "解密失败，请检查主密码、安全问题答案是否正确")?;
+#[cfg(feature = "2fa")]
+/// Re-encrypt a triple-factor wallet for a new hardware factor
+///
+/// Needs every current factor, with either a TOTP code or a backup code. Used to re-bind a
+/// wallet to the device it now runs on, or to move a legacy fingerprint wallet to a
+/// [`hardware_fingerprint::HardwareBinding`]. Returns the new envelope, to store and commit.
+pub fn rebind_triple_factor_hardware(
+    totp_state: &mut dyn totp_guard::TotpStateStore,
+    encrypted_data: &str,
+    hardware_fingerprint: &str,
+    master_password: &str,
+    security_answer: &str,
+    twofa_code: &str,
+    new_hardware_fingerprint: &str,
+) -> Result<TripleFactorReseal, String> {
+    let (mut package, _) = open_triple_factor_with_code(
+        totp_state,
+        encrypted_data,
+        hardware_fingerprint,
+        master_password,
+        security_answer,
+        twofa_code,
+    )?;
+    package.key = Zeroizing::new(generate_triple_factor_key(
+        new_hardware_fingerprint,
+        master_password,
+        security_answer,
+    ));
+    reseal_triple_factor(encrypted_data, package)
+}
 
-    let data: serde_json::Value = serde_json::from_str(&decrypted)
-        .map_err(|_| "解密失败，请检查主密码、安全问题答案是否正确")?;
+#[cfg(feature = "2fa")]
+/// Number of unused backup codes in a triple-factor wallet
+///
+/// Needs the factors other than 2FA; a failed decryption counts towards the lockout.
+pub fn triple_factor_backup_codes_remaining(
+    totp_state: &mut dyn totp_guard::TotpStateStore,
+    encrypted_data: &str,
+    hardware_fingerprint: &str,
+    master_password: &str,
+    security_answer: &str,
+) -> Result<usize, String> {
+    let envelope = TripleFactorEnvelope::parse(encrypted_data)?;
+    let state = totp_state.load(&envelope.state_key)?;
+    let package = totp_guard::guarded_unlock(totp_state, &envelope.state_key, || {
+        TripleFactorPackage::open(
+            &envelope,
+            &state,
+            hardware_fingerprint,
+            master_password,
+            security_answer,
+        )
+    })?;
+    Ok(package
+        .backup_code_hashes()
+        .iter()
+        .filter(|hash| !state.spent_backup_codes.contains(hash))
+        .count())
+}
 
-    let private_key = data["private_key"]
-        .as_str()
-        .ok_or("缺少私钥数据")?
-        .to_string();
+#[cfg(feature = "2fa")]
+/// Key under which [`totp_guard`] keeps a triple-factor wallet's state
+///
+/// For callers that check other factors, such as security answers, under the same lockout
+/// before calling into this module.
+pub fn triple_factor_state_key(encrypted_data: &str) -> Result<String, String> {
+    TripleFactorEnvelope::parse(encrypted_data).map(|envelope| envelope.state_key)
+}
 
-    let twofa_secret = data["twofa_secret"]
-        .as_str()
-        .ok_or("缺少2FA密钥数据")?
-        .to_string();
+#[cfg(feature = "2fa")]
+fn open_triple_factor_with_code(
+    totp_state: &mut dyn totp_guard::TotpStateStore,
+    encrypted_data: &str,
+    hardware_fingerprint: &str,
+    master_password: &str,
+    security_answer: &str,
+    twofa_code: &str,
+) -> Result<(TripleFactorPackage, totp_guard::SecondFactor), String> {
+    let envelope = TripleFactorEnvelope::parse(encrypted_data)?;
+    let state = totp_state.load(&envelope.state_key)?;
+    totp_guard::unlock_with_second_factor(
+        totp_state,
+        &envelope.state_key,
+        twofa_code,
+        || {
+            let package = TripleFactorPackage::open(
+                &envelope,
+                &state,
+                hardware_fingerprint,
+                master_password,
+                security_answer,
+            )?;
+            let twofa_secret = package.unlocked()?.twofa_secret;
+            Ok((package, twofa_secret))
+        },
+        |package, code| {
+            let mut hashes = package.backup_code_hashes();
+            let Some(index) = backup_codes::find_backup_code(&hashes, code) else {
+                return Ok(None);
+            };
+            let spent = hashes.remove(index);
+            package.data["backup_codes"] = serde_json::json!(hashes);
+            Ok(Some(spent))
+        },
+    )
+    .map_err(String::from)
+}
 
-    let question_index = data["question_index"].as_u64().ok_or("缺少安全问题索引")? as usize;
+#[cfg(feature = "2fa")]
+/// Seal `package` as the next generation of `encrypted_data`
+///
+/// A legacy envelope has no wallet id, so it gets one here.
+fn reseal_triple_factor(
+    encrypted_data: &str,
+    mut package: TripleFactorPackage,
+) -> Result<TripleFactorReseal, String> {
+    let envelope = TripleFactorEnvelope::parse(encrypted_data)?;
+    let generation = package.generation() + 1;
+    package.data["generation"] = serde_json::json!(generation);
+    Ok(TripleFactorReseal {
+        encrypted_data: package.seal()?,
+        wallet_id: package.wallet_id,
+        state_key: envelope.state_key,
+        generation,
+    })
+}
+
+#[cfg(feature = "2fa")]
+fn new_triple_factor_wallet_id() -> Result<String, String> {
+    use ring::rand::SecureRandom;
+
+    let mut id = [0u8; 16];
+    ring::rand::SystemRandom::new()
+        .fill(&mut id)
+        .map_err(|_| "生成钱包 ID 失败".to_string())?;
+    Ok(hex::encode(id))
+}
 
-    // Verify 2FA code
-    verify_current_totp_code(&twofa_secret, twofa_code)?;
+#[cfg(feature = "2fa")]
+const TRIPLE_FACTOR_ENVELOPE_PREFIX: &str = "tfa2:";
 
-    Ok((private_key, twofa_secret, question_index))
+#[cfg(feature = "2fa")]
+/// A triple-factor envelope split into its state key and ciphertext
+struct TripleFactorEnvelope<'a> {
+    /// `None` for envelopes sealed before wallet ids existed
+    wallet_id: Option<&'a str>,
+    /// Where `totp_guard` keeps this wallet's state
+    state_key: String,
+    ciphertext: &'a str,
+}
+
+#[cfg(feature = "2fa")]
+impl<'a> TripleFactorEnvelope<'a> {
+    fn parse(encrypted_data: &'a str) -> Result<Self, String> {
+        let encrypted_data = encrypted_data.trim();
+        let Some(rest) = encrypted_data.strip_prefix(TRIPLE_FACTOR_ENVELOPE_PREFIX) else {
+            return Ok(Self {
+                wallet_id: None,
+                state_key: totp_guard::totp_wallet_key(encrypted_data),
+                ciphertext: encrypted_data,
+            });
+        };
+        let (wallet_id, ciphertext) = rest
+            .split_once(':')
+            .filter(|(id, _)| id.len() == 32 && id.bytes().all(|b| b.is_ascii_hexdigit()))
+            .ok_or("三因素钱包数据格式无效")?;
+        Ok(Self {
+            wallet_id: Some(wallet_id),
+            state_key: wallet_id.to_string(),
+            ciphertext,
+        })
+    }
+}
+
+#[cfg(feature = "2fa")]
+/// Decrypted triple-factor package together with the key that re-seals it
+struct TripleFactorPackage {
+    key: Zeroizing<[u8; 32]>,
+    data: serde_json::Value,
+    /// Id the package is sealed under; new for a legacy envelope
+    wallet_id: String,
+}
+
+#[cfg(feature = "2fa")]
+impl TripleFactorPackage {
+    /// Decrypt `envelope` and check it against the wallet's recorded `state`
+    fn open(
+        envelope: &TripleFactorEnvelope,
+        state: &totp_guard::TotpGuardState,
+        hardware_fingerprint: &str,
+        master_password: &str,
+        security_answer: &str,
+    ) -> Result<Self, String> {
+        let key = Zeroizing::new(generate_triple_factor_key(
+            hardware_fingerprint,
+            master_password,
+            security_answer,
+        ));
+
+        let decrypted = Zeroizing::new(
+            decrypt_key(envelope.ciphertext, &key)
+                .map_err(|_| "解密失败，请检查主密码、安全问题答案是否正确")?,
+        );
+
+        let data: serde_json::Value = serde_json::from_str(&decrypted)
+            .map_err(|_| "解密失败，请检查主密码、安全问题答案是否正确")?;
+
+        // The id in the clear must be the one sealed inside, or a copied envelope could
+        // pick a fresh state key
+        if data["wallet_id"].as_str() != envelope.wallet_id {
+            return Err("三因素钱包数据与钱包 ID 不一致".to_string());
+        }
+        let wallet_id = match envelope.wallet_id {
+            Some(id) => id.to_string(),
+            None => new_triple_factor_wallet_id()?,
+        };
+        let package = Self {
+            key,
+            data,
+            wallet_id,
+        };
+        state.ensure_current(package.generation())?;
+        Ok(package)
+    }
+
+    /// Incremented each time the envelope is re-sealed; 0 for envelopes sealed once
+    fn generation(&self) -> u64 {
+        self.data["generation"].as_u64().unwrap_or(0)
+    }
+
+    fn unlocked(&self) -> Result<TripleFactorUnlock, String> {
+        let private_key = self.data["private_key"]
+            .as_str()
+            .ok_or("缺少私钥数据")?
+            .to_string();
+
+        let twofa_secret = self.data["twofa_secret"]
+            .as_str()
+            .ok_or("缺少2FA密钥数据")?
+            .to_string();
+
+        let question_index = self.data["question_index"]
+            .as_u64()
+            .ok_or("缺少安全问题索引")? as usize;
+
+        Ok(TripleFactorUnlock {
+            private_key,
+            twofa_secret,
+            question_index,
+            backup_codes_remaining: self.backup_code_hashes().len(),
+            reseal: None,
+        })
+    }
+
+    /// Wallets created before backup codes have none
+    fn backup_code_hashes(&self) -> Vec<String> {
+        self.data["backup_codes"]
+            .as_array()
+            .map(|hashes| {
+                hashes
+                    .iter()
+                    .filter_map(|hash| hash.as_str().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn seal(&mut self) -> Result<String, String> {
+        self.data["wallet_id"] = serde_json::json!(self.wallet_id);
+        let package_str = Zeroizing::new(self.data.to_string());
+        let ciphertext = encrypt_key(&package_str, &self.key)?;
+        Ok(format!(
+            "{}{}:{}",
+            TRIPLE_FACTOR_ENVELOPE_PREFIX, self.wallet_id, ciphertext
+        ))
+    }
 }
 
 // ============================================================================
@@ -1195,4 +1542,279 @@ mod tests {
         assert!(KeyManager::keystore_version(r#"{"version":2}"#).is_err());
         assert!(KeyManager::keystore_version("{}").is_err());
     }
+
+    #[cfg(feature = "2fa")]
+    fn committed(
+        store: &mut totp_guard::MemoryTotpStateStore,
+        reseal: TripleFactorReseal,
+    ) -> String {
+        let encrypted = reseal.encrypted_data.clone();
+        reseal.commit(store).unwrap();
+        encrypted
+    }
+
+    #[cfg(feature = "2fa")]
+    #[test]
+    fn failed_wallet_write_keeps_the_old_envelope_usable() {
+        let mut store = totp_guard::MemoryTotpStateStore::default();
+        let codes = backup_codes::generate_backup_codes().unwrap();
+        let encrypted = encrypt_with_triple_factor(
+            "private-key",
+            &totp::TOTPManager::generate_secret(),
+            "hw",
+            "password",
+            0,
+            "answer",
+            &codes.hashes,
+        )
+        .unwrap();
+        let dir = std::env::temp_dir().join(format!(
+            "tfa-reseal-{}",
+            new_triple_factor_wallet_id().unwrap()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let wallet_file = dir.join("wallet.json");
+        std::fs::write(
+            &wallet_file,
+            json!({ "encrypted_private_key": encrypted }).to_string(),
+        )
+        .unwrap();
+        let wallet_file = wallet_file.to_str().unwrap();
+
+        let reseal = decrypt_with_triple_factor_and_2fa(
+            &mut store,
+            &encrypted,
+            "hw",
+            "password",
+            "answer",
+            &codes.codes[0],
+        )
+        .unwrap()
+        .reseal
+        .unwrap();
+        // The temporary file cannot be written while a directory sits at its path
+        std::fs::create_dir(format!("{}.tmp", wallet_file)).unwrap();
+        assert!(backup_codes::save_resealed_wallet_file(wallet_file, reseal, &mut store).is_err());
+
+        let on_disk: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(wallet_file).unwrap()).unwrap();
+        let on_disk = on_disk["encrypted_private_key"].as_str().unwrap();
+        assert_eq!(on_disk, encrypted);
+        // The spent code stays spent, but the old envelope still opens with another one
+        assert!(decrypt_with_triple_factor_and_2fa(
+            &mut store,
+            on_disk,
+            "hw",
+            "password",
+            "answer",
+            &codes.codes[0],
+        )
+        .is_err());
+        let unlocked = decrypt_with_triple_factor_and_2fa(
+            &mut store,
+            on_disk,
+            "hw",
+            "password",
+            "answer",
+            &codes.codes[1],
+        )
+        .unwrap();
+        assert_eq!(unlocked.private_key, "private-key");
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[cfg(feature = "2fa")]
+    #[test]
+    fn backup_codes_unlock_once_and_regenerate() {
+        let mut store = totp_guard::MemoryTotpStateStore::default();
+        let secret = totp::TOTPManager::generate_secret();
+        let codes = backup_codes::generate_backup_codes().unwrap();
+        let encrypted = encrypt_with_triple_factor(
+            "private-key",
+            &secret,
+            "hw",
+            "password",
+            2,
+            "answer",
+            &codes.hashes,
+        )
+        .unwrap();
+
+        let unlocked = decrypt_with_triple_factor_and_2fa(
+            &mut store,
+            &encrypted,
+            "hw",
+            "password",
+            "answer",
+            &codes.codes[3],
+        )
+        .unwrap();
+        assert_eq!(unlocked.private_key, "private-key");
+        assert_eq!(unlocked.backup_codes_remaining, codes.codes.len() - 1);
+        let updated = committed(&mut store, unlocked.reseal.unwrap());
+
+        // The spent code is gone from the new envelope; the others still work
+        assert!(decrypt_with_triple_factor_and_2fa(
+            &mut store,
+            &updated,
+            "hw",
+            "password",
+            "answer",
+            &codes.codes[3],
+        )
+        .is_err());
+        assert_eq!(
+            triple_factor_backup_codes_remaining(&mut store, &updated, "hw", "password", "answer"),
+            Ok(codes.codes.len() - 1)
+        );
+
+        let (reseal, new_codes) = regenerate_triple_factor_backup_codes(
+            &mut store,
+            &updated,
+            "hw",
+            "password",
+            "answer",
+            &codes.codes[0],
+        )
+        .unwrap();
+        let regenerated = committed(&mut store, reseal);
+        assert!(decrypt_with_triple_factor_and_2fa(
+            &mut store,
+            &regenerated,
+            "hw",
+            "password",
+            "answer",
+            &codes.codes[1],
+        )
+        .is_err());
+        let unlocked = decrypt_with_triple_factor_and_2fa(
+            &mut store,
+            &regenerated,
+            "hw",
+            "password",
+            "answer",
+            &new_codes[0],
+        )
+        .unwrap();
+        assert_eq!(unlocked.backup_codes_remaining, new_codes.len() - 1);
+    }
+
+    #[cfg(feature = "2fa")]
+    #[test]
+    fn envelopes_from_before_a_reseal_are_refused() {
+        let mut store = totp_guard::MemoryTotpStateStore::default();
+        let codes = backup_codes::generate_backup_codes().unwrap();
+        let secret = totp::TOTPManager::generate_secret();
+        let encrypted = encrypt_with_triple_factor(
+            "private-key",
+            &secret,
+            "hw",
+            "password",
+            0,
+            "answer",
+            &codes.hashes,
+        )
+        .unwrap();
+        let unlock = |store: &mut totp_guard::MemoryTotpStateStore, envelope: &str, code: &str| {
+            decrypt_with_triple_factor_and_2fa(store, envelope, "hw", "password", "answer", code)
+        };
+
+        let reseal = unlock(&mut store, &encrypted, &codes.codes[0])
+            .unwrap()
+            .reseal
+            .unwrap();
+        let updated = committed(&mut store, reseal);
+        // The pre-unlock envelope still holds the spent code, and the unused ones
+        assert!(unlock(&mut store, &encrypted, &codes.codes[0]).is_err());
+        assert!(unlock(&mut store, &encrypted, &codes.codes[1]).is_err());
+
+        let (reseal, _) = regenerate_triple_factor_backup_codes(
+            &mut store,
+            &updated,
+            "hw",
+            "password",
+            "answer",
+            &codes.codes[1],
+        )
+        .unwrap();
+        let regenerated = committed(&mut store, reseal);
+        assert!(unlock(&mut store, &updated, &codes.codes[2]).is_err());
+        assert!(unlock(&mut store, &regenerated, &codes.codes[2]).is_err());
+
+        // Stripping the wallet id does not buy a fresh state key
+        let (_, ciphertext) = regenerated.rsplit_once(':').unwrap();
+        assert!(unlock(&mut store, ciphertext, &codes.codes[2]).is_err());
+
+        // A legacy envelope moves to a wallet id on its first re-seal and is retired
+        let legacy_key = generate_triple_factor_key("hw", "password", "answer");
+        let legacy = encrypt_key(
+            &json!({
+                "private_key": "private-key",
+                "twofa_secret": secret,
+                "question_index": 0,
+                "backup_codes": codes.hashes,
+                "version": "triple_factor_v1",
+            })
+            .to_string(),
+            &legacy_key,
+        )
+        .unwrap();
+        let reseal = unlock(&mut store, &legacy, &codes.codes[3])
+            .unwrap()
+            .reseal
+            .unwrap();
+        let upgraded = committed(&mut store, reseal);
+        assert!(upgraded.starts_with(TRIPLE_FACTOR_ENVELOPE_PREFIX));
+        assert!(unlock(&mut store, &legacy, &codes.codes[4]).is_err());
+        assert!(unlock(&mut store, &upgraded, &codes.codes[4]).is_ok());
+    }
+
+    #[cfg(feature = "2fa")]
+    #[test]
+    fn rebind_reencrypts_for_the_new_hardware_factor() {
+        let mut store = totp_guard::MemoryTotpStateStore::default();
+        let codes = backup_codes::generate_backup_codes().unwrap();
+        let encrypted = encrypt_with_triple_factor(
+            "private-key",
+            &totp::TOTPManager::generate_secret(),
+            "legacy-fingerprint",
+            "password",
+            0,
+            "answer",
+            &codes.hashes,
+        )
+        .unwrap();
+
+        let reseal = rebind_triple_factor_hardware(
+            &mut store,
+            &encrypted,
+            "legacy-fingerprint",
+            "password",
+            "answer",
+            &codes.codes[0],
+            "device-key",
+        )
+        .unwrap();
+        let rebound = committed(&mut store, reseal);
+        assert!(triple_factor_backup_codes_remaining(
+            &mut store,
+            &rebound,
+            "legacy-fingerprint",
+            "password",
+            "answer"
+        )
+        .is_err());
+        let unlocked = decrypt_with_triple_factor_and_2fa(
+            &mut store,
+            &rebound,
+            "device-key",
+            "password",
+            "answer",
+            &codes.codes[1],
+        )
+        .unwrap();
+        assert_eq!(unlocked.private_key, "private-key");
+        // The code that authorised the re-bind is spent in the new envelope too
+        assert_eq!(unlocked.backup_codes_remaining, codes.codes.len() - 2);
+    }
 }
@@ -1,9 +1,19 @@
 use clap::{Parser, Subcommand};
 use colored::*;
 use fnzero_safe::{
+    backup_codes::{generate_backup_codes, save_resealed_wallet_file},
     decrypt_key, decrypt_with_triple_factor_and_2fa, derive_totp_secret_from_hardware_and_password,
+    device_factor::{
+        device_factor_by_kind, enroll_device_factor, save_wallet_file_device_factor,
+        unlock_wallet_device_factor, wallet_device_factor_kind, DeviceSecret,
+    },
     encrypt_key, encrypt_with_triple_factor, generate_encryption_key_simple,
-    hardware_fingerprint::*, security_question::*, totp::*,
+    hardware_fingerprint::*,
+    rebind_triple_factor_hardware, regenerate_triple_factor_backup_codes,
+    security_question::*,
+    totp::*,
+    totp_guard::JsonFileTotpStateStore,
+    triple_factor_backup_codes_remaining,
 };
 use solana_sdk::signer::Signer;
 use std::{
@@ -41,6 +51,10 @@ enum Commands {
         /// 输出文件路径
         #[arg(short = 'o', long, default_value = "secure-wallet.json")]
         output: String,
+
+        /// 设备因子：fingerprint（硬件指纹）、device-key（本机密钥文件）、tpm（TPM 2.0 封装）
+        #[arg(long, default_value = "fingerprint")]
+        device_factor: String,
     },
 
     /// 使用三因子 + 2FA 验证码解锁钱包 | Unlock 2FA wallet
@@ -51,6 +65,34 @@ enum Commands {
         file_path: String,
     },
 
+    /// 将 2FA 钱包重新绑定到当前设备 | Re-bind 2FA wallet to this device
+    #[command(name = "rebind-2fa-wallet")]
+    Rebind2FAWallet {
+        /// 加密文件路径
+        #[arg(short = 'f', long)]
+        file_path: String,
+
+        /// 原设备的硬件指纹（硬件变化过多、无法自动验证时使用）
+        #[arg(long)]
+        old_fingerprint: Option<String>,
+
+        /// 新的设备因子；默认沿用钱包当前的类型
+        #[arg(long)]
+        device_factor: Option<String>,
+    },
+
+    /// 查看或重新生成 2FA 钱包的备用恢复码 | Show or regenerate 2FA backup codes
+    #[command(name = "backup-codes")]
+    BackupCodes {
+        /// 加密文件路径
+        #[arg(short = 'f', long)]
+        file_path: String,
+
+        /// 生成新的一组恢复码，旧恢复码全部作废（需要 2FA 验证码）
+        #[arg(long)]
+        regenerate: bool,
+    },
+
     /// Solana 操作命令（使用加密私钥）| Solana operations with encrypted keys
     #[command(name = "sol-ops")]
     SolOps {
@@ -61,6 +103,26 @@ enum Commands {
         #[command(subcommand)]
         command: fnzero_safe::solana_utils::SolanaOpsCommand,
     },
+
+    /// 校验链上程序与本地构建产物一致 | Verify deployed program against a local build
+    #[command(name = "verify-program")]
+    VerifyProgram {
+        /// Program ID
+        #[arg(short, long)]
+        program_id: String,
+
+        /// 本地构建的 .so 文件路径（如 target/verifiable/<name>.so）
+        #[arg(short, long)]
+        so_path: String,
+
+        /// RPC URL (defaults to mainnet)
+        #[arg(short, long, default_value = "https://api.mainnet-beta.solana.com")]
+        rpc_url: String,
+
+        /// 可选：期望的 SHA-256（经评审的构建哈希）
+        #[arg(short, long)]
+        expected_sha256: Option<String>,
+    },
 }
 
 /// Print colored help message with bilingual content
@@ -130,6 +192,25 @@ fn print_colored_help() {
     println!("                    Unlock 2FA wallet");
     println!();
 
+    println!(
+        "  {} {}",
+        "rebind-2fa-wallet".bright_green().bold(),
+        "将 2FA 钱包重新绑定到当前设备".white()
+    );
+    println!("                    Re-bind 2FA wallet to this device");
+    println!("                    硬件部分变化（换网卡、迁移虚拟机）后刷新绑定，需全部因子");
+    println!("                    --device-factor 可改用 device-key 或 tpm");
+    println!();
+
+    println!(
+        "  {} {}",
+        "backup-codes".bright_green().bold(),
+        "查看/重新生成 2FA 备用恢复码".white()
+    );
+    println!("               Show remaining or regenerate 2FA backup codes");
+    println!("               手机丢失时可用恢复码代替 2FA 验证码，每个只能使用一次");
+    println!();
+
     println!(
         "  {} {}",
         "sol-ops".bright_green().bold(),
@@ -138,6 +219,15 @@ fn print_colored_help() {
     println!("          Solana operations (transfer/check balance)");
     println!();
 
+    println!(
+        "  {} {}",
+        "verify-program".bright_green().bold(),
+        "校验链上程序与本地构建产物".white()
+    );
+    println!("                 Verify deployed program bytes against a local build");
+    println!("                 匹配时退出码为 0，不匹配时为 1（适用于 CI）");
+    println!();
+
     println!("{}", "使用示例 | Usage Examples:".bright_cyan().bold());
     println!();
     println!("  {} 交互式模式（推荐新手使用）:", "1.".bright_yellow());
@@ -179,6 +269,14 @@ fn print_colored_help() {
     );
     println!();
 
+    println!("  {} 程序校验 (CI):", "4.".bright_yellow());
+    println!(
+        "     {} {}",
+        "$".bright_white(),
+        "fnzero-safe verify-program -p <Program ID> -s target/verifiable/<name>.so".bright_green()
+    );
+    println!();
+
     println!("{}", "选项 | Options:".bright_yellow().bold());
     println!(
         "  {} {}",
@@ -253,6 +351,143 @@ fn check_password_strength(password: &str) -> Result<(), String> {
     Ok(())
 }
 
+/// Factors of a triple-factor wallet other than 2FA, as read by [`read_triple_factor_inputs`]
+struct TripleFactorInputs {
+    encrypted_data: String,
+    /// Secret of the wallet's device factor provider, or the legacy fingerprint
+    device_secret: DeviceSecret,
+    master_password: String,
+    security_answer: zeroize::Zeroizing<String>,
+}
+
+/// Read a triple-factor wallet file and prompt for the factors other than 2FA; exits on failure.
+///
+/// `old_fingerprint` replaces the hardware check, for re-binding a wallet whose device changed.
+fn read_triple_factor_inputs(file_path: &str, old_fingerprint: Option<&str>) -> TripleFactorInputs {
+    // 读取加密文件
+    let file_content = match fs::read_to_string(file_path) {
+        Ok(content) => content,
+        Err(e) => {
+            eprintln!("{} 读取文件失败: {}", "❌".red(), e);
+            process::exit(1);
+        }
+    };
+
+    // 解析JSON
+    let data: serde_json::Value = match serde_json::from_str(&file_content) {
+        Ok(data) => data,
+        Err(e) => {
+            eprintln!("{} 文件格式错误: {}", "❌".red(), e);
+            process::exit(1);
+        }
+    };
+
+    let encrypted_data = data["encrypted_private_key"]
+        .as_str()
+        .unwrap_or("")
+        .to_string();
+
+    if encrypted_data.is_empty() {
+        eprintln!("{} 加密数据缺失", "❌".red());
+        process::exit(1);
+    }
+
+    // 步骤1: 验证设备因子
+    println!("{}", "步骤 1/3: 验证设备因子...".bright_blue());
+    let device_secret = match old_fingerprint {
+        Some(fingerprint) => {
+            println!("{} 使用指定的原硬件指纹", "⚠️".yellow());
+            DeviceSecret {
+                secret: fingerprint.trim().to_string().into(),
+                notice: None,
+            }
+        }
+        None => match unlock_wallet_device_factor(&data) {
+            Ok(device_secret) => {
+                println!(
+                    "{} 设备因子验证通过（{}）",
+                    "✅".bright_green(),
+                    wallet_device_factor_kind(&data)
+                );
+                if let Some(notice) = &device_secret.notice {
+                    println!(
+                        "{} {}，运行 {}",
+                        "⚠️".yellow(),
+                        notice,
+                        format!("fnzero-safe rebind-2fa-wallet -f {}", file_path).bright_white()
+                    );
+                }
+                device_secret
+            }
+            Err(e) => {
+                eprintln!("{} 设备因子验证失败: {}", "❌".red(), e);
+                eprintln!("   此钱包可能在其他设备上创建");
+                process::exit(1);
+            }
+        },
+    };
+    println!();
+
+    // 步骤2: 输入主密码
+    println!("{}", "步骤 2/3: 输入主密码".bright_blue());
+    print!("{} ", "请输入主密码:".bright_yellow());
+    io::stdout().flush().unwrap();
+    let master_password = rpassword::read_password()
+        .map_err(|e| {
+            eprintln!("{} 读取密码失败: {}", "❌".red(), e);
+            process::exit(1);
+        })
+        .unwrap();
+    println!();
+
+    // 步骤3: 回答安全问题
+    println!("{}", "步骤 3/3: 回答安全问题".bright_blue());
+    let security_answer = match SecurityQuestion::answer_wallet_interactive(&data, &master_password)
+    {
+        Ok(answer) => answer,
+        Err(e) => {
+            eprintln!("{} 安全问题验证失败: {}", "❌".red(), e);
+            process::exit(1);
+        }
+    };
+    println!();
+
+    TripleFactorInputs {
+        encrypted_data,
+        device_secret,
+        master_password,
+        security_answer,
+    }
+}
+
+/// One-line description of a wallet file's `device_factor`
+fn describe_device_factor(device_factor: &serde_json::Value) -> String {
+    let binding = &device_factor["binding"];
+    match device_factor["kind"].as_str() {
+        Some("device-key") => "本机设备密钥文件".to_string(),
+        Some("tpm") => "TPM 2.0 封装的随机密钥".to_string(),
+        _ => format!(
+            "硬件指纹（{} 项中任意 {} 项一致即可）",
+            binding["components"].as_array().map_or(0, Vec::len),
+            binding["threshold"]
+        ),
+    }
+}
+
+/// Show a new set of backup codes; they are not stored anywhere in plain text
+fn print_backup_codes(codes: &[String]) {
+    println!(
+        "{}",
+        "🧾 2FA 备用恢复码（只显示这一次）:".bright_yellow().bold()
+    );
+    for pair in codes.chunks(2) {
+        println!("   {}", pair.join("    ").bright_white());
+    }
+    println!("  • 手机丢失时可代替 2FA 验证码解锁，每个恢复码只能使用一次");
+    println!("  • 请离线抄写保存，不要与钱包文件放在一起");
+    println!();
+}
+
 /// Generate new Solana keypair and return as (private_key, public_key) strings
 fn generate_new_keypair() -> (String, String) {
     let keypair = solana_sdk::signature::Keypair::new();
@@ -545,7 +780,10 @@ fn main() {
                 "💡 下一步: 使用 gen-2fa-wallet 命令生成安全钱包".bright_blue()
             );
         }
-        Commands::Gen2FAWallet { output } => {
+        Commands::Gen2FAWallet {
+            output,
+            device_factor,
+        } => {
             println!("{}", "🔐 生成三因子加密钱包".bright_cyan().bold());
             println!();
 
@@ -580,13 +818,14 @@ fn main() {
 
             // 步骤3: 回答安全问题
             println!();
-            let (question_index, security_answer) = match SecurityQuestion::setup_interactive() {
-                Ok(result) => result,
-                Err(e) => {
-                    eprintln!("{} 设置安全问题失败: {}", "❌".red(), e);
-                    process::exit(1);
-                }
-            };
+            let (security_questions, security_answer) =
+                match SecurityQuestion::setup_set_interactive(&master_password) {
+                    Ok(result) => result,
+                    Err(e) => {
+                        eprintln!("{} 设置安全问题失败: {}", "❌".red(), e);
+                        process::exit(1);
+                    }
+                };
 
             // 步骤4: 验证2FA
             println!();
@@ -638,15 +877,37 @@ fn main() {
                 }
             }
 
+            // 生成备用恢复码，加密包内只保存其哈希
+            let backup_codes = match generate_backup_codes() {
+                Ok(set) => set,
+                Err(e) => {
+                    eprintln!("{} 生成备用恢复码失败: {}", "❌".red(), e);
+                    process::exit(1);
+                }
+            };
+
+            // 设备因子：硬件指纹绑定（多数硬件一致即可解锁）、本机密钥文件或 TPM 封装的随机密钥
+            let (device_secret, device_factor) = match device_factor_by_kind(&device_factor)
+                .and_then(|factor| enroll_device_factor(factor.as_ref()))
+            {
+                Ok(result) => result,
+                Err(e) => {
+                    eprintln!("{} 创建设备因子失败: {}", "❌".red(), e);
+                    process::exit(1);
+                }
+            };
+
             // 使用三因子加密
             println!("{}", "正在加密...".bright_blue());
             match encrypt_with_triple_factor(
                 &private_key,
                 &twofa_secret,
-                hardware_fp.as_str(),
+                &device_secret.secret,
                 &master_password,
-                question_index,
+                // 问题组保存在钱包文件中；加密包内的问题编号只对旧版单问题钱包有意义
+                0,
                 &security_answer,
+                &backup_codes.hashes,
             ) {
                 Ok(encrypted_data) => {
                     // 保存加密钱包
@@ -654,7 +915,8 @@ fn main() {
                         "encrypted_private_key": encrypted_data,
                         "public_key": public_key,
                         "version": "triple_factor_v1",
-                        "question_index": question_index,
+                        "security_questions": security_questions,
+                        "device_factor": device_factor,
                         "created_at": chrono::Utc::now().to_rfc3339()
                     });
 
@@ -666,6 +928,7 @@ fn main() {
                                 output.bright_white()
                             );
                             println!();
+                            print_backup_codes(&backup_codes.codes);
 
                             // 生成跨设备的 keystore 备份
                             println!("{}", "生成 Keystore 备份...".bright_blue());
@@ -737,12 +1000,12 @@ fn main() {
 
                             println!();
                             println!("{}", "🔒 安全架构:".bright_blue().bold());
-                            println!("  ✓ 硬件指纹: 绑定到当前设备");
+                            println!("  ✓ 设备因子: {}", describe_device_factor(&device_factor));
                             println!("  ✓ 主密码: 强密码保护");
                             println!(
-                                "  ✓ 安全问题: 问题 {} - {}",
-                                question_index + 1,
-                                SECURITY_QUESTIONS[question_index]
+                                "  ✓ 安全问题: {} 个问题中答对 {} 个",
+                                security_questions.questions.len(),
+                                security_questions.threshold
                             );
                             println!("  ✓ 2FA验证码: 动态验证（每30秒更新）");
                             println!();
@@ -796,7 +1059,7 @@ fn main() {
                                 format!("fnzero-safe unlock-2fa-wallet -f {}", output)
                                     .bright_white()
                             );
-                            println!("   输入: 主密码 → 安全问题答案 → 2FA验证码");
+                            println!("   输入: 主密码 → 安全问题答案 → 2FA验证码（或备用恢复码）");
                             println!();
                             println!("{} 任意设备 - 使用 Keystore 备份:", "方式2".bright_yellow());
                             println!(
@@ -844,77 +1107,20 @@ fn main() {
             println!("{}", "🔐 解锁三因子加密钱包".bright_cyan().bold());
             println!();
 
-            // 读取加密文件
-            let file_content = match fs::read_to_string(file_path) {
-                Ok(content) => content,
-                Err(e) => {
-                    eprintln!("{} 读取文件失败: {}", "❌".red(), e);
-                    process::exit(1);
-                }
-            };
-
-            // 解析JSON
-            let data: serde_json::Value = match serde_json::from_str(&file_content) {
-                Ok(data) => data,
-                Err(e) => {
-                    eprintln!("{} 文件格式错误: {}", "❌".red(), e);
-                    process::exit(1);
-                }
-            };
-
-            let encrypted_data = data["encrypted_private_key"]
-                .as_str()
-                .unwrap_or("")
-                .to_string();
-
-            let question_index = data["question_index"].as_u64().unwrap_or(0) as usize;
-
-            if encrypted_data.is_empty() {
-                eprintln!("{} 加密数据缺失", "❌".red());
-                process::exit(1);
-            }
-
-            // 步骤1: 收集硬件指纹
-            println!("{}", "步骤 1/3: 验证硬件指纹...".bright_blue());
-            let hardware_fp = match HardwareFingerprint::collect() {
-                Ok(fp) => {
-                    println!("{} 硬件指纹验证通过", "✅".bright_green());
-                    fp
-                }
-                Err(e) => {
-                    eprintln!("{} 硬件指纹验证失败: {}", "❌".red(), e);
-                    eprintln!("   此钱包可能在其他设备上创建");
-                    process::exit(1);
-                }
-            };
-            println!();
-
-            // 步骤2: 输入主密码
-            println!("{}", "步骤 2/3: 输入主密码".bright_blue());
-            print!("{} ", "请输入主密码:".bright_yellow());
-            io::stdout().flush().unwrap();
-            let master_password = rpassword::read_password()
-                .map_err(|e| {
-                    eprintln!("{} 读取密码失败: {}", "❌".red(), e);
-                    process::exit(1);
-                })
-                .unwrap();
-            println!();
-
-            // 步骤3: 回答安全问题
-            println!("{}", "步骤 3/3: 回答安全问题".bright_blue());
-            let security_answer = match SecurityQuestion::verify_interactive(question_index) {
-                Ok(answer) => answer,
-                Err(e) => {
-                    eprintln!("{} 安全问题验证失败: {}", "❌".red(), e);
-                    process::exit(1);
-                }
-            };
-            println!();
+            let TripleFactorInputs {
+                encrypted_data,
+                device_secret,
+                master_password,
+                security_answer,
+                ..
+            } = read_triple_factor_inputs(&file_path, None);
 
             // 步骤4: 输入当前2FA验证码
             println!("{}", "步骤 4/4: 输入 2FA 动态验证码".bright_blue());
-            print!("{} ", "请输入认证器显示的 6 位验证码:".bright_yellow());
+            print!(
+                "{} ",
+                "请输入认证器显示的 6 位验证码（或备用恢复码）:".bright_yellow()
+            );
             io::stdout().flush().unwrap();
             let mut twofa_code = String::new();
             io::stdin().read_line(&mut twofa_code).unwrap();
@@ -923,14 +1129,36 @@ fn main() {
 
             // 使用三因子解密并验证2FA
             println!("{}", "🔓 正在解密钱包...".bright_blue());
+            let mut totp_state = JsonFileTotpStateStore::beside(&file_path);
             match decrypt_with_triple_factor_and_2fa(
+                &mut totp_state,
                 &encrypted_data,
-                hardware_fp.as_str(),
+                &device_secret.secret,
                 &master_password,
                 &security_answer,
                 twofa_code,
             ) {
-                Ok((private_key, _twofa_secret, _question_idx)) => {
+                Ok(unlocked) => {
+                    if let Some(reseal) = unlocked.reseal {
+                        if let Err(e) =
+                            save_resealed_wallet_file(&file_path, reseal, &mut totp_state)
+                        {
+                            eprintln!("{} 更新钱包文件失败: {}", "❌".red(), e);
+                            process::exit(1);
+                        }
+                        println!(
+                            "{} 已使用一个备用恢复码，剩余 {} 个",
+                            "⚠️".yellow(),
+                            unlocked.backup_codes_remaining
+                        );
+                        println!(
+                            "   如手机已丢失，请重新设置 2FA；可运行 {} 补充恢复码",
+                            format!("fnzero-safe backup-codes -f {} --regenerate", file_path)
+                                .bright_white()
+                        );
+                        println!();
+                    }
+                    let private_key = unlocked.private_key;
                     // 验证私钥有效性
                     let keypair = solana_sdk::signature::Keypair::from_base58_string(&private_key);
                     let pubkey = keypair.pubkey();
@@ -944,7 +1172,7 @@ fn main() {
                     println!("  ✓ 硬件指纹匹配");
                     println!("  ✓ 主密码正确");
                     println!("  ✓ 安全问题答案正确");
-                    println!("  ✓ 2FA动态验证码正确");
+                    println!("  ✓ 2FA动态验证码或备用恢复码正确");
                 }
                 Err(e) => {
                     eprintln!("{} 解锁失败: {}", "❌".red(), e);
@@ -952,12 +1180,165 @@ fn main() {
                     eprintln!("{} 可能的原因:", "💡".bright_yellow());
                     eprintln!("  • 主密码错误");
                     eprintln!("  • 安全问题答案错误");
-                    eprintln!("  • 2FA验证码错误或已过期");
+                    eprintln!("  • 2FA验证码错误、已过期或已使用过");
                     eprintln!("  • 硬件指纹不匹配（设备不同）");
                     process::exit(1);
                 }
             }
         }
+        Commands::Rebind2FAWallet {
+            file_path,
+            old_fingerprint,
+            device_factor,
+        } => {
+            println!("{}", "🔗 重新绑定 2FA 钱包到当前设备".bright_cyan().bold());
+            println!();
+
+            let TripleFactorInputs {
+                encrypted_data,
+                device_secret,
+                master_password,
+                security_answer,
+            } = read_triple_factor_inputs(&file_path, old_fingerprint.as_deref());
+            let device_factor = match device_factor {
+                Some(kind) => kind,
+                None => match fs::read_to_string(&file_path)
+                    .ok()
+                    .and_then(|content| serde_json::from_str(&content).ok())
+                {
+                    Some(data) => wallet_device_factor_kind(&data).to_string(),
+                    None => {
+                        eprintln!("{} 读取钱包文件失败", "❌".red());
+                        process::exit(1);
+                    }
+                },
+            };
+
+            println!("{}", "步骤 4/4: 输入 2FA 动态验证码".bright_blue());
+            print!(
+                "{} ",
+                "请输入认证器显示的 6 位验证码（或备用恢复码）:".bright_yellow()
+            );
+            io::stdout().flush().unwrap();
+            let mut twofa_code = String::new();
+            io::stdin().read_line(&mut twofa_code).unwrap();
+            println!();
+
+            let (new_device_secret, stored) = match device_factor_by_kind(&device_factor)
+                .and_then(|factor| enroll_device_factor(factor.as_ref()))
+            {
+                Ok(result) => result,
+                Err(e) => {
+                    eprintln!("{} 创建设备因子失败: {}", "❌".red(), e);
+                    process::exit(1);
+                }
+            };
+            let mut totp_state = JsonFileTotpStateStore::beside(&file_path);
+            let reseal = match rebind_triple_factor_hardware(
+                &mut totp_state,
+                &encrypted_data,
+                &device_secret.secret,
+                &master_password,
+                &security_answer,
+                twofa_code.trim(),
+                &new_device_secret.secret,
+            ) {
+                Ok(reseal) => reseal,
+                Err(e) => {
+                    eprintln!("{} 解锁失败: {}", "❌".red(), e);
+                    process::exit(1);
+                }
+            };
+            if let Err(e) =
+                save_wallet_file_device_factor(&file_path, &reseal.encrypted_data, stored.clone())
+                    .and_then(|()| reseal.commit(&mut totp_state))
+            {
+                eprintln!("{} 更新钱包文件失败: {}", "❌".red(), e);
+                process::exit(1);
+            }
+            println!(
+                "{} 已重新绑定到当前设备: {}",
+                "✅".bright_green(),
+                describe_device_factor(&stored)
+            );
+        }
+        Commands::BackupCodes {
+            file_path,
+            regenerate,
+        } => {
+            println!("{}", "🧾 2FA 备用恢复码".bright_cyan().bold());
+            println!();
+
+            let TripleFactorInputs {
+                encrypted_data,
+                device_secret,
+                master_password,
+                security_answer,
+                ..
+            } = read_triple_factor_inputs(&file_path, None);
+            let mut totp_state = JsonFileTotpStateStore::beside(&file_path);
+
+            if !regenerate {
+                match triple_factor_backup_codes_remaining(
+                    &mut totp_state,
+                    &encrypted_data,
+                    &device_secret.secret,
+                    &master_password,
+                    &security_answer,
+                ) {
+                    Ok(remaining) => {
+                        println!("{} 剩余可用恢复码: {}", "✅".bright_green(), remaining);
+                        if remaining == 0 {
+                            println!(
+                                "   运行 {} 生成新的一组",
+                                format!("fnzero-safe backup-codes -f {} --regenerate", file_path)
+                                    .bright_white()
+                            );
+                        }
+                    }
+                    Err(e) => {
+                        eprintln!("{} 解锁失败: {}", "❌".red(), e);
+                        process::exit(1);
+                    }
+                }
+                return;
+            }
+
+            println!("{}", "步骤 4/4: 输入 2FA 动态验证码".bright_blue());
+            print!(
+                "{} ",
+                "请输入认证器显示的 6 位验证码（或备用恢复码）:".bright_yellow()
+            );
+            io::stdout().flush().unwrap();
+            let mut twofa_code = String::new();
+            io::stdin().read_line(&mut twofa_code).unwrap();
+            println!();
+
+            let (reseal, codes) = match regenerate_triple_factor_backup_codes(
+                &mut totp_state,
+                &encrypted_data,
+                &device_secret.secret,
+                &master_password,
+                &security_answer,
+                twofa_code.trim(),
+            ) {
+                Ok(result) => result,
+                Err(e) => {
+                    eprintln!("{} 解锁失败: {}", "❌".red(), e);
+                    process::exit(1);
+                }
+            };
+            if let Err(e) = save_resealed_wallet_file(&file_path, reseal, &mut totp_state) {
+                eprintln!("{} 更新钱包文件失败: {}", "❌".red(), e);
+                process::exit(1);
+            }
+            println!(
+                "{} 已生成新的恢复码，旧恢复码已全部作废",
+                "✅".bright_green()
+            );
+            println!();
+            print_backup_codes(&codes);
+        }
         Commands::SolOps { file_path, command } => {
             // Run Solana operations with encrypted keypair
             let args = fnzero_safe::solana_utils::SolanaOpsArgs {
@@ -972,5 +1353,79 @@ fn main() {
                 }
             }
         }
+        Commands::VerifyProgram {
+            program_id,
+            so_path,
+            rpc_url,
+            expected_sha256,
+        } => verify_program(&program_id, &so_path, &rpc_url, expected_sha256.as_deref()),
+    }
+}
+
+/// Compare deployed ProgramData with a local `.so`; exits non-zero on mismatch.
+fn verify_program(program_id: &str, so_path: &str, rpc_url: &str, expected_sha256: Option<&str>) {
+    use fnzero_safe::program_verify::{compare_program_bytes, fetch_program_executable};
+    use solana_client::rpc_client::RpcClient;
+    use std::str::FromStr;
+
+    let program_id = match solana_sdk::pubkey::Pubkey::from_str(program_id.trim()) {
+        Ok(program_id) => program_id,
+        Err(_) => {
+            eprintln!("{} 无效的 Program ID", "❌".red());
+            process::exit(2);
+        }
+    };
+    let local = match fs::read(so_path) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            eprintln!("{} 读取 .so 文件失败: {}", "❌".red(), e);
+            process::exit(2);
+        }
+    };
+    let client = RpcClient::new(rpc_url.to_string());
+    let onchain = match fetch_program_executable(&client, &program_id) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            eprintln!("{} 读取链上程序失败: {}", "❌".red(), e);
+            process::exit(2);
+        }
+    };
+
+    let comparison = compare_program_bytes(&onchain, &local);
+    println!("{} Program:      {}", "🆔".bright_cyan(), program_id);
+    println!(
+        "   On-chain:     {} ({} bytes)",
+        comparison.onchain_sha256, comparison.onchain_len
+    );
+    println!(
+        "   Local build:  {} ({} bytes)",
+        comparison.local_sha256, comparison.local_len
+    );
+
+    let mut ok = comparison.matches;
+    if let Some(expected) = expected_sha256 {
+        let expected = expected.trim().to_ascii_lowercase();
+        if expected != comparison.local_sha256 {
+            eprintln!("{} 本地构建哈希与期望值不符: 期望 {}", "❌".red(), expected);
+            ok = false;
+        }
+    }
+    if comparison.matches {
+        println!(
+            "{}",
+            "✅ 链上程序与本地构建一致 | Match".bright_green().bold()
+        );
+    } else {
+        eprintln!("{}", "❌ 链上程序与本地构建不一致 | Mismatch".red().bold());
+        if let Some(offset) = comparison.first_difference {
+            eprintln!("   首个差异偏移 | First difference: {}", offset);
+        }
+        eprintln!(
+            "   差异字节 | Differing bytes: {}, 长度差 | Length delta: {}",
+            comparison.differing_bytes, comparison.length_delta
+        );
+    }
+    if !ok {
+        process::exit(1);
     }
 }
@@ -19,7 +19,7 @@ use solana_sdk::signer::Signer;
 use std::str::FromStr;
 
 #[cfg(feature = "solana-ops")]
-use crate::solana_utils::{lamports_to_sol, SolanaClient, SolanaClientSdk};
+use crate::solana_utils::{lamports_to_sol, normalize_memo, SolanaClient, SolanaClientSdk};
 
 #[cfg(any(feature = "solana-ops", feature = "sol-trade-sdk"))]
 use solana_client::rpc_client::RpcClient;
@@ -287,6 +287,19 @@ pub fn transfer_sol(keypair: &Keypair, language: Language) -> Result<(), String>
 
     let amount_lamports = (amount_sol * 1_000_000_000.0) as u64;
 
+    let memo_prompt = if language == Language::English {
+        "Memo (optional, required by some exchanges): "
+    } else {
+        "备注 Memo（可选，部分交易所充值需要）: "
+    };
+    let memo = normalize_memo(Some(&read_input(memo_prompt, ""))).map_err(|e| {
+        if language == Language::English {
+            format!("❌ Invalid memo: {}", e)
+        } else {
+            format!("❌ 无效的备注: {}", e)
+        }
+    })?;
+
     println!("\n{}", "📋 Transaction Summary:".bright_yellow());
     println!("  From: {}", keypair.pubkey().to_string().bright_white());
     println!("  To: {}", recipient.to_string().bright_white());
@@ -294,6 +307,9 @@ pub fn transfer_sol(keypair: &Keypair, language: Language) -> Result<(), String>
         "  Amount: {} SOL",
         amount_sol.to_string().bright_white().bold()
     );
+    if let Some(memo) = &memo {
+        println!("  Memo: {}", memo.bright_white());
+    }
 
     let confirm_prompt = if language == Language::English {
         "\nConfirm transaction? (yes/no) [no]: "
@@ -319,7 +335,7 @@ pub fn transfer_sol(keypair: &Keypair, language: Language) -> Result<(), String>
     }
 
     let client = SolanaClient::new(rpc_url.to_string());
-    match client.transfer_sol(keypair, &recipient, amount_lamports) {
+    match client.transfer_sol_with_memo(keypair, &recipient, amount_lamports, memo.as_deref()) {
         Ok(signature) => {
             println!("\n{}", "✅ Transfer successful!".bright_green().bold());
             println!("  📝 Signature: {}", signature.to_string().bright_white());
@@ -995,6 +1011,19 @@ pub fn transfer_token(keypair: &Keypair, language: Language) -> Result<(), Strin
         }
     })?;
 
+    let memo_prompt = if language == Language::English {
+        "Memo (optional, required by some exchanges): "
+    } else {
+        "备注 Memo（可选，部分交易所充值需要）: "
+    };
+    let memo = normalize_memo(Some(&read_input(memo_prompt, ""))).map_err(|e| {
+        if language == Language::English {
+            format!("❌ Invalid memo: {}", e)
+        } else {
+            format!("❌ 无效的备注: {}", e)
+        }
+    })?;
+
     println!("\n{}", "📋 Transaction Summary:".bright_yellow());
     println!("  From: {}", keypair.pubkey().to_string().bright_white());
     println!("  To: {}", recipient.to_string().bright_white());
@@ -1003,6 +1032,36 @@ pub fn transfer_token(keypair: &Keypair, language: Language) -> Result<(), Strin
         "  Amount: {} (smallest units)",
         amount.to_string().bright_white().bold()
     );
+    if let Some(memo) = &memo {
+        println!("  Memo: {}", memo.bright_white());
+    }
+
+    let client = SolanaClient::new(rpc_url.to_string());
+    let plan = client
+        .plan_token_transfer(&keypair.pubkey(), &recipient, &mint, amount)
+        .and_then(|plan| plan.with_memo(memo.as_deref()))
+        .map_err(|e| {
+            if language == Language::English {
+                format!("❌ Transfer not possible: {}", e)
+            } else {
+                format!("❌ 无法转账: {}", e)
+            }
+        })?;
+    if plan.fee > 0 {
+        if language == Language::English {
+            println!(
+                "  Transfer fee: {} (smallest units)",
+                plan.fee.to_string().red()
+            );
+            println!("  Recipient receives: {} (smallest units)", plan.net_amount);
+        } else {
+            println!("  转账手续费: {} (最小单位)", plan.fee.to_string().red());
+            println!("  对方实收: {} (最小单位)", plan.net_amount);
+        }
+    }
+    for warning in &plan.warnings {
+        println!("  ⚠️  {}", warning.yellow());
+    }
 
     let confirm_prompt = if language == Language::English {
         "\nConfirm transaction? (yes/no) [no]: "
@@ -1027,8 +1086,7 @@ pub fn transfer_token(keypair: &Keypair, language: Language) -> Result<(), Strin
         println!("\n🚀 正在发送交易...");
     }
 
-    let client = SolanaClient::new(rpc_url.to_string());
-    match client.transfer_token(keypair, &recipient, &mint, amount) {
+    match client.send_token_transfer(keypair, &plan) {
         Ok(signature) => {
             println!("\n{}", "✅ Transfer successful!".bright_green().bold());
             println!("  📝 Signature: {}", signature.to_string().bright_white());
@@ -1948,6 +2006,184 @@ pub fn pumpfun_sell_interactive(keypair: &Keypair, language: Language) -> Result
     Ok(())
 }
 
+/// Pump.fun 交互式买入（内盘已完成时自动改走 PumpSwap）
+#[cfg(feature = "sol-trade-sdk")]
+pub fn pumpfun_buy_interactive(keypair: &Keypair, language: Language) -> Result<(), String> {
+    pump_buy_interactive(
+        keypair,
+        language,
+        sol_trade_sdk::trading::factory::DexType::PumpFun,
+    )
+}
+
+/// PumpSwap 交互式买入
+#[cfg(feature = "sol-trade-sdk")]
+pub fn pumpswap_buy_interactive(keypair: &Keypair, language: Language) -> Result<(), String> {
+    pump_buy_interactive(
+        keypair,
+        language,
+        sol_trade_sdk::trading::factory::DexType::PumpSwap,
+    )
+}
+
+#[cfg(feature = "sol-trade-sdk")]
+fn pump_buy_interactive(
+    keypair: &Keypair,
+    language: Language,
+    dex_type: sol_trade_sdk::trading::factory::DexType,
+) -> Result<(), String> {
+    use crate::solana_utils::pump_buy::{
+        handle_pump_buy_no_prompt, parse_buy_input_token, PumpBuyOptions,
+    };
+
+    let venue = if dex_type == sol_trade_sdk::trading::factory::DexType::PumpFun {
+        "Pump.fun"
+    } else {
+        "PumpSwap"
+    };
+    println!(
+        "\n{}",
+        "━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━".bright_magenta()
+    );
+    if language == Language::English {
+        println!(
+            "  {}",
+            format!("🟢 {} Buy Tokens", venue).bright_magenta().bold()
+        );
+    } else {
+        println!(
+            "  {}",
+            format!("🟢 {} 买入代币", venue).bright_magenta().bold()
+        );
+    }
+    println!(
+        "{}",
+        "━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━".bright_magenta()
+    );
+    println!("  📍 {}", keypair.pubkey().to_string().bright_white());
+
+    let english = language == Language::English;
+    let rpc_prompt = if english {
+        format!("RPC URL (default: {}): ", DEFAULT_RPC_URL)
+    } else {
+        format!("RPC URL (默认: {}): ", DEFAULT_RPC_URL)
+    };
+    let rpc_url = read_input(&rpc_prompt, DEFAULT_RPC_URL);
+
+    let mint = read_input(
+        if english {
+            "Token Mint Address: "
+        } else {
+            "代币 Mint 地址: "
+        },
+        "",
+    );
+    Pubkey::from_str(&mint).map_err(|e| {
+        if english {
+            format!("Invalid mint address: {}", e)
+        } else {
+            format!("无效的 Mint 地址: {}", e)
+        }
+    })?;
+
+    let input = read_input(
+        if english {
+            "Pay with (sol/wsol, default: sol): "
+        } else {
+            "资金类型 (sol/wsol, 默认 sol): "
+        },
+        "sol",
+    );
+    let input_token_type = parse_buy_input_token(&input)?;
+
+    let amount = read_input(
+        if english {
+            "Amount to spend (SOL): "
+        } else {
+            "买入金额 (SOL): "
+        },
+        "",
+    );
+    let amount: f64 = amount
+        .parse()
+        .ok()
+        .filter(|value: &f64| value.is_finite() && *value > 0.0)
+        .ok_or_else(|| {
+            if english {
+                "Invalid amount".to_string()
+            } else {
+                "无效的金额".to_string()
+            }
+        })?;
+
+    let slippage = read_input(
+        if english {
+            "Slippage in bps (default: 500 = 5%): "
+        } else {
+            "滑点基点 (默认 500 = 5%): "
+        },
+        "500",
+    );
+    let slippage: u64 = slippage
+        .parse()
+        .ok()
+        .filter(|value| *value <= 10_000)
+        .ok_or_else(|| {
+            if english {
+                "Invalid slippage".to_string()
+            } else {
+                "无效的滑点".to_string()
+            }
+        })?;
+
+    let create_ata = read_input(
+        if english {
+            "Create token ATA if missing? (yes/no, default: yes): "
+        } else {
+            "缺少代币 ATA 时自动创建? (yes/no, 默认 yes): "
+        },
+        "yes",
+    )
+    .to_lowercase();
+    let options = PumpBuyOptions {
+        amount_lamports: (amount * 1_000_000_000.0) as u64,
+        input_token_type,
+        slippage,
+        create_ata: create_ata == "yes" || create_ata == "y",
+    };
+
+    println!();
+    let result = match tokio::runtime::Handle::try_current() {
+        Ok(handle) => {
+            let keypair_b58 = bs58::encode(keypair.to_bytes()).into_string();
+            std::thread::spawn(move || {
+                let keypair_clone = Keypair::from_base58_string(&keypair_b58);
+                handle.block_on(async move {
+                    handle_pump_buy_no_prompt(
+                        &keypair_clone,
+                        &mint,
+                        &rpc_url,
+                        dex_type,
+                        options,
+                        language,
+                        false,
+                    )
+                    .await
+                })
+            })
+            .join()
+            .map_err(|_| "Thread panicked".to_string())?
+        }
+        Err(_) => {
+            let rt = tokio::runtime::Runtime::new().map_err(|e| e.to_string())?;
+            rt.block_on(handle_pump_buy_no_prompt(
+                keypair, &mint, &rpc_url, dex_type, options, language, false,
+            ))
+        }
+    };
+    result
+}
+
 /// Pump (Pump.fun) 返现：查询余额 → 显示 → 确认 → 领取（原生 SOL）
 #[cfg(feature = "sol-trade-sdk")]
 pub fn pumpfun_cashback_interactive(keypair: &Keypair, language: Language) -> Result<(), String> {
@@ -2392,3 +2628,21 @@ pub fn pumpswap_cashback_interactive(_keypair: &Keypair, language: Language) ->
         "PumpSwap 返现需要 'sol-trade-sdk' 功能。".to_string()
     })
 }
+
+#[cfg(not(feature = "sol-trade-sdk"))]
+pub fn pumpfun_buy_interactive(_keypair: &Keypair, language: Language) -> Result<(), String> {
+    Err(if language == Language::English {
+        "Pump.fun buy requires 'sol-trade-sdk' feature.".to_string()
+    } else {
+        "Pump.fun 买入需要 'sol-trade-sdk' 功能。".to_string()
+    })
+}
+
+#[cfg(not(feature = "sol-trade-sdk"))]
+pub fn pumpswap_buy_interactive(_keypair: &Keypair, language: Language) -> Result<(), String> {
+    Err(if language == Language::English {
+        "PumpSwap buy requires 'sol-trade-sdk' feature.".to_string()
+    } else {
+        "PumpSwap 买入需要 'sol-trade-sdk' 功能。".to_string()
+    })
+}
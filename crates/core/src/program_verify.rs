@@ -0,0 +1,156 @@
+//! Verifiable-build comparison for deployed upgradeable programs.
+//!
+//! The upgradeable loader stores the executable inside the ProgramData account
+//! after a 45-byte header and pads it with zeros up to the allocated length.
+//! Like `solana-verify`, both sides are compared with trailing zeros removed so
+//! a locally built `.so` can be checked against what is actually deployed.
+
+use ring::digest;
+use serde::Serialize;
+use solana_sdk::pubkey::Pubkey;
+
+/// `UpgradeableLoaderState::ProgramData` header: u32 tag + u64 slot + Option<Pubkey>.
+pub const PROGRAMDATA_METADATA_SIZE: usize = 45;
+
+const PROGRAM_STATE_TAG: u32 = 2;
+const PROGRAMDATA_STATE_TAG: u32 = 3;
+
+/// Result of comparing deployed program bytes with a local artifact.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ProgramComparison {
+    pub onchain_sha256: String,
+    pub local_sha256: String,
+    pub onchain_len: usize,
+    pub local_len: usize,
+    pub matches: bool,
+    /// Offset of the first differing byte, including a length mismatch.
+    pub first_difference: Option<usize>,
+    /// Differing bytes within the common length.
+    pub differing_bytes: usize,
+    /// `onchain_len - local_len`.
+    pub length_delta: i64,
+}
+
+/// Remove the zero padding the loader leaves after the executable.
+pub fn strip_trailing_zeros(bytes: &[u8]) -> &[u8] {
+    let end = bytes
+        .iter()
+        .rposition(|byte| *byte != 0)
+        .map_or(0, |index| index + 1);
+    &bytes[..end]
+}
+
+fn read_tag(data: &[u8]) -> Option<u32> {
+    data.get(..4)
+        .map(|tag| u32::from_le_bytes([tag[0], tag[1], tag[2], tag[3]]))
+}
+
+/// Extract the ProgramData address from an upgradeable `Program` account.
+pub fn program_account_programdata_address(data: &[u8]) -> Result<Pubkey, String> {
+    if read_tag(data) != Some(PROGRAM_STATE_TAG) || data.len() < 36 {
+        return Err("Account is not an upgradeable loader Program account".to_string());
+    }
+    let mut key = [0u8; 32];
+    key.copy_from_slice(&data[4..36]);
+    Ok(Pubkey::new_from_array(key))
+}
+
+/// Return the executable bytes stored in a ProgramData account, without padding.
+pub fn programdata_executable(data: &[u8]) -> Result<&[u8], String> {
+    if read_tag(data) != Some(PROGRAMDATA_STATE_TAG) || data.len() < PROGRAMDATA_METADATA_SIZE {
+        return Err("Account is not an upgradeable loader ProgramData account".to_string());
+    }
+    Ok(strip_trailing_zeros(&data[PROGRAMDATA_METADATA_SIZE..]))
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    hex::encode(digest::digest(&digest::SHA256, bytes).as_ref())
+}
+
+/// Compare deployed executable bytes with a local build artifact.
+pub fn compare_program_bytes(onchain: &[u8], local: &[u8]) -> ProgramComparison {
+    let onchain = strip_trailing_zeros(onchain);
+    let local = strip_trailing_zeros(local);
+    let common = onchain.len().min(local.len());
+    let mut first_difference = None;
+    let mut differing_bytes = 0;
+    for (index, (left, right)) in onchain[..common].iter().zip(&local[..common]).enumerate() {
+        if left != right {
+            first_difference.get_or_insert(index);
+            differing_bytes += 1;
+        }
+    }
+    if first_difference.is_none() && onchain.len() != local.len() {
+        first_difference = Some(common);
+    }
+
+    ProgramComparison {
+        onchain_sha256: sha256_hex(onchain),
+        local_sha256: sha256_hex(local),
+        onchain_len: onchain.len(),
+        local_len: local.len(),
+        matches: first_difference.is_none(),
+        first_difference,
+        differing_bytes,
+        length_delta: onchain.len() as i64 - local.len() as i64,
+    }
+}
+
+/// Download the deployed executable of an upgradeable program.
+#[cfg(feature = "mobile-solana-ops")]
+pub fn fetch_program_executable(
+    client: &solana_client::rpc_client::RpcClient,
+    program_id: &Pubkey,
+) -> Result<Vec<u8>, String> {
+    let program = client
+        .get_account(program_id)
+        .map_err(|e| format!("Failed to fetch program account: {}", e))?;
+    let programdata_address = program_account_programdata_address(&program.data)?;
+    let programdata = client
+        .get_account(&programdata_address)
+        .map_err(|e| format!("Failed to fetch ProgramData account: {}", e))?;
+    programdata_executable(&programdata.data).map(<[u8]>::to_vec)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn programdata(executable: &[u8], padding: usize) -> Vec<u8> {
+        let mut data = vec![0u8; PROGRAMDATA_METADATA_SIZE];
+        data[..4].copy_from_slice(&PROGRAMDATA_STATE_TAG.to_le_bytes());
+        data.extend_from_slice(executable);
+        data.extend(std::iter::repeat_n(0, padding));
+        data
+    }
+
+    #[test]
+    fn padded_programdata_matches_local_artifact() {
+        let local = b"\x7fELF-program-bytes";
+        let data = programdata(local, 4096);
+        let onchain = programdata_executable(&data).unwrap();
+        let comparison = compare_program_bytes(onchain, local);
+        assert!(comparison.matches);
+        assert_eq!(comparison.onchain_sha256, comparison.local_sha256);
+        assert_eq!(comparison.length_delta, 0);
+    }
+
+    #[test]
+    fn reports_byte_diff_statistics() {
+        let comparison = compare_program_bytes(b"abcdefgh", b"abXdeYghij");
+        assert!(!comparison.matches);
+        assert_eq!(comparison.first_difference, Some(2));
+        assert_eq!(comparison.differing_bytes, 2);
+        assert_eq!(comparison.length_delta, -2);
+
+        let truncated = compare_program_bytes(b"abc", b"abcd");
+        assert_eq!(truncated.first_difference, Some(3));
+        assert_eq!(truncated.differing_bytes, 0);
+    }
+
+    #[test]
+    fn rejects_non_programdata_accounts() {
+        assert!(programdata_executable(&[2, 0, 0, 0]).is_err());
+        assert!(program_account_programdata_address(&[3u8; 36]).is_err());
+    }
+}
@@ -117,13 +117,18 @@ impl SecureTOTPManager {
 }
 
 /// 实用安全的 2FA 解锁函数
+///
+/// 2FA 密钥只保存在三因子加密包内，因此必须先用设备因子、主密码和安全问题答案解密，
+/// 再经 `totp_guard` 校验验证码（防重放、失败锁定，状态保存在钱包文件旁）。
 pub fn secure_unlock_with_2fa(
     encrypted_file_path: &str,
     _account: &str,
     _issuer: &str,
 ) -> Result<(), String> {
-    use crate::totp::parse_encrypted_file;
-    use crate::{decrypt_key, generate_encryption_key_simple};
+    use crate::decrypt_with_triple_factor_and_2fa;
+    use crate::device_factor::unlock_wallet_device_factor;
+    use crate::security_question::SecurityQuestion;
+    use crate::totp_guard::JsonFileTotpStateStore;
     use rpassword;
     use solana_sdk::signer::Signer;
     use std::fs;
@@ -131,55 +136,82 @@ pub fn secure_unlock_with_2fa(
     println!("{}", "🔐 实用安全 2FA 解锁模式".bright_cyan().bold());
     println!();
 
+    // 读取加密文件
+    let file_content =
+        fs::read_to_string(encrypted_file_path).map_err(|e| format!("读取文件失败: {}", e))?;
+    let data: serde_json::Value =
+        serde_json::from_str(&file_content).map_err(|e| format!("文件格式错误: {}", e))?;
+    if data["encryption_type"].as_str() != Some("triple_factor_v1") {
+        return Err("该文件不是 2FA 钱包，无法进行 2FA 解锁".to_string());
+    }
+    let encrypted_data = data["encrypted_private_key"]
+        .as_str()
+        .ok_or("文件中未找到加密私钥")?;
+    let device = unlock_wallet_device_factor(&data)?;
+
     // 第一步：获取主密码
     print!("{} ", "请输入主密码:".bright_yellow());
     io::stdout().flush().unwrap();
     let master_password = rpassword::read_password().map_err(|e| format!("读取密码失败: {}", e))?;
 
-    // 第二步：获取当前 2FA 验证码
-    print!("{} ", "请输入当前 2FA 验证码:".bright_green());
-    io::stdout().flush().unwrap();
-    let _totp_code = rpassword::read_password().map_err(|e| format!("读取验证码失败: {}", e))?;
+    // 第二步：回答安全问题
+    let security_answer = SecurityQuestion::answer_wallet_interactive(&data, &master_password)?;
 
-    // 读取加密文件
-    let file_content =
-        fs::read_to_string(encrypted_file_path).map_err(|e| format!("读取文件失败: {}", e))?;
-    let encrypted_data = parse_encrypted_file(&file_content)?;
+    // 第三步：获取当前 2FA 验证码
+    print!("{} ", "请输入当前 2FA 验证码或备用恢复码:".bright_green());
+    io::stdout().flush().unwrap();
+    let totp_code = rpassword::read_password().map_err(|e| format!("读取验证码失败: {}", e))?;
 
     println!("🔍 正在验证主密码和2FA验证码...");
 
-    // 使用简单的密码解密方案（实用安全解密已移除）
-    let encryption_key = generate_encryption_key_simple(&master_password);
-    match decrypt_key(&encrypted_data, &encryption_key) {
-        Ok(private_key) => {
-            // 验证私钥有效性
-            let keypair = solana_sdk::signature::Keypair::from_base58_string(&private_key);
-            let pubkey = keypair.pubkey();
-            println!("{}", "✅ 双重验证通过，解锁成功！".bright_green());
-            if std::env::var("SOL_SAFEKEY_CLI_SHOW_SECRETS")
-                .ok()
-                .as_deref()
-                == Some("true")
-            {
-                println!("{} 私钥: {}", "🔑".bright_cyan(), private_key);
-            } else {
-                println!(
-                    "{} 私钥: {}",
-                    "🔑".bright_cyan(),
-                    "[已隐藏；如确需显示，请设置 SOL_SAFEKEY_CLI_SHOW_SECRETS=true]".yellow()
-                );
-            }
-            println!("{} 公钥: {}", "🆔".bright_cyan(), pubkey);
-            println!();
-            println!("{}", "🔒 安全确认:".bright_blue().bold());
-            println!("  • 主密码验证通过（解密成功）");
-            println!("  • 2FA验证码验证通过（身份确认）");
-            println!("  • 提供了真正的双因子安全保护");
-            println!("  • 用户体验简单：只需主密码+验证码");
-            Ok(())
-        }
-        Err(e) => Err(format!("解锁失败: {}. 请检查主密码和2FA验证码", e)),
+    let mut totp_state = JsonFileTotpStateStore::beside(encrypted_file_path);
+    let unlocked = decrypt_with_triple_factor_and_2fa(
+        &mut totp_state,
+        encrypted_data,
+        &device.secret,
+        &master_password,
+        &security_answer,
+        &totp_code,
+    )
+    .map_err(|e| format!("解锁失败: {}", e))?;
+    if let Some(reseal) = unlocked.reseal {
+        crate::backup_codes::save_resealed_wallet_file(
+            encrypted_file_path,
+            reseal,
+            &mut totp_state,
+        )?;
+        println!(
+            "{} 已使用一个备用恢复码，剩余 {} 个",
+            "⚠️".yellow(),
+            unlocked.backup_codes_remaining
+        );
     }
+    let private_key = unlocked.private_key;
+
+    // 验证私钥有效性
+    let keypair = solana_sdk::signature::Keypair::from_base58_string(&private_key);
+    let pubkey = keypair.pubkey();
+    println!("{}", "✅ 双重验证通过，解锁成功！".bright_green());
+    if std::env::var("SOL_SAFEKEY_CLI_SHOW_SECRETS")
+        .ok()
+        .as_deref()
+        == Some("true")
+    {
+        println!("{} 私钥: {}", "🔑".bright_cyan(), private_key);
+    } else {
+        println!(
+            "{} 私钥: {}",
+            "🔑".bright_cyan(),
+            "[已隐藏；如确需显示，请设置 SOL_SAFEKEY_CLI_SHOW_SECRETS=true]".yellow()
+        );
+    }
+    println!("{} 公钥: {}", "🆔".bright_cyan(), pubkey);
+    println!();
+    println!("{}", "🔒 安全确认:".bright_blue().bold());
+    println!("  • 主密码与安全问题验证通过（解密成功）");
+    println!("  • 2FA验证码验证通过，且每个验证码只能使用一次");
+    println!("  • 连续失败会暂时锁定钱包");
+    Ok(())
 }
 
 #[cfg(test)]
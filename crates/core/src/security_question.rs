@@ -1,5 +1,22 @@
+//! 三因子钱包的安全问题。
+//!
+//! 旧版钱包只有一个内置问题（`question_index`），答案去空格、转小写后直接参与密钥派生。
+//! 新钱包使用 [`SecurityQuestionSet`]：支持自定义问题与“M 个问题中答对 N 个”，答案先经
+//! [`normalize_answer`]（NFKC、转小写、忽略空白与标点）归一化，再与主密码一起解出随机的
+//! 答案密钥，由答案密钥代替答案参与三因子密钥派生。
+
+use aes_gcm::{
+    aead::{rand_core::RngCore, Aead, AeadCore, KeyInit, OsRng},
+    Aes256Gcm,
+};
+use base64::{engine::general_purpose, Engine};
 use colored::*;
+use ring::digest;
+use serde::{Deserialize, Serialize};
 use std::io::{self, Write};
+use std::num::NonZeroU32;
+use unicode_normalization::UnicodeNormalization;
+use zeroize::Zeroizing;
 
 /// 预定义的安全问题列表
 pub const SECURITY_QUESTIONS: &[&str] = &[
@@ -13,6 +30,288 @@ pub const SECURITY_QUESTIONS: &[&str] = &[
     "您最好朋友的名字是？",
 ];
 
+/// 预定义安全问题的英文版本，与 [`SECURITY_QUESTIONS`] 一一对应
+pub const SECURITY_QUESTIONS_EN: &[&str] = &[
+    "What is your mother's name?",
+    "In which city were you born?",
+    "What is the name of your primary school?",
+    "What is your favorite movie?",
+    "What was the name of your first pet?",
+    "What is your father's birthday? (YYYYMMDD)",
+    "What is your spouse's name?",
+    "What is your best friend's name?",
+];
+
+/// 当前的安全问题组格式版本
+pub const SECURITY_QUESTION_SET_VERSION: u32 = 1;
+/// 一个问题组最多包含的问题数
+pub const MAX_SECURITY_QUESTIONS: usize = 5;
+/// 自定义问题的最大字符数
+pub const MAX_CUSTOM_QUESTION_CHARS: usize = 200;
+
+const ANSWER_PBKDF2_ITERATIONS: u32 = 100_000;
+
+/// 按语言取内置问题：`en` 开头为英文，其余为中文
+pub fn builtin_question(index: usize, locale: &str) -> Option<&'static str> {
+    if locale.starts_with("en") {
+        SECURITY_QUESTIONS_EN.get(index).copied()
+    } else {
+        SECURITY_QUESTIONS.get(index).copied()
+    }
+}
+
+/// 归一化答案：NFKC 后转小写，并忽略空白与标点
+///
+/// 全角字符、多余空格以及 `1990-01-02` 与 `19900102` 这类写法差异不会导致答案不一致。
+pub fn normalize_answer(answer: &str) -> String {
+    answer
+        .nfkc()
+        .flat_map(char::to_lowercase)
+        .filter(|c| !c.is_whitespace() && !is_punctuation(*c))
+        .collect()
+}
+
+fn is_punctuation(c: char) -> bool {
+    c.is_ascii_punctuation()
+        || matches!(
+            c,
+            '\u{2010}'..='\u{2027}'
+                | '\u{2030}'..='\u{205E}'
+                | '\u{3001}'..='\u{3003}'
+                | '\u{3008}'..='\u{3011}'
+                | '\u{3014}'..='\u{301F}'
+                | '\u{30FB}'
+                | '\u{FF61}'..='\u{FF65}'
+        )
+}
+
+/// 问题组中的一个问题
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum QuestionPrompt {
+    /// [`SECURITY_QUESTIONS`] 中的内置问题
+    Builtin { index: usize },
+    /// 用户自定义的问题
+    Custom { text: String },
+}
+
+impl QuestionPrompt {
+    /// 按语言显示的问题文本
+    pub fn text(&self, locale: &str) -> String {
+        match self {
+            Self::Builtin { index } => builtin_question(*index, locale).unwrap_or("?").to_string(),
+            Self::Custom { text } => text.clone(),
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        match self {
+            Self::Builtin { index } if *index >= SECURITY_QUESTIONS.len() => {
+                Err(format!("无效的问题索引: {}", index))
+            }
+            Self::Custom { text } if text.trim().is_empty() => {
+                Err("自定义问题不能为空".to_string())
+            }
+            Self::Custom { text } if text.chars().count() > MAX_CUSTOM_QUESTION_CHARS => Err(
+                format!("自定义问题不能超过 {} 个字符", MAX_CUSTOM_QUESTION_CHARS),
+            ),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// 保存在钱包文件中的安全问题组
+///
+/// 随机答案密钥按每种 `threshold` 个问题的组合分别加密；包装密钥由主密码和归一化答案派生，
+/// 因此离线猜测答案同样需要主密码。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SecurityQuestionSet {
+    pub version: u32,
+    /// 派生包装密钥使用的随机盐（hex）
+    pub salt: String,
+    /// 至少需要答对的问题数
+    pub threshold: usize,
+    pub questions: Vec<QuestionPrompt>,
+    pub wrapped_keys: Vec<WrappedAnswerKey>,
+}
+
+/// 用一组答案加密的答案密钥
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WrappedAnswerKey {
+    /// `questions` 中的下标
+    pub questions: Vec<usize>,
+    /// base64(nonce || AES-256-GCM 密文)
+    pub ciphertext: String,
+}
+
+impl SecurityQuestionSet {
+    /// 创建问题组，返回问题组和代替答案参与三因子密钥派生的答案密钥
+    pub fn create(
+        questions: Vec<QuestionPrompt>,
+        answers: &[String],
+        threshold: usize,
+        master_password: &str,
+    ) -> Result<(Self, Zeroizing<String>), String> {
+        if questions.is_empty() || questions.len() > MAX_SECURITY_QUESTIONS {
+            return Err(format!(
+                "安全问题数量必须在 1 到 {} 之间",
+                MAX_SECURITY_QUESTIONS
+            ));
+        }
+        if answers.len() != questions.len() {
+            return Err("答案数量与问题数量不一致".to_string());
+        }
+        if threshold == 0 || threshold > questions.len() {
+            return Err(format!(
+                "需要答对的问题数必须在 1 到 {} 之间",
+                questions.len()
+            ));
+        }
+        for question in &questions {
+            question.validate()?;
+        }
+        if answers
+            .iter()
+            .any(|answer| normalize_answer(answer).is_empty())
+        {
+            return Err("答案不能为空（空白和标点不计入答案）".to_string());
+        }
+
+        let mut salt = [0u8; 16];
+        let mut answer_key = Zeroizing::new([0u8; 32]);
+        OsRng.fill_bytes(&mut salt);
+        OsRng.fill_bytes(answer_key.as_mut());
+        let salt = hex::encode(salt);
+        let answer_key = Zeroizing::new(hex::encode(answer_key.as_ref()));
+
+        let stretched: Vec<_> = answers
+            .iter()
+            .enumerate()
+            .map(|(index, answer)| Some(stretch_answer(&salt, master_password, index, answer)))
+            .collect();
+        let mut wrapped_keys = Vec::new();
+        for subset in crate::hardware_fingerprint::combinations(questions.len(), threshold) {
+            let key = wrapping_key(&salt, &subset, &stretched).ok_or("生成包装密钥失败")?;
+            wrapped_keys.push(WrappedAnswerKey {
+                ciphertext: seal_answer_key(&answer_key, &key)?,
+                questions: subset,
+            });
+        }
+
+        let set = Self {
+            version: SECURITY_QUESTION_SET_VERSION,
+            salt,
+            threshold,
+            questions,
+            wrapped_keys,
+        };
+        Ok((set, answer_key))
+    }
+
+    /// 用答案解出答案密钥；`answers[i]` 为 `None` 表示跳过第 `i` 个问题
+    pub fn unlock(
+        &self,
+        answers: &[Option<String>],
+        master_password: &str,
+    ) -> Result<Zeroizing<String>, String> {
+        if self.version != SECURITY_QUESTION_SET_VERSION {
+            return Err(format!("不支持的安全问题组版本: {}", self.version));
+        }
+        let stretched: Vec<_> = (0..self.questions.len())
+            .map(|index| {
+                answers
+                    .get(index)
+                    .and_then(Option::as_deref)
+                    .filter(|answer| !normalize_answer(answer).is_empty())
+                    .map(|answer| stretch_answer(&self.salt, master_password, index, answer))
+            })
+            .collect();
+        let answered = stretched.iter().flatten().count();
+        if answered < self.threshold {
+            return Err(format!(
+                "至少需要回答 {} 个安全问题（已回答 {} 个）",
+                self.threshold, answered
+            ));
+        }
+
+        self.wrapped_keys
+            .iter()
+            .find_map(|wrapped| {
+                let key = wrapping_key(&self.salt, &wrapped.questions, &stretched)?;
+                open_answer_key(&wrapped.ciphertext, &key)
+            })
+            .ok_or_else(|| "安全问题答案错误".to_string())
+    }
+}
+
+/// 单个答案经 PBKDF2 拉伸后的值；主密码参与派生
+fn stretch_answer(
+    salt: &str,
+    master_password: &str,
+    index: usize,
+    answer: &str,
+) -> Zeroizing<[u8; 32]> {
+    use ring::pbkdf2;
+
+    let material = Zeroizing::new(format!(
+        "fnzero-qa|{}|{}|{}",
+        index,
+        master_password,
+        normalize_answer(answer)
+    ));
+    let mut stretched = Zeroizing::new([0u8; 32]);
+    pbkdf2::derive(
+        pbkdf2::PBKDF2_HMAC_SHA256,
+        NonZeroU32::new(ANSWER_PBKDF2_ITERATIONS).unwrap(),
+        salt.as_bytes(),
+        material.as_bytes(),
+        stretched.as_mut(),
+    );
+    stretched
+}
+
+/// 组合 `subset` 的包装密钥；其中有未回答的问题时为 `None`
+fn wrapping_key(
+    salt: &str,
+    subset: &[usize],
+    stretched: &[Option<Zeroizing<[u8; 32]>>],
+) -> Option<Zeroizing<[u8; 32]>> {
+    let mut context = digest::Context::new(&digest::SHA256);
+    context.update(format!("fnzero-qa-wrap|{}", salt).as_bytes());
+    for &index in subset {
+        context.update(format!("|{}:", index).as_bytes());
+        context.update(stretched.get(index)?.as_ref()?.as_ref());
+    }
+    let mut key = Zeroizing::new([0u8; 32]);
+    key.copy_from_slice(context.finish().as_ref());
+    Some(key)
+}
+
+fn seal_answer_key(answer_key: &str, key: &[u8; 32]) -> Result<String, String> {
+    let cipher =
+        Aes256Gcm::new_from_slice(key).map_err(|_| "AES-256-GCM 初始化失败".to_string())?;
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, answer_key.as_bytes())
+        .map_err(|_| "加密答案密钥失败".to_string())?;
+    let mut sealed = nonce.to_vec();
+    sealed.extend_from_slice(&ciphertext);
+    Ok(general_purpose::STANDARD.encode(sealed))
+}
+
+fn open_answer_key(sealed: &str, key: &[u8; 32]) -> Option<Zeroizing<String>> {
+    let sealed = general_purpose::STANDARD.decode(sealed).ok()?;
+    if sealed.len() < 12 {
+        return None;
+    }
+    let (nonce, ciphertext) = sealed.split_at(12);
+    let cipher = Aes256Gcm::new_from_slice(key).ok()?;
+    let plaintext = cipher
+        .decrypt(aes_gcm::Nonce::from_slice(nonce), ciphertext)
+        .ok()?;
+    String::from_utf8(plaintext).ok().map(Zeroizing::new)
+}
+
 /// 安全问题和答案
 #[derive(Debug, Clone)]
 pub struct SecurityQuestion {
@@ -81,6 +380,147 @@ impl SecurityQuestion {
         Ok((question_index, answer))
     }
 
+    /// 交互式设置问题组：可选内置或自定义问题，并设置需要答对的数量
+    pub fn setup_set_interactive(
+        master_password: &str,
+    ) -> Result<(SecurityQuestionSet, Zeroizing<String>), String> {
+        println!();
+        println!(
+            "{}",
+            format!(
+                "请设置安全问题（1-{} 个，解锁时答对指定数量即可）",
+                MAX_SECURITY_QUESTIONS
+            )
+            .bright_cyan()
+            .bold()
+        );
+        let count = loop {
+            let input = read_line("问题数量 [1]:")?;
+            match input.parse::<usize>() {
+                _ if input.is_empty() => break 1,
+                Ok(n) if (1..=MAX_SECURITY_QUESTIONS).contains(&n) => break n,
+                _ => println!("{}", "❌ 无效的数量，请重新输入".red()),
+            }
+        };
+
+        let mut questions = Vec::with_capacity(count);
+        let mut answers = Vec::with_capacity(count);
+        for number in 1..=count {
+            println!();
+            for (i, question) in SECURITY_QUESTIONS.iter().enumerate() {
+                println!("  {}. {}", i + 1, question);
+            }
+            println!("  0. 自定义问题");
+            let question = loop {
+                let input = read_line(&format!(
+                    "请选择第 {} 个问题 (0-{}):",
+                    number,
+                    SECURITY_QUESTIONS.len()
+                ))?;
+                let question = match input.parse::<usize>() {
+                    Ok(0) => QuestionPrompt::Custom {
+                        text: read_line("请输入自定义问题:")?,
+                    },
+                    Ok(n) => QuestionPrompt::Builtin { index: n - 1 },
+                    Err(_) => {
+                        println!("{}", "❌ 无效的编号，请重新输入".red());
+                        continue;
+                    }
+                };
+                if questions.contains(&question) {
+                    println!("{}", "❌ 该问题已选择，请选择其他问题".red());
+                    continue;
+                }
+                match question.validate() {
+                    Ok(()) => break question,
+                    Err(e) => println!("{} {}", "❌".red(), e),
+                }
+            };
+            println!("问题: {}", question.text("zh").bright_green());
+            let answer = loop {
+                let answer = read_line("请输入答案:")?;
+                if normalize_answer(&answer).is_empty() {
+                    println!("{}", "❌ 答案不能为空".red());
+                    continue;
+                }
+                break answer;
+            };
+            questions.push(question);
+            answers.push(answer);
+        }
+
+        let threshold = if count == 1 {
+            1
+        } else {
+            println!();
+            loop {
+                let input = read_line(&format!("解锁时需要答对的问题数 (1-{}):", count))?;
+                match input.parse::<usize>() {
+                    Ok(n) if (1..=count).contains(&n) => break n,
+                    _ => println!("{}", "❌ 无效的数量，请重新输入".red()),
+                }
+            }
+        };
+
+        let result = SecurityQuestionSet::create(questions, &answers, threshold, master_password)?;
+        println!(
+            "{}",
+            format!(
+                "✅ 安全问题已设置（{} 个问题中答对 {} 个即可）",
+                count, threshold
+            )
+            .bright_green()
+        );
+        println!("   答案忽略大小写、空白与标点");
+        Ok(result)
+    }
+
+    /// 交互式回答问题组，返回答案密钥；直接回车可跳过问题
+    pub fn answer_set_interactive(
+        set: &SecurityQuestionSet,
+        master_password: &str,
+    ) -> Result<Zeroizing<String>, String> {
+        println!();
+        println!("{}", "安全问题验证".bright_cyan().bold());
+        if set.threshold < set.questions.len() {
+            println!(
+                "共 {} 个问题，答对其中 {} 个即可；不记得的问题直接回车跳过",
+                set.questions.len(),
+                set.threshold
+            );
+        }
+        // 每个问题都询问一次，某个答案输错时其余答案仍可凑足门限
+        let mut answers: Vec<Option<String>> = vec![None; set.questions.len()];
+        for (index, question) in set.questions.iter().enumerate() {
+            println!(
+                "问题 {}: {}",
+                index + 1,
+                question.text("zh").bright_yellow()
+            );
+            let answer = read_line("请输入答案:")?;
+            if !normalize_answer(&answer).is_empty() {
+                answers[index] = Some(answer);
+            }
+        }
+        set.unlock(&answers, master_password)
+    }
+
+    /// 按 CLI 钱包文件的设置回答安全问题，返回参与三因子密钥派生的值
+    ///
+    /// 有 `security_questions` 时为问题组的答案密钥，否则为 `question_index` 对应旧版问题的答案。
+    pub fn answer_wallet_interactive(
+        wallet: &serde_json::Value,
+        master_password: &str,
+    ) -> Result<Zeroizing<String>, String> {
+        if wallet["security_questions"].is_null() {
+            let question_index = wallet["question_index"].as_u64().unwrap_or(0) as usize;
+            return Self::verify_interactive(question_index).map(Zeroizing::new);
+        }
+        let set: SecurityQuestionSet = serde_json::from_value(wallet["security_questions"].clone())
+            .map_err(|e| format!("安全问题数据无效: {}", e))?;
+        Self::answer_set_interactive(&set, master_password)
+    }
+
     /// 验证答案
     pub fn verify_interactive(question_index: usize) -> Result<String, String> {
         if question_index >= SECURITY_QUESTIONS.len() {
@@ -117,8 +557,7 @@ impl SecurityQuestion {
 
     /// 生成答案的哈希值（用于验证）
     pub fn hash_answer(answer: &str) -> String {
-        use ring::digest;
-        let normalized = answer.trim().to_lowercase();
+        let normalized = normalize_answer(answer);
         let hash = digest::digest(&digest::SHA256, normalized.as_bytes());
         hex::encode(hash.as_ref())
     }
@@ -129,6 +568,16 @@ impl SecurityQuestion {
     }
 }
 
+fn read_line(prompt: &str) -> Result<String, String> {
+    print!("{} ", prompt.bright_yellow());
+    io::stdout().flush().unwrap();
+    let mut input = String::new();
+    io::stdin()
+        .read_line(&mut input)
+        .map_err(|e| format!("读取输入失败: {}", e))?;
+    Ok(input.trim().to_string())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -156,4 +605,43 @@ mod tests {
         assert!(SecurityQuestion::verify_answer(" BEIJING ", &hash));
         assert!(!SecurityQuestion::verify_answer("Shanghai", &hash));
     }
+
+    #[test]
+    fn normalize_answer_folds_width_spacing_and_punctuation() {
+        assert_eq!(normalize_answer("  New   York "), "newyork");
+        assert_eq!(normalize_answer("Ｂｅｉｊｉｎｇ"), "beijing");
+        assert_eq!(normalize_answer("1990-01-02"), "19900102");
+        assert_eq!(normalize_answer("北京。"), "北京");
+        assert_eq!(normalize_answer(" ，。 "), "");
+    }
+
+    #[test]
+    fn question_set_unlocks_with_any_threshold_answers() {
+        let questions = vec![
+            QuestionPrompt::Builtin { index: 1 },
+            QuestionPrompt::Custom {
+                text: "First concert?".to_string(),
+            },
+            QuestionPrompt::Builtin { index: 4 },
+        ];
+        let answers = ["Beijing", "Blur, 1997", "Rex"].map(String::from);
+        let (set, key) = SecurityQuestionSet::create(questions, &answers, 2, "pw").unwrap();
+        assert_eq!(set.wrapped_keys.len(), 3);
+
+        let skip_first = [None, Some("blur 1997".to_string()), Some("REX".to_string())];
+        assert_eq!(set.unlock(&skip_first, "pw").unwrap(), key);
+        let spaced = [
+            Some(" bei jing ".to_string()),
+            None,
+            Some("rex!".to_string()),
+        ];
+        assert_eq!(set.unlock(&spaced, "pw").unwrap(), key);
+
+        assert!(set.unlock(&skip_first, "other password").is_err());
+        let one_wrong = [Some("Shanghai".to_string()), None, Some("Rex".to_string())];
+        assert!(set.unlock(&one_wrong, "pw").is_err());
+        assert!(set
+            .unlock(&[None, None, Some("Rex".to_string())], "pw")
+            .is_err());
+    }
 }
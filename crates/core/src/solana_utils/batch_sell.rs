@@ -0,0 +1,679 @@
+// Pump 批量卖出与粉尘清理
+// 按场所 / 价值 / 持仓时长筛选钱包持仓，并行卖出后关闭已清空的代币账户回收租金
+
+use futures::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use solana_account_decoder_client_types::UiAccountData;
+use solana_client::{
+    nonblocking::rpc_client::RpcClient, rpc_client::GetConfirmedSignaturesForAddress2Config,
+    rpc_request::TokenAccountsFilter,
+};
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    signer::Signer,
+    transaction::Transaction,
+};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use sol_trade_sdk::{
+    common::{fast_fn::get_associated_token_address_with_program_id_fast_use_seed, GasFeeStrategy},
+    constants::{TOKEN_PROGRAM, TOKEN_PROGRAM_2022, WSOL_TOKEN_ACCOUNT},
+    trading::{
+        core::params::{DexParamEnum, PumpFunParams, PumpSwapParams},
+        factory::DexType,
+    },
+    SolanaTrade, TradeSellParams, TradeTokenType,
+};
+
+use crate::solana_utils::position_watcher::{reserves_price, token_account_amount, PumpVenue};
+
+/// SPL Token `CloseAccount` 指令序号（Token-2022 相同）
+const CLOSE_ACCOUNT_INSTRUCTION: u8 = 9;
+const CLOSE_ACCOUNTS_PER_TX: usize = 20;
+pub const DEFAULT_BATCH_SELL_CONCURRENCY: usize = 4;
+pub const MAX_BATCH_SELL_CONCURRENCY: usize = 16;
+
+/// 钱包中的一个代币账户
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenHolding {
+    pub account: Pubkey,
+    pub mint: Pubkey,
+    pub token_program: Pubkey,
+    pub raw_amount: u64,
+    pub decimals: u8,
+}
+
+/// 批量卖出参数；筛选条件为空表示不限制
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BatchSellOptions {
+    /// 默认滑点（基点）
+    pub slippage: u64,
+    /// 按 mint 覆盖滑点
+    #[serde(default)]
+    pub slippage_overrides: HashMap<String, u64>,
+    #[serde(default)]
+    pub venues: Option<Vec<PumpVenue>>,
+    #[serde(default)]
+    pub min_value_sol: Option<f64>,
+    #[serde(default)]
+    pub max_value_sol: Option<f64>,
+    /// 距该代币账户最近一笔交易的最短时长
+    #[serde(default)]
+    pub min_age_secs: Option<u64>,
+    #[serde(default)]
+    pub exclude_mints: Vec<String>,
+    #[serde(default)]
+    pub max_concurrency: Option<usize>,
+    /// 卖出后关闭已清空的代币账户（包括原本就为空的账户）
+    #[serde(default)]
+    pub close_empty_accounts: bool,
+    /// 只生成计划，不发送交易
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+impl BatchSellOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        for mint in self.slippage_overrides.keys().chain(&self.exclude_mints) {
+            Pubkey::from_str(mint).map_err(|e| format!("无效的代币地址 {}: {}", mint, e))?;
+        }
+        for slippage in std::iter::once(&self.slippage).chain(self.slippage_overrides.values()) {
+            if *slippage > 10_000 {
+                return Err(format!("滑点不能超过 10000 基点: {}", slippage));
+            }
+        }
+        for value in [self.min_value_sol, self.max_value_sol]
+            .into_iter()
+            .flatten()
+        {
+            if !value.is_finite() || value < 0.0 {
+                return Err(format!("价值筛选无效: {}", value));
+            }
+        }
+        if let Some(concurrency) = self.max_concurrency {
+            if !(1..=MAX_BATCH_SELL_CONCURRENCY).contains(&concurrency) {
+                return Err(format!(
+                    "并发数必须在 1 到 {} 之间",
+                    MAX_BATCH_SELL_CONCURRENCY
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn slippage_for(&self, mint: &Pubkey) -> u64 {
+        self.slippage_overrides
+            .get(&mint.to_string())
+            .copied()
+            .unwrap_or(self.slippage)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchSellStatus {
+    /// dry run 下计划卖出
+    Planned,
+    Sold,
+    /// 被筛选条件排除或非 Pump 代币
+    Skipped,
+    Failed,
+    /// 余额为 0，只参与关户
+    Empty,
+}
+
+/// 单个代币账户的处理结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchSellItem {
+    pub mint: String,
+    pub account: String,
+    pub venue: Option<PumpVenue>,
+    pub raw_amount: String,
+    pub decimals: u8,
+    pub value_sol: Option<f64>,
+    pub age_secs: Option<u64>,
+    pub slippage: u64,
+    pub status: BatchSellStatus,
+    pub signature: Option<String>,
+    pub reason: Option<String>,
+    pub account_closed: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BatchSellReport {
+    pub items: Vec<BatchSellItem>,
+    pub sold: usize,
+    pub failed: usize,
+    pub closed_accounts: usize,
+    pub reclaimed_lamports: u64,
+    pub close_signatures: Vec<String>,
+    pub close_errors: Vec<String>,
+}
+
+struct SellCandidate {
+    holding: TokenHolding,
+    use_seed: bool,
+    item: BatchSellItem,
+    dex_type: Option<DexType>,
+    extension_params: Option<DexParamEnum>,
+}
+
+/// 读取钱包在 SPL Token 与 Token-2022 下的全部代币账户
+pub async fn fetch_token_holdings(
+    rpc: &RpcClient,
+    owner: &Pubkey,
+) -> Result<Vec<TokenHolding>, String> {
+    let mut holdings = Vec::new();
+    for program in [TOKEN_PROGRAM, TOKEN_PROGRAM_2022] {
+        let accounts = rpc
+            .get_token_accounts_by_owner(owner, TokenAccountsFilter::ProgramId(program))
+            .await
+            .map_err(|e| format!("查询 Token 账户失败: {}", e))?;
+        holdings.extend(accounts.into_iter().filter_map(|keyed| {
+            let UiAccountData::Json(parsed) = keyed.account.data else {
+                return None;
+            };
+            holding_from_parsed(&keyed.pubkey, &parsed.parsed, program)
+        }));
+    }
+    Ok(holdings)
+}
+
+/// 由 jsonParsed 格式的代币账户构造持仓
+pub fn holding_from_parsed(
+    account: &str,
+    parsed: &serde_json::Value,
+    token_program: Pubkey,
+) -> Option<TokenHolding> {
+    let info = parsed.get("info")?;
+    let amount = info.get("tokenAmount")?;
+    Some(TokenHolding {
+        account: Pubkey::from_str(account).ok()?,
+        mint: Pubkey::from_str(info.get("mint")?.as_str()?).ok()?,
+        token_program,
+        raw_amount: amount.get("amount")?.as_str()?.parse().ok()?,
+        decimals: amount.get("decimals")?.as_u64()?.try_into().ok()?,
+    })
+}
+
+/// 判断代币账户是普通 ATA 还是 seed 账户；两者都不是时返回 `None`
+fn holding_uses_seed(owner: &Pubkey, holding: &TokenHolding) -> Option<bool> {
+    [false, true].into_iter().find(|use_seed| {
+        get_associated_token_address_with_program_id_fast_use_seed(
+            owner,
+            &holding.mint,
+            &holding.token_program,
+            *use_seed,
+        ) == holding.account
+    })
+}
+
+fn now_unix_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+async fn account_age_secs(rpc: &RpcClient, account: &Pubkey) -> Option<u64> {
+    let signatures = rpc
+        .get_signatures_for_address_with_config(
+            account,
+            GetConfirmedSignaturesForAddress2Config {
+                limit: Some(1),
+                ..GetConfirmedSignaturesForAddress2Config::default()
+            },
+        )
+        .await
+        .ok()?;
+    let block_time = signatures.first()?.block_time?;
+    Some(now_unix_secs().saturating_sub(block_time.max(0) as u64))
+}
+
+/// 解析卖出路由并估算持仓价值（SOL）
+async fn route_and_value(
+    rpc: &RpcClient,
+    holding: &TokenHolding,
+) -> Result<(PumpVenue, DexType, DexParamEnum, f64), String> {
+    let amount = holding.raw_amount as f64 / 10f64.powi(holding.decimals as i32);
+    if let Ok(params) = PumpFunParams::from_mint_by_rpc(rpc, &holding.mint).await {
+        if !params.bonding_curve.complete {
+            let curve = &params.bonding_curve;
+            let price = if curve.virtual_token_reserves == 0 {
+                0.0
+            } else {
+                reserves_price(
+                    curve.virtual_sol_reserves,
+                    curve.virtual_token_reserves,
+                    holding.decimals,
+                )
+            };
+            return Ok((
+                PumpVenue::PumpFun,
+                DexType::PumpFun,
+                DexParamEnum::PumpFun(params.with_quote_mint(WSOL_TOKEN_ACCOUNT)),
+                amount * price,
+            ));
+        }
+    }
+    let params = PumpSwapParams::from_mint_by_rpc(rpc, &holding.mint)
+        .await
+        .map_err(|_| "不是 Pump.fun / PumpSwap 代币".to_string())?;
+    if params.base_mint != holding.mint || params.quote_mint != WSOL_TOKEN_ACCOUNT {
+        return Err("仅支持以 SOL 计价的 PumpSwap 池".to_string());
+    }
+    let price = if params.pool_base_token_reserves == 0 {
+        0.0
+    } else {
+        reserves_price(
+            params.pool_quote_token_reserves,
+            params.pool_base_token_reserves,
+            holding.decimals,
+        )
+    };
+    Ok((
+        PumpVenue::PumpSwap,
+        DexType::PumpSwap,
+        DexParamEnum::PumpSwap(params),
+        amount * price,
+    ))
+}
+
+async fn plan_holding(
+    rpc: &RpcClient,
+    owner: &Pubkey,
+    holding: TokenHolding,
+    options: &BatchSellOptions,
+) -> SellCandidate {
+    let mut item = BatchSellItem {
+        mint: holding.mint.to_string(),
+        account: holding.account.to_string(),
+        venue: None,
+        raw_amount: holding.raw_amount.to_string(),
+        decimals: holding.decimals,
+        value_sol: None,
+        age_secs: None,
+        slippage: options.slippage_for(&holding.mint),
+        status: BatchSellStatus::Skipped,
+        signature: None,
+        reason: None,
+        account_closed: false,
+    };
+    let mut candidate = SellCandidate {
+        use_seed: false,
+        holding,
+        item: item.clone(),
+        dex_type: None,
+        extension_params: None,
+    };
+    let holding = &candidate.holding;
+
+    let skip = |mut item: BatchSellItem, reason: &str| {
+        item.reason = Some(reason.to_string());
+        item
+    };
+    if holding.mint == WSOL_TOKEN_ACCOUNT {
+        candidate.item = skip(item, "WSOL 不参与批量卖出");
+        return candidate;
+    }
+    if options.exclude_mints.iter().any(|mint| mint == &item.mint) {
+        candidate.item = skip(item, "已排除");
+        return candidate;
+    }
+    let Some(use_seed) = holding_uses_seed(owner, holding) else {
+        candidate.item = skip(item, "不是 ATA 或 seed 代币账户");
+        return candidate;
+    };
+    candidate.use_seed = use_seed;
+    if holding.raw_amount == 0 {
+        item.status = BatchSellStatus::Empty;
+        candidate.item = item;
+        return candidate;
+    }
+
+    if let Some(min_age) = options.min_age_secs {
+        item.age_secs = account_age_secs(rpc, &holding.account).await;
+        if item.age_secs.is_some_and(|age| age < min_age) {
+            candidate.item = skip(item, "持仓时间不足");
+            return candidate;
+        }
+    }
+
+    let (venue, dex_type, extension_params, value_sol) = match route_and_value(rpc, holding).await {
+        Ok(route) => route,
+        Err(reason) => {
+            candidate.item = skip(item, &reason);
+            return candidate;
+        }
+    };
+    item.venue = Some(venue);
+    item.value_sol = Some(value_sol);
+    if options
+        .venues
+        .as_ref()
+        .is_some_and(|venues| !venues.contains(&venue))
+    {
+        candidate.item = skip(item, "交易场所不在筛选范围内");
+        return candidate;
+    }
+    if options.min_value_sol.is_some_and(|min| value_sol < min)
+        || options.max_value_sol.is_some_and(|max| value_sol > max)
+    {
+        candidate.item = skip(item, "价值不在筛选范围内");
+        return candidate;
+    }
+
+    item.status = BatchSellStatus::Planned;
+    candidate.item = item;
+    candidate.dex_type = Some(dex_type);
+    candidate.extension_params = Some(extension_params);
+    candidate
+}
+
+async fn sell_candidate(
+    clients: &[Arc<SolanaTrade>; 2],
+    mut candidate: SellCandidate,
+    tip_sol: f64,
+) -> SellCandidate {
+    let (Some(dex_type), Some(extension_params)) =
+        (candidate.dex_type, candidate.extension_params.take())
+    else {
+        return candidate;
+    };
+    let client = &clients[candidate.use_seed as usize];
+    let result = async {
+        let recent_blockhash = client
+            .infrastructure
+            .rpc
+            .get_latest_blockhash()
+            .await
+            .map_err(|e| format!("获取 blockhash 失败: {}", e))?;
+        let gas_fee_strategy = GasFeeStrategy::new();
+        gas_fee_strategy.set_global_fee_strategy(150000, 150000, 500000, 500000, tip_sol, tip_sol);
+        let is_pumpswap = dex_type == DexType::PumpSwap;
+        let params = TradeSellParams {
+            dex_type,
+            output_token_type: if is_pumpswap {
+                TradeTokenType::WSOL
+            } else {
+                TradeTokenType::SOL
+            },
+            mint: candidate.holding.mint,
+            input_token_amount: candidate.holding.raw_amount,
+            slippage_basis_points: Some(candidate.item.slippage),
+            recent_blockhash: Some(recent_blockhash),
+            with_tip: true,
+            extension_params,
+            address_lookup_table_account: None,
+            wait_tx_confirmed: true,
+            create_output_token_ata: is_pumpswap,
+            close_output_token_ata: false,
+            close_mint_token_ata: false,
+            durable_nonce: None,
+            fixed_output_token_amount: None,
+            gas_fee_strategy,
+            simulate: false,
+            wait_for_all_submits: false,
+            grpc_recv_us: None,
+        };
+        match client.sell(params).await {
+            Ok((true, signatures, _, _)) => Ok(signatures.first().map(|sig| sig.to_string())),
+            Ok((false, _, error, _)) => Err(error
+                .map(|e| e.to_string())
+                .unwrap_or_else(|| "Unknown error".to_string())),
+            Err(e) => Err(e.to_string()),
+        }
+    }
+    .await;
+
+    match result {
+        Ok(signature) => {
+            candidate.item.status = BatchSellStatus::Sold;
+            candidate.item.signature = signature;
+        }
+        Err(reason) => {
+            candidate.item.status = BatchSellStatus::Failed;
+            candidate.item.reason = Some(format!("卖出失败: {}", reason));
+        }
+    }
+    candidate
+}
+
+fn close_account_instruction(holding: &TokenHolding, owner: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: holding.token_program,
+        accounts: vec![
+            AccountMeta::new(holding.account, false),
+            AccountMeta::new(*owner, false),
+            AccountMeta::new_readonly(*owner, true),
+        ],
+        data: vec![CLOSE_ACCOUNT_INSTRUCTION],
+    }
+}
+
+/// 关闭余额已为 0 的代币账户；返回（已关闭账户, 回收 lamports, 签名, 错误）
+async fn close_empty_accounts(
+    client: &SolanaTrade,
+    candidates: &[&TokenHolding],
+) -> (Vec<Pubkey>, u64, Vec<String>, Vec<String>) {
+    let rpc = &client.infrastructure.rpc;
+    let owner = client.payer.pubkey();
+    let mut closed = Vec::new();
+    let mut reclaimed = 0u64;
+    let mut signatures = Vec::new();
+    let mut errors = Vec::new();
+
+    for chunk in candidates.chunks(CLOSE_ACCOUNTS_PER_TX) {
+        let keys = chunk
+            .iter()
+            .map(|holding| holding.account)
+            .collect::<Vec<_>>();
+        let accounts = match rpc.get_multiple_accounts(&keys).await {
+            Ok(accounts) => accounts,
+            Err(e) => {
+                errors.push(format!("读取待关闭账户失败: {}", e));
+                continue;
+            }
+        };
+        let mut instructions = Vec::new();
+        let mut chunk_closed = Vec::new();
+        let mut chunk_lamports = 0u64;
+        for (holding, account) in chunk.iter().zip(accounts) {
+            let Some(account) = account else {
+                continue;
+            };
+            if token_account_amount(&account.data) != Some(0) {
+                errors.push(format!("{} 仍有余额，未关闭", holding.account));
+                continue;
+            }
+            instructions.push(close_account_instruction(holding, &owner));
+            chunk_closed.push(holding.account);
+            chunk_lamports = chunk_lamports.saturating_add(account.lamports);
+        }
+        if instructions.is_empty() {
+            continue;
+        }
+        let result = async {
+            let blockhash = rpc.get_latest_blockhash().await?;
+            let transaction = Transaction::new_signed_with_payer(
+                &instructions,
+                Some(&owner),
+                &[client.payer.as_ref()],
+                blockhash,
+            );
+            rpc.send_and_confirm_transaction(&transaction).await
+        }
+        .await;
+        match result {
+            Ok(signature) => {
+                signatures.push(signature.to_string());
+                closed.extend(chunk_closed);
+                reclaimed = reclaimed.saturating_add(chunk_lamports);
+            }
+            Err(e) => errors.push(format!("关闭代币账户失败: {}", e)),
+        }
+    }
+    (closed, reclaimed, signatures, errors)
+}
+
+/// 批量卖出钱包持仓并清理空账户
+///
+/// `client` 决定发送通道（CLI 的 RPC 客户端或桌面端按 SWQoS 设置构建的客户端）；
+/// seed 账户会基于同一基础设施派生对应客户端。
+pub async fn batch_sell(
+    client: &SolanaTrade,
+    holdings: Vec<TokenHolding>,
+    options: &BatchSellOptions,
+    tip_sol: f64,
+) -> Result<BatchSellReport, String> {
+    options.validate()?;
+    let owner = client.payer.pubkey();
+    let rpc = &client.infrastructure.rpc;
+    let concurrency = options
+        .max_concurrency
+        .unwrap_or(DEFAULT_BATCH_SELL_CONCURRENCY);
+
+    let planned = stream::iter(holdings)
+        .map(|holding| plan_holding(rpc, &owner, holding, options))
+        .buffered(concurrency)
+        .collect::<Vec<_>>()
+        .await;
+    if options.dry_run {
+        return Ok(build_report(planned, Vec::new(), 0, Vec::new(), Vec::new()));
+    }
+
+    let derive = |use_seed: bool| {
+        let mut derived = SolanaTrade::from_infrastructure(
+            client.payer.clone(),
+            client.infrastructure.clone(),
+            use_seed,
+        );
+        derived.max_sender_concurrency = client.max_sender_concurrency;
+        derived.log_enabled = false;
+        derived.check_min_tip = false;
+        Arc::new(derived)
+    };
+    let clients = [derive(false), derive(true)];
+    let results = stream::iter(planned)
+        .map(|candidate| sell_candidate(&clients, candidate, tip_sol))
+        .buffered(concurrency)
+        .collect::<Vec<_>>()
+        .await;
+
+    let (closed, reclaimed, signatures, errors) = if options.close_empty_accounts {
+        let closable = results
+            .iter()
+            .filter(|candidate| {
+                matches!(
+                    candidate.item.status,
+                    BatchSellStatus::Sold | BatchSellStatus::Empty
+                )
+            })
+            .map(|candidate| &candidate.holding)
+            .collect::<Vec<_>>();
+        close_empty_accounts(client, &closable).await
+    } else {
+        Default::default()
+    };
+    Ok(build_report(results, closed, reclaimed, signatures, errors))
+}
+
+fn build_report(
+    candidates: Vec<SellCandidate>,
+    closed: Vec<Pubkey>,
+    reclaimed_lamports: u64,
+    close_signatures: Vec<String>,
+    close_errors: Vec<String>,
+) -> BatchSellReport {
+    let items = candidates
+        .into_iter()
+        .map(|mut candidate| {
+            candidate.item.account_closed = closed.contains(&candidate.holding.account);
+            candidate.item
+        })
+        .collect::<Vec<_>>();
+    BatchSellReport {
+        sold: items
+            .iter()
+            .filter(|item| item.status == BatchSellStatus::Sold)
+            .count(),
+        failed: items
+            .iter()
+            .filter(|item| item.status == BatchSellStatus::Failed)
+            .count(),
+        closed_accounts: closed.len(),
+        reclaimed_lamports,
+        close_signatures,
+        close_errors,
+        items,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_json_token_account() {
+        let mint = Pubkey::new_unique();
+        let account = Pubkey::new_unique();
+        let parsed = serde_json::json!({
+            "info": {
+                "mint": mint.to_string(),
+                "tokenAmount": {"amount": "1500000", "decimals": 6, "uiAmountString": "1.5"}
+            },
+            "type": "account"
+        });
+        let holding = holding_from_parsed(&account.to_string(), &parsed, TOKEN_PROGRAM).unwrap();
+        assert_eq!(holding.mint, mint);
+        assert_eq!(holding.raw_amount, 1_500_000);
+        assert_eq!(holding.decimals, 6);
+        assert!(holding_from_parsed("bad", &parsed, TOKEN_PROGRAM).is_none());
+    }
+
+    #[test]
+    fn classifies_ata_and_seed_accounts() {
+        let owner = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let holding = |use_seed| TokenHolding {
+            account: get_associated_token_address_with_program_id_fast_use_seed(
+                &owner,
+                &mint,
+                &TOKEN_PROGRAM,
+                use_seed,
+            ),
+            mint,
+            token_program: TOKEN_PROGRAM,
+            raw_amount: 1,
+            decimals: 6,
+        };
+        assert_eq!(holding_uses_seed(&owner, &holding(false)), Some(false));
+        assert_eq!(holding_uses_seed(&owner, &holding(true)), Some(true));
+        let mut foreign = holding(false);
+        foreign.account = Pubkey::new_unique();
+        assert_eq!(holding_uses_seed(&owner, &foreign), None);
+    }
+
+    #[test]
+    fn validates_options_and_slippage_overrides() {
+        let mint = Pubkey::new_unique();
+        let mut options = BatchSellOptions {
+            slippage: 500,
+            ..BatchSellOptions::default()
+        };
+        options.slippage_overrides.insert(mint.to_string(), 2000);
+        options.validate().unwrap();
+        assert_eq!(options.slippage_for(&mint), 2000);
+        assert_eq!(options.slippage_for(&Pubkey::new_unique()), 500);
+
+        options.max_concurrency = Some(0);
+        assert!(options.validate().is_err());
+        options.max_concurrency = None;
+        options.slippage_overrides.insert("bad".to_string(), 100);
+        assert!(options.validate().is_err());
+    }
+}
@@ -6,7 +6,10 @@ use std::str::FromStr;
 
 #[cfg(feature = "solana-ops")]
 use crate::solana_utils::solana_ops::SolanaClientSdk;
-use crate::solana_utils::solana_ops::{format_token_amount, lamports_to_sol, SolanaClient};
+use crate::solana_utils::solana_ops::{
+    format_token_amount, lamports_to_sol, normalize_memo, SolanaClient,
+};
+use crate::solana_utils::stake::{StakeAction, StakeAuthority};
 use crate::KeyManager;
 
 #[derive(Parser)]
@@ -58,6 +61,10 @@ pub enum SolanaOpsCommand {
         /// RPC URL (defaults to mainnet)
         #[arg(short, long, default_value = "https://api.mainnet-beta.solana.com")]
         rpc_url: String,
+
+        /// SPL Memo to attach (e.g. an exchange deposit tag)
+        #[arg(long)]
+        memo: Option<String>,
     },
 
     /// Transfer SPL tokens
@@ -77,6 +84,10 @@ pub enum SolanaOpsCommand {
         /// RPC URL (defaults to mainnet)
         #[arg(short, long, default_value = "https://api.mainnet-beta.solana.com")]
         rpc_url: String,
+
+        /// SPL Memo to attach (e.g. an exchange deposit tag)
+        #[arg(long)]
+        memo: Option<String>,
     },
 
     /// Wrap SOL to WSOL
@@ -132,6 +143,70 @@ pub enum SolanaOpsCommand {
         slippage: u64,
     },
 
+    /// Pump.fun buy with SOL/WSOL (routes to PumpSwap once the bonding curve is complete)
+    #[command(name = "pumpfun-buy")]
+    PumpFunBuy {
+        /// Token mint address to buy
+        #[arg(short, long)]
+        mint: String,
+
+        /// Amount of SOL/WSOL to spend
+        #[arg(short, long)]
+        amount: f64,
+
+        /// Funding token: sol or wsol
+        #[arg(short, long, default_value = "sol")]
+        input: String,
+
+        /// RPC URL (defaults to mainnet)
+        #[arg(short, long, default_value = "https://api.mainnet-beta.solana.com")]
+        rpc_url: String,
+
+        /// Slippage tolerance in basis points (e.g., 100 = 1%)
+        #[arg(short, long, default_value = "500")]
+        slippage: u64,
+
+        /// Do not create the token ATA in the buy transaction
+        #[arg(long)]
+        no_create_ata: bool,
+
+        /// Skip the confirmation prompt
+        #[arg(short, long)]
+        yes: bool,
+    },
+
+    /// PumpSwap buy with SOL/WSOL
+    #[command(name = "pumpswap-buy")]
+    PumpSwapBuy {
+        /// Token mint address to buy
+        #[arg(short, long)]
+        mint: String,
+
+        /// Amount of SOL/WSOL to spend
+        #[arg(short, long)]
+        amount: f64,
+
+        /// Funding token: sol or wsol
+        #[arg(short, long, default_value = "sol")]
+        input: String,
+
+        /// RPC URL (defaults to mainnet)
+        #[arg(short, long, default_value = "https://api.mainnet-beta.solana.com")]
+        rpc_url: String,
+
+        /// Slippage tolerance in basis points (e.g., 100 = 1%)
+        #[arg(short, long, default_value = "500")]
+        slippage: u64,
+
+        /// Do not create the token ATA in the buy transaction
+        #[arg(long)]
+        no_create_ata: bool,
+
+        /// Skip the confirmation prompt
+        #[arg(short, long)]
+        yes: bool,
+    },
+
     /// View and claim Pump (Pump.fun) cashback (native SOL)
     #[command(name = "pumpfun-cashback")]
     PumpFunCashback {
@@ -147,6 +222,383 @@ pub enum SolanaOpsCommand {
         #[arg(short, long, default_value = "https://api.mainnet-beta.solana.com")]
         rpc_url: String,
     },
+
+    /// Batch sell Pump.fun / PumpSwap holdings and close emptied token accounts
+    #[command(name = "pump-batch-sell")]
+    PumpBatchSell {
+        /// RPC URL (defaults to mainnet)
+        #[arg(short, long, default_value = "https://api.mainnet-beta.solana.com")]
+        rpc_url: String,
+
+        /// Default slippage tolerance in basis points (e.g., 100 = 1%)
+        #[arg(short, long, default_value = "500")]
+        slippage: u64,
+
+        /// Per-mint slippage override as MINT=BPS (repeatable)
+        #[arg(long = "mint-slippage")]
+        mint_slippage: Vec<String>,
+
+        /// Only sell on this venue: pump_fun or pump_swap (repeatable)
+        #[arg(long)]
+        venue: Vec<String>,
+
+        /// Minimum position value in SOL
+        #[arg(long)]
+        min_value: Option<f64>,
+
+        /// Maximum position value in SOL (e.g. 0.01 to sweep dust)
+        #[arg(long)]
+        max_value: Option<f64>,
+
+        /// Minimum seconds since the token account's last transaction
+        #[arg(long)]
+        min_age_secs: Option<u64>,
+
+        /// Mint to leave untouched (repeatable)
+        #[arg(long)]
+        exclude: Vec<String>,
+
+        /// Number of sells sent in parallel
+        #[arg(long, default_value = "4")]
+        concurrency: usize,
+
+        /// Keep emptied token accounts open
+        #[arg(long)]
+        no_close: bool,
+
+        /// Print the plan without sending transactions
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Skip the confirmation prompt
+        #[arg(short, long)]
+        yes: bool,
+    },
+
+    /// Pay many recipients from a CSV or JSON list (recipient,asset,amount[,memo])
+    Payouts {
+        /// CSV or JSON payout list; asset is SOL or a token mint, amount is a decimal
+        file: String,
+
+        /// RPC URL (defaults to mainnet)
+        #[arg(short, long, default_value = "https://api.mainnet-beta.solana.com")]
+        rpc_url: String,
+
+        /// Progress file used to resume an interrupted run (defaults to <file>.progress.json)
+        #[arg(long)]
+        progress: Option<String>,
+
+        /// Print the preview without sending transactions
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Skip the confirmation prompt
+        #[arg(short, long)]
+        yes: bool,
+    },
+
+    /// List stake accounts where the wallet (or --address) is staker or withdrawer
+    StakeList {
+        /// RPC URL (defaults to mainnet)
+        #[arg(short, long, default_value = "https://api.mainnet-beta.solana.com")]
+        rpc_url: String,
+
+        /// Optional authority address (if not provided, will use encrypted keypair)
+        #[arg(short, long)]
+        address: Option<String>,
+    },
+
+    /// Create a stake account, optionally delegating it to a vote account
+    StakeCreate {
+        /// Amount in SOL, including the rent reserve
+        #[arg(short, long)]
+        amount: f64,
+
+        /// Vote account to delegate to
+        #[arg(long)]
+        vote: Option<String>,
+
+        /// Derive the stake account address from the wallet and this seed
+        #[arg(long)]
+        seed: Option<String>,
+
+        /// RPC URL (defaults to mainnet)
+        #[arg(short, long, default_value = "https://api.mainnet-beta.solana.com")]
+        rpc_url: String,
+
+        /// Skip the confirmation prompt
+        #[arg(short, long)]
+        yes: bool,
+    },
+
+    /// Delegate an undelegated or inactive stake account
+    StakeDelegate {
+        /// Stake account address
+        #[arg(short, long)]
+        stake: String,
+
+        /// Vote account to delegate to
+        #[arg(long)]
+        vote: String,
+
+        /// RPC URL (defaults to mainnet)
+        #[arg(short, long, default_value = "https://api.mainnet-beta.solana.com")]
+        rpc_url: String,
+
+        /// Skip the confirmation prompt
+        #[arg(short, long)]
+        yes: bool,
+    },
+
+    /// Deactivate a stake account; it can be withdrawn once cooldown completes
+    StakeDeactivate {
+        /// Stake account address
+        #[arg(short, long)]
+        stake: String,
+
+        /// RPC URL (defaults to mainnet)
+        #[arg(short, long, default_value = "https://api.mainnet-beta.solana.com")]
+        rpc_url: String,
+
+        /// Skip the confirmation prompt
+        #[arg(short, long)]
+        yes: bool,
+    },
+
+    /// Withdraw from a stake account
+    StakeWithdraw {
+        /// Stake account address
+        #[arg(short, long)]
+        stake: String,
+
+        /// Recipient address (defaults to the wallet)
+        #[arg(short, long)]
+        to: Option<String>,
+
+        /// Amount in SOL (defaults to everything withdrawable)
+        #[arg(short, long)]
+        amount: Option<f64>,
+
+        /// RPC URL (defaults to mainnet)
+        #[arg(short, long, default_value = "https://api.mainnet-beta.solana.com")]
+        rpc_url: String,
+
+        /// Skip the confirmation prompt
+        #[arg(short, long)]
+        yes: bool,
+    },
+
+    /// Split part of a stake account into a new stake account
+    StakeSplit {
+        /// Stake account address
+        #[arg(short, long)]
+        stake: String,
+
+        /// Amount in SOL to move into the new account
+        #[arg(short, long)]
+        amount: f64,
+
+        /// Derive the new account address from the wallet and this seed
+        #[arg(long)]
+        seed: Option<String>,
+
+        /// RPC URL (defaults to mainnet)
+        #[arg(short, long, default_value = "https://api.mainnet-beta.solana.com")]
+        rpc_url: String,
+
+        /// Skip the confirmation prompt
+        #[arg(short, long)]
+        yes: bool,
+    },
+
+    /// Merge the source stake account into the destination
+    StakeMerge {
+        /// Stake account that remains
+        #[arg(short, long)]
+        destination: String,
+
+        /// Stake account that is merged and closed
+        #[arg(short, long)]
+        source: String,
+
+        /// RPC URL (defaults to mainnet)
+        #[arg(short, long, default_value = "https://api.mainnet-beta.solana.com")]
+        rpc_url: String,
+
+        /// Skip the confirmation prompt
+        #[arg(short, long)]
+        yes: bool,
+    },
+
+    /// Hand the staker or withdrawer authority of a stake account to another address
+    StakeAuthorize {
+        /// Stake account address
+        #[arg(short, long)]
+        stake: String,
+
+        /// New authority address
+        #[arg(short, long)]
+        new_authority: String,
+
+        /// Authority to replace: staker or withdrawer
+        #[arg(long, default_value = "staker")]
+        authority: String,
+
+        /// RPC URL (defaults to mainnet)
+        #[arg(short, long, default_value = "https://api.mainnet-beta.solana.com")]
+        rpc_url: String,
+
+        /// Skip the confirmation prompt
+        #[arg(short, long)]
+        yes: bool,
+    },
+}
+
+/// Print the aggregated preview of a payout list
+fn print_payout_plan(plan: &crate::solana_utils::payouts::PayoutPlan) {
+    println!("\n{}", "Payout Preview:".green().bold());
+    println!("Batch: {}", plan.batch_id.yellow());
+    if plan.completed_lines > 0 {
+        println!(
+            "Already paid in earlier runs: {} line(s)",
+            plan.completed_lines
+        );
+    }
+    for total in &plan.totals {
+        let fee = if total.fee > 0 {
+            format!(
+                " (transfer fees {})",
+                format_token_amount(total.fee, total.decimals)
+            )
+        } else {
+            String::new()
+        };
+        println!(
+            "  {} → {} recipient(s): {}{} / balance {}",
+            total.asset.to_string().yellow(),
+            total.recipients,
+            format_token_amount(total.amount, total.decimals)
+                .to_string()
+                .green(),
+            fee,
+            format_token_amount(total.balance, total.decimals)
+        );
+    }
+    println!(
+        "New token accounts: {} ({} SOL rent)",
+        plan.accounts_to_create,
+        lamports_to_sol(plan.account_rent_lamports)
+    );
+    println!(
+        "Transactions: {} ({} SOL network fees)",
+        plan.transactions.len(),
+        lamports_to_sol(plan.network_fee_lamports)
+    );
+    for warning in &plan.warnings {
+        println!("{} {}", "⚠️".yellow(), warning.to_string().yellow());
+    }
+    for error in &plan.errors {
+        println!("{} {}", "❌".red(), error.to_string().red());
+    }
+}
+
+/// Plan a stake action, confirm it and send it with the wallet as authority and fee payer
+fn run_stake_action(
+    rpc_url: String,
+    encrypted_file: &str,
+    action: StakeAction,
+    yes: bool,
+) -> Result<()> {
+    let keypair = load_encrypted_keypair(encrypted_file)?;
+    let client = SolanaClient::new(rpc_url);
+
+    println!("\n{}", "🥩 Preparing stake transaction...".cyan());
+    let plan = client.plan_stake_action(&keypair.pubkey(), &action)?;
+    println!("Authority: {}", keypair.pubkey().to_string().yellow());
+    println!("{}", plan.description);
+    if plan.lamports > 0 {
+        println!(
+            "Amount: {} SOL ({} lamports)",
+            lamports_to_sol(plan.lamports),
+            plan.lamports
+        );
+    }
+    for warning in &plan.warnings {
+        println!("{} {}", "⚠️".yellow(), warning.to_string().yellow());
+    }
+    if !yes {
+        use std::io::{self, Write};
+        print!(
+            "\n{}",
+            "❓ Confirm stake transaction? (yes/no, default: no): ".yellow()
+        );
+        io::stdout().flush()?;
+        let mut confirm = String::new();
+        io::stdin().read_line(&mut confirm)?;
+        let confirm = confirm.trim().to_lowercase();
+        if confirm != "yes" && confirm != "y" {
+            println!("{}", "❌ Operation cancelled".red());
+            return Ok(());
+        }
+    }
+
+    println!("\n{}", "🚀 Sending transaction...".cyan());
+    let signature = client.send_stake_plan(&keypair, &plan)?;
+    println!("\n{}", "✅ Stake transaction confirmed!".green().bold());
+    if let Some(stake) = plan.new_stake_account {
+        println!("Stake account: {}", stake.to_string().yellow());
+    }
+    println!("Signature: {}", signature.to_string().yellow());
+    println!("Explorer: https://solscan.io/tx/{}", signature);
+    Ok(())
+}
+
+fn sol_amount_to_lamports(amount: f64) -> u64 {
+    (amount * solana_sdk::native_token::LAMPORTS_PER_SOL as f64) as u64
+}
+
+/// Print a batch sell plan or result
+#[cfg(feature = "sol-trade-sdk")]
+fn print_batch_sell_report(report: &crate::solana_utils::batch_sell::BatchSellReport) {
+    use crate::solana_utils::batch_sell::BatchSellStatus;
+
+    for item in &report.items {
+        let status = match item.status {
+            BatchSellStatus::Planned => "planned".cyan(),
+            BatchSellStatus::Sold => "sold".green(),
+            BatchSellStatus::Skipped => "skipped".dimmed(),
+            BatchSellStatus::Failed => "failed".red(),
+            BatchSellStatus::Empty => "empty".dimmed(),
+        };
+        let value = item
+            .value_sol
+            .map(|value| format!("{:.6} SOL", value))
+            .unwrap_or_else(|| "-".to_string());
+        println!(
+            "  {} {} ({}, slippage {} bps) {}",
+            status,
+            item.mint.yellow(),
+            value,
+            item.slippage,
+            item.reason.as_deref().unwrap_or_default()
+        );
+        if let Some(signature) = &item.signature {
+            println!("     🔗 https://solscan.io/tx/{}", signature);
+        }
+    }
+    println!(
+        "\nSold: {}  Failed: {}  Closed accounts: {}  Reclaimed: {} SOL",
+        report.sold,
+        report.failed,
+        report.closed_accounts,
+        lamports_to_sol(report.reclaimed_lamports)
+    );
+    for signature in &report.close_signatures {
+        println!("  Close: https://solscan.io/tx/{}", signature);
+    }
+    for error in &report.close_errors {
+        println!("  {}", error.red());
+    }
 }
 
 /// Load keypair from encrypted file
@@ -172,7 +624,10 @@ pub fn load_encrypted_keypair(file_path: &str) -> Result<Keypair> {
         }
         "triple_factor_v1" => {
             // Triple-factor authentication
-            load_triple_factor_keypair(&encrypted_data)?
+            let encrypted_key = json["encrypted_private_key"]
+                .as_str()
+                .ok_or_else(|| anyhow::anyhow!("Missing encrypted_private_key"))?;
+            load_triple_factor_keypair(file_path, &json, encrypted_key)?
         }
         _ => {
             return Err(anyhow::anyhow!(
@@ -188,39 +643,72 @@ pub fn load_encrypted_keypair(file_path: &str) -> Result<Keypair> {
     Ok(keypair)
 }
 
-/// Load keypair with triple-factor authentication
-fn load_triple_factor_keypair(encrypted_data: &str) -> Result<Keypair> {
-    use crate::hardware_fingerprint::HardwareFingerprint;
+/// Load keypair with triple-factor authentication; 2FA state is kept beside the wallet file
+fn load_triple_factor_keypair(
+    file_path: &str,
+    wallet: &serde_json::Value,
+    encrypted_data: &str,
+) -> Result<Keypair> {
+    use crate::device_factor::unlock_wallet_device_factor;
+    use crate::totp_guard::JsonFileTotpStateStore;
 
     println!(
         "{}",
         "\n🔐 Triple-Factor Authentication Required".cyan().bold()
     );
 
-    // Get hardware fingerprint
-    let hw_fp = HardwareFingerprint::collect()
-        .map_err(|e| anyhow::anyhow!("Failed to get hardware fingerprint: {}", e))?;
+    // Recover the device factor secret
+    let device =
+        unlock_wallet_device_factor(wallet).map_err(|e| anyhow::anyhow!("Device factor: {}", e))?;
+    if let Some(notice) = &device.notice {
+        println!(
+            "{} {}; run rebind-2fa-wallet to refresh the binding",
+            "⚠️".yellow(),
+            notice
+        );
+    }
 
     // Get master password
     let master_password = rpassword::prompt_password("Enter master password: ")?;
 
-    // Get security question answer
-    let security_answer = rpassword::prompt_password("Enter security question answer: ")?;
+    // Get security question answers
+    let security_answer = if wallet["security_questions"].is_null() {
+        zeroize::Zeroizing::new(rpassword::prompt_password(
+            "Enter security question answer: ",
+        )?)
+    } else {
+        crate::security_question::SecurityQuestion::answer_wallet_interactive(
+            wallet,
+            &master_password,
+        )
+        .map_err(|e| anyhow::anyhow!(e))?
+    };
 
     // Get 2FA code
-    let twofa_code = rpassword::prompt_password("Enter 2FA code: ")?;
+    let twofa_code = rpassword::prompt_password("Enter 2FA code or backup code: ")?;
 
     // Decrypt
-    let (private_key, _, _) = crate::decrypt_with_triple_factor_and_2fa(
+    let mut totp_state = JsonFileTotpStateStore::beside(file_path);
+    let unlocked = crate::decrypt_with_triple_factor_and_2fa(
+        &mut totp_state,
         encrypted_data,
-        &hw_fp.fingerprint,
+        &device.secret,
         &master_password,
         &security_answer,
         &twofa_code,
     )
     .map_err(|e| anyhow::anyhow!(e))?;
+    if let Some(reseal) = unlocked.reseal {
+        crate::backup_codes::save_resealed_wallet_file(file_path, reseal, &mut totp_state)
+            .map_err(|e| anyhow::anyhow!(e))?;
+        println!(
+            "{} Backup code used; {} left",
+            "⚠️".yellow(),
+            unlocked.backup_codes_remaining
+        );
+    }
 
-    Ok(Keypair::from_base58_string(&private_key))
+    Ok(Keypair::from_base58_string(&unlocked.private_key))
 }
 
 /// Execute Solana operations CLI
@@ -278,7 +766,9 @@ pub fn execute_solana_ops(args: SolanaOpsArgs, encrypted_file: &str) -> Result<(
             to,
             amount,
             rpc_url,
+            memo,
         } => {
+            let memo = normalize_memo(memo.as_deref())?;
             let keypair = load_encrypted_keypair(encrypted_file)?;
             let client = SolanaClient::new(rpc_url);
             let to_pubkey = Pubkey::from_str(&to)?;
@@ -289,6 +779,9 @@ pub fn execute_solana_ops(args: SolanaOpsArgs, encrypted_file: &str) -> Result<(
             println!("From: {}", keypair.pubkey().to_string().yellow());
             println!("To: {}", to.yellow());
             println!("Amount: {} SOL ({} lamports)", amount, lamports);
+            if let Some(memo) = &memo {
+                println!("Memo: {}", memo.yellow());
+            }
 
             // Confirm transfer
             print!("\n{}", "Confirm transfer? (yes/no): ".yellow());
@@ -303,7 +796,8 @@ pub fn execute_solana_ops(args: SolanaOpsArgs, encrypted_file: &str) -> Result<(
             }
 
             println!("\n{}", "🚀 Sending transaction...".cyan());
-            let signature = client.transfer_sol(&keypair, &to_pubkey, lamports)?;
+            let signature =
+                client.transfer_sol_with_memo(&keypair, &to_pubkey, lamports, memo.as_deref())?;
 
             println!("\n{}", "✅ Transfer successful!".green().bold());
             println!("Signature: {}", signature.to_string().yellow());
@@ -315,6 +809,7 @@ pub fn execute_solana_ops(args: SolanaOpsArgs, encrypted_file: &str) -> Result<(
             to,
             amount,
             rpc_url,
+            memo,
         } => {
             let keypair = load_encrypted_keypair(encrypted_file)?;
             let client = SolanaClient::new(rpc_url);
@@ -327,6 +822,23 @@ pub fn execute_solana_ops(args: SolanaOpsArgs, encrypted_file: &str) -> Result<(
             println!("Token Mint: {}", mint.yellow());
             println!("Amount: {} (smallest units)", amount);
 
+            let plan = client
+                .plan_token_transfer(&keypair.pubkey(), &to_pubkey, &mint_pubkey, amount)?
+                .with_memo(memo.as_deref())?;
+            if let Some(memo) = &plan.memo {
+                println!("Memo: {}", memo.yellow());
+            }
+            if plan.fee > 0 {
+                println!(
+                    "Transfer fee: {} (smallest units)",
+                    plan.fee.to_string().red()
+                );
+                println!("Recipient receives: {} (smallest units)", plan.net_amount);
+            }
+            for warning in &plan.warnings {
+                println!("{} {}", "⚠️".yellow(), warning.yellow());
+            }
+
             // Confirm transfer
             print!("\n{}", "Confirm transfer? (yes/no): ".yellow());
             use std::io::{self, Write};
@@ -340,7 +852,7 @@ pub fn execute_solana_ops(args: SolanaOpsArgs, encrypted_file: &str) -> Result<(
             }
 
             println!("\n{}", "🚀 Sending transaction...".cyan());
-            let signature = client.transfer_token(&keypair, &to_pubkey, &mint_pubkey, amount)?;
+            let signature = client.send_token_transfer(&keypair, &plan)?;
 
             println!("\n{}", "✅ Transfer successful!".green().bold());
             println!("Signature: {}", signature.to_string().yellow());
@@ -517,6 +1029,98 @@ pub fn execute_solana_ops(args: SolanaOpsArgs, encrypted_file: &str) -> Result<(
             }
         }
 
+        SolanaOpsCommand::PumpFunBuy {
+            mint,
+            amount,
+            input,
+            rpc_url,
+            slippage,
+            no_create_ata,
+            yes,
+        } => {
+            #[cfg(not(feature = "sol-trade-sdk"))]
+            {
+                return Err(anyhow::anyhow!(
+                    "Pump.fun buy requires 'sol-trade-sdk' feature. Please rebuild with:\ncargo build --release --features sol-trade-sdk"
+                ));
+            }
+
+            #[cfg(feature = "sol-trade-sdk")]
+            {
+                use crate::solana_utils::pump_buy::{
+                    handle_pump_buy_no_prompt, parse_buy_input_token, PumpBuyOptions,
+                };
+
+                let input_token_type =
+                    parse_buy_input_token(&input).map_err(|e| anyhow::anyhow!(e))?;
+                let options = PumpBuyOptions {
+                    amount_lamports: (amount * solana_sdk::native_token::LAMPORTS_PER_SOL as f64)
+                        as u64,
+                    input_token_type,
+                    slippage,
+                    create_ata: !no_create_ata,
+                };
+                let keypair = load_encrypted_keypair(encrypted_file)?;
+                let rt = tokio::runtime::Runtime::new().map_err(|e| anyhow::anyhow!(e))?;
+                rt.block_on(handle_pump_buy_no_prompt(
+                    &keypair,
+                    &mint,
+                    &rpc_url,
+                    sol_trade_sdk::trading::factory::DexType::PumpFun,
+                    options,
+                    crate::operations::Language::English,
+                    yes,
+                ))
+                .map_err(|e| anyhow::anyhow!(e))?;
+            }
+        }
+
+        SolanaOpsCommand::PumpSwapBuy {
+            mint,
+            amount,
+            input,
+            rpc_url,
+            slippage,
+            no_create_ata,
+            yes,
+        } => {
+            #[cfg(not(feature = "sol-trade-sdk"))]
+            {
+                return Err(anyhow::anyhow!(
+                    "PumpSwap buy requires 'sol-trade-sdk' feature. Please rebuild with:\ncargo build --release --features sol-trade-sdk"
+                ));
+            }
+
+            #[cfg(feature = "sol-trade-sdk")]
+            {
+                use crate::solana_utils::pump_buy::{
+                    handle_pump_buy_no_prompt, parse_buy_input_token, PumpBuyOptions,
+                };
+
+                let input_token_type =
+                    parse_buy_input_token(&input).map_err(|e| anyhow::anyhow!(e))?;
+                let options = PumpBuyOptions {
+                    amount_lamports: (amount * solana_sdk::native_token::LAMPORTS_PER_SOL as f64)
+                        as u64,
+                    input_token_type,
+                    slippage,
+                    create_ata: !no_create_ata,
+                };
+                let keypair = load_encrypted_keypair(encrypted_file)?;
+                let rt = tokio::runtime::Runtime::new().map_err(|e| anyhow::anyhow!(e))?;
+                rt.block_on(handle_pump_buy_no_prompt(
+                    &keypair,
+                    &mint,
+                    &rpc_url,
+                    sol_trade_sdk::trading::factory::DexType::PumpSwap,
+                    options,
+                    crate::operations::Language::English,
+                    yes,
+                ))
+                .map_err(|e| anyhow::anyhow!(e))?;
+            }
+        }
+
         SolanaOpsCommand::PumpFunCashback { rpc_url } => {
             #[cfg(not(feature = "sol-trade-sdk"))]
             {
@@ -593,6 +1197,334 @@ pub fn execute_solana_ops(args: SolanaOpsArgs, encrypted_file: &str) -> Result<(
                 println!("Explorer: https://solscan.io/tx/{}", sig);
             }
         }
+
+        SolanaOpsCommand::PumpBatchSell {
+            rpc_url,
+            slippage,
+            mint_slippage,
+            venue,
+            min_value,
+            max_value,
+            min_age_secs,
+            exclude,
+            concurrency,
+            no_close,
+            dry_run,
+            yes,
+        } => {
+            #[cfg(not(feature = "sol-trade-sdk"))]
+            {
+                return Err(anyhow::anyhow!(
+                    "Pump batch sell requires 'sol-trade-sdk' feature. Please rebuild with:\ncargo build --release --features sol-trade-sdk"
+                ));
+            }
+
+            #[cfg(feature = "sol-trade-sdk")]
+            {
+                use crate::solana_utils::batch_sell::{
+                    batch_sell, fetch_token_holdings, BatchSellOptions,
+                };
+                use crate::solana_utils::position_watcher::PumpVenue;
+                use crate::solana_utils::swqos::cli_trade_client;
+
+                let mut slippage_overrides = std::collections::HashMap::new();
+                for entry in &mint_slippage {
+                    let (mint, bps) = entry
+                        .split_once('=')
+                        .ok_or_else(|| anyhow::anyhow!("Expected MINT=BPS, got: {}", entry))?;
+                    slippage_overrides.insert(mint.trim().to_string(), bps.trim().parse::<u64>()?);
+                }
+                let venues = venue
+                    .iter()
+                    .map(|value| match value.trim().to_lowercase().as_str() {
+                        "pump_fun" | "pumpfun" => Ok(PumpVenue::PumpFun),
+                        "pump_swap" | "pumpswap" => Ok(PumpVenue::PumpSwap),
+                        other => Err(anyhow::anyhow!("Unsupported venue: {}", other)),
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                let mut options = BatchSellOptions {
+                    slippage,
+                    slippage_overrides,
+                    venues: (!venues.is_empty()).then_some(venues),
+                    min_value_sol: min_value,
+                    max_value_sol: max_value,
+                    min_age_secs,
+                    exclude_mints: exclude,
+                    max_concurrency: Some(concurrency),
+                    close_empty_accounts: !no_close,
+                    dry_run: true,
+                };
+                options.validate().map_err(|e| anyhow::anyhow!(e))?;
+
+                let keypair = load_encrypted_keypair(encrypted_file)?;
+                let rt = tokio::runtime::Runtime::new().map_err(|e| anyhow::anyhow!(e))?;
+                let payer = std::sync::Arc::new(keypair.insecure_clone());
+                let (client, tip_sol) = rt
+                    .block_on(cli_trade_client(payer, &rpc_url, false))
+                    .map_err(|e| anyhow::anyhow!(e))?;
+                let holdings = rt
+                    .block_on(fetch_token_holdings(
+                        &client.infrastructure.rpc,
+                        &keypair.pubkey(),
+                    ))
+                    .map_err(|e| anyhow::anyhow!(e))?;
+
+                println!("\n{}", "📡 Planning batch sell...".cyan());
+                let plan = rt
+                    .block_on(batch_sell(&client, holdings.clone(), &options, tip_sol))
+                    .map_err(|e| anyhow::anyhow!(e))?;
+                print_batch_sell_report(&plan);
+                if dry_run {
+                    return Ok(());
+                }
+                if !yes {
+                    use std::io::{self, Write};
+                    print!(
+                        "\n{}",
+                        "❓ Confirm batch sell? (yes/no, default: no): ".yellow()
+                    );
+                    io::stdout().flush()?;
+                    let mut confirm = String::new();
+                    io::stdin().read_line(&mut confirm)?;
+                    let confirm = confirm.trim().to_lowercase();
+                    if confirm != "yes" && confirm != "y" {
+                        println!("{}", "❌ Operation cancelled".red());
+                        return Ok(());
+                    }
+                }
+
+                options.dry_run = false;
+                println!("{}", "📤 Sending transactions...".bright_blue());
+                let report = rt
+                    .block_on(batch_sell(&client, holdings, &options, tip_sol))
+                    .map_err(|e| anyhow::anyhow!(e))?;
+                print_batch_sell_report(&report);
+            }
+        }
+
+        SolanaOpsCommand::Payouts {
+            file,
+            rpc_url,
+            progress,
+            dry_run,
+            yes,
+        } => {
+            use crate::solana_utils::payouts::{
+                parse_payouts, payout_batch_id, JsonFileProgressStore, PayoutLineStatus,
+                PayoutProgressStore,
+            };
+
+            let entries = parse_payouts(&std::fs::read_to_string(&file)?)?;
+            let keypair = load_encrypted_keypair(encrypted_file)?;
+            let client = SolanaClient::new(rpc_url);
+            let mut store = JsonFileProgressStore::new(
+                progress.unwrap_or_else(|| format!("{}.progress.json", file)),
+            );
+
+            println!("\n{}", "📋 Checking payout list...".cyan());
+            let completed = store
+                .load(&payout_batch_id(&keypair.pubkey(), &entries))?
+                .into_iter()
+                .filter(|entry| entry.status == PayoutLineStatus::Confirmed)
+                .map(|entry| entry.line)
+                .collect();
+            let plan = client.plan_payouts(&keypair.pubkey(), &entries, &completed)?;
+            print_payout_plan(&plan);
+            if plan.lines.is_empty() && plan.errors.is_empty() {
+                println!(
+                    "\n{}",
+                    "✅ Every payout in this list is already confirmed".green()
+                );
+                return Ok(());
+            }
+            if !plan.is_executable() {
+                return Err(anyhow::anyhow!("Fix the errors above before paying out"));
+            }
+            if dry_run {
+                return Ok(());
+            }
+            if !yes {
+                use std::io::{self, Write};
+                print!(
+                    "\n{}",
+                    "❓ Confirm payouts? (yes/no, default: no): ".yellow()
+                );
+                io::stdout().flush()?;
+                let mut confirm = String::new();
+                io::stdin().read_line(&mut confirm)?;
+                let confirm = confirm.trim().to_lowercase();
+                if confirm != "yes" && confirm != "y" {
+                    println!("{}", "❌ Operation cancelled".red());
+                    return Ok(());
+                }
+            }
+
+            println!("{}", "📤 Sending transactions...".bright_blue());
+            let report = client.execute_payouts(&keypair, &entries, &mut store, &mut |sent| {
+                let lines: Vec<String> = sent
+                    .lines
+                    .iter()
+                    .map(|line| (line + 1).to_string())
+                    .collect();
+                println!(
+                    "  {} line(s) {}: https://solscan.io/tx/{}",
+                    "paid".green(),
+                    lines.join(", "),
+                    sent.signature
+                );
+            })?;
+            println!(
+                "\nPaid: {}/{} line(s), remaining {}. Progress: {}",
+                report.total_lines - report.remaining,
+                report.total_lines,
+                report.remaining,
+                store.path().display()
+            );
+            if let Some(error) = report.error {
+                return Err(anyhow::anyhow!(
+                    "{}\nRerun the same command to resume",
+                    error
+                ));
+            }
+        }
+        SolanaOpsCommand::StakeList { rpc_url, address } => {
+            let authority = match address {
+                Some(address) => Pubkey::from_str(&address)?,
+                None => load_encrypted_keypair(encrypted_file)?.pubkey(),
+            };
+            let client = SolanaClient::new(rpc_url);
+            let (epoch, accounts) = client.list_stake_accounts(&authority)?;
+
+            println!(
+                "\n{} {} (epoch {})",
+                "🥩 Stake accounts for".cyan(),
+                authority.to_string().yellow(),
+                epoch
+            );
+            if accounts.is_empty() {
+                println!("No stake accounts found");
+            }
+            for account in &accounts {
+                let vote = account
+                    .state
+                    .and_then(|state| state.delegation)
+                    .map(|delegation| format!(" → {}", delegation.vote_account))
+                    .unwrap_or_default();
+                println!(
+                    "  {} {} SOL [{}]{}",
+                    account.address.to_string().yellow(),
+                    lamports_to_sol(account.lamports).to_string().green(),
+                    account.activation.as_str(),
+                    vote
+                );
+                if let Some(state) = account.state {
+                    println!(
+                        "    staker {} / withdrawer {} / withdrawable {} SOL",
+                        state.staker,
+                        state.withdrawer,
+                        lamports_to_sol(account.withdrawable_lamports())
+                    );
+                }
+            }
+        }
+
+        SolanaOpsCommand::StakeCreate {
+            amount,
+            vote,
+            seed,
+            rpc_url,
+            yes,
+        } => {
+            let action = StakeAction::Create {
+                lamports: sol_amount_to_lamports(amount),
+                vote_account: vote.as_deref().map(Pubkey::from_str).transpose()?,
+                seed,
+            };
+            run_stake_action(rpc_url, encrypted_file, action, yes)?;
+        }
+
+        SolanaOpsCommand::StakeDelegate {
+            stake,
+            vote,
+            rpc_url,
+            yes,
+        } => {
+            let action = StakeAction::Delegate {
+                stake_account: Pubkey::from_str(&stake)?,
+                vote_account: Pubkey::from_str(&vote)?,
+            };
+            run_stake_action(rpc_url, encrypted_file, action, yes)?;
+        }
+
+        SolanaOpsCommand::StakeDeactivate {
+            stake,
+            rpc_url,
+            yes,
+        } => {
+            let action = StakeAction::Deactivate {
+                stake_account: Pubkey::from_str(&stake)?,
+            };
+            run_stake_action(rpc_url, encrypted_file, action, yes)?;
+        }
+
+        SolanaOpsCommand::StakeWithdraw {
+            stake,
+            to,
+            amount,
+            rpc_url,
+            yes,
+        } => {
+            let action = StakeAction::Withdraw {
+                stake_account: Pubkey::from_str(&stake)?,
+                to: to.as_deref().map(Pubkey::from_str).transpose()?,
+                lamports: amount.map(sol_amount_to_lamports),
+            };
+            run_stake_action(rpc_url, encrypted_file, action, yes)?;
+        }
+
+        SolanaOpsCommand::StakeSplit {
+            stake,
+            amount,
+            seed,
+            rpc_url,
+            yes,
+        } => {
+            let action = StakeAction::Split {
+                stake_account: Pubkey::from_str(&stake)?,
+                lamports: sol_amount_to_lamports(amount),
+                seed,
+            };
+            run_stake_action(rpc_url, encrypted_file, action, yes)?;
+        }
+
+        SolanaOpsCommand::StakeMerge {
+            destination,
+            source,
+            rpc_url,
+            yes,
+        } => {
+            let action = StakeAction::Merge {
+                destination: Pubkey::from_str(&destination)?,
+                source: Pubkey::from_str(&source)?,
+            };
+            run_stake_action(rpc_url, encrypted_file, action, yes)?;
+        }
+
+        SolanaOpsCommand::StakeAuthorize {
+            stake,
+            new_authority,
+            authority,
+            rpc_url,
+            yes,
+        } => {
+            let action = StakeAction::Authorize {
+                stake_account: Pubkey::from_str(&stake)?,
+                new_authority: Pubkey::from_str(&new_authority)?,
+                authority: StakeAuthority::parse(&authority)
+                    .ok_or_else(|| anyhow::anyhow!("Authority must be staker or withdrawer"))?,
+            };
+            run_stake_action(rpc_url, encrypted_file, action, yes)?;
+        }
     }
 
     Ok(())
@@ -1,13 +1,29 @@
 #[cfg(any(feature = "solana-ops", feature = "mobile-solana-ops"))]
+pub mod payouts;
+#[cfg(any(feature = "solana-ops", feature = "mobile-solana-ops"))]
 pub mod solana_ops;
+#[cfg(any(feature = "solana-ops", feature = "mobile-solana-ops"))]
+pub mod stake;
+#[cfg(any(feature = "solana-ops", feature = "mobile-solana-ops"))]
+pub mod token_extensions;
 
 #[cfg(feature = "cli")]
 pub mod cli;
 
+#[cfg(feature = "sol-trade-sdk")]
+pub mod batch_sell;
+#[cfg(feature = "sol-trade-sdk")]
+pub mod position_watcher;
+#[cfg(feature = "sol-trade-sdk")]
+pub mod pump_buy;
+#[cfg(feature = "sol-trade-sdk")]
+pub mod pump_route;
 #[cfg(feature = "sol-trade-sdk")]
 pub mod pumpfun_sell;
 #[cfg(feature = "sol-trade-sdk")]
 pub mod pumpswap_sell;
+#[cfg(feature = "sol-trade-sdk")]
+pub mod swqos;
 
 #[cfg(any(feature = "solana-ops", feature = "mobile-solana-ops"))]
 pub use solana_ops::*;
@@ -0,0 +1,1295 @@
+//! Batch payouts: many SOL / SPL token transfers from one source wallet, validated and
+//! previewed as a whole, packed into as few transactions as fit, and executed with
+//! per-line progress so an interrupted run can be resumed without paying anyone twice.
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Deserializer, Serialize};
+use solana_client::rpc_client::RpcClient;
+use solana_commitment_config::CommitmentConfig;
+use solana_sdk::{
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{Keypair, Signature},
+    signer::Signer,
+    transaction::Transaction,
+};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use super::solana_ops::{
+    create_associated_token_account_idempotent, create_transfer_checked_instruction,
+    create_transfer_instruction, get_associated_token_address, memo_instruction, normalize_memo,
+    transaction_fits, SolanaClient, MAX_MULTIPLE_ACCOUNTS,
+};
+use super::token_extensions::{self, MintInfo, TOKEN_2022_PROGRAM_ID, TOKEN_PROGRAM_ID};
+
+pub const MAX_PAYOUT_ENTRIES: usize = 5_000;
+/// Base fee per signature; payout transactions carry a single signature
+const LAMPORTS_PER_SIGNATURE: u64 = 5_000;
+const TOKEN_ACCOUNT_LEN: usize = 165;
+/// Token-2022 account: base layout, account type byte and the ImmutableOwner TLV header
+const TOKEN_2022_ACCOUNT_BASE_LEN: usize = 170;
+const TOKEN_2022_TRANSFER_FEE_AMOUNT_LEN: usize = 4 + 8;
+const TOKEN_2022_TRANSFER_HOOK_ACCOUNT_LEN: usize = 4 + 1;
+
+/// One requested payout, as read from CSV or JSON
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PayoutEntry {
+    pub recipient: String,
+    /// `SOL` or a token mint address
+    #[serde(alias = "mint", default = "default_asset")]
+    pub asset: String,
+    /// Decimal amount in UI units (e.g. `1.5` SOL)
+    #[serde(deserialize_with = "deserialize_decimal")]
+    pub amount: String,
+    #[serde(default)]
+    pub memo: Option<String>,
+}
+
+fn default_asset() -> String {
+    "SOL".to_string()
+}
+
+fn deserialize_decimal<'de, D>(deserializer: D) -> std::result::Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match serde_json::Value::deserialize(deserializer)? {
+        serde_json::Value::String(value) => Ok(value),
+        serde_json::Value::Number(value) => Ok(value.to_string()),
+        _ => Err(serde::de::Error::custom("amount must be a decimal value")),
+    }
+}
+
+/// What a payout line sends
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum PayoutAsset {
+    Sol,
+    Token(Pubkey),
+}
+
+impl PayoutAsset {
+    pub fn parse(value: &str) -> Result<Self> {
+        let value = value.trim();
+        if value.is_empty() || value.eq_ignore_ascii_case("SOL") {
+            return Ok(Self::Sol);
+        }
+        Pubkey::from_str(value)
+            .map(Self::Token)
+            .map_err(|_| anyhow!("Invalid mint address: {}", value))
+    }
+}
+
+impl fmt::Display for PayoutAsset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Sol => f.write_str("SOL"),
+            Self::Token(mint) => write!(f, "{}", mint),
+        }
+    }
+}
+
+/// A validated payout line with the instructions that pay it
+#[derive(Debug, Clone)]
+pub struct PayoutLine {
+    /// Position of the entry in the input list (0-based)
+    pub index: usize,
+    pub recipient: Pubkey,
+    pub asset: PayoutAsset,
+    /// Amount debited from the source, in base units
+    pub amount: u64,
+    pub decimals: u8,
+    /// Token-2022 transfer fee withheld from `amount`
+    pub fee: u64,
+    pub memo: Option<String>,
+    /// Whether this line creates the recipient's associated token account
+    pub create_recipient_account: bool,
+    pub instructions: Vec<Instruction>,
+}
+
+/// A group of payout lines sent as one transaction
+#[derive(Debug, Clone)]
+pub struct PayoutTransaction {
+    /// Input indices of the lines in this transaction
+    pub lines: Vec<usize>,
+    pub instructions: Vec<Instruction>,
+}
+
+/// Totals for one asset across the whole payout
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PayoutAssetTotal {
+    pub asset: PayoutAsset,
+    pub decimals: u8,
+    pub recipients: usize,
+    pub amount: u64,
+    pub fee: u64,
+    /// Source balance at planning time
+    pub balance: u64,
+}
+
+/// A problem with the payout; `line` is the input index when it concerns a single entry
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PayoutIssue {
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl PayoutIssue {
+    fn line(index: usize, message: impl Into<String>) -> Self {
+        Self {
+            line: Some(index),
+            message: message.into(),
+        }
+    }
+
+    fn general(message: impl Into<String>) -> Self {
+        Self {
+            line: None,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for PayoutIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(index) => write!(f, "Line {}: {}", index + 1, self.message),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+/// The aggregated preview of a payout; execute only when [`PayoutPlan::is_executable`]
+#[derive(Debug, Clone)]
+pub struct PayoutPlan {
+    /// Stable identifier of the input list, used to key resumable progress
+    pub batch_id: String,
+    pub source: Pubkey,
+    pub lines: Vec<PayoutLine>,
+    pub transactions: Vec<PayoutTransaction>,
+    pub totals: Vec<PayoutAssetTotal>,
+    /// Lines skipped because earlier runs already confirmed them
+    pub completed_lines: usize,
+    pub accounts_to_create: usize,
+    pub account_rent_lamports: u64,
+    /// Base fees when the source signs every transaction
+    pub network_fee_lamports: u64,
+    pub warnings: Vec<PayoutIssue>,
+    pub errors: Vec<PayoutIssue>,
+}
+
+impl PayoutPlan {
+    pub fn is_executable(&self) -> bool {
+        self.errors.is_empty() && !self.lines.is_empty()
+    }
+
+    /// SOL leaving the source: SOL payouts plus rent for new token accounts
+    pub fn sol_outflow_lamports(&self) -> u64 {
+        self.totals
+            .iter()
+            .filter(|total| total.asset == PayoutAsset::Sol)
+            .map(|total| total.amount)
+            .sum::<u64>()
+            .saturating_add(self.account_rent_lamports)
+    }
+
+    /// Repack the lines with a different size check, e.g. for Squads batch transactions
+    pub fn repack(&mut self, fits: impl Fn(&[Instruction]) -> bool) -> Result<()> {
+        self.transactions = pack_payout_lines(&self.lines, fits)?;
+        self.network_fee_lamports = LAMPORTS_PER_SIGNATURE * self.transactions.len() as u64;
+        Ok(())
+    }
+}
+
+/// Status of one payout line across runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PayoutLineStatus {
+    /// Signed and sent; the outcome is not known yet
+    Submitted,
+    Confirmed,
+    /// Definitely not executed; the line is retried on the next run
+    Failed,
+}
+
+impl PayoutLineStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Submitted => "submitted",
+            Self::Confirmed => "confirmed",
+            Self::Failed => "failed",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "submitted" => Some(Self::Submitted),
+            "confirmed" => Some(Self::Confirmed),
+            "failed" => Some(Self::Failed),
+            _ => None,
+        }
+    }
+}
+
+/// Recorded progress of one payout line
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PayoutProgress {
+    pub line: usize,
+    pub status: PayoutLineStatus,
+    #[serde(default)]
+    pub signature: Option<String>,
+    /// Last block height at which the submitted transaction can still land
+    #[serde(default)]
+    pub last_valid_block_height: Option<u64>,
+    #[serde(default)]
+    pub error: Option<String>,
+}
+
+/// Where payout progress is persisted between runs
+pub trait PayoutProgressStore {
+    fn load(&mut self, batch_id: &str) -> Result<Vec<PayoutProgress>>;
+    fn record(&mut self, batch_id: &str, progress: &[PayoutProgress]) -> Result<()>;
+}
+
+/// Progress kept in a JSON file, keyed by batch id
+#[derive(Debug, Clone)]
+pub struct JsonFileProgressStore {
+    path: PathBuf,
+}
+
+impl JsonFileProgressStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn read_all(&self) -> Result<BTreeMap<String, BTreeMap<usize, PayoutProgress>>> {
+        match std::fs::read_to_string(&self.path) {
+            Ok(text) => serde_json::from_str(&text)
+                .with_context(|| format!("Invalid payout progress file {}", self.path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+impl PayoutProgressStore for JsonFileProgressStore {
+    fn load(&mut self, batch_id: &str) -> Result<Vec<PayoutProgress>> {
+        Ok(self
+            .read_all()?
+            .remove(batch_id)
+            .map(|lines| lines.into_values().collect())
+            .unwrap_or_default())
+    }
+
+    fn record(&mut self, batch_id: &str, progress: &[PayoutProgress]) -> Result<()> {
+        let mut all = self.read_all()?;
+        let lines = all.entry(batch_id.to_string()).or_default();
+        for entry in progress {
+            lines.insert(entry.line, entry.clone());
+        }
+        let tmp = self.path.with_extension("tmp");
+        std::fs::write(&tmp, serde_json::to_vec_pretty(&all)?)?;
+        std::fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+}
+
+/// One transaction sent by [`SolanaClient::execute_payouts`]
+#[derive(Debug, Clone)]
+pub struct PayoutTransactionResult {
+    pub lines: Vec<usize>,
+    pub signature: Signature,
+}
+
+/// Outcome of [`SolanaClient::execute_payouts`]
+#[derive(Debug, Clone)]
+pub struct PayoutReport {
+    pub batch_id: String,
+    pub total_lines: usize,
+    /// Lines confirmed before this run, including ones reconciled from submitted transactions
+    pub previously_confirmed: usize,
+    pub sent: Vec<PayoutTransactionResult>,
+    /// Lines still unpaid or unconfirmed after this run
+    pub remaining: usize,
+    /// Why the run stopped early; rerun with the same input to resume
+    pub error: Option<String>,
+}
+
+/// Parse payouts from CSV or JSON, detected by the first character
+pub fn parse_payouts(text: &str) -> Result<Vec<PayoutEntry>> {
+    if text.trim_start().starts_with('[') {
+        parse_payouts_json(text)
+    } else {
+        parse_payouts_csv(text)
+    }
+}
+
+/// Parse a JSON array of `{recipient, asset|mint, amount, memo}` objects
+pub fn parse_payouts_json(text: &str) -> Result<Vec<PayoutEntry>> {
+    let entries: Vec<PayoutEntry> =
+        serde_json::from_str(text).map_err(|e| anyhow!("Invalid payout JSON: {}", e))?;
+    check_entry_count(entries.len())?;
+    Ok(entries)
+}
+
+/// Parse `recipient,asset,amount[,memo]` rows
+///
+/// A header row starting with `recipient`, blank lines and `#` comments are skipped.
+/// Fields may be double-quoted, with `""` for a literal quote.
+pub fn parse_payouts_csv(text: &str) -> Result<Vec<PayoutEntry>> {
+    let mut entries = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let fields = split_csv_line(line).map_err(|e| anyhow!("CSV line {}: {}", number + 1, e))?;
+        if entries.is_empty()
+            && fields
+                .first()
+                .is_some_and(|field| field.trim().eq_ignore_ascii_case("recipient"))
+        {
+            continue;
+        }
+        if !(3..=4).contains(&fields.len()) {
+            return Err(anyhow!(
+                "CSV line {}: expected recipient,asset,amount[,memo], found {} fields",
+                number + 1,
+                fields.len()
+            ));
+        }
+        let memo = fields
+            .get(3)
+            .map(|memo| memo.trim().to_string())
+            .filter(|memo| !memo.is_empty());
+        entries.push(PayoutEntry {
+            recipient: fields[0].trim().to_string(),
+            asset: fields[1].trim().to_string(),
+            amount: fields[2].trim().to_string(),
+            memo,
+        });
+    }
+    check_entry_count(entries.len())?;
+    Ok(entries)
+}
+
+fn check_entry_count(count: usize) -> Result<()> {
+    if count == 0 {
+        return Err(anyhow!("Payout list is empty"));
+    }
+    if count > MAX_PAYOUT_ENTRIES {
+        return Err(anyhow!(
+            "Payout list has {} entries; the limit is {}",
+            count,
+            MAX_PAYOUT_ENTRIES
+        ));
+    }
+    Ok(())
+}
+
+fn split_csv_line(line: &str) -> std::result::Result<Vec<String>, &'static str> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut chars = line.chars().peekable();
+    let mut quoted = false;
+    while let Some(ch) = chars.next() {
+        match ch {
+            '"' if quoted => {
+                if chars.peek() == Some(&'"') {
+                    chars.next();
+                    field.push('"');
+                } else {
+                    quoted = false;
+                }
+            }
+            '"' if field.trim().is_empty() => {
+                field.clear();
+                quoted = true;
+            }
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            _ => field.push(ch),
+        }
+    }
+    if quoted {
+        return Err("unterminated quoted field");
+    }
+    fields.push(field);
+    Ok(fields)
+}
+
+/// Convert a decimal UI amount to base units, rejecting zero and excess precision
+pub fn parse_ui_amount(amount: &str, decimals: u8) -> Result<u64> {
+    let amount = amount.trim();
+    let invalid = || anyhow!("Invalid amount '{}'", amount);
+    let (whole, fraction) = amount.split_once('.').unwrap_or((amount, ""));
+    if (whole.is_empty() && fraction.is_empty())
+        || !whole.bytes().all(|b| b.is_ascii_digit())
+        || !fraction.bytes().all(|b| b.is_ascii_digit())
+    {
+        return Err(invalid());
+    }
+    let decimals = decimals as usize;
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.len() > decimals {
+        return Err(anyhow!(
+            "Amount '{}' has more than {} decimal places",
+            amount,
+            decimals
+        ));
+    }
+    let digits = format!(
+        "{}{}{}",
+        whole,
+        fraction,
+        "0".repeat(decimals - fraction.len())
+    );
+    let units = digits.trim_start_matches('0').parse::<u64>().or_else(|_| {
+        if digits.bytes().all(|b| b == b'0') {
+            Ok(0)
+        } else {
+            Err(anyhow!("Amount '{}' is out of range", amount))
+        }
+    })?;
+    if units == 0 {
+        return Err(anyhow!("Amount must be greater than zero"));
+    }
+    Ok(units)
+}
+
+/// Stable identifier of a payout list: the same entries in the same order resume the same run
+pub fn payout_batch_id(source: &Pubkey, entries: &[PayoutEntry]) -> String {
+    let mut context = ring::digest::Context::new(&ring::digest::SHA256);
+    context.update(source.as_ref());
+    for entry in entries {
+        for field in [
+            entry.recipient.trim(),
+            entry.asset.trim(),
+            entry.amount.trim(),
+            entry.memo.as_deref().unwrap_or("").trim(),
+        ] {
+            context.update(&(field.len() as u64).to_le_bytes());
+            context.update(field.as_bytes());
+        }
+    }
+    hex::encode(&context.finish().as_ref()[..16])
+}
+
+/// Pack lines, in order, into as few transactions as `fits` allows
+pub fn pack_payout_lines(
+    lines: &[PayoutLine],
+    fits: impl Fn(&[Instruction]) -> bool,
+) -> Result<Vec<PayoutTransaction>> {
+    let mut transactions: Vec<PayoutTransaction> = Vec::new();
+    for line in lines {
+        if let Some(last) = transactions.last_mut() {
+            let candidate = [last.instructions.as_slice(), line.instructions.as_slice()].concat();
+            if fits(&candidate) {
+                last.instructions = candidate;
+                last.lines.push(line.index);
+                continue;
+            }
+        }
+        if !fits(&line.instructions) {
+            return Err(anyhow!(
+                "Line {} does not fit in a single transaction",
+                line.index + 1
+            ));
+        }
+        transactions.push(PayoutTransaction {
+            lines: vec![line.index],
+            instructions: line.instructions.clone(),
+        });
+    }
+    Ok(transactions)
+}
+
+/// Rent-exempt size of a new associated token account for this mint
+fn token_account_len(mint: &MintInfo) -> usize {
+    if !mint.is_token_2022() {
+        return TOKEN_ACCOUNT_LEN;
+    }
+    let mut len = TOKEN_2022_ACCOUNT_BASE_LEN;
+    if mint.transfer_fee.is_some() {
+        len += TOKEN_2022_TRANSFER_FEE_AMOUNT_LEN;
+    }
+    if mint.transfer_hook_program.is_some() {
+        len += TOKEN_2022_TRANSFER_HOOK_ACCOUNT_LEN;
+    }
+    len
+}
+
+fn token_account_amount(data: &[u8]) -> u64 {
+    data.get(64..72)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(u64::from_le_bytes)
+        .unwrap_or(0)
+}
+
+/// An entry whose addresses parsed, waiting for on-chain checks
+struct ParsedEntry {
+    index: usize,
+    recipient: Pubkey,
+    asset: PayoutAsset,
+    amount: String,
+    memo: Option<String>,
+}
+
+fn parse_entry(index: usize, entry: &PayoutEntry, source: &Pubkey) -> Result<ParsedEntry> {
+    let recipient = Pubkey::from_str(entry.recipient.trim())
+        .map_err(|_| anyhow!("Invalid recipient address: {}", entry.recipient.trim()))?;
+    if recipient == *source {
+        return Err(anyhow!("Recipient is the source wallet"));
+    }
+    let asset = PayoutAsset::parse(&entry.asset)?;
+    let memo = normalize_memo(entry.memo.as_deref())?;
+    Ok(ParsedEntry {
+        index,
+        recipient,
+        asset,
+        amount: entry.amount.clone(),
+        memo,
+    })
+}
+
+fn get_multiple_accounts_chunked(
+    client: &RpcClient,
+    addresses: &[Pubkey],
+) -> Result<HashMap<Pubkey, solana_sdk::account::Account>> {
+    let mut accounts = HashMap::new();
+    for chunk in addresses.chunks(MAX_MULTIPLE_ACCOUNTS) {
+        for (address, account) in chunk.iter().zip(client.get_multiple_accounts(chunk)?) {
+            if let Some(account) = account {
+                accounts.insert(*address, account);
+            }
+        }
+    }
+    Ok(accounts)
+}
+
+impl SolanaClient {
+    /// Validate a payout list against the chain and build its aggregated preview
+    ///
+    /// Every address is checked, recipients' missing token accounts are created (and their
+    /// rent counted), Token-2022 fees and hooks are handled as in
+    /// [`SolanaClient::plan_token_transfer`], and source balances must cover the totals.
+    /// Lines whose input index is in `completed` are left out. Lines are grouped by asset
+    /// and packed into as few transactions as fit the packet size limit.
+    pub fn plan_payouts(
+        &self,
+        source: &Pubkey,
+        entries: &[PayoutEntry],
+        completed: &HashSet<usize>,
+    ) -> Result<PayoutPlan> {
+        check_entry_count(entries.len())?;
+        let client = RpcClient::new(self.rpc_url.clone());
+        let mut warnings = Vec::new();
+        let mut errors = Vec::new();
+
+        let mut parsed = Vec::new();
+        for (index, entry) in entries.iter().enumerate() {
+            if completed.contains(&index) {
+                continue;
+            }
+            match parse_entry(index, entry, source) {
+                Ok(entry) => parsed.push(entry),
+                Err(e) => errors.push(PayoutIssue::line(index, e.to_string())),
+            }
+        }
+
+        let mut seen = HashMap::new();
+        for entry in &parsed {
+            if let Some(first) = seen.insert((entry.recipient, entry.asset), entry.index) {
+                warnings.push(PayoutIssue::line(
+                    entry.index,
+                    format!("Duplicate of line {}: same recipient and asset", first + 1),
+                ));
+            }
+        }
+
+        let mints: Vec<Pubkey> = parsed
+            .iter()
+            .filter_map(|entry| match entry.asset {
+                PayoutAsset::Token(mint) => Some(mint),
+                PayoutAsset::Sol => None,
+            })
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        let mint_accounts = get_multiple_accounts_chunked(&client, &mints)?;
+        let mut mint_infos: HashMap<Pubkey, MintInfo> = HashMap::new();
+        let mut mint_errors: HashMap<Pubkey, String> = HashMap::new();
+        for mint in &mints {
+            let result = mint_accounts
+                .get(mint)
+                .ok_or_else(|| anyhow!("Mint {} does not exist", mint))
+                .and_then(|account| token_extensions::parse_mint(account.owner, &account.data))
+                .and_then(|info| {
+                    if info.non_transferable {
+                        Err(anyhow!("Token {} is non-transferable", mint))
+                    } else {
+                        Ok(info)
+                    }
+                });
+            match result {
+                Ok(info) => {
+                    mint_infos.insert(*mint, info);
+                }
+                Err(e) => {
+                    mint_errors.insert(*mint, e.to_string());
+                }
+            }
+        }
+        let epoch = if mint_infos.values().any(|info| info.transfer_fee.is_some()) {
+            Some(client.get_epoch_info()?.epoch)
+        } else {
+            None
+        };
+
+        // Recipients themselves: catch token accounts, mints and programs pasted as wallets
+        let recipients: Vec<Pubkey> = parsed
+            .iter()
+            .map(|entry| entry.recipient)
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        let recipient_accounts = get_multiple_accounts_chunked(&client, &recipients)?;
+
+        let mut token_accounts = Vec::new();
+        for entry in &parsed {
+            if let PayoutAsset::Token(mint) = entry.asset {
+                if let Some(info) = mint_infos.get(&mint) {
+                    token_accounts.push(get_associated_token_address(
+                        &entry.recipient,
+                        &mint,
+                        &info.token_program,
+                    ));
+                }
+            }
+        }
+        for (mint, info) in &mint_infos {
+            token_accounts.push(get_associated_token_address(
+                source,
+                mint,
+                &info.token_program,
+            ));
+        }
+        let token_account_data = get_multiple_accounts_chunked(&client, &token_accounts)?;
+
+        let sol_rent_minimum = client.get_minimum_balance_for_rent_exemption(0)?;
+        let mut rent_by_len: HashMap<usize, u64> = HashMap::new();
+        let mut created: HashSet<Pubkey> = HashSet::new();
+        let mut lines = Vec::new();
+        for entry in parsed {
+            let index = entry.index;
+            if let Some(account) = recipient_accounts.get(&entry.recipient) {
+                if account.executable {
+                    errors.push(PayoutIssue::line(
+                        index,
+                        "Recipient is an executable program",
+                    ));
+                    continue;
+                }
+                if account.owner == TOKEN_PROGRAM_ID || account.owner == TOKEN_2022_PROGRAM_ID {
+                    errors.push(PayoutIssue::line(
+                        index,
+                        "Recipient is a token account or mint, not a wallet",
+                    ));
+                    continue;
+                }
+            }
+            if !entry.recipient.is_on_curve() {
+                warnings.push(PayoutIssue::line(
+                    index,
+                    "Recipient is a program-derived address, not a wallet key",
+                ));
+            }
+
+            let line = match entry.asset {
+                PayoutAsset::Sol => {
+                    let amount = match parse_ui_amount(&entry.amount, 9) {
+                        Ok(amount) => amount,
+                        Err(e) => {
+                            errors.push(PayoutIssue::line(index, e.to_string()));
+                            continue;
+                        }
+                    };
+                    if !recipient_accounts.contains_key(&entry.recipient)
+                        && amount < sol_rent_minimum
+                    {
+                        errors.push(PayoutIssue::line(
+                            index,
+                            format!(
+                                "Recipient account does not exist; at least {} lamports are needed to create it",
+                                sol_rent_minimum
+                            ),
+                        ));
+                        continue;
+                    }
+                    PayoutLine {
+                        index,
+                        recipient: entry.recipient,
+                        asset: entry.asset,
+                        amount,
+                        decimals: 9,
+                        fee: 0,
+                        memo: entry.memo,
+                        create_recipient_account: false,
+                        instructions: vec![create_transfer_instruction(
+                            source,
+                            &entry.recipient,
+                            amount,
+                        )],
+                    }
+                }
+                PayoutAsset::Token(mint) => {
+                    if let Some(message) = mint_errors.get(&mint) {
+                        errors.push(PayoutIssue::line(index, message.clone()));
+                        continue;
+                    }
+                    let info = &mint_infos[&mint];
+                    let amount = match parse_ui_amount(&entry.amount, info.decimals) {
+                        Ok(amount) => amount,
+                        Err(e) => {
+                            errors.push(PayoutIssue::line(index, e.to_string()));
+                            continue;
+                        }
+                    };
+                    let token_program = info.token_program;
+                    let from_ata = get_associated_token_address(source, &mint, &token_program);
+                    let to_ata =
+                        get_associated_token_address(&entry.recipient, &mint, &token_program);
+                    let mut instructions = Vec::new();
+                    let mut create_recipient_account = false;
+                    match token_account_data.get(&to_ata) {
+                        Some(account) => {
+                            if token_extensions::token_account_is_frozen(&account.data) {
+                                errors.push(PayoutIssue::line(
+                                    index,
+                                    "Recipient token account is frozen",
+                                ));
+                                continue;
+                            }
+                            if !token_extensions::accepts_non_confidential_credits(&account.data) {
+                                errors.push(PayoutIssue::line(
+                                    index,
+                                    "Recipient token account only accepts confidential transfers",
+                                ));
+                                continue;
+                            }
+                        }
+                        None => {
+                            if info.default_account_state_frozen {
+                                errors.push(PayoutIssue::line(
+                                    index,
+                                    "New accounts for this mint start frozen; the recipient must have a thawed token account first",
+                                ));
+                                continue;
+                            }
+                            // Several lines may pay the same new account; only the first pays rent
+                            if created.insert(to_ata) {
+                                create_recipient_account = true;
+                            }
+                            instructions.push(create_associated_token_account_idempotent(
+                                source,
+                                &entry.recipient,
+                                &mint,
+                                &token_program,
+                            ));
+                        }
+                    }
+                    let fee = match (&info.transfer_fee, epoch) {
+                        (Some(config), Some(epoch)) => {
+                            Some(config.epoch_fee(epoch).calculate(amount))
+                        }
+                        _ => None,
+                    };
+                    let mut transfer = create_transfer_checked_instruction(
+                        &from_ata,
+                        &mint,
+                        &to_ata,
+                        source,
+                        amount,
+                        info.decimals,
+                        &token_program,
+                        fee,
+                    );
+                    if let Some(hook_program) = info.transfer_hook_program {
+                        let validation_address = token_extensions::transfer_hook_validation_address(
+                            &mint,
+                            &hook_program,
+                        );
+                        let extra_accounts = client
+                            .get_account(&validation_address)
+                            .map_err(|_| {
+                                anyhow!(
+                                    "Transfer hook program {} has no extra account list for this mint",
+                                    hook_program
+                                )
+                            })
+                            .and_then(|validation| {
+                                token_extensions::resolve_transfer_hook_accounts(
+                                    &hook_program,
+                                    &validation.data,
+                                    &from_ata,
+                                    &mint,
+                                    &to_ata,
+                                    source,
+                                    amount,
+                                    &mut |address| {
+                                        client.get_account(address).ok().map(|account| account.data)
+                                    },
+                                )
+                            });
+                        match extra_accounts {
+                            Ok(extra_accounts) => transfer.accounts.extend(extra_accounts),
+                            Err(e) => {
+                                errors.push(PayoutIssue::line(index, e.to_string()));
+                                continue;
+                            }
+                        }
+                    }
+                    instructions.push(transfer);
+                    if create_recipient_account {
+                        let len = token_account_len(info);
+                        if let std::collections::hash_map::Entry::Vacant(slot) =
+                            rent_by_len.entry(len)
+                        {
+                            slot.insert(client.get_minimum_balance_for_rent_exemption(len)?);
+                        }
+                    }
+                    PayoutLine {
+                        index,
+                        recipient: entry.recipient,
+                        asset: entry.asset,
+                        amount,
+                        decimals: info.decimals,
+                        fee: fee.unwrap_or(0),
+                        memo: entry.memo,
+                        create_recipient_account,
+                        instructions,
+                    }
+                }
+            };
+            lines.push(line);
+        }
+
+        for line in &mut lines {
+            if let Some(memo) = &line.memo {
+                line.instructions.push(memo_instruction(memo));
+            }
+        }
+
+        let mut totals: BTreeMap<PayoutAsset, PayoutAssetTotal> = BTreeMap::new();
+        let mut account_rent_lamports = 0u64;
+        let mut accounts_to_create = 0;
+        for line in &lines {
+            let total = totals.entry(line.asset).or_insert(PayoutAssetTotal {
+                asset: line.asset,
+                decimals: line.decimals,
+                recipients: 0,
+                amount: 0,
+                fee: 0,
+                balance: 0,
+            });
+            total.recipients += 1;
+            total.amount = total.amount.saturating_add(line.amount);
+            total.fee = total.fee.saturating_add(line.fee);
+            if line.create_recipient_account {
+                if let PayoutAsset::Token(mint) = line.asset {
+                    accounts_to_create += 1;
+                    let len = token_account_len(&mint_infos[&mint]);
+                    account_rent_lamports = account_rent_lamports.saturating_add(rent_by_len[&len]);
+                }
+            }
+        }
+
+        let sol_balance = client.get_balance(source)?;
+        for total in totals.values_mut() {
+            total.balance = match total.asset {
+                PayoutAsset::Sol => sol_balance,
+                PayoutAsset::Token(mint) => {
+                    let ata = get_associated_token_address(
+                        source,
+                        &mint,
+                        &mint_infos[&mint].token_program,
+                    );
+                    token_account_data
+                        .get(&ata)
+                        .map(|account| token_account_amount(&account.data))
+                        .unwrap_or(0)
+                }
+            };
+            if let PayoutAsset::Token(_) = total.asset {
+                if total.balance < total.amount {
+                    errors.push(PayoutIssue::general(format!(
+                        "Insufficient {} balance: have {}, need {} base units",
+                        total.asset, total.balance, total.amount
+                    )));
+                }
+            }
+        }
+
+        // Lines of the same asset share mint, program and source accounts, so they pack tighter
+        lines.sort_by_key(|line| (line.asset, line.index));
+        let transactions = pack_payout_lines(&lines, |instructions| {
+            transaction_fits(instructions, source)
+        })?;
+        let network_fee_lamports = LAMPORTS_PER_SIGNATURE * transactions.len() as u64;
+
+        let mut plan = PayoutPlan {
+            batch_id: payout_batch_id(source, entries),
+            source: *source,
+            lines,
+            transactions,
+            totals: totals.into_values().collect(),
+            completed_lines: completed.len(),
+            accounts_to_create,
+            account_rent_lamports,
+            network_fee_lamports,
+            warnings,
+            errors,
+        };
+        let sol_needed = plan.sol_outflow_lamports();
+        if sol_balance < sol_needed {
+            plan.errors.push(PayoutIssue::general(format!(
+                "Insufficient SOL balance: have {} lamports, need {} for payouts and new token accounts",
+                sol_balance, sol_needed
+            )));
+        } else if sol_balance < sol_needed.saturating_add(plan.network_fee_lamports) {
+            plan.warnings.push(PayoutIssue::general(format!(
+                "SOL balance does not cover the {} lamports of network fees",
+                plan.network_fee_lamports
+            )));
+        }
+        plan.errors.sort_by_key(|issue| issue.line);
+        plan.warnings.sort_by_key(|issue| issue.line);
+        Ok(plan)
+    }
+
+    /// Settle lines left `Submitted` by an earlier run
+    ///
+    /// Landed transactions become `Confirmed`, failed or expired ones `Failed`. A transaction
+    /// whose blockhash is still valid may yet land, so resuming is refused until it expires.
+    pub fn reconcile_payout_progress(
+        &self,
+        progress: &mut [PayoutProgress],
+    ) -> Result<Vec<PayoutProgress>> {
+        let client = RpcClient::new(self.rpc_url.clone());
+        let mut signatures: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+        for (position, entry) in progress.iter().enumerate() {
+            if entry.status == PayoutLineStatus::Submitted {
+                let signature = entry.signature.clone().unwrap_or_default();
+                signatures.entry(signature).or_default().push(position);
+            }
+        }
+        if signatures.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let block_height = client.get_block_height()?;
+        let mut updated = Vec::new();
+        for (signature, positions) in signatures {
+            let parsed = Signature::from_str(&signature).ok();
+            let status = match parsed {
+                Some(parsed) => client
+                    .get_signature_statuses_with_history(&[parsed])?
+                    .value
+                    .into_iter()
+                    .next()
+                    .flatten(),
+                None => None,
+            };
+            let (status, error) = match status {
+                Some(status) => match status.err {
+                    None if status.satisfies_commitment(CommitmentConfig::confirmed()) => {
+                        (PayoutLineStatus::Confirmed, None)
+                    }
+                    None => {
+                        return Err(anyhow!(
+                            "Payout transaction {} is not confirmed yet; retry shortly",
+                            signature
+                        ))
+                    }
+                    Some(err) => (PayoutLineStatus::Failed, Some(err.to_string())),
+                },
+                None => {
+                    let expired = progress[positions[0]]
+                        .last_valid_block_height
+                        .is_none_or(|last_valid| block_height > last_valid);
+                    if !expired {
+                        return Err(anyhow!(
+                            "Payout transaction {} may still land; retry after its blockhash expires",
+                            signature
+                        ));
+                    }
+                    (
+                        PayoutLineStatus::Failed,
+                        Some("Transaction expired without landing".to_string()),
+                    )
+                }
+            };
+            for position in positions {
+                progress[position].status = status;
+                progress[position].error = error.clone();
+                updated.push(progress[position].clone());
+            }
+        }
+        Ok(updated)
+    }
+
+    /// Pay out `entries` from `keypair`, resuming from `store`
+    ///
+    /// Lines already confirmed in the store are skipped, so rerunning the same input after an
+    /// interruption only pays what is left. Each transaction is recorded as submitted before it
+    /// is sent; the run stops at the first transaction whose outcome is not a confirmation.
+    pub fn execute_payouts(
+        &self,
+        keypair: &Keypair,
+        entries: &[PayoutEntry],
+        store: &mut dyn PayoutProgressStore,
+        on_sent: &mut dyn FnMut(&PayoutTransactionResult),
+    ) -> Result<PayoutReport> {
+        let client = RpcClient::new(self.rpc_url.clone());
+        let source = keypair.pubkey();
+        let batch_id = payout_batch_id(&source, entries);
+
+        let mut progress = store.load(&batch_id)?;
+        let reconciled = self.reconcile_payout_progress(&mut progress)?;
+        if !reconciled.is_empty() {
+            store.record(&batch_id, &reconciled)?;
+        }
+        let completed: HashSet<usize> = progress
+            .iter()
+            .filter(|entry| entry.status == PayoutLineStatus::Confirmed)
+            .map(|entry| entry.line)
+            .collect();
+        let mut report = PayoutReport {
+            batch_id: batch_id.clone(),
+            total_lines: entries.len(),
+            previously_confirmed: completed.len(),
+            sent: Vec::new(),
+            remaining: entries.len() - completed.len(),
+            error: None,
+        };
+        if report.remaining == 0 {
+            return Ok(report);
+        }
+
+        let plan = self.plan_payouts(&source, entries, &completed)?;
+        if !plan.is_executable() {
+            let issues: Vec<String> = plan.errors.iter().map(ToString::to_string).collect();
+            return Err(anyhow!("Payout plan has errors:\n{}", issues.join("\n")));
+        }
+
+        for transaction in &plan.transactions {
+            let (blockhash, last_valid_block_height) =
+                client.get_latest_blockhash_with_commitment(CommitmentConfig::confirmed())?;
+            let signed = Transaction::new_signed_with_payer(
+                &transaction.instructions,
+                Some(&source),
+                &[keypair],
+                blockhash,
+            );
+            let signature = signed.signatures[0];
+            let progress_for = |status, error: Option<String>| -> Vec<PayoutProgress> {
+                transaction
+                    .lines
+                    .iter()
+                    .map(|line| PayoutProgress {
+                        line: *line,
+                        status,
+                        signature: Some(signature.to_string()),
+                        last_valid_block_height: Some(last_valid_block_height),
+                        error: error.clone(),
+                    })
+                    .collect()
+            };
+            store.record(&batch_id, &progress_for(PayoutLineStatus::Submitted, None))?;
+
+            match client.send_and_confirm_transaction(&signed) {
+                Ok(_) => {
+                    store.record(&batch_id, &progress_for(PayoutLineStatus::Confirmed, None))?;
+                    let result = PayoutTransactionResult {
+                        lines: transaction.lines.clone(),
+                        signature,
+                    };
+                    on_sent(&result);
+                    report.remaining -= transaction.lines.len();
+                    report.sent.push(result);
+                }
+                Err(e) => {
+                    // A transaction error is definitive; anything else may still land
+                    if e.get_transaction_error().is_some() {
+                        store.record(
+                            &batch_id,
+                            &progress_for(PayoutLineStatus::Failed, Some(e.to_string())),
+                        )?;
+                    }
+                    report.error = Some(format!(
+                        "Transaction {} for line(s) {:?} failed: {}",
+                        signature,
+                        transaction
+                            .lines
+                            .iter()
+                            .map(|line| line + 1)
+                            .collect::<Vec<_>>(),
+                        e
+                    ));
+                    break;
+                }
+            }
+        }
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_csv_with_header_quotes_and_comments() {
+        let text = "recipient,asset,amount,memo\n\
+                    # team payouts\n\
+                    11111111111111111111111111111112,SOL,1.5,\"March, bonus\"\n\
+                    \n\
+                    11111111111111111111111111111113,So11111111111111111111111111111111111111112,2,\"say \"\"hi\"\"\"\n\
+                    11111111111111111111111111111114,sol,0.1\n";
+        let entries = parse_payouts(text).unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].memo.as_deref(), Some("March, bonus"));
+        assert_eq!(entries[1].memo.as_deref(), Some("say \"hi\""));
+        assert_eq!(entries[2].memo, None);
+        assert_eq!(
+            PayoutAsset::parse(&entries[2].asset).unwrap(),
+            PayoutAsset::Sol
+        );
+
+        assert!(parse_payouts_csv("a,b\n").is_err());
+        assert!(parse_payouts_csv("a,SOL,\"1\n").is_err());
+        assert!(parse_payouts_csv("recipient,asset,amount\n").is_err());
+    }
+
+    #[test]
+    fn parses_json_with_numeric_amounts() {
+        let entries = parse_payouts(
+            r#"[{"recipient":"11111111111111111111111111111112","amount":0.25},
+                {"recipient":"11111111111111111111111111111113","mint":"So11111111111111111111111111111111111111112","amount":"3","memo":"x"}]"#,
+        )
+        .unwrap();
+        assert_eq!(entries[0].asset, "SOL");
+        assert_eq!(entries[0].amount, "0.25");
+        assert_eq!(entries[1].memo.as_deref(), Some("x"));
+    }
+
+    #[test]
+    fn converts_ui_amounts() {
+        assert_eq!(parse_ui_amount("1.5", 9).unwrap(), 1_500_000_000);
+        assert_eq!(parse_ui_amount(".25", 2).unwrap(), 25);
+        assert_eq!(parse_ui_amount("7.000", 0).unwrap(), 7);
+        assert_eq!(
+            parse_ui_amount("18446744073709551615", 0).unwrap(),
+            u64::MAX
+        );
+        assert!(parse_ui_amount("0.001", 2).is_err());
+        assert!(parse_ui_amount("0", 6).is_err());
+        assert!(parse_ui_amount("-1", 6).is_err());
+        assert!(parse_ui_amount("1e3", 6).is_err());
+        assert!(parse_ui_amount("18446744073709551616", 0).is_err());
+    }
+
+    #[test]
+    fn batch_id_tracks_content_and_order() {
+        let source = Pubkey::new_unique();
+        let entry = |recipient: &str, amount: &str| PayoutEntry {
+            recipient: recipient.to_string(),
+            asset: "SOL".to_string(),
+            amount: amount.to_string(),
+            memo: None,
+        };
+        let a = vec![entry("a", "1"), entry("b", "2")];
+        let b = vec![entry("b", "2"), entry("a", "1")];
+        assert_eq!(payout_batch_id(&source, &a), payout_batch_id(&source, &a));
+        assert_ne!(payout_batch_id(&source, &a), payout_batch_id(&source, &b));
+        assert_ne!(
+            payout_batch_id(&source, &a),
+            payout_batch_id(&Pubkey::new_unique(), &a)
+        );
+    }
+
+    #[test]
+    fn packs_lines_into_packet_sized_transactions() {
+        let source = Pubkey::new_unique();
+        let lines: Vec<PayoutLine> = (0..40)
+            .map(|index| {
+                let recipient = Pubkey::new_unique();
+                PayoutLine {
+                    index,
+                    recipient,
+                    asset: PayoutAsset::Sol,
+                    amount: 1,
+                    decimals: 9,
+                    fee: 0,
+                    memo: None,
+                    create_recipient_account: false,
+                    instructions: vec![create_transfer_instruction(&source, &recipient, 1)],
+                }
+            })
+            .collect();
+        let transactions = pack_payout_lines(&lines, |instructions| {
+            transaction_fits(instructions, &source)
+        })
+        .unwrap();
+        assert!(transactions.len() > 1 && transactions.len() < 40);
+        let packed: Vec<usize> = transactions
+            .iter()
+            .flat_map(|transaction| transaction.lines.clone())
+            .collect();
+        assert_eq!(packed, (0..40).collect::<Vec<_>>());
+        assert!(transactions
+            .iter()
+            .all(|transaction| transaction_fits(&transaction.instructions, &source)));
+
+        assert!(pack_payout_lines(&lines, |_| false).is_err());
+    }
+
+    #[test]
+    fn json_store_merges_progress_by_line() {
+        let path = std::env::temp_dir().join(format!(
+            "fnzero-payout-progress-{}.json",
+            Pubkey::new_unique()
+        ));
+        let mut store = JsonFileProgressStore::new(&path);
+        let progress = |line, status| PayoutProgress {
+            line,
+            status,
+            signature: Some("sig".to_string()),
+            last_valid_block_height: Some(10),
+            error: None,
+        };
+        store
+            .record(
+                "batch",
+                &[
+                    progress(0, PayoutLineStatus::Submitted),
+                    progress(1, PayoutLineStatus::Submitted),
+                ],
+            )
+            .unwrap();
+        store
+            .record("batch", &[progress(0, PayoutLineStatus::Confirmed)])
+            .unwrap();
+        let loaded = store.load("batch").unwrap();
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded[0].status, PayoutLineStatus::Confirmed);
+        assert_eq!(loaded[1].status, PayoutLineStatus::Submitted);
+        assert!(store.load("other").unwrap().is_empty());
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,688 @@
+// Pump 持仓止盈 / 止损 / 移动止损监控
+// 订阅 Pump.fun bonding curve 或 PumpSwap 池子金库账户，计算标记价格，触发阈值后调用无提示卖出
+//
+// 本地验证节点测试示例:
+//   solana-test-validator --url mainnet-beta --clone <BONDING_CURVE> --clone <MINT> --reset
+//   FNZERO_SAFE_WATCHER_TEST_MINT=<MINT> cargo test -p fnzero-safe-core --features sol-trade-sdk \
+//       position_watcher -- --ignored
+
+use futures::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use solana_account_decoder_client_types::UiAccountEncoding;
+use solana_client::{
+    nonblocking::{pubsub_client::PubsubClient, rpc_client::RpcClient},
+    rpc_config::RpcAccountInfoConfig,
+};
+use solana_commitment_config::CommitmentConfig;
+use solana_sdk::{pubkey::Pubkey, signature::Keypair};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use sol_trade_sdk::{
+    constants::WSOL_TOKEN_ACCOUNT,
+    trading::core::params::{PumpFunParams, PumpSwapParams},
+};
+
+use crate::operations::Language;
+use crate::solana_utils::{pumpfun_sell, pumpswap_sell};
+
+const BONDING_CURVE_RESERVES_END: usize = 8 + 8 * 5;
+const TOKEN_ACCOUNT_AMOUNT_OFFSET: usize = 64;
+const MINT_DECIMALS_OFFSET: usize = 44;
+const SOL_DECIMALS: i32 = 9;
+const RECONNECT_DELAY: Duration = Duration::from_secs(3);
+const SELL_RETRY_DELAY: Duration = Duration::from_secs(2);
+const MAX_SELL_ATTEMPTS: u32 = 3;
+
+/// 当前监控的交易场所
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PumpVenue {
+    PumpFun,
+    PumpSwap,
+}
+
+/// 触发卖出的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExitTrigger {
+    TakeProfit,
+    StopLoss,
+    TrailingStop,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum PositionStatus {
+    Watching,
+    /// 已触发但卖出尚未确认；重启后会直接重试卖出
+    Triggered {
+        trigger: ExitTrigger,
+        price_sol: f64,
+    },
+    Closed {
+        trigger: ExitTrigger,
+        price_sol: f64,
+    },
+}
+
+/// 持久化的持仓状态
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PositionState {
+    pub mint: String,
+    pub venue: Option<PumpVenue>,
+    /// 每个完整代币对应的 SOL 价格
+    pub entry_price_sol: Option<f64>,
+    pub peak_price_sol: Option<f64>,
+    pub last_price_sol: Option<f64>,
+    pub status: PositionStatus,
+    pub sell_attempts: u32,
+    pub last_error: Option<String>,
+    pub updated_at: u64,
+}
+
+impl PositionState {
+    fn new(mint: &str, entry_price_sol: Option<f64>) -> Self {
+        Self {
+            mint: mint.to_string(),
+            venue: None,
+            entry_price_sol,
+            peak_price_sol: entry_price_sol,
+            last_price_sol: None,
+            status: PositionStatus::Watching,
+            sell_attempts: 0,
+            last_error: None,
+            updated_at: now_unix_secs(),
+        }
+    }
+
+    /// 记录新的标记价格；返回峰值是否被刷新
+    pub fn record_price(&mut self, price_sol: f64) -> bool {
+        self.last_price_sol = Some(price_sol);
+        if self.entry_price_sol.is_none() {
+            self.entry_price_sol = Some(price_sol);
+        }
+        let raised = self.peak_price_sol.is_none_or(|peak| price_sol > peak);
+        if raised {
+            self.peak_price_sol = Some(price_sol);
+        }
+        raised
+    }
+}
+
+/// 监控参数；阈值均为相对百分比（10.0 = 10%）
+#[derive(Clone)]
+pub struct PositionWatchConfig {
+    pub mint: String,
+    pub rpc_url: String,
+    /// 为空时由 `rpc_url` 推导（http → ws，https → wss）
+    pub ws_url: Option<String>,
+    /// 为空时使用首次观测到的标记价格
+    pub entry_price_sol: Option<f64>,
+    pub take_profit_pct: Option<f64>,
+    pub stop_loss_pct: Option<f64>,
+    pub trailing_stop_pct: Option<f64>,
+    /// 卖出滑点（基点）
+    pub slippage: u64,
+    pub use_seed: bool,
+    pub state_path: PathBuf,
+    pub language: Language,
+}
+
+impl PositionWatchConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        Pubkey::from_str(&self.mint).map_err(|e| format!("无效的代币地址: {}", e))?;
+        if self.take_profit_pct.is_none()
+            && self.stop_loss_pct.is_none()
+            && self.trailing_stop_pct.is_none()
+        {
+            return Err("至少需要设置止盈、止损或移动止损之一".to_string());
+        }
+        for (label, value, max) in [
+            ("止盈", self.take_profit_pct, f64::MAX),
+            ("止损", self.stop_loss_pct, 100.0),
+            ("移动止损", self.trailing_stop_pct, 100.0),
+        ] {
+            if let Some(value) = value {
+                if !value.is_finite() || value <= 0.0 || value >= max {
+                    return Err(format!("{}百分比无效: {}", label, value));
+                }
+            }
+        }
+        if let Some(entry) = self.entry_price_sol {
+            if !entry.is_finite() || entry <= 0.0 {
+                return Err(format!("买入价格无效: {}", entry));
+            }
+        }
+        Ok(())
+    }
+
+    fn ws_url(&self) -> String {
+        self.ws_url.clone().unwrap_or_else(|| {
+            if let Some(rest) = self.rpc_url.strip_prefix("https://") {
+                format!("wss://{}", rest)
+            } else if let Some(rest) = self.rpc_url.strip_prefix("http://") {
+                format!("ws://{}", rest)
+            } else {
+                self.rpc_url.clone()
+            }
+        })
+    }
+}
+
+/// 按阈值判断是否需要卖出（止盈优先，其次止损、移动止损）
+pub fn evaluate_exit(state: &PositionState, config: &PositionWatchConfig) -> Option<ExitTrigger> {
+    let price = state.last_price_sol?;
+    let entry = state.entry_price_sol?;
+    if let Some(pct) = config.take_profit_pct {
+        if price >= entry * (1.0 + pct / 100.0) {
+            return Some(ExitTrigger::TakeProfit);
+        }
+    }
+    if let Some(pct) = config.stop_loss_pct {
+        if price <= entry * (1.0 - pct / 100.0) {
+            return Some(ExitTrigger::StopLoss);
+        }
+    }
+    if let (Some(pct), Some(peak)) = (config.trailing_stop_pct, state.peak_price_sol) {
+        if price <= peak * (1.0 - pct / 100.0) {
+            return Some(ExitTrigger::TrailingStop);
+        }
+    }
+    None
+}
+
+/// 解析 bonding curve 账户，返回（每个完整代币的 SOL 价格, 是否已完成）
+pub fn bonding_curve_mark_price(data: &[u8], decimals: u8) -> Result<(f64, bool), String> {
+    if data.len() <= BONDING_CURVE_RESERVES_END {
+        return Err("bonding curve 账户数据过短".to_string());
+    }
+    let virtual_token_reserves = read_u64(data, 8).unwrap_or_default();
+    let virtual_sol_reserves = read_u64(data, 16).unwrap_or_default();
+    let complete = data[BONDING_CURVE_RESERVES_END] != 0;
+    if virtual_token_reserves == 0 {
+        return Ok((0.0, complete));
+    }
+    Ok((
+        reserves_price(virtual_sol_reserves, virtual_token_reserves, decimals),
+        complete,
+    ))
+}
+
+/// 读取 SPL Token / Token-2022 账户余额
+pub fn token_account_amount(data: &[u8]) -> Option<u64> {
+    read_u64(data, TOKEN_ACCOUNT_AMOUNT_OFFSET)
+}
+
+pub fn reserves_price(sol_reserves: u64, token_reserves: u64, decimals: u8) -> f64 {
+    (sol_reserves as f64 / 10f64.powi(SOL_DECIMALS))
+        / (token_reserves as f64 / 10f64.powi(decimals as i32))
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Option<u64> {
+    data.get(offset..offset + 8)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(u64::from_le_bytes)
+}
+
+fn now_unix_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+/// 监控目标：bonding curve，或 PumpSwap 池子的 base / quote 金库
+#[derive(Debug, Clone, Copy)]
+enum WatchTarget {
+    BondingCurve(Pubkey),
+    Pool {
+        base_vault: Pubkey,
+        quote_vault: Pubkey,
+    },
+}
+
+enum WatchOutcome {
+    Triggered,
+    Migrated,
+}
+
+/// 单个 mint 的持仓监控器
+pub struct PositionWatcher {
+    config: PositionWatchConfig,
+    state: PositionState,
+    decimals: u8,
+}
+
+impl PositionWatcher {
+    /// 创建监控器；状态文件存在且属于同一 mint 时恢复之前的状态
+    pub fn new(config: PositionWatchConfig) -> Result<Self, String> {
+        config.validate()?;
+        let state = match load_state(&config.state_path)? {
+            Some(state) if state.mint == config.mint => state,
+            Some(state) => {
+                return Err(format!(
+                    "状态文件 {} 属于其他代币: {}",
+                    config.state_path.display(),
+                    state.mint
+                ))
+            }
+            None => PositionState::new(&config.mint, config.entry_price_sol),
+        };
+        Ok(Self {
+            config,
+            state,
+            decimals: 6,
+        })
+    }
+
+    pub fn state(&self) -> &PositionState {
+        &self.state
+    }
+
+    /// 持续监控直到触发并卖出成功（或卖出重试耗尽）
+    pub async fn run(&mut self, keypair: &Keypair) -> Result<PositionState, String> {
+        if matches!(self.state.status, PositionStatus::Closed { .. }) {
+            return Ok(self.state.clone());
+        }
+        let rpc = RpcClient::new_with_commitment(
+            self.config.rpc_url.clone(),
+            CommitmentConfig::confirmed(),
+        );
+
+        if self.state.status == PositionStatus::Watching {
+            let mint = Pubkey::from_str(&self.config.mint).map_err(|e| e.to_string())?;
+            self.decimals = fetch_mint_decimals(&rpc, &mint).await?;
+            let mut target = self.resolve_target(&rpc, &mint).await?;
+            loop {
+                self.poll_price(&rpc, target).await?;
+                if self.check_trigger()? {
+                    break;
+                }
+                match self.watch(target).await {
+                    Ok(WatchOutcome::Triggered) => break,
+                    Ok(WatchOutcome::Migrated) => {
+                        target = self.resolve_target(&rpc, &mint).await?;
+                    }
+                    Err(error) => {
+                        self.state.last_error = Some(error);
+                        self.save()?;
+                        tokio::time::sleep(RECONNECT_DELAY).await;
+                    }
+                }
+            }
+        }
+
+        self.sell(keypair).await
+    }
+
+    async fn resolve_target(
+        &mut self,
+        rpc: &RpcClient,
+        mint: &Pubkey,
+    ) -> Result<WatchTarget, String> {
+        let target = match PumpFunParams::from_mint_by_rpc(rpc, mint).await {
+            Ok(params) if !params.bonding_curve.complete => {
+                self.state.venue = Some(PumpVenue::PumpFun);
+                WatchTarget::BondingCurve(params.bonding_curve.account)
+            }
+            _ => {
+                let params = PumpSwapParams::from_mint_by_rpc(rpc, mint)
+                    .await
+                    .map_err(|e| format!("获取 Pump.fun / PumpSwap 参数失败: {}", e))?;
+                if params.base_mint != *mint || params.quote_mint != WSOL_TOKEN_ACCOUNT {
+                    return Err("仅支持以 SOL 计价的 PumpSwap 池".to_string());
+                }
+                self.state.venue = Some(PumpVenue::PumpSwap);
+                WatchTarget::Pool {
+                    base_vault: params.pool_base_token_account,
+                    quote_vault: params.pool_quote_token_account,
+                }
+            }
+        };
+        self.save()?;
+        Ok(target)
+    }
+
+    async fn poll_price(&mut self, rpc: &RpcClient, target: WatchTarget) -> Result<(), String> {
+        let keys = match target {
+            WatchTarget::BondingCurve(curve) => vec![curve],
+            WatchTarget::Pool {
+                base_vault,
+                quote_vault,
+            } => vec![base_vault, quote_vault],
+        };
+        let accounts = rpc
+            .get_multiple_accounts(&keys)
+            .await
+            .map_err(|e| format!("读取价格账户失败: {}", e))?;
+        let data = accounts
+            .into_iter()
+            .map(|account| account.map(|account| account.data))
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| "价格账户不存在".to_string())?;
+        let price = match target {
+            WatchTarget::BondingCurve(_) => bonding_curve_mark_price(&data[0], self.decimals)?.0,
+            WatchTarget::Pool { .. } => {
+                let base = token_account_amount(&data[0]).unwrap_or_default();
+                let quote = token_account_amount(&data[1]).unwrap_or_default();
+                if base == 0 {
+                    return Err("PumpSwap 池子 base 储备为 0".to_string());
+                }
+                reserves_price(quote, base, self.decimals)
+            }
+        };
+        self.update_price(price)
+    }
+
+    async fn watch(&mut self, target: WatchTarget) -> Result<WatchOutcome, String> {
+        let client = PubsubClient::new(self.config.ws_url())
+            .await
+            .map_err(|e| format!("连接 WebSocket 失败: {}", e))?;
+        let config = || {
+            Some(RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                commitment: Some(CommitmentConfig::confirmed()),
+                ..RpcAccountInfoConfig::default()
+            })
+        };
+        let subscribe_error = |e| format!("订阅价格账户失败: {}", e);
+
+        match target {
+            WatchTarget::BondingCurve(curve) => {
+                let (mut updates, unsubscribe) = client
+                    .account_subscribe(&curve, config())
+                    .await
+                    .map_err(subscribe_error)?;
+                let mut outcome = Err("bonding curve 订阅已断开".to_string());
+                while let Some(update) = updates.next().await {
+                    let Some(data) = update.value.data.decode() else {
+                        continue;
+                    };
+                    let (price, complete) = bonding_curve_mark_price(&data, self.decimals)?;
+                    if complete {
+                        outcome = Ok(WatchOutcome::Migrated);
+                        break;
+                    }
+                    self.update_price(price)?;
+                    if self.check_trigger()? {
+                        outcome = Ok(WatchOutcome::Triggered);
+                        break;
+                    }
+                }
+                drop(updates);
+                unsubscribe().await;
+                outcome
+            }
+            WatchTarget::Pool {
+                base_vault,
+                quote_vault,
+            } => {
+                let (base_updates, base_unsubscribe) = client
+                    .account_subscribe(&base_vault, config())
+                    .await
+                    .map_err(subscribe_error)?;
+                let (quote_updates, quote_unsubscribe) = client
+                    .account_subscribe(&quote_vault, config())
+                    .await
+                    .map_err(subscribe_error)?;
+                let mut updates = stream::select(
+                    base_updates.map(|update| (true, update)),
+                    quote_updates.map(|update| (false, update)),
+                );
+                let mut base = None;
+                let mut quote = None;
+                let mut outcome = Err("PumpSwap 订阅已断开".to_string());
+                while let Some((is_base, update)) = updates.next().await {
+                    let amount = update
+                        .value
+                        .data
+                        .decode()
+                        .and_then(|data| token_account_amount(&data));
+                    if is_base {
+                        base = amount.or(base);
+                    } else {
+                        quote = amount.or(quote);
+                    }
+                    let (Some(base), Some(quote)) = (base, quote) else {
+                        continue;
+                    };
+                    if base == 0 {
+                        continue;
+                    }
+                    self.update_price(reserves_price(quote, base, self.decimals))?;
+                    if self.check_trigger()? {
+                        outcome = Ok(WatchOutcome::Triggered);
+                        break;
+                    }
+                }
+                drop(updates);
+                base_unsubscribe().await;
+                quote_unsubscribe().await;
+                outcome
+            }
+        }
+    }
+
+    fn update_price(&mut self, price_sol: f64) -> Result<(), String> {
+        let first = self.state.entry_price_sol.is_none();
+        if self.state.record_price(price_sol) || first {
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    fn check_trigger(&mut self) -> Result<bool, String> {
+        let Some(trigger) = evaluate_exit(&self.state, &self.config) else {
+            return Ok(false);
+        };
+        let price_sol = self.state.last_price_sol.unwrap_or_default();
+        self.state.status = PositionStatus::Triggered { trigger, price_sol };
+        self.save()?;
+        if self.config.language == Language::Chinese {
+            println!("🎯 触发 {:?}，标记价格 {} SOL", trigger, price_sol);
+        } else {
+            println!("🎯 {:?} triggered at mark price {} SOL", trigger, price_sol);
+        }
+        Ok(true)
+    }
+
+    async fn sell(&mut self, keypair: &Keypair) -> Result<PositionState, String> {
+        let PositionStatus::Triggered { trigger, price_sol } = self.state.status.clone() else {
+            return Ok(self.state.clone());
+        };
+        loop {
+            self.state.sell_attempts += 1;
+            let result = match self.state.venue {
+                Some(PumpVenue::PumpFun) => {
+                    pumpfun_sell::handle_pumpfun_sell_no_prompt(
+                        keypair,
+                        &self.config.mint,
+                        &self.config.rpc_url,
+                        self.config.slippage,
+                        self.config.use_seed,
+                        self.config.language,
+                        true,
+                    )
+                    .await
+                }
+                _ => {
+                    pumpswap_sell::handle_pumpswap_sell_no_prompt(
+                        keypair,
+                        &self.config.mint,
+                        &self.config.rpc_url,
+                        self.config.slippage,
+                        self.config.use_seed,
+                        self.config.language,
+                        true,
+                    )
+                    .await
+                }
+            };
+            match result {
+                Ok(()) => {
+                    self.state.status = PositionStatus::Closed { trigger, price_sol };
+                    self.state.last_error = None;
+                    self.save()?;
+                    return Ok(self.state.clone());
+                }
+                Err(error) => {
+                    self.state.last_error = Some(error.clone());
+                    self.save()?;
+                    if self.state.sell_attempts >= MAX_SELL_ATTEMPTS {
+                        return Err(format!(
+                            "卖出失败（已尝试 {} 次）: {}",
+                            self.state.sell_attempts, error
+                        ));
+                    }
+                    tokio::time::sleep(SELL_RETRY_DELAY).await;
+                }
+            }
+        }
+    }
+
+    fn save(&mut self) -> Result<(), String> {
+        self.state.updated_at = now_unix_secs();
+        save_state(&self.config.state_path, &self.state)
+    }
+}
+
+async fn fetch_mint_decimals(rpc: &RpcClient, mint: &Pubkey) -> Result<u8, String> {
+    let account = rpc
+        .get_account(mint)
+        .await
+        .map_err(|e| format!("读取 mint 账户失败: {}", e))?;
+    account
+        .data
+        .get(MINT_DECIMALS_OFFSET)
+        .copied()
+        .ok_or_else(|| "mint 账户数据无效".to_string())
+}
+
+/// 读取状态文件；不存在时返回 `None`
+pub fn load_state(path: &Path) -> Result<Option<PositionState>, String> {
+    match std::fs::read_to_string(path) {
+        Ok(content) => serde_json::from_str(&content)
+            .map(Some)
+            .map_err(|e| format!("解析持仓状态失败 {}: {}", path.display(), e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(format!("读取持仓状态失败 {}: {}", path.display(), e)),
+    }
+}
+
+/// 先写临时文件再重命名，避免进程中断留下半截状态
+pub fn save_state(path: &Path, state: &PositionState) -> Result<(), String> {
+    let json =
+        serde_json::to_string_pretty(state).map_err(|e| format!("序列化持仓状态失败: {}", e))?;
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, json)
+        .and_then(|_| std::fs::rename(&tmp, path))
+        .map_err(|e| format!("保存持仓状态失败 {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(tp: Option<f64>, sl: Option<f64>, trailing: Option<f64>) -> PositionWatchConfig {
+        PositionWatchConfig {
+            mint: Pubkey::new_unique().to_string(),
+            rpc_url: "http://127.0.0.1:8899".to_string(),
+            ws_url: None,
+            entry_price_sol: Some(1.0),
+            take_profit_pct: tp,
+            stop_loss_pct: sl,
+            trailing_stop_pct: trailing,
+            slippage: 500,
+            use_seed: false,
+            state_path: std::env::temp_dir()
+                .join(format!("fnzero-position-{}.json", Pubkey::new_unique())),
+            language: Language::English,
+        }
+    }
+
+    #[test]
+    fn evaluates_thresholds() {
+        let config = config(Some(50.0), Some(20.0), Some(10.0));
+        let mut state = PositionState::new(&config.mint, Some(1.0));
+
+        state.record_price(1.05);
+        assert_eq!(evaluate_exit(&state, &config), None);
+        state.record_price(1.5);
+        assert_eq!(
+            evaluate_exit(&state, &config),
+            Some(ExitTrigger::TakeProfit)
+        );
+
+        let mut state = PositionState::new(&config.mint, Some(1.0));
+        state.record_price(1.3);
+        state.record_price(1.16);
+        assert_eq!(
+            evaluate_exit(&state, &config),
+            Some(ExitTrigger::TrailingStop)
+        );
+
+        let mut state = PositionState::new(&config.mint, Some(1.0));
+        state.record_price(0.8);
+        assert_eq!(evaluate_exit(&state, &config), Some(ExitTrigger::StopLoss));
+    }
+
+    #[test]
+    fn decodes_bonding_curve_and_token_accounts() {
+        let mut curve = vec![0u8; 8];
+        curve.extend_from_slice(&1_000_000_000_000u64.to_le_bytes());
+        curve.extend_from_slice(&30_000_000_000u64.to_le_bytes());
+        curve.extend_from_slice(&[0u8; 24]);
+        curve.push(1);
+        let (price, complete) = bonding_curve_mark_price(&curve, 6).unwrap();
+        assert!((price - 0.00003).abs() < 1e-12);
+        assert!(complete);
+        assert!(bonding_curve_mark_price(&curve[..40], 6).is_err());
+
+        let mut token_account = vec![0u8; 165];
+        token_account[64..72].copy_from_slice(&42u64.to_le_bytes());
+        assert_eq!(token_account_amount(&token_account), Some(42));
+    }
+
+    #[test]
+    fn restores_persisted_state() {
+        let config = config(None, Some(30.0), None);
+        let mut watcher = PositionWatcher::new(config.clone()).unwrap();
+        watcher.update_price(2.0).unwrap();
+        watcher.update_price(0.6).unwrap();
+        assert!(watcher.check_trigger().unwrap());
+
+        let restored = PositionWatcher::new(config.clone()).unwrap();
+        assert_eq!(restored.state().peak_price_sol, Some(2.0));
+        assert!(matches!(
+            restored.state().status,
+            PositionStatus::Triggered {
+                trigger: ExitTrigger::StopLoss,
+                ..
+            }
+        ));
+
+        let mut other = config.clone();
+        other.mint = Pubkey::new_unique().to_string();
+        assert!(PositionWatcher::new(other).is_err());
+        std::fs::remove_file(&config.state_path).unwrap();
+    }
+
+    #[tokio::test]
+    #[ignore = "requires a local validator with cloned Pump accounts"]
+    async fn reads_mark_price_from_local_validator() {
+        let mint = std::env::var("FNZERO_SAFE_WATCHER_TEST_MINT").unwrap();
+        let mut config = config(Some(1000.0), None, None);
+        config.mint = mint.clone();
+        config.entry_price_sol = None;
+        let mut watcher = PositionWatcher::new(config.clone()).unwrap();
+        let rpc = RpcClient::new(config.rpc_url.clone());
+        let mint = Pubkey::from_str(&mint).unwrap();
+        watcher.decimals = fetch_mint_decimals(&rpc, &mint).await.unwrap();
+        let target = watcher.resolve_target(&rpc, &mint).await.unwrap();
+        watcher.poll_price(&rpc, target).await.unwrap();
+        assert!(watcher.state().last_price_sol.unwrap() > 0.0);
+        std::fs::remove_file(&config.state_path).unwrap();
+    }
+}
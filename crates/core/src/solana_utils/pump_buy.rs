@@ -0,0 +1,260 @@
+// Pump.fun / PumpSwap 买入功能模块
+// 使用 SOL 或 WSOL 买入代币；Pump.fun 内盘已完成（迁移）时自动改走 PumpSwap 外盘
+
+use colored::Colorize;
+use solana_sdk::{pubkey::Pubkey, signature::Keypair, signer::Signer};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::operations::Language;
+#[cfg(feature = "sol-trade-sdk")]
+use crate::solana_utils::{pump_route::resolve_pump_route, swqos::cli_trade_client};
+
+#[cfg(feature = "sol-trade-sdk")]
+use sol_trade_sdk::{
+    common::{fast_fn::get_associated_token_address_with_program_id_fast_use_seed, GasFeeStrategy},
+    constants::{TOKEN_PROGRAM, WSOL_TOKEN_ACCOUNT},
+    trading::factory::DexType,
+    SolanaTrade, TradeBuyParams, TradeTokenType,
+};
+
+/// 买入参数
+#[cfg(feature = "sol-trade-sdk")]
+#[derive(Clone)]
+pub struct PumpBuyOptions {
+    /// 花费的 SOL/WSOL 数量（lamports）
+    pub amount_lamports: u64,
+    /// 资金来源：SOL 或 WSOL
+    pub input_token_type: TradeTokenType,
+    /// 滑点（基点，100 = 1%）
+    pub slippage: u64,
+    /// 是否在交易中创建代币 ATA
+    pub create_ata: bool,
+}
+
+/// 解析资金类型（sol / wsol）
+#[cfg(feature = "sol-trade-sdk")]
+pub fn parse_buy_input_token(value: &str) -> Result<TradeTokenType, String> {
+    match value.trim().to_lowercase().as_str() {
+        "" | "sol" => Ok(TradeTokenType::SOL),
+        "wsol" => Ok(TradeTokenType::WSOL),
+        other => Err(format!("不支持的资金类型: {} (仅支持 sol / wsol)", other)),
+    }
+}
+
+/// 检查买入资金余额
+#[cfg(feature = "sol-trade-sdk")]
+async fn check_buy_balance(
+    client: &SolanaTrade,
+    owner: &Pubkey,
+    input_token_type: &TradeTokenType,
+) -> Result<u64, String> {
+    if *input_token_type == TradeTokenType::WSOL {
+        let wsol_ata = get_associated_token_address_with_program_id_fast_use_seed(
+            owner,
+            &WSOL_TOKEN_ACCOUNT,
+            &TOKEN_PROGRAM,
+            false,
+        );
+        return match client
+            .infrastructure
+            .rpc
+            .get_token_account_balance(&wsol_ata)
+            .await
+        {
+            Ok(balance) => balance
+                .amount
+                .parse::<u64>()
+                .map_err(|_| "解析 WSOL 余额失败".to_string()),
+            Err(_) => Ok(0),
+        };
+    }
+    client
+        .infrastructure
+        .rpc
+        .get_balance(owner)
+        .await
+        .map_err(|e| format!("查询 SOL 余额失败: {}", e))
+}
+
+/// 处理 Pump.fun / PumpSwap 买入（无交互式提示版本，用于 CLI 与主菜单）
+#[cfg(feature = "sol-trade-sdk")]
+pub async fn handle_pump_buy_no_prompt(
+    keypair: &Keypair,
+    mint: &str,
+    rpc_url: &str,
+    dex_type: DexType,
+    options: PumpBuyOptions,
+    language: Language,
+    skip_confirmation: bool,
+) -> Result<(), String> {
+    let zh = language == Language::Chinese;
+    let mint_pubkey = Pubkey::from_str(mint).map_err(|e| format!("无效的代币地址: {}", e))?;
+    if options.amount_lamports == 0 {
+        return Err(if zh {
+            "买入金额必须大于 0".to_string()
+        } else {
+            "Buy amount must be greater than 0".to_string()
+        });
+    }
+    let input_label = if options.input_token_type == TradeTokenType::WSOL {
+        "WSOL"
+    } else {
+        "SOL"
+    };
+    let amount_sol = options.amount_lamports as f64 / 1_000_000_000.0;
+
+    if zh {
+        println!("📍 代币地址: {}", mint.yellow());
+        println!("🌐 RPC: {}", rpc_url);
+        println!("💵 买入金额: {} {}", amount_sol, input_label);
+        println!("📊 滑点容忍度: {}%", options.slippage as f64 / 100.0);
+        println!(
+            "🔧 创建代币 ATA: {}",
+            if options.create_ata { "是" } else { "否" }
+        );
+    } else {
+        println!("📍 Token Address: {}", mint.yellow());
+        println!("🌐 RPC: {}", rpc_url);
+        println!("💵 Amount: {} {}", amount_sol, input_label);
+        println!("📊 Slippage: {}%", options.slippage as f64 / 100.0);
+        println!(
+            "🔧 Create token ATA: {}",
+            if options.create_ata { "Yes" } else { "No" }
+        );
+    }
+
+    let payer = Arc::new(keypair.insecure_clone());
+    let (client, tip_sol) = cli_trade_client(payer.clone(), rpc_url, false).await?;
+
+    let available =
+        check_buy_balance(&client, &keypair.pubkey(), &options.input_token_type).await?;
+    if available < options.amount_lamports {
+        return Err(if zh {
+            format!(
+                "❌ {} 余额不足: 可用 {} lamports，需要 {} lamports",
+                input_label, available, options.amount_lamports
+            )
+        } else {
+            format!(
+                "❌ Insufficient {} balance: available {} lamports, required {} lamports",
+                input_label, available, options.amount_lamports
+            )
+        });
+    }
+
+    if !skip_confirmation {
+        use std::io::{self, Write};
+        print!(
+            "\n{}",
+            if zh {
+                "❓ 确认买入? (yes/no, 默认 no): "
+            } else {
+                "❓ Confirm buy? (yes/no, default: no): "
+            }
+            .yellow()
+        );
+        io::stdout().flush().map_err(|e| e.to_string())?;
+        let mut confirm = String::new();
+        io::stdin()
+            .read_line(&mut confirm)
+            .map_err(|e| e.to_string())?;
+        let confirm_trimmed = confirm.trim().to_lowercase();
+        if confirm_trimmed != "yes" && confirm_trimmed != "y" {
+            return Err(if zh {
+                "❌ 操作已取消".to_string()
+            } else {
+                "❌ Operation cancelled".to_string()
+            });
+        }
+    }
+
+    if zh {
+        println!("\n{}", "📡 从链上获取池子参数...".cyan());
+    } else {
+        println!("\n{}", "📡 Fetching pool parameters...".cyan());
+    }
+    let route = resolve_pump_route(&client.infrastructure.rpc, &mint_pubkey, dex_type).await?;
+    let actual_dex_type = route.dex_type;
+    let venue = if actual_dex_type == DexType::PumpFun {
+        "Pump.fun"
+    } else {
+        "PumpSwap"
+    };
+    if actual_dex_type != dex_type {
+        if zh {
+            println!("🔀 内盘已完成，自动改走 {}", venue);
+        } else {
+            println!("🔀 Bonding curve complete, routing to {}", venue);
+        }
+    } else if zh {
+        println!("✅ 使用 {}", venue);
+    } else {
+        println!("✅ Using {}", venue);
+    }
+
+    let recent_blockhash = client
+        .infrastructure
+        .rpc
+        .get_latest_blockhash()
+        .await
+        .map_err(|e| format!("获取 blockhash 失败: {}", e))?;
+
+    let gas_fee_strategy = GasFeeStrategy::new();
+    gas_fee_strategy.set_global_fee_strategy(150000, 150000, 500000, 500000, tip_sol, tip_sol);
+
+    // PumpSwap 以 WSOL 计价：原生 SOL 会临时包装为 WSOL，交易结束后关闭该 ATA
+    let wrap_native_sol =
+        actual_dex_type == DexType::PumpSwap && options.input_token_type == TradeTokenType::SOL;
+
+    let buy_params = TradeBuyParams {
+        dex_type: actual_dex_type,
+        input_token_type: options.input_token_type.clone(),
+        mint: mint_pubkey,
+        input_token_amount: options.amount_lamports,
+        slippage_basis_points: Some(options.slippage),
+        recent_blockhash: Some(recent_blockhash),
+        extension_params: route.extension_params,
+        address_lookup_table_account: None,
+        wait_tx_confirmed: true,
+        wait_for_all_submits: false,
+        create_input_token_ata: wrap_native_sol,
+        close_input_token_ata: wrap_native_sol,
+        create_mint_ata: options.create_ata,
+        durable_nonce: None,
+        fixed_output_token_amount: None,
+        gas_fee_strategy,
+        simulate: false,
+        use_exact_sol_amount: Some(true),
+        grpc_recv_us: None,
+    };
+
+    if zh {
+        println!("{}", "📤 发送交易到链上...".bright_blue());
+    } else {
+        println!("{}", "📤 Sending transaction...".bright_blue());
+    }
+
+    match client.buy(buy_params).await {
+        Ok((true, signatures, _error, _latency_info)) => {
+            if zh {
+                println!("\n{}", "✅ 买入成功！".green().bold());
+            } else {
+                println!("\n{}", "✅ Buy successful!".green().bold());
+            }
+            let label = if zh { "交易签名" } else { "Signature" };
+            for signature in &signatures {
+                println!("   {}: {}", label, signature.to_string().yellow());
+                println!("   🔗 https://solscan.io/tx/{}", signature);
+            }
+            Ok(())
+        }
+        Ok((false, _signatures, error, _latency_info)) => {
+            let error_msg = error
+                .map(|e| e.to_string())
+                .unwrap_or_else(|| "Unknown error".to_string());
+            Err(format!("买入失败: {}", error_msg))
+        }
+        Err(e) => Err(format!("买入失败: {}", e)),
+    }
+}
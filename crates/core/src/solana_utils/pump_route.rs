@@ -0,0 +1,154 @@
+// Pump.fun / PumpSwap 路由解析
+// 桌面端与 CLI 的买入、卖出共用：内盘未完成走 Pump.fun，已完成或内盘参数不可用时改走 PumpSwap
+
+use solana_sdk::pubkey::Pubkey;
+use std::time::Duration;
+
+use sol_trade_sdk::{
+    common::SolanaRpcClient,
+    constants::WSOL_TOKEN_ACCOUNT,
+    instruction::utils::pumpswap,
+    trading::{
+        core::params::{DexParamEnum, PumpFunParams, PumpSwapParams},
+        factory::DexType,
+    },
+    TradeTokenType,
+};
+
+const PUMPFUN_PARAM_RPC_ATTEMPTS: usize = 2;
+const PUMPFUN_PARAM_RPC_TIMEOUT_MS: u64 = 1_500;
+const PUMPFUN_PARAM_RETRY_DELAY_MS: u64 = 75;
+
+/// 解析后的交易路由
+pub struct PumpRoute {
+    /// 实际使用的交易场所
+    pub dex_type: DexType,
+    /// 对应场所的池子参数
+    pub extension_params: DexParamEnum,
+    /// 卖出所得的代币类型：Pump.fun 为 SOL，PumpSwap 为 WSOL
+    pub output_token_type: TradeTokenType,
+    /// 卖出时是否需要创建 WSOL ATA
+    pub create_output_token_ata: bool,
+}
+
+impl PumpRoute {
+    fn pumpfun(params: PumpFunParams) -> Self {
+        Self {
+            dex_type: DexType::PumpFun,
+            extension_params: DexParamEnum::PumpFun(params.with_quote_mint(WSOL_TOKEN_ACCOUNT)),
+            output_token_type: TradeTokenType::SOL,
+            create_output_token_ata: false,
+        }
+    }
+
+    fn pumpswap(params: PumpSwapParams) -> Self {
+        Self {
+            dex_type: DexType::PumpSwap,
+            extension_params: DexParamEnum::PumpSwap(params),
+            output_token_type: TradeTokenType::WSOL,
+            create_output_token_ata: true,
+        }
+    }
+}
+
+/// 判断 RPC 读取错误是否值得重试（超时、限流、连接重置等）
+fn is_retryable_rpc_read_error(error: &anyhow::Error) -> bool {
+    let message = error.to_string().to_ascii_lowercase();
+    message.contains("error sending request")
+        || message.contains("timed out")
+        || message.contains("timeout")
+        || message.contains("超时")
+        || message.contains("connection rate limits exceeded")
+        || message.contains("too many requests")
+        || message.contains("429")
+        || message.contains("econnreset")
+        || message.contains("connection reset")
+        || message.contains("connection refused")
+        || message.contains("temporarily unavailable")
+}
+
+async fn pumpfun_params_from_mint_by_rpc(
+    rpc: &SolanaRpcClient,
+    mint: &Pubkey,
+) -> Result<PumpFunParams, anyhow::Error> {
+    let mut last_error = None;
+    for attempt in 1..=PUMPFUN_PARAM_RPC_ATTEMPTS {
+        let retry_error = match tokio::time::timeout(
+            Duration::from_millis(PUMPFUN_PARAM_RPC_TIMEOUT_MS),
+            PumpFunParams::from_mint_by_rpc(rpc, mint),
+        )
+        .await
+        {
+            Ok(Ok(params)) => return Ok(params),
+            Ok(Err(error)) if is_retryable_rpc_read_error(&error) => error,
+            Ok(Err(error)) => return Err(error),
+            Err(_) => anyhow::anyhow!(
+                "Pump.fun 参数读取超时 (timeout after {} ms)",
+                PUMPFUN_PARAM_RPC_TIMEOUT_MS
+            ),
+        };
+
+        last_error = Some(retry_error);
+        if attempt < PUMPFUN_PARAM_RPC_ATTEMPTS {
+            tokio::time::sleep(Duration::from_millis(PUMPFUN_PARAM_RETRY_DELAY_MS)).await;
+        }
+    }
+
+    Err(last_error.unwrap_or_else(|| anyhow::anyhow!("Pump.fun 参数读取失败")))
+}
+
+async fn pumpswap_params_from_mint_by_rpc(
+    rpc: &SolanaRpcClient,
+    mint: &Pubkey,
+) -> Result<PumpSwapParams, anyhow::Error> {
+    let (pool_address, pool_data) = pumpswap::find_by_mint(rpc, mint).await?;
+    PumpSwapParams::from_pool_data(rpc, &pool_address, &pool_data).await
+}
+
+/// 选择 Pump 路由：请求 Pump.fun 时内盘未完成走内盘，否则或内盘参数不可用时走 PumpSwap
+pub async fn resolve_pump_route(
+    rpc: &SolanaRpcClient,
+    mint: &Pubkey,
+    requested_dex_type: DexType,
+) -> Result<PumpRoute, String> {
+    match requested_dex_type {
+        DexType::PumpFun => match pumpfun_params_from_mint_by_rpc(rpc, mint).await {
+            Ok(params) => {
+                if !params.bonding_curve.complete {
+                    return Ok(PumpRoute::pumpfun(params));
+                }
+
+                pumpswap_params_from_mint_by_rpc(rpc, mint)
+                    .await
+                    .map(PumpRoute::pumpswap)
+                    .map_err(|pumpswap_err| {
+                        format!(
+                            "该代币 Pump.fun bonding curve 已完成，但未找到可用 PumpSwap 池，无法自动改走外盘: {}",
+                            pumpswap_err
+                        )
+                    })
+            }
+            Err(pumpfun_err) => pumpswap_params_from_mint_by_rpc(rpc, mint)
+                .await
+                .map(PumpRoute::pumpswap)
+                .map_err(|pumpswap_err| {
+                    if is_retryable_rpc_read_error(&pumpfun_err) {
+                        format!(
+                            "RPC 节点暂时无法读取 Pump.fun 参数: {}；同时未找到 PumpSwap 池: {}。该代币可能仍在 Pump.fun 内盘，请稍后重试，或在网络设置中换用稳定的主网 RPC。",
+                            pumpfun_err, pumpswap_err
+                        )
+                    } else {
+                        format!(
+                            "获取 Pump.fun 参数失败: {}；同时获取 PumpSwap 参数失败: {}。请确认代币还在 Pump.fun 内盘或已经迁移到 PumpSwap 外盘。",
+                            pumpfun_err, pumpswap_err
+                        )
+                    }
+                }),
+        },
+        DexType::PumpSwap => pumpswap_params_from_mint_by_rpc(rpc, mint)
+            .await
+            .map(PumpRoute::pumpswap)
+            .map_err(|e| format!("获取 PumpSwap 参数失败: {}", e)),
+        _ => Err("不支持的交易类型".to_string()),
+    }
+}
@@ -8,21 +8,18 @@ use std::str::FromStr;
 use std::sync::Arc;
 
 use crate::operations::Language;
+#[cfg(feature = "sol-trade-sdk")]
+use crate::solana_utils::swqos::cli_trade_client;
 
 #[cfg(feature = "sol-trade-sdk")]
 use sol_trade_sdk::{
-    common::{
-        fast_fn::get_associated_token_address_with_program_id_fast_use_seed, GasFeeStrategy,
-        TradeConfig,
-    },
-    swqos::SwqosConfig,
+    common::{fast_fn::get_associated_token_address_with_program_id_fast_use_seed, GasFeeStrategy},
     trading::{
         core::params::{DexParamEnum, PumpFunParams},
         factory::DexType,
     },
     SolanaTrade, TradeSellParams, TradeTokenType,
 };
-use solana_commitment_config::CommitmentConfig;
 
 /// 处理 Pump.fun 内盘卖出操作（交互式，单次调用）
 #[cfg(feature = "sol-trade-sdk")]
@@ -48,19 +45,9 @@ pub async fn handle_pumpfun_sell(
     println!("\n{}", "🔍 检查代币余额...".cyan());
 
     let payer = Arc::new(keypair.insecure_clone());
-    let commitment = CommitmentConfig::confirmed();
-    let swqos_configs: Vec<SwqosConfig> = vec![SwqosConfig::Default(rpc_url.to_string())];
-
-    let trade_config = TradeConfig::builder(rpc_url.to_string(), swqos_configs, commitment)
-        .create_wsol_ata_on_startup(false)
-        .use_seed_optimize(use_seed)
-        .check_min_tip(false)
-        .log_enabled(false)
-        .swqos_cores_from_end(false)
-        .mev_protection(false)
-        .build();
-
-    let client = SolanaTrade::new(payer.clone(), trade_config).await;
+    let (client, tip_sol) = cli_trade_client(payer.clone(), rpc_url, use_seed)
+        .await
+        .map_err(|e| anyhow::anyhow!(e))?;
 
     let (token_balance, decimals, token_program) =
         check_token_balance(&client, &mint_pubkey, &keypair.pubkey(), use_seed).await?;
@@ -118,7 +105,7 @@ pub async fn handle_pumpfun_sell(
     let recent_blockhash = client.infrastructure.rpc.get_latest_blockhash().await?;
 
     let gas_fee_strategy = GasFeeStrategy::new();
-    gas_fee_strategy.set_global_fee_strategy(150000, 150000, 500000, 500000, 0.001, 0.001);
+    gas_fee_strategy.set_global_fee_strategy(150000, 150000, 500000, 500000, tip_sol, tip_sol);
 
     println!("\n{}", "🚀 构建卖出交易...".cyan());
 
@@ -274,19 +261,7 @@ pub async fn handle_pumpfun_sell_no_prompt(
     }
 
     let payer = Arc::new(keypair.insecure_clone());
-    let commitment = CommitmentConfig::confirmed();
-    let swqos_configs: Vec<SwqosConfig> = vec![SwqosConfig::Default(rpc_url.to_string())];
-
-    let trade_config = TradeConfig::builder(rpc_url.to_string(), swqos_configs, commitment)
-        .create_wsol_ata_on_startup(false)
-        .use_seed_optimize(use_seed)
-        .check_min_tip(false)
-        .log_enabled(false)
-        .swqos_cores_from_end(false)
-        .mev_protection(false)
-        .build();
-
-    let client = SolanaTrade::new(payer.clone(), trade_config).await;
+    let (client, tip_sol) = cli_trade_client(payer.clone(), rpc_url, use_seed).await?;
 
     let (token_balance, decimals, token_program) =
         check_token_balance(&client, &mint_pubkey, &keypair.pubkey(), use_seed)
@@ -381,7 +356,7 @@ pub async fn handle_pumpfun_sell_no_prompt(
         .map_err(|e| format!("获取 blockhash 失败: {}", e))?;
 
     let gas_fee_strategy = GasFeeStrategy::new();
-    gas_fee_strategy.set_global_fee_strategy(150000, 150000, 500000, 500000, 0.001, 0.001);
+    gas_fee_strategy.set_global_fee_strategy(150000, 150000, 500000, 500000, tip_sol, tip_sol);
 
     if language == Language::Chinese {
         println!("\n{}", "🚀 构建卖出交易...".cyan());
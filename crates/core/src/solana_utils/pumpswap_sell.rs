@@ -9,21 +9,18 @@ use std::sync::Arc;
 
 // Import Language from operations module
 use crate::operations::Language;
+#[cfg(feature = "sol-trade-sdk")]
+use crate::solana_utils::swqos::cli_trade_client;
 
 #[cfg(feature = "sol-trade-sdk")]
 use sol_trade_sdk::{
-    common::{
-        fast_fn::get_associated_token_address_with_program_id_fast_use_seed, GasFeeStrategy,
-        TradeConfig,
-    },
-    swqos::SwqosConfig,
+    common::{fast_fn::get_associated_token_address_with_program_id_fast_use_seed, GasFeeStrategy},
     trading::{
         core::params::{DexParamEnum, PumpSwapParams},
         factory::DexType,
     },
     SolanaTrade, TradeSellParams, TradeTokenType,
 };
-use solana_commitment_config::CommitmentConfig;
 
 /// 处理 PumpSwap 卖出操作
 ///
@@ -57,20 +54,9 @@ pub async fn handle_pumpswap_sell(
 
     // 初始化客户端
     let payer = Arc::new(keypair.insecure_clone());
-    let commitment = CommitmentConfig::confirmed();
-    let swqos_configs: Vec<SwqosConfig> = vec![SwqosConfig::Default(rpc_url.to_string())];
-
-    // 创建 TradeConfig，根据用户选择设置 use_seed_optimize
-    let trade_config = TradeConfig::builder(rpc_url.to_string(), swqos_configs, commitment)
-        .create_wsol_ata_on_startup(false)
-        .use_seed_optimize(use_seed)
-        .check_min_tip(false)
-        .log_enabled(false)
-        .swqos_cores_from_end(false)
-        .mev_protection(false)
-        .build();
-
-    let client = SolanaTrade::new(payer.clone(), trade_config).await;
+    let (client, tip_sol) = cli_trade_client(payer.clone(), rpc_url, use_seed)
+        .await
+        .map_err(|e| anyhow::anyhow!(e))?;
 
     // 检查代币余额和 ATA 信息
     let (token_balance, decimals, token_program) =
@@ -147,7 +133,7 @@ pub async fn handle_pumpswap_sell(
 
     // 配置 Gas 策略
     let gas_fee_strategy = GasFeeStrategy::new();
-    gas_fee_strategy.set_global_fee_strategy(150000, 150000, 500000, 500000, 0.001, 0.001);
+    gas_fee_strategy.set_global_fee_strategy(150000, 150000, 500000, 500000, tip_sol, tip_sol);
 
     println!("\n{}", "🚀 构建卖出交易...".cyan());
 
@@ -366,20 +352,7 @@ pub async fn handle_pumpswap_sell_no_prompt(
 
     // 初始化客户端
     let payer = Arc::new(keypair.insecure_clone());
-    let commitment = CommitmentConfig::confirmed();
-    let swqos_configs: Vec<SwqosConfig> = vec![SwqosConfig::Default(rpc_url.to_string())];
-
-    // 创建 TradeConfig
-    let trade_config = TradeConfig::builder(rpc_url.to_string(), swqos_configs, commitment)
-        .create_wsol_ata_on_startup(false)
-        .use_seed_optimize(use_seed)
-        .check_min_tip(false)
-        .log_enabled(false)
-        .swqos_cores_from_end(false)
-        .mev_protection(false)
-        .build();
-
-    let client = SolanaTrade::new(payer.clone(), trade_config).await;
+    let (client, tip_sol) = cli_trade_client(payer.clone(), rpc_url, use_seed).await?;
 
     // 检查代币余额和 ATA 信息
     let (token_balance, decimals, token_program) =
@@ -489,7 +462,7 @@ pub async fn handle_pumpswap_sell_no_prompt(
 
     // 配置 Gas 策略
     let gas_fee_strategy = GasFeeStrategy::new();
-    gas_fee_strategy.set_global_fee_strategy(150000, 150000, 500000, 500000, 0.001, 0.001);
+    gas_fee_strategy.set_global_fee_strategy(150000, 150000, 500000, 500000, tip_sol, tip_sol);
 
     if language == Language::Chinese {
         println!("\n{}", "🚀 构建卖出交易...".cyan());
@@ -11,15 +11,39 @@ use solana_sdk::{
 };
 use std::str::FromStr;
 
+use super::token_extensions::{self, MintInfo};
+
 // System program ID - hardcoded for solana-sdk 3.0 compatibility
-const SYSTEM_PROGRAM_ID: Pubkey = Pubkey::from_str_const("11111111111111111111111111111111");
+pub(crate) const SYSTEM_PROGRAM_ID: Pubkey =
+    Pubkey::from_str_const("11111111111111111111111111111111");
 
 const WSOL_MINT: &str = "So11111111111111111111111111111111111111112";
 const TOKEN_PROGRAM_ID: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
 const ASSOCIATED_TOKEN_PROGRAM_ID: &str = "ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL";
+const TOKEN_2022_PROGRAM_ID: &str = "TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb";
+const SOLANA_TRANSACTION_PACKET_DATA_BYTES: usize = 1232;
+pub(crate) const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
+/// SPL Memo program (v2)
+pub const MEMO_PROGRAM_ID: Pubkey =
+    Pubkey::from_str_const("MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr");
+/// Longest memo accepted; exchange deposit tags are far shorter and this keeps a memo
+/// plus a token transfer comfortably inside one packet
+pub const MAX_MEMO_BYTES: usize = 256;
+
+// SPL Token account layout (Token-2022 extensions follow the 165-byte base + account type byte)
+const TOKEN_ACCOUNT_LEN: usize = 165;
+const TOKEN_ACCOUNT_STATE_OFFSET: usize = 108;
+const TOKEN_ACCOUNT_NATIVE_OFFSET: usize = 109;
+const TOKEN_ACCOUNT_CLOSE_AUTHORITY_OFFSET: usize = 129;
+const TOKEN_ACCOUNT_STATE_FROZEN: u8 = 2;
 
 /// Create a transfer instruction (replacement for system_instruction::transfer)
-fn create_transfer_instruction(from: &Pubkey, to: &Pubkey, lamports: u64) -> Instruction {
+pub(crate) fn create_transfer_instruction(
+    from: &Pubkey,
+    to: &Pubkey,
+    lamports: u64,
+) -> Instruction {
     Instruction {
         program_id: SYSTEM_PROGRAM_ID,
         accounts: vec![
@@ -36,7 +60,7 @@ fn create_transfer_instruction(from: &Pubkey, to: &Pubkey, lamports: u64) -> Ins
 
 /// Solana RPC client wrapper
 pub struct SolanaClient {
-    rpc_url: String,
+    pub(crate) rpc_url: String,
 }
 
 impl SolanaClient {
@@ -75,7 +99,19 @@ impl SolanaClient {
 
     /// Transfer SOL from one account to another
     pub fn transfer_sol(&self, from: &Keypair, to: &Pubkey, amount: u64) -> Result<Signature> {
+        self.transfer_sol_with_memo(from, to, amount, None)
+    }
+
+    /// Transfer SOL with an optional SPL Memo attached
+    pub fn transfer_sol_with_memo(
+        &self,
+        from: &Keypair,
+        to: &Pubkey,
+        amount: u64,
+        memo: Option<&str>,
+    ) -> Result<Signature> {
         let client = RpcClient::new(self.rpc_url.clone());
+        let memo = normalize_memo(memo)?;
 
         if amount == 0 {
             return Err(anyhow!("Transfer amount cannot be zero"));
@@ -90,11 +126,14 @@ impl SolanaClient {
             ));
         }
 
-        let instruction = create_transfer_instruction(&from.pubkey(), to, amount);
+        let mut instructions = vec![create_transfer_instruction(&from.pubkey(), to, amount)];
+        if let Some(memo) = &memo {
+            instructions.push(memo_instruction(memo));
+        }
         let recent_blockhash = client.get_latest_blockhash()?;
 
         let transaction = Transaction::new_signed_with_payer(
-            &[instruction],
+            &instructions,
             Some(&from.pubkey()),
             &[from],
             recent_blockhash,
@@ -104,48 +143,162 @@ impl SolanaClient {
         Ok(signature)
     }
 
-    /// Transfer SPL tokens from one account to another
-    pub fn transfer_token(
+    /// Read a token mint, including its Token-2022 extensions
+    pub fn get_mint_info(&self, mint: &Pubkey) -> Result<MintInfo> {
+        let client = RpcClient::new(self.rpc_url.clone());
+        let account = client.get_account(mint)?;
+        token_extensions::parse_mint(account.owner, &account.data)
+    }
+
+    /// Build a token transfer after checking the mint's Token-2022 extensions
+    ///
+    /// Non-transferable mints, frozen or confidential-only recipients and recipients that
+    /// would be created frozen are refused. Transfer fees are computed for the current
+    /// epoch and pinned with `TransferCheckedWithFee`; transfer hook accounts are resolved
+    /// from the hook's validation account.
+    pub fn plan_token_transfer(
         &self,
-        from: &Keypair,
+        from: &Pubkey,
         to: &Pubkey,
         mint: &Pubkey,
         amount: u64,
-    ) -> Result<Signature> {
+    ) -> Result<TokenTransferPlan> {
         let client = RpcClient::new(self.rpc_url.clone());
 
         if amount == 0 {
             return Err(anyhow!("Transfer amount cannot be zero"));
         }
 
-        let token_program = Pubkey::from_str(TOKEN_PROGRAM_ID)?;
-        let from_ata = get_associated_token_address(&from.pubkey(), mint, &token_program);
+        let mint_info = self.get_mint_info(mint)?;
+        if mint_info.non_transferable {
+            return Err(anyhow!(
+                "Token is non-transferable (Token-2022 NonTransferable)"
+            ));
+        }
+        let token_program = mint_info.token_program;
+        let from_ata = get_associated_token_address(from, mint, &token_program);
         let to_ata = get_associated_token_address(to, mint, &token_program);
 
+        let mut warnings = Vec::new();
         let mut instructions = vec![];
 
         // Create recipient's ATA if it doesn't exist
-        if client.get_account(&to_ata).is_err() {
-            instructions.push(create_associated_token_account(
-                &from.pubkey(),
-                to,
-                mint,
-                &token_program,
-            )?);
+        let create_recipient_account = match client.get_account(&to_ata) {
+            Ok(account) => {
+                if token_extensions::token_account_is_frozen(&account.data) {
+                    return Err(anyhow!("Recipient token account is frozen"));
+                }
+                if !token_extensions::accepts_non_confidential_credits(&account.data) {
+                    return Err(anyhow!(
+                        "Recipient token account only accepts confidential transfers"
+                    ));
+                }
+                false
+            }
+            Err(_) => {
+                if mint_info.default_account_state_frozen {
+                    return Err(anyhow!(
+                        "New accounts for this mint start frozen; the recipient must have a thawed token account first"
+                    ));
+                }
+                instructions.push(create_associated_token_account(
+                    from,
+                    to,
+                    mint,
+                    &token_program,
+                )?);
+                true
+            }
+        };
+        if mint_info.default_account_state_frozen {
+            warnings.push(
+                "Mint freezes new token accounts by default; the issuer can freeze balances"
+                    .to_string(),
+            );
         }
+        if mint_info.confidential_transfer {
+            warnings.push(
+                "Mint supports confidential transfers; this is a public transfer".to_string(),
+            );
+        }
+
+        let fee = match &mint_info.transfer_fee {
+            Some(config) => {
+                let epoch = client.get_epoch_info()?.epoch;
+                let fee = config.epoch_fee(epoch);
+                let amount_fee = fee.calculate(amount);
+                if amount_fee > 0 {
+                    warnings.push(format!(
+                        "Transfer fee of {} bps (max {}) withholds {} of {} base units",
+                        fee.basis_points, fee.maximum_fee, amount_fee, amount
+                    ));
+                }
+                Some(amount_fee)
+            }
+            None => None,
+        };
 
-        // Create transfer instruction
-        instructions.push(create_transfer_checked_instruction(
+        let mut transfer = create_transfer_checked_instruction(
             &from_ata,
             mint,
             &to_ata,
-            &from.pubkey(),
+            from,
+            amount,
+            mint_info.decimals,
+            &token_program,
+            fee,
+        );
+        if let Some(hook_program) = mint_info.transfer_hook_program {
+            let validation_address =
+                token_extensions::transfer_hook_validation_address(mint, &hook_program);
+            let validation = client.get_account(&validation_address).map_err(|_| {
+                anyhow!(
+                    "Transfer hook program {} has no extra account list for this mint",
+                    hook_program
+                )
+            })?;
+            let extra_accounts = token_extensions::resolve_transfer_hook_accounts(
+                &hook_program,
+                &validation.data,
+                &from_ata,
+                mint,
+                &to_ata,
+                from,
+                amount,
+                &mut |address| client.get_account(address).ok().map(|account| account.data),
+            )?;
+            transfer.accounts.extend(extra_accounts);
+            warnings.push(format!("Transfer invokes hook program {}", hook_program));
+        }
+        instructions.push(transfer);
+
+        let fee_amount = fee.unwrap_or(0);
+        Ok(TokenTransferPlan {
+            mint: *mint,
+            token_program,
+            decimals: mint_info.decimals,
             amount,
-        )?);
+            fee: fee_amount,
+            net_amount: amount - fee_amount,
+            create_recipient_account,
+            transfer_hook_program: mint_info.transfer_hook_program,
+            memo: None,
+            warnings,
+            instructions,
+        })
+    }
+
+    /// Sign and send a transfer built by [`SolanaClient::plan_token_transfer`]
+    pub fn send_token_transfer(
+        &self,
+        from: &Keypair,
+        plan: &TokenTransferPlan,
+    ) -> Result<Signature> {
+        let client = RpcClient::new(self.rpc_url.clone());
 
         let recent_blockhash = client.get_latest_blockhash()?;
         let transaction = Transaction::new_signed_with_payer(
-            &instructions,
+            &plan.instructions,
             Some(&from.pubkey()),
             &[from],
             recent_blockhash,
@@ -155,6 +308,18 @@ impl SolanaClient {
         Ok(signature)
     }
 
+    /// Transfer SPL tokens from one account to another
+    pub fn transfer_token(
+        &self,
+        from: &Keypair,
+        to: &Pubkey,
+        mint: &Pubkey,
+        amount: u64,
+    ) -> Result<Signature> {
+        let plan = self.plan_token_transfer(&from.pubkey(), to, mint, amount)?;
+        self.send_token_transfer(from, &plan)
+    }
+
     /// Wrap SOL to WSOL
     pub fn wrap_sol(&self, keypair: &Keypair, amount: u64) -> Result<Signature> {
         let client = RpcClient::new(self.rpc_url.clone());
@@ -234,6 +399,124 @@ impl SolanaClient {
         Ok(signature)
     }
 
+    /// Find zero-balance SPL Token and Token-2022 accounts owned by `owner`
+    pub fn find_empty_token_accounts(&self, owner: &Pubkey) -> Result<Vec<EmptyTokenAccount>> {
+        let client = RpcClient::new(self.rpc_url.clone());
+
+        let mut addresses = Vec::new();
+        for program in [TOKEN_PROGRAM_ID, TOKEN_2022_PROGRAM_ID] {
+            let program = Pubkey::from_str(program)?;
+            let accounts = client.get_token_accounts_by_owner(
+                owner,
+                solana_client::rpc_request::TokenAccountsFilter::ProgramId(program),
+            )?;
+            for keyed in accounts {
+                addresses.push(Pubkey::from_str(&keyed.pubkey)?);
+            }
+        }
+        self.inspect_empty_token_accounts(owner, &addresses)
+    }
+
+    /// Read the given token accounts and keep those owned by `owner` with a zero balance
+    pub fn inspect_empty_token_accounts(
+        &self,
+        owner: &Pubkey,
+        addresses: &[Pubkey],
+    ) -> Result<Vec<EmptyTokenAccount>> {
+        let client = RpcClient::new(self.rpc_url.clone());
+        let token_programs = [
+            Pubkey::from_str(TOKEN_PROGRAM_ID)?,
+            Pubkey::from_str(TOKEN_2022_PROGRAM_ID)?,
+        ];
+
+        let mut empty = Vec::new();
+        for chunk in addresses.chunks(MAX_MULTIPLE_ACCOUNTS) {
+            let accounts = client.get_multiple_accounts(chunk)?;
+            for (address, account) in chunk.iter().zip(accounts) {
+                let Some(account) = account else {
+                    continue;
+                };
+                if !token_programs.contains(&account.owner) {
+                    continue;
+                }
+                if let Some(parsed) = parse_empty_token_account(
+                    *address,
+                    account.owner,
+                    account.lamports,
+                    &account.data,
+                    owner,
+                ) {
+                    empty.push(parsed);
+                }
+            }
+        }
+        Ok(empty)
+    }
+
+    /// Close zero-balance token accounts and return the rent to the owner
+    ///
+    /// Frozen accounts and accounts whose close authority is not the owner are skipped.
+    /// Token-2022 accounts holding withheld transfer fees are harvested to the mint first.
+    /// Instructions are packed into as few transactions as fit the packet size limit.
+    pub fn close_empty_token_accounts(
+        &self,
+        keypair: &Keypair,
+        accounts: &[EmptyTokenAccount],
+    ) -> Result<CloseTokenAccountsResult> {
+        let client = RpcClient::new(self.rpc_url.clone());
+        let owner = keypair.pubkey();
+
+        let mut result = CloseTokenAccountsResult::default();
+        let mut batches: Vec<(Vec<Instruction>, Vec<&EmptyTokenAccount>)> = Vec::new();
+        for account in accounts {
+            if let Err(reason) = account.closable_by(&owner) {
+                result.skipped.push((account.address, reason.to_string()));
+                continue;
+            }
+            let instructions = close_empty_account_instructions(account, &owner)?;
+            match batches.last_mut() {
+                Some((batch, members))
+                    if transaction_fits(
+                        &[batch.as_slice(), instructions.as_slice()].concat(),
+                        &owner,
+                    ) =>
+                {
+                    batch.extend(instructions);
+                    members.push(account);
+                }
+                _ => batches.push((instructions, vec![account])),
+            }
+        }
+
+        for (instructions, members) in batches {
+            let sent = client.get_latest_blockhash().and_then(|recent_blockhash| {
+                let transaction = Transaction::new_signed_with_payer(
+                    &instructions,
+                    Some(&owner),
+                    &[keypair],
+                    recent_blockhash,
+                );
+                client.send_and_confirm_transaction(&transaction)
+            });
+            match sent {
+                Ok(signature) => {
+                    result.signatures.push(signature);
+                    for account in members {
+                        result.closed.push(account.address);
+                        result.reclaimed_lamports =
+                            result.reclaimed_lamports.saturating_add(account.lamports);
+                    }
+                }
+                Err(e) => result.errors.push(format!(
+                    "Failed to close {} account(s): {}",
+                    members.len(),
+                    e
+                )),
+            }
+        }
+        Ok(result)
+    }
+
     /// Create a durable nonce account
     /// Returns the nonce account pubkey and transaction signature
     pub fn create_nonce_account(&self, payer: &Keypair) -> Result<(Pubkey, Signature)> {
@@ -367,7 +650,11 @@ impl SolanaClient {
 }
 
 /// Get associated token address
-fn get_associated_token_address(wallet: &Pubkey, mint: &Pubkey, token_program: &Pubkey) -> Pubkey {
+pub(crate) fn get_associated_token_address(
+    wallet: &Pubkey,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+) -> Pubkey {
     let associated_token_program = Pubkey::from_str(ASSOCIATED_TOKEN_PROGRAM_ID).unwrap();
 
     Pubkey::find_program_address(
@@ -401,23 +688,58 @@ fn create_associated_token_account(
     })
 }
 
-/// Create transfer checked instruction
-fn create_transfer_checked_instruction(
+/// Create associated token account instruction that succeeds if the account already exists
+pub(crate) fn create_associated_token_account_idempotent(
+    payer: &Pubkey,
+    wallet: &Pubkey,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+) -> Instruction {
+    let associated_token_program = Pubkey::from_str(ASSOCIATED_TOKEN_PROGRAM_ID).unwrap();
+    let ata = get_associated_token_address(wallet, mint, token_program);
+
+    Instruction {
+        program_id: associated_token_program,
+        accounts: vec![
+            AccountMeta::new(*payer, true),
+            AccountMeta::new(ata, false),
+            AccountMeta::new_readonly(*wallet, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new_readonly(SYSTEM_PROGRAM_ID, false),
+            AccountMeta::new_readonly(*token_program, false),
+        ],
+        // CreateIdempotent
+        data: vec![1],
+    }
+}
+
+/// Create transfer checked instruction; with `expected_fee` this is Token-2022
+/// `TransferCheckedWithFee`, which fails if the on-chain fee differs
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn create_transfer_checked_instruction(
     from: &Pubkey,
     mint: &Pubkey,
     to: &Pubkey,
     authority: &Pubkey,
     amount: u64,
-) -> Result<Instruction> {
-    let token_program = Pubkey::from_str(TOKEN_PROGRAM_ID)?;
-
-    // TransferChecked instruction (instruction index: 12)
-    let mut data = vec![12];
+    decimals: u8,
+    token_program: &Pubkey,
+    expected_fee: Option<u64>,
+) -> Instruction {
+    let mut data = match expected_fee {
+        // TransferFeeExtension (26) / TransferCheckedWithFee (1)
+        Some(_) => vec![26, 1],
+        // TransferChecked instruction (instruction index: 12)
+        None => vec![12],
+    };
     data.extend_from_slice(&amount.to_le_bytes());
-    data.push(9); // decimals (standard for most tokens, will be ignored for actual execution)
+    data.push(decimals);
+    if let Some(fee) = expected_fee {
+        data.extend_from_slice(&fee.to_le_bytes());
+    }
 
-    Ok(Instruction {
-        program_id: token_program,
+    Instruction {
+        program_id: *token_program,
         accounts: vec![
             solana_sdk::instruction::AccountMeta::new(*from, false),
             solana_sdk::instruction::AccountMeta::new_readonly(*mint, false),
@@ -425,7 +747,7 @@ fn create_transfer_checked_instruction(
             solana_sdk::instruction::AccountMeta::new_readonly(*authority, true),
         ],
         data,
-    })
+    }
 }
 
 /// Create sync native instruction
@@ -457,6 +779,185 @@ fn close_account_instruction(
     })
 }
 
+/// A token transfer ready to sign, with what the recipient will actually receive
+#[derive(Debug, Clone)]
+pub struct TokenTransferPlan {
+    pub mint: Pubkey,
+    pub token_program: Pubkey,
+    pub decimals: u8,
+    /// Amount debited from the sender, in base units
+    pub amount: u64,
+    /// Token-2022 transfer fee withheld from `amount`
+    pub fee: u64,
+    /// Amount credited to the recipient
+    pub net_amount: u64,
+    pub create_recipient_account: bool,
+    pub transfer_hook_program: Option<Pubkey>,
+    pub memo: Option<String>,
+    pub warnings: Vec<String>,
+    pub instructions: Vec<Instruction>,
+}
+
+impl TokenTransferPlan {
+    /// Attach an SPL Memo to the transfer; `None` or a blank memo leaves the plan unchanged
+    pub fn with_memo(mut self, memo: Option<&str>) -> Result<Self> {
+        if self.memo.is_some() {
+            return Err(anyhow!("Transfer already carries a memo"));
+        }
+        if let Some(memo) = normalize_memo(memo)? {
+            self.instructions.push(memo_instruction(&memo));
+            self.memo = Some(memo);
+        }
+        Ok(self)
+    }
+}
+
+/// A zero-balance token account that still holds rent
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmptyTokenAccount {
+    pub address: Pubkey,
+    pub mint: Pubkey,
+    pub token_program: Pubkey,
+    pub lamports: u64,
+    pub frozen: bool,
+    pub is_native: bool,
+    pub close_authority: Option<Pubkey>,
+    /// Token-2022 transfer fees withheld in the account (blocks `CloseAccount` until harvested)
+    pub withheld_fee_amount: u64,
+}
+
+impl EmptyTokenAccount {
+    /// Whether `owner` can close this account, with the reason when it cannot
+    pub fn closable_by(&self, owner: &Pubkey) -> std::result::Result<(), &'static str> {
+        if self.frozen {
+            return Err("Account is frozen by the mint freeze authority");
+        }
+        if self
+            .close_authority
+            .is_some_and(|authority| authority != *owner)
+        {
+            return Err("Close authority is not the wallet");
+        }
+        Ok(())
+    }
+
+    pub fn is_token_2022(&self) -> bool {
+        self.token_program == token_extensions::TOKEN_2022_PROGRAM_ID
+    }
+}
+
+/// Outcome of [`SolanaClient::close_empty_token_accounts`]
+#[derive(Debug, Clone, Default)]
+pub struct CloseTokenAccountsResult {
+    pub signatures: Vec<Signature>,
+    pub closed: Vec<Pubkey>,
+    pub reclaimed_lamports: u64,
+    pub skipped: Vec<(Pubkey, String)>,
+    pub errors: Vec<String>,
+}
+
+/// Parse raw token account data; returns `None` unless it is an initialized,
+/// zero-balance account owned by `owner`
+fn parse_empty_token_account(
+    address: Pubkey,
+    token_program: Pubkey,
+    lamports: u64,
+    data: &[u8],
+    owner: &Pubkey,
+) -> Option<EmptyTokenAccount> {
+    if data.len() < TOKEN_ACCOUNT_LEN {
+        return None;
+    }
+    let mint = Pubkey::try_from(&data[0..32]).ok()?;
+    let account_owner = Pubkey::try_from(&data[32..64]).ok()?;
+    let amount = u64::from_le_bytes(data[64..72].try_into().ok()?);
+    let state = data[TOKEN_ACCOUNT_STATE_OFFSET];
+    if account_owner != *owner || amount != 0 || state == 0 {
+        return None;
+    }
+    let is_native = data[TOKEN_ACCOUNT_NATIVE_OFFSET] == 1;
+    let close_authority = (data[TOKEN_ACCOUNT_CLOSE_AUTHORITY_OFFSET] == 1)
+        .then(|| {
+            Pubkey::try_from(
+                &data[TOKEN_ACCOUNT_CLOSE_AUTHORITY_OFFSET + 4
+                    ..TOKEN_ACCOUNT_CLOSE_AUTHORITY_OFFSET + 36],
+            )
+            .ok()
+        })
+        .flatten();
+
+    Some(EmptyTokenAccount {
+        address,
+        mint,
+        token_program,
+        lamports,
+        frozen: state == TOKEN_ACCOUNT_STATE_FROZEN,
+        is_native,
+        close_authority,
+        withheld_fee_amount: token_extensions::withheld_transfer_fee(data).unwrap_or(0),
+    })
+}
+
+/// Instructions that close one empty account, harvesting withheld fees first when needed
+fn close_empty_account_instructions(
+    account: &EmptyTokenAccount,
+    owner: &Pubkey,
+) -> Result<Vec<Instruction>> {
+    let mut instructions = Vec::new();
+    if account.withheld_fee_amount > 0 {
+        // TransferFeeExtension (26) / HarvestWithheldTokensToMint (4); permissionless
+        instructions.push(Instruction {
+            program_id: account.token_program,
+            accounts: vec![
+                AccountMeta::new(account.mint, false),
+                AccountMeta::new(account.address, false),
+            ],
+            data: vec![26, 4],
+        });
+    }
+    instructions.push(close_account_instruction(
+        &account.address,
+        owner,
+        owner,
+        &account.token_program,
+    )?);
+    Ok(instructions)
+}
+
+/// Trim a memo and check it; blank memos become `None`
+pub fn normalize_memo(memo: Option<&str>) -> Result<Option<String>> {
+    let Some(memo) = memo.map(str::trim).filter(|memo| !memo.is_empty()) else {
+        return Ok(None);
+    };
+    if memo.len() > MAX_MEMO_BYTES {
+        return Err(anyhow!(
+            "Memo is {} bytes; the limit is {} bytes",
+            memo.len(),
+            MAX_MEMO_BYTES
+        ));
+    }
+    if memo.chars().any(char::is_control) {
+        return Err(anyhow!("Memo cannot contain control characters"));
+    }
+    Ok(Some(memo.to_string()))
+}
+
+/// SPL Memo v2 instruction without signer accounts
+pub fn memo_instruction(memo: &str) -> Instruction {
+    Instruction {
+        program_id: MEMO_PROGRAM_ID,
+        accounts: vec![],
+        data: memo.as_bytes().to_vec(),
+    }
+}
+
+/// Whether a transaction with these instructions, signed by `payer`, fits in one packet
+pub(crate) fn transaction_fits(instructions: &[Instruction], payer: &Pubkey) -> bool {
+    let transaction = Transaction::new_with_payer(instructions, Some(payer));
+    bincode::serialized_size(&transaction)
+        .is_ok_and(|size| size as usize <= SOLANA_TRANSACTION_PACKET_DATA_BYTES)
+}
+
 /// Format lamports to SOL with proper decimals
 pub fn lamports_to_sol(lamports: u64) -> f64 {
     lamports as f64 / LAMPORTS_PER_SOL as f64
@@ -617,3 +1118,120 @@ impl SolanaClientSdk {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token_account_data(mint: &Pubkey, owner: &Pubkey, amount: u64, state: u8) -> Vec<u8> {
+        let mut data = vec![0u8; TOKEN_ACCOUNT_LEN];
+        data[0..32].copy_from_slice(mint.as_ref());
+        data[32..64].copy_from_slice(owner.as_ref());
+        data[64..72].copy_from_slice(&amount.to_le_bytes());
+        data[TOKEN_ACCOUNT_STATE_OFFSET] = state;
+        data
+    }
+
+    #[test]
+    fn parses_empty_token_accounts() {
+        let owner = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let program = Pubkey::from_str(TOKEN_PROGRAM_ID).unwrap();
+        let address = Pubkey::new_unique();
+
+        let data = token_account_data(&mint, &owner, 0, 1);
+        let parsed = parse_empty_token_account(address, program, 2_039_280, &data, &owner).unwrap();
+        assert_eq!(parsed.mint, mint);
+        assert_eq!(parsed.lamports, 2_039_280);
+        assert!(parsed.closable_by(&owner).is_ok());
+
+        let funded = token_account_data(&mint, &owner, 5, 1);
+        assert!(parse_empty_token_account(address, program, 0, &funded, &owner).is_none());
+        assert!(
+            parse_empty_token_account(address, program, 0, &data, &Pubkey::new_unique()).is_none()
+        );
+
+        let frozen = token_account_data(&mint, &owner, 0, TOKEN_ACCOUNT_STATE_FROZEN);
+        let parsed = parse_empty_token_account(address, program, 0, &frozen, &owner).unwrap();
+        assert!(parsed.closable_by(&owner).is_err());
+
+        let mut delegated_close = data.clone();
+        delegated_close[TOKEN_ACCOUNT_CLOSE_AUTHORITY_OFFSET] = 1;
+        delegated_close
+            [TOKEN_ACCOUNT_CLOSE_AUTHORITY_OFFSET + 4..TOKEN_ACCOUNT_CLOSE_AUTHORITY_OFFSET + 36]
+            .copy_from_slice(Pubkey::new_unique().as_ref());
+        let parsed =
+            parse_empty_token_account(address, program, 0, &delegated_close, &owner).unwrap();
+        assert!(parsed.closable_by(&owner).is_err());
+    }
+
+    #[test]
+    fn reads_withheld_transfer_fee_and_harvests_before_close() {
+        let owner = Pubkey::new_unique();
+        let program = Pubkey::from_str(TOKEN_2022_PROGRAM_ID).unwrap();
+        let mut data = token_account_data(&Pubkey::new_unique(), &owner, 0, 1);
+        data.push(2); // AccountType::Account
+                      // ImmutableOwner (7, empty) followed by TransferFeeAmount (2, u64)
+        data.extend_from_slice(&7u16.to_le_bytes());
+        data.extend_from_slice(&0u16.to_le_bytes());
+        data.extend_from_slice(&token_extensions::EXTENSION_TRANSFER_FEE_AMOUNT.to_le_bytes());
+        data.extend_from_slice(&8u16.to_le_bytes());
+        data.extend_from_slice(&42u64.to_le_bytes());
+
+        let parsed =
+            parse_empty_token_account(Pubkey::new_unique(), program, 0, &data, &owner).unwrap();
+        assert!(parsed.is_token_2022());
+        assert_eq!(parsed.withheld_fee_amount, 42);
+
+        let instructions = close_empty_account_instructions(&parsed, &owner).unwrap();
+        assert_eq!(instructions.len(), 2);
+        assert_eq!(instructions[0].data, vec![26, 4]);
+        assert_eq!(instructions[1].data, vec![9]);
+    }
+
+    #[test]
+    fn packs_close_instructions_within_packet_limit() {
+        let owner = Pubkey::new_unique();
+        let program = Pubkey::from_str(TOKEN_PROGRAM_ID).unwrap();
+        let mut instructions = Vec::new();
+        while transaction_fits(&instructions, &owner) {
+            instructions.push(
+                close_account_instruction(&Pubkey::new_unique(), &owner, &owner, &program).unwrap(),
+            );
+        }
+        // Each close adds one 32-byte account key plus a few bytes of instruction data
+        assert!(instructions.len() > 20 && instructions.len() < 40);
+    }
+
+    #[test]
+    fn normalizes_and_attaches_memos() {
+        assert_eq!(normalize_memo(None).unwrap(), None);
+        assert_eq!(normalize_memo(Some("  ")).unwrap(), None);
+        assert_eq!(
+            normalize_memo(Some(" 104857 ")).unwrap().as_deref(),
+            Some("104857")
+        );
+        assert!(normalize_memo(Some(&"x".repeat(MAX_MEMO_BYTES + 1))).is_err());
+        assert!(normalize_memo(Some("tag\n2")).is_err());
+
+        let plan = TokenTransferPlan {
+            mint: Pubkey::new_unique(),
+            token_program: Pubkey::from_str(TOKEN_PROGRAM_ID).unwrap(),
+            decimals: 6,
+            amount: 1,
+            fee: 0,
+            net_amount: 1,
+            create_recipient_account: false,
+            transfer_hook_program: None,
+            memo: None,
+            warnings: vec![],
+            instructions: vec![],
+        };
+        let plan = plan.with_memo(Some("deposit-7")).unwrap();
+        assert_eq!(plan.memo.as_deref(), Some("deposit-7"));
+        assert_eq!(plan.instructions.len(), 1);
+        assert_eq!(plan.instructions[0].program_id, MEMO_PROGRAM_ID);
+        assert_eq!(plan.instructions[0].data, b"deposit-7");
+        assert!(plan.with_memo(Some("again")).is_err());
+    }
+}
@@ -0,0 +1,1009 @@
+//! Native stake accounts: create (with a fresh keypair or a seed-derived address),
+//! delegate, deactivate, withdraw, split, merge and change authorities.
+//!
+//! Instructions are encoded by hand like the system instructions in `solana_ops`. Every
+//! action is planned against an authority that also pays, so the same plan can be signed
+//! directly or wrapped in a Squads vault proposal where the vault is the authority. Vaults
+//! cannot co-sign with a new keypair, which is what the seed-derived addresses are for.
+
+use anyhow::{anyhow, Result};
+use solana_account_decoder_client_types::UiAccountEncoding;
+use solana_client::rpc_client::RpcClient;
+use solana_client::rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig};
+use solana_client::rpc_filter::{Memcmp, RpcFilterType};
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    signature::{Keypair, Signature},
+    signer::Signer,
+    sysvar,
+    transaction::Transaction,
+};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::solana_ops::{SolanaClient, SYSTEM_PROGRAM_ID};
+
+pub const STAKE_PROGRAM_ID: Pubkey =
+    Pubkey::from_str_const("Stake11111111111111111111111111111111111111");
+pub const VOTE_PROGRAM_ID: Pubkey =
+    Pubkey::from_str_const("Vote111111111111111111111111111111111111111");
+const STAKE_HISTORY_SYSVAR_ID: Pubkey =
+    Pubkey::from_str_const("SysvarStakeHistory1111111111111111111111111");
+// Deprecated config account the Stake program still expects in DelegateStake
+const STAKE_CONFIG_ID: Pubkey =
+    Pubkey::from_str_const("StakeConfig11111111111111111111111111111111");
+
+/// Size of a `StakeStateV2` account
+pub const STAKE_ACCOUNT_LEN: u64 = 200;
+/// Longest seed accepted by `create_with_seed`
+pub const MAX_STAKE_SEED_LEN: usize = 32;
+
+// StakeStateV2 layout: u32 tag, Meta { rent_exempt_reserve, Authorized, Lockup }, Stake
+const STAKER_OFFSET: usize = 12;
+const WITHDRAWER_OFFSET: usize = 44;
+const LOCKUP_OFFSET: usize = 76;
+const DELEGATION_OFFSET: usize = 124;
+const DELEGATION_END: usize = 180;
+
+/// Which authority an authorize action replaces
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StakeAuthority {
+    Staker,
+    Withdrawer,
+}
+
+impl StakeAuthority {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "staker" => Some(Self::Staker),
+            "withdrawer" => Some(Self::Withdrawer),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Staker => "staker",
+            Self::Withdrawer => "withdrawer",
+        }
+    }
+}
+
+/// Activation by epoch. Warmup and cooldown are rate limited network-wide, so a large
+/// stake can stay activating or deactivating for more than one epoch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StakeActivation {
+    /// Initialized but never delegated
+    Undelegated,
+    Activating,
+    Active,
+    Deactivating,
+    /// Deactivated; the whole balance can be withdrawn
+    Inactive,
+}
+
+impl StakeActivation {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Undelegated => "undelegated",
+            Self::Activating => "activating",
+            Self::Active => "active",
+            Self::Deactivating => "deactivating",
+            Self::Inactive => "inactive",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StakeDelegation {
+    pub vote_account: Pubkey,
+    pub stake: u64,
+    pub activation_epoch: u64,
+    pub deactivation_epoch: u64,
+}
+
+/// Decoded `Initialized` or `Stake` state of a stake account
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StakeAccountState {
+    pub rent_exempt_reserve: u64,
+    pub staker: Pubkey,
+    pub withdrawer: Pubkey,
+    pub lockup_unix_timestamp: i64,
+    pub lockup_epoch: u64,
+    pub custodian: Pubkey,
+    pub delegation: Option<StakeDelegation>,
+}
+
+impl StakeAccountState {
+    pub fn activation(&self, current_epoch: u64) -> StakeActivation {
+        let Some(delegation) = self.delegation else {
+            return StakeActivation::Undelegated;
+        };
+        if delegation.deactivation_epoch != u64::MAX {
+            if current_epoch > delegation.deactivation_epoch {
+                StakeActivation::Inactive
+            } else {
+                StakeActivation::Deactivating
+            }
+        } else if delegation.activation_epoch == u64::MAX
+            || current_epoch > delegation.activation_epoch
+        {
+            // u64::MAX marks stake that has been active since genesis
+            StakeActivation::Active
+        } else {
+            StakeActivation::Activating
+        }
+    }
+
+    /// Whether a lockup still blocks withdrawals and withdrawer changes
+    pub fn is_locked(&self, current_epoch: u64, now_unix: i64) -> bool {
+        self.lockup_epoch > current_epoch || self.lockup_unix_timestamp > now_unix
+    }
+
+    pub fn authority(&self, authority: StakeAuthority) -> Pubkey {
+        match authority {
+            StakeAuthority::Staker => self.staker,
+            StakeAuthority::Withdrawer => self.withdrawer,
+        }
+    }
+}
+
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+}
+
+fn read_pubkey(data: &[u8], offset: usize) -> Pubkey {
+    Pubkey::new_from_array(data[offset..offset + 32].try_into().unwrap())
+}
+
+/// Decode stake account data; `None` for an uninitialized account
+pub fn decode_stake_account(data: &[u8]) -> Result<Option<StakeAccountState>> {
+    if data.len() < 4 {
+        return Err(anyhow!("Stake account data is too short"));
+    }
+    let tag = u32::from_le_bytes(data[..4].try_into().unwrap());
+    let delegated = match tag {
+        0 => return Ok(None),
+        1 => false,
+        2 => true,
+        _ => return Err(anyhow!("Unsupported stake account state {}", tag)),
+    };
+    if data.len()
+        < if delegated {
+            DELEGATION_END
+        } else {
+            DELEGATION_OFFSET
+        }
+    {
+        return Err(anyhow!("Stake account data is too short"));
+    }
+    Ok(Some(StakeAccountState {
+        rent_exempt_reserve: read_u64(data, 4),
+        staker: read_pubkey(data, STAKER_OFFSET),
+        withdrawer: read_pubkey(data, WITHDRAWER_OFFSET),
+        lockup_unix_timestamp: read_u64(data, LOCKUP_OFFSET) as i64,
+        lockup_epoch: read_u64(data, LOCKUP_OFFSET + 8),
+        custodian: read_pubkey(data, LOCKUP_OFFSET + 16),
+        delegation: delegated.then(|| StakeDelegation {
+            vote_account: read_pubkey(data, DELEGATION_OFFSET),
+            stake: read_u64(data, DELEGATION_OFFSET + 32),
+            activation_epoch: read_u64(data, DELEGATION_OFFSET + 40),
+            deactivation_epoch: read_u64(data, DELEGATION_OFFSET + 48),
+        }),
+    }))
+}
+
+/// Stake account address derived from `base` and `seed`
+pub fn stake_address_with_seed(base: &Pubkey, seed: &str) -> Result<Pubkey> {
+    if seed.is_empty() || seed.len() > MAX_STAKE_SEED_LEN {
+        return Err(anyhow!(
+            "Seed must be 1 to {} bytes long",
+            MAX_STAKE_SEED_LEN
+        ));
+    }
+    Pubkey::create_with_seed(base, seed, &STAKE_PROGRAM_ID)
+        .map_err(|e| anyhow!("Invalid stake account seed: {:?}", e))
+}
+
+fn stake_instruction(tag: u32, accounts: Vec<AccountMeta>, payload: &[u8]) -> Instruction {
+    let mut data = tag.to_le_bytes().to_vec();
+    data.extend_from_slice(payload);
+    Instruction {
+        program_id: STAKE_PROGRAM_ID,
+        accounts,
+        data,
+    }
+}
+
+/// System CreateAccount for a new stake account signed by its own keypair
+fn create_account_instruction(funder: &Pubkey, account: &Pubkey, lamports: u64) -> Instruction {
+    let mut data = 0u32.to_le_bytes().to_vec();
+    data.extend_from_slice(&lamports.to_le_bytes());
+    data.extend_from_slice(&STAKE_ACCOUNT_LEN.to_le_bytes());
+    data.extend_from_slice(STAKE_PROGRAM_ID.as_ref());
+    Instruction {
+        program_id: SYSTEM_PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(*funder, true),
+            AccountMeta::new(*account, true),
+        ],
+        data,
+    }
+}
+
+/// System CreateAccountWithSeed for a stake account derived from `base`
+fn create_account_with_seed_instruction(
+    funder: &Pubkey,
+    account: &Pubkey,
+    base: &Pubkey,
+    seed: &str,
+    lamports: u64,
+) -> Instruction {
+    let mut data = 3u32.to_le_bytes().to_vec();
+    data.extend_from_slice(base.as_ref());
+    data.extend_from_slice(&(seed.len() as u64).to_le_bytes());
+    data.extend_from_slice(seed.as_bytes());
+    data.extend_from_slice(&lamports.to_le_bytes());
+    data.extend_from_slice(&STAKE_ACCOUNT_LEN.to_le_bytes());
+    data.extend_from_slice(STAKE_PROGRAM_ID.as_ref());
+    Instruction {
+        program_id: SYSTEM_PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(*funder, true),
+            AccountMeta::new(*account, false),
+            AccountMeta::new_readonly(*base, true),
+        ],
+        data,
+    }
+}
+
+/// Initialize with both authorities and no lockup
+pub fn initialize_stake_instruction(
+    stake: &Pubkey,
+    staker: &Pubkey,
+    withdrawer: &Pubkey,
+) -> Instruction {
+    let mut payload = Vec::with_capacity(112);
+    payload.extend_from_slice(staker.as_ref());
+    payload.extend_from_slice(withdrawer.as_ref());
+    // Lockup { unix_timestamp: 0, epoch: 0, custodian: default }
+    payload.extend_from_slice(&[0; 48]);
+    stake_instruction(
+        0,
+        vec![
+            AccountMeta::new(*stake, false),
+            AccountMeta::new_readonly(sysvar::rent::id(), false),
+        ],
+        &payload,
+    )
+}
+
+pub fn authorize_stake_instruction(
+    stake: &Pubkey,
+    authority: &Pubkey,
+    new_authority: &Pubkey,
+    kind: StakeAuthority,
+) -> Instruction {
+    let mut payload = new_authority.to_bytes().to_vec();
+    payload.extend_from_slice(&(kind as u32).to_le_bytes());
+    stake_instruction(
+        1,
+        vec![
+            AccountMeta::new(*stake, false),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+            AccountMeta::new_readonly(*authority, true),
+        ],
+        &payload,
+    )
+}
+
+pub fn delegate_stake_instruction(stake: &Pubkey, staker: &Pubkey, vote: &Pubkey) -> Instruction {
+    stake_instruction(
+        2,
+        vec![
+            AccountMeta::new(*stake, false),
+            AccountMeta::new_readonly(*vote, false),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+            AccountMeta::new_readonly(STAKE_HISTORY_SYSVAR_ID, false),
+            AccountMeta::new_readonly(STAKE_CONFIG_ID, false),
+            AccountMeta::new_readonly(*staker, true),
+        ],
+        &[],
+    )
+}
+
+pub fn split_stake_instruction(
+    stake: &Pubkey,
+    staker: &Pubkey,
+    split_stake: &Pubkey,
+    lamports: u64,
+) -> Instruction {
+    stake_instruction(
+        3,
+        vec![
+            AccountMeta::new(*stake, false),
+            AccountMeta::new(*split_stake, false),
+            AccountMeta::new_readonly(*staker, true),
+        ],
+        &lamports.to_le_bytes(),
+    )
+}
+
+pub fn withdraw_stake_instruction(
+    stake: &Pubkey,
+    withdrawer: &Pubkey,
+    to: &Pubkey,
+    lamports: u64,
+) -> Instruction {
+    stake_instruction(
+        4,
+        vec![
+            AccountMeta::new(*stake, false),
+            AccountMeta::new(*to, false),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+            AccountMeta::new_readonly(STAKE_HISTORY_SYSVAR_ID, false),
+            AccountMeta::new_readonly(*withdrawer, true),
+        ],
+        &lamports.to_le_bytes(),
+    )
+}
+
+pub fn deactivate_stake_instruction(stake: &Pubkey, staker: &Pubkey) -> Instruction {
+    stake_instruction(
+        5,
+        vec![
+            AccountMeta::new(*stake, false),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+            AccountMeta::new_readonly(*staker, true),
+        ],
+        &[],
+    )
+}
+
+pub fn merge_stake_instruction(
+    destination: &Pubkey,
+    source: &Pubkey,
+    staker: &Pubkey,
+) -> Instruction {
+    stake_instruction(
+        7,
+        vec![
+            AccountMeta::new(*destination, false),
+            AccountMeta::new(*source, false),
+            AccountMeta::new_readonly(sysvar::clock::id(), false),
+            AccountMeta::new_readonly(STAKE_HISTORY_SYSVAR_ID, false),
+            AccountMeta::new_readonly(*staker, true),
+        ],
+        &[],
+    )
+}
+
+/// A stake account with its balance and activation in the current epoch
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StakeAccount {
+    pub address: Pubkey,
+    pub lamports: u64,
+    /// `None` for an uninitialized account
+    pub state: Option<StakeAccountState>,
+    pub activation: StakeActivation,
+}
+
+impl StakeAccount {
+    fn from_data(address: Pubkey, lamports: u64, data: &[u8], epoch: u64) -> Result<Self> {
+        let state = decode_stake_account(data)?;
+        Ok(Self {
+            address,
+            lamports,
+            state,
+            activation: state
+                .map(|state| state.activation(epoch))
+                .unwrap_or(StakeActivation::Undelegated),
+        })
+    }
+
+    fn initialized(&self) -> Result<&StakeAccountState> {
+        self.state
+            .as_ref()
+            .ok_or_else(|| anyhow!("Stake account {} is not initialized", self.address))
+    }
+
+    /// Lamports that can be withdrawn now; only the excess over the delegated stake and
+    /// rent reserve while the stake is (de)activating or active
+    pub fn withdrawable_lamports(&self) -> u64 {
+        match (self.state, self.activation) {
+            (None, _) | (_, StakeActivation::Undelegated | StakeActivation::Inactive) => {
+                self.lamports
+            }
+            (Some(state), _) => self.lamports.saturating_sub(
+                state.rent_exempt_reserve
+                    + state
+                        .delegation
+                        .map(|delegation| delegation.stake)
+                        .unwrap_or(0),
+            ),
+        }
+    }
+
+    /// Fail unless `signer` holds the given authority
+    pub fn require_authority(&self, signer: &Pubkey, authority: StakeAuthority) -> Result<()> {
+        let expected = self.initialized()?.authority(authority);
+        if &expected != signer {
+            return Err(anyhow!(
+                "{} is not the {} authority of stake account {} (expected {})",
+                signer,
+                authority.as_str(),
+                self.address,
+                expected
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// A stake operation planned against one authority that also pays fees and new accounts
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StakeAction {
+    /// New stake account, delegated right away when `vote_account` is set. Without a seed
+    /// a fresh keypair is generated for the account.
+    Create {
+        lamports: u64,
+        vote_account: Option<Pubkey>,
+        seed: Option<String>,
+    },
+    Delegate {
+        stake_account: Pubkey,
+        vote_account: Pubkey,
+    },
+    Deactivate {
+        stake_account: Pubkey,
+    },
+    /// Withdraw to `to` (the authority when unset); everything withdrawable when
+    /// `lamports` is unset
+    Withdraw {
+        stake_account: Pubkey,
+        to: Option<Pubkey>,
+        lamports: Option<u64>,
+    },
+    Split {
+        stake_account: Pubkey,
+        lamports: u64,
+        seed: Option<String>,
+    },
+    Merge {
+        destination: Pubkey,
+        source: Pubkey,
+    },
+    Authorize {
+        stake_account: Pubkey,
+        new_authority: Pubkey,
+        authority: StakeAuthority,
+    },
+}
+
+#[derive(Debug)]
+pub struct StakeActionPlan {
+    pub instructions: Vec<Instruction>,
+    /// Account created by a create or split
+    pub new_stake_account: Option<Pubkey>,
+    /// Keypair of a new account created without a seed; it must co-sign
+    pub new_account_keypair: Option<Keypair>,
+    /// Lamports staked, split off or withdrawn
+    pub lamports: u64,
+    pub description: String,
+    pub warnings: Vec<String>,
+}
+
+fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+impl SolanaClient {
+    fn fetch_stake_account(
+        &self,
+        client: &RpcClient,
+        address: &Pubkey,
+        epoch: u64,
+    ) -> Result<StakeAccount> {
+        let account = client
+            .get_account(address)
+            .map_err(|e| anyhow!("Stake account {} not found: {}", address, e))?;
+        if account.owner != STAKE_PROGRAM_ID {
+            return Err(anyhow!("{} is not a stake account", address));
+        }
+        StakeAccount::from_data(*address, account.lamports, &account.data, epoch)
+    }
+
+    pub fn get_stake_account(&self, address: &Pubkey) -> Result<StakeAccount> {
+        let client = RpcClient::new(self.rpc_url.clone());
+        let epoch = client.get_epoch_info()?.epoch;
+        self.fetch_stake_account(&client, address, epoch)
+    }
+
+    /// Stake accounts where `authority` is the staker or the withdrawer, largest first
+    pub fn list_stake_accounts(&self, authority: &Pubkey) -> Result<(u64, Vec<StakeAccount>)> {
+        let client = RpcClient::new(self.rpc_url.clone());
+        let epoch = client.get_epoch_info()?.epoch;
+        let mut accounts: Vec<StakeAccount> = Vec::new();
+        for offset in [STAKER_OFFSET, WITHDRAWER_OFFSET] {
+            let config = RpcProgramAccountsConfig {
+                filters: Some(vec![
+                    RpcFilterType::DataSize(STAKE_ACCOUNT_LEN),
+                    RpcFilterType::Memcmp(Memcmp::new_base58_encoded(offset, authority.as_ref())),
+                ]),
+                account_config: RpcAccountInfoConfig {
+                    encoding: Some(UiAccountEncoding::Base64),
+                    ..RpcAccountInfoConfig::default()
+                },
+                ..RpcProgramAccountsConfig::default()
+            };
+            #[allow(deprecated)]
+            let found = client.get_program_accounts_with_config(&STAKE_PROGRAM_ID, config)?;
+            for (address, account) in found {
+                if accounts.iter().any(|known| known.address == address) {
+                    continue;
+                }
+                accounts.push(StakeAccount::from_data(
+                    address,
+                    account.lamports,
+                    &account.data,
+                    epoch,
+                )?);
+            }
+        }
+        accounts.sort_by(|a, b| b.lamports.cmp(&a.lamports).then(a.address.cmp(&b.address)));
+        Ok((epoch, accounts))
+    }
+
+    fn require_vote_account(&self, client: &RpcClient, vote: &Pubkey) -> Result<()> {
+        let account = client
+            .get_account(vote)
+            .map_err(|e| anyhow!("Vote account {} not found: {}", vote, e))?;
+        if account.owner != VOTE_PROGRAM_ID {
+            return Err(anyhow!("{} is not a vote account", vote));
+        }
+        Ok(())
+    }
+
+    /// Build the instructions for `action` with `authority` as signer and fee payer
+    pub fn plan_stake_action(
+        &self,
+        authority: &Pubkey,
+        action: &StakeAction,
+    ) -> Result<StakeActionPlan> {
+        let client = RpcClient::new(self.rpc_url.clone());
+        let epoch = client.get_epoch_info()?.epoch;
+        let rent = client.get_minimum_balance_for_rent_exemption(STAKE_ACCOUNT_LEN as usize)?;
+        let mut warnings = Vec::new();
+
+        let plan = match action {
+            StakeAction::Create {
+                lamports,
+                vote_account,
+                seed,
+            } => {
+                let minimum_delegation = client.get_stake_minimum_delegation()?;
+                if *lamports < rent + minimum_delegation {
+                    return Err(anyhow!(
+                        "A stake account needs at least {} lamports ({} rent reserve + {} minimum delegation)",
+                        rent + minimum_delegation,
+                        rent,
+                        minimum_delegation
+                    ));
+                }
+                let balance = client.get_balance(authority)?;
+                if balance < *lamports {
+                    return Err(anyhow!(
+                        "Insufficient balance. Have: {} lamports, Need: {} lamports",
+                        balance,
+                        lamports
+                    ));
+                }
+                if let Some(vote) = vote_account {
+                    self.require_vote_account(&client, vote)?;
+                }
+
+                let (stake, keypair, mut instructions) = match seed {
+                    Some(seed) => {
+                        let stake = stake_address_with_seed(authority, seed)?;
+                        if client.get_account(&stake).is_ok() {
+                            return Err(anyhow!(
+                                "Seed \"{}\" is already used by account {}",
+                                seed,
+                                stake
+                            ));
+                        }
+                        let create = create_account_with_seed_instruction(
+                            authority, &stake, authority, seed, *lamports,
+                        );
+                        (stake, None, vec![create])
+                    }
+                    None => {
+                        let keypair = Keypair::new();
+                        let stake = keypair.pubkey();
+                        let create = create_account_instruction(authority, &stake, *lamports);
+                        (stake, Some(keypair), vec![create])
+                    }
+                };
+                instructions.push(initialize_stake_instruction(&stake, authority, authority));
+                let description = match vote_account {
+                    Some(vote) => {
+                        instructions.push(delegate_stake_instruction(&stake, authority, vote));
+                        format!("Create stake account {} delegated to {}", stake, vote)
+                    }
+                    None => format!("Create undelegated stake account {}", stake),
+                };
+                StakeActionPlan {
+                    instructions,
+                    new_stake_account: Some(stake),
+                    new_account_keypair: keypair,
+                    lamports: *lamports,
+                    description,
+                    warnings,
+                }
+            }
+            StakeAction::Delegate {
+                stake_account,
+                vote_account,
+            } => {
+                let stake = self.fetch_stake_account(&client, stake_account, epoch)?;
+                stake.require_authority(authority, StakeAuthority::Staker)?;
+                if matches!(
+                    stake.activation,
+                    StakeActivation::Activating | StakeActivation::Active
+                ) {
+                    return Err(anyhow!(
+                        "Stake account {} is already delegated; deactivate it first",
+                        stake_account
+                    ));
+                }
+                self.require_vote_account(&client, vote_account)?;
+                StakeActionPlan {
+                    instructions: vec![delegate_stake_instruction(
+                        stake_account,
+                        authority,
+                        vote_account,
+                    )],
+                    new_stake_account: None,
+                    new_account_keypair: None,
+                    lamports: stake.lamports,
+                    description: format!("Delegate {} to {}", stake_account, vote_account),
+                    warnings,
+                }
+            }
+            StakeAction::Deactivate { stake_account } => {
+                let stake = self.fetch_stake_account(&client, stake_account, epoch)?;
+                stake.require_authority(authority, StakeAuthority::Staker)?;
+                if !matches!(
+                    stake.activation,
+                    StakeActivation::Activating | StakeActivation::Active
+                ) {
+                    return Err(anyhow!(
+                        "Stake account {} is {} and cannot be deactivated",
+                        stake_account,
+                        stake.activation.as_str()
+                    ));
+                }
+                StakeActionPlan {
+                    instructions: vec![deactivate_stake_instruction(stake_account, authority)],
+                    new_stake_account: None,
+                    new_account_keypair: None,
+                    lamports: stake.lamports,
+                    description: format!("Deactivate {}", stake_account),
+                    warnings,
+                }
+            }
+            StakeAction::Withdraw {
+                stake_account,
+                to,
+                lamports,
+            } => {
+                let stake = self.fetch_stake_account(&client, stake_account, epoch)?;
+                stake.require_authority(authority, StakeAuthority::Withdrawer)?;
+                if let Some(state) = stake.state {
+                    if state.is_locked(epoch, unix_now()) {
+                        return Err(anyhow!(
+                            "Stake account {} is still locked up",
+                            stake_account
+                        ));
+                    }
+                }
+                let withdrawable = stake.withdrawable_lamports();
+                let amount = lamports.unwrap_or(withdrawable);
+                if amount == 0 {
+                    return Err(anyhow!(
+                        "Nothing to withdraw from {} while it is {}",
+                        stake_account,
+                        stake.activation.as_str()
+                    ));
+                }
+                if amount > withdrawable {
+                    return Err(anyhow!(
+                        "Only {} lamports can be withdrawn from {} while it is {}",
+                        withdrawable,
+                        stake_account,
+                        stake.activation.as_str()
+                    ));
+                }
+                if amount == stake.lamports {
+                    warnings.push(format!(
+                        "Withdrawing the whole balance closes {}",
+                        stake_account
+                    ));
+                }
+                let to = to.unwrap_or(*authority);
+                StakeActionPlan {
+                    instructions: vec![withdraw_stake_instruction(
+                        stake_account,
+                        authority,
+                        &to,
+                        amount,
+                    )],
+                    new_stake_account: None,
+                    new_account_keypair: None,
+                    lamports: amount,
+                    description: format!(
+                        "Withdraw {} lamports from {} to {}",
+                        amount, stake_account, to
+                    ),
+                    warnings,
+                }
+            }
+            StakeAction::Split {
+                stake_account,
+                lamports,
+                seed,
+            } => {
+                let stake = self.fetch_stake_account(&client, stake_account, epoch)?;
+                stake.require_authority(authority, StakeAuthority::Staker)?;
+                if *lamports == 0 || *lamports >= stake.lamports {
+                    return Err(anyhow!(
+                        "Split amount must be between 1 and {} lamports",
+                        stake.lamports.saturating_sub(1)
+                    ));
+                }
+                // The destination is prefunded with its rent reserve so the split moves
+                // only `lamports` of stake
+                let (split, keypair, create) = match seed {
+                    Some(seed) => {
+                        let split = stake_address_with_seed(authority, seed)?;
+                        if client.get_account(&split).is_ok() {
+                            return Err(anyhow!(
+                                "Seed \"{}\" is already used by account {}",
+                                seed,
+                                split
+                            ));
+                        }
+                        let create = create_account_with_seed_instruction(
+                            authority, &split, authority, seed, rent,
+                        );
+                        (split, None, create)
+                    }
+                    None => {
+                        let keypair = Keypair::new();
+                        let split = keypair.pubkey();
+                        let create = create_account_instruction(authority, &split, rent);
+                        (split, Some(keypair), create)
+                    }
+                };
+                StakeActionPlan {
+                    instructions: vec![
+                        create,
+                        split_stake_instruction(stake_account, authority, &split, *lamports),
+                    ],
+                    new_stake_account: Some(split),
+                    new_account_keypair: keypair,
+                    lamports: *lamports,
+                    description: format!(
+                        "Split {} lamports from {} into {}",
+                        lamports, stake_account, split
+                    ),
+                    warnings,
+                }
+            }
+            StakeAction::Merge {
+                destination,
+                source,
+            } => {
+                if destination == source {
+                    return Err(anyhow!("Cannot merge a stake account into itself"));
+                }
+                let destination_account = self.fetch_stake_account(&client, destination, epoch)?;
+                let source_account = self.fetch_stake_account(&client, source, epoch)?;
+                destination_account.require_authority(authority, StakeAuthority::Staker)?;
+                source_account.require_authority(authority, StakeAuthority::Staker)?;
+                let (destination_state, source_state) = (
+                    destination_account.initialized()?,
+                    source_account.initialized()?,
+                );
+                if destination_state.withdrawer != source_state.withdrawer {
+                    return Err(anyhow!(
+                        "Stake accounts have different withdraw authorities"
+                    ));
+                }
+                if let (Some(a), Some(b)) = (destination_state.delegation, source_state.delegation)
+                {
+                    if a.vote_account != b.vote_account {
+                        return Err(anyhow!(
+                            "Stake accounts are delegated to different validators"
+                        ));
+                    }
+                }
+                if [destination_account.activation, source_account.activation]
+                    .contains(&StakeActivation::Deactivating)
+                {
+                    return Err(anyhow!(
+                        "Stake accounts cannot be merged while deactivating"
+                    ));
+                }
+                StakeActionPlan {
+                    instructions: vec![merge_stake_instruction(destination, source, authority)],
+                    new_stake_account: None,
+                    new_account_keypair: None,
+                    lamports: source_account.lamports,
+                    description: format!("Merge {} into {}", source, destination),
+                    warnings,
+                }
+            }
+            StakeAction::Authorize {
+                stake_account,
+                new_authority,
+                authority: kind,
+            } => {
+                let stake = self.fetch_stake_account(&client, stake_account, epoch)?;
+                stake.require_authority(authority, *kind)?;
+                if *kind == StakeAuthority::Withdrawer {
+                    warnings.push(format!(
+                        "{} will be able to withdraw everything in {}",
+                        new_authority, stake_account
+                    ));
+                }
+                StakeActionPlan {
+                    instructions: vec![authorize_stake_instruction(
+                        stake_account,
+                        authority,
+                        new_authority,
+                        *kind,
+                    )],
+                    new_stake_account: None,
+                    new_account_keypair: None,
+                    lamports: 0,
+                    description: format!(
+                        "Set the {} authority of {} to {}",
+                        kind.as_str(),
+                        stake_account,
+                        new_authority
+                    ),
+                    warnings,
+                }
+            }
+        };
+        Ok(plan)
+    }
+
+    /// Sign and send a stake plan with `keypair` as authority and fee payer
+    pub fn send_stake_plan(&self, keypair: &Keypair, plan: &StakeActionPlan) -> Result<Signature> {
+        let client = RpcClient::new(self.rpc_url.clone());
+        let mut signers: Vec<&Keypair> = vec![keypair];
+        if let Some(new_account) = &plan.new_account_keypair {
+            signers.push(new_account);
+        }
+        let recent_blockhash = client.get_latest_blockhash()?;
+        let transaction = Transaction::new_signed_with_payer(
+            &plan.instructions,
+            Some(&keypair.pubkey()),
+            &signers,
+            recent_blockhash,
+        );
+        Ok(client.send_and_confirm_transaction(&transaction)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stake_data(tag: u32, delegation: Option<(u64, u64)>) -> Vec<u8> {
+        let mut data = vec![0u8; STAKE_ACCOUNT_LEN as usize];
+        data[..4].copy_from_slice(&tag.to_le_bytes());
+        data[4..12].copy_from_slice(&2_282_880u64.to_le_bytes());
+        data[STAKER_OFFSET..STAKER_OFFSET + 32].copy_from_slice(&[1; 32]);
+        data[WITHDRAWER_OFFSET..WITHDRAWER_OFFSET + 32].copy_from_slice(&[2; 32]);
+        if let Some((activation, deactivation)) = delegation {
+            data[DELEGATION_OFFSET..DELEGATION_OFFSET + 32].copy_from_slice(&[3; 32]);
+            data[DELEGATION_OFFSET + 32..DELEGATION_OFFSET + 40]
+                .copy_from_slice(&1_000_000_000u64.to_le_bytes());
+            data[DELEGATION_OFFSET + 40..DELEGATION_OFFSET + 48]
+                .copy_from_slice(&activation.to_le_bytes());
+            data[DELEGATION_OFFSET + 48..DELEGATION_OFFSET + 56]
+                .copy_from_slice(&deactivation.to_le_bytes());
+        }
+        data
+    }
+
+    #[test]
+    fn decodes_stake_accounts_and_activation() {
+        assert_eq!(decode_stake_account(&stake_data(0, None)).unwrap(), None);
+
+        let initialized = decode_stake_account(&stake_data(1, None)).unwrap().unwrap();
+        assert_eq!(initialized.staker, Pubkey::new_from_array([1; 32]));
+        assert_eq!(initialized.withdrawer, Pubkey::new_from_array([2; 32]));
+        assert_eq!(initialized.rent_exempt_reserve, 2_282_880);
+        assert_eq!(initialized.activation(10), StakeActivation::Undelegated);
+
+        let delegated = decode_stake_account(&stake_data(2, Some((10, u64::MAX))))
+            .unwrap()
+            .unwrap();
+        let delegation = delegated.delegation.unwrap();
+        assert_eq!(delegation.vote_account, Pubkey::new_from_array([3; 32]));
+        assert_eq!(delegation.stake, 1_000_000_000);
+        assert_eq!(delegated.activation(10), StakeActivation::Activating);
+        assert_eq!(delegated.activation(11), StakeActivation::Active);
+
+        let deactivating = decode_stake_account(&stake_data(2, Some((10, 20))))
+            .unwrap()
+            .unwrap();
+        assert_eq!(deactivating.activation(20), StakeActivation::Deactivating);
+        assert_eq!(deactivating.activation(21), StakeActivation::Inactive);
+
+        assert!(decode_stake_account(&stake_data(2, None)[..150]).is_err());
+        assert!(decode_stake_account(&stake_data(3, None)).is_err());
+    }
+
+    #[test]
+    fn withdrawable_keeps_delegated_stake_and_reserve() {
+        let account = |activation| StakeAccount {
+            address: Pubkey::new_unique(),
+            lamports: 1_500_000_000,
+            state: decode_stake_account(&stake_data(2, Some((10, u64::MAX)))).unwrap(),
+            activation,
+        };
+        assert_eq!(
+            account(StakeActivation::Active).withdrawable_lamports(),
+            1_500_000_000 - 1_000_000_000 - 2_282_880
+        );
+        assert_eq!(
+            account(StakeActivation::Inactive).withdrawable_lamports(),
+            1_500_000_000
+        );
+        let staker = Pubkey::new_from_array([1; 32]);
+        let active = account(StakeActivation::Active);
+        assert!(active
+            .require_authority(&staker, StakeAuthority::Staker)
+            .is_ok());
+        assert!(active
+            .require_authority(&staker, StakeAuthority::Withdrawer)
+            .is_err());
+    }
+
+    #[test]
+    fn encodes_stake_instructions() {
+        let stake = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+        let vote = Pubkey::new_unique();
+
+        let initialize = initialize_stake_instruction(&stake, &authority, &authority);
+        assert_eq!(initialize.data.len(), 4 + 64 + 48);
+        assert_eq!(&initialize.data[..4], &[0, 0, 0, 0]);
+
+        let delegate = delegate_stake_instruction(&stake, &authority, &vote);
+        assert_eq!(delegate.data, 2u32.to_le_bytes());
+        assert_eq!(delegate.accounts[1].pubkey, vote);
+        assert!(delegate.accounts[5].is_signer);
+
+        let withdraw = withdraw_stake_instruction(&stake, &authority, &authority, 42);
+        assert_eq!(&withdraw.data[4..], &42u64.to_le_bytes());
+
+        let authorize =
+            authorize_stake_instruction(&stake, &authority, &vote, StakeAuthority::Withdrawer);
+        assert_eq!(&authorize.data[36..], &1u32.to_le_bytes());
+
+        let seeded = stake_address_with_seed(&authority, "stake-1").unwrap();
+        let create =
+            create_account_with_seed_instruction(&authority, &seeded, &authority, "stake-1", 5);
+        assert_eq!(create.data.len(), 4 + 32 + 8 + 7 + 8 + 8 + 32);
+        assert!(stake_address_with_seed(&authority, &"x".repeat(33)).is_err());
+    }
+}
@@ -0,0 +1,621 @@
+// SWQoS 发送通道配置
+// 服务商、区域、小费策略与发送并发的持久化设置，桌面 API 与 CLI pump 命令共用
+
+use serde::{Deserialize, Serialize};
+use solana_sdk::signature::Keypair;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use sol_trade_sdk::{
+    common::TradeConfig,
+    swqos::{SwqosConfig, SwqosRegion, SwqosType},
+    SolanaTrade, TradingInfrastructure,
+};
+use solana_commitment_config::CommitmentConfig;
+
+/// CLI 读取的 SWQoS 配置文件路径（JSON，格式同桌面 API `/api/settings/swqos`）
+pub const SWQOS_CONFIG_ENV: &str = "FNZERO_SAFE_SWQOS_CONFIG";
+pub const LEGACY_SWQOS_CONFIG_ENV: &str = "SOL_SAFEKEY_SWQOS_CONFIG";
+/// 桌面端保存 SWQoS 设置时导出的副本文件名，位于 `<config dir>/fnzero-safe/`
+pub const SWQOS_CONFIG_FILE_NAME: &str = "swqos.json";
+const SWQOS_CONFIG_DIR_NAME: &str = "fnzero-safe";
+pub const FLASHBLOCK_SWQOS_API_TOKEN_ENV: &str = "FNZERO_SAFE_FLASHBLOCK_SWQOS_API_TOKEN";
+pub const LEGACY_FLASHBLOCK_SWQOS_API_TOKEN_ENV: &str = "SOL_SAFEKEY_FLASHBLOCK_SWQOS_API_TOKEN";
+pub const BLOCKRAZOR_SWQOS_API_TOKEN_ENV: &str = "FNZERO_SAFE_BLOCKRAZOR_SWQOS_API_TOKEN";
+pub const LEGACY_BLOCKRAZOR_SWQOS_API_TOKEN_ENV: &str = "SOL_SAFEKEY_BLOCKRAZOR_SWQOS_API_TOKEN";
+pub const ASTRALANE_SWQOS_API_TOKEN_ENV: &str = "FNZERO_SAFE_ASTRALANE_SWQOS_API_TOKEN";
+pub const LEGACY_ASTRALANE_SWQOS_API_TOKEN_ENV: &str = "SOL_SAFEKEY_ASTRALANE_SWQOS_API_TOKEN";
+pub const SPEEDLANDING_SWQOS_API_TOKEN_ENV: &str = "FNZERO_SAFE_SPEEDLANDING_SWQOS_API_TOKEN";
+pub const LEGACY_SPEEDLANDING_SWQOS_API_TOKEN_ENV: &str =
+    "SOL_SAFEKEY_SPEEDLANDING_SWQOS_API_TOKEN";
+
+pub const DEFAULT_SWQOS_TIP_SOL: f64 = 0.0001;
+pub const DEFAULT_SWQOS_REGION: &str = "frankfurt";
+/// 单笔小费上限，防止配置错误烧掉大量 SOL
+pub const MAX_SWQOS_TIP_SOL: f64 = 0.1;
+pub const MAX_SWQOS_SENDER_CONCURRENCY: usize = 64;
+pub const SUPPORTED_TIP_PERCENTILES: [u8; 5] = [25, 50, 75, 95, 99];
+pub const JITO_TIP_FLOOR_URL: &str = "https://bundles.jito.wtf/api/v1/bundles/tip_floor";
+const TIP_FLOOR_TIMEOUT_SECS: u64 = 2;
+
+/// 支持配置的 SWQoS 服务商
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SwqosProvider {
+    FlashBlock,
+    BlockRazor,
+    Astralane,
+    Speedlanding,
+}
+
+impl SwqosProvider {
+    pub const ALL: [SwqosProvider; 4] = [
+        SwqosProvider::FlashBlock,
+        SwqosProvider::BlockRazor,
+        SwqosProvider::Astralane,
+        SwqosProvider::Speedlanding,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SwqosProvider::FlashBlock => "flash_block",
+            SwqosProvider::BlockRazor => "block_razor",
+            SwqosProvider::Astralane => "astralane",
+            SwqosProvider::Speedlanding => "speedlanding",
+        }
+    }
+
+    /// 未在设置中保存 token 时回退读取的环境变量
+    pub fn token_env_names(self) -> [&'static str; 2] {
+        match self {
+            SwqosProvider::FlashBlock => [
+                FLASHBLOCK_SWQOS_API_TOKEN_ENV,
+                LEGACY_FLASHBLOCK_SWQOS_API_TOKEN_ENV,
+            ],
+            SwqosProvider::BlockRazor => [
+                BLOCKRAZOR_SWQOS_API_TOKEN_ENV,
+                LEGACY_BLOCKRAZOR_SWQOS_API_TOKEN_ENV,
+            ],
+            SwqosProvider::Astralane => [
+                ASTRALANE_SWQOS_API_TOKEN_ENV,
+                LEGACY_ASTRALANE_SWQOS_API_TOKEN_ENV,
+            ],
+            SwqosProvider::Speedlanding => [
+                SPEEDLANDING_SWQOS_API_TOKEN_ENV,
+                LEGACY_SPEEDLANDING_SWQOS_API_TOKEN_ENV,
+            ],
+        }
+    }
+
+    fn config(self, token: String, region: SwqosRegion) -> SwqosConfig {
+        match self {
+            SwqosProvider::FlashBlock => SwqosConfig::FlashBlock(token, region, None),
+            SwqosProvider::BlockRazor => SwqosConfig::BlockRazor(token, region, None, None),
+            SwqosProvider::Astralane => SwqosConfig::Astralane(token, region, None, None),
+            SwqosProvider::Speedlanding => SwqosConfig::Speedlanding(token, region, None),
+        }
+    }
+}
+
+/// 单个服务商的设置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SwqosProviderSettings {
+    pub provider: SwqosProvider,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    #[serde(default = "default_region")]
+    pub region: String,
+    /// 为空时回退读取对应环境变量
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_token: Option<String>,
+}
+
+/// 小费策略：固定金额，或按 Jito tip floor 的落地分位数取值
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum SwqosTipStrategy {
+    Fixed {
+        sol: f64,
+    },
+    Percentile {
+        percentile: u8,
+        /// tip floor 不可用时使用
+        #[serde(default = "default_tip_sol")]
+        fallback_sol: f64,
+        #[serde(default = "default_max_tip_sol")]
+        max_sol: f64,
+    },
+}
+
+/// SWQoS 发送设置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SwqosSettings {
+    #[serde(default)]
+    pub providers: Vec<SwqosProviderSettings>,
+    #[serde(default = "default_tip_strategy")]
+    pub tip: SwqosTipStrategy,
+    /// 覆盖 SDK 按通道数和 CPU 核数推算的发送并发
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sender_concurrency: Option<usize>,
+    /// 只走 SWQoS 通道，从不回退为普通 RPC 发送
+    #[serde(default = "default_true")]
+    pub swqos_only: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_region() -> String {
+    DEFAULT_SWQOS_REGION.to_string()
+}
+
+fn default_tip_sol() -> f64 {
+    DEFAULT_SWQOS_TIP_SOL
+}
+
+fn default_max_tip_sol() -> f64 {
+    MAX_SWQOS_TIP_SOL
+}
+
+fn default_tip_strategy() -> SwqosTipStrategy {
+    SwqosTipStrategy::Fixed {
+        sol: DEFAULT_SWQOS_TIP_SOL,
+    }
+}
+
+impl Default for SwqosSettings {
+    fn default() -> Self {
+        Self {
+            providers: SwqosProvider::ALL
+                .iter()
+                .map(|provider| SwqosProviderSettings {
+                    provider: *provider,
+                    enabled: true,
+                    region: default_region(),
+                    api_token: None,
+                })
+                .collect(),
+            tip: default_tip_strategy(),
+            sender_concurrency: None,
+            swqos_only: true,
+        }
+    }
+}
+
+/// 解析区域名（不区分大小写，允许 `new_york` / `new-york` / `ny` 等写法）
+pub fn parse_swqos_region(value: &str) -> Result<SwqosRegion, String> {
+    let normalized = value
+        .trim()
+        .to_ascii_lowercase()
+        .replace(['-', '_', ' '], "");
+    match normalized.as_str() {
+        "newyork" | "ny" => Ok(SwqosRegion::NewYork),
+        "frankfurt" | "fra" => Ok(SwqosRegion::Frankfurt),
+        "amsterdam" | "ams" => Ok(SwqosRegion::Amsterdam),
+        "dublin" => Ok(SwqosRegion::Dublin),
+        "slc" | "saltlakecity" => Ok(SwqosRegion::SLC),
+        "tokyo" => Ok(SwqosRegion::Tokyo),
+        "singapore" | "sg" => Ok(SwqosRegion::Singapore),
+        "london" => Ok(SwqosRegion::London),
+        "losangeles" | "la" => Ok(SwqosRegion::LosAngeles),
+        "" | "default" => Ok(SwqosRegion::Default),
+        _ => Err(format!("不支持的 SWQoS 区域: {}", value.trim())),
+    }
+}
+
+/// 从 Jito tip floor 响应中取指定分位数的小费（SOL）
+pub fn tip_from_floor(floor: &serde_json::Value, percentile: u8) -> Option<f64> {
+    let entry = floor.as_array().and_then(|entries| entries.first())?;
+    entry
+        .get(format!("landed_tips_{}th_percentile", percentile))
+        .and_then(serde_json::Value::as_f64)
+        .filter(|tip| tip.is_finite() && *tip >= 0.0)
+}
+
+fn validate_tip_sol(label: &str, sol: f64) -> Result<(), String> {
+    if !sol.is_finite() || !(0.0..=MAX_SWQOS_TIP_SOL).contains(&sol) {
+        return Err(format!(
+            "{} 必须在 0 到 {} SOL 之间",
+            label, MAX_SWQOS_TIP_SOL
+        ));
+    }
+    Ok(())
+}
+
+fn env_value(names: &[&str]) -> Option<String> {
+    names.iter().find_map(|name| {
+        std::env::var(name)
+            .ok()
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    })
+}
+
+impl SwqosSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        let mut seen = Vec::new();
+        for entry in &self.providers {
+            if seen.contains(&entry.provider) {
+                return Err(format!("SWQoS 服务商重复: {}", entry.provider.as_str()));
+            }
+            seen.push(entry.provider);
+            parse_swqos_region(&entry.region)?;
+        }
+        match &self.tip {
+            SwqosTipStrategy::Fixed { sol } => validate_tip_sol("小费", *sol)?,
+            SwqosTipStrategy::Percentile {
+                percentile,
+                fallback_sol,
+                max_sol,
+            } => {
+                if !SUPPORTED_TIP_PERCENTILES.contains(percentile) {
+                    return Err(format!(
+                        "不支持的小费分位数: {} (可选 25/50/75/95/99)",
+                        percentile
+                    ));
+                }
+                validate_tip_sol("回退小费", *fallback_sol)?;
+                validate_tip_sol("小费上限", *max_sol)?;
+            }
+        }
+        if let Some(concurrency) = self.sender_concurrency {
+            if !(1..=MAX_SWQOS_SENDER_CONCURRENCY).contains(&concurrency) {
+                return Err(format!(
+                    "发送并发必须在 1 到 {} 之间",
+                    MAX_SWQOS_SENDER_CONCURRENCY
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// 服务商 token：优先使用设置中保存的值，否则读取环境变量
+    pub fn provider_token(&self, entry: &SwqosProviderSettings) -> Option<String> {
+        entry
+            .api_token
+            .as_deref()
+            .map(str::trim)
+            .filter(|token| !token.is_empty())
+            .map(str::to_string)
+            .or_else(|| env_value(&entry.provider.token_env_names()))
+    }
+
+    /// 是否至少有一个启用的服务商拿得到 token 且区域有效
+    pub fn has_usable_provider(&self) -> bool {
+        self.providers.iter().any(|entry| {
+            entry.enabled
+                && self.provider_token(entry).is_some()
+                && parse_swqos_region(&entry.region).is_ok()
+        })
+    }
+
+    /// 生成 SDK 通道配置；末尾总是附带 RPC Default，由 `restrict_client` 按需剔除
+    pub fn swqos_configs(&self, rpc_url: &str) -> Vec<SwqosConfig> {
+        let mut configs = self
+            .providers
+            .iter()
+            .filter(|entry| entry.enabled)
+            .filter_map(|entry| {
+                let token = self.provider_token(entry)?;
+                let region = parse_swqos_region(&entry.region).ok()?;
+                Some(entry.provider.config(token, region))
+            })
+            .collect::<Vec<_>>();
+        configs.push(SwqosConfig::Default(rpc_url.to_string()));
+        configs
+    }
+
+    /// 按设置剔除 RPC 通道并覆盖发送并发
+    pub fn restrict_client(
+        &self,
+        client: &SolanaTrade,
+        payer: Arc<Keypair>,
+        use_seed: bool,
+    ) -> Result<SolanaTrade, String> {
+        let swqos_clients = client
+            .infrastructure
+            .swqos_clients
+            .iter()
+            .filter(|swqos| {
+                !self.swqos_only || !matches!(swqos.get_swqos_type(), SwqosType::Default)
+            })
+            .cloned()
+            .collect::<Vec<_>>();
+        if swqos_clients.is_empty() {
+            return Err("未初始化可用 SWQOS 通道，已按配置禁止 RPC 发送".to_string());
+        }
+
+        let mut max_sender_concurrency = client.infrastructure.max_sender_concurrency;
+        let mut effective_core_ids = client.infrastructure.effective_core_ids.clone();
+        if let Some(concurrency) = self.sender_concurrency {
+            max_sender_concurrency = concurrency.max(1);
+            if effective_core_ids.len() > max_sender_concurrency {
+                effective_core_ids =
+                    Arc::new(effective_core_ids[..max_sender_concurrency].to_vec());
+            }
+        }
+
+        let infrastructure = TradingInfrastructure {
+            rpc: client.infrastructure.rpc.clone(),
+            swqos_clients: Arc::new(swqos_clients),
+            config: client.infrastructure.config.clone(),
+            max_sender_concurrency,
+            effective_core_ids,
+        };
+        let mut restricted =
+            SolanaTrade::from_infrastructure(payer, Arc::new(infrastructure), use_seed);
+        restricted.max_sender_concurrency = max_sender_concurrency;
+        restricted.log_enabled = false;
+        restricted.check_min_tip = false;
+        Ok(restricted)
+    }
+
+    /// 按设置初始化交易客户端
+    pub async fn trade_client(
+        &self,
+        payer: Arc<Keypair>,
+        rpc_url: &str,
+        use_seed: bool,
+    ) -> Result<SolanaTrade, String> {
+        let trade_config = TradeConfig::builder(
+            rpc_url.to_string(),
+            self.swqos_configs(rpc_url),
+            CommitmentConfig::confirmed(),
+        )
+        .create_wsol_ata_on_startup(false)
+        .use_seed_optimize(use_seed)
+        .check_min_tip(false)
+        .log_enabled(false)
+        .swqos_cores_from_end(false)
+        .mev_protection(false)
+        .build();
+        let client = SolanaTrade::new(payer.clone(), trade_config).await;
+        self.restrict_client(&client, payer, use_seed)
+    }
+
+    /// 计算本次发送使用的小费（SOL）
+    pub async fn resolve_tip_sol(&self) -> f64 {
+        match &self.tip {
+            SwqosTipStrategy::Fixed { sol } => *sol,
+            SwqosTipStrategy::Percentile {
+                percentile,
+                fallback_sol,
+                max_sol,
+            } => fetch_tip_floor()
+                .await
+                .ok()
+                .and_then(|floor| tip_from_floor(&floor, *percentile))
+                .map_or(*fallback_sol, |tip| tip.min(*max_sol)),
+        }
+    }
+
+    pub fn from_json_file(path: &str) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("读取 SWQoS 配置失败 {}: {}", path, e))?;
+        let settings: SwqosSettings = serde_json::from_str(&content)
+            .map_err(|e| format!("解析 SWQoS 配置失败 {}: {}", path, e))?;
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    /// CLI 使用：优先读取 `FNZERO_SAFE_SWQOS_CONFIG`，否则读取桌面端导出的
+    /// `swqos.json`；两者都不存在时返回 `None`，保持仅 RPC 发送
+    pub fn load_from_env() -> Result<Option<Self>, String> {
+        if let Some(path) = env_value(&[SWQOS_CONFIG_ENV, LEGACY_SWQOS_CONFIG_ENV]) {
+            return Self::from_json_file(&path).map(Some);
+        }
+        match default_swqos_config_path() {
+            Some(path) if path.is_file() => Self::from_json_file(&path.to_string_lossy()).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    /// 去掉服务商 token 的副本，token 只保存在桌面端数据库、配置文件或环境变量中
+    pub fn without_tokens(&self) -> Self {
+        let mut settings = self.clone();
+        for entry in &mut settings.providers {
+            entry.api_token = None;
+        }
+        settings
+    }
+
+    /// 导出给 CLI 读取的 JSON 副本（不含 token，权限 0600，先写临时文件再替换）
+    pub fn export_json_file(&self, path: &Path) -> Result<(), String> {
+        self.validate()?;
+        let content = serde_json::to_string_pretty(&self.without_tokens())
+            .map_err(|e| format!("序列化 SWQoS 设置失败: {}", e))?;
+        if let Some(parent) = path.parent() {
+            let mut builder = std::fs::DirBuilder::new();
+            builder.recursive(true);
+            #[cfg(unix)]
+            std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+            builder
+                .create(parent)
+                .map_err(|e| format!("创建配置目录失败 {}: {}", parent.display(), e))?;
+        }
+        let tmp_path = path.with_extension("json.tmp");
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options
+            .open(&tmp_path)
+            .map_err(|e| format!("写入 SWQoS 配置失败 {}: {}", tmp_path.display(), e))?;
+        std::io::Write::write_all(&mut file, content.as_bytes())
+            .and_then(|_| file.sync_all())
+            .map_err(|e| format!("写入 SWQoS 配置失败 {}: {}", tmp_path.display(), e))?;
+        std::fs::rename(&tmp_path, path)
+            .map_err(|e| format!("写入 SWQoS 配置失败 {}: {}", path.display(), e))
+    }
+}
+
+/// 桌面端导出、CLI 默认读取的 SWQoS 设置路径：`<config dir>/fnzero-safe/swqos.json`
+pub fn default_swqos_config_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join(SWQOS_CONFIG_DIR_NAME).join(SWQOS_CONFIG_FILE_NAME))
+}
+
+/// CLI pump 命令的发送客户端与小费（SOL）
+///
+/// 与桌面端使用同一套 SWQoS 设置（见 [`SwqosSettings::load_from_env`]），都没有时保持仅 RPC 发送。
+/// 导出的设置不含 token：没有任何服务商拿得到 token 时提示并改走 RPC，而不是因
+/// `swqos_only` 直接失败。
+pub async fn cli_trade_client(
+    payer: Arc<Keypair>,
+    rpc_url: &str,
+    use_seed: bool,
+) -> Result<(SolanaTrade, f64), String> {
+    if let Some(settings) = SwqosSettings::load_from_env()? {
+        if settings.has_usable_provider() {
+            let client = settings.trade_client(payer, rpc_url, use_seed).await?;
+            let tip_sol = settings.resolve_tip_sol().await;
+            return Ok((client, tip_sol));
+        }
+        eprintln!(
+            "⚠️ SWQoS 设置中没有可用 token 的服务商，本次改用普通 RPC 发送；\
+             请设置 FNZERO_SAFE_<服务商>_SWQOS_API_TOKEN 环境变量"
+        );
+    }
+
+    let trade_config = TradeConfig::builder(
+        rpc_url.to_string(),
+        vec![SwqosConfig::Default(rpc_url.to_string())],
+        CommitmentConfig::confirmed(),
+    )
+    .create_wsol_ata_on_startup(false)
+    .use_seed_optimize(use_seed)
+    .check_min_tip(false)
+    .log_enabled(false)
+    .swqos_cores_from_end(false)
+    .mev_protection(false)
+    .build();
+    Ok((
+        SolanaTrade::new(payer, trade_config).await,
+        DEFAULT_SWQOS_TIP_SOL,
+    ))
+}
+
+async fn fetch_tip_floor() -> Result<serde_json::Value, String> {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(TIP_FLOOR_TIMEOUT_SECS))
+        .build()
+        .map_err(|e| e.to_string())?
+        .get(JITO_TIP_FLOOR_URL)
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .map_err(|e| e.to_string())?
+        .json::<serde_json::Value>()
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_region_aliases() {
+        assert_eq!(parse_swqos_region("Tokyo").unwrap(), SwqosRegion::Tokyo);
+        assert_eq!(
+            parse_swqos_region("new-york").unwrap(),
+            SwqosRegion::NewYork
+        );
+        assert_eq!(parse_swqos_region("NY").unwrap(), SwqosRegion::NewYork);
+        assert!(parse_swqos_region("mars").is_err());
+    }
+
+    #[test]
+    fn settings_round_trip_and_validate() {
+        let json = r#"{
+            "providers": [
+                {"provider": "flash_block", "region": "tokyo", "api_token": "t"},
+                {"provider": "astralane", "enabled": false}
+            ],
+            "tip": {"mode": "percentile", "percentile": 75},
+            "sender_concurrency": 4,
+            "swqos_only": false
+        }"#;
+        let settings: SwqosSettings = serde_json::from_str(json).unwrap();
+        settings.validate().unwrap();
+        assert_eq!(settings.providers[1].region, DEFAULT_SWQOS_REGION);
+        assert_eq!(
+            settings.tip,
+            SwqosTipStrategy::Percentile {
+                percentile: 75,
+                fallback_sol: DEFAULT_SWQOS_TIP_SOL,
+                max_sol: MAX_SWQOS_TIP_SOL,
+            }
+        );
+        let configs = settings.swqos_configs("http://rpc");
+        assert_eq!(configs.len(), 2);
+        assert!(matches!(
+            &configs[0],
+            SwqosConfig::FlashBlock(token, SwqosRegion::Tokyo, None) if token == "t"
+        ));
+        assert!(matches!(configs.last(), Some(SwqosConfig::Default(_))));
+
+        let mut invalid = settings.clone();
+        invalid.tip = SwqosTipStrategy::Fixed { sol: 5.0 };
+        assert!(invalid.validate().is_err());
+        invalid = settings.clone();
+        invalid.providers.push(invalid.providers[0].clone());
+        assert!(invalid.validate().is_err());
+        invalid = settings;
+        invalid.sender_concurrency = Some(0);
+        assert!(invalid.validate().is_err());
+    }
+
+    #[test]
+    fn reads_tip_floor_percentile() {
+        let floor = serde_json::json!([{
+            "landed_tips_50th_percentile": 0.00002,
+            "landed_tips_75th_percentile": 0.00005
+        }]);
+        assert_eq!(tip_from_floor(&floor, 75), Some(0.00005));
+        assert_eq!(tip_from_floor(&floor, 99), None);
+        assert_eq!(tip_from_floor(&serde_json::json!({}), 50), None);
+    }
+
+    #[test]
+    fn exported_file_drops_tokens_and_round_trips() {
+        let dir = std::env::temp_dir().join(format!("swqos-export-{}", std::process::id()));
+        let path = dir.join(SWQOS_CONFIG_FILE_NAME);
+        let mut settings = SwqosSettings::default();
+        settings.providers[0].api_token = Some("secret-token".to_string());
+        settings.sender_concurrency = Some(8);
+        settings.export_json_file(&path).unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(!content.contains("secret-token"));
+        let loaded = SwqosSettings::from_json_file(&path.to_string_lossy()).unwrap();
+        assert_eq!(loaded, settings.without_tokens());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn exported_file_without_env_tokens_has_no_usable_provider() {
+        let dir = std::env::temp_dir().join(format!("swqos-no-token-{}", std::process::id()));
+        let path = dir.join(SWQOS_CONFIG_FILE_NAME);
+        let mut settings = SwqosSettings::default();
+        for entry in &mut settings.providers {
+            entry.api_token = Some("db-token".to_string());
+        }
+        assert!(settings.has_usable_provider());
+        settings.export_json_file(&path).unwrap();
+        let loaded = SwqosSettings::from_json_file(&path.to_string_lossy()).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let env_token_set = SwqosProvider::ALL
+            .iter()
+            .any(|provider| env_value(&provider.token_env_names()).is_some());
+        assert_eq!(loaded.has_usable_provider(), env_token_set);
+    }
+}
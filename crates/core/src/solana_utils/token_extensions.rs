@@ -0,0 +1,595 @@
+//! Token-2022 mint and account extension parsing
+//!
+//! Reads the TLV extension area directly from account data so transfers and asset
+//! views can handle transfer fees, transfer hooks, non-transferable, default-frozen,
+//! confidential and interest-bearing mints without pulling in the full token crates.
+
+use anyhow::{anyhow, Result};
+use solana_sdk::{instruction::AccountMeta, pubkey::Pubkey};
+
+pub const TOKEN_PROGRAM_ID: Pubkey =
+    Pubkey::from_str_const("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+pub const TOKEN_2022_PROGRAM_ID: Pubkey =
+    Pubkey::from_str_const("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+
+const MINT_LEN: usize = 82;
+const MINT_DECIMALS_OFFSET: usize = 44;
+const MINT_INITIALIZED_OFFSET: usize = 45;
+const ACCOUNT_LEN: usize = 165;
+const ACCOUNT_STATE_OFFSET: usize = 108;
+const ACCOUNT_TYPE_MINT: u8 = 1;
+const ACCOUNT_TYPE_ACCOUNT: u8 = 2;
+const ACCOUNT_STATE_FROZEN: u8 = 2;
+
+// spl-token-2022 ExtensionType values
+const EXTENSION_TRANSFER_FEE_CONFIG: u16 = 1;
+pub(crate) const EXTENSION_TRANSFER_FEE_AMOUNT: u16 = 2;
+const EXTENSION_CONFIDENTIAL_TRANSFER_MINT: u16 = 4;
+const EXTENSION_CONFIDENTIAL_TRANSFER_ACCOUNT: u16 = 5;
+const EXTENSION_DEFAULT_ACCOUNT_STATE: u16 = 6;
+const EXTENSION_NON_TRANSFERABLE: u16 = 9;
+const EXTENSION_INTEREST_BEARING_CONFIG: u16 = 10;
+const EXTENSION_TRANSFER_HOOK: u16 = 14;
+
+/// `ConfidentialTransferAccount.allow_non_confidential_credits`
+const CONFIDENTIAL_ACCOUNT_NON_CONFIDENTIAL_CREDITS_OFFSET: usize = 262;
+
+const ONE_IN_BASIS_POINTS: u128 = 10_000;
+const SECONDS_PER_YEAR: f64 = 60.0 * 60.0 * 24.0 * 365.24;
+
+const EXTRA_ACCOUNT_METAS_SEED: &[u8] = b"extra-account-metas";
+/// First 8 bytes of sha256("spl-transfer-hook-interface:execute")
+const TRANSFER_HOOK_EXECUTE_DISCRIMINATOR: [u8; 8] = [105, 37, 101, 197, 75, 251, 102, 26];
+const EXTRA_ACCOUNT_META_LEN: usize = 35;
+
+/// One transfer fee schedule entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransferFee {
+    pub epoch: u64,
+    pub maximum_fee: u64,
+    pub basis_points: u16,
+}
+
+impl TransferFee {
+    /// Fee withheld by the token program when `amount` is transferred (rounded up, capped)
+    pub fn calculate(&self, amount: u64) -> u64 {
+        if self.basis_points == 0 || amount == 0 {
+            return 0;
+        }
+        let fee = (amount as u128 * self.basis_points as u128).div_ceil(ONE_IN_BASIS_POINTS);
+        fee.min(self.maximum_fee as u128) as u64
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransferFeeConfig {
+    pub older: TransferFee,
+    pub newer: TransferFee,
+}
+
+impl TransferFeeConfig {
+    /// The schedule in force during `epoch`
+    pub fn epoch_fee(&self, epoch: u64) -> &TransferFee {
+        if epoch >= self.newer.epoch {
+            &self.newer
+        } else {
+            &self.older
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InterestBearingConfig {
+    pub initialization_timestamp: i64,
+    pub pre_update_average_rate: i16,
+    pub last_update_timestamp: i64,
+    pub current_rate: i16,
+}
+
+impl InterestBearingConfig {
+    /// UI amount including interest accrued up to `unix_timestamp`
+    pub fn ui_amount(&self, amount: u64, decimals: u8, unix_timestamp: i64) -> f64 {
+        let pre_update = self.pre_update_average_rate as f64
+            * (self.last_update_timestamp - self.initialization_timestamp) as f64;
+        let post_update =
+            self.current_rate as f64 * (unix_timestamp - self.last_update_timestamp) as f64;
+        let scale =
+            ((pre_update + post_update) / SECONDS_PER_YEAR / ONE_IN_BASIS_POINTS as f64).exp();
+        amount as f64 * scale / 10f64.powi(decimals as i32)
+    }
+
+    pub fn ui_amount_string(&self, amount: u64, decimals: u8, unix_timestamp: i64) -> String {
+        let formatted = format!(
+            "{:.*}",
+            decimals as usize,
+            self.ui_amount(amount, decimals, unix_timestamp)
+        );
+        if formatted.contains('.') {
+            formatted
+                .trim_end_matches('0')
+                .trim_end_matches('.')
+                .to_string()
+        } else {
+            formatted
+        }
+    }
+}
+
+/// Mint state relevant to transfers and balance display
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MintInfo {
+    pub token_program: Pubkey,
+    pub decimals: u8,
+    pub transfer_fee: Option<TransferFeeConfig>,
+    pub transfer_hook_program: Option<Pubkey>,
+    pub non_transferable: bool,
+    pub default_account_state_frozen: bool,
+    pub confidential_transfer: bool,
+    pub interest_bearing: Option<InterestBearingConfig>,
+}
+
+impl MintInfo {
+    pub fn is_token_2022(&self) -> bool {
+        self.token_program == TOKEN_2022_PROGRAM_ID
+    }
+
+    /// Short names of the extensions this module understands, for display
+    pub fn extension_labels(&self) -> Vec<&'static str> {
+        let mut labels = Vec::new();
+        if self.transfer_fee.is_some() {
+            labels.push("transfer_fee");
+        }
+        if self.transfer_hook_program.is_some() {
+            labels.push("transfer_hook");
+        }
+        if self.non_transferable {
+            labels.push("non_transferable");
+        }
+        if self.default_account_state_frozen {
+            labels.push("default_frozen");
+        }
+        if self.confidential_transfer {
+            labels.push("confidential_transfer");
+        }
+        if self.interest_bearing.is_some() {
+            labels.push("interest_bearing");
+        }
+        labels
+    }
+}
+
+/// Iterate `(extension_type, value)` entries after the base state of a Token-2022 account or mint
+fn extensions(data: &[u8], account_type: u8) -> impl Iterator<Item = (u16, &[u8])> {
+    let mut offset = if data.len() > ACCOUNT_LEN && data[ACCOUNT_LEN] == account_type {
+        ACCOUNT_LEN + 1
+    } else {
+        data.len()
+    };
+    std::iter::from_fn(move || {
+        let header = data.get(offset..offset + 4)?;
+        let extension_type = u16::from_le_bytes([header[0], header[1]]);
+        let length = u16::from_le_bytes([header[2], header[3]]) as usize;
+        if extension_type == 0 {
+            return None;
+        }
+        let value = data.get(offset + 4..offset + 4 + length)?;
+        offset += 4 + length;
+        Some((extension_type, value))
+    })
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(
+        data.get(offset..offset + 8)?.try_into().ok()?,
+    ))
+}
+
+fn read_i64(data: &[u8], offset: usize) -> Option<i64> {
+    Some(i64::from_le_bytes(
+        data.get(offset..offset + 8)?.try_into().ok()?,
+    ))
+}
+
+fn read_i16(data: &[u8], offset: usize) -> Option<i16> {
+    Some(i16::from_le_bytes(
+        data.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+fn read_transfer_fee(data: &[u8], offset: usize) -> Option<TransferFee> {
+    Some(TransferFee {
+        epoch: read_u64(data, offset)?,
+        maximum_fee: read_u64(data, offset + 8)?,
+        basis_points: u16::from_le_bytes(data.get(offset + 16..offset + 18)?.try_into().ok()?),
+    })
+}
+
+/// Parse a mint owned by the SPL Token or Token-2022 program
+pub fn parse_mint(token_program: Pubkey, data: &[u8]) -> Result<MintInfo> {
+    if token_program != TOKEN_PROGRAM_ID && token_program != TOKEN_2022_PROGRAM_ID {
+        return Err(anyhow!("Mint is not owned by a token program"));
+    }
+    if data.len() < MINT_LEN || data[MINT_INITIALIZED_OFFSET] != 1 {
+        return Err(anyhow!("Account is not an initialized token mint"));
+    }
+    let mut info = MintInfo {
+        token_program,
+        decimals: data[MINT_DECIMALS_OFFSET],
+        transfer_fee: None,
+        transfer_hook_program: None,
+        non_transferable: false,
+        default_account_state_frozen: false,
+        confidential_transfer: false,
+        interest_bearing: None,
+    };
+    if token_program != TOKEN_2022_PROGRAM_ID {
+        return Ok(info);
+    }
+
+    for (extension_type, value) in extensions(data, ACCOUNT_TYPE_MINT) {
+        match extension_type {
+            EXTENSION_TRANSFER_FEE_CONFIG => {
+                // authority (32) + withdraw authority (32) + withheld amount (8)
+                info.transfer_fee = read_transfer_fee(value, 72)
+                    .zip(read_transfer_fee(value, 90))
+                    .map(|(older, newer)| TransferFeeConfig { older, newer });
+            }
+            EXTENSION_CONFIDENTIAL_TRANSFER_MINT => info.confidential_transfer = true,
+            EXTENSION_DEFAULT_ACCOUNT_STATE => {
+                info.default_account_state_frozen = value.first() == Some(&ACCOUNT_STATE_FROZEN);
+            }
+            EXTENSION_NON_TRANSFERABLE => info.non_transferable = true,
+            EXTENSION_INTEREST_BEARING_CONFIG => {
+                info.interest_bearing = (|| {
+                    Some(InterestBearingConfig {
+                        initialization_timestamp: read_i64(value, 32)?,
+                        pre_update_average_rate: read_i16(value, 40)?,
+                        last_update_timestamp: read_i64(value, 42)?,
+                        current_rate: read_i16(value, 50)?,
+                    })
+                })();
+            }
+            EXTENSION_TRANSFER_HOOK => {
+                info.transfer_hook_program = value
+                    .get(32..64)
+                    .and_then(|bytes| Pubkey::try_from(bytes).ok())
+                    .filter(|program| *program != Pubkey::default());
+            }
+            _ => {}
+        }
+    }
+    Ok(info)
+}
+
+/// Whether the token account data is in the frozen state
+pub fn token_account_is_frozen(data: &[u8]) -> bool {
+    data.len() >= ACCOUNT_LEN && data[ACCOUNT_STATE_OFFSET] == ACCOUNT_STATE_FROZEN
+}
+
+/// `TransferFeeAmount.withheld_amount` of a Token-2022 account, if present
+pub fn withheld_transfer_fee(data: &[u8]) -> Option<u64> {
+    extensions(data, ACCOUNT_TYPE_ACCOUNT)
+        .find(|(extension_type, _)| *extension_type == EXTENSION_TRANSFER_FEE_AMOUNT)
+        .and_then(|(_, value)| read_u64(value, 0))
+}
+
+/// False when a Token-2022 account only accepts confidential transfers
+pub fn accepts_non_confidential_credits(data: &[u8]) -> bool {
+    extensions(data, ACCOUNT_TYPE_ACCOUNT)
+        .find(|(extension_type, _)| *extension_type == EXTENSION_CONFIDENTIAL_TRANSFER_ACCOUNT)
+        .and_then(|(_, value)| {
+            value
+                .get(CONFIDENTIAL_ACCOUNT_NON_CONFIDENTIAL_CREDITS_OFFSET)
+                .copied()
+        })
+        .is_none_or(|allowed| allowed != 0)
+}
+
+/// Address of the transfer hook `ExtraAccountMetaList` account for `mint`
+pub fn transfer_hook_validation_address(mint: &Pubkey, hook_program: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[EXTRA_ACCOUNT_METAS_SEED, mint.as_ref()], hook_program).0
+}
+
+/// Accounts of a transfer hook `Execute` instruction, used to resolve extra account seeds
+struct HookExecuteContext<'a> {
+    accounts: Vec<AccountMeta>,
+    instruction_data: Vec<u8>,
+    fetch: &'a mut dyn FnMut(&Pubkey) -> Option<Vec<u8>>,
+}
+
+impl HookExecuteContext<'_> {
+    fn account_key(&self, index: u8) -> Result<Pubkey> {
+        self.accounts
+            .get(index as usize)
+            .map(|meta| meta.pubkey)
+            .ok_or_else(|| anyhow!("Transfer hook seed refers to unknown account {}", index))
+    }
+
+    fn account_data(&mut self, index: u8) -> Result<Vec<u8>> {
+        let key = self.account_key(index)?;
+        (self.fetch)(&key).ok_or_else(|| anyhow!("Transfer hook seed account {} not found", key))
+    }
+
+    fn seeds(&mut self, config: &[u8; 32]) -> Result<Vec<Vec<u8>>> {
+        let mut seeds = Vec::new();
+        let mut offset = 0;
+        let invalid = || anyhow!("Invalid transfer hook seed configuration");
+        while offset < config.len() {
+            let byte = |index: usize| config.get(index).copied().ok_or_else(invalid);
+            match config[offset] {
+                0 => break,
+                1 => {
+                    let length = byte(offset + 1)? as usize;
+                    let literal = config
+                        .get(offset + 2..offset + 2 + length)
+                        .ok_or_else(invalid)?;
+                    seeds.push(literal.to_vec());
+                    offset += 2 + length;
+                }
+                2 => {
+                    let (index, length) = (byte(offset + 1)? as usize, byte(offset + 2)? as usize);
+                    let data = self
+                        .instruction_data
+                        .get(index..index + length)
+                        .ok_or_else(invalid)?;
+                    seeds.push(data.to_vec());
+                    offset += 3;
+                }
+                3 => {
+                    seeds.push(self.account_key(byte(offset + 1)?)?.to_bytes().to_vec());
+                    offset += 2;
+                }
+                4 => {
+                    let account_index = byte(offset + 1)?;
+                    let (index, length) = (byte(offset + 2)? as usize, byte(offset + 3)? as usize);
+                    let data = self.account_data(account_index)?;
+                    seeds.push(
+                        data.get(index..index + length)
+                            .ok_or_else(invalid)?
+                            .to_vec(),
+                    );
+                    offset += 4;
+                }
+                other => return Err(anyhow!("Unsupported transfer hook seed type {}", other)),
+            }
+        }
+        Ok(seeds)
+    }
+
+    fn pubkey_data(&mut self, config: &[u8; 32]) -> Result<Pubkey> {
+        let invalid = || anyhow!("Invalid transfer hook pubkey configuration");
+        let bytes = match config[0] {
+            1 => {
+                let index = config[1] as usize;
+                self.instruction_data
+                    .get(index..index + 32)
+                    .ok_or_else(invalid)?
+                    .to_vec()
+            }
+            2 => {
+                let index = config[2] as usize;
+                self.account_data(config[1])?
+                    .get(index..index + 32)
+                    .ok_or_else(invalid)?
+                    .to_vec()
+            }
+            _ => return Err(invalid()),
+        };
+        Pubkey::try_from(bytes.as_slice()).map_err(|_| invalid())
+    }
+}
+
+/// Resolve the accounts a Token-2022 transfer must append for a transfer hook
+///
+/// Mirrors `spl_transfer_hook_interface::offchain`: the extra metas stored in the
+/// validation account, followed by the hook program and the validation account.
+/// `fetch` returns account data for seeds that read from other accounts.
+#[allow(clippy::too_many_arguments)]
+pub fn resolve_transfer_hook_accounts(
+    hook_program: &Pubkey,
+    validation_data: &[u8],
+    source: &Pubkey,
+    mint: &Pubkey,
+    destination: &Pubkey,
+    authority: &Pubkey,
+    amount: u64,
+    fetch: &mut dyn FnMut(&Pubkey) -> Option<Vec<u8>>,
+) -> Result<Vec<AccountMeta>> {
+    let validation_address = transfer_hook_validation_address(mint, hook_program);
+    let invalid = || anyhow!("Invalid transfer hook extra account list");
+
+    // TLV entry: discriminator (8) + length (4) + PodSlice { count (4), items }
+    let mut offset = 0;
+    let entries = loop {
+        let header = validation_data
+            .get(offset..offset + 12)
+            .ok_or_else(invalid)?;
+        let length = u32::from_le_bytes(header[8..12].try_into()?) as usize;
+        if header[..8] == TRANSFER_HOOK_EXECUTE_DISCRIMINATOR {
+            break validation_data
+                .get(offset + 12..offset + 12 + length)
+                .ok_or_else(invalid)?;
+        }
+        offset += 12 + length;
+    };
+    let count = u32::from_le_bytes(entries.get(0..4).ok_or_else(invalid)?.try_into()?) as usize;
+
+    let mut instruction_data = TRANSFER_HOOK_EXECUTE_DISCRIMINATOR.to_vec();
+    instruction_data.extend_from_slice(&amount.to_le_bytes());
+    let mut context = HookExecuteContext {
+        accounts: vec![
+            AccountMeta::new_readonly(*source, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new_readonly(*destination, false),
+            AccountMeta::new_readonly(*authority, false),
+            AccountMeta::new_readonly(validation_address, false),
+        ],
+        instruction_data,
+        fetch,
+    };
+
+    for index in 0..count {
+        let start = 4 + index * EXTRA_ACCOUNT_META_LEN;
+        let item = entries
+            .get(start..start + EXTRA_ACCOUNT_META_LEN)
+            .ok_or_else(invalid)?;
+        let config: [u8; 32] = item[1..33].try_into()?;
+        let pubkey = match item[0] {
+            0 => Pubkey::new_from_array(config),
+            1 => {
+                let seeds = context.seeds(&config)?;
+                let seeds = seeds.iter().map(Vec::as_slice).collect::<Vec<_>>();
+                Pubkey::find_program_address(&seeds, hook_program).0
+            }
+            2 => context.pubkey_data(&config)?,
+            discriminator if discriminator >= 128 => {
+                let program = context.account_key(discriminator - 128)?;
+                let seeds = context.seeds(&config)?;
+                let seeds = seeds.iter().map(Vec::as_slice).collect::<Vec<_>>();
+                Pubkey::find_program_address(&seeds, &program).0
+            }
+            other => return Err(anyhow!("Unsupported transfer hook account type {}", other)),
+        };
+        context.accounts.push(AccountMeta {
+            pubkey,
+            is_signer: item[33] != 0,
+            is_writable: item[34] != 0,
+        });
+    }
+
+    let mut accounts = context.accounts.split_off(5);
+    accounts.push(AccountMeta::new_readonly(*hook_program, false));
+    accounts.push(AccountMeta::new_readonly(validation_address, false));
+    Ok(accounts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token_2022_mint(extensions: &[(u16, Vec<u8>)]) -> Vec<u8> {
+        let mut data = vec![0u8; ACCOUNT_LEN];
+        data[MINT_DECIMALS_OFFSET] = 6;
+        data[MINT_INITIALIZED_OFFSET] = 1;
+        data.push(ACCOUNT_TYPE_MINT);
+        for (extension_type, value) in extensions {
+            data.extend_from_slice(&extension_type.to_le_bytes());
+            data.extend_from_slice(&(value.len() as u16).to_le_bytes());
+            data.extend_from_slice(value);
+        }
+        data
+    }
+
+    fn fee_bytes(epoch: u64, maximum_fee: u64, basis_points: u16) -> Vec<u8> {
+        let mut bytes = epoch.to_le_bytes().to_vec();
+        bytes.extend_from_slice(&maximum_fee.to_le_bytes());
+        bytes.extend_from_slice(&basis_points.to_le_bytes());
+        bytes
+    }
+
+    #[test]
+    fn parses_transfer_fee_and_selects_epoch() {
+        let mut value = vec![0u8; 72];
+        value.extend(fee_bytes(0, 1_000, 100));
+        value.extend(fee_bytes(500, 5_000, 250));
+        let data = token_2022_mint(&[(EXTENSION_TRANSFER_FEE_CONFIG, value)]);
+
+        let mint = parse_mint(TOKEN_2022_PROGRAM_ID, &data).unwrap();
+        assert_eq!(mint.decimals, 6);
+        let config = mint.transfer_fee.unwrap();
+        assert_eq!(config.epoch_fee(10).calculate(50_000), 500);
+        assert_eq!(config.epoch_fee(10).calculate(1_000_000), 1_000);
+        assert_eq!(config.epoch_fee(500).calculate(1_001), 26);
+        assert_eq!(config.epoch_fee(500).calculate(0), 0);
+    }
+
+    #[test]
+    fn parses_blocking_extensions_and_hook_program() {
+        let hook = Pubkey::new_unique();
+        let mut hook_value = vec![0u8; 32];
+        hook_value.extend_from_slice(hook.as_ref());
+        let data = token_2022_mint(&[
+            (EXTENSION_NON_TRANSFERABLE, Vec::new()),
+            (EXTENSION_DEFAULT_ACCOUNT_STATE, vec![ACCOUNT_STATE_FROZEN]),
+            (EXTENSION_CONFIDENTIAL_TRANSFER_MINT, vec![0u8; 65]),
+            (EXTENSION_TRANSFER_HOOK, hook_value),
+        ]);
+        let mint = parse_mint(TOKEN_2022_PROGRAM_ID, &data).unwrap();
+        assert!(mint.non_transferable);
+        assert!(mint.default_account_state_frozen);
+        assert!(mint.confidential_transfer);
+        assert_eq!(mint.transfer_hook_program, Some(hook));
+
+        // Classic mints never carry extensions
+        let classic = parse_mint(TOKEN_PROGRAM_ID, &data[..MINT_LEN]).unwrap();
+        assert!(classic.extension_labels().is_empty());
+        assert!(parse_mint(Pubkey::new_unique(), &data).is_err());
+    }
+
+    #[test]
+    fn accrues_interest_for_display() {
+        let config = InterestBearingConfig {
+            initialization_timestamp: 0,
+            pre_update_average_rate: 0,
+            last_update_timestamp: 0,
+            current_rate: 500,
+        };
+        let one_year = SECONDS_PER_YEAR as i64;
+        let ui = config.ui_amount(1_000_000, 6, one_year);
+        assert!((ui - 0.05f64.exp()).abs() < 1e-6);
+        assert_eq!(config.ui_amount_string(1_000_000, 6, 0), "1");
+    }
+
+    #[test]
+    fn resolves_transfer_hook_extra_accounts() {
+        let hook = Pubkey::new_unique();
+        let (source, mint, destination, authority) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let fixed = Pubkey::new_unique();
+
+        // Fixed account, then a hook PDA seeded by literal "counter" + mint (account 1)
+        let mut metas = Vec::new();
+        metas.push(0u8);
+        metas.extend_from_slice(fixed.as_ref());
+        metas.extend_from_slice(&[0, 1]);
+        let mut seeds = [0u8; 32];
+        seeds[..9].copy_from_slice(&[1, 7, b'c', b'o', b'u', b'n', b't', b'e', b'r']);
+        seeds[9..11].copy_from_slice(&[3, 1]);
+        metas.push(1u8);
+        metas.extend_from_slice(&seeds);
+        metas.extend_from_slice(&[0, 1]);
+
+        let mut data = TRANSFER_HOOK_EXECUTE_DISCRIMINATOR.to_vec();
+        data.extend_from_slice(&((4 + metas.len()) as u32).to_le_bytes());
+        data.extend_from_slice(&2u32.to_le_bytes());
+        data.extend_from_slice(&metas);
+
+        let accounts = resolve_transfer_hook_accounts(
+            &hook,
+            &data,
+            &source,
+            &mint,
+            &destination,
+            &authority,
+            10,
+            &mut |_| None,
+        )
+        .unwrap();
+        let counter = Pubkey::find_program_address(&[b"counter", mint.as_ref()], &hook).0;
+        let keys = accounts.iter().map(|meta| meta.pubkey).collect::<Vec<_>>();
+        assert_eq!(
+            keys,
+            vec![
+                fixed,
+                counter,
+                hook,
+                transfer_hook_validation_address(&mint, &hook)
+            ]
+        );
+        assert!(accounts[1].is_writable);
+    }
+}
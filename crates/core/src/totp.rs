@@ -192,16 +192,11 @@ impl TOTPManager {
     }
 
     /// 生成备用恢复码
+    ///
+    /// 仅生成明文；要让恢复码可用于解锁，需用 [`crate::backup_codes::generate_backup_codes`]
+    /// 将其哈希写入三因子加密包。
     pub fn generate_backup_codes(&self, count: usize) -> Vec<String> {
-        let mut codes = Vec::new();
-        let mut rng = thread_rng();
-
-        for _ in 0..count {
-            let code: String = (0..8).map(|_| rng.gen_range(0..=9).to_string()).collect();
-            codes.push(format!("{}-{}", &code[0..4], &code[4..8]));
-        }
-
-        codes
+        crate::backup_codes::new_backup_codes(count)
     }
 }
 
@@ -0,0 +1,496 @@
+//! One TOTP check for every 2FA unlock path.
+//!
+//! A code is accepted at most once: the time-step it matched is recorded per wallet and
+//! later codes must come from a newer step. Consecutive failures, wrong codes and failed
+//! decryptions alike, lock the wallet for a period that doubles with every further
+//! failure. State is kept behind [`TotpStateStore`] so each front end persists it next
+//! to its other wallet data.
+//!
+//! Wallets whose envelope is re-encrypted after an unlock (spent backup codes, a new code
+//! set, a re-bind) also record the envelope generation and the spent codes here, so an
+//! older copy of the envelope is refused even though it still decrypts.
+
+use crate::totp::{TOTPConfig, TOTPManager};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// TOTP time-step length in seconds
+pub const TOTP_STEP_SECS: u64 = 30;
+/// Steps accepted on either side of the current one for clock drift
+pub const TOTP_SKEW_STEPS: u64 = 1;
+/// Consecutive failures before the wallet is locked
+pub const MAX_FAILED_ATTEMPTS: u32 = 5;
+/// Lockout after the first locking failure; doubles with each further failure
+pub const LOCKOUT_BASE_SECS: u64 = 60;
+pub const MAX_LOCKOUT_SECS: u64 = 3600;
+
+/// Per-wallet verification state
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TotpGuardState {
+    /// Time-step of the last accepted code
+    #[serde(default)]
+    pub last_accepted_step: Option<u64>,
+    /// Failures since the last accepted code
+    #[serde(default)]
+    pub failed_attempts: u32,
+    /// Unix time until which every attempt is refused
+    #[serde(default)]
+    pub locked_until: u64,
+    /// Oldest envelope generation still accepted; [`RETIRED_GENERATION`] refuses them all
+    #[serde(default)]
+    pub generation: u64,
+    /// Hashes of backup codes already redeemed, refused even if an envelope still has them
+    #[serde(default)]
+    pub spent_backup_codes: Vec<String>,
+}
+
+/// Generation recorded for a state key that no envelope may use any more
+pub const RETIRED_GENERATION: u64 = u64::MAX;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TotpGuardError {
+    Locked {
+        retry_after_secs: u64,
+    },
+    InvalidCode {
+        remaining_attempts: u32,
+    },
+    /// The code was valid but its time-step has already been used
+    Replayed,
+    /// Another factor failed before the code could be checked
+    Unlock(String),
+    /// The secret is malformed or the state could not be read or saved
+    Config(String),
+}
+
+impl fmt::Display for TotpGuardError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Locked { retry_after_secs } => write!(
+                f,
+                "2FA 验证失败次数过多，钱包已锁定，请在 {} 秒后重试",
+                retry_after_secs
+            ),
+            Self::InvalidCode { remaining_attempts } => write!(
+                f,
+                "2FA 验证码错误，锁定前还可尝试 {} 次",
+                remaining_attempts
+            ),
+            Self::Replayed => write!(f, "该 2FA 验证码已使用过，请等待下一个验证码"),
+            Self::Unlock(message) | Self::Config(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for TotpGuardError {}
+
+impl From<TotpGuardError> for String {
+    fn from(error: TotpGuardError) -> Self {
+        error.to_string()
+    }
+}
+
+impl TotpGuardState {
+    /// Refuse while a lockout is running
+    pub fn ensure_unlocked(&self, now: u64) -> Result<(), TotpGuardError> {
+        if self.locked_until > now {
+            return Err(TotpGuardError::Locked {
+                retry_after_secs: self.locked_until - now,
+            });
+        }
+        Ok(())
+    }
+
+    /// Fold in state kept elsewhere, keeping the stricter value of each field
+    pub fn merge(&mut self, other: &Self) {
+        self.last_accepted_step = self.last_accepted_step.max(other.last_accepted_step);
+        self.failed_attempts = self.failed_attempts.max(other.failed_attempts);
+        self.locked_until = self.locked_until.max(other.locked_until);
+        self.generation = self.generation.max(other.generation);
+        for hash in &other.spent_backup_codes {
+            if !self.spent_backup_codes.contains(hash) {
+                self.spent_backup_codes.push(hash.clone());
+            }
+        }
+    }
+
+    /// Refuse an envelope older than the newest one this wallet has issued
+    pub fn ensure_current(&self, generation: u64) -> Result<(), String> {
+        if generation < self.generation {
+            return Err("钱包数据已被更新的版本取代，请使用最近一次保存的钱包".to_string());
+        }
+        Ok(())
+    }
+
+    /// Count a failed attempt and lock once the limit is reached
+    pub fn record_failure(&mut self, now: u64) {
+        self.failed_attempts = self.failed_attempts.saturating_add(1);
+        if self.failed_attempts >= MAX_FAILED_ATTEMPTS {
+            let doublings = (self.failed_attempts - MAX_FAILED_ATTEMPTS).min(16);
+            let lockout = (LOCKOUT_BASE_SECS << doublings).min(MAX_LOCKOUT_SECS);
+            self.locked_until = now + lockout;
+        }
+    }
+
+    fn failure(&mut self, now: u64) -> TotpGuardError {
+        self.record_failure(now);
+        match self.ensure_unlocked(now) {
+            Err(locked) => locked,
+            Ok(()) => TotpGuardError::InvalidCode {
+                remaining_attempts: MAX_FAILED_ATTEMPTS.saturating_sub(self.failed_attempts),
+            },
+        }
+    }
+
+    /// Check `code` against `secret` at `now` and return the accepted time-step
+    pub fn verify(&mut self, secret: &str, code: &str, now: u64) -> Result<u64, TotpGuardError> {
+        self.ensure_unlocked(now)?;
+        let totp = TOTPManager::new(TOTPConfig {
+            secret: secret.trim().to_string(),
+            step: TOTP_STEP_SECS,
+            ..TOTPConfig::default()
+        })
+        .create_totp()
+        .map_err(TotpGuardError::Config)?;
+
+        let code = code.trim();
+        let current = now / TOTP_STEP_SECS;
+        let matched = (current.saturating_sub(TOTP_SKEW_STEPS)..=current + TOTP_SKEW_STEPS)
+            .find(|step| totp.generate(step * TOTP_STEP_SECS) == code);
+        match matched {
+            None => Err(self.failure(now)),
+            Some(step) if self.last_accepted_step.is_some_and(|last| step <= last) => {
+                self.record_failure(now);
+                self.ensure_unlocked(now)?;
+                Err(TotpGuardError::Replayed)
+            }
+            Some(step) => {
+                self.last_accepted_step = Some(step);
+                self.failed_attempts = 0;
+                self.locked_until = 0;
+                Ok(step)
+            }
+        }
+    }
+}
+
+/// Where verification state is persisted, keyed by [`totp_wallet_key`]
+pub trait TotpStateStore {
+    fn load(&mut self, wallet_key: &str) -> Result<TotpGuardState, String>;
+    fn save(&mut self, wallet_key: &str, state: &TotpGuardState) -> Result<(), String>;
+}
+
+/// State for every wallet in one JSON file, e.g. next to a CLI wallet file
+pub struct JsonFileTotpStateStore {
+    path: PathBuf,
+}
+
+impl JsonFileTotpStateStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// Store kept beside `wallet_file` as `<wallet_file>.2fa-state.json`
+    pub fn beside(wallet_file: impl AsRef<Path>) -> Self {
+        let mut path = wallet_file.as_ref().as_os_str().to_owned();
+        path.push(".2fa-state.json");
+        Self::new(path)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn read_all(&self) -> Result<BTreeMap<String, TotpGuardState>, String> {
+        match std::fs::read_to_string(&self.path) {
+            Ok(text) => serde_json::from_str(&text)
+                .map_err(|e| format!("2FA 状态文件 {} 无效: {}", self.path.display(), e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+            Err(e) => Err(format!("读取 2FA 状态文件失败: {}", e)),
+        }
+    }
+}
+
+impl TotpStateStore for JsonFileTotpStateStore {
+    fn load(&mut self, wallet_key: &str) -> Result<TotpGuardState, String> {
+        Ok(self.read_all()?.remove(wallet_key).unwrap_or_default())
+    }
+
+    fn save(&mut self, wallet_key: &str, state: &TotpGuardState) -> Result<(), String> {
+        let mut all = self.read_all()?;
+        all.insert(wallet_key.to_string(), state.clone());
+        let json =
+            serde_json::to_vec_pretty(&all).map_err(|e| format!("2FA 状态序列化失败: {}", e))?;
+        let tmp = self.path.with_extension("tmp");
+        std::fs::write(&tmp, json).map_err(|e| format!("保存 2FA 状态失败: {}", e))?;
+        std::fs::rename(&tmp, &self.path).map_err(|e| format!("保存 2FA 状态失败: {}", e))
+    }
+}
+
+/// Process-lifetime state, for callers that persist the state themselves
+#[derive(Debug, Default)]
+pub struct MemoryTotpStateStore {
+    states: HashMap<String, TotpGuardState>,
+}
+
+impl TotpStateStore for MemoryTotpStateStore {
+    fn load(&mut self, wallet_key: &str) -> Result<TotpGuardState, String> {
+        Ok(self.states.get(wallet_key).cloned().unwrap_or_default())
+    }
+
+    fn save(&mut self, wallet_key: &str, state: &TotpGuardState) -> Result<(), String> {
+        self.states.insert(wallet_key.to_string(), state.clone());
+        Ok(())
+    }
+}
+
+/// Key for state tied to one piece of material: SHA-256 of a TOTP secret, or of a legacy
+/// envelope that carries no wallet id
+pub fn totp_wallet_key(material: &str) -> String {
+    hex::encode(ring::digest::digest(&ring::digest::SHA256, material.trim().as_bytes()).as_ref())
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+/// How the second factor of an unlock was satisfied
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecondFactor {
+    /// A TOTP code, accepted at this time-step
+    Totp(u64),
+    /// A one-time backup code, now spent
+    BackupCode,
+}
+
+/// Verify a code for `wallet_key` and persist the outcome
+pub fn verify_totp(
+    store: &mut dyn TotpStateStore,
+    wallet_key: &str,
+    secret: &str,
+    code: &str,
+) -> Result<u64, TotpGuardError> {
+    let ((), factor) = unlock_at(
+        store,
+        wallet_key,
+        code,
+        unix_now(),
+        || Ok(((), secret.to_string())),
+        |_, _| Ok(None),
+    )?;
+    match factor {
+        SecondFactor::Totp(step) => Ok(step),
+        SecondFactor::BackupCode => Err(TotpGuardError::Config("没有可用的备用码".to_string())),
+    }
+}
+
+/// Run `unlock`, which decrypts with the other factors and returns the TOTP secret, then
+/// verify `code`. Nothing is returned unless both succeed; either failure counts towards
+/// the lockout.
+pub fn unlock_with_totp<T>(
+    store: &mut dyn TotpStateStore,
+    wallet_key: &str,
+    code: &str,
+    unlock: impl FnOnce() -> Result<(T, String), String>,
+) -> Result<T, TotpGuardError> {
+    unlock_at(store, wallet_key, code, unix_now(), unlock, |_, _| Ok(None)).map(|(value, _)| value)
+}
+
+/// [`unlock_with_totp`] for wallets with backup codes: a code shaped like one (see
+/// [`crate::backup_codes::normalize_backup_code`]) goes to `redeem` instead, which spends
+/// it and returns the stored hash it matched. The hash is recorded as spent; a miss, or a
+/// hash spent before, counts as a failed attempt.
+pub fn unlock_with_second_factor<T>(
+    store: &mut dyn TotpStateStore,
+    wallet_key: &str,
+    code: &str,
+    unlock: impl FnOnce() -> Result<(T, String), String>,
+    redeem: impl FnOnce(&mut T, &str) -> Result<Option<String>, String>,
+) -> Result<(T, SecondFactor), TotpGuardError> {
+    unlock_at(store, wallet_key, code, unix_now(), unlock, redeem)
+}
+
+/// Run `unlock` under the wallet's lockout without a code, for reads that need the other
+/// factors only. A failure counts towards the lockout; a success leaves it as it was.
+pub fn guarded_unlock<T>(
+    store: &mut dyn TotpStateStore,
+    wallet_key: &str,
+    unlock: impl FnOnce() -> Result<T, String>,
+) -> Result<T, TotpGuardError> {
+    let now = unix_now();
+    let mut state = store.load(wallet_key).map_err(TotpGuardError::Config)?;
+    state.ensure_unlocked(now)?;
+    unlock().or_else(|message| {
+        state.record_failure(now);
+        store
+            .save(wallet_key, &state)
+            .map_err(TotpGuardError::Config)?;
+        Err(TotpGuardError::Unlock(message))
+    })
+}
+
+fn unlock_at<T>(
+    store: &mut dyn TotpStateStore,
+    wallet_key: &str,
+    code: &str,
+    now: u64,
+    unlock: impl FnOnce() -> Result<(T, String), String>,
+    redeem: impl FnOnce(&mut T, &str) -> Result<Option<String>, String>,
+) -> Result<(T, SecondFactor), TotpGuardError> {
+    let mut state = store.load(wallet_key).map_err(TotpGuardError::Config)?;
+    state.ensure_unlocked(now)?;
+    let result = match unlock() {
+        Ok((mut value, secret)) => match crate::backup_codes::normalize_backup_code(code) {
+            Some(backup_code) => match redeem(&mut value, &backup_code) {
+                Ok(Some(hash)) if !state.spent_backup_codes.contains(&hash) => {
+                    state.spent_backup_codes.push(hash);
+                    state.failed_attempts = 0;
+                    state.locked_until = 0;
+                    Ok((value, SecondFactor::BackupCode))
+                }
+                Ok(_) => Err(state.failure(now)),
+                Err(message) => Err(TotpGuardError::Config(message)),
+            },
+            None => state
+                .verify(&secret, code, now)
+                .map(|step| (value, SecondFactor::Totp(step))),
+        },
+        Err(message) => {
+            state.record_failure(now);
+            Err(TotpGuardError::Unlock(message))
+        }
+    };
+    // Fail closed: an accepted code only counts once its step is on record
+    store
+        .save(wallet_key, &state)
+        .map_err(TotpGuardError::Config)?;
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn code_at(secret: &str, now: u64) -> String {
+        TOTPManager::new(TOTPConfig {
+            secret: secret.to_string(),
+            ..TOTPConfig::default()
+        })
+        .create_totp()
+        .unwrap()
+        .generate(now)
+    }
+
+    #[test]
+    fn accepts_each_step_once() {
+        let secret = TOTPManager::generate_secret();
+        let now = 1_700_000_010;
+        let mut state = TotpGuardState::default();
+
+        let code = code_at(&secret, now);
+        assert_eq!(state.verify(&secret, &code, now), Ok(now / 30));
+        assert_eq!(
+            state.verify(&secret, &code, now + 5),
+            Err(TotpGuardError::Replayed)
+        );
+        // A code from the previous step is older than the accepted one
+        let previous = code_at(&secret, now - 30);
+        assert!(state.verify(&secret, &previous, now).is_err());
+
+        let next = code_at(&secret, now + 30);
+        assert_eq!(state.verify(&secret, &next, now + 30), Ok(now / 30 + 1));
+        assert_eq!(state.failed_attempts, 0);
+    }
+
+    #[test]
+    fn locks_out_after_repeated_failures() {
+        let secret = TOTPManager::generate_secret();
+        let now = 1_700_000_000;
+        let mut state = TotpGuardState::default();
+        let wrong = if code_at(&secret, now) == "000000" {
+            "111111"
+        } else {
+            "000000"
+        };
+
+        for remaining in (1..MAX_FAILED_ATTEMPTS).rev() {
+            assert_eq!(
+                state.verify(&secret, wrong, now),
+                Err(TotpGuardError::InvalidCode {
+                    remaining_attempts: remaining
+                })
+            );
+        }
+        assert_eq!(
+            state.verify(&secret, wrong, now),
+            Err(TotpGuardError::Locked {
+                retry_after_secs: LOCKOUT_BASE_SECS
+            })
+        );
+        // Even the right code is refused until the lockout ends
+        let code = code_at(&secret, now + 1);
+        assert!(matches!(
+            state.verify(&secret, &code, now + 1),
+            Err(TotpGuardError::Locked { .. })
+        ));
+
+        let later = now + LOCKOUT_BASE_SECS;
+        assert_eq!(
+            state.verify(&secret, wrong, later),
+            Err(TotpGuardError::Locked {
+                retry_after_secs: LOCKOUT_BASE_SECS * 2
+            })
+        );
+    }
+
+    #[test]
+    fn failed_unlocks_count_and_state_persists() {
+        let dir = std::env::temp_dir().join(format!("totp-guard-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut store = JsonFileTotpStateStore::beside(dir.join("wallet.json"));
+        let secret = TOTPManager::generate_secret();
+        let key = totp_wallet_key("encrypted");
+        let now = 1_700_000_000;
+
+        let no_backup = |_: &mut &str, _: &str| Ok(None);
+        let result = unlock_at(
+            &mut store,
+            &key,
+            "123456",
+            now,
+            || Err::<(&str, String), _>("解密失败".to_string()),
+            no_backup,
+        );
+        assert_eq!(result, Err(TotpGuardError::Unlock("解密失败".to_string())));
+        assert_eq!(store.load(&key).unwrap().failed_attempts, 1);
+
+        let code = code_at(&secret, now);
+        let unlock = || Ok(("key", secret.clone()));
+        let unlocked = unlock_at(&mut store, &key, &code, now, unlock, no_backup);
+        assert_eq!(unlocked, Ok(("key", SecondFactor::Totp(now / 30))));
+        let replay = unlock_at(&mut store, &key, &code, now, unlock, no_backup);
+        assert_eq!(replay, Err(TotpGuardError::Replayed));
+
+        // Backup-shaped codes go to `redeem`, never to the TOTP check
+        let missed = unlock_at(&mut store, &key, "1234-5678", now, unlock, no_backup);
+        assert!(matches!(missed, Err(TotpGuardError::InvalidCode { .. })));
+        let redeem = |_: &mut &str, code: &str| Ok((code == "12345678").then(|| "h1".to_string()));
+        let redeemed = unlock_at(&mut store, &key, "1234 5678", now, unlock, redeem);
+        assert_eq!(redeemed, Ok(("key", SecondFactor::BackupCode)));
+        let state = store.load(&key).unwrap();
+        assert_eq!(state.failed_attempts, 0);
+        assert_eq!(state.last_accepted_step, Some(now / 30));
+        assert_eq!(state.spent_backup_codes, vec!["h1".to_string()]);
+        // The hash is on record, so a copy of the envelope that still holds it is refused
+        let again = unlock_at(&mut store, &key, "1234 5678", now, unlock, redeem);
+        assert!(matches!(again, Err(TotpGuardError::InvalidCode { .. })));
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,412 @@
+//! Desktop API settings: platform directories, `config.toml` and env overrides.
+//!
+//! The config file is read from `$FNZERO_SAFE_CONFIG`, else `<config dir>/fnzero-safe/config.toml`
+//! (`~/.config` on Linux, `~/Library/Application Support` on macOS, `%APPDATA%` on Windows).
+//! A missing file means defaults. Every key is optional and an env var, when set, wins over
+//! the file:
+//!
+//! ```toml
+//! [server]
+//! host = "127.0.0.1"                  # HOST; must be a loopback address
+//! port = 3841                         # PORT
+//! api_token = "..."                   # FNZERO_SAFE_API_TOKEN; random per start when unset
+//! allowed_origins = ["http://localhost:5173"]  # FNZERO_SAFE_ALLOWED_ORIGINS (comma-separated)
+//!
+//! [storage]
+//! data_dir = "/srv/fnzero-safe"       # FNZERO_SAFE_DATA_DIR; default <data dir>/fnzero-safe
+//! database_path = "/srv/wallets.db"   # FNZERO_SAFE_DB_PATH; default <data_dir>/fnzero-safe.sqlite3
+//!
+//! [security]
+//! allow_secret_export = false         # FNZERO_SAFE_ALLOW_SECRET_EXPORT
+//! allow_direct_secret_input = false   # FNZERO_SAFE_ALLOW_DIRECT_SECRET_INPUT
+//!
+//! # Used when the SWQoS settings have no token for a provider; FNZERO_SAFE_*_SWQOS_API_TOKEN
+//! [swqos_tokens]
+//! flash_block = "..."
+//! block_razor = "..."
+//! astralane = "..."
+//! speedlanding = "..."
+//!
+//! [remote_sign]
+//! relay_url = "https://relay.example.com"  # FNZERO_SAFE_RELAY_URL; default relay for new pairings
+//! ```
+//!
+//! The `SOL_SAFEKEY_*` names of these env vars are still honoured.
+
+use fnzero_safe::solana_utils::swqos::{SwqosProvider, SwqosSettings};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+pub const CONFIG_PATH_ENV: &str = "FNZERO_SAFE_CONFIG";
+pub const DATA_DIR_ENV: &str = "FNZERO_SAFE_DATA_DIR";
+pub const DATABASE_PATH_ENVS: [&str; 2] = ["FNZERO_SAFE_DB_PATH", "SOL_SAFEKEY_DB_PATH"];
+pub const API_TOKEN_ENVS: [&str; 2] = ["FNZERO_SAFE_API_TOKEN", "SOL_SAFEKEY_API_TOKEN"];
+pub const ALLOWED_ORIGINS_ENVS: [&str; 2] =
+    ["FNZERO_SAFE_ALLOWED_ORIGINS", "SOL_SAFEKEY_ALLOWED_ORIGINS"];
+pub const ALLOW_SECRET_EXPORT_ENVS: [&str; 2] = [
+    "FNZERO_SAFE_ALLOW_SECRET_EXPORT",
+    "SOL_SAFEKEY_ALLOW_SECRET_EXPORT",
+];
+pub const ALLOW_DIRECT_SECRET_INPUT_ENVS: [&str; 2] = [
+    "FNZERO_SAFE_ALLOW_DIRECT_SECRET_INPUT",
+    "SOL_SAFEKEY_ALLOW_DIRECT_SECRET_INPUT",
+];
+pub const RELAY_URL_ENV: &str = "FNZERO_SAFE_RELAY_URL";
+const APP_DIR_NAME: &str = "fnzero-safe";
+const CONFIG_FILE_NAME: &str = "config.toml";
+const DATABASE_FILE_NAME: &str = "fnzero-safe.sqlite3";
+const DEFAULT_HOST: &str = "127.0.0.1";
+const DEFAULT_PORT: u16 = 3841;
+
+static CONFIG: OnceLock<DesktopConfig> = OnceLock::new();
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    #[serde(default)]
+    server: ServerSection,
+    #[serde(default)]
+    storage: StorageSection,
+    #[serde(default)]
+    security: SecuritySection,
+    #[serde(default)]
+    swqos_tokens: SwqosTokensSection,
+    #[serde(default)]
+    remote_sign: RemoteSignSection,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ServerSection {
+    host: Option<String>,
+    port: Option<u16>,
+    api_token: Option<String>,
+    allowed_origins: Option<Vec<String>>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StorageSection {
+    data_dir: Option<PathBuf>,
+    database_path: Option<PathBuf>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SecuritySection {
+    allow_secret_export: Option<bool>,
+    allow_direct_secret_input: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SwqosTokensSection {
+    flash_block: Option<String>,
+    block_razor: Option<String>,
+    astralane: Option<String>,
+    speedlanding: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RemoteSignSection {
+    relay_url: Option<String>,
+}
+
+impl SwqosTokensSection {
+    fn get(&self, provider: SwqosProvider) -> Option<&String> {
+        match provider {
+            SwqosProvider::FlashBlock => self.flash_block.as_ref(),
+            SwqosProvider::BlockRazor => self.block_razor.as_ref(),
+            SwqosProvider::Astralane => self.astralane.as_ref(),
+            SwqosProvider::Speedlanding => self.speedlanding.as_ref(),
+        }
+    }
+}
+
+/// Effective settings after applying env overrides to the config file
+#[derive(Debug, Clone)]
+pub struct DesktopConfig {
+    /// Config file that was read, if any
+    pub config_path: Option<PathBuf>,
+    pub host: String,
+    pub port: u16,
+    pub api_token: Option<String>,
+    /// Raw origins; normalized where they are checked
+    pub allowed_origins: Vec<String>,
+    pub database_path: PathBuf,
+    /// The database path is the platform default, so a legacy database may be migrated into it
+    pub database_path_is_default: bool,
+    pub allow_secret_export: bool,
+    pub allow_direct_secret_input: bool,
+    /// Relay offered by default when pairing a remote signing client
+    pub remote_sign_relay_url: Option<String>,
+    swqos_tokens: Vec<(SwqosProvider, String)>,
+}
+
+/// Load the config once at startup; fails on an unreadable or invalid config file
+pub fn init() -> Result<&'static DesktopConfig, String> {
+    let config = DesktopConfig::load()?;
+    Ok(CONFIG.get_or_init(|| config))
+}
+
+/// The loaded config; falls back to env vars and defaults if [`init`] was not called
+pub fn get() -> &'static DesktopConfig {
+    CONFIG.get_or_init(|| {
+        DesktopConfig::load().unwrap_or_else(|e| {
+            tracing::warn!("{}; using env vars and defaults", e);
+            DesktopConfig::resolve(
+                ConfigFile::default(),
+                None,
+                &process_env,
+                platform_data_dir(),
+            )
+        })
+    })
+}
+
+fn process_env(name: &str) -> Option<String> {
+    std::env::var(name)
+        .ok()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+fn platform_data_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join(APP_DIR_NAME))
+}
+
+/// `$FNZERO_SAFE_CONFIG`, else `<config dir>/fnzero-safe/config.toml`
+pub fn config_file_path() -> Option<PathBuf> {
+    process_env(CONFIG_PATH_ENV)
+        .map(PathBuf::from)
+        .or_else(|| dirs::config_dir().map(|dir| dir.join(APP_DIR_NAME).join(CONFIG_FILE_NAME)))
+}
+
+impl DesktopConfig {
+    fn load() -> Result<Self, String> {
+        let path = config_file_path();
+        let file = match &path {
+            Some(path) => read_config_file(path, process_env(CONFIG_PATH_ENV).is_some())?,
+            None => None,
+        };
+        let config_path = file.is_some().then(|| path.clone()).flatten();
+        Ok(Self::resolve(
+            file.unwrap_or_default(),
+            config_path,
+            &process_env,
+            platform_data_dir(),
+        ))
+    }
+
+    fn resolve(
+        file: ConfigFile,
+        config_path: Option<PathBuf>,
+        env: &dyn Fn(&str) -> Option<String>,
+        platform_data_dir: Option<PathBuf>,
+    ) -> Self {
+        let first_env = |names: &[&str]| names.iter().find_map(|name| env(name));
+        let flag = |names: &[&str], file_value: Option<bool>| {
+            first_env(names)
+                .map(|value| value.eq_ignore_ascii_case("true"))
+                .or(file_value)
+                .unwrap_or(false)
+        };
+
+        let data_dir = env(DATA_DIR_ENV)
+            .map(PathBuf::from)
+            .or(file.storage.data_dir)
+            .or(platform_data_dir)
+            // No home directory to resolve against: keep the pre-config location
+            .unwrap_or_else(|| Path::new(env!("CARGO_MANIFEST_DIR")).join("data"));
+        let explicit_database_path = first_env(&DATABASE_PATH_ENVS)
+            .map(PathBuf::from)
+            .or(file.storage.database_path);
+
+        let allowed_origins = match first_env(&ALLOWED_ORIGINS_ENVS) {
+            Some(value) => value
+                .split(',')
+                .map(str::trim)
+                .filter(|origin| !origin.is_empty())
+                .map(ToOwned::to_owned)
+                .collect(),
+            None => file.server.allowed_origins.unwrap_or_default(),
+        };
+        let swqos_tokens = SwqosProvider::ALL
+            .into_iter()
+            .filter_map(|provider| {
+                let token = file.swqos_tokens.get(provider)?.trim();
+                (!token.is_empty()).then(|| (provider, token.to_string()))
+            })
+            .collect();
+
+        Self {
+            config_path,
+            host: env("HOST")
+                .or(file.server.host)
+                .unwrap_or_else(|| DEFAULT_HOST.to_string()),
+            port: env("PORT")
+                .and_then(|port| port.parse().ok())
+                .or(file.server.port)
+                .unwrap_or(DEFAULT_PORT),
+            api_token: first_env(&API_TOKEN_ENVS).or_else(|| {
+                file.server
+                    .api_token
+                    .map(|token| token.trim().to_string())
+                    .filter(|token| !token.is_empty())
+            }),
+            allowed_origins,
+            database_path_is_default: explicit_database_path.is_none(),
+            database_path: explicit_database_path
+                .unwrap_or_else(|| data_dir.join(DATABASE_FILE_NAME)),
+            allow_secret_export: flag(&ALLOW_SECRET_EXPORT_ENVS, file.security.allow_secret_export),
+            allow_direct_secret_input: flag(
+                &ALLOW_DIRECT_SECRET_INPUT_ENVS,
+                file.security.allow_direct_secret_input,
+            ),
+            remote_sign_relay_url: env(RELAY_URL_ENV)
+                .or(file.remote_sign.relay_url)
+                .map(|url| url.trim().to_string())
+                .filter(|url| !url.is_empty()),
+            swqos_tokens,
+        }
+    }
+
+    /// Config-file token for `provider`
+    pub fn swqos_token(&self, provider: SwqosProvider) -> Option<&str> {
+        self.swqos_tokens
+            .iter()
+            .find(|(candidate, _)| *candidate == provider)
+            .map(|(_, token)| token.as_str())
+    }
+
+    /// Fill in config-file tokens for providers with neither a saved nor an env token
+    ///
+    /// Only for sending: the result must not be saved back to the database.
+    pub fn apply_swqos_tokens(&self, settings: &mut SwqosSettings) {
+        let missing: Vec<bool> = settings
+            .providers
+            .iter()
+            .map(|entry| settings.provider_token(entry).is_none())
+            .collect();
+        for (entry, missing) in settings.providers.iter_mut().zip(missing) {
+            if let Some(token) = self.swqos_token(entry.provider).filter(|_| missing) {
+                entry.api_token = Some(token.to_string());
+            }
+        }
+    }
+}
+
+/// `Ok(None)` when the file does not exist, unless it was named explicitly
+fn read_config_file(path: &Path, explicit: bool) -> Result<Option<ConfigFile>, String> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound && !explicit => return Ok(None),
+        Err(e) => return Err(format!("读取配置文件失败 {}: {}", path.display(), e)),
+    };
+    toml::from_str(&content)
+        .map(Some)
+        .map_err(|e| format!("解析配置文件失败 {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn resolve(file: &str, env: &[(&str, &str)]) -> DesktopConfig {
+        let env: HashMap<String, String> = env
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        DesktopConfig::resolve(
+            toml::from_str(file).unwrap(),
+            None,
+            &|name| env.get(name).cloned(),
+            Some(PathBuf::from("/home/user/.local/share/fnzero-safe")),
+        )
+    }
+
+    #[test]
+    fn defaults_use_the_platform_data_dir() {
+        let config = resolve("", &[]);
+        assert_eq!(
+            config.database_path,
+            PathBuf::from("/home/user/.local/share/fnzero-safe/fnzero-safe.sqlite3")
+        );
+        assert!(config.database_path_is_default);
+        assert_eq!((config.host.as_str(), config.port), ("127.0.0.1", 3841));
+        assert!(!config.allow_secret_export);
+        assert!(config.api_token.is_none());
+    }
+
+    #[test]
+    fn env_vars_override_file_values() {
+        let file = r#"
+            [server]
+            port = 4000
+            api_token = "from-file"
+            allowed_origins = ["http://localhost:5173"]
+
+            [storage]
+            data_dir = "/srv/fnzero"
+
+            [security]
+            allow_secret_export = true
+
+            [swqos_tokens]
+            astralane = "file-token"
+
+            [remote_sign]
+            relay_url = "https://relay.file.test"
+        "#;
+        let config = resolve(file, &[]);
+        assert_eq!(config.port, 4000);
+        assert_eq!(config.api_token.as_deref(), Some("from-file"));
+        assert_eq!(config.allowed_origins, ["http://localhost:5173"]);
+        assert_eq!(
+            config.database_path,
+            PathBuf::from("/srv/fnzero/fnzero-safe.sqlite3")
+        );
+        assert!(config.allow_secret_export);
+        assert_eq!(
+            config.swqos_token(SwqosProvider::Astralane),
+            Some("file-token")
+        );
+        assert_eq!(
+            config.remote_sign_relay_url.as_deref(),
+            Some("https://relay.file.test")
+        );
+
+        let config = resolve(
+            file,
+            &[
+                ("PORT", "5000"),
+                ("SOL_SAFEKEY_API_TOKEN", "from-env"),
+                (
+                    "FNZERO_SAFE_ALLOWED_ORIGINS",
+                    "http://a.test, http://b.test",
+                ),
+                ("FNZERO_SAFE_DB_PATH", "/tmp/wallets.db"),
+                ("FNZERO_SAFE_ALLOW_SECRET_EXPORT", "false"),
+                ("FNZERO_SAFE_RELAY_URL", "http://127.0.0.1:8787"),
+            ],
+        );
+        assert_eq!(config.port, 5000);
+        assert_eq!(config.api_token.as_deref(), Some("from-env"));
+        assert_eq!(config.allowed_origins, ["http://a.test", "http://b.test"]);
+        assert_eq!(config.database_path, PathBuf::from("/tmp/wallets.db"));
+        assert!(!config.database_path_is_default);
+        assert!(!config.allow_secret_export);
+        assert_eq!(
+            config.remote_sign_relay_url.as_deref(),
+            Some("http://127.0.0.1:8787")
+        );
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        assert!(toml::from_str::<ConfigFile>("[server]\nprot = 1").is_err());
+    }
+}
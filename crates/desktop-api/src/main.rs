@@ -21,7 +21,29 @@ use base64::{
     Engine as _,
 };
 use bip39::{Language, Mnemonic};
-use fnzero_safe::solana_utils::{lamports_to_sol, SolanaClient};
+use fnzero_safe::address_book::{check_recipient, KnownAddress, Lookalike};
+use fnzero_safe::device_factor::{self, DeviceSecret};
+use fnzero_safe::hardware_fingerprint::HardwareFingerprint;
+use fnzero_safe::security_question::{
+    QuestionPrompt, SecurityQuestionSet, MAX_SECURITY_QUESTIONS, SECURITY_QUESTIONS,
+    SECURITY_QUESTIONS_EN,
+};
+use fnzero_safe::solana_utils::batch_sell::{
+    batch_sell, BatchSellOptions, BatchSellReport, TokenHolding,
+};
+use fnzero_safe::solana_utils::payouts::{
+    self, PayoutEntry, PayoutIssue, PayoutLineStatus, PayoutPlan, MAX_PAYOUT_ENTRIES,
+};
+use fnzero_safe::solana_utils::position_watcher::PumpVenue;
+use fnzero_safe::solana_utils::pump_route::{self, PumpRoute};
+use fnzero_safe::solana_utils::stake::{StakeAccount, StakeAction, StakeAuthority};
+use fnzero_safe::solana_utils::swqos::{
+    self, SwqosProvider, SwqosProviderSettings, SwqosSettings, SwqosTipStrategy,
+};
+use fnzero_safe::solana_utils::token_extensions::{self, MintInfo};
+use fnzero_safe::solana_utils::{
+    lamports_to_sol, normalize_memo, EmptyTokenAccount, SolanaClient, TokenTransferPlan,
+};
 use fnzero_safe::{KeyManager, KeystoreVersion};
 use futures::{
     future::join_all,
@@ -37,14 +59,9 @@ use sol_trade_sdk::{
         fast_fn::get_associated_token_address_with_program_id_fast_use_seed, GasFeeStrategy,
         SolanaRpcClient, TradeConfig,
     },
-    constants::WSOL_TOKEN_ACCOUNT,
-    instruction::utils::pumpswap,
-    swqos::{SwqosConfig, SwqosRegion, SwqosType},
-    trading::{
-        core::params::{DexParamEnum, PumpFunParams, PumpSwapParams},
-        factory::DexType,
-    },
-    SolanaTrade, TradeSellParams, TradeTokenType, TradingInfrastructure,
+    constants::{TOKEN_PROGRAM, WSOL_TOKEN_ACCOUNT},
+    trading::factory::DexType,
+    SolanaTrade, TradeBuyParams, TradeSellParams, TradeTokenType,
 };
 use solana_account_decoder_client_types::{
     token::{TokenAccountType, UiExtension},
@@ -59,11 +76,11 @@ use solana_loader_v3_interface::{
 use solana_rpc_client_api::{
     config::{RpcAccountInfoConfig, RpcSimulateTransactionConfig, RpcTransactionConfig},
     request::{Address as RpcAddress, TokenAccountsFilter},
-    response::RpcKeyedAccount,
+    response::{RpcConfirmedTransactionStatusWithSignature, RpcKeyedAccount},
 };
 use solana_sdk::account::Account;
 use solana_sdk::instruction::{AccountMeta, Instruction};
-use solana_sdk::message::Message;
+use solana_sdk::message::{AddressLookupTableAccount, Message};
 use solana_sdk::pubkey::Pubkey;
 use solana_sdk::sanitize::Sanitize;
 use solana_sdk::signature::Signer;
@@ -76,7 +93,7 @@ use solana_transaction_status_client_types::{
     option_serializer::OptionSerializer, EncodedTransaction, TransactionStatus, UiInstruction,
     UiMessage, UiParsedInstruction, UiTransactionEncoding,
 };
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::net::{IpAddr, SocketAddr};
 use std::panic::AssertUnwindSafe;
@@ -87,23 +104,30 @@ use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 use zeroize::{Zeroize, Zeroizing};
 
+mod config;
 mod program_deploy;
 mod program_source_build;
-mod squads_v4;
+mod remote_sign;
+mod vault;
 mod wallet_store;
+use fnzero_safe_remote_sign as remote_sign_protocol;
+use fnzero_safe_squads as squads_v4;
 use program_source_build::{
     display_program_source_build_command, execute_program_source_build, program_source_build_plans,
     program_source_keys_build_warning, ProgramSourceBuildPlan,
 };
 use squads_v4::{
-    Member as SquadsMember, Multisig as SquadsMultisig, Permissions as SquadsPermissions,
-    ProgramConfig as SquadsProgramConfig, Proposal as SquadsProposal,
+    Batch as SquadsBatch, Member as SquadsMember, Multisig as SquadsMultisig,
+    Permissions as SquadsPermissions, ProgramConfig as SquadsProgramConfig,
+    Proposal as SquadsProposal, VaultBatchTransaction as SquadsVaultBatchTransaction,
     VaultTransaction as SquadsVaultTransaction,
 };
 use wallet_store::TokenMetadataRecord;
 use wallet_store::WalletAssetsRecord;
 use wallet_store::WalletSummary;
 use wallet_store::WalletTokenAssetRecord;
+use wallet_store::WalletTransactionChange;
+use wallet_store::WalletTransactionRecord;
 
 const API_TOKEN_HEADER: &str = "x-fnzero-safe-token";
 const LEGACY_API_TOKEN_HEADER: &str = "x-sol-safekey-token";
@@ -117,6 +141,11 @@ const MAX_GENERIC_PROGRAM_INSTRUCTION_DATA_BYTES: usize = 8 * 1024;
 const MAX_EXTERNAL_SIGN_MESSAGE_BYTES: usize = 16 * 1024;
 const MAX_GENERIC_PROGRAM_INSTRUCTION_ACCOUNTS: usize = 64;
 const MAX_GENERIC_PROGRAM_ADDITIONAL_SIGNERS: usize = 8;
+const MAX_SQUADS_PROPOSAL_INSTRUCTIONS: usize = 16;
+const MAX_SQUADS_BATCH_TRANSACTIONS: usize = 20;
+const MAX_SQUADS_ADDRESS_LOOKUP_TABLES: usize = 4;
+const MAX_SQUADS_CLEANUP_CLOSES: usize = 40;
+const SQUADS_CLOSES_PER_TRANSACTION: usize = 4;
 const PROGRAM_WRITE_CHUNK_BYTES: usize = 800;
 const SOLANA_TRANSACTION_PACKET_DATA_BYTES: usize = 1232;
 const UPGRADEABLE_LOADER_ID: Pubkey =
@@ -132,12 +161,16 @@ const MAX_TOKEN_DECIMALS: u8 = 18;
 const DEFAULT_SLIPPAGE_BPS: u64 = 100;
 const MAX_SLIPPAGE_BPS: u64 = 10_000;
 const DEFAULT_SELL_PERCENT_BPS: u64 = 10_000;
-const PUMPFUN_PARAM_RPC_ATTEMPTS: usize = 2;
-const PUMPFUN_PARAM_RPC_TIMEOUT_MS: u64 = 1_500;
-const PUMPFUN_PARAM_RETRY_DELAY_MS: u64 = 75;
 const PUMP_SELL_SUBMIT_TIMEOUT_SECS: u64 = 8;
+const PUMP_BUY_SUBMIT_TIMEOUT_SECS: u64 = 8;
 const MAX_NONCE_BATCH_COUNT: u8 = 20;
 const MAX_WALLET_TRANSACTION_HISTORY: usize = 100;
+// Transactions whose details are fetched per history sync pass
+const WALLET_HISTORY_SYNC_BATCH: usize = 100;
+const WALLET_HISTORY_SIGNATURE_PAGE: usize = 1000;
+const WALLET_HISTORY_DETAIL_RETRIES: usize = 20;
+// Backfill passes a single filtered page may trigger before returning what it has
+const WALLET_HISTORY_MAX_BACKFILL_ROUNDS: usize = 5;
 const PUMPFUN_UVA_DISCRIMINATOR: [u8; 8] = [86, 255, 112, 14, 102, 53, 154, 250];
 const PUMPFUN_PROGRAM_ID: &str = "6EF8rrecthR5DkP5hnbZQGmVfRGhPUgAaoeS8QJmR5j";
 const PUMPSWAP_PROGRAM_ID: &str = "pAMMBay6oceH9fJKBRHGP5D4bD4sWpmSwMn52FMfXEA";
@@ -221,28 +254,13 @@ const TOKEN_METADATA_JSON_TIMEOUT_MS: u64 = 1_500;
 const TOKEN_METADATA_CACHE_TTL_SECS: u64 = 24 * 60 * 60;
 const RPC_QUERY_TIMEOUT_SECS: u64 = 8;
 const RPC_TRANSACTION_DETAIL_TIMEOUT_SECS: u64 = 2;
-const ALLOW_SECRET_EXPORT_ENV: &str = "FNZERO_SAFE_ALLOW_SECRET_EXPORT";
-const LEGACY_ALLOW_SECRET_EXPORT_ENV: &str = "SOL_SAFEKEY_ALLOW_SECRET_EXPORT";
-const ALLOW_DIRECT_SECRET_INPUT_ENV: &str = "FNZERO_SAFE_ALLOW_DIRECT_SECRET_INPUT";
-const LEGACY_ALLOW_DIRECT_SECRET_INPUT_ENV: &str = "SOL_SAFEKEY_ALLOW_DIRECT_SECRET_INPUT";
 const DEFAULT_MNEMONIC_DERIVATION_PATH: &str = "m/44'/501'/0'/0'";
-const ALLOWED_ORIGINS_ENV: &str = "FNZERO_SAFE_ALLOWED_ORIGINS";
-const LEGACY_ALLOWED_ORIGINS_ENV: &str = "SOL_SAFEKEY_ALLOWED_ORIGINS";
 const MAX_SECURITY_QUESTION_INDEX: usize = 7;
 const SECURE_BODY_HEADER: &str = "x-fnzero-safe-secure-body";
 const LEGACY_SECURE_BODY_HEADER: &str = "x-sol-safekey-secure-body";
 const SECURE_BODY_VERSION: &str = "1";
 const SECURE_MAX_ENCRYPTED_KEY_BYTES: usize = 1024;
 const SECURE_OPTIONAL_API_PATHS: &[&str] = &["/api/health", "/api/secure/session"];
-const FLASHBLOCK_SWQOS_API_TOKEN_ENV: &str = "FNZERO_SAFE_FLASHBLOCK_SWQOS_API_TOKEN";
-const LEGACY_FLASHBLOCK_SWQOS_API_TOKEN_ENV: &str = "SOL_SAFEKEY_FLASHBLOCK_SWQOS_API_TOKEN";
-const BLOCKRAZOR_SWQOS_API_TOKEN_ENV: &str = "FNZERO_SAFE_BLOCKRAZOR_SWQOS_API_TOKEN";
-const LEGACY_BLOCKRAZOR_SWQOS_API_TOKEN_ENV: &str = "SOL_SAFEKEY_BLOCKRAZOR_SWQOS_API_TOKEN";
-const ASTRALANE_SWQOS_API_TOKEN_ENV: &str = "FNZERO_SAFE_ASTRALANE_SWQOS_API_TOKEN";
-const LEGACY_ASTRALANE_SWQOS_API_TOKEN_ENV: &str = "SOL_SAFEKEY_ASTRALANE_SWQOS_API_TOKEN";
-const SPEEDLANDING_SWQOS_API_TOKEN_ENV: &str = "FNZERO_SAFE_SPEEDLANDING_SWQOS_API_TOKEN";
-const LEGACY_SPEEDLANDING_SWQOS_API_TOKEN_ENV: &str = "SOL_SAFEKEY_SPEEDLANDING_SWQOS_API_TOKEN";
-const DEFAULT_SWQOS_TIP_SOL: f64 = 0.0001;
 
 static SECURE_BODY_KEYPAIR: OnceLock<SecureBodyKeyPair> = OnceLock::new();
 static LOCAL_API_TOKEN: OnceLock<String> = OnceLock::new();
@@ -254,6 +272,8 @@ static PROGRAM_SOURCE_BUILD_JOBS: OnceLock<
 > = OnceLock::new();
 static SBF_VERIFY_LIMIT: OnceLock<Arc<tokio::sync::Semaphore>> = OnceLock::new();
 static KEYSTORE_TASK_LIMIT: OnceLock<Arc<tokio::sync::Semaphore>> = OnceLock::new();
+static WALLET_HISTORY_SYNC_LOCK: OnceLock<tokio::sync::Mutex<()>> = OnceLock::new();
+static TOTP_UNLOCK_LOCK: OnceLock<tokio::sync::Mutex<()>> = OnceLock::new();
 
 #[derive(Clone, Debug, Serialize)]
 struct ProgramUpgradeProgress {
@@ -368,78 +388,55 @@ async fn finish_program_upgrade_progress(result: &Result<Json<UpgradeProgramResp
     }
 }
 
-fn official_swqos_configs() -> Vec<SwqosConfig> {
-    let region = SwqosRegion::Frankfurt;
-    let mut configs = Vec::new();
-    if let Some(token) = configured_env_value(&[
-        FLASHBLOCK_SWQOS_API_TOKEN_ENV,
-        LEGACY_FLASHBLOCK_SWQOS_API_TOKEN_ENV,
-    ]) {
-        configs.push(SwqosConfig::FlashBlock(token, region.clone(), None));
-    }
-    if let Some(token) = configured_env_value(&[
-        BLOCKRAZOR_SWQOS_API_TOKEN_ENV,
-        LEGACY_BLOCKRAZOR_SWQOS_API_TOKEN_ENV,
-    ]) {
-        configs.push(SwqosConfig::BlockRazor(token, region.clone(), None, None));
-    }
-    if let Some(token) = configured_env_value(&[
-        ASTRALANE_SWQOS_API_TOKEN_ENV,
-        LEGACY_ASTRALANE_SWQOS_API_TOKEN_ENV,
-    ]) {
-        configs.push(SwqosConfig::Astralane(token, region.clone(), None, None));
-    }
-    if let Some(token) = configured_env_value(&[
-        SPEEDLANDING_SWQOS_API_TOKEN_ENV,
-        LEGACY_SPEEDLANDING_SWQOS_API_TOKEN_ENV,
-    ]) {
-        configs.push(SwqosConfig::Speedlanding(token, region, None));
+fn load_swqos_settings() -> Result<SwqosSettings, ApiError> {
+    wallet_store::get_swqos_settings()
+        .map(Option::unwrap_or_default)
+        .map_err(|e| ApiError { message: e })
+}
+
+/// Mirrors the saved settings (without tokens) to the `swqos.json` the CLI reads.
+/// The database copy stays authoritative; a failed export only logs.
+fn export_swqos_settings(settings: &SwqosSettings) {
+    let Some(path) = swqos::default_swqos_config_path() else {
+        tracing::warn!("No config dir; CLI SWQoS settings not exported");
+        return;
+    };
+    match settings.export_json_file(&path) {
+        Ok(()) => tracing::info!("Exported SWQoS settings for the CLI to {}", path.display()),
+        Err(e) => tracing::warn!("Failed to export SWQoS settings for the CLI: {}", e),
     }
-    configs
 }
 
-fn default_swqos_configs(rpc_url: &str) -> Vec<SwqosConfig> {
-    let mut configs = official_swqos_configs();
-    configs.push(SwqosConfig::Default(rpc_url.to_string()));
-    configs
+/// Saved settings plus config-file tokens; never save the result
+fn load_sending_swqos_settings() -> Result<SwqosSettings, ApiError> {
+    let mut settings = load_swqos_settings()?;
+    config::get().apply_swqos_tokens(&mut settings);
+    Ok(settings)
 }
 
-fn swqos_only_sell_client(
-    client: &SolanaTrade,
+async fn swqos_trade_client(
+    settings: &SwqosSettings,
     payer: Arc<Keypair>,
+    rpc_url: &str,
     use_seed: bool,
 ) -> Result<SolanaTrade, ApiError> {
-    let swqos_clients = client
+    let client = settings
+        .trade_client(payer, rpc_url, use_seed)
+        .await
+        .map_err(|message| ApiError { message })?;
+    let labels = client
         .infrastructure
         .swqos_clients
-        .iter()
-        .filter(|swqos| !matches!(swqos.get_swqos_type(), SwqosType::Default))
-        .cloned()
-        .collect::<Vec<_>>();
-    if swqos_clients.is_empty() {
-        return Err(ApiError {
-            message: "未初始化可用 SWQOS 通道，已按配置禁止 RPC 卖出".to_string(),
-        });
-    }
-
-    let labels = swqos_clients
         .iter()
         .map(|swqos| swqos.get_swqos_type().as_str())
         .collect::<Vec<_>>();
-    tracing::info!("Pump sell SWQOS-only channels: {}", labels.join(", "));
-
-    let infrastructure = TradingInfrastructure {
-        rpc: client.infrastructure.rpc.clone(),
-        swqos_clients: Arc::new(swqos_clients),
-        config: client.infrastructure.config.clone(),
-        max_sender_concurrency: client.infrastructure.max_sender_concurrency,
-        effective_core_ids: client.infrastructure.effective_core_ids.clone(),
-    };
-    let mut sell_client =
-        SolanaTrade::from_infrastructure(payer, Arc::new(infrastructure), use_seed);
-    sell_client.log_enabled = false;
-    sell_client.check_min_tip = false;
-    Ok(sell_client)
+    tracing::info!(
+        "Pump trade channels: {} (swqos_only={}, sender_concurrency={})",
+        labels.join(", "),
+        settings.swqos_only,
+        client.max_sender_concurrency
+    );
+    Ok(client)
 }
 
 struct SecureBodyKeyPair {
@@ -483,24 +480,7 @@ fn local_api_token() -> &'static str {
 }
 
 fn configured_api_token() -> Option<String> {
-    configured_env_value(&["FNZERO_SAFE_API_TOKEN", "SOL_SAFEKEY_API_TOKEN"])
-}
-
-fn configured_env_value(names: &[&str]) -> Option<String> {
-    names.iter().find_map(|name| {
-        std::env::var(name)
-            .ok()
-            .map(|value| value.trim().to_string())
-            .filter(|value| !value.is_empty())
-    })
-}
-
-fn configured_env_flag(names: &[&str]) -> bool {
-    names.iter().any(|name| {
-        std::env::var(name)
-            .ok()
-            .is_some_and(|value| value.trim().eq_ignore_ascii_case("true"))
-    })
+    config::get().api_token.clone()
 }
 
 fn is_optional_api_path(path: &str) -> bool {
@@ -1415,7 +1395,7 @@ async fn execute_cashback_claim(
 ) -> Result<String, ApiError> {
     let payer = Arc::new(keypair);
     let commitment = CommitmentConfig::confirmed();
-    let swqos_configs = default_swqos_configs(rpc_url);
+    let swqos_configs = load_sending_swqos_settings()?.swqos_configs(rpc_url);
     let trade_config = TradeConfig::builder(rpc_url.to_string(), swqos_configs, commitment)
         .create_wsol_ata_on_startup(false)
         .use_seed_optimize(false)
@@ -1436,13 +1416,13 @@ async fn execute_cashback_claim(
 }
 
 fn require_secret_export_enabled() -> Result<(), ApiError> {
-    if configured_env_flag(&[ALLOW_SECRET_EXPORT_ENV, LEGACY_ALLOW_SECRET_EXPORT_ENV]) {
+    if config::get().allow_secret_export {
         Ok(())
     } else {
         Err(ApiError {
             message: format!(
                 "明文密钥导出仅允许桌面端或已验证的本机页面；如确需其它 Web 调试，请显式设置 {}=true",
-                ALLOW_SECRET_EXPORT_ENV
+                config::ALLOW_SECRET_EXPORT_ENVS[0]
             ),
         })
     }
@@ -1484,7 +1464,7 @@ fn header_origin_or_referer_is_local(headers: &HeaderMap) -> bool {
 }
 
 fn require_secret_export_enabled_or_tauri(headers: &HeaderMap) -> Result<(), ApiError> {
-    if configured_env_flag(&[ALLOW_SECRET_EXPORT_ENV, LEGACY_ALLOW_SECRET_EXPORT_ENV]) {
+    if config::get().allow_secret_export {
         return Ok(());
     }
     if header_origin_is_tauri(headers)
@@ -1497,16 +1477,13 @@ fn require_secret_export_enabled_or_tauri(headers: &HeaderMap) -> Result<(), Api
 }
 
 fn require_direct_secret_input_enabled() -> Result<(), ApiError> {
-    if configured_env_flag(&[
-        ALLOW_DIRECT_SECRET_INPUT_ENV,
-        LEGACY_ALLOW_DIRECT_SECRET_INPUT_ENV,
-    ]) {
+    if config::get().allow_direct_secret_input {
         Ok(())
     } else {
         Err(ApiError {
             message: format!(
                 "Web 直接提交明文私钥或加密私钥已禁用；请导入 keystore 后选择钱包，或显式设置 {}=true 进行本机调试",
-                ALLOW_DIRECT_SECRET_INPUT_ENV
+                config::ALLOW_DIRECT_SECRET_INPUT_ENVS[0]
             ),
         })
     }
@@ -1566,14 +1543,11 @@ fn normalized_origin(value: &str) -> Option<String> {
 }
 
 fn configured_allowed_origins() -> Vec<String> {
-    configured_env_value(&[ALLOWED_ORIGINS_ENV, LEGACY_ALLOWED_ORIGINS_ENV])
-        .map(|value| {
-            value
-                .split(',')
-                .filter_map(|item| normalized_origin(item.trim()))
-                .collect()
-        })
-        .unwrap_or_default()
+    config::get()
+        .allowed_origins
+        .iter()
+        .filter_map(|item| normalized_origin(item))
+        .collect()
 }
 
 fn is_allowed_local_origin(value: &str) -> bool {
@@ -1620,7 +1594,7 @@ async fn require_local_origin(headers: HeaderMap, request: Request, next: Next)
                 Json(json!({
                     "error": format!(
                         "拒绝非本机页面发起的本地 API 请求；如需允许自己的 HTTPS 页面访问本机 API，请设置 {}=https://你的域名",
-                        ALLOWED_ORIGINS_ENV
+                        config::ALLOWED_ORIGINS_ENVS[0]
                     )
                 })),
             )
@@ -2376,6 +2350,7 @@ fn token_accounts_by_program(
 ) -> Result<
     Vec<(
         String,
+        Pubkey,
         solana_account_decoder_client_types::token::UiTokenAccount,
     )>,
     ApiError,
@@ -2393,6 +2368,7 @@ fn token_accounts_by_program(
     Ok(accounts
         .into_iter()
         .filter_map(parse_token_account)
+        .map(|(account, token_account)| (account, program_id, token_account))
         .collect())
 }
 
@@ -2402,6 +2378,7 @@ fn all_owner_token_accounts(
 ) -> Result<
     Vec<(
         String,
+        Pubkey,
         solana_account_decoder_client_types::token::UiTokenAccount,
     )>,
     ApiError,
@@ -2913,6 +2890,52 @@ fn load_squads_vault_transaction(
         .map_err(|message| ApiError { message })
 }
 
+fn load_squads_batch(client: &RpcClient, batch: &Pubkey) -> Result<SquadsBatch, ApiError> {
+    let account = client.get_account(batch).map_err(|e| ApiError {
+        message: format!("读取 Squads batch 账户失败: {}", e),
+    })?;
+    squads_v4::decode_account::<SquadsBatch>(&account.data, "Batch")
+        .map_err(|message| ApiError { message })
+}
+
+fn load_squads_batch_transaction(
+    client: &RpcClient,
+    transaction: &Pubkey,
+) -> Result<SquadsVaultBatchTransaction, ApiError> {
+    let account = client.get_account(transaction).map_err(|e| ApiError {
+        message: format!("读取 Squads batch 交易账户失败: {}", e),
+    })?;
+    squads_v4::decode_account::<SquadsVaultBatchTransaction>(&account.data, "VaultBatchTransaction")
+        .map_err(|message| ApiError { message })
+}
+
+fn load_address_lookup_tables(
+    client: &RpcClient,
+    keys: &[Pubkey],
+) -> Result<Vec<AddressLookupTableAccount>, ApiError> {
+    keys.iter()
+        .map(|key| {
+            let account = client.get_account(key).map_err(|e| ApiError {
+                message: format!("读取 Address Lookup Table {} 失败: {}", key, e),
+            })?;
+            squads_v4::parse_address_lookup_table(key, &account.data)
+                .map_err(|message| ApiError { message })
+        })
+        .collect()
+}
+
+fn load_squads_message_lookup_tables(
+    client: &RpcClient,
+    message: &squads_v4::TransactionMessage,
+) -> Result<Vec<AddressLookupTableAccount>, ApiError> {
+    let keys = message
+        .address_table_lookups
+        .iter()
+        .map(|lookup| lookup.account_key)
+        .collect::<Vec<_>>();
+    load_address_lookup_tables(client, &keys)
+}
+
 fn load_squads_program_config(client: &RpcClient) -> Result<SquadsProgramConfig, ApiError> {
     let program_config = squads_v4::program_config_pda();
     let account = client.get_account(&program_config).map_err(|e| ApiError {
@@ -3078,33 +3101,6 @@ fn require_upgrade_buffer_authority(
     }
 }
 
-struct PumpSellRoute {
-    dex_type: DexType,
-    extension_params: DexParamEnum,
-    output_token_type: TradeTokenType,
-    create_output_token_ata: bool,
-}
-
-impl PumpSellRoute {
-    fn pumpfun(params: PumpFunParams) -> Self {
-        Self {
-            dex_type: DexType::PumpFun,
-            extension_params: DexParamEnum::PumpFun(params.with_quote_mint(WSOL_TOKEN_ACCOUNT)),
-            output_token_type: TradeTokenType::SOL,
-            create_output_token_ata: false,
-        }
-    }
-
-    fn pumpswap(params: PumpSwapParams) -> Self {
-        Self {
-            dex_type: DexType::PumpSwap,
-            extension_params: DexParamEnum::PumpSwap(params),
-            output_token_type: TradeTokenType::WSOL,
-            create_output_token_ata: true,
-        }
-    }
-}
-
 struct PumpSellExecution {
     signature: String,
     dex_type: DexType,
@@ -3129,131 +3125,21 @@ fn pump_sell_market_value(dex_type: DexType) -> &'static str {
     }
 }
 
-fn is_retryable_rpc_read_error(error: &anyhow::Error) -> bool {
-    let message = error.to_string().to_ascii_lowercase();
-    message.contains("error sending request")
-        || message.contains("timed out")
-        || message.contains("timeout")
-        || message.contains("超时")
-        || message.contains("connection rate limits exceeded")
-        || message.contains("too many requests")
-        || message.contains("429")
-        || message.contains("econnreset")
-        || message.contains("connection reset")
-        || message.contains("connection refused")
-        || message.contains("temporarily unavailable")
-}
-
-async fn pumpfun_params_from_mint_by_rpc(
+async fn resolve_pump_route(
     rpc: &SolanaRpcClient,
     mint: &Pubkey,
-) -> Result<PumpFunParams, anyhow::Error> {
-    let mut last_error = None;
-    for attempt in 1..=PUMPFUN_PARAM_RPC_ATTEMPTS {
-        let retry_error = match tokio::time::timeout(
-            Duration::from_millis(PUMPFUN_PARAM_RPC_TIMEOUT_MS),
-            PumpFunParams::from_mint_by_rpc(rpc, mint),
-        )
+    requested_dex_type: DexType,
+) -> Result<PumpRoute, ApiError> {
+    let route = pump_route::resolve_pump_route(rpc, mint, requested_dex_type)
         .await
-        {
-            Ok(Ok(params)) => return Ok(params),
-            Ok(Err(error)) if is_retryable_rpc_read_error(&error) => error,
-            Ok(Err(error)) => return Err(error),
-            Err(_) => anyhow::anyhow!(
-                "Pump.fun 参数读取超时 (timeout after {} ms)",
-                PUMPFUN_PARAM_RPC_TIMEOUT_MS
-            ),
-        };
-
-        tracing::warn!(
-            "Pump.fun param read failed for mint {} on attempt {}/{}: {}",
-            mint,
-            attempt,
-            PUMPFUN_PARAM_RPC_ATTEMPTS,
-            retry_error
+        .map_err(|message| ApiError { message })?;
+    if route.dex_type != requested_dex_type {
+        tracing::info!(
+            "Pump.fun route unavailable for mint {}; routing to PumpSwap",
+            mint
         );
-        last_error = Some(retry_error);
-        if attempt < PUMPFUN_PARAM_RPC_ATTEMPTS {
-            tokio::time::sleep(Duration::from_millis(PUMPFUN_PARAM_RETRY_DELAY_MS)).await;
-            continue;
-        }
-        break;
-    }
-
-    Err(last_error.unwrap_or_else(|| anyhow::anyhow!("Pump.fun 参数读取失败")))
-}
-
-async fn pumpswap_params_from_mint_by_rpc(
-    rpc: &SolanaRpcClient,
-    mint: &Pubkey,
-) -> Result<PumpSwapParams, anyhow::Error> {
-    let (pool_address, pool_data) = pumpswap::find_by_mint(rpc, mint).await?;
-    PumpSwapParams::from_pool_data(rpc, &pool_address, &pool_data).await
-}
-
-async fn resolve_pump_sell_route(
-    rpc: &SolanaRpcClient,
-    mint: &Pubkey,
-    requested_dex_type: DexType,
-) -> Result<PumpSellRoute, ApiError> {
-    match requested_dex_type {
-        DexType::PumpFun => match pumpfun_params_from_mint_by_rpc(rpc, mint).await {
-            Ok(params) => {
-                if !params.bonding_curve.complete {
-                    return Ok(PumpSellRoute::pumpfun(params));
-                }
-
-                match pumpswap_params_from_mint_by_rpc(rpc, mint).await {
-                    Ok(pumpswap_params) => {
-                        tracing::info!(
-                            "Pump.fun bonding curve is complete; routing sell to PumpSwap for mint {}",
-                            mint
-                        );
-                        Ok(PumpSellRoute::pumpswap(pumpswap_params))
-                    }
-                    Err(pumpswap_err) => Err(ApiError {
-                        message: format!(
-                            "该代币 Pump.fun bonding curve 已完成，但未找到可用 PumpSwap 池，无法自动外盘卖出: {}",
-                            pumpswap_err
-                        ),
-                    }),
-                }
-            }
-            Err(pumpfun_err) => match pumpswap_params_from_mint_by_rpc(rpc, mint).await {
-                Ok(pumpswap_params) => {
-                    tracing::info!(
-                        "Pump.fun params failed for mint {}; routing sell to PumpSwap: {}",
-                        mint,
-                        pumpfun_err
-                    );
-                    Ok(PumpSellRoute::pumpswap(pumpswap_params))
-                }
-                Err(pumpswap_err) => {
-                    let message = if is_retryable_rpc_read_error(&pumpfun_err) {
-                        format!(
-                            "RPC 节点暂时无法读取 Pump.fun 参数: {}；同时未找到 PumpSwap 池: {}。该代币可能仍在 Pump.fun 内盘，请稍后重试，或在网络设置中换用稳定的主网 RPC。",
-                            pumpfun_err, pumpswap_err
-                        )
-                    } else {
-                        format!(
-                            "获取 Pump.fun 参数失败: {}；同时获取 PumpSwap 参数失败: {}。请确认代币还在 Pump.fun 内盘或已经迁移到 PumpSwap 外盘。",
-                            pumpfun_err, pumpswap_err
-                        )
-                    };
-                    Err(ApiError { message })
-                }
-            },
-        },
-        DexType::PumpSwap => pumpswap_params_from_mint_by_rpc(rpc, mint)
-            .await
-            .map(PumpSellRoute::pumpswap)
-            .map_err(|e| ApiError {
-                message: format!("获取 PumpSwap 参数失败: {}", e),
-            }),
-        _ => Err(ApiError {
-            message: "不支持的交易类型".to_string(),
-        }),
     }
+    Ok(route)
 }
 
 async fn execute_pump_sell(
@@ -3283,17 +3169,8 @@ async fn execute_pump_sell(
         sell_percent_bps,
     )?;
     let use_seed = sell_source.kind.use_seed();
-    let swqos_configs = default_swqos_configs(rpc_url);
-    let init_trade_config = TradeConfig::builder(rpc_url.to_string(), swqos_configs, commitment)
-        .create_wsol_ata_on_startup(false)
-        .use_seed_optimize(use_seed)
-        .check_min_tip(false)
-        .log_enabled(false)
-        .swqos_cores_from_end(false)
-        .mev_protection(false)
-        .build();
-    let init_client = SolanaTrade::new(payer.clone(), init_trade_config).await;
-    let client = swqos_only_sell_client(&init_client, payer.clone(), use_seed)?;
+    let swqos_settings = load_sending_swqos_settings()?;
+    let client = swqos_trade_client(&swqos_settings, payer.clone(), rpc_url, use_seed).await?;
 
     let recent_blockhash = client
         .infrastructure
@@ -3303,17 +3180,11 @@ async fn execute_pump_sell(
         .map_err(|e| ApiError {
             message: format!("获取 blockhash 失败: {}", e),
         })?;
+    let tip_sol = swqos_settings.resolve_tip_sol().await;
     let gas_fee_strategy = GasFeeStrategy::new();
-    gas_fee_strategy.set_global_fee_strategy(
-        150000,
-        150000,
-        500000,
-        500000,
-        DEFAULT_SWQOS_TIP_SOL,
-        DEFAULT_SWQOS_TIP_SOL,
-    );
+    gas_fee_strategy.set_global_fee_strategy(150000, 150000, 500000, 500000, tip_sol, tip_sol);
 
-    let route = resolve_pump_sell_route(&client.infrastructure.rpc, &mint_pubkey, dex_type).await?;
+    let route = resolve_pump_route(&client.infrastructure.rpc, &mint_pubkey, dex_type).await?;
     let actual_dex_type = route.dex_type;
     tracing::info!(
         "Submitting pump sell mint={} requested_dex={} actual_dex={} raw_amount={} source_kind={} source_account={} source_balance={} decimals={} token_program={} use_seed={} slippage_bps={}",
@@ -3429,116 +3300,371 @@ async fn execute_pump_sell(
     })
 }
 
-#[derive(RustEmbed)]
-#[folder = "../../apps/desktop/out"]
-struct Assets;
+struct PumpBuyExecution {
+    signature: String,
+    dex_type: DexType,
+    input_token_type: TradeTokenType,
+    spent_lamports: u64,
+}
 
-const DEFAULT_RPC_URL: &str = "https://api.mainnet-beta.solana.com";
-const DEVNET_RPC_URL: &str = "https://api.devnet.solana.com";
-const TESTNET_RPC_URL: &str = "https://api.testnet.solana.com";
-const PUBLICNODE_MAINNET_RPC_URL: &str = "https://solana.publicnode.com";
-const PUBLICNODE_TESTNET_RPC_URL: &str = "https://solana-testnet-rpc.publicnode.com";
+fn pump_buy_input_token(value: Option<&str>) -> Result<TradeTokenType, ApiError> {
+    match value
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::to_ascii_lowercase)
+        .as_deref()
+    {
+        None | Some("sol") => Ok(TradeTokenType::SOL),
+        Some("wsol") => Ok(TradeTokenType::WSOL),
+        Some(_) => Err(ApiError {
+            message: "买入资金类型只支持 sol 或 wsol".to_string(),
+        }),
+    }
+}
 
-#[tokio::main]
-async fn main() -> anyhow::Result<()> {
-    tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "fnzero_safe_ui=info".into()),
-        )
-        .with(tracing_subscriber::fmt::layer())
-        .init();
-    let _ = secure_body_keypair();
+fn pump_buy_input_value(input_token_type: &TradeTokenType) -> &'static str {
+    match input_token_type {
+        TradeTokenType::WSOL => "wsol",
+        _ => "sol",
+    }
+}
 
-    let app = Router::new()
-        // Health
-        .route("/api/health", get(health))
-        .route("/api/health/", get(health))
-        .route("/api/secure/session", get(secure_session))
-        .route("/api/secure/session/", get(secure_session))
-        // Core Functions (1-3)
-        .route("/api/keys/create", post(create_key))
-        .route("/api/keys/create/", post(create_key))
-        .route("/api/keys/encrypt", post(encrypt_key))
-        .route("/api/keys/encrypt/", post(encrypt_key))
-        .route("/api/keys/create-encrypted", post(create_encrypted_key))
-        .route("/api/keys/create-encrypted/", post(create_encrypted_key))
-        .route("/api/keys/decrypt", post(decrypt_key))
-        .route("/api/keys/decrypt/", post(decrypt_key))
-        .route("/api/keys/create-keystore", post(create_keystore))
-        .route("/api/keys/create-keystore/", post(create_keystore))
-        .route(
-            "/api/keys/create-mnemonic-keystore",
-            post(create_mnemonic_keystore),
-        )
-        .route(
-            "/api/keys/create-mnemonic-keystore/",
-            post(create_mnemonic_keystore),
-        )
-        .route(
-            "/api/keys/import-mnemonic-keystore",
-            post(import_mnemonic_keystore),
-        )
-        .route(
-            "/api/keys/import-mnemonic-keystore/",
-            post(import_mnemonic_keystore),
-        )
-        .route("/api/keys/import-keystore", post(import_keystore))
-        .route("/api/keys/import-keystore/", post(import_keystore))
-        .route("/api/wallets", get(list_wallets).post(save_keystore_wallet))
-        .route(
-            "/api/wallets/",
-            get(list_wallets).post(save_keystore_wallet),
-        )
-        .route(
-            "/api/wallets/{wallet_id}",
-            patch(rename_wallet).delete(delete_wallet),
-        )
-        .route(
-            "/api/wallets/{wallet_id}/",
-            patch(rename_wallet).delete(delete_wallet),
-        )
-        .route("/api/wallets/{wallet_id}/delete", post(delete_wallet_post))
-        .route("/api/wallets/{wallet_id}/delete/", post(delete_wallet_post))
-        .route("/api/wallets/{wallet_id}/export", post(export_wallet))
-        .route("/api/wallets/{wallet_id}/export/", post(export_wallet))
-        .route(
-            "/api/wallets/{wallet_id}/migrate-keystore",
-            post(migrate_wallet_keystore),
-        )
-        .route(
-            "/api/wallets/{wallet_id}/migrate-keystore/",
-            post(migrate_wallet_keystore),
-        )
-        .route(
-            "/api/wallets/{wallet_id}/export-private-key",
-            post(export_wallet_private_key),
-        )
-        .route(
-            "/api/wallets/{wallet_id}/export-private-key/",
-            post(export_wallet_private_key),
-        )
-        .route(
-            "/api/wallets/{wallet_id}/export-mnemonic",
-            post(export_wallet_mnemonic),
-        )
-        .route(
-            "/api/wallets/{wallet_id}/export-mnemonic/",
-            post(export_wallet_mnemonic),
-        )
-        // Wallet Management (U, 7)
-        .route("/api/wallet/balance", post(get_balance))
-        .route("/api/wallet/balance/", post(get_balance))
-        .route("/api/wallet/assets", post(get_assets))
-        .route("/api/wallet/assets/", post(get_assets))
-        .route("/api/wallet/transactions", post(get_wallet_transactions))
-        .route("/api/wallet/transactions/", post(get_wallet_transactions))
-        .route("/api/wallet/unlock", post(unlock_wallet))
-        .route("/api/wallet/unlock/", post(unlock_wallet))
-        .route("/api/wallet/get-pubkey", post(get_pubkey))
-        .route("/api/wallet/get-pubkey/", post(get_pubkey))
-        // SOL Operations (8)
-        .route("/api/transfer/sol", post(transfer_sol))
+/// Make sure the wallet can fund the buy before a route is resolved.
+fn require_pump_buy_balance(
+    rpc_client: &RpcClient,
+    owner: &Pubkey,
+    input_token_type: &TradeTokenType,
+    amount_lamports: u64,
+) -> Result<(), ApiError> {
+    let available = if *input_token_type == TradeTokenType::WSOL {
+        let wsol_account = get_associated_token_address_with_program_id_fast_use_seed(
+            owner,
+            &WSOL_TOKEN_ACCOUNT,
+            &TOKEN_PROGRAM,
+            false,
+        );
+        token_account_balance_for_sell(rpc_client, &wsol_account)?
+            .map(|(raw_amount, _decimals)| raw_amount)
+            .unwrap_or(0)
+    } else {
+        rpc_client.get_balance(owner).map_err(|e| ApiError {
+            message: format!("查询 SOL 余额失败: {}", e),
+        })?
+    };
+    if available < amount_lamports {
+        return Err(ApiError {
+            message: format!(
+                "{} 余额不足: 可用 {}，需要 {}",
+                pump_buy_input_value(input_token_type).to_ascii_uppercase(),
+                lamports_to_sol(available),
+                lamports_to_sol(amount_lamports)
+            ),
+        });
+    }
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn execute_pump_buy(
+    keypair: Keypair,
+    mint: String,
+    rpc_url: &str,
+    amount_lamports: u64,
+    input_token_type: TradeTokenType,
+    slippage: u64,
+    create_mint_ata: bool,
+    dex_type: DexType,
+) -> Result<PumpBuyExecution, ApiError> {
+    let mint_pubkey = Pubkey::from_str(&mint).map_err(|_| ApiError {
+        message: "无效的代币地址".to_string(),
+    })?;
+    if amount_lamports == 0 {
+        return Err(ApiError {
+            message: "买入金额必须大于 0".to_string(),
+        });
+    }
+    let payer = Arc::new(keypair);
+    let commitment = CommitmentConfig::confirmed();
+    let rpc_client = RpcClient::new_with_timeout_and_commitment(
+        rpc_url.to_string(),
+        Duration::from_secs(RPC_QUERY_TIMEOUT_SECS),
+        commitment,
+    );
+    require_pump_buy_balance(
+        &rpc_client,
+        &payer.pubkey(),
+        &input_token_type,
+        amount_lamports,
+    )?;
+
+    let swqos_settings = load_sending_swqos_settings()?;
+    let client = swqos_trade_client(&swqos_settings, payer.clone(), rpc_url, false).await?;
+
+    let recent_blockhash = client
+        .infrastructure
+        .rpc
+        .get_latest_blockhash()
+        .await
+        .map_err(|e| ApiError {
+            message: format!("获取 blockhash 失败: {}", e),
+        })?;
+    let tip_sol = swqos_settings.resolve_tip_sol().await;
+    let gas_fee_strategy = GasFeeStrategy::new();
+    gas_fee_strategy.set_global_fee_strategy(150000, 150000, 500000, 500000, tip_sol, tip_sol);
+
+    // Buys follow the same bonding-curve vs. AMM routing as sells.
+    let route = resolve_pump_route(&client.infrastructure.rpc, &mint_pubkey, dex_type).await?;
+    let actual_dex_type = route.dex_type;
+    // PumpSwap quotes in WSOL: native SOL is wrapped into a temporary WSOL ATA
+    // that is closed again after the swap. Existing WSOL balances are spent as-is.
+    let wrap_native_sol =
+        actual_dex_type == DexType::PumpSwap && input_token_type == TradeTokenType::SOL;
+    tracing::info!(
+        "Submitting pump buy mint={} requested_dex={} actual_dex={} input={} amount_lamports={} create_mint_ata={} slippage_bps={}",
+        mint_pubkey,
+        pump_sell_dex_value(dex_type),
+        pump_sell_dex_value(actual_dex_type),
+        pump_buy_input_value(&input_token_type),
+        amount_lamports,
+        create_mint_ata,
+        slippage
+    );
+
+    let buy_params = TradeBuyParams {
+        dex_type: actual_dex_type,
+        input_token_type: input_token_type.clone(),
+        mint: mint_pubkey,
+        input_token_amount: amount_lamports,
+        slippage_basis_points: Some(slippage),
+        recent_blockhash: Some(recent_blockhash),
+        extension_params: route.extension_params,
+        address_lookup_table_account: None,
+        // Same as sells: return as soon as a signature is available.
+        wait_tx_confirmed: false,
+        wait_for_all_submits: false,
+        create_input_token_ata: wrap_native_sol,
+        close_input_token_ata: wrap_native_sol,
+        create_mint_ata,
+        durable_nonce: None,
+        fixed_output_token_amount: None,
+        gas_fee_strategy,
+        simulate: false,
+        use_exact_sol_amount: Some(true),
+        grpc_recv_us: None,
+    };
+
+    let buy_result = tokio::time::timeout(
+        Duration::from_secs(PUMP_BUY_SUBMIT_TIMEOUT_SECS),
+        AssertUnwindSafe(client.buy(buy_params)).catch_unwind(),
+    )
+    .await
+    .map_err(|_| {
+        tracing::warn!(
+            "Pump buy submit timeout mint={} dex={} amount_lamports={} after {}s",
+            mint_pubkey,
+            pump_sell_dex_value(actual_dex_type),
+            amount_lamports,
+            PUMP_BUY_SUBMIT_TIMEOUT_SECS
+        );
+        ApiError {
+            message: format!(
+                "买入提交超时，{} 秒内没有拿到交易签名；请先刷新交易记录或链上浏览器确认是否已提交成功",
+                PUMP_BUY_SUBMIT_TIMEOUT_SECS
+            ),
+        }
+    })?
+    .map_err(|panic_payload| {
+        let message = format!("买入执行异常: {}", panic_message(panic_payload.as_ref()));
+        tracing::error!(
+            "Pump buy panic mint={} dex={} amount_lamports={}: {}",
+            mint_pubkey,
+            pump_sell_dex_value(actual_dex_type),
+            amount_lamports,
+            message
+        );
+        ApiError { message }
+    })?;
+
+    let (success, signatures, error, _latency_info) = buy_result.map_err(|e| ApiError {
+        message: format!("买入失败: {}", e),
+    })?;
+    if !success {
+        let error_msg = error
+            .map(|e| e.to_string())
+            .unwrap_or_else(|| "Unknown error".to_string());
+        tracing::warn!(
+            "Pump buy returned unsuccessful mint={} dex={} amount_lamports={}: {}",
+            mint_pubkey,
+            pump_sell_dex_value(actual_dex_type),
+            amount_lamports,
+            error_msg
+        );
+        return Err(ApiError {
+            message: format!("买入失败: {}", error_msg),
+        });
+    }
+
+    let signature = signatures
+        .first()
+        .map(|sig| sig.to_string())
+        .ok_or_else(|| ApiError {
+            message: "买入成功但未返回交易签名".to_string(),
+        })?;
+
+    Ok(PumpBuyExecution {
+        signature,
+        dex_type: actual_dex_type,
+        input_token_type,
+        spent_lamports: amount_lamports,
+    })
+}
+
+#[derive(RustEmbed)]
+#[folder = "../../apps/desktop/out"]
+struct Assets;
+
+const DEFAULT_RPC_URL: &str = "https://api.mainnet-beta.solana.com";
+const DEVNET_RPC_URL: &str = "https://api.devnet.solana.com";
+const TESTNET_RPC_URL: &str = "https://api.testnet.solana.com";
+const PUBLICNODE_MAINNET_RPC_URL: &str = "https://solana.publicnode.com";
+const PUBLICNODE_TESTNET_RPC_URL: &str = "https://solana-testnet-rpc.publicnode.com";
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| "fnzero_safe_ui=info".into()),
+        )
+        .with(tracing_subscriber::fmt::layer())
+        .init();
+    let config = config::init().map_err(anyhow::Error::msg)?;
+    if let Some(path) = &config.config_path {
+        tracing::info!("Loaded config from {}", path.display());
+    }
+    let _ = secure_body_keypair();
+    match wallet_store::get_swqos_settings() {
+        Ok(Some(settings)) => export_swqos_settings(&settings),
+        Ok(None) => {}
+        Err(e) => tracing::warn!("Failed to load SWQoS settings: {}", e),
+    }
+
+    let app = Router::new()
+        // Health
+        .route("/api/health", get(health))
+        .route("/api/health/", get(health))
+        .route("/api/secure/session", get(secure_session))
+        .route("/api/secure/session/", get(secure_session))
+        // Core Functions (1-3)
+        .route("/api/keys/create", post(create_key))
+        .route("/api/keys/create/", post(create_key))
+        .route("/api/keys/encrypt", post(encrypt_key))
+        .route("/api/keys/encrypt/", post(encrypt_key))
+        .route("/api/keys/create-encrypted", post(create_encrypted_key))
+        .route("/api/keys/create-encrypted/", post(create_encrypted_key))
+        .route("/api/keys/decrypt", post(decrypt_key))
+        .route("/api/keys/decrypt/", post(decrypt_key))
+        .route("/api/keys/create-keystore", post(create_keystore))
+        .route("/api/keys/create-keystore/", post(create_keystore))
+        .route(
+            "/api/keys/create-mnemonic-keystore",
+            post(create_mnemonic_keystore),
+        )
+        .route(
+            "/api/keys/create-mnemonic-keystore/",
+            post(create_mnemonic_keystore),
+        )
+        .route(
+            "/api/keys/import-mnemonic-keystore",
+            post(import_mnemonic_keystore),
+        )
+        .route(
+            "/api/keys/import-mnemonic-keystore/",
+            post(import_mnemonic_keystore),
+        )
+        .route("/api/keys/import-keystore", post(import_keystore))
+        .route("/api/keys/import-keystore/", post(import_keystore))
+        .route("/api/wallets", get(list_wallets).post(save_keystore_wallet))
+        .route(
+            "/api/wallets/",
+            get(list_wallets).post(save_keystore_wallet),
+        )
+        .route(
+            "/api/wallets/{wallet_id}",
+            patch(rename_wallet).delete(delete_wallet),
+        )
+        .route(
+            "/api/wallets/{wallet_id}/",
+            patch(rename_wallet).delete(delete_wallet),
+        )
+        .route("/api/vault", get(vault_status))
+        .route("/api/vault/", get(vault_status))
+        .route("/api/vault/enable", post(enable_vault))
+        .route("/api/vault/enable/", post(enable_vault))
+        .route("/api/vault/unlock", post(unlock_vault))
+        .route("/api/vault/unlock/", post(unlock_vault))
+        .route("/api/vault/lock", post(lock_vault))
+        .route("/api/vault/lock/", post(lock_vault))
+        .route("/api/vault/rotate", post(rotate_vault))
+        .route("/api/vault/rotate/", post(rotate_vault))
+        .route("/api/wallets/{wallet_id}/delete", post(delete_wallet_post))
+        .route("/api/wallets/{wallet_id}/delete/", post(delete_wallet_post))
+        .route("/api/wallets/{wallet_id}/export", post(export_wallet))
+        .route("/api/wallets/{wallet_id}/export/", post(export_wallet))
+        .route(
+            "/api/wallets/{wallet_id}/migrate-keystore",
+            post(migrate_wallet_keystore),
+        )
+        .route(
+            "/api/wallets/{wallet_id}/migrate-keystore/",
+            post(migrate_wallet_keystore),
+        )
+        .route(
+            "/api/wallets/{wallet_id}/export-private-key",
+            post(export_wallet_private_key),
+        )
+        .route(
+            "/api/wallets/{wallet_id}/export-private-key/",
+            post(export_wallet_private_key),
+        )
+        .route(
+            "/api/wallets/{wallet_id}/export-mnemonic",
+            post(export_wallet_mnemonic),
+        )
+        .route(
+            "/api/wallets/{wallet_id}/export-mnemonic/",
+            post(export_wallet_mnemonic),
+        )
+        // Wallet Management (U, 7)
+        .route("/api/wallet/balance", post(get_balance))
+        .route("/api/wallet/balance/", post(get_balance))
+        .route("/api/wallet/assets", post(get_assets))
+        .route("/api/wallet/assets/", post(get_assets))
+        .route("/api/wallet/transactions", post(get_wallet_transactions))
+        .route("/api/wallet/transactions/", post(get_wallet_transactions))
+        .route(
+            "/api/wallet/transactions/sync",
+            post(sync_wallet_transactions),
+        )
+        .route(
+            "/api/wallet/transactions/sync/",
+            post(sync_wallet_transactions),
+        )
+        .route("/api/wallet/unlock", post(unlock_wallet))
+        .route("/api/wallet/unlock/", post(unlock_wallet))
+        .route("/api/wallet/get-pubkey", post(get_pubkey))
+        .route("/api/wallet/get-pubkey/", post(get_pubkey))
+        // Address Book
+        .route("/api/address-book/list", post(address_book_list))
+        .route("/api/address-book/list/", post(address_book_list))
+        .route("/api/address-book/save", post(address_book_save))
+        .route("/api/address-book/save/", post(address_book_save))
+        .route("/api/address-book/delete", post(address_book_delete))
+        .route("/api/address-book/delete/", post(address_book_delete))
+        .route("/api/address-book/check", post(address_book_check))
+        .route("/api/address-book/check/", post(address_book_check))
+        // SOL Operations (8)
+        .route("/api/transfer/sol", post(transfer_sol))
         .route("/api/transfer/sol/", post(transfer_sol))
         // WSOL Operations (9-12)
         .route("/api/wsol/create-ata", post(create_wsol_ata))
@@ -3549,31 +3675,83 @@ async fn main() -> anyhow::Result<()> {
         .route("/api/wsol/unwrap/", post(unwrap_sol))
         .route("/api/wsol/close-ata", post(close_wsol_ata))
         .route("/api/wsol/close-ata/", post(close_wsol_ata))
+        .route("/api/token-accounts/empty", post(empty_token_accounts))
+        .route("/api/token-accounts/empty/", post(empty_token_accounts))
+        .route(
+            "/api/token-accounts/close-empty",
+            post(close_empty_token_accounts),
+        )
+        .route(
+            "/api/token-accounts/close-empty/",
+            post(close_empty_token_accounts),
+        )
+        // Batch Payouts
+        .route("/api/payouts/preview", post(payouts_preview))
+        .route("/api/payouts/preview/", post(payouts_preview))
+        .route("/api/payouts/execute", post(payouts_execute))
+        .route("/api/payouts/execute/", post(payouts_execute))
+        .route("/api/payouts/status", post(payouts_status))
+        .route("/api/payouts/status/", post(payouts_status))
+        // Staking
+        .route("/api/stake/list", post(stake_list))
+        .route("/api/stake/list/", post(stake_list))
+        .route("/api/stake/action", post(stake_action))
+        .route("/api/stake/action/", post(stake_action))
         // 2FA Operations (4-6)
         .route("/api/2fa/setup", post(setup_2fa))
+        .route("/api/2fa/security-questions", get(security_questions))
+        .route("/api/2fa/security-questions/", get(security_questions))
         .route("/api/2fa/setup/", post(setup_2fa))
         .route("/api/2fa/create-tfa", post(create_triple_factor_wallet))
         .route("/api/2fa/create-tfa/", post(create_triple_factor_wallet))
         .route("/api/2fa/unlock-tfa", post(unlock_triple_factor_wallet))
         .route("/api/2fa/unlock-tfa/", post(unlock_triple_factor_wallet))
+        .route("/api/2fa/rebind-tfa", post(rebind_triple_factor_wallet))
+        .route("/api/2fa/rebind-tfa/", post(rebind_triple_factor_wallet))
+        .route("/api/2fa/backup-codes", post(triple_factor_backup_codes))
+        .route("/api/2fa/backup-codes/", post(triple_factor_backup_codes))
+        .route(
+            "/api/2fa/backup-codes/regenerate",
+            post(regenerate_triple_factor_backup_codes),
+        )
+        .route(
+            "/api/2fa/backup-codes/regenerate/",
+            post(regenerate_triple_factor_backup_codes),
+        )
         // Pump.fun Operations (15-18)
         .route("/api/pumpfun/sell", post(pumpfun_sell))
         .route("/api/pumpfun/sell/", post(pumpfun_sell))
+        .route("/api/pumpfun/buy", post(pumpfun_buy))
+        .route("/api/pumpfun/buy/", post(pumpfun_buy))
         .route("/api/pumpfun/cashback-info", post(pumpfun_cashback_info))
         .route("/api/pumpfun/cashback-info/", post(pumpfun_cashback_info))
         .route("/api/pumpfun/cashback", post(pumpfun_cashback))
         .route("/api/pumpfun/cashback/", post(pumpfun_cashback))
         .route("/api/pumpswap/sell", post(pumpswap_sell))
         .route("/api/pumpswap/sell/", post(pumpswap_sell))
+        .route("/api/pumpswap/buy", post(pumpswap_buy))
+        .route("/api/pumpswap/buy/", post(pumpswap_buy))
         .route("/api/pumpswap/cashback-info", post(pumpswap_cashback_info))
         .route("/api/pumpswap/cashback-info/", post(pumpswap_cashback_info))
         .route("/api/pumpswap/cashback", post(pumpswap_cashback))
         .route("/api/pumpswap/cashback/", post(pumpswap_cashback))
+        .route("/api/pump/batch-sell", post(pump_batch_sell))
+        .route("/api/pump/batch-sell/", post(pump_batch_sell))
+        .route(
+            "/api/settings/swqos",
+            get(get_swqos_settings).post(save_swqos_settings),
+        )
+        .route(
+            "/api/settings/swqos/",
+            get(get_swqos_settings).post(save_swqos_settings),
+        )
         // Token Operations (13)
         .route("/api/token/mint-info", post(token_mint_info))
         .route("/api/token/mint-info/", post(token_mint_info))
         .route("/api/transfer/token", post(transfer_token))
         .route("/api/transfer/token/", post(transfer_token))
+        .route("/api/transfer/token/preview", post(transfer_token_preview))
+        .route("/api/transfer/token/preview/", post(transfer_token_preview))
         // Nonce Operations (14)
         .route("/api/nonce/create", post(create_nonce_account))
         .route("/api/nonce/create/", post(create_nonce_account))
@@ -3592,6 +3770,8 @@ async fn main() -> anyhow::Result<()> {
             "/api/program/upgrade/progress/",
             get(program_upgrade_progress),
         )
+        .route("/api/program/verify", post(program_verify))
+        .route("/api/program/verify/", post(program_verify))
         .route("/api/program/deploy-source", post(program_deploy_source))
         .route("/api/program/deploy-source/", post(program_deploy_source))
         .route(
@@ -3646,9 +3826,42 @@ async fn main() -> anyhow::Result<()> {
         .route("/api/external-sign/message/", post(external_sign_message))
         .route("/api/external-sign/submit", post(external_sign_submit))
         .route("/api/external-sign/submit/", post(external_sign_submit))
-        .route("/api/program/info", post(program_info))
-        .route("/api/program/info/", post(program_info))
-        .route("/api/program/derive-address", post(program_derive_address))
+        .route("/api/remote-sign/pairings", get(remote_sign_pairings))
+        .route("/api/remote-sign/pairings/", get(remote_sign_pairings))
+        .route(
+            "/api/remote-sign/pairings/create",
+            post(remote_sign_pairing_create),
+        )
+        .route(
+            "/api/remote-sign/pairings/create/",
+            post(remote_sign_pairing_create),
+        )
+        .route(
+            "/api/remote-sign/pairings/delete",
+            post(remote_sign_pairing_delete),
+        )
+        .route(
+            "/api/remote-sign/pairings/delete/",
+            post(remote_sign_pairing_delete),
+        )
+        .route("/api/remote-sign/requests", post(remote_sign_requests))
+        .route("/api/remote-sign/requests/", post(remote_sign_requests))
+        .route(
+            "/api/remote-sign/requests/approve",
+            post(remote_sign_approve),
+        )
+        .route(
+            "/api/remote-sign/requests/approve/",
+            post(remote_sign_approve),
+        )
+        .route("/api/remote-sign/requests/reject", post(remote_sign_reject))
+        .route(
+            "/api/remote-sign/requests/reject/",
+            post(remote_sign_reject),
+        )
+        .route("/api/program/info", post(program_info))
+        .route("/api/program/info/", post(program_info))
+        .route("/api/program/derive-address", post(program_derive_address))
         .route("/api/program/derive-address/", post(program_derive_address))
         // Squads v4 multisig
         .route("/api/squads/create", post(squads_create))
@@ -3697,6 +3910,22 @@ async fn main() -> anyhow::Result<()> {
             "/api/squads/proposal/approve/",
             post(squads_proposal_approve),
         )
+        .route(
+            "/api/squads/proposal/instructions",
+            post(squads_instructions_proposal),
+        )
+        .route(
+            "/api/squads/proposal/instructions/",
+            post(squads_instructions_proposal),
+        )
+        .route("/api/squads/batch/create", post(squads_batch_create))
+        .route("/api/squads/batch/create/", post(squads_batch_create))
+        .route("/api/squads/batch/execute", post(squads_batch_execute))
+        .route("/api/squads/batch/execute/", post(squads_batch_execute))
+        .route("/api/squads/payouts", post(squads_payouts))
+        .route("/api/squads/payouts/", post(squads_payouts))
+        .route("/api/squads/stake", post(squads_stake_proposal))
+        .route("/api/squads/stake/", post(squads_stake_proposal))
         .route("/api/squads/proposal/reject", post(squads_proposal_reject))
         .route("/api/squads/proposal/reject/", post(squads_proposal_reject))
         .route(
@@ -3707,6 +3936,26 @@ async fn main() -> anyhow::Result<()> {
             "/api/squads/proposal/execute/",
             post(squads_proposal_execute),
         )
+        .route("/api/squads/proposal/cancel", post(squads_proposal_cancel))
+        .route("/api/squads/proposal/cancel/", post(squads_proposal_cancel))
+        .route(
+            "/api/squads/proposal/activate",
+            post(squads_proposal_activate),
+        )
+        .route(
+            "/api/squads/proposal/activate/",
+            post(squads_proposal_activate),
+        )
+        .route(
+            "/api/squads/transaction/close",
+            post(squads_transaction_close),
+        )
+        .route(
+            "/api/squads/transaction/close/",
+            post(squads_transaction_close),
+        )
+        .route("/api/squads/cleanup", post(squads_cleanup))
+        .route("/api/squads/cleanup/", post(squads_cleanup))
         .route(
             "/api/squads/program/set-authority",
             post(squads_set_program_authority),
@@ -3723,21 +3972,16 @@ async fn main() -> anyhow::Result<()> {
         .layer(middleware::from_fn(catch_panics))
         .fallback(serve_assets);
 
-    const DEFAULT_PORT: u16 = 3841;
-    let port: u16 = std::env::var("PORT")
-        .ok()
-        .and_then(|s| s.parse().ok())
-        .unwrap_or(DEFAULT_PORT);
-    let host = std::env::var("HOST").unwrap_or_else(|_| "127.0.0.1".to_string());
-    let host: IpAddr = host.parse()?;
+    let host: IpAddr = config.host.parse()?;
     if !host.is_loopback() {
         anyhow::bail!(
             "FnzeroSafe API contains local-only wallet operations and must bind to a loopback address"
         );
     }
-    let addr = SocketAddr::new(host, port);
+    let addr = SocketAddr::new(host, config.port);
     let listener = tokio::net::TcpListener::bind(addr).await?;
     tracing::info!("Server listening on http://{}", addr);
+    remote_sign::spawn_poller();
     axum::serve(listener, app).await?;
     Ok(())
 }
@@ -4227,6 +4471,107 @@ async fn save_keystore_wallet(
     Ok(Json(response))
 }
 
+#[derive(Deserialize)]
+struct EnableVaultRequest {
+    source: vault::VaultKeySource,
+    #[serde(default)]
+    passphrase: Option<String>,
+}
+
+impl Drop for EnableVaultRequest {
+    fn drop(&mut self) {
+        self.passphrase.zeroize();
+    }
+}
+
+#[derive(Deserialize)]
+struct UnlockVaultRequest {
+    #[serde(default)]
+    passphrase: Option<String>,
+}
+
+impl Drop for UnlockVaultRequest {
+    fn drop(&mut self) {
+        self.passphrase.zeroize();
+    }
+}
+
+#[derive(Deserialize)]
+struct RotateVaultRequest {
+    #[serde(default)]
+    current_passphrase: Option<String>,
+    source: vault::VaultKeySource,
+    #[serde(default)]
+    new_passphrase: Option<String>,
+}
+
+impl Drop for RotateVaultRequest {
+    fn drop(&mut self) {
+        self.current_passphrase.zeroize();
+        self.new_passphrase.zeroize();
+    }
+}
+
+fn validate_vault_passphrase(passphrase: Option<&str>, field: &str) -> Result<(), ApiError> {
+    if let Some(passphrase) = passphrase {
+        validate_text_len(passphrase, field, MAX_TEXT_FIELD_CHARS)?;
+    }
+    Ok(())
+}
+
+async fn vault_status() -> Result<Json<wallet_store::VaultStatus>, ApiError> {
+    let status = wallet_store::vault_status().map_err(|message| ApiError { message })?;
+    Ok(Json(status))
+}
+
+// Vault calls run on the blocking pool: PBKDF2 is slow and the keyring backends
+// block on their own runtime.
+async fn enable_vault(
+    Json(req): Json<EnableVaultRequest>,
+) -> Result<Json<wallet_store::VaultStatus>, ApiError> {
+    validate_vault_passphrase(req.passphrase.as_deref(), "保险库口令")?;
+    let status = run_keystore_task(move || {
+        wallet_store::enable_vault(req.source, req.passphrase.as_deref())
+            .map_err(|message| ApiError { message })
+    })
+    .await?;
+    Ok(Json(status))
+}
+
+async fn unlock_vault(
+    Json(req): Json<UnlockVaultRequest>,
+) -> Result<Json<wallet_store::VaultStatus>, ApiError> {
+    validate_vault_passphrase(req.passphrase.as_deref(), "保险库口令")?;
+    let status = run_keystore_task(move || {
+        wallet_store::unlock_vault(req.passphrase.as_deref())
+            .map_err(|message| ApiError { message })
+    })
+    .await?;
+    Ok(Json(status))
+}
+
+async fn lock_vault() -> Result<Json<wallet_store::VaultStatus>, ApiError> {
+    let status = wallet_store::lock_vault().map_err(|message| ApiError { message })?;
+    Ok(Json(status))
+}
+
+async fn rotate_vault(
+    Json(req): Json<RotateVaultRequest>,
+) -> Result<Json<wallet_store::VaultStatus>, ApiError> {
+    validate_vault_passphrase(req.current_passphrase.as_deref(), "当前保险库口令")?;
+    validate_vault_passphrase(req.new_passphrase.as_deref(), "新保险库口令")?;
+    let status = run_keystore_task(move || {
+        wallet_store::rotate_vault(
+            req.current_passphrase.as_deref(),
+            req.source,
+            req.new_passphrase.as_deref(),
+        )
+        .map_err(|message| ApiError { message })
+    })
+    .await?;
+    Ok(Json(status))
+}
+
 async fn rename_wallet(
     Path(wallet_id): Path<String>,
     Json(req): Json<RenameWalletRequest>,
@@ -4474,6 +4819,14 @@ struct WalletTokenAsset {
     symbol: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     logo_uri: Option<String>,
+    /// UI amount including accrued interest (Token-2022 interest-bearing mints)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    interest_bearing_amount: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    interest_rate_bps: Option<i16>,
+    /// Token-2022 extensions that affect transfers or display
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    extensions: Vec<&'static str>,
 }
 
 #[derive(Serialize)]
@@ -4545,7 +4898,32 @@ fn wallet_token_asset_from_record(record: WalletTokenAssetRecord) -> WalletToken
         name: metadata.name,
         symbol: metadata.symbol,
         logo_uri: metadata.logo_uri,
+        interest_bearing_amount: None,
+        interest_rate_bps: None,
+        extensions: Vec::new(),
+    }
+}
+
+/// Token-2022 mint extensions for the given mints; mints that cannot be read are omitted
+fn token_2022_mint_infos(client: &RpcClient, mints: &[Pubkey]) -> HashMap<String, MintInfo> {
+    let mut infos = HashMap::new();
+    for chunk in mints.chunks(100) {
+        let accounts = match client.get_multiple_accounts(chunk) {
+            Ok(accounts) => accounts,
+            Err(e) => {
+                tracing::warn!("Token-2022 mint extension read failed: {}", e);
+                continue;
+            }
+        };
+        for (mint, account) in chunk.iter().zip(accounts) {
+            if let Some(info) = account
+                .and_then(|account| token_extensions::parse_mint(account.owner, &account.data).ok())
+            {
+                infos.insert(mint.to_string(), info);
+            }
+        }
     }
+    infos
 }
 
 fn clean_cached_metadata_string(value: Option<String>) -> Option<String> {
@@ -4590,11 +4968,17 @@ async fn read_fresh_wallet_assets(
     let mut raw_tokens = Vec::new();
     let mut mints = Vec::new();
     let mut seen_mints = HashMap::new();
-    for (account, token_account) in all_owner_token_accounts(client, pubkey)? {
+    let mut token_2022_mints = Vec::new();
+    for (account, token_program, token_account) in all_owner_token_accounts(client, pubkey)? {
         let token_amount = token_account.token_amount;
         if token_amount.amount == "0" {
             continue;
         }
+        if token_program == token_extensions::TOKEN_2022_PROGRAM_ID {
+            if let Ok(mint) = Pubkey::from_str(&token_account.mint) {
+                token_2022_mints.push(mint);
+            }
+        }
         let ui_amount_string = token_amount.real_number_string_trimmed();
         if !seen_mints.contains_key(&token_account.mint) {
             seen_mints.insert(token_account.mint.clone(), true);
@@ -4611,6 +4995,10 @@ async fn read_fresh_wallet_assets(
     let mut metadata_by_mint = cached_metadata_by_mint.clone();
     let missing_mints = merge_cached_token_metadata(network, &mut metadata_by_mint, &mints);
     load_missing_token_metadata(client, network, &mut metadata_by_mint, &missing_mints).await;
+    token_2022_mints.sort();
+    token_2022_mints.dedup();
+    let mint_infos = token_2022_mint_infos(client, &token_2022_mints);
+    let now_secs = now_unix_secs_lossy() as i64;
     let tokens: Vec<WalletTokenAsset> = raw_tokens
         .into_iter()
         .map(|(account, mint, amount, ui_amount_string, decimals)| {
@@ -4619,6 +5007,14 @@ async fn read_fresh_wallet_assets(
                 metadata.merge_missing(cached_metadata.clone());
             }
             let metadata = normalize_token_metadata(&mint, metadata);
+            let mint_info = mint_infos.get(&mint);
+            let interest = mint_info.and_then(|info| info.interest_bearing);
+            let interest_bearing_amount = interest.and_then(|interest| {
+                amount
+                    .parse::<u64>()
+                    .ok()
+                    .map(|raw| interest.ui_amount_string(raw, decimals, now_secs))
+            });
             WalletTokenAsset {
                 account,
                 mint,
@@ -4628,6 +5024,11 @@ async fn read_fresh_wallet_assets(
                 name: metadata.name,
                 symbol: metadata.symbol,
                 logo_uri: metadata.logo_uri,
+                interest_bearing_amount,
+                interest_rate_bps: interest.map(|interest| interest.current_rate),
+                extensions: mint_info
+                    .map(MintInfo::extension_labels)
+                    .unwrap_or_default(),
             }
         })
         .collect();
@@ -4760,33 +5161,22 @@ struct WalletTransactionsRequest {
     limit: Option<usize>,
     #[serde(default)]
     before: Option<String>,
-}
-
-#[derive(Serialize)]
-struct WalletTransactionChange {
-    asset: String,
+    /// Token mint, or `SOL` for native SOL changes
+    #[serde(default)]
     mint: Option<String>,
-    amount: String,
-    ui_amount: String,
-    direction: String,
-    decimals: u8,
-}
-
-#[derive(Serialize)]
-struct WalletTransactionRecord {
-    signature: String,
-    slot: u64,
-    block_time: Option<i64>,
-    confirmation_status: Option<String>,
-    err: Option<Value>,
-    memo: Option<String>,
-    action: String,
-    summary: String,
+    #[serde(default)]
     counterparty: Option<String>,
-    programs: Vec<String>,
-    changes: Vec<WalletTransactionChange>,
+    /// `send`, `receive`, `swap`, `contract` or `transaction`
+    #[serde(default)]
+    action: Option<String>,
+    /// Substring of the signature, counterparty, memo, programs or assets
+    #[serde(default)]
+    search: Option<String>,
 }
 
+/// Balance changes, counterparty and programs parsed from `getTransaction`
+type WalletTransactionDetails = (Vec<WalletTransactionChange>, Option<String>, Vec<String>);
+
 fn ui_amount_from_raw_amount(amount: u128, decimals: u8) -> String {
     let raw = amount.to_string();
     if decimals == 0 {
@@ -4992,7 +5382,7 @@ fn parse_wallet_transaction_details(
     signature: &Signature,
     owner: &Pubkey,
     token_metadata: &HashMap<String, TokenMetadata>,
-) -> (Vec<WalletTransactionChange>, Option<String>, Vec<String>) {
+) -> Option<WalletTransactionDetails> {
     let tx = match client.get_transaction_with_config(
         signature,
         RpcTransactionConfig {
@@ -5002,12 +5392,12 @@ fn parse_wallet_transaction_details(
         },
     ) {
         Ok(tx) => tx,
-        Err(_) => return (Vec::new(), None, Vec::new()),
+        Err(_) => return None,
     };
     let (accounts, programs) = parsed_message_accounts_and_programs(&tx.transaction.transaction);
     let counterparty = transaction_counterparty(&accounts, &owner.to_string());
     let Some(meta) = tx.transaction.meta else {
-        return (Vec::new(), counterparty, programs);
+        return Some((Vec::new(), counterparty, programs));
     };
 
     let mut changes = Vec::new();
@@ -5067,7 +5457,7 @@ fn parse_wallet_transaction_details(
         add_change(&mut changes, &asset, Some(mint.clone()), delta, decimals);
     }
 
-    (changes, counterparty, programs)
+    Some((changes, counterparty, programs))
 }
 
 fn parse_wallet_transaction_details_with_timeout(
@@ -5075,68 +5465,117 @@ fn parse_wallet_transaction_details_with_timeout(
     signature: Signature,
     owner: Pubkey,
     token_metadata: HashMap<String, TokenMetadata>,
-) -> (Vec<WalletTransactionChange>, Option<String>, Vec<String>) {
+) -> Option<WalletTransactionDetails> {
     let Ok((client, _)) =
         rpc_query_client_for_timeout(network, RPC_TRANSACTION_DETAIL_TIMEOUT_SECS)
     else {
-        return (Vec::new(), None, Vec::new());
+        return None;
     };
     parse_wallet_transaction_details(&client, &signature, &owner, &token_metadata)
 }
 
-#[derive(Serialize)]
-struct WalletTransactionsResponse {
-    address: String,
-    network: String,
-    limit: usize,
-    before: Option<String>,
-    next_before: Option<String>,
-    has_more: bool,
-    transactions: Vec<WalletTransactionRecord>,
+/// Signature entry without details; `load_wallet_transaction_details` fills in the rest
+fn wallet_transaction_from_signature(
+    item: RpcConfirmedTransactionStatusWithSignature,
+) -> WalletTransactionRecord {
+    WalletTransactionRecord {
+        signature: item.signature,
+        slot: item.slot,
+        block_time: item.block_time,
+        confirmation_status: item
+            .confirmation_status
+            .map(|status| format!("{:?}", status)),
+        err: item.err.map(|err| json!(err)),
+        memo: item.memo,
+        action: "transaction".to_string(),
+        summary: "Transaction".to_string(),
+        counterparty: None,
+        programs: Vec::new(),
+        changes: Vec::new(),
+        details_loaded: false,
+    }
+}
+
+async fn load_wallet_transaction_details(
+    client: &RpcClient,
+    request_network: Option<&str>,
+    network: &str,
+    owner: &Pubkey,
+    records: &mut [WalletTransactionRecord],
+) {
+    let cached_token_metadata = wallet_store::get_wallet_assets(&owner.to_string(), network)
+        .ok()
+        .flatten()
+        .map(|cached| cached_token_metadata_by_mint(&cached.tokens))
+        .unwrap_or_default();
+    let pending_details = records
+        .iter()
+        .map(|record| {
+            Signature::from_str(&record.signature).ok().map(|sig| {
+                let owner = *owner;
+                let network = request_network.map(ToOwned::to_owned);
+                let token_metadata = cached_token_metadata.clone();
+                tokio::task::spawn_blocking(move || {
+                    parse_wallet_transaction_details_with_timeout(
+                        network.as_deref(),
+                        sig,
+                        owner,
+                        token_metadata,
+                    )
+                })
+            })
+        })
+        .collect::<Vec<_>>();
+    let details = join_all(pending_details.into_iter().map(|details| async move {
+        match details {
+            Some(details) => tokio::time::timeout(
+                Duration::from_secs(RPC_TRANSACTION_DETAIL_TIMEOUT_SECS + 1),
+                details,
+            )
+            .await
+            .ok()
+            .and_then(Result::ok)
+            .flatten(),
+            None => None,
+        }
+    }))
+    .await;
+
+    for (record, details) in records.iter_mut().zip(details) {
+        if let Some((changes, counterparty, programs)) = details {
+            record.changes = changes;
+            record.counterparty = counterparty;
+            record.programs = programs;
+            record.details_loaded = true;
+        }
+        let (action, summary) = summarize_transaction(&record.changes, &record.programs);
+        record.action = action;
+        record.summary = summary;
+    }
+    enrich_transaction_change_assets(client, network, records).await;
 }
 
-async fn get_wallet_transactions(
-    Json(req): Json<WalletTransactionsRequest>,
-) -> Result<Json<WalletTransactionsResponse>, ApiError> {
-    let pubkey = Pubkey::from_str(&req.address).map_err(|_| ApiError {
-        message: "无效的地址".to_string(),
-    })?;
-    let limit = req
-        .limit
-        .unwrap_or(20)
-        .clamp(1, MAX_WALLET_TRANSACTION_HISTORY);
-    let before = req
-        .before
-        .as_deref()
-        .map(str::trim)
-        .filter(|value| !value.is_empty())
-        .map(ToOwned::to_owned);
-    let before_signature = before
-        .as_deref()
-        .map(Signature::from_str)
-        .transpose()
-        .map_err(|_| ApiError {
-            message: "无效的分页交易签名".to_string(),
-        })?;
-    let selector = rpc_selector(req.network.as_deref())?;
-    let network = selector.network.clone();
+/// One newest-first page of signatures, trying the fallback RPC nodes in turn
+fn fetch_wallet_signatures(
+    selector: &RpcSelector,
+    owner: &Pubkey,
+    before: Option<Signature>,
+    until: Option<Signature>,
+    limit: usize,
+) -> Result<(Vec<RpcConfirmedTransactionStatusWithSignature>, RpcClient), ApiError> {
     let mut errors = Vec::new();
-    let mut signatures_with_client = None;
-    for rpc_url in fallback_rpc_urls(&selector) {
+    for rpc_url in fallback_rpc_urls(selector) {
         let client = rpc_query_client_for_url(rpc_url.clone(), RPC_QUERY_TIMEOUT_SECS);
         match client.get_signatures_for_address_with_config(
-            &pubkey,
+            owner,
             GetConfirmedSignaturesForAddress2Config {
-                before: before_signature,
-                until: None,
+                before,
+                until,
                 limit: Some(limit),
                 commitment: Some(CommitmentConfig::confirmed()),
             },
         ) {
-            Ok(signatures) => {
-                signatures_with_client = Some((signatures, client));
-                break;
-            }
+            Ok(signatures) => return Ok((signatures, client)),
             Err(error) => {
                 let message = format!("查询交易记录失败: {}", error);
                 if !is_retryable_rpc_message(&message) {
@@ -5151,91 +5590,253 @@ async fn get_wallet_transactions(
             }
         }
     }
-    let Some((signatures, client)) = signatures_with_client else {
-        return Err(ApiError {
-            message: format!(
-                "所有 RPC 节点均无法查询交易记录: {}",
-                rpc_failure_summary(&errors)
-            ),
-        });
-    };
+    Err(ApiError {
+        message: format!(
+            "所有 RPC 节点均无法查询交易记录: {}",
+            rpc_failure_summary(&errors)
+        ),
+    })
+}
 
-    let has_more = signatures.len() >= limit;
-    let request_network = req.network.clone();
-    let cached_token_metadata = wallet_store::get_wallet_assets(&req.address, &network)
-        .ok()
-        .flatten()
-        .map(|cached| cached_token_metadata_by_mint(&cached.tokens))
-        .unwrap_or_default();
-    let pending_transactions = signatures
-        .into_iter()
-        .take(MAX_WALLET_TRANSACTION_HISTORY)
-        .map(|item| {
-            let details = Signature::from_str(&item.signature).ok().map(|sig| {
-                let owner = pubkey;
-                let network = request_network.clone();
-                let token_metadata = cached_token_metadata.clone();
-                tokio::task::spawn_blocking(move || {
-                    parse_wallet_transaction_details_with_timeout(
-                        network.as_deref(),
-                        sig,
-                        owner,
-                        token_metadata,
-                    )
-                })
-            });
-            (item, details)
+fn parse_history_cursor(signature: Option<&str>) -> Result<Option<Signature>, ApiError> {
+    signature
+        .map(Signature::from_str)
+        .transpose()
+        .map_err(|_| ApiError {
+            message: "本地交易索引中的签名无效".to_string(),
         })
-        .collect::<Vec<_>>();
+}
 
-    let mut transactions = join_all(pending_transactions.into_iter().map(
-        |(item, details)| async move {
-            let signature = item.signature.clone();
-            let (changes, counterparty, programs) = match details {
-                Some(details) => tokio::time::timeout(
-                    Duration::from_secs(RPC_TRANSACTION_DETAIL_TIMEOUT_SECS + 1),
-                    details,
-                )
-                .await
-                .ok()
-                .and_then(Result::ok)
-                .unwrap_or_else(|| (Vec::new(), None, Vec::new())),
-                None => (Vec::new(), None, Vec::new()),
-            };
-            let (action, summary) = summarize_transaction(&changes, &programs);
-            WalletTransactionRecord {
-                signature,
-                slot: item.slot,
-                block_time: item.block_time,
-                confirmation_status: item
-                    .confirmation_status
-                    .map(|status| format!("{:?}", status)),
-                err: item.err.map(|err| json!(err)),
-                memo: item.memo,
-                action,
-                summary,
-                counterparty,
-                programs,
-                changes,
+/// Bring the local index up to date: signatures newer than the newest indexed one, a retry of
+/// missing details and, when `backfill` is set, one batch of older history.
+async fn sync_wallet_history(
+    request_network: Option<&str>,
+    owner: &Pubkey,
+    backfill: bool,
+) -> Result<wallet_store::WalletHistoryState, ApiError> {
+    let _sync_guard = WALLET_HISTORY_SYNC_LOCK
+        .get_or_init(|| tokio::sync::Mutex::new(()))
+        .lock()
+        .await;
+    let selector = rpc_selector(request_network)?;
+    let network = selector.network.clone();
+    let owner_key = owner.to_string();
+    let mut state = wallet_store::wallet_history_state(&owner_key, &network)
+        .map_err(|message| ApiError { message })?;
+
+    if let Some(newest) = parse_history_cursor(state.newest_signature.as_deref())? {
+        let mut pending = Vec::new();
+        let mut before = None;
+        let client = loop {
+            let (page, client) = fetch_wallet_signatures(
+                &selector,
+                owner,
+                before,
+                Some(newest),
+                WALLET_HISTORY_SIGNATURE_PAGE,
+            )?;
+            let full_page = page.len() >= WALLET_HISTORY_SIGNATURE_PAGE;
+            before = page
+                .last()
+                .and_then(|item| Signature::from_str(&item.signature).ok());
+            pending.extend(page);
+            if !full_page || before.is_none() {
+                break client;
             }
-        },
-    ))
-    .await;
-    enrich_transaction_change_assets(&client, &network, &mut transactions).await;
-    let next_before = transactions.last().map(|item| item.signature.clone());
+        };
+        // Index the batch next to the newest known signature so the indexed range stays
+        // contiguous; the next sync continues towards the tip.
+        let batch = pending.split_off(pending.len().saturating_sub(WALLET_HISTORY_SYNC_BATCH));
+        if let Some(first) = batch.first() {
+            state.newest_signature = Some(first.signature.clone());
+            let mut records = batch
+                .into_iter()
+                .map(wallet_transaction_from_signature)
+                .collect::<Vec<_>>();
+            load_wallet_transaction_details(
+                &client,
+                request_network,
+                &network,
+                owner,
+                &mut records,
+            )
+            .await;
+            wallet_store::save_wallet_transactions(&owner_key, &network, &records, &state)
+                .map_err(|message| ApiError { message })?;
+        }
+    }
 
-    Ok(Json(WalletTransactionsResponse {
-        address: req.address,
-        network,
-        limit,
-        before,
-        next_before,
-        has_more,
-        transactions,
-    }))
+    if state.newest_signature.is_none() || (backfill && !state.backfill_complete) {
+        let before = parse_history_cursor(state.oldest_signature.as_deref())?;
+        let (page, client) =
+            fetch_wallet_signatures(&selector, owner, before, None, WALLET_HISTORY_SYNC_BATCH)?;
+        state.backfill_complete = page.len() < WALLET_HISTORY_SYNC_BATCH;
+        if state.newest_signature.is_none() {
+            state.newest_signature = page.first().map(|item| item.signature.clone());
+        }
+        if let Some(last) = page.last() {
+            state.oldest_signature = Some(last.signature.clone());
+        }
+        let mut records = page
+            .into_iter()
+            .map(wallet_transaction_from_signature)
+            .collect::<Vec<_>>();
+        load_wallet_transaction_details(&client, request_network, &network, owner, &mut records)
+            .await;
+        wallet_store::save_wallet_transactions(&owner_key, &network, &records, &state)
+            .map_err(|message| ApiError { message })?;
+    }
+
+    let mut retry = wallet_store::undetailed_wallet_transactions(
+        &owner_key,
+        &network,
+        WALLET_HISTORY_DETAIL_RETRIES,
+    )
+    .map_err(|message| ApiError { message })?;
+    if !retry.is_empty() {
+        let client = rpc_query_client_for_url(selector.url.clone(), RPC_QUERY_TIMEOUT_SECS);
+        load_wallet_transaction_details(&client, request_network, &network, owner, &mut retry)
+            .await;
+        retry.retain(|record| record.details_loaded);
+        wallet_store::save_wallet_transactions(&owner_key, &network, &retry, &state)
+            .map_err(|message| ApiError { message })?;
+    }
+
+    wallet_store::wallet_history_state(&owner_key, &network).map_err(|message| ApiError { message })
 }
 
-// Get Public Key from Secret Key
+fn non_empty_filter(value: Option<String>) -> Option<String> {
+    value
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+#[derive(Serialize)]
+struct WalletTransactionsResponse {
+    address: String,
+    network: String,
+    limit: usize,
+    before: Option<String>,
+    next_before: Option<String>,
+    has_more: bool,
+    transactions: Vec<WalletTransactionRecord>,
+    /// Local index progress after this request
+    index: wallet_store::WalletHistoryState,
+    /// Set when the RPC could not be reached and results come from the local index only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sync_error: Option<String>,
+}
+
+async fn get_wallet_transactions(
+    Json(req): Json<WalletTransactionsRequest>,
+) -> Result<Json<WalletTransactionsResponse>, ApiError> {
+    let pubkey = Pubkey::from_str(&req.address).map_err(|_| ApiError {
+        message: "无效的地址".to_string(),
+    })?;
+    let limit = req
+        .limit
+        .unwrap_or(20)
+        .clamp(1, MAX_WALLET_TRANSACTION_HISTORY);
+    let before = non_empty_filter(req.before);
+    if let Some(before) = before.as_deref() {
+        Signature::from_str(before).map_err(|_| ApiError {
+            message: "无效的分页交易签名".to_string(),
+        })?;
+    }
+    let filter = wallet_store::WalletTransactionFilter {
+        mint: non_empty_filter(req.mint).map(|mint| {
+            if mint.eq_ignore_ascii_case("sol") {
+                "SOL".to_string()
+            } else {
+                mint
+            }
+        }),
+        counterparty: non_empty_filter(req.counterparty),
+        action: non_empty_filter(req.action).map(|action| action.to_ascii_lowercase()),
+        search: non_empty_filter(req.search),
+    };
+    let network = rpc_selector(req.network.as_deref())?.network;
+    let owner = pubkey.to_string();
+
+    // Serve from the local index; the RPC is only asked for what it does not have yet.
+    let mut sync_error = None;
+    let mut index = match sync_wallet_history(req.network.as_deref(), &pubkey, false).await {
+        Ok(index) => index,
+        Err(error) => {
+            tracing::warn!(
+                "Wallet history sync for {} failed: {}",
+                owner,
+                error.message
+            );
+            sync_error = Some(error.message);
+            wallet_store::wallet_history_state(&owner, &network)
+                .map_err(|message| ApiError { message })?
+        }
+    };
+    let mut transactions;
+    let mut backfill_rounds = 0;
+    loop {
+        transactions = wallet_store::query_wallet_transactions(
+            &owner,
+            &network,
+            &filter,
+            before.as_deref(),
+            limit + 1,
+        )
+        .map_err(|message| ApiError { message })?;
+        if transactions.len() > limit
+            || index.backfill_complete
+            || sync_error.is_some()
+            || backfill_rounds >= WALLET_HISTORY_MAX_BACKFILL_ROUNDS
+        {
+            break;
+        }
+        backfill_rounds += 1;
+        match sync_wallet_history(req.network.as_deref(), &pubkey, true).await {
+            Ok(state) => index = state,
+            Err(error) => sync_error = Some(error.message),
+        }
+    }
+
+    let has_more = transactions.len() > limit || !index.backfill_complete;
+    transactions.truncate(limit);
+    let next_before = transactions.last().map(|item| item.signature.clone());
+
+    Ok(Json(WalletTransactionsResponse {
+        address: req.address,
+        network,
+        limit,
+        before,
+        next_before,
+        has_more,
+        transactions,
+        index,
+        sync_error,
+    }))
+}
+
+#[derive(Deserialize)]
+struct WalletTransactionsSyncRequest {
+    address: String,
+    #[serde(default)]
+    network: Option<String>,
+    /// Also index one batch of older history
+    #[serde(default)]
+    backfill: bool,
+}
+
+async fn sync_wallet_transactions(
+    Json(req): Json<WalletTransactionsSyncRequest>,
+) -> Result<Json<wallet_store::WalletHistoryState>, ApiError> {
+    let pubkey = Pubkey::from_str(&req.address).map_err(|_| ApiError {
+        message: "无效的地址".to_string(),
+    })?;
+    sync_wallet_history(req.network.as_deref(), &pubkey, req.backfill)
+        .await
+        .map(Json)
+}
+
+// Get Public Key from Secret Key
 #[derive(Deserialize)]
 struct GetPubkeyRequest {
     #[serde(flatten)]
@@ -5254,6 +5855,217 @@ async fn get_pubkey(
     }))
 }
 
+// ============= Address Book =============
+
+#[derive(Deserialize)]
+struct AddressBookListRequest {
+    /// Only entries for this network; all networks when omitted
+    #[serde(default)]
+    network: Option<String>,
+}
+
+async fn address_book_list(
+    Json(req): Json<AddressBookListRequest>,
+) -> Result<Json<Vec<wallet_store::AddressBookRecord>>, ApiError> {
+    let network = req
+        .network
+        .as_deref()
+        .map(|network| network_name(Some(network)));
+    wallet_store::list_address_book(network.as_deref())
+        .map(Json)
+        .map_err(|message| ApiError { message })
+}
+
+#[derive(Deserialize)]
+struct AddressBookSaveRequest {
+    /// Existing entry to update; a new entry is created when omitted
+    #[serde(default)]
+    id: Option<String>,
+    address: String,
+    label: String,
+    #[serde(default)]
+    notes: Option<String>,
+    /// Mint the address should only receive, e.g. an exchange deposit address
+    #[serde(default)]
+    expected_mint: Option<String>,
+    #[serde(default)]
+    network: Option<String>,
+}
+
+async fn address_book_save(
+    Json(req): Json<AddressBookSaveRequest>,
+) -> Result<Json<wallet_store::AddressBookRecord>, ApiError> {
+    let address = Pubkey::from_str(req.address.trim()).map_err(|_| ApiError {
+        message: "无效的地址".to_string(),
+    })?;
+    validate_text_len(&req.label, "label", MAX_TEXT_FIELD_CHARS)?;
+    if let Some(notes) = &req.notes {
+        validate_text_len(notes, "notes", MAX_TEXT_FIELD_CHARS)?;
+    }
+    let expected_mint = match req
+        .expected_mint
+        .as_deref()
+        .map(str::trim)
+        .filter(|mint| !mint.is_empty())
+    {
+        Some(mint) => Some(
+            Pubkey::from_str(mint)
+                .map_err(|_| ApiError {
+                    message: "无效的 Token Mint 地址".to_string(),
+                })?
+                .to_string(),
+        ),
+        None => None,
+    };
+    let network = network_name(req.network.as_deref());
+    wallet_store::save_address_book_entry(
+        req.id.as_deref(),
+        &network,
+        &address.to_string(),
+        &req.label,
+        req.notes.as_deref(),
+        expected_mint.as_deref(),
+    )
+    .map(Json)
+    .map_err(|message| ApiError { message })
+}
+
+#[derive(Deserialize)]
+struct AddressBookDeleteRequest {
+    id: String,
+}
+
+async fn address_book_delete(
+    Json(req): Json<AddressBookDeleteRequest>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    wallet_store::delete_address_book_entry(&req.id).map_err(|message| ApiError { message })?;
+    Ok(Json(serde_json::json!({ "status": "success" })))
+}
+
+#[derive(Serialize)]
+struct RecipientCheckView {
+    address: String,
+    network: String,
+    /// Address-book label, or the saved wallet name for own wallets
+    label: Option<String>,
+    notes: Option<String>,
+    expected_mint: Option<String>,
+    mint_mismatch: bool,
+    first_send: bool,
+    send_count: u64,
+    last_sent_at: Option<u64>,
+    lookalikes: Vec<Lookalike>,
+    warnings: Vec<String>,
+}
+
+/// Match a recipient against the address book and own wallets before sending
+fn recipient_check_view(
+    network: &str,
+    recipient: &Pubkey,
+    mint: Option<&Pubkey>,
+) -> Result<RecipientCheckView, ApiError> {
+    let address = recipient.to_string();
+    let mint = mint.map(ToString::to_string);
+    let book =
+        wallet_store::list_address_book(Some(network)).map_err(|message| ApiError { message })?;
+    let wallets = wallet_store::list_summaries().map_err(|message| ApiError { message })?;
+    let history = wallet_store::recipient_history(network, &address)
+        .map_err(|message| ApiError { message })?;
+
+    let known = book
+        .iter()
+        .map(|entry| KnownAddress {
+            address: &entry.address,
+            label: &entry.label,
+            expected_mint: entry.expected_mint.as_deref(),
+        })
+        .chain(wallets.iter().map(|wallet| KnownAddress {
+            address: &wallet.public_key,
+            label: &wallet.name,
+            expected_mint: None,
+        }));
+    let check = check_recipient(&address, mint.as_deref(), known);
+    let own_wallet = wallets.iter().any(|wallet| wallet.public_key == address);
+    let first_send = history.send_count == 0;
+
+    let mut warnings = Vec::new();
+    for lookalike in &check.lookalikes {
+        warnings.push(format!(
+            "警告：接收地址与「{}」({}) 首尾字符相同但并非同一地址，可能是地址投毒，请逐字核对完整地址",
+            lookalike.label, lookalike.address
+        ));
+    }
+    if check.mint_mismatch {
+        warnings.push(format!(
+            "「{}」在地址簿中仅用于接收 {}，与本次转账的资产不符",
+            check.label.as_deref().unwrap_or_default(),
+            check.expected_mint.as_deref().unwrap_or_default()
+        ));
+    }
+    if first_send && !own_wallet {
+        warnings.push(if check.label.is_some() {
+            "此前从未向该地址转账，请确认地址簿条目无误".to_string()
+        } else {
+            "首次向该地址转账，且地址不在地址簿中，请逐字核对完整地址".to_string()
+        });
+    }
+
+    Ok(RecipientCheckView {
+        notes: book
+            .iter()
+            .find(|entry| entry.address == address)
+            .and_then(|entry| entry.notes.clone()),
+        address,
+        network: network.to_string(),
+        label: check.label,
+        expected_mint: check.expected_mint,
+        mint_mismatch: check.mint_mismatch,
+        first_send,
+        send_count: history.send_count,
+        last_sent_at: history.last_sent_at,
+        lookalikes: check.lookalikes,
+        warnings,
+    })
+}
+
+/// Record a confirmed send so later previews stop flagging the recipient as new
+fn record_recipient_send(network: &str, recipient: &Pubkey) {
+    if let Err(error) = wallet_store::record_sent_recipient(network, &recipient.to_string()) {
+        tracing::warn!("Could not record send to {}: {}", recipient, error);
+    }
+}
+
+#[derive(Deserialize)]
+struct AddressBookCheckRequest {
+    recipient: String,
+    /// Token being sent; SOL when omitted
+    #[serde(default)]
+    mint: Option<String>,
+    #[serde(default)]
+    network: Option<String>,
+}
+
+async fn address_book_check(
+    Json(req): Json<AddressBookCheckRequest>,
+) -> Result<Json<RecipientCheckView>, ApiError> {
+    let recipient = Pubkey::from_str(req.recipient.trim()).map_err(|_| ApiError {
+        message: "无效的接收地址".to_string(),
+    })?;
+    let mint = match req
+        .mint
+        .as_deref()
+        .map(str::trim)
+        .filter(|mint| !mint.is_empty())
+    {
+        Some(mint) => Some(Pubkey::from_str(mint).map_err(|_| ApiError {
+            message: "无效的 Token Mint 地址".to_string(),
+        })?),
+        None => None,
+    };
+    let network = network_name(req.network.as_deref());
+    recipient_check_view(&network, &recipient, mint.as_ref()).map(Json)
+}
+
 // ============= SOL Operations (8) =============
 
 // 8. Transfer SOL
@@ -5263,6 +6075,9 @@ struct TransferSolRequest {
     wallet: WalletAuthRequest,
     to_address: String,
     amount: DecimalAmount,
+    /// SPL Memo attached to the transfer, e.g. an exchange deposit tag
+    #[serde(default)]
+    memo: Option<String>,
     #[serde(default)]
     network: Option<String>,
 }
@@ -5270,6 +6085,14 @@ struct TransferSolRequest {
 struct TransferSolResponse {
     signature: String,
     status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    memo: Option<String>,
+}
+
+fn transfer_memo(memo: Option<&str>) -> Result<Option<String>, ApiError> {
+    normalize_memo(memo).map_err(|e| ApiError {
+        message: format!("备注无效: {}", e),
+    })
 }
 
 async fn transfer_sol(
@@ -5279,6 +6102,7 @@ async fn transfer_sol(
         message: "无效的接收地址".to_string(),
     })?;
 
+    let memo = transfer_memo(req.memo.as_deref())?;
     let keypair = req.wallet.keypair()?;
 
     let amount_lamports = sol_to_lamports(&req.amount)?;
@@ -5286,14 +6110,16 @@ async fn transfer_sol(
     let client = SolanaClient::new(rpc_url.to_string());
 
     let signature = client
-        .transfer_sol(&keypair, &to_pubkey, amount_lamports)
+        .transfer_sol_with_memo(&keypair, &to_pubkey, amount_lamports, memo.as_deref())
         .map_err(|e| ApiError {
             message: format!("转账失败: {}", e),
         })?;
+    record_recipient_send(&network_name(req.network.as_deref()), &to_pubkey);
 
     Ok(Json(TransferSolResponse {
         signature: signature.to_string(),
         status: "success".to_string(),
+        memo,
     }))
 }
 
@@ -5434,5786 +6260,7040 @@ async fn close_wsol_ata(
     }))
 }
 
-// ============= Token Operations (13) =============
-
+// 12b. Empty Token Accounts (rent reclamation)
 #[derive(Deserialize)]
-struct TokenMintInfoRequest {
-    mint: String,
+struct EmptyTokenAccountsRequest {
+    owner: String,
     #[serde(default)]
     network: Option<String>,
 }
 
 #[derive(Serialize)]
-struct TokenMintInfoResponse {
+struct EmptyTokenAccountView {
+    address: String,
     mint: String,
+    token_program: String,
+    lamports: u64,
+    rent_sol: f64,
+    frozen: bool,
+    withheld_fee_amount: String,
+    closable: bool,
+    reason: Option<String>,
+}
+
+#[derive(Serialize)]
+struct EmptyTokenAccountsResponse {
+    owner: String,
     network: String,
-    decimals: u8,
-    supply: String,
-    ui_amount_string: String,
+    accounts: Vec<EmptyTokenAccountView>,
+    reclaimable_lamports: u64,
+    reclaimable_sol: f64,
 }
 
-async fn token_mint_info(
-    Json(req): Json<TokenMintInfoRequest>,
-) -> Result<Json<TokenMintInfoResponse>, ApiError> {
-    let mint = Pubkey::from_str(req.mint.trim()).map_err(|_| ApiError {
-        message: "无效的 Token Mint 地址".to_string(),
+fn find_empty_token_accounts(
+    network: Option<&str>,
+    owner: &Pubkey,
+) -> Result<(Vec<EmptyTokenAccount>, String), ApiError> {
+    let (client, network) = rpc_query_client_for_timeout(network, RPC_QUERY_TIMEOUT_SECS)?;
+    let candidates = all_owner_token_accounts(&client, owner)?
+        .into_iter()
+        .filter(|(_, _, token_account)| token_account.token_amount.amount == "0")
+        .filter_map(|(account, _, _)| Pubkey::from_str(&account).ok())
+        .collect::<Vec<_>>();
+    let accounts = SolanaClient::new(client.url())
+        .inspect_empty_token_accounts(owner, &candidates)
+        .map_err(|e| ApiError {
+            message: format!("查询空 Token 账户失败: {}", e),
+        })?;
+    Ok((accounts, network))
+}
+
+async fn empty_token_accounts(
+    Json(req): Json<EmptyTokenAccountsRequest>,
+) -> Result<Json<EmptyTokenAccountsResponse>, ApiError> {
+    let owner = Pubkey::from_str(&req.owner).map_err(|_| ApiError {
+        message: "无效的钱包地址".to_string(),
     })?;
-    let (client, network) = rpc_client_for(req.network.as_deref())?;
-    let supply = token_mint_info_from_rpc(&client, &mint)?;
-    let ui_amount_string = supply.real_number_string_trimmed();
+    let (accounts, network) = find_empty_token_accounts(req.network.as_deref(), &owner)?;
 
-    Ok(Json(TokenMintInfoResponse {
-        mint: mint.to_string(),
+    let mut reclaimable_lamports = 0u64;
+    let accounts = accounts
+        .into_iter()
+        .map(|account| {
+            let reason = account.closable_by(&owner).err();
+            if reason.is_none() {
+                reclaimable_lamports = reclaimable_lamports.saturating_add(account.lamports);
+            }
+            EmptyTokenAccountView {
+                address: account.address.to_string(),
+                mint: account.mint.to_string(),
+                token_program: account.token_program.to_string(),
+                lamports: account.lamports,
+                rent_sol: lamports_to_sol(account.lamports),
+                frozen: account.frozen,
+                withheld_fee_amount: account.withheld_fee_amount.to_string(),
+                closable: reason.is_none(),
+                reason: reason.map(str::to_string),
+            }
+        })
+        .collect();
+
+    Ok(Json(EmptyTokenAccountsResponse {
+        owner: owner.to_string(),
         network,
-        decimals: supply.decimals,
-        supply: supply.amount,
-        ui_amount_string,
+        accounts,
+        reclaimable_lamports,
+        reclaimable_sol: lamports_to_sol(reclaimable_lamports),
     }))
 }
 
-// 13. Transfer SPL Token
 #[derive(Deserialize)]
-struct TransferTokenRequest {
+struct CloseEmptyTokenAccountsRequest {
     #[serde(flatten)]
     wallet: WalletAuthRequest,
-    to_address: String,
-    mint: String,
-    amount: DecimalAmount,
-    #[allow(dead_code)]
+    /// Limit closing to these accounts; omitted closes every closable empty account.
     #[serde(default)]
-    decimals: Option<u8>,
+    accounts: Option<Vec<String>>,
     #[serde(default)]
     network: Option<String>,
 }
+
 #[derive(Serialize)]
-struct TransferTokenResponse {
-    signature: String,
-    status: String,
+struct SkippedTokenAccount {
+    address: String,
+    reason: String,
 }
 
-async fn transfer_token(
-    Json(req): Json<TransferTokenRequest>,
-) -> Result<Json<TransferTokenResponse>, ApiError> {
-    let to_pubkey = Pubkey::from_str(&req.to_address).map_err(|_| ApiError {
-        message: "无效的接收地址".to_string(),
-    })?;
-    let mint = Pubkey::from_str(&req.mint).map_err(|_| ApiError {
-        message: "无效的mint地址".to_string(),
-    })?;
+#[derive(Serialize)]
+struct CloseEmptyTokenAccountsResponse {
+    status: String,
+    signatures: Vec<String>,
+    closed: Vec<String>,
+    reclaimed_lamports: u64,
+    reclaimed_sol: f64,
+    skipped: Vec<SkippedTokenAccount>,
+    errors: Vec<String>,
+}
 
+async fn close_empty_token_accounts(
+    Json(req): Json<CloseEmptyTokenAccountsRequest>,
+) -> Result<Json<CloseEmptyTokenAccountsResponse>, ApiError> {
     let keypair = req.wallet.keypair()?;
+    let selected = req
+        .accounts
+        .map(|accounts| {
+            accounts
+                .iter()
+                .map(|account| {
+                    Pubkey::from_str(account.trim()).map_err(|_| ApiError {
+                        message: format!("无效的 Token 账户地址: {}", account),
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .transpose()?;
+
+    let (mut accounts, _) = find_empty_token_accounts(req.network.as_deref(), &keypair.pubkey())?;
+    if let Some(selected) = &selected {
+        accounts.retain(|account| selected.contains(&account.address));
+    }
+    if accounts.is_empty() {
+        return Err(ApiError {
+            message: "没有可关闭的空 Token 账户".to_string(),
+        });
+    }
 
     let rpc_url = get_rpc_url(req.network.as_deref())?;
-    let rpc_client =
-        RpcClient::new_with_commitment(rpc_url.to_string(), CommitmentConfig::confirmed());
-    let client = SolanaClient::new(rpc_url.to_string());
-
-    // Always trust on-chain mint decimals instead of user-submitted decimals.
-    let mint_info = token_mint_info_from_rpc(&rpc_client, &mint)?;
-    let token_amount = token_amount_to_raw(&req.amount, mint_info.decimals)?;
-
-    let signature = client
-        .transfer_token(&keypair, &to_pubkey, &mint, token_amount)
+    let result = SolanaClient::new(rpc_url)
+        .close_empty_token_accounts(&keypair, &accounts)
         .map_err(|e| ApiError {
-            message: format!("转账失败: {}", e),
+            message: format!("关闭空 Token 账户失败: {}", e),
         })?;
+    let status = if result.errors.is_empty() {
+        "success"
+    } else if result.closed.is_empty() {
+        "failed"
+    } else {
+        "partial"
+    };
 
-    Ok(Json(TransferTokenResponse {
-        signature: signature.to_string(),
-        status: "success".to_string(),
+    Ok(Json(CloseEmptyTokenAccountsResponse {
+        status: status.to_string(),
+        signatures: result
+            .signatures
+            .iter()
+            .map(|signature| signature.to_string())
+            .collect(),
+        closed: result
+            .closed
+            .iter()
+            .map(|account| account.to_string())
+            .collect(),
+        reclaimed_lamports: result.reclaimed_lamports,
+        reclaimed_sol: lamports_to_sol(result.reclaimed_lamports),
+        skipped: result
+            .skipped
+            .into_iter()
+            .map(|(address, reason)| SkippedTokenAccount {
+                address: address.to_string(),
+                reason,
+            })
+            .collect(),
+        errors: result.errors,
     }))
 }
 
-// ============= Nonce Operations (14) =============
+// ============= Batch Payouts =============
 
-// 14. Create Nonce Account
 #[derive(Deserialize)]
-struct CreateNonceAccountRequest {
+struct PayoutInputRequest {
+    /// Entries as JSON objects; alternatively `csv` with `recipient,asset,amount[,memo]` rows
+    #[serde(default)]
+    payouts: Option<Vec<PayoutEntry>>,
+    #[serde(default)]
+    csv: Option<String>,
+}
+
+impl PayoutInputRequest {
+    fn entries(&self) -> Result<Vec<PayoutEntry>, ApiError> {
+        let entries = match (&self.payouts, &self.csv) {
+            (Some(payouts), None) => payouts.clone(),
+            (None, Some(csv)) => payouts::parse_payouts(csv).map_err(|e| ApiError {
+                message: format!("解析批量转账列表失败: {}", e),
+            })?,
+            _ => {
+                return Err(ApiError {
+                    message: "请提供 payouts 或 csv 其中之一".to_string(),
+                })
+            }
+        };
+        if entries.is_empty() || entries.len() > MAX_PAYOUT_ENTRIES {
+            return Err(ApiError {
+                message: format!("批量转账需要 1 到 {} 条记录", MAX_PAYOUT_ENTRIES),
+            });
+        }
+        Ok(entries)
+    }
+}
+
+#[derive(Deserialize)]
+struct PayoutPreviewRequest {
+    source: String,
     #[serde(flatten)]
-    wallet: WalletAuthRequest,
+    input: PayoutInputRequest,
     #[serde(default)]
     network: Option<String>,
-    #[serde(default)]
-    count: Option<u8>,
 }
+
 #[derive(Serialize)]
-struct CreatedNonceAccount {
-    nonce_account: String,
-    signature: String,
-    created_at: u64,
+struct PayoutLineView {
+    /// 1-based line number in the input list
+    line: usize,
+    recipient: String,
+    asset: String,
+    amount: String,
+    fee: String,
+    memo: Option<String>,
+    create_recipient_account: bool,
+    transaction: usize,
 }
+
 #[derive(Serialize)]
-struct CreateNonceAccountResponse {
-    nonce_accounts: Vec<CreatedNonceAccount>,
-    nonce_account: String,
-    signature: String,
-    count: usize,
-    status: String,
+struct PayoutTotalView {
+    asset: String,
+    decimals: u8,
+    recipients: usize,
+    amount: String,
+    fee: String,
+    balance: String,
 }
 
-async fn create_nonce_account(
-    Json(req): Json<CreateNonceAccountRequest>,
-) -> Result<Json<CreateNonceAccountResponse>, ApiError> {
-    let keypair = req.wallet.keypair()?;
-    let owner = keypair.pubkey().to_string();
-    let wallet_id = req
-        .wallet
-        .wallet_id
-        .as_deref()
-        .map(str::trim)
-        .filter(|value| !value.is_empty());
-    let count = req.count.unwrap_or(1);
-    if count == 0 || count > MAX_NONCE_BATCH_COUNT {
-        return Err(ApiError {
-            message: format!("Nonce 创建数量必须在 1-{} 之间", MAX_NONCE_BATCH_COUNT),
-        });
+#[derive(Serialize)]
+struct PayoutIssueView {
+    line: Option<usize>,
+    message: String,
+}
+
+impl From<&PayoutIssue> for PayoutIssueView {
+    fn from(issue: &PayoutIssue) -> Self {
+        Self {
+            line: issue.line.map(|line| line + 1),
+            message: issue.message.clone(),
+        }
     }
+}
 
-    let selector = rpc_selector(req.network.as_deref())?;
-    let rpc_url = selector.url;
-    let network = selector.network;
-    let client = SolanaClient::new(rpc_url.to_string());
+#[derive(Serialize)]
+struct PayoutPreviewResponse {
+    batch_id: String,
+    source: String,
+    network: String,
+    executable: bool,
+    completed_lines: usize,
+    transaction_count: usize,
+    accounts_to_create: usize,
+    account_rent_lamports: u64,
+    account_rent_sol: f64,
+    network_fee_lamports: u64,
+    network_fee_sol: f64,
+    totals: Vec<PayoutTotalView>,
+    lines: Vec<PayoutLineView>,
+    warnings: Vec<PayoutIssueView>,
+    errors: Vec<PayoutIssueView>,
+}
+
+impl PayoutPreviewResponse {
+    fn from_plan(plan: &PayoutPlan, network: String) -> Self {
+        let transaction_of: HashMap<usize, usize> = plan
+            .transactions
+            .iter()
+            .enumerate()
+            .flat_map(|(position, transaction)| {
+                transaction
+                    .lines
+                    .iter()
+                    .map(move |line| (*line, position + 1))
+            })
+            .collect();
+        let mut lines: Vec<PayoutLineView> = plan
+            .lines
+            .iter()
+            .map(|line| PayoutLineView {
+                line: line.index + 1,
+                recipient: line.recipient.to_string(),
+                asset: line.asset.to_string(),
+                amount: ui_amount_from_raw_amount(line.amount as u128, line.decimals),
+                fee: ui_amount_from_raw_amount(line.fee as u128, line.decimals),
+                memo: line.memo.clone(),
+                create_recipient_account: line.create_recipient_account,
+                transaction: transaction_of.get(&line.index).copied().unwrap_or_default(),
+            })
+            .collect();
+        lines.sort_by_key(|line| line.line);
 
-    let mut nonce_accounts = Vec::with_capacity(count as usize);
-    for _ in 0..count {
-        let (nonce_account, signature) =
-            client
-                .create_nonce_account(&keypair)
-                .map_err(|e| ApiError {
-                    message: format!("创建失败: {}", e),
-                })?;
-        let record = wallet_store::add_nonce_account(
-            wallet_id,
-            &owner,
-            &network,
-            &nonce_account.to_string(),
-            &signature.to_string(),
-        )
-        .map_err(|message| ApiError { message })?;
-        nonce_accounts.push(CreatedNonceAccount {
-            nonce_account: record.nonce_account,
-            signature: record.signature,
-            created_at: record.created_at,
-        });
+        Self {
+            batch_id: plan.batch_id.clone(),
+            source: plan.source.to_string(),
+            network,
+            executable: plan.is_executable(),
+            completed_lines: plan.completed_lines,
+            transaction_count: plan.transactions.len(),
+            accounts_to_create: plan.accounts_to_create,
+            account_rent_lamports: plan.account_rent_lamports,
+            account_rent_sol: lamports_to_sol(plan.account_rent_lamports),
+            network_fee_lamports: plan.network_fee_lamports,
+            network_fee_sol: lamports_to_sol(plan.network_fee_lamports),
+            totals: plan
+                .totals
+                .iter()
+                .map(|total| PayoutTotalView {
+                    asset: total.asset.to_string(),
+                    decimals: total.decimals,
+                    recipients: total.recipients,
+                    amount: ui_amount_from_raw_amount(total.amount as u128, total.decimals),
+                    fee: ui_amount_from_raw_amount(total.fee as u128, total.decimals),
+                    balance: ui_amount_from_raw_amount(total.balance as u128, total.decimals),
+                })
+                .collect(),
+            lines,
+            warnings: plan.warnings.iter().map(PayoutIssueView::from).collect(),
+            errors: plan.errors.iter().map(PayoutIssueView::from).collect(),
+        }
     }
-    let first = nonce_accounts.first().ok_or_else(|| ApiError {
-        message: "未创建 Nonce 账户".to_string(),
+}
+
+/// Input lines already confirmed by an earlier run of the same payout list
+fn confirmed_payout_lines(network: &str, batch_id: &str) -> Result<HashSet<usize>, ApiError> {
+    Ok(wallet_store::load_payout_progress(network, batch_id)
+        .map_err(|message| ApiError { message })?
+        .into_iter()
+        .filter(|progress| progress.status == PayoutLineStatus::Confirmed)
+        .map(|progress| progress.line)
+        .collect())
+}
+
+async fn payouts_preview(
+    Json(req): Json<PayoutPreviewRequest>,
+) -> Result<Json<PayoutPreviewResponse>, ApiError> {
+    let source = Pubkey::from_str(req.source.trim()).map_err(|_| ApiError {
+        message: "无效的付款钱包地址".to_string(),
     })?;
+    let entries = req.input.entries()?;
+    let (client, network) = rpc_client_for(req.network.as_deref())?;
+    let completed = confirmed_payout_lines(&network, &payouts::payout_batch_id(&source, &entries))?;
+    let plan = SolanaClient::new(client.url())
+        .plan_payouts(&source, &entries, &completed)
+        .map_err(|e| ApiError {
+            message: format!("生成批量转账预览失败: {}", e),
+        })?;
+    Ok(Json(PayoutPreviewResponse::from_plan(&plan, network)))
+}
 
-    Ok(Json(CreateNonceAccountResponse {
-        nonce_account: first.nonce_account.clone(),
-        signature: first.signature.clone(),
-        count: nonce_accounts.len(),
-        nonce_accounts,
-        status: "success".to_string(),
+#[derive(Deserialize)]
+struct PayoutExecuteRequest {
+    #[serde(flatten)]
+    wallet: WalletAuthRequest,
+    #[serde(flatten)]
+    input: PayoutInputRequest,
+    #[serde(default)]
+    network: Option<String>,
+}
+
+#[derive(Serialize)]
+struct PayoutTransactionView {
+    /// 1-based line numbers paid by this transaction
+    lines: Vec<usize>,
+    signature: String,
+}
+
+#[derive(Serialize)]
+struct PayoutExecuteResponse {
+    status: String,
+    batch_id: String,
+    network: String,
+    total_lines: usize,
+    previously_confirmed: usize,
+    remaining: usize,
+    transactions: Vec<PayoutTransactionView>,
+    error: Option<String>,
+}
+
+async fn payouts_execute(
+    Json(req): Json<PayoutExecuteRequest>,
+) -> Result<Json<PayoutExecuteResponse>, ApiError> {
+    let keypair = req.wallet.keypair()?;
+    let entries = req.input.entries()?;
+    let (client, network) = rpc_client_for(req.network.as_deref())?;
+    let mut store = wallet_store::PayoutProgressDb::new(&network);
+    let report = SolanaClient::new(client.url())
+        .execute_payouts(&keypair, &entries, &mut store, &mut |_| {})
+        .map_err(|e| ApiError {
+            message: format!("批量转账失败: {}", e),
+        })?;
+    let status = if report.remaining == 0 {
+        "success"
+    } else if report.sent.is_empty() {
+        "failed"
+    } else {
+        "partial"
+    };
+
+    Ok(Json(PayoutExecuteResponse {
+        status: status.to_string(),
+        batch_id: report.batch_id,
+        network,
+        total_lines: report.total_lines,
+        previously_confirmed: report.previously_confirmed,
+        remaining: report.remaining,
+        transactions: report
+            .sent
+            .iter()
+            .map(|sent| PayoutTransactionView {
+                lines: sent.lines.iter().map(|line| line + 1).collect(),
+                signature: sent.signature.to_string(),
+            })
+            .collect(),
+        error: report.error,
     }))
 }
 
 #[derive(Deserialize)]
-struct ListNonceAccountsRequest {
-    owner: String,
+struct PayoutStatusRequest {
+    batch_id: String,
     #[serde(default)]
     network: Option<String>,
 }
 
 #[derive(Serialize)]
-struct ListNonceAccountsResponse {
-    owner: String,
+struct PayoutProgressView {
+    line: usize,
+    status: String,
+    signature: Option<String>,
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct PayoutStatusResponse {
+    batch_id: String,
     network: String,
-    nonce_accounts: Vec<wallet_store::NonceAccountRecord>,
+    confirmed: usize,
+    lines: Vec<PayoutProgressView>,
 }
 
-async fn list_nonce_accounts(
-    Json(req): Json<ListNonceAccountsRequest>,
-) -> Result<Json<ListNonceAccountsResponse>, ApiError> {
-    let owner = Pubkey::from_str(&req.owner).map_err(|_| ApiError {
-        message: "无效的钱包地址".to_string(),
-    })?;
-    let network = network_name(req.network.as_deref()).to_string();
-    let nonce_accounts = wallet_store::list_nonce_accounts(&owner.to_string(), &network)
+async fn payouts_status(
+    Json(req): Json<PayoutStatusRequest>,
+) -> Result<Json<PayoutStatusResponse>, ApiError> {
+    let network = network_name(req.network.as_deref());
+    let progress = wallet_store::load_payout_progress(&network, req.batch_id.trim())
         .map_err(|message| ApiError { message })?;
-    Ok(Json(ListNonceAccountsResponse {
-        owner: owner.to_string(),
+    Ok(Json(PayoutStatusResponse {
+        batch_id: req.batch_id.trim().to_string(),
         network,
-        nonce_accounts,
+        confirmed: progress
+            .iter()
+            .filter(|progress| progress.status == PayoutLineStatus::Confirmed)
+            .count(),
+        lines: progress
+            .into_iter()
+            .map(|progress| PayoutProgressView {
+                line: progress.line + 1,
+                status: progress.status.as_str().to_string(),
+                signature: progress.signature,
+                error: progress.error,
+            })
+            .collect(),
     }))
 }
 
-// ============= Program Deployment =============
-
-const DEPLOYMENT_STATUS_CREATE_BUFFER_SIGNED: &str = "create_buffer_signed";
-const DEPLOYMENT_STATUS_CREATE_BUFFER_RECONCILE: &str = "create_buffer_requires_reconciliation";
-const DEPLOYMENT_STATUS_BUFFER_READY: &str = "buffer_ready";
-const DEPLOYMENT_STATUS_WRITE_SIGNED: &str = "write_signed";
-const DEPLOYMENT_STATUS_WRITE_CONFIRMED: &str = "write_confirmed";
-const DEPLOYMENT_STATUS_WRITE_RECONCILE: &str = "write_requires_reconciliation";
-const DEPLOYMENT_STATUS_BUFFER_FINALIZED: &str = "buffer_finalized";
-const DEPLOYMENT_STATUS_DEPLOY_SIGNED: &str = "deploy_signed";
-const DEPLOYMENT_STATUS_DEPLOY_RECONCILE: &str = "deploy_requires_reconciliation";
-const DEPLOYMENT_STATUS_DEPLOY_FINALIZED: &str = "deploy_finalized_pending_readback";
-const DEPLOYMENT_STATUS_FINALIZED: &str = "finalized";
+// ============= Staking =============
 
-#[derive(Debug, PartialEq, Eq)]
-enum DeploymentAttemptExpiryDecision {
-    NotExpired,
-    ExpiredAbsent {
-        min_context_slot: u64,
-    },
-    HistoryContextTooOld {
-        expiry_observation_slot: u64,
-        history_context_slot: u64,
-    },
-    SeenButNotFinalized,
-    FinalizedSucceeded,
-    FinalizedFailed(String),
+#[derive(Deserialize)]
+struct StakeListRequest {
+    /// Staker or withdrawer authority
+    authority: String,
+    #[serde(default)]
+    network: Option<String>,
 }
 
-fn classify_deployment_attempt_expiry(
-    finalized_block_height: u64,
-    last_valid_block_height: u64,
-    historical_status: Option<&TransactionStatus>,
-    history_context_slot: u64,
-    expiry_observation_slot: u64,
-) -> DeploymentAttemptExpiryDecision {
-    if finalized_block_height <= last_valid_block_height {
-        return DeploymentAttemptExpiryDecision::NotExpired;
-    }
-    if history_context_slot < expiry_observation_slot {
-        return DeploymentAttemptExpiryDecision::HistoryContextTooOld {
-            expiry_observation_slot,
-            history_context_slot,
-        };
-    }
-    let Some(status) = historical_status else {
-        return DeploymentAttemptExpiryDecision::ExpiredAbsent {
-            min_context_slot: history_context_slot,
-        };
-    };
-    if !status.satisfies_commitment(CommitmentConfig::finalized()) {
-        return DeploymentAttemptExpiryDecision::SeenButNotFinalized;
-    }
-    match status.err.as_ref() {
-        Some(error) => DeploymentAttemptExpiryDecision::FinalizedFailed(format!("{error:?}")),
-        None => DeploymentAttemptExpiryDecision::FinalizedSucceeded,
+#[derive(Serialize)]
+struct StakeAccountView {
+    address: String,
+    lamports: u64,
+    sol: f64,
+    activation: String,
+    withdrawable_lamports: u64,
+    staker: Option<String>,
+    withdrawer: Option<String>,
+    rent_exempt_reserve: Option<u64>,
+    vote_account: Option<String>,
+    delegated_lamports: Option<u64>,
+    activation_epoch: Option<u64>,
+    deactivation_epoch: Option<u64>,
+    lockup_epoch: Option<u64>,
+    lockup_unix_timestamp: Option<i64>,
+}
+
+impl From<&StakeAccount> for StakeAccountView {
+    fn from(account: &StakeAccount) -> Self {
+        let state = account.state.as_ref();
+        let delegation = state.and_then(|state| state.delegation);
+        Self {
+            address: account.address.to_string(),
+            lamports: account.lamports,
+            sol: lamports_to_sol(account.lamports),
+            activation: account.activation.as_str().to_string(),
+            withdrawable_lamports: account.withdrawable_lamports(),
+            staker: state.map(|state| state.staker.to_string()),
+            withdrawer: state.map(|state| state.withdrawer.to_string()),
+            rent_exempt_reserve: state.map(|state| state.rent_exempt_reserve),
+            vote_account: delegation.map(|delegation| delegation.vote_account.to_string()),
+            delegated_lamports: delegation.map(|delegation| delegation.stake),
+            activation_epoch: delegation.map(|delegation| delegation.activation_epoch),
+            deactivation_epoch: delegation
+                .map(|delegation| delegation.deactivation_epoch)
+                .filter(|epoch| *epoch != u64::MAX),
+            lockup_epoch: state.map(|state| state.lockup_epoch),
+            lockup_unix_timestamp: state.map(|state| state.lockup_unix_timestamp),
+        }
     }
 }
 
-fn inspect_deployment_attempt_expiry(
-    client: &RpcClient,
-    attempt: &wallet_store::ProgramDeploymentAttemptRecord,
-) -> Result<DeploymentAttemptExpiryDecision, ApiError> {
-    let finalized_epoch_info = client
-        .get_epoch_info_with_commitment(CommitmentConfig::finalized())
-        .map_err(|error| {
-            deployment_journal_error(format!(
-                "读取 finalized epoch info 失败，无法判定签名 {} 是否过期: {error}",
-                attempt.signature
-            ))
-        })?;
-    let signature = Signature::from_str(attempt.signature.trim()).map_err(|_| {
-        deployment_journal_error(format!(
-            "attempt {} 包含无效签名，拒绝自动恢复",
-            attempt.signature
-        ))
-    })?;
-    if finalized_epoch_info.block_height <= attempt.last_valid_block_height {
-        return Ok(DeploymentAttemptExpiryDecision::NotExpired);
-    }
-    let response = client
-        .get_signature_statuses_with_history(&[signature])
-        .map_err(|error| {
-            deployment_journal_error(format!(
-                "查询签名 {} 的完整历史失败，拒绝自动重试: {error}",
-                attempt.signature
-            ))
-        })?;
-    if response.value.len() != 1 {
-        return Err(deployment_journal_error(format!(
-            "签名 {} 的历史响应数量异常，拒绝自动重试",
-            attempt.signature
-        )));
-    }
-    Ok(classify_deployment_attempt_expiry(
-        finalized_epoch_info.block_height,
-        attempt.last_valid_block_height,
-        response.value.first().and_then(Option::as_ref),
-        response.context.slot,
-        finalized_epoch_info.absolute_slot,
-    ))
+#[derive(Serialize)]
+struct StakeListResponse {
+    authority: String,
+    network: String,
+    epoch: u64,
+    total_lamports: u64,
+    accounts: Vec<StakeAccountView>,
 }
 
-fn gate_expired_absent_with<F>(
-    decision: DeploymentAttemptExpiryDecision,
-    confirm_absent: F,
-) -> Result<DeploymentAttemptExpiryDecision, ApiError>
-where
-    F: FnOnce(u64) -> Result<(), ApiError>,
-{
-    match decision {
-        DeploymentAttemptExpiryDecision::ExpiredAbsent { min_context_slot } => {
-            confirm_absent(min_context_slot)?;
-            Ok(DeploymentAttemptExpiryDecision::ExpiredAbsent { min_context_slot })
-        }
-        other => Ok(other),
-    }
+async fn stake_list(
+    Json(req): Json<StakeListRequest>,
+) -> Result<Json<StakeListResponse>, ApiError> {
+    let authority = stake_pubkey(&req.authority, "质押权限地址")?;
+    let (client, network) = rpc_client_for(req.network.as_deref())?;
+    let (epoch, accounts) = SolanaClient::new(client.url())
+        .list_stake_accounts(&authority)
+        .map_err(|e| ApiError {
+            message: format!("查询质押账户失败: {}", e),
+        })?;
+    Ok(Json(StakeListResponse {
+        authority: authority.to_string(),
+        network,
+        epoch,
+        total_lamports: accounts.iter().map(|account| account.lamports).sum(),
+        accounts: accounts.iter().map(StakeAccountView::from).collect(),
+    }))
 }
 
-fn finalized_recovery_account_config(min_context_slot: u64) -> RpcAccountInfoConfig {
-    RpcAccountInfoConfig {
-        encoding: Some(UiAccountEncoding::Base64Zstd),
-        commitment: Some(CommitmentConfig::finalized()),
-        data_slice: None,
-        min_context_slot: Some(min_context_slot),
-    }
+fn stake_pubkey(value: &str, field: &str) -> Result<Pubkey, ApiError> {
+    Pubkey::from_str(value.trim()).map_err(|_| ApiError {
+        message: format!("无效的{}", field),
+    })
 }
 
-fn decode_recovery_ui_account(address: &Pubkey, account: UiAccount) -> Result<Account, ApiError> {
-    account.decode().ok_or_else(|| {
-        deployment_journal_error(format!(
-            "finalized 账户 {} 的 Base64Zstd 数据无法解码；拒绝将其视为不存在",
-            address
-        ))
-    })
+fn stake_seed(seed: Option<&str>) -> Option<String> {
+    seed.map(str::trim)
+        .filter(|seed| !seed.is_empty())
+        .map(str::to_string)
 }
 
-fn get_finalized_recovery_account(
-    client: &RpcClient,
-    address: &Pubkey,
-    min_context_slot: u64,
-) -> Result<Option<Account>, ApiError> {
-    let response = client
-        .get_ui_account_with_config(address, finalized_recovery_account_config(min_context_slot))
-        .map_err(|error| {
-            deployment_journal_error(format!(
-                "在最小上下文 slot {} 二次读取 finalized 账户 {} 失败: {error}",
-                min_context_slot, address
-            ))
-        })?;
-    if response.context.slot < min_context_slot {
-        return Err(deployment_journal_error(format!(
-            "finalized 账户 {} 二次回读 slot {} 早于签名历史 slot {}",
-            address, response.context.slot, min_context_slot
-        )));
-    }
-    response
-        .value
-        .map(|account| decode_recovery_ui_account(address, account))
-        .transpose()
+/// One stake operation; `action` selects the variant
+#[derive(Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+enum StakeActionRequest {
+    Create {
+        amount: DecimalAmount,
+        #[serde(default)]
+        vote_account: Option<String>,
+        /// Derive the stake account from the authority and this seed instead of a new keypair
+        #[serde(default)]
+        seed: Option<String>,
+    },
+    Delegate {
+        stake_account: String,
+        vote_account: String,
+    },
+    Deactivate {
+        stake_account: String,
+    },
+    Withdraw {
+        stake_account: String,
+        #[serde(default)]
+        to_address: Option<String>,
+        /// Everything withdrawable when omitted
+        #[serde(default)]
+        amount: Option<DecimalAmount>,
+    },
+    Split {
+        stake_account: String,
+        amount: DecimalAmount,
+        #[serde(default)]
+        seed: Option<String>,
+    },
+    Merge {
+        destination: String,
+        source: String,
+    },
+    Authorize {
+        stake_account: String,
+        new_authority: String,
+        /// `staker` or `withdrawer`
+        authority: String,
+    },
 }
 
-fn get_finalized_recovery_accounts(
-    client: &RpcClient,
-    addresses: &[Pubkey],
-    min_context_slot: u64,
-) -> Result<Vec<Option<Account>>, ApiError> {
-    let response = client
-        .get_multiple_ui_accounts_with_config(
-            addresses,
-            finalized_recovery_account_config(min_context_slot),
-        )
-        .map_err(|error| {
-            deployment_journal_error(format!(
-                "在最小上下文 slot {} 二次读取 finalized 账户组失败: {error}",
-                min_context_slot
-            ))
-        })?;
-    if response.context.slot < min_context_slot {
-        return Err(deployment_journal_error(format!(
-            "finalized 账户组二次回读 slot {} 早于签名历史 slot {}",
-            response.context.slot, min_context_slot
-        )));
-    }
-    if response.value.len() != addresses.len() {
-        return Err(deployment_journal_error(format!(
-            "finalized 账户组二次回读返回 {} 项，预期 {} 项",
-            response.value.len(),
-            addresses.len()
-        )));
-    }
-    response
-        .value
-        .into_iter()
-        .zip(addresses)
-        .map(|(account, address)| {
-            account
-                .map(|account| decode_recovery_ui_account(address, account))
-                .transpose()
+impl StakeActionRequest {
+    fn to_action(&self) -> Result<StakeAction, ApiError> {
+        Ok(match self {
+            Self::Create {
+                amount,
+                vote_account,
+                seed,
+            } => StakeAction::Create {
+                lamports: sol_to_lamports(amount)?,
+                vote_account: vote_account
+                    .as_deref()
+                    .map(str::trim)
+                    .filter(|vote| !vote.is_empty())
+                    .map(|vote| stake_pubkey(vote, "投票账户地址"))
+                    .transpose()?,
+                seed: stake_seed(seed.as_deref()),
+            },
+            Self::Delegate {
+                stake_account,
+                vote_account,
+            } => StakeAction::Delegate {
+                stake_account: stake_pubkey(stake_account, "质押账户地址")?,
+                vote_account: stake_pubkey(vote_account, "投票账户地址")?,
+            },
+            Self::Deactivate { stake_account } => StakeAction::Deactivate {
+                stake_account: stake_pubkey(stake_account, "质押账户地址")?,
+            },
+            Self::Withdraw {
+                stake_account,
+                to_address,
+                amount,
+            } => StakeAction::Withdraw {
+                stake_account: stake_pubkey(stake_account, "质押账户地址")?,
+                to: to_address
+                    .as_deref()
+                    .map(str::trim)
+                    .filter(|to| !to.is_empty())
+                    .map(|to| stake_pubkey(to, "接收地址"))
+                    .transpose()?,
+                lamports: amount.as_ref().map(sol_to_lamports).transpose()?,
+            },
+            Self::Split {
+                stake_account,
+                amount,
+                seed,
+            } => StakeAction::Split {
+                stake_account: stake_pubkey(stake_account, "质押账户地址")?,
+                lamports: sol_to_lamports(amount)?,
+                seed: stake_seed(seed.as_deref()),
+            },
+            Self::Merge {
+                destination,
+                source,
+            } => StakeAction::Merge {
+                destination: stake_pubkey(destination, "目标质押账户地址")?,
+                source: stake_pubkey(source, "来源质押账户地址")?,
+            },
+            Self::Authorize {
+                stake_account,
+                new_authority,
+                authority,
+            } => StakeAction::Authorize {
+                stake_account: stake_pubkey(stake_account, "质押账户地址")?,
+                new_authority: stake_pubkey(new_authority, "新权限地址")?,
+                authority: StakeAuthority::parse(authority).ok_or_else(|| ApiError {
+                    message: "authority 必须是 staker 或 withdrawer".to_string(),
+                })?,
+            },
         })
-        .collect()
+    }
 }
 
-fn confirm_create_buffer_attempt_still_absent(
-    client: &RpcClient,
-    buffer_address: &Pubkey,
-    min_context_slot: u64,
-) -> Result<(), ApiError> {
-    if get_finalized_recovery_account(client, buffer_address, min_context_slot)?.is_some() {
-        return Err(deployment_journal_error(format!(
-            "Buffer {} 已在签名历史 slot {} 之后出现；保持 attempt 活动状态并中止本次恢复",
-            buffer_address, min_context_slot
-        )));
-    }
-    Ok(())
+#[derive(Deserialize)]
+struct StakeActionExecuteRequest {
+    #[serde(flatten)]
+    wallet: WalletAuthRequest,
+    #[serde(flatten)]
+    action: StakeActionRequest,
+    #[serde(default)]
+    network: Option<String>,
 }
 
-fn validate_write_attempt_recovery_snapshot(
-    previous_plan: &program_deploy::BufferWritePlan,
-    current_plan: &program_deploy::BufferWritePlan,
-    chunk_index: usize,
-) -> Result<(), ApiError> {
-    if previous_plan != current_plan {
-        return Err(deployment_journal_error(format!(
-            "Buffer finalized 写入计划在 chunk {} 过期判定后已变化；保持 attempt 活动状态并中止本次恢复",
-            chunk_index
-        )));
-    }
-    if !current_plan.pending_chunk_indexes.contains(&chunk_index) {
-        return Err(deployment_journal_error(format!(
-            "Buffer chunk {} 已不再缺失；保持 attempt 活动状态并中止本次恢复",
-            chunk_index
-        )));
-    }
-    Ok(())
+#[derive(Serialize)]
+struct StakeActionResponse {
+    signature: String,
+    description: String,
+    /// Account created by create or split
+    stake_account: Option<String>,
+    lamports: u64,
+    warnings: Vec<String>,
+    network: String,
+    status: String,
 }
 
-#[allow(clippy::too_many_arguments)]
-fn confirm_write_attempt_still_absent(
-    client: &RpcClient,
-    buffer_address: &Pubkey,
-    expected_authority: &Pubkey,
-    expected_program: &[u8],
-    previous_plan: &program_deploy::BufferWritePlan,
-    chunk_index: usize,
-    min_context_slot: u64,
-) -> Result<(), ApiError> {
-    let account = get_finalized_recovery_account(client, buffer_address, min_context_slot)?
-        .ok_or_else(|| {
-            deployment_journal_error(format!(
-                "Buffer {} 在 write attempt 二次回读时不存在；拒绝自动重签 chunk {}",
-                buffer_address, chunk_index
-            ))
+async fn stake_action(
+    Json(req): Json<StakeActionExecuteRequest>,
+) -> Result<Json<StakeActionResponse>, ApiError> {
+    let action = req.action.to_action()?;
+    let keypair = req.wallet.keypair()?;
+    let (client, network) = rpc_client_for(req.network.as_deref())?;
+    let client = SolanaClient::new(client.url());
+    let plan = client
+        .plan_stake_action(&keypair.pubkey(), &action)
+        .map_err(|e| ApiError {
+            message: format!("质押操作校验失败: {}", e),
         })?;
-    let current_plan = program_deploy::verify_resume_buffer(
-        expected_authority,
-        expected_program,
-        PROGRAM_WRITE_CHUNK_BYTES,
-        &account.owner,
-        account.executable,
-        &account.data,
-    )
-    .map_err(|error| {
-        deployment_journal_error(format!(
-            "Buffer {} 在 write attempt 二次回读时校验失败: {error}",
-            buffer_address
-        ))
-    })?;
-    validate_write_attempt_recovery_snapshot(previous_plan, &current_plan, chunk_index)
+    let signature = client
+        .send_stake_plan(&keypair, &plan)
+        .map_err(|e| ApiError {
+            message: format!("质押交易失败: {}", e),
+        })?;
+
+    Ok(Json(StakeActionResponse {
+        signature: signature.to_string(),
+        description: plan.description,
+        stake_account: plan.new_stake_account.map(|stake| stake.to_string()),
+        lamports: plan.lamports,
+        warnings: plan.warnings,
+        network,
+        status: "success".to_string(),
+    }))
 }
 
-fn confirm_deploy_attempt_still_absent(
-    client: &RpcClient,
-    program_id: &Pubkey,
-    programdata_address: &Pubkey,
-    min_context_slot: u64,
-) -> Result<(), ApiError> {
-    let accounts = get_finalized_recovery_accounts(
-        client,
-        &[*program_id, *programdata_address],
-        min_context_slot,
-    )?;
-    if accounts.iter().any(Option::is_some) {
-        return Err(deployment_journal_error(format!(
-            "Program {} 或 ProgramData {} 已在签名历史 slot {} 之后出现；保持 attempt 活动状态并中止本次恢复",
-            program_id, programdata_address, min_context_slot
-        )));
-    }
-    Ok(())
+// ============= Token Operations (13) =============
+
+#[derive(Deserialize)]
+struct TokenMintInfoRequest {
+    mint: String,
+    #[serde(default)]
+    network: Option<String>,
 }
 
-fn deployment_attempt_is_active(status: &str) -> bool {
-    matches!(
-        status,
-        wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_SIGNED
-            | wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_CONFIRMED
-            | wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_REQUIRES_RECONCILIATION
-    )
+#[derive(Serialize)]
+struct TokenMintInfoResponse {
+    mint: String,
+    network: String,
+    decimals: u8,
+    supply: String,
+    ui_amount_string: String,
 }
 
-fn deployment_attempt_is_blocking_terminal(status: &str) -> bool {
-    matches!(
-        status,
-        wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_FINALIZED
-            | wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_FINALIZED_FAILED
-    )
+async fn token_mint_info(
+    Json(req): Json<TokenMintInfoRequest>,
+) -> Result<Json<TokenMintInfoResponse>, ApiError> {
+    let mint = Pubkey::from_str(req.mint.trim()).map_err(|_| ApiError {
+        message: "无效的 Token Mint 地址".to_string(),
+    })?;
+    let (client, network) = rpc_client_for(req.network.as_deref())?;
+    let supply = token_mint_info_from_rpc(&client, &mint)?;
+    let ui_amount_string = supply.real_number_string_trimmed();
+
+    Ok(Json(TokenMintInfoResponse {
+        mint: mint.to_string(),
+        network,
+        decimals: supply.decimals,
+        supply: supply.amount,
+        ui_amount_string,
+    }))
 }
 
-fn new_program_deployment_attempt(
-    record: &wallet_store::ProgramDeploymentRecord,
-    stage: &str,
-    chunk_index: Option<usize>,
-    signature: &Signature,
-    last_valid_block_height: u64,
-) -> wallet_store::ProgramDeploymentAttemptRecord {
-    wallet_store::ProgramDeploymentAttemptRecord {
-        genesis_hash: record.genesis_hash.clone(),
-        program_id: record.program_id.clone(),
-        stage: stage.to_string(),
-        buffer_address: record.buffer_address.clone(),
-        chunk_index,
-        signature: signature.to_string(),
-        last_valid_block_height,
-        status: wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_SIGNED.to_string(),
-        created_at: 0,
-        updated_at: 0,
+// 13. Transfer SPL Token
+#[derive(Deserialize)]
+struct TransferTokenRequest {
+    #[serde(flatten)]
+    wallet: WalletAuthRequest,
+    to_address: String,
+    mint: String,
+    amount: DecimalAmount,
+    #[allow(dead_code)]
+    #[serde(default)]
+    decimals: Option<u8>,
+    /// SPL Memo attached to the transfer, e.g. an exchange deposit tag
+    #[serde(default)]
+    memo: Option<String>,
+    #[serde(default)]
+    network: Option<String>,
+}
+#[derive(Serialize)]
+struct TransferTokenResponse {
+    signature: String,
+    status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    memo: Option<String>,
+    /// Token-2022 transfer fee withheld from the amount, in base units
+    fee: String,
+    /// Amount the recipient receives, in base units
+    net_amount: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    warnings: Vec<String>,
+}
+
+fn plan_desktop_token_transfer(
+    client: &SolanaClient,
+    from: &Pubkey,
+    to_address: &str,
+    mint: &str,
+    amount: &DecimalAmount,
+    memo: Option<&str>,
+) -> Result<TokenTransferPlan, ApiError> {
+    let memo = transfer_memo(memo)?;
+    let to_pubkey = Pubkey::from_str(to_address).map_err(|_| ApiError {
+        message: "无效的接收地址".to_string(),
+    })?;
+    let mint = Pubkey::from_str(mint).map_err(|_| ApiError {
+        message: "无效的mint地址".to_string(),
+    })?;
+
+    // Always trust on-chain mint decimals instead of user-submitted decimals.
+    let mint_info = client.get_mint_info(&mint).map_err(|e| ApiError {
+        message: format!("查询 Token Mint 信息失败: {}", e),
+    })?;
+    if mint_info.decimals > MAX_TOKEN_DECIMALS {
+        return Err(ApiError {
+            message: format!("Token 精度不能超过 {}", MAX_TOKEN_DECIMALS),
+        });
     }
+    let token_amount = token_amount_to_raw(amount, mint_info.decimals)?;
+
+    client
+        .plan_token_transfer(from, &to_pubkey, &mint, token_amount)
+        .and_then(|plan| plan.with_memo(memo.as_deref()))
+        .map_err(|e| ApiError {
+            message: format!("无法转账: {}", e),
+        })
 }
 
-fn validate_program_deployment_attempt_record(
-    record: &wallet_store::ProgramDeploymentRecord,
-    attempt: &wallet_store::ProgramDeploymentAttemptRecord,
-) -> Result<(), ApiError> {
-    let shape_is_valid = match attempt.stage.as_str() {
-        wallet_store::PROGRAM_DEPLOYMENT_STAGE_CREATE_BUFFER
-        | wallet_store::PROGRAM_DEPLOYMENT_STAGE_DEPLOY => attempt.chunk_index.is_none(),
-        wallet_store::PROGRAM_DEPLOYMENT_STAGE_WRITE => attempt.chunk_index.is_some(),
-        _ => false,
-    };
-    let status_is_valid = deployment_attempt_is_active(&attempt.status)
-        || deployment_attempt_is_blocking_terminal(&attempt.status)
-        || attempt.status == wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_EXPIRED_ABSENT;
-    let signature_is_valid = Signature::from_str(&attempt.signature).is_ok_and(|signature| {
-        signature != Signature::default() && signature.to_string() == attempt.signature
-    });
-    let buffer_is_valid = Pubkey::from_str(&attempt.buffer_address).is_ok_and(|buffer| {
-        buffer != Pubkey::default() && buffer.to_string() == attempt.buffer_address
-    });
-    if attempt.genesis_hash != record.genesis_hash
-        || attempt.program_id != record.program_id
-        || !shape_is_valid
-        || !status_is_valid
-        || (attempt.buffer_address != record.buffer_address
-            && !(attempt.status == wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_EXPIRED_ABSENT
-                && attempt.stage == wallet_store::PROGRAM_DEPLOYMENT_STAGE_CREATE_BUFFER))
-        || !signature_is_valid
-        || !buffer_is_valid
-        || attempt.last_valid_block_height == 0
-        || attempt.created_at == 0
-        || attempt.updated_at < attempt.created_at
-    {
-        return Err(deployment_journal_error(format!(
-            "Program {} 包含无效或不一致的 attempt 证据 {}，拒绝继续",
-            record.program_id, attempt.signature
-        )));
+async fn transfer_token(
+    Json(req): Json<TransferTokenRequest>,
+) -> Result<Json<TransferTokenResponse>, ApiError> {
+    let keypair = req.wallet.keypair()?;
+
+    let rpc_url = get_rpc_url(req.network.as_deref())?;
+    let client = SolanaClient::new(rpc_url.to_string());
+    let plan = plan_desktop_token_transfer(
+        &client,
+        &keypair.pubkey(),
+        &req.to_address,
+        &req.mint,
+        &req.amount,
+        req.memo.as_deref(),
+    )?;
+
+    let signature = client
+        .send_token_transfer(&keypair, &plan)
+        .map_err(|e| ApiError {
+            message: format!("转账失败: {}", e),
+        })?;
+    if let Ok(recipient) = Pubkey::from_str(&req.to_address) {
+        record_recipient_send(&network_name(req.network.as_deref()), &recipient);
     }
-    Ok(())
+
+    Ok(Json(TransferTokenResponse {
+        signature: signature.to_string(),
+        status: "success".to_string(),
+        memo: plan.memo,
+        fee: plan.fee.to_string(),
+        net_amount: plan.net_amount.to_string(),
+        warnings: plan.warnings,
+    }))
 }
 
-fn begin_deployment_attempt(
-    journal: &mut Option<wallet_store::ProgramDeploymentRecord>,
-    attempt: wallet_store::ProgramDeploymentAttemptRecord,
-    journal_status: &str,
-    completed_writes: usize,
-) -> Result<(), ApiError> {
-    let current = journal.as_ref().ok_or_else(|| ApiError {
-        message: "部署 journal 不存在，拒绝记录签名 attempt".to_string(),
-    })?;
-    let updated = wallet_store::begin_program_deployment_attempt(
-        current,
-        attempt,
-        journal_status,
-        completed_writes,
-    )
-    .map_err(|message| ApiError { message })?;
-    *journal = Some(updated);
-    Ok(())
+// 13b. Preview SPL Token transfer (fees and Token-2022 extension checks)
+#[derive(Deserialize)]
+struct TransferTokenPreviewRequest {
+    from_address: String,
+    to_address: String,
+    mint: String,
+    amount: DecimalAmount,
+    #[serde(default)]
+    memo: Option<String>,
+    #[serde(default)]
+    network: Option<String>,
 }
 
-fn transition_deployment_attempt(
-    journal: &mut Option<wallet_store::ProgramDeploymentRecord>,
-    signature: &str,
-    expected_attempt_status: &str,
-    next_attempt_status: &str,
-    journal_status: &str,
-    completed_writes: usize,
-) -> Result<(), ApiError> {
-    let current = journal.as_ref().ok_or_else(|| ApiError {
-        message: "部署 journal 不存在，拒绝迁移签名 attempt".to_string(),
-    })?;
-    let updated = wallet_store::transition_program_deployment_attempt(
-        current,
-        signature,
-        expected_attempt_status,
-        next_attempt_status,
-        journal_status,
-        completed_writes,
-    )
-    .map_err(|message| ApiError { message })?;
-    *journal = Some(updated);
-    Ok(())
+#[derive(Serialize)]
+struct TransferTokenPreviewResponse {
+    mint: String,
+    token_program: String,
+    decimals: u8,
+    amount: String,
+    fee: String,
+    net_amount: String,
+    create_recipient_account: bool,
+    transfer_hook_program: Option<String>,
+    memo: Option<String>,
+    recipient: RecipientCheckView,
+    warnings: Vec<String>,
 }
 
-fn reconcile_absent_deployment_attempt<F>(
-    client: &RpcClient,
-    journal: &mut Option<wallet_store::ProgramDeploymentRecord>,
-    attempt: &wallet_store::ProgramDeploymentAttemptRecord,
-    journal_status: &str,
-    completed_writes: usize,
-    confirm_absent: F,
-) -> Result<bool, ApiError>
-where
-    F: FnOnce(&RpcClient, u64) -> Result<(), ApiError>,
-{
-    let stage_context = attempt
-        .chunk_index
-        .map(|index| format!("{} chunk {}", attempt.stage, index))
-        .unwrap_or_else(|| attempt.stage.clone());
-    let decision = gate_expired_absent_with(
-        inspect_deployment_attempt_expiry(client, attempt)?,
-        |min_context_slot| confirm_absent(client, min_context_slot),
+async fn transfer_token_preview(
+    Json(req): Json<TransferTokenPreviewRequest>,
+) -> Result<Json<TransferTokenPreviewResponse>, ApiError> {
+    let from = Pubkey::from_str(&req.from_address).map_err(|_| ApiError {
+        message: "无效的发送地址".to_string(),
+    })?;
+    let rpc_url = get_rpc_url(req.network.as_deref())?;
+    let client = SolanaClient::new(rpc_url);
+    let plan = plan_desktop_token_transfer(
+        &client,
+        &from,
+        &req.to_address,
+        &req.mint,
+        &req.amount,
+        req.memo.as_deref(),
     )?;
-    match decision {
-        DeploymentAttemptExpiryDecision::ExpiredAbsent { .. } => {
-            transition_deployment_attempt(
-                journal,
-                &attempt.signature,
-                &attempt.status,
-                wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_EXPIRED_ABSENT,
-                journal_status,
-                completed_writes,
-            )?;
-            Ok(true)
-        }
-        DeploymentAttemptExpiryDecision::FinalizedSucceeded => {
-            transition_deployment_attempt(
-                journal,
-                &attempt.signature,
-                &attempt.status,
-                wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_FINALIZED,
-                journal_status,
-                completed_writes,
-            )?;
-            Err(deployment_journal_error(format!(
-                "{} 签名 {} 已 finalized 成功，但对应业务状态缺失；已持久化冲突并禁止自动重签",
-                stage_context, attempt.signature
-            )))
-        }
-        DeploymentAttemptExpiryDecision::FinalizedFailed(error) => {
-            transition_deployment_attempt(
-                journal,
-                &attempt.signature,
-                &attempt.status,
-                wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_FINALIZED_FAILED,
-                journal_status,
-                completed_writes,
-            )?;
-            Err(deployment_journal_error(format!(
-                "{} 签名 {} 已 finalized 失败（{}）；已持久化终态，禁止自动重签",
-                stage_context, attempt.signature, error
-            )))
-        }
-        DeploymentAttemptExpiryDecision::NotExpired => Err(deployment_journal_error(format!(
-            "{} 签名 {} 尚未越过 last valid block height {}，不得自动重签",
-            stage_context, attempt.signature, attempt.last_valid_block_height
-        ))),
-        DeploymentAttemptExpiryDecision::HistoryContextTooOld {
-            expiry_observation_slot,
-            history_context_slot,
-        } => Err(deployment_journal_error(format!(
-            "{} 签名 {} 的历史上下文 slot {} 早于过期观测 slot {}；RPC 后端视图不一致，拒绝自动重签",
-            stage_context, attempt.signature, history_context_slot, expiry_observation_slot
-        ))),
-        DeploymentAttemptExpiryDecision::SeenButNotFinalized => {
-            Err(deployment_journal_error(format!(
-                "{} 签名 {} 仍可在历史中观察到但尚未 finalized，不得自动重签",
-                stage_context, attempt.signature
-            )))
-        }
-    }
-}
-
-fn deployment_journal_error(message: impl Into<String>) -> ApiError {
-    ApiError {
-        message: format!("部署 journal 冲突: {}", message.into()),
-    }
+    let to_pubkey = Pubkey::from_str(&req.to_address).map_err(|_| ApiError {
+        message: "无效的接收地址".to_string(),
+    })?;
+    let recipient = recipient_check_view(
+        &network_name(req.network.as_deref()),
+        &to_pubkey,
+        Some(&plan.mint),
+    )?;
+    let mut warnings = recipient.warnings.clone();
+    warnings.extend(plan.warnings);
+
+    Ok(Json(TransferTokenPreviewResponse {
+        mint: plan.mint.to_string(),
+        token_program: plan.token_program.to_string(),
+        decimals: plan.decimals,
+        amount: plan.amount.to_string(),
+        fee: plan.fee.to_string(),
+        net_amount: plan.net_amount.to_string(),
+        create_recipient_account: plan.create_recipient_account,
+        transfer_hook_program: plan
+            .transfer_hook_program
+            .map(|program| program.to_string()),
+        memo: plan.memo,
+        recipient,
+        warnings,
+    }))
 }
 
-type DeploymentReceiptEvidence = (
-    Option<u64>,
-    Option<u32>,
-    Vec<wallet_store::ProgramDeploymentAttemptRecord>,
-);
+// ============= Nonce Operations (14) =============
 
-fn load_deployment_receipt_evidence(
-    journal: Option<&wallet_store::ProgramDeploymentRecord>,
-) -> Result<DeploymentReceiptEvidence, ApiError> {
-    let Some(record) = journal else {
-        return Ok((None, None, Vec::new()));
-    };
-    let attempts =
-        wallet_store::load_program_deployment_attempts(&record.genesis_hash, &record.program_id)
-            .map_err(|message| ApiError { message })?;
-    for attempt in &attempts {
-        validate_program_deployment_attempt_record(record, attempt)?;
-    }
-    Ok((
-        Some(record.revision),
-        Some(record.attempt_evidence_version),
-        attempts,
-    ))
+// 14. Create Nonce Account
+#[derive(Deserialize)]
+struct CreateNonceAccountRequest {
+    #[serde(flatten)]
+    wallet: WalletAuthRequest,
+    #[serde(default)]
+    network: Option<String>,
+    #[serde(default)]
+    count: Option<u8>,
+}
+#[derive(Serialize)]
+struct CreatedNonceAccount {
+    nonce_account: String,
+    signature: String,
+    created_at: u64,
+}
+#[derive(Serialize)]
+struct CreateNonceAccountResponse {
+    nonce_accounts: Vec<CreatedNonceAccount>,
+    nonce_account: String,
+    signature: String,
+    count: usize,
+    status: String,
 }
 
-#[allow(clippy::too_many_arguments)]
-fn validate_deployment_journal_binding(
-    record: &wallet_store::ProgramDeploymentRecord,
-    genesis_hash: &str,
-    program_id: &Pubkey,
-    program_sha256: &str,
-    program_len: usize,
-    max_data_len: usize,
-    upgrade_authority: &Pubkey,
-    requested_buffer: Option<&Pubkey>,
-) -> Result<Pubkey, ApiError> {
-    let expected_program_id = program_id.to_string();
-    let expected_upgrade_authority = upgrade_authority.to_string();
-    if record.genesis_hash != genesis_hash
-        || record.program_id != expected_program_id
-        || record.program_sha256 != program_sha256
-        || record.program_len != program_len
-        || record.max_data_len != max_data_len
-        || record.upgrade_authority != expected_upgrade_authority
-    {
-        return Err(deployment_journal_error(format!(
-            "Program {} 已有不同的部署意图，拒绝复用或覆盖；已记录 artifact={} len={} max_len={} upgrade_authority={}",
-            program_id,
-            record.program_sha256,
-            record.program_len,
-            record.max_data_len,
-            record.upgrade_authority,
-        )));
+async fn create_nonce_account(
+    Json(req): Json<CreateNonceAccountRequest>,
+) -> Result<Json<CreateNonceAccountResponse>, ApiError> {
+    let keypair = req.wallet.keypair()?;
+    let owner = keypair.pubkey().to_string();
+    let wallet_id = req
+        .wallet
+        .wallet_id
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty());
+    let count = req.count.unwrap_or(1);
+    if count == 0 || count > MAX_NONCE_BATCH_COUNT {
+        return Err(ApiError {
+            message: format!("Nonce 创建数量必须在 1-{} 之间", MAX_NONCE_BATCH_COUNT),
+        });
     }
 
-    let stored_buffer = Pubkey::from_str(record.buffer_address.trim()).map_err(|_| {
-        deployment_journal_error(format!(
-            "Program {} 已记录无效 Buffer 地址 {}",
-            program_id, record.buffer_address
-        ))
-    })?;
-    if stored_buffer == Pubkey::default() || record.buffer_address != stored_buffer.to_string() {
-        return Err(deployment_journal_error(format!(
-            "Program {} 已记录非 canonical Buffer 地址 {}",
-            program_id, record.buffer_address
-        )));
-    }
-    if record.created_at == 0 || record.updated_at < record.created_at {
-        return Err(deployment_journal_error(format!(
-            "Program {} 的部署 journal 时间戳无效",
-            program_id
-        )));
-    }
-    if requested_buffer.is_some_and(|requested| requested != &stored_buffer) {
-        return Err(deployment_journal_error(format!(
-            "请求恢复的 Buffer 与已记录 Buffer {} 不一致",
-            stored_buffer
-        )));
+    let selector = rpc_selector(req.network.as_deref())?;
+    let rpc_url = selector.url;
+    let network = selector.network;
+    let client = SolanaClient::new(rpc_url.to_string());
+
+    let mut nonce_accounts = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        let (nonce_account, signature) =
+            client
+                .create_nonce_account(&keypair)
+                .map_err(|e| ApiError {
+                    message: format!("创建失败: {}", e),
+                })?;
+        let record = wallet_store::add_nonce_account(
+            wallet_id,
+            &owner,
+            &network,
+            &nonce_account.to_string(),
+            &signature.to_string(),
+        )
+        .map_err(|message| ApiError { message })?;
+        nonce_accounts.push(CreatedNonceAccount {
+            nonce_account: record.nonce_account,
+            signature: record.signature,
+            created_at: record.created_at,
+        });
     }
-    Ok(stored_buffer)
-}
+    let first = nonce_accounts.first().ok_or_else(|| ApiError {
+        message: "未创建 Nonce 账户".to_string(),
+    })?;
 
-fn deployment_status_is_known(status: &str) -> bool {
-    matches!(
-        status,
-        DEPLOYMENT_STATUS_CREATE_BUFFER_SIGNED
-            | DEPLOYMENT_STATUS_CREATE_BUFFER_RECONCILE
-            | DEPLOYMENT_STATUS_BUFFER_READY
-            | DEPLOYMENT_STATUS_WRITE_SIGNED
-            | DEPLOYMENT_STATUS_WRITE_CONFIRMED
-            | DEPLOYMENT_STATUS_WRITE_RECONCILE
-            | DEPLOYMENT_STATUS_BUFFER_FINALIZED
-            | DEPLOYMENT_STATUS_DEPLOY_SIGNED
-            | DEPLOYMENT_STATUS_DEPLOY_RECONCILE
-            | DEPLOYMENT_STATUS_DEPLOY_FINALIZED
-            | DEPLOYMENT_STATUS_FINALIZED
-    )
+    Ok(Json(CreateNonceAccountResponse {
+        nonce_account: first.nonce_account.clone(),
+        signature: first.signature.clone(),
+        count: nonce_accounts.len(),
+        nonce_accounts,
+        status: "success".to_string(),
+    }))
 }
 
-#[allow(clippy::too_many_arguments)]
-fn update_deployment_journal(
-    record: &wallet_store::ProgramDeploymentRecord,
-    status: &str,
-    create_signature: Option<&str>,
-    create_last_valid_block_height: Option<u64>,
-    last_write_signature: Option<&str>,
-    last_write_chunk_index: Option<usize>,
-    last_write_last_valid_block_height: Option<u64>,
-    completed_writes: usize,
-    deploy_signature: Option<&str>,
-    deploy_last_valid_block_height: Option<u64>,
-) -> Result<wallet_store::ProgramDeploymentRecord, ApiError> {
-    wallet_store::update_program_deployment_progress(
-        record,
-        status,
-        create_signature,
-        create_last_valid_block_height,
-        last_write_signature,
-        last_write_chunk_index,
-        last_write_last_valid_block_height,
-        completed_writes,
-        deploy_signature,
-        deploy_last_valid_block_height,
-    )
-    .map_err(|message| ApiError { message })
+#[derive(Deserialize)]
+struct ListNonceAccountsRequest {
+    owner: String,
+    #[serde(default)]
+    network: Option<String>,
 }
 
-#[allow(clippy::too_many_arguments)]
-fn transition_deployment_journal(
-    journal: &mut Option<wallet_store::ProgramDeploymentRecord>,
-    status: &str,
-    create_signature: Option<&str>,
-    create_last_valid_block_height: Option<u64>,
-    last_write_signature: Option<&str>,
-    last_write_chunk_index: Option<usize>,
-    last_write_last_valid_block_height: Option<u64>,
-    completed_writes: usize,
-    deploy_signature: Option<&str>,
-    deploy_last_valid_block_height: Option<u64>,
-) -> Result<(), ApiError> {
-    let current = journal.as_ref().ok_or_else(|| ApiError {
-        message: "部署 journal 不存在，拒绝更新部署状态".to_string(),
+#[derive(Serialize)]
+struct ListNonceAccountsResponse {
+    owner: String,
+    network: String,
+    nonce_accounts: Vec<wallet_store::NonceAccountRecord>,
+}
+
+async fn list_nonce_accounts(
+    Json(req): Json<ListNonceAccountsRequest>,
+) -> Result<Json<ListNonceAccountsResponse>, ApiError> {
+    let owner = Pubkey::from_str(&req.owner).map_err(|_| ApiError {
+        message: "无效的钱包地址".to_string(),
     })?;
-    let updated = update_deployment_journal(
-        current,
-        status,
-        create_signature,
-        create_last_valid_block_height,
-        last_write_signature,
-        last_write_chunk_index,
-        last_write_last_valid_block_height,
-        completed_writes,
-        deploy_signature,
-        deploy_last_valid_block_height,
-    )?;
-    *journal = Some(updated);
-    Ok(())
+    let network = network_name(req.network.as_deref()).to_string();
+    let nonce_accounts = wallet_store::list_nonce_accounts(&owner.to_string(), &network)
+        .map_err(|message| ApiError { message })?;
+    Ok(Json(ListNonceAccountsResponse {
+        owner: owner.to_string(),
+        network,
+        nonce_accounts,
+    }))
 }
 
-#[cfg(test)]
-mod deployment_journal_tests {
-    use super::*;
+// ============= Program Deployment =============
 
-    #[test]
-    fn program_upgrade_requires_squads_member() {
-        let signer = Pubkey::new_unique();
-        let multisig = SquadsMultisig {
-            create_key: Pubkey::new_unique(),
-            config_authority: Pubkey::default(),
-            threshold: 1,
-            time_lock: 0,
-            transaction_index: 0,
-            stale_transaction_index: 0,
-            rent_collector: None,
-            bump: 0,
-            members: vec![SquadsMember {
-                key: signer,
-                permissions: squads_v4::Permissions::all(),
-            }],
+const DEPLOYMENT_STATUS_CREATE_BUFFER_SIGNED: &str = "create_buffer_signed";
+const DEPLOYMENT_STATUS_CREATE_BUFFER_RECONCILE: &str = "create_buffer_requires_reconciliation";
+const DEPLOYMENT_STATUS_BUFFER_READY: &str = "buffer_ready";
+const DEPLOYMENT_STATUS_WRITE_SIGNED: &str = "write_signed";
+const DEPLOYMENT_STATUS_WRITE_CONFIRMED: &str = "write_confirmed";
+const DEPLOYMENT_STATUS_WRITE_RECONCILE: &str = "write_requires_reconciliation";
+const DEPLOYMENT_STATUS_BUFFER_FINALIZED: &str = "buffer_finalized";
+const DEPLOYMENT_STATUS_DEPLOY_SIGNED: &str = "deploy_signed";
+const DEPLOYMENT_STATUS_DEPLOY_RECONCILE: &str = "deploy_requires_reconciliation";
+const DEPLOYMENT_STATUS_DEPLOY_FINALIZED: &str = "deploy_finalized_pending_readback";
+const DEPLOYMENT_STATUS_FINALIZED: &str = "finalized";
+
+#[derive(Debug, PartialEq, Eq)]
+enum DeploymentAttemptExpiryDecision {
+    NotExpired,
+    ExpiredAbsent {
+        min_context_slot: u64,
+    },
+    HistoryContextTooOld {
+        expiry_observation_slot: u64,
+        history_context_slot: u64,
+    },
+    SeenButNotFinalized,
+    FinalizedSucceeded,
+    FinalizedFailed(String),
+}
+
+fn classify_deployment_attempt_expiry(
+    finalized_block_height: u64,
+    last_valid_block_height: u64,
+    historical_status: Option<&TransactionStatus>,
+    history_context_slot: u64,
+    expiry_observation_slot: u64,
+) -> DeploymentAttemptExpiryDecision {
+    if finalized_block_height <= last_valid_block_height {
+        return DeploymentAttemptExpiryDecision::NotExpired;
+    }
+    if history_context_slot < expiry_observation_slot {
+        return DeploymentAttemptExpiryDecision::HistoryContextTooOld {
+            expiry_observation_slot,
+            history_context_slot,
         };
-        assert!(require_squads_member(&multisig, &signer).is_ok());
-        assert!(require_squads_member(&multisig, &Pubkey::new_unique()).is_err());
     }
+    let Some(status) = historical_status else {
+        return DeploymentAttemptExpiryDecision::ExpiredAbsent {
+            min_context_slot: history_context_slot,
+        };
+    };
+    if !status.satisfies_commitment(CommitmentConfig::finalized()) {
+        return DeploymentAttemptExpiryDecision::SeenButNotFinalized;
+    }
+    match status.err.as_ref() {
+        Some(error) => DeploymentAttemptExpiryDecision::FinalizedFailed(format!("{error:?}")),
+        None => DeploymentAttemptExpiryDecision::FinalizedSucceeded,
+    }
+}
 
-    #[test]
-    fn parses_programdata_upgrade_authority() {
-        let authority = Pubkey::new_unique();
-        let mut data = bincode::serialize(&UpgradeableLoaderState::ProgramData {
-            slot: 42,
-            upgrade_authority_address: Some(authority),
-        })
-        .unwrap();
-        data.resize(UpgradeableLoaderState::size_of_programdata_metadata(), 0);
-        assert_eq!(
-            loader_upgrade_authority_from_programdata(&data).unwrap(),
-            authority
-        );
-
-        let mut immutable = bincode::serialize(&UpgradeableLoaderState::ProgramData {
-            slot: 42,
-            upgrade_authority_address: None,
-        })
-        .unwrap();
-        immutable.resize(UpgradeableLoaderState::size_of_programdata_metadata(), 0);
-        assert!(loader_upgrade_authority_from_programdata(&immutable).is_err());
+fn inspect_deployment_attempt_expiry(
+    client: &RpcClient,
+    attempt: &wallet_store::ProgramDeploymentAttemptRecord,
+) -> Result<DeploymentAttemptExpiryDecision, ApiError> {
+    let finalized_epoch_info = client
+        .get_epoch_info_with_commitment(CommitmentConfig::finalized())
+        .map_err(|error| {
+            deployment_journal_error(format!(
+                "读取 finalized epoch info 失败，无法判定签名 {} 是否过期: {error}",
+                attempt.signature
+            ))
+        })?;
+    let signature = Signature::from_str(attempt.signature.trim()).map_err(|_| {
+        deployment_journal_error(format!(
+            "attempt {} 包含无效签名，拒绝自动恢复",
+            attempt.signature
+        ))
+    })?;
+    if finalized_epoch_info.block_height <= attempt.last_valid_block_height {
+        return Ok(DeploymentAttemptExpiryDecision::NotExpired);
+    }
+    let response = client
+        .get_signature_statuses_with_history(&[signature])
+        .map_err(|error| {
+            deployment_journal_error(format!(
+                "查询签名 {} 的完整历史失败，拒绝自动重试: {error}",
+                attempt.signature
+            ))
+        })?;
+    if response.value.len() != 1 {
+        return Err(deployment_journal_error(format!(
+            "签名 {} 的历史响应数量异常，拒绝自动重试",
+            attempt.signature
+        )));
     }
+    Ok(classify_deployment_attempt_expiry(
+        finalized_epoch_info.block_height,
+        attempt.last_valid_block_height,
+        response.value.first().and_then(Option::as_ref),
+        response.context.slot,
+        finalized_epoch_info.absolute_slot,
+    ))
+}
 
-    fn record(
-        program_id: &Pubkey,
-        authority: &Pubkey,
-        buffer: &Pubkey,
-    ) -> wallet_store::ProgramDeploymentRecord {
-        wallet_store::ProgramDeploymentRecord {
-            genesis_hash: "devnet-genesis".to_string(),
-            program_id: program_id.to_string(),
-            program_sha256: "11".repeat(32),
-            program_len: 1024,
-            max_data_len: 2048,
-            upgrade_authority: authority.to_string(),
-            buffer_address: buffer.to_string(),
-            status: DEPLOYMENT_STATUS_BUFFER_READY.to_string(),
-            create_signature: Some(Signature::new_unique().to_string()),
-            create_last_valid_block_height: Some(42),
-            last_write_signature: None,
-            last_write_chunk_index: None,
-            last_write_last_valid_block_height: None,
-            completed_writes: 0,
-            deploy_signature: None,
-            deploy_last_valid_block_height: None,
-            attempt_evidence_version: wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_EVIDENCE_VERSION,
-            revision: 0,
-            created_at: 1,
-            updated_at: 1,
+fn gate_expired_absent_with<F>(
+    decision: DeploymentAttemptExpiryDecision,
+    confirm_absent: F,
+) -> Result<DeploymentAttemptExpiryDecision, ApiError>
+where
+    F: FnOnce(u64) -> Result<(), ApiError>,
+{
+    match decision {
+        DeploymentAttemptExpiryDecision::ExpiredAbsent { min_context_slot } => {
+            confirm_absent(min_context_slot)?;
+            Ok(DeploymentAttemptExpiryDecision::ExpiredAbsent { min_context_slot })
         }
+        other => Ok(other),
     }
+}
 
-    #[test]
-    fn journal_binding_accepts_only_the_recorded_deployment_intent() {
-        let program_id = Pubkey::new_unique();
-        let authority = Pubkey::new_unique();
-        let buffer = Pubkey::new_unique();
-        let record = record(&program_id, &authority, &buffer);
-
-        let stored_buffer = validate_deployment_journal_binding(
-            &record,
-            "devnet-genesis",
-            &program_id,
-            &"11".repeat(32),
-            1024,
-            2048,
-            &authority,
-            Some(&buffer),
-        )
-        .unwrap();
-        assert_eq!(stored_buffer, buffer);
+fn finalized_recovery_account_config(min_context_slot: u64) -> RpcAccountInfoConfig {
+    RpcAccountInfoConfig {
+        encoding: Some(UiAccountEncoding::Base64Zstd),
+        commitment: Some(CommitmentConfig::finalized()),
+        data_slice: None,
+        min_context_slot: Some(min_context_slot),
+    }
+}
 
-        assert!(validate_deployment_journal_binding(
-            &record,
-            "devnet-genesis",
-            &program_id,
-            &"22".repeat(32),
-            1024,
-            2048,
-            &authority,
-            Some(&buffer),
-        )
-        .is_err());
-        assert!(validate_deployment_journal_binding(
-            &record,
-            "devnet-genesis",
-            &program_id,
-            &"11".repeat(32),
-            1024,
-            2048,
-            &authority,
-            Some(&Pubkey::new_unique()),
-        )
-        .is_err());
-        let mut noncanonical_buffer = record.clone();
-        noncanonical_buffer.buffer_address.push(' ');
-        assert!(validate_deployment_journal_binding(
-            &noncanonical_buffer,
-            "devnet-genesis",
-            &program_id,
-            &"11".repeat(32),
-            1024,
-            2048,
-            &authority,
-            None,
-        )
-        .is_err());
-        let mut zero_buffer = record.clone();
-        zero_buffer.buffer_address = Pubkey::default().to_string();
-        assert!(validate_deployment_journal_binding(
-            &zero_buffer,
-            "devnet-genesis",
-            &program_id,
-            &"11".repeat(32),
-            1024,
-            2048,
-            &authority,
-            None,
-        )
-        .is_err());
-        let mut invalid_timestamps = record.clone();
-        invalid_timestamps.updated_at = 0;
-        assert!(validate_deployment_journal_binding(
-            &invalid_timestamps,
-            "devnet-genesis",
-            &program_id,
-            &"11".repeat(32),
-            1024,
-            2048,
-            &authority,
-            None,
-        )
-        .is_err());
-    }
+fn decode_recovery_ui_account(address: &Pubkey, account: UiAccount) -> Result<Account, ApiError> {
+    account.decode().ok_or_else(|| {
+        deployment_journal_error(format!(
+            "finalized 账户 {} 的 Base64Zstd 数据无法解码；拒绝将其视为不存在",
+            address
+        ))
+    })
+}
 
-    #[test]
-    fn journal_status_allowlist_rejects_unknown_states() {
-        assert!(deployment_status_is_known(
-            DEPLOYMENT_STATUS_CREATE_BUFFER_SIGNED
-        ));
-        assert!(deployment_status_is_known(
-            DEPLOYMENT_STATUS_WRITE_CONFIRMED
-        ));
-        assert!(deployment_status_is_known(DEPLOYMENT_STATUS_FINALIZED));
-        assert!(!deployment_status_is_known("retry_everything"));
+fn get_finalized_recovery_account(
+    client: &RpcClient,
+    address: &Pubkey,
+    min_context_slot: u64,
+) -> Result<Option<Account>, ApiError> {
+    let response = client
+        .get_ui_account_with_config(address, finalized_recovery_account_config(min_context_slot))
+        .map_err(|error| {
+            deployment_journal_error(format!(
+                "在最小上下文 slot {} 二次读取 finalized 账户 {} 失败: {error}",
+                min_context_slot, address
+            ))
+        })?;
+    if response.context.slot < min_context_slot {
+        return Err(deployment_journal_error(format!(
+            "finalized 账户 {} 二次回读 slot {} 早于签名历史 slot {}",
+            address, response.context.slot, min_context_slot
+        )));
     }
+    response
+        .value
+        .map(|account| decode_recovery_ui_account(address, account))
+        .transpose()
+}
 
-    #[test]
-    fn expiry_classifier_requires_strict_finalized_height_and_absent_history() {
-        assert_eq!(
-            classify_deployment_attempt_expiry(41, 42, None, 100, 101),
-            DeploymentAttemptExpiryDecision::NotExpired
-        );
-        assert_eq!(
-            classify_deployment_attempt_expiry(42, 42, None, 101, 102),
-            DeploymentAttemptExpiryDecision::NotExpired
-        );
-        assert_eq!(
-            classify_deployment_attempt_expiry(43, 42, None, 102, 102),
-            DeploymentAttemptExpiryDecision::ExpiredAbsent {
-                min_context_slot: 102
-            }
-        );
+fn get_finalized_recovery_accounts(
+    client: &RpcClient,
+    addresses: &[Pubkey],
+    min_context_slot: u64,
+) -> Result<Vec<Option<Account>>, ApiError> {
+    let response = client
+        .get_multiple_ui_accounts_with_config(
+            addresses,
+            finalized_recovery_account_config(min_context_slot),
+        )
+        .map_err(|error| {
+            deployment_journal_error(format!(
+                "在最小上下文 slot {} 二次读取 finalized 账户组失败: {error}",
+                min_context_slot
+            ))
+        })?;
+    if response.context.slot < min_context_slot {
+        return Err(deployment_journal_error(format!(
+            "finalized 账户组二次回读 slot {} 早于签名历史 slot {}",
+            response.context.slot, min_context_slot
+        )));
     }
-
-    #[test]
-    fn expiry_classifier_rejects_history_older_than_expiry_observation() {
-        let decision = classify_deployment_attempt_expiry(43, 42, None, 499, 500);
-        assert_eq!(
-            decision,
-            DeploymentAttemptExpiryDecision::HistoryContextTooOld {
-                expiry_observation_slot: 500,
-                history_context_slot: 499,
-            }
-        );
-        let gated = gate_expired_absent_with(decision, |_| {
-            panic!("stale history must not reach business-state readback")
-        })
-        .unwrap();
-        assert!(matches!(
-            gated,
-            DeploymentAttemptExpiryDecision::HistoryContextTooOld { .. }
-        ));
+    if response.value.len() != addresses.len() {
+        return Err(deployment_journal_error(format!(
+            "finalized 账户组二次回读返回 {} 项，预期 {} 项",
+            response.value.len(),
+            addresses.len()
+        )));
     }
+    response
+        .value
+        .into_iter()
+        .zip(addresses)
+        .map(|(account, address)| {
+            account
+                .map(|account| decode_recovery_ui_account(address, account))
+                .transpose()
+        })
+        .collect()
+}
 
-    #[test]
-    fn expired_absent_gate_passes_exact_history_slot_once() {
-        let observed_slot = std::cell::Cell::new(None);
-        let decision = gate_expired_absent_with(
-            DeploymentAttemptExpiryDecision::ExpiredAbsent {
-                min_context_slot: 987,
-            },
-            |slot| {
-                assert!(observed_slot.replace(Some(slot)).is_none());
-                Ok(())
-            },
-        )
-        .unwrap();
-        assert_eq!(observed_slot.get(), Some(987));
-        assert_eq!(
-            decision,
-            DeploymentAttemptExpiryDecision::ExpiredAbsent {
-                min_context_slot: 987
-            }
-        );
+fn confirm_create_buffer_attempt_still_absent(
+    client: &RpcClient,
+    buffer_address: &Pubkey,
+    min_context_slot: u64,
+) -> Result<(), ApiError> {
+    if get_finalized_recovery_account(client, buffer_address, min_context_slot)?.is_some() {
+        return Err(deployment_journal_error(format!(
+            "Buffer {} 已在签名历史 slot {} 之后出现；保持 attempt 活动状态并中止本次恢复",
+            buffer_address, min_context_slot
+        )));
     }
+    Ok(())
+}
 
-    #[test]
-    fn expired_absent_gate_fails_before_authorizing_transition() {
-        let transition_authorized = std::cell::Cell::new(false);
-        let result = gate_expired_absent_with(
-            DeploymentAttemptExpiryDecision::ExpiredAbsent {
-                min_context_slot: 654,
-            },
-            |slot| {
-                assert_eq!(slot, 654);
-                Err(deployment_journal_error("二次业务回读已前进"))
-            },
-        );
-        if result.is_ok() {
-            transition_authorized.set(true);
-        }
-        assert!(result.is_err());
-        assert!(!transition_authorized.get());
-
-        let non_expired =
-            gate_expired_absent_with(DeploymentAttemptExpiryDecision::NotExpired, |_| {
-                panic!("non-expired decision must not run the absence callback")
-            })
-            .unwrap();
-        assert_eq!(non_expired, DeploymentAttemptExpiryDecision::NotExpired);
+fn validate_write_attempt_recovery_snapshot(
+    previous_plan: &program_deploy::BufferWritePlan,
+    current_plan: &program_deploy::BufferWritePlan,
+    chunk_index: usize,
+) -> Result<(), ApiError> {
+    if previous_plan != current_plan {
+        return Err(deployment_journal_error(format!(
+            "Buffer finalized 写入计划在 chunk {} 过期判定后已变化；保持 attempt 活动状态并中止本次恢复",
+            chunk_index
+        )));
     }
-
-    #[test]
-    fn recovery_account_config_is_finalized_and_history_slot_bound() {
-        let config = finalized_recovery_account_config(321);
-        assert_eq!(config.encoding, Some(UiAccountEncoding::Base64Zstd));
-        assert_eq!(config.commitment, Some(CommitmentConfig::finalized()));
-        assert_eq!(config.min_context_slot, Some(321));
-        assert!(config.data_slice.is_none());
+    if !current_plan.pending_chunk_indexes.contains(&chunk_index) {
+        return Err(deployment_journal_error(format!(
+            "Buffer chunk {} 已不再缺失；保持 attempt 活动状态并中止本次恢复",
+            chunk_index
+        )));
     }
+    Ok(())
+}
 
-    #[test]
-    fn recovery_account_decode_failure_is_not_treated_as_absence() {
-        let address = Pubkey::new_unique();
-        let malformed = UiAccount {
-            lamports: 1,
-            data: UiAccountData::Binary(
-                "not-valid-base64-zstd".to_string(),
-                UiAccountEncoding::Base64Zstd,
-            ),
-            owner: Pubkey::new_unique().to_string(),
-            executable: false,
-            rent_epoch: 0,
-            space: None,
-        };
-        let error = decode_recovery_ui_account(&address, malformed).unwrap_err();
-        assert!(error.message.contains("拒绝将其视为不存在"));
-    }
-
-    #[test]
-    fn write_recovery_requires_the_exact_chunk_and_unchanged_snapshot() {
-        let initial = program_deploy::BufferWritePlan {
-            completed_chunks: 1,
-            pending_chunk_indexes: vec![1, 2],
-        };
-        let unchanged = program_deploy::BufferWritePlan {
-            completed_chunks: 1,
-            pending_chunk_indexes: vec![1, 2],
-        };
-        assert!(validate_write_attempt_recovery_snapshot(&initial, &unchanged, 2).is_ok());
-        assert!(validate_write_attempt_recovery_snapshot(&initial, &unchanged, 0).is_err());
-
-        let advanced_other_chunk = program_deploy::BufferWritePlan {
-            completed_chunks: 2,
-            pending_chunk_indexes: vec![2],
-        };
-        assert!(
-            validate_write_attempt_recovery_snapshot(&initial, &advanced_other_chunk, 2).is_err()
-        );
+#[allow(clippy::too_many_arguments)]
+fn confirm_write_attempt_still_absent(
+    client: &RpcClient,
+    buffer_address: &Pubkey,
+    expected_authority: &Pubkey,
+    expected_program: &[u8],
+    previous_plan: &program_deploy::BufferWritePlan,
+    chunk_index: usize,
+    min_context_slot: u64,
+) -> Result<(), ApiError> {
+    let account = get_finalized_recovery_account(client, buffer_address, min_context_slot)?
+        .ok_or_else(|| {
+            deployment_journal_error(format!(
+                "Buffer {} 在 write attempt 二次回读时不存在；拒绝自动重签 chunk {}",
+                buffer_address, chunk_index
+            ))
+        })?;
+    let current_plan = program_deploy::verify_resume_buffer(
+        expected_authority,
+        expected_program,
+        PROGRAM_WRITE_CHUNK_BYTES,
+        &account.owner,
+        account.executable,
+        &account.data,
+    )
+    .map_err(|error| {
+        deployment_journal_error(format!(
+            "Buffer {} 在 write attempt 二次回读时校验失败: {error}",
+            buffer_address
+        ))
+    })?;
+    validate_write_attempt_recovery_snapshot(previous_plan, &current_plan, chunk_index)
+}
 
-        let target_completed = program_deploy::BufferWritePlan {
-            completed_chunks: 2,
-            pending_chunk_indexes: vec![1],
-        };
-        assert!(validate_write_attempt_recovery_snapshot(&initial, &target_completed, 2).is_err());
+fn confirm_deploy_attempt_still_absent(
+    client: &RpcClient,
+    program_id: &Pubkey,
+    programdata_address: &Pubkey,
+    min_context_slot: u64,
+) -> Result<(), ApiError> {
+    let accounts = get_finalized_recovery_accounts(
+        client,
+        &[*program_id, *programdata_address],
+        min_context_slot,
+    )?;
+    if accounts.iter().any(Option::is_some) {
+        return Err(deployment_journal_error(format!(
+            "Program {} 或 ProgramData {} 已在签名历史 slot {} 之后出现；保持 attempt 活动状态并中止本次恢复",
+            program_id, programdata_address, min_context_slot
+        )));
     }
+    Ok(())
+}
 
-    #[test]
-    fn expiry_classifier_blocks_every_observed_history_state() {
-        let non_finalized = TransactionStatus {
-            slot: 1,
-            confirmations: Some(1),
-            status: Ok(()),
-            err: None,
-            confirmation_status: None,
-        };
-        assert_eq!(
-            classify_deployment_attempt_expiry(43, 42, Some(&non_finalized), 200, 199),
-            DeploymentAttemptExpiryDecision::SeenButNotFinalized
-        );
+fn deployment_attempt_is_active(status: &str) -> bool {
+    matches!(
+        status,
+        wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_SIGNED
+            | wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_CONFIRMED
+            | wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_REQUIRES_RECONCILIATION
+    )
+}
 
-        let finalized_success = TransactionStatus {
-            slot: 2,
-            confirmations: None,
-            status: Ok(()),
-            err: None,
-            confirmation_status: None,
-        };
-        assert_eq!(
-            classify_deployment_attempt_expiry(43, 42, Some(&finalized_success), 201, 200),
-            DeploymentAttemptExpiryDecision::FinalizedSucceeded
-        );
+fn deployment_attempt_is_blocking_terminal(status: &str) -> bool {
+    matches!(
+        status,
+        wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_FINALIZED
+            | wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_FINALIZED_FAILED
+    )
+}
 
-        let transaction_error = solana_sdk::transaction::TransactionError::AccountNotFound;
-        let finalized_failure = TransactionStatus {
-            slot: 3,
-            confirmations: None,
-            status: Err(transaction_error.clone()),
-            err: Some(transaction_error),
-            confirmation_status: None,
-        };
-        assert_eq!(
-            classify_deployment_attempt_expiry(43, 42, Some(&finalized_failure), 202, 201),
-            DeploymentAttemptExpiryDecision::FinalizedFailed("AccountNotFound".to_string())
-        );
+fn new_program_deployment_attempt(
+    record: &wallet_store::ProgramDeploymentRecord,
+    stage: &str,
+    chunk_index: Option<usize>,
+    signature: &Signature,
+    last_valid_block_height: u64,
+) -> wallet_store::ProgramDeploymentAttemptRecord {
+    wallet_store::ProgramDeploymentAttemptRecord {
+        genesis_hash: record.genesis_hash.clone(),
+        program_id: record.program_id.clone(),
+        stage: stage.to_string(),
+        buffer_address: record.buffer_address.clone(),
+        chunk_index,
+        signature: signature.to_string(),
+        last_valid_block_height,
+        status: wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_SIGNED.to_string(),
+        created_at: 0,
+        updated_at: 0,
     }
+}
 
-    #[test]
-    fn malformed_attempt_evidence_is_rejected_before_recovery() {
-        let program_id = Pubkey::new_unique();
-        let authority = Pubkey::new_unique();
-        let buffer = Pubkey::new_unique();
-        let record = record(&program_id, &authority, &buffer);
-        let attempt = wallet_store::ProgramDeploymentAttemptRecord {
-            genesis_hash: record.genesis_hash.clone(),
-            program_id: record.program_id.clone(),
-            stage: wallet_store::PROGRAM_DEPLOYMENT_STAGE_WRITE.to_string(),
-            buffer_address: record.buffer_address.clone(),
-            chunk_index: Some(0),
-            signature: Signature::new_unique().to_string(),
-            last_valid_block_height: 42,
-            status: wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_SIGNED.to_string(),
-            created_at: 1,
-            updated_at: 1,
-        };
-        assert!(validate_program_deployment_attempt_record(&record, &attempt).is_ok());
-
-        let mut invalid_signature = attempt.clone();
-        invalid_signature.signature = "not-a-signature".to_string();
-        let mut default_signature = attempt.clone();
-        default_signature.signature = Signature::default().to_string();
-        let mut noncanonical_signature = attempt.clone();
-        noncanonical_signature.signature.push(' ');
-        let mut zero_buffer = attempt.clone();
-        zero_buffer.buffer_address = Pubkey::default().to_string();
-        let mut noncanonical_buffer = attempt.clone();
-        noncanonical_buffer.buffer_address.push(' ');
-        let mut zero_height = attempt.clone();
-        zero_height.last_valid_block_height = 0;
-        let mut zero_created_at = attempt.clone();
-        zero_created_at.created_at = 0;
-        let mut reversed_timestamps = attempt.clone();
-        reversed_timestamps.updated_at = 0;
-        for invalid in [
-            invalid_signature,
-            default_signature,
-            noncanonical_signature,
-            zero_buffer,
-            noncanonical_buffer,
-            zero_height,
-            zero_created_at,
-            reversed_timestamps,
-        ] {
-            assert!(validate_program_deployment_attempt_record(&record, &invalid).is_err());
-        }
+fn validate_program_deployment_attempt_record(
+    record: &wallet_store::ProgramDeploymentRecord,
+    attempt: &wallet_store::ProgramDeploymentAttemptRecord,
+) -> Result<(), ApiError> {
+    let shape_is_valid = match attempt.stage.as_str() {
+        wallet_store::PROGRAM_DEPLOYMENT_STAGE_CREATE_BUFFER
+        | wallet_store::PROGRAM_DEPLOYMENT_STAGE_DEPLOY => attempt.chunk_index.is_none(),
+        wallet_store::PROGRAM_DEPLOYMENT_STAGE_WRITE => attempt.chunk_index.is_some(),
+        _ => false,
+    };
+    let status_is_valid = deployment_attempt_is_active(&attempt.status)
+        || deployment_attempt_is_blocking_terminal(&attempt.status)
+        || attempt.status == wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_EXPIRED_ABSENT;
+    let signature_is_valid = Signature::from_str(&attempt.signature).is_ok_and(|signature| {
+        signature != Signature::default() && signature.to_string() == attempt.signature
+    });
+    let buffer_is_valid = Pubkey::from_str(&attempt.buffer_address).is_ok_and(|buffer| {
+        buffer != Pubkey::default() && buffer.to_string() == attempt.buffer_address
+    });
+    if attempt.genesis_hash != record.genesis_hash
+        || attempt.program_id != record.program_id
+        || !shape_is_valid
+        || !status_is_valid
+        || (attempt.buffer_address != record.buffer_address
+            && !(attempt.status == wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_EXPIRED_ABSENT
+                && attempt.stage == wallet_store::PROGRAM_DEPLOYMENT_STAGE_CREATE_BUFFER))
+        || !signature_is_valid
+        || !buffer_is_valid
+        || attempt.last_valid_block_height == 0
+        || attempt.created_at == 0
+        || attempt.updated_at < attempt.created_at
+    {
+        return Err(deployment_journal_error(format!(
+            "Program {} 包含无效或不一致的 attempt 证据 {}，拒绝继续",
+            record.program_id, attempt.signature
+        )));
     }
+    Ok(())
+}
 
-    #[test]
-    fn generic_deploy_request_rejects_unknown_policy_fields() {
-        let request = serde_json::from_value::<DeployProgramRequest>(serde_json::json!({
-            "program_keypair_json": "[]",
-            "expected_program_id": Pubkey::new_unique().to_string(),
-            "expected_upgrade_authority": Pubkey::new_unique().to_string(),
-            "expected_genesis_hash": solana_sdk::hash::Hash::new_unique().to_string(),
-            "expected_program_sha256": "11".repeat(32),
-            "program_so_base64": "f0VMRg==",
-            "network": "devnet",
-            "require_authenticated_keystore": true
-        }));
-
-        assert!(request.is_err());
-    }
-
-    #[test]
-    fn canonical_program_id_uses_generic_journal_rules() {
-        let program_id = Pubkey::new_unique();
-        let authority = Pubkey::new_unique();
-        let buffer = Pubkey::new_unique();
-        let record = record(&program_id, &authority, &buffer);
-
-        assert_eq!(
-            validate_deployment_journal_binding(
-                &record,
-                "devnet-genesis",
-                &program_id,
-                &"11".repeat(32),
-                1024,
-                2048,
-                &authority,
-                Some(&buffer),
-            )
-            .unwrap(),
-            buffer
-        );
-    }
-
-    #[test]
-    fn deployment_capacity_errors_have_retryable_http_statuses() {
-        assert_eq!(
-            ApiError {
-                message: PROGRAM_DEPLOY_BUSY_MESSAGE.to_string(),
-            }
-            .status_code(),
-            StatusCode::CONFLICT
-        );
-        assert_eq!(
-            ApiError {
-                message: SBF_VERIFY_BUSY_MESSAGE.to_string(),
-            }
-            .status_code(),
-            StatusCode::TOO_MANY_REQUESTS
-        );
-    }
-}
-
-#[derive(Deserialize)]
-struct ProgramDeploySourceRequest {
-    source_dir: String,
-    #[serde(default)]
-    build: bool,
-    #[serde(default)]
-    confirm_untrusted_build: bool,
-    #[serde(default)]
-    network: Option<String>,
-}
-
-#[derive(Serialize)]
-struct ProgramDeploySourceResponse {
-    source_dir: String,
-    built: bool,
-    build_status: Option<String>,
-    build_job_id: Option<String>,
-    build_error: Option<String>,
-    build_command: Option<String>,
-    build_template: Option<String>,
-    build_stdout: Option<String>,
-    build_stderr: Option<String>,
-    program_so_path: Option<String>,
-    program_so_name: Option<String>,
-    program_so_base64: Option<String>,
-    program_so_sha256: Option<String>,
-    program_so_size: Option<usize>,
-    program_keypair_path: Option<String>,
-    expected_program_id: Option<String>,
-    manifest_program_id: Option<String>,
-    manifest_network: Option<String>,
-    manifest_genesis_hash: Option<String>,
-    manifest_upgrade_authority: Option<String>,
-    manifest_owner_admin: Option<String>,
-    manifest_operational_admin: Option<String>,
-    build_available: bool,
-    build_blocked_reason: Option<String>,
-    source_validation_errors: Vec<String>,
-    warnings: Vec<String>,
-}
-
-struct ProgramSourceArtifacts {
-    program_so_path: Option<PathBuf>,
-    program_so_name: Option<String>,
-    program_so_base64: Option<String>,
-    program_so_sha256: Option<String>,
-    program_so_size: Option<usize>,
-    program_keypair_path: Option<PathBuf>,
-    expected_program_id: Option<String>,
-    manifest_program_id: Option<String>,
-    manifest_network: Option<String>,
-    manifest_genesis_hash: Option<String>,
-    manifest_upgrade_authority: Option<String>,
-    manifest_owner_admin: Option<String>,
-    manifest_operational_admin: Option<String>,
-    source_validation_errors: Vec<String>,
-    warnings: Vec<String>,
-}
-
-struct ProgramIdDeclaration {
-    label: String,
-    program_id: String,
+fn begin_deployment_attempt(
+    journal: &mut Option<wallet_store::ProgramDeploymentRecord>,
+    attempt: wallet_store::ProgramDeploymentAttemptRecord,
+    journal_status: &str,
+    completed_writes: usize,
+) -> Result<(), ApiError> {
+    let current = journal.as_ref().ok_or_else(|| ApiError {
+        message: "部署 journal 不存在，拒绝记录签名 attempt".to_string(),
+    })?;
+    let updated = wallet_store::begin_program_deployment_attempt(
+        current,
+        attempt,
+        journal_status,
+        completed_writes,
+    )
+    .map_err(|message| ApiError { message })?;
+    *journal = Some(updated);
+    Ok(())
 }
 
-#[derive(Clone)]
-struct ProgramSourceBuildJob {
-    source_dir: PathBuf,
-    status: String,
-    build_command: Option<String>,
-    build_template: Option<String>,
-    build_stdout: Option<String>,
-    build_stderr: Option<String>,
-    build_error: Option<String>,
-    warnings: Vec<String>,
+fn transition_deployment_attempt(
+    journal: &mut Option<wallet_store::ProgramDeploymentRecord>,
+    signature: &str,
+    expected_attempt_status: &str,
+    next_attempt_status: &str,
+    journal_status: &str,
+    completed_writes: usize,
+) -> Result<(), ApiError> {
+    let current = journal.as_ref().ok_or_else(|| ApiError {
+        message: "部署 journal 不存在，拒绝迁移签名 attempt".to_string(),
+    })?;
+    let updated = wallet_store::transition_program_deployment_attempt(
+        current,
+        signature,
+        expected_attempt_status,
+        next_attempt_status,
+        journal_status,
+        completed_writes,
+    )
+    .map_err(|message| ApiError { message })?;
+    *journal = Some(updated);
+    Ok(())
 }
 
-fn program_source_build_jobs(
-) -> &'static Arc<tokio::sync::Mutex<HashMap<String, ProgramSourceBuildJob>>> {
-    PROGRAM_SOURCE_BUILD_JOBS.get_or_init(|| Arc::new(tokio::sync::Mutex::new(HashMap::new())))
+fn reconcile_absent_deployment_attempt<F>(
+    client: &RpcClient,
+    journal: &mut Option<wallet_store::ProgramDeploymentRecord>,
+    attempt: &wallet_store::ProgramDeploymentAttemptRecord,
+    journal_status: &str,
+    completed_writes: usize,
+    confirm_absent: F,
+) -> Result<bool, ApiError>
+where
+    F: FnOnce(&RpcClient, u64) -> Result<(), ApiError>,
+{
+    let stage_context = attempt
+        .chunk_index
+        .map(|index| format!("{} chunk {}", attempt.stage, index))
+        .unwrap_or_else(|| attempt.stage.clone());
+    let decision = gate_expired_absent_with(
+        inspect_deployment_attempt_expiry(client, attempt)?,
+        |min_context_slot| confirm_absent(client, min_context_slot),
+    )?;
+    match decision {
+        DeploymentAttemptExpiryDecision::ExpiredAbsent { .. } => {
+            transition_deployment_attempt(
+                journal,
+                &attempt.signature,
+                &attempt.status,
+                wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_EXPIRED_ABSENT,
+                journal_status,
+                completed_writes,
+            )?;
+            Ok(true)
+        }
+        DeploymentAttemptExpiryDecision::FinalizedSucceeded => {
+            transition_deployment_attempt(
+                journal,
+                &attempt.signature,
+                &attempt.status,
+                wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_FINALIZED,
+                journal_status,
+                completed_writes,
+            )?;
+            Err(deployment_journal_error(format!(
+                "{} 签名 {} 已 finalized 成功，但对应业务状态缺失；已持久化冲突并禁止自动重签",
+                stage_context, attempt.signature
+            )))
+        }
+        DeploymentAttemptExpiryDecision::FinalizedFailed(error) => {
+            transition_deployment_attempt(
+                journal,
+                &attempt.signature,
+                &attempt.status,
+                wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_FINALIZED_FAILED,
+                journal_status,
+                completed_writes,
+            )?;
+            Err(deployment_journal_error(format!(
+                "{} 签名 {} 已 finalized 失败（{}）；已持久化终态，禁止自动重签",
+                stage_context, attempt.signature, error
+            )))
+        }
+        DeploymentAttemptExpiryDecision::NotExpired => Err(deployment_journal_error(format!(
+            "{} 签名 {} 尚未越过 last valid block height {}，不得自动重签",
+            stage_context, attempt.signature, attempt.last_valid_block_height
+        ))),
+        DeploymentAttemptExpiryDecision::HistoryContextTooOld {
+            expiry_observation_slot,
+            history_context_slot,
+        } => Err(deployment_journal_error(format!(
+            "{} 签名 {} 的历史上下文 slot {} 早于过期观测 slot {}；RPC 后端视图不一致，拒绝自动重签",
+            stage_context, attempt.signature, history_context_slot, expiry_observation_slot
+        ))),
+        DeploymentAttemptExpiryDecision::SeenButNotFinalized => {
+            Err(deployment_journal_error(format!(
+                "{} 签名 {} 仍可在历史中观察到但尚未 finalized，不得自动重签",
+                stage_context, attempt.signature
+            )))
+        }
+    }
 }
 
-fn program_source_error(message: impl Into<String>) -> ApiError {
+fn deployment_journal_error(message: impl Into<String>) -> ApiError {
     ApiError {
-        message: message.into(),
+        message: format!("部署 journal 冲突: {}", message.into()),
     }
 }
 
-fn canonical_child_path(root: &FsPath, path: &FsPath, label: &str) -> Result<PathBuf, ApiError> {
-    let canonical = path
-        .canonicalize()
-        .map_err(|error| program_source_error(format!("读取 {label} 路径失败: {error}")))?;
-    if !canonical.starts_with(root) {
-        return Err(program_source_error(format!("{label} 必须位于源码目录内")));
-    }
-    Ok(canonical)
-}
+type DeploymentReceiptEvidence = (
+    Option<u64>,
+    Option<u32>,
+    Vec<wallet_store::ProgramDeploymentAttemptRecord>,
+);
 
-fn read_text_file_limited(
-    path: &FsPath,
-    max_bytes: usize,
-    label: &str,
-) -> Result<String, ApiError> {
-    let metadata = fs::metadata(path)
-        .map_err(|error| program_source_error(format!("读取 {label} 元数据失败: {error}")))?;
-    if !metadata.is_file() {
-        return Err(program_source_error(format!("{label} 不是普通文件")));
-    }
-    if metadata.len() > max_bytes as u64 {
-        return Err(program_source_error(format!("{label} 文件过大")));
+fn load_deployment_receipt_evidence(
+    journal: Option<&wallet_store::ProgramDeploymentRecord>,
+) -> Result<DeploymentReceiptEvidence, ApiError> {
+    let Some(record) = journal else {
+        return Ok((None, None, Vec::new()));
+    };
+    let attempts =
+        wallet_store::load_program_deployment_attempts(&record.genesis_hash, &record.program_id)
+            .map_err(|message| ApiError { message })?;
+    for attempt in &attempts {
+        validate_program_deployment_attempt_record(record, attempt)?;
     }
-    fs::read_to_string(path)
-        .map_err(|error| program_source_error(format!("读取 {label} 失败: {error}")))
+    Ok((
+        Some(record.revision),
+        Some(record.attempt_evidence_version),
+        attempts,
+    ))
 }
 
-fn read_bytes_file_limited(
-    path: &FsPath,
-    max_bytes: usize,
-    label: &str,
-) -> Result<Vec<u8>, ApiError> {
-    let metadata = fs::metadata(path)
-        .map_err(|error| program_source_error(format!("读取 {label} 元数据失败: {error}")))?;
-    if !metadata.is_file() {
-        return Err(program_source_error(format!("{label} 不是普通文件")));
+#[allow(clippy::too_many_arguments)]
+fn validate_deployment_journal_binding(
+    record: &wallet_store::ProgramDeploymentRecord,
+    genesis_hash: &str,
+    program_id: &Pubkey,
+    program_sha256: &str,
+    program_len: usize,
+    max_data_len: usize,
+    upgrade_authority: &Pubkey,
+    requested_buffer: Option<&Pubkey>,
+) -> Result<Pubkey, ApiError> {
+    let expected_program_id = program_id.to_string();
+    let expected_upgrade_authority = upgrade_authority.to_string();
+    if record.genesis_hash != genesis_hash
+        || record.program_id != expected_program_id
+        || record.program_sha256 != program_sha256
+        || record.program_len != program_len
+        || record.max_data_len != max_data_len
+        || record.upgrade_authority != expected_upgrade_authority
+    {
+        return Err(deployment_journal_error(format!(
+            "Program {} 已有不同的部署意图，拒绝复用或覆盖；已记录 artifact={} len={} max_len={} upgrade_authority={}",
+            program_id,
+            record.program_sha256,
+            record.program_len,
+            record.max_data_len,
+            record.upgrade_authority,
+        )));
     }
-    if metadata.len() == 0 || metadata.len() > max_bytes as u64 {
-        return Err(program_source_error(format!("{label} 大小无效")));
+
+    let stored_buffer = Pubkey::from_str(record.buffer_address.trim()).map_err(|_| {
+        deployment_journal_error(format!(
+            "Program {} 已记录无效 Buffer 地址 {}",
+            program_id, record.buffer_address
+        ))
+    })?;
+    if stored_buffer == Pubkey::default() || record.buffer_address != stored_buffer.to_string() {
+        return Err(deployment_journal_error(format!(
+            "Program {} 已记录非 canonical Buffer 地址 {}",
+            program_id, record.buffer_address
+        )));
     }
-    fs::read(path).map_err(|error| program_source_error(format!("读取 {label} 失败: {error}")))
+    if record.created_at == 0 || record.updated_at < record.created_at {
+        return Err(deployment_journal_error(format!(
+            "Program {} 的部署 journal 时间戳无效",
+            program_id
+        )));
+    }
+    if requested_buffer.is_some_and(|requested| requested != &stored_buffer) {
+        return Err(deployment_journal_error(format!(
+            "请求恢复的 Buffer 与已记录 Buffer {} 不一致",
+            stored_buffer
+        )));
+    }
+    Ok(stored_buffer)
 }
 
-fn first_existing_path_owned(root: &FsPath, candidates: &[String]) -> Option<PathBuf> {
-    candidates
-        .iter()
-        .map(|relative| root.join(relative))
-        .filter_map(|path| canonical_child_path(root, &path, "候选产物").ok())
-        .find(|path| path.is_file())
+fn deployment_status_is_known(status: &str) -> bool {
+    matches!(
+        status,
+        DEPLOYMENT_STATUS_CREATE_BUFFER_SIGNED
+            | DEPLOYMENT_STATUS_CREATE_BUFFER_RECONCILE
+            | DEPLOYMENT_STATUS_BUFFER_READY
+            | DEPLOYMENT_STATUS_WRITE_SIGNED
+            | DEPLOYMENT_STATUS_WRITE_CONFIRMED
+            | DEPLOYMENT_STATUS_WRITE_RECONCILE
+            | DEPLOYMENT_STATUS_BUFFER_FINALIZED
+            | DEPLOYMENT_STATUS_DEPLOY_SIGNED
+            | DEPLOYMENT_STATUS_DEPLOY_RECONCILE
+            | DEPLOYMENT_STATUS_DEPLOY_FINALIZED
+            | DEPLOYMENT_STATUS_FINALIZED
+    )
 }
 
-fn first_json_file(root: &FsPath, directory: &FsPath) -> Option<PathBuf> {
-    fs::read_dir(directory)
-        .ok()?
-        .filter_map(Result::ok)
-        .map(|entry| entry.path())
-        .filter_map(|path| canonical_child_path(root, &path, "JSON 产物").ok())
-        .filter(|path| {
-            path.is_file()
-                && path
-                    .extension()
-                    .and_then(|ext| ext.to_str())
-                    .is_some_and(|ext| ext.eq_ignore_ascii_case("json"))
-        })
-        .min()
+#[allow(clippy::too_many_arguments)]
+fn update_deployment_journal(
+    record: &wallet_store::ProgramDeploymentRecord,
+    status: &str,
+    create_signature: Option<&str>,
+    create_last_valid_block_height: Option<u64>,
+    last_write_signature: Option<&str>,
+    last_write_chunk_index: Option<usize>,
+    last_write_last_valid_block_height: Option<u64>,
+    completed_writes: usize,
+    deploy_signature: Option<&str>,
+    deploy_last_valid_block_height: Option<u64>,
+) -> Result<wallet_store::ProgramDeploymentRecord, ApiError> {
+    wallet_store::update_program_deployment_progress(
+        record,
+        status,
+        create_signature,
+        create_last_valid_block_height,
+        last_write_signature,
+        last_write_chunk_index,
+        last_write_last_valid_block_height,
+        completed_writes,
+        deploy_signature,
+        deploy_last_valid_block_height,
+    )
+    .map_err(|message| ApiError { message })
 }
 
-fn file_modified_time_key(path: &FsPath) -> SystemTime {
-    path.metadata()
-        .and_then(|metadata| metadata.modified())
-        .unwrap_or(SystemTime::UNIX_EPOCH)
+#[allow(clippy::too_many_arguments)]
+fn transition_deployment_journal(
+    journal: &mut Option<wallet_store::ProgramDeploymentRecord>,
+    status: &str,
+    create_signature: Option<&str>,
+    create_last_valid_block_height: Option<u64>,
+    last_write_signature: Option<&str>,
+    last_write_chunk_index: Option<usize>,
+    last_write_last_valid_block_height: Option<u64>,
+    completed_writes: usize,
+    deploy_signature: Option<&str>,
+    deploy_last_valid_block_height: Option<u64>,
+) -> Result<(), ApiError> {
+    let current = journal.as_ref().ok_or_else(|| ApiError {
+        message: "部署 journal 不存在，拒绝更新部署状态".to_string(),
+    })?;
+    let updated = update_deployment_journal(
+        current,
+        status,
+        create_signature,
+        create_last_valid_block_height,
+        last_write_signature,
+        last_write_chunk_index,
+        last_write_last_valid_block_height,
+        completed_writes,
+        deploy_signature,
+        deploy_last_valid_block_height,
+    )?;
+    *journal = Some(updated);
+    Ok(())
 }
 
-fn newest_file(paths: Vec<PathBuf>) -> Option<PathBuf> {
-    paths.into_iter().max_by(|left, right| {
-        file_modified_time_key(left)
-            .cmp(&file_modified_time_key(right))
-            .then_with(|| left.cmp(right))
-    })
-}
+#[cfg(test)]
+mod deployment_journal_tests {
+    use super::*;
 
-fn so_file_paths(root: &FsPath, directory: &FsPath) -> Vec<PathBuf> {
-    fs::read_dir(directory)
-        .ok()
-        .into_iter()
-        .flat_map(|entries| entries.filter_map(Result::ok))
-        .map(|entry| entry.path())
-        .filter_map(|path| canonical_child_path(root, &path, ".so 产物").ok())
-        .filter(|path| {
-            path.is_file()
-                && path
-                    .extension()
-                    .and_then(|ext| ext.to_str())
-                    .is_some_and(|ext| ext.eq_ignore_ascii_case("so"))
+    #[test]
+    fn program_upgrade_requires_squads_member() {
+        let signer = Pubkey::new_unique();
+        let multisig = SquadsMultisig {
+            create_key: Pubkey::new_unique(),
+            config_authority: Pubkey::default(),
+            threshold: 1,
+            time_lock: 0,
+            transaction_index: 0,
+            stale_transaction_index: 0,
+            rent_collector: None,
+            bump: 0,
+            members: vec![SquadsMember {
+                key: signer,
+                permissions: squads_v4::Permissions::all(),
+            }],
+        };
+        assert!(require_squads_member(&multisig, &signer).is_ok());
+        assert!(require_squads_member(&multisig, &Pubkey::new_unique()).is_err());
+    }
+
+    #[test]
+    fn parses_programdata_upgrade_authority() {
+        let authority = Pubkey::new_unique();
+        let mut data = bincode::serialize(&UpgradeableLoaderState::ProgramData {
+            slot: 42,
+            upgrade_authority_address: Some(authority),
         })
-        .collect()
-}
+        .unwrap();
+        data.resize(UpgradeableLoaderState::size_of_programdata_metadata(), 0);
+        assert_eq!(
+            loader_upgrade_authority_from_programdata(&data).unwrap(),
+            authority
+        );
 
-fn newest_existing_path_owned(root: &FsPath, candidates: &[String]) -> Option<PathBuf> {
-    newest_file(
-        candidates
-            .iter()
-            .map(|relative| root.join(relative))
-            .filter_map(|path| canonical_child_path(root, &path, "候选产物").ok())
-            .filter(|path| path.is_file())
-            .collect(),
-    )
-}
-
-fn newest_build_metadata_file(root: &FsPath, directory: &FsPath) -> Option<PathBuf> {
-    newest_file(
-        fs::read_dir(directory)
-            .ok()?
-            .filter_map(Result::ok)
-            .map(|entry| entry.path())
-            .filter_map(|path| canonical_child_path(root, &path, "build metadata").ok())
-            .filter(|path| {
-                let Some(name) = path.file_name().and_then(|value| value.to_str()) else {
-                    return false;
-                };
-                path.is_file() && name.ends_with("-build.json")
-            })
-            .collect(),
-    )
-}
-
-fn safe_artifact_stem(value: &str) -> Option<String> {
-    let stem = value.trim().replace('-', "_");
-    if stem.is_empty()
-        || stem.len() > 128
-        || !stem
-            .bytes()
-            .all(|byte| byte.is_ascii_alphanumeric() || byte == b'_')
-    {
-        return None;
-    }
-    Some(stem)
-}
-
-fn artifact_stem_from_so_name(value: &str) -> Option<String> {
-    let name = value.trim();
-    let stem = name
-        .strip_suffix(".so")
-        .or_else(|| name.strip_suffix(".SO"))
-        .or_else(|| {
-            name.rsplit_once('.').and_then(|(stem, ext)| {
-                if ext.eq_ignore_ascii_case("so") {
-                    Some(stem)
-                } else {
-                    None
-                }
-            })
+        let mut immutable = bincode::serialize(&UpgradeableLoaderState::ProgramData {
+            slot: 42,
+            upgrade_authority_address: None,
         })
-        .unwrap_or(name);
-    safe_artifact_stem(stem)
-}
-
-fn parse_quoted_toml_value(value: &str) -> Option<String> {
-    let trimmed = value.trim();
-    if let Some(rest) = trimmed.strip_prefix('"') {
-        return rest
-            .split_once('"')
-            .map(|(value, _)| value.trim().to_string())
-            .filter(|value| !value.is_empty());
+        .unwrap();
+        immutable.resize(UpgradeableLoaderState::size_of_programdata_metadata(), 0);
+        assert!(loader_upgrade_authority_from_programdata(&immutable).is_err());
     }
-    trimmed
-        .split('#')
-        .next()
-        .map(str::trim)
-        .filter(|value| !value.is_empty())
-        .map(str::to_string)
-}
 
-fn parse_anchor_program_name(root: &FsPath) -> Option<String> {
-    let anchor = fs::read_to_string(root.join("Anchor.toml")).ok()?;
-    let mut in_programs_section = false;
-    for line in anchor.lines() {
-        let trimmed = line.trim();
-        if trimmed.starts_with('[') && trimmed.ends_with(']') {
-            in_programs_section = trimmed.starts_with("[programs.");
-            continue;
-        }
-        if !in_programs_section || trimmed.starts_with('#') || !trimmed.contains('=') {
-            continue;
-        }
-        let Some((name, value)) = trimmed.split_once('=') else {
-            continue;
-        };
-        let Some(program_id) = parse_quoted_toml_value(value) else {
-            continue;
-        };
-        if Pubkey::from_str(&program_id).is_ok() {
-            if let Some(stem) = safe_artifact_stem(name) {
-                return Some(stem);
-            }
+    fn record(
+        program_id: &Pubkey,
+        authority: &Pubkey,
+        buffer: &Pubkey,
+    ) -> wallet_store::ProgramDeploymentRecord {
+        wallet_store::ProgramDeploymentRecord {
+            genesis_hash: "devnet-genesis".to_string(),
+            program_id: program_id.to_string(),
+            program_sha256: "11".repeat(32),
+            program_len: 1024,
+            max_data_len: 2048,
+            upgrade_authority: authority.to_string(),
+            buffer_address: buffer.to_string(),
+            status: DEPLOYMENT_STATUS_BUFFER_READY.to_string(),
+            create_signature: Some(Signature::new_unique().to_string()),
+            create_last_valid_block_height: Some(42),
+            last_write_signature: None,
+            last_write_chunk_index: None,
+            last_write_last_valid_block_height: None,
+            completed_writes: 0,
+            deploy_signature: None,
+            deploy_last_valid_block_height: None,
+            attempt_evidence_version: wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_EVIDENCE_VERSION,
+            revision: 0,
+            created_at: 1,
+            updated_at: 1,
         }
     }
-    None
-}
 
-fn parse_cargo_package_name(root: &FsPath) -> Option<String> {
-    let cargo = fs::read_to_string(root.join("Cargo.toml")).ok()?;
-    let mut in_package_section = false;
-    for line in cargo.lines() {
-        let trimmed = line.trim();
-        if trimmed.starts_with('[') && trimmed.ends_with(']') {
-            in_package_section = trimmed == "[package]";
-            continue;
-        }
-        if !in_package_section || trimmed.starts_with('#') {
-            continue;
-        }
-        let Some((name, value)) = trimmed.split_once('=') else {
-            continue;
-        };
-        if name.trim() != "name" {
-            continue;
-        }
-        let package_name = parse_quoted_toml_value(value)?;
-        return safe_artifact_stem(&package_name);
-    }
-    None
-}
+    #[test]
+    fn journal_binding_accepts_only_the_recorded_deployment_intent() {
+        let program_id = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+        let buffer = Pubkey::new_unique();
+        let record = record(&program_id, &authority, &buffer);
 
-fn detect_program_artifact_stem(root: &FsPath) -> Option<String> {
-    parse_anchor_program_name(root).or_else(|| parse_cargo_package_name(root))
-}
+        let stored_buffer = validate_deployment_journal_binding(
+            &record,
+            "devnet-genesis",
+            &program_id,
+            &"11".repeat(32),
+            1024,
+            2048,
+            &authority,
+            Some(&buffer),
+        )
+        .unwrap();
+        assert_eq!(stored_buffer, buffer);
 
-fn find_program_so_path(root: &FsPath, artifact_stem: Option<&str>) -> Option<PathBuf> {
-    // Anchor's canonical deploy artifacts live under target/deploy/:
-    //   target/deploy/<program_name>.so
-    //   target/deploy/<program_name>-keypair.json
-    // Prefer that pair so the .so and keypair stay in lockstep. Verifiable
-    // builds are only a fallback when deploy output is missing.
-    let mut deploy_candidates = Vec::new();
-    let mut verifiable_candidates = Vec::new();
-    if let Some(stem) = artifact_stem.and_then(safe_artifact_stem) {
-        deploy_candidates.push(format!("target/deploy/{stem}.so"));
-        verifiable_candidates.push(format!("target/verifiable/{stem}.so"));
+        assert!(validate_deployment_journal_binding(
+            &record,
+            "devnet-genesis",
+            &program_id,
+            &"22".repeat(32),
+            1024,
+            2048,
+            &authority,
+            Some(&buffer),
+        )
+        .is_err());
+        assert!(validate_deployment_journal_binding(
+            &record,
+            "devnet-genesis",
+            &program_id,
+            &"11".repeat(32),
+            1024,
+            2048,
+            &authority,
+            Some(&Pubkey::new_unique()),
+        )
+        .is_err());
+        let mut noncanonical_buffer = record.clone();
+        noncanonical_buffer.buffer_address.push(' ');
+        assert!(validate_deployment_journal_binding(
+            &noncanonical_buffer,
+            "devnet-genesis",
+            &program_id,
+            &"11".repeat(32),
+            1024,
+            2048,
+            &authority,
+            None,
+        )
+        .is_err());
+        let mut zero_buffer = record.clone();
+        zero_buffer.buffer_address = Pubkey::default().to_string();
+        assert!(validate_deployment_journal_binding(
+            &zero_buffer,
+            "devnet-genesis",
+            &program_id,
+            &"11".repeat(32),
+            1024,
+            2048,
+            &authority,
+            None,
+        )
+        .is_err());
+        let mut invalid_timestamps = record.clone();
+        invalid_timestamps.updated_at = 0;
+        assert!(validate_deployment_journal_binding(
+            &invalid_timestamps,
+            "devnet-genesis",
+            &program_id,
+            &"11".repeat(32),
+            1024,
+            2048,
+            &authority,
+            None,
+        )
+        .is_err());
     }
-    newest_existing_path_owned(root, &deploy_candidates)
-        .or_else(|| newest_file(so_file_paths(root, &root.join("target/deploy"))))
-        .or_else(|| newest_existing_path_owned(root, &verifiable_candidates))
-        .or_else(|| newest_file(so_file_paths(root, &root.join("target/verifiable"))))
-}
 
-fn find_program_keypair_path(root: &FsPath, artifact_stem: Option<&str>) -> Option<PathBuf> {
-    // Prefer Anchor's build output under target/deploy/. Historical copies under
-    // .keys/ are often stale and must not override the keypair that matches the
-    // just-built .so / declare_id!.
-    let mut deploy_candidates = Vec::new();
-    let mut keys_candidates = Vec::new();
-    if let Some(stem) = artifact_stem.and_then(safe_artifact_stem) {
-        deploy_candidates.push(format!("target/deploy/{stem}-keypair.json"));
-        keys_candidates.push(format!(".keys/{stem}-program-keypair.json"));
-        keys_candidates.push(format!(".keys/{stem}-keypair.json"));
+    #[test]
+    fn journal_status_allowlist_rejects_unknown_states() {
+        assert!(deployment_status_is_known(
+            DEPLOYMENT_STATUS_CREATE_BUFFER_SIGNED
+        ));
+        assert!(deployment_status_is_known(
+            DEPLOYMENT_STATUS_WRITE_CONFIRMED
+        ));
+        assert!(deployment_status_is_known(DEPLOYMENT_STATUS_FINALIZED));
+        assert!(!deployment_status_is_known("retry_everything"));
     }
-    newest_existing_path_owned(root, &deploy_candidates)
-        .or_else(|| first_json_file(root, &root.join("target/deploy")))
-        .or_else(|| newest_existing_path_owned(root, &keys_candidates))
-        .or_else(|| first_json_file(root, &root.join(".keys")))
-}
 
-fn find_program_idl_path(root: &FsPath, artifact_stem: Option<&str>) -> Option<PathBuf> {
-    let mut candidates = Vec::new();
-    if let Some(stem) = artifact_stem.and_then(safe_artifact_stem) {
-        candidates.push(format!("target/idl/{stem}.json"));
+    #[test]
+    fn expiry_classifier_requires_strict_finalized_height_and_absent_history() {
+        assert_eq!(
+            classify_deployment_attempt_expiry(41, 42, None, 100, 101),
+            DeploymentAttemptExpiryDecision::NotExpired
+        );
+        assert_eq!(
+            classify_deployment_attempt_expiry(42, 42, None, 101, 102),
+            DeploymentAttemptExpiryDecision::NotExpired
+        );
+        assert_eq!(
+            classify_deployment_attempt_expiry(43, 42, None, 102, 102),
+            DeploymentAttemptExpiryDecision::ExpiredAbsent {
+                min_context_slot: 102
+            }
+        );
     }
-    first_existing_path_owned(root, &candidates)
-        .or_else(|| first_json_file(root, &root.join("target/idl")))
-}
 
-fn find_release_manifest_path(root: &FsPath) -> Option<PathBuf> {
-    fs::read_dir(root.join("docs"))
-        .ok()?
-        .filter_map(Result::ok)
-        .map(|entry| entry.path())
-        .filter_map(|path| canonical_child_path(root, &path, "release manifest").ok())
-        .filter(|path| {
-            let Some(name) = path.file_name().and_then(|value| value.to_str()) else {
-                return false;
-            };
-            path.is_file()
-                && name.contains("RELEASE_MANIFEST")
-                && name.ends_with(".json")
-                && !name.contains(".example.")
+    #[test]
+    fn expiry_classifier_rejects_history_older_than_expiry_observation() {
+        let decision = classify_deployment_attempt_expiry(43, 42, None, 499, 500);
+        assert_eq!(
+            decision,
+            DeploymentAttemptExpiryDecision::HistoryContextTooOld {
+                expiry_observation_slot: 500,
+                history_context_slot: 499,
+            }
+        );
+        let gated = gate_expired_absent_with(decision, |_| {
+            panic!("stale history must not reach business-state readback")
         })
-        .min()
-}
-
-fn path_string(path: &FsPath) -> String {
-    path.to_string_lossy().to_string()
-}
+        .unwrap();
+        assert!(matches!(
+            gated,
+            DeploymentAttemptExpiryDecision::HistoryContextTooOld { .. }
+        ));
+    }
 
-fn json_pointer_string(value: &Value, pointer: &str) -> Option<String> {
-    value
-        .pointer(pointer)
-        .and_then(Value::as_str)
-        .map(str::trim)
-        .filter(|value| !value.is_empty() && !value.starts_with("REPLACE_WITH_"))
-        .map(ToOwned::to_owned)
-}
+    #[test]
+    fn expired_absent_gate_passes_exact_history_slot_once() {
+        let observed_slot = std::cell::Cell::new(None);
+        let decision = gate_expired_absent_with(
+            DeploymentAttemptExpiryDecision::ExpiredAbsent {
+                min_context_slot: 987,
+            },
+            |slot| {
+                assert!(observed_slot.replace(Some(slot)).is_none());
+                Ok(())
+            },
+        )
+        .unwrap();
+        assert_eq!(observed_slot.get(), Some(987));
+        assert_eq!(
+            decision,
+            DeploymentAttemptExpiryDecision::ExpiredAbsent {
+                min_context_slot: 987
+            }
+        );
+    }
 
-fn anchor_program_id_declarations(
-    root: &FsPath,
-    artifact_stem: Option<&str>,
-    network: Option<&str>,
-) -> Vec<ProgramIdDeclaration> {
-    let Ok(anchor) = fs::read_to_string(root.join("Anchor.toml")) else {
-        return Vec::new();
-    };
-    let wanted_stem = artifact_stem.and_then(safe_artifact_stem);
-    let wanted_network = network
-        .map(str::trim)
-        .filter(|value| !value.is_empty())
-        .map(ToOwned::to_owned);
-    let mut current_programs_section: Option<String> = None;
-    let mut declarations = Vec::new();
-    for line in anchor.lines() {
-        let trimmed = line.trim();
-        if trimmed.starts_with('[') && trimmed.ends_with(']') {
-            let section = trimmed.trim_start_matches('[').trim_end_matches(']');
-            current_programs_section = section
-                .strip_prefix("programs.")
-                .map(str::trim)
-                .filter(|value| !value.is_empty())
-                .map(ToOwned::to_owned);
-            continue;
+    #[test]
+    fn expired_absent_gate_fails_before_authorizing_transition() {
+        let transition_authorized = std::cell::Cell::new(false);
+        let result = gate_expired_absent_with(
+            DeploymentAttemptExpiryDecision::ExpiredAbsent {
+                min_context_slot: 654,
+            },
+            |slot| {
+                assert_eq!(slot, 654);
+                Err(deployment_journal_error("二次业务回读已前进"))
+            },
+        );
+        if result.is_ok() {
+            transition_authorized.set(true);
         }
-        let Some(section_network) = current_programs_section.as_ref() else {
-            continue;
+        assert!(result.is_err());
+        assert!(!transition_authorized.get());
+
+        let non_expired =
+            gate_expired_absent_with(DeploymentAttemptExpiryDecision::NotExpired, |_| {
+                panic!("non-expired decision must not run the absence callback")
+            })
+            .unwrap();
+        assert_eq!(non_expired, DeploymentAttemptExpiryDecision::NotExpired);
+    }
+
+    #[test]
+    fn recovery_account_config_is_finalized_and_history_slot_bound() {
+        let config = finalized_recovery_account_config(321);
+        assert_eq!(config.encoding, Some(UiAccountEncoding::Base64Zstd));
+        assert_eq!(config.commitment, Some(CommitmentConfig::finalized()));
+        assert_eq!(config.min_context_slot, Some(321));
+        assert!(config.data_slice.is_none());
+    }
+
+    #[test]
+    fn recovery_account_decode_failure_is_not_treated_as_absence() {
+        let address = Pubkey::new_unique();
+        let malformed = UiAccount {
+            lamports: 1,
+            data: UiAccountData::Binary(
+                "not-valid-base64-zstd".to_string(),
+                UiAccountEncoding::Base64Zstd,
+            ),
+            owner: Pubkey::new_unique().to_string(),
+            executable: false,
+            rent_epoch: 0,
+            space: None,
         };
-        if let Some(wanted_network) = wanted_network.as_ref() {
-            if section_network != wanted_network {
-                continue;
-            }
-        }
-        if trimmed.starts_with('#') || !trimmed.contains('=') {
-            continue;
-        }
-        let Some((name, value)) = trimmed.split_once('=') else {
-            continue;
+        let error = decode_recovery_ui_account(&address, malformed).unwrap_err();
+        assert!(error.message.contains("拒绝将其视为不存在"));
+    }
+
+    #[test]
+    fn write_recovery_requires_the_exact_chunk_and_unchanged_snapshot() {
+        let initial = program_deploy::BufferWritePlan {
+            completed_chunks: 1,
+            pending_chunk_indexes: vec![1, 2],
         };
-        let Some(program_name) = safe_artifact_stem(name.trim()) else {
-            continue;
+        let unchanged = program_deploy::BufferWritePlan {
+            completed_chunks: 1,
+            pending_chunk_indexes: vec![1, 2],
         };
-        if let Some(wanted_stem) = wanted_stem.as_ref() {
-            if &program_name != wanted_stem {
-                continue;
-            }
-        }
-        let Some(value) = parse_quoted_toml_value(value) else {
-            continue;
+        assert!(validate_write_attempt_recovery_snapshot(&initial, &unchanged, 2).is_ok());
+        assert!(validate_write_attempt_recovery_snapshot(&initial, &unchanged, 0).is_err());
+
+        let advanced_other_chunk = program_deploy::BufferWritePlan {
+            completed_chunks: 2,
+            pending_chunk_indexes: vec![2],
         };
-        declarations.push(ProgramIdDeclaration {
-            label: format!("Anchor.toml [programs.{section_network}] {program_name}"),
-            program_id: value,
-        });
-    }
-    if declarations.is_empty() && wanted_network.is_some() {
-        return anchor_program_id_declarations(root, artifact_stem, None);
-    }
-    declarations
-}
+        assert!(
+            validate_write_attempt_recovery_snapshot(&initial, &advanced_other_chunk, 2).is_err()
+        );
 
-fn parse_anchor_program_id(
-    root: &FsPath,
-    artifact_stem: Option<&str>,
-    network: Option<&str>,
-) -> Option<String> {
-    anchor_program_id_declarations(root, artifact_stem, network)
-        .into_iter()
-        .next()
-        .map(|declaration| declaration.program_id)
-}
+        let target_completed = program_deploy::BufferWritePlan {
+            completed_chunks: 2,
+            pending_chunk_indexes: vec![1],
+        };
+        assert!(validate_write_attempt_recovery_snapshot(&initial, &target_completed, 2).is_err());
+    }
 
-fn parse_idl_program_id(
-    root: &FsPath,
-    artifact_stem: Option<&str>,
-) -> Result<Option<ProgramIdDeclaration>, ApiError> {
-    let Some(idl_path) = find_program_idl_path(root, artifact_stem) else {
-        return Ok(None);
-    };
-    let text = read_text_file_limited(&idl_path, MAX_PROGRAM_IDL_JSON_BYTES, "Anchor IDL")?;
-    let value: Value = serde_json::from_str(&text)
-        .map_err(|_| program_source_error("Anchor IDL 不是有效 JSON"))?;
-    let Some(program_id) = json_pointer_string(&value, "/address")
-        .or_else(|| json_pointer_string(&value, "/metadata/address"))
-    else {
-        return Ok(None);
-    };
-    Ok(Some(ProgramIdDeclaration {
-        label: format!("{} address", path_string(&idl_path)),
-        program_id,
-    }))
-}
+    #[test]
+    fn expiry_classifier_blocks_every_observed_history_state() {
+        let non_finalized = TransactionStatus {
+            slot: 1,
+            confirmations: Some(1),
+            status: Ok(()),
+            err: None,
+            confirmation_status: None,
+        };
+        assert_eq!(
+            classify_deployment_attempt_expiry(43, 42, Some(&non_finalized), 200, 199),
+            DeploymentAttemptExpiryDecision::SeenButNotFinalized
+        );
 
-fn rust_declare_id_candidate_paths(root: &FsPath, artifact_stem: Option<&str>) -> Vec<PathBuf> {
-    let mut candidates = Vec::new();
-    if let Some(stem) = artifact_stem.and_then(safe_artifact_stem) {
-        candidates.push(root.join(format!("programs/{stem}/src/lib.rs")));
-    }
-    candidates.push(root.join("src/lib.rs"));
-    let exact_candidates: Vec<PathBuf> = candidates
-        .into_iter()
-        .filter_map(|path| canonical_child_path(root, &path, "Rust declare_id 源文件").ok())
-        .filter(|path| path.is_file())
-        .collect();
-    if !exact_candidates.is_empty() {
-        return exact_candidates;
-    }
-    newest_file(
-        fs::read_dir(root.join("programs"))
-            .ok()
-            .into_iter()
-            .flat_map(|entries| entries.filter_map(Result::ok))
-            .map(|entry| entry.path().join("src/lib.rs"))
-            .filter_map(|path| canonical_child_path(root, &path, "Rust declare_id 源文件").ok())
-            .filter(|path| path.is_file())
-            .collect(),
-    )
-    .into_iter()
-    .collect()
-}
+        let finalized_success = TransactionStatus {
+            slot: 2,
+            confirmations: None,
+            status: Ok(()),
+            err: None,
+            confirmation_status: None,
+        };
+        assert_eq!(
+            classify_deployment_attempt_expiry(43, 42, Some(&finalized_success), 201, 200),
+            DeploymentAttemptExpiryDecision::FinalizedSucceeded
+        );
 
-fn parse_rust_declare_id_values(text: &str) -> Vec<String> {
-    let mut values = Vec::new();
-    let mut rest = text;
-    while let Some(index) = rest.find("declare_id!") {
-        rest = &rest[index + "declare_id!".len()..];
-        let Some(open_quote) = rest.find('"') else {
-            break;
+        let transaction_error = solana_sdk::transaction::TransactionError::AccountNotFound;
+        let finalized_failure = TransactionStatus {
+            slot: 3,
+            confirmations: None,
+            status: Err(transaction_error.clone()),
+            err: Some(transaction_error),
+            confirmation_status: None,
         };
-        rest = &rest[open_quote + 1..];
-        let Some(close_quote) = rest.find('"') else {
-            break;
+        assert_eq!(
+            classify_deployment_attempt_expiry(43, 42, Some(&finalized_failure), 202, 201),
+            DeploymentAttemptExpiryDecision::FinalizedFailed("AccountNotFound".to_string())
+        );
+    }
+
+    #[test]
+    fn malformed_attempt_evidence_is_rejected_before_recovery() {
+        let program_id = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+        let buffer = Pubkey::new_unique();
+        let record = record(&program_id, &authority, &buffer);
+        let attempt = wallet_store::ProgramDeploymentAttemptRecord {
+            genesis_hash: record.genesis_hash.clone(),
+            program_id: record.program_id.clone(),
+            stage: wallet_store::PROGRAM_DEPLOYMENT_STAGE_WRITE.to_string(),
+            buffer_address: record.buffer_address.clone(),
+            chunk_index: Some(0),
+            signature: Signature::new_unique().to_string(),
+            last_valid_block_height: 42,
+            status: wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_SIGNED.to_string(),
+            created_at: 1,
+            updated_at: 1,
         };
-        let value = rest[..close_quote].trim();
-        if !value.is_empty() {
-            values.push(value.to_string());
+        assert!(validate_program_deployment_attempt_record(&record, &attempt).is_ok());
+
+        let mut invalid_signature = attempt.clone();
+        invalid_signature.signature = "not-a-signature".to_string();
+        let mut default_signature = attempt.clone();
+        default_signature.signature = Signature::default().to_string();
+        let mut noncanonical_signature = attempt.clone();
+        noncanonical_signature.signature.push(' ');
+        let mut zero_buffer = attempt.clone();
+        zero_buffer.buffer_address = Pubkey::default().to_string();
+        let mut noncanonical_buffer = attempt.clone();
+        noncanonical_buffer.buffer_address.push(' ');
+        let mut zero_height = attempt.clone();
+        zero_height.last_valid_block_height = 0;
+        let mut zero_created_at = attempt.clone();
+        zero_created_at.created_at = 0;
+        let mut reversed_timestamps = attempt.clone();
+        reversed_timestamps.updated_at = 0;
+        for invalid in [
+            invalid_signature,
+            default_signature,
+            noncanonical_signature,
+            zero_buffer,
+            noncanonical_buffer,
+            zero_height,
+            zero_created_at,
+            reversed_timestamps,
+        ] {
+            assert!(validate_program_deployment_attempt_record(&record, &invalid).is_err());
         }
-        rest = &rest[close_quote + 1..];
     }
-    values
-}
 
-fn replace_rust_declare_id_values(text: &str, program_id: &str) -> (String, usize) {
-    let mut output = String::with_capacity(text.len());
-    let mut rest = text;
-    let mut replacements = 0usize;
-    while let Some(index) = rest.find("declare_id!") {
-        output.push_str(&rest[..index + "declare_id!".len()]);
-        rest = &rest[index + "declare_id!".len()..];
-        let Some(open_quote) = rest.find('"') else {
-            output.push_str(rest);
-            return (output, replacements);
-        };
-        output.push_str(&rest[..open_quote + 1]);
-        rest = &rest[open_quote + 1..];
-        let Some(close_quote) = rest.find('"') else {
-            output.push_str(rest);
-            return (output, replacements);
-        };
-        output.push_str(program_id);
-        rest = &rest[close_quote..];
-        replacements += 1;
+    #[test]
+    fn generic_deploy_request_rejects_unknown_policy_fields() {
+        let request = serde_json::from_value::<DeployProgramRequest>(serde_json::json!({
+            "program_keypair_json": "[]",
+            "expected_program_id": Pubkey::new_unique().to_string(),
+            "expected_upgrade_authority": Pubkey::new_unique().to_string(),
+            "expected_genesis_hash": solana_sdk::hash::Hash::new_unique().to_string(),
+            "expected_program_sha256": "11".repeat(32),
+            "program_so_base64": "f0VMRg==",
+            "network": "devnet",
+            "require_authenticated_keystore": true
+        }));
+
+        assert!(request.is_err());
     }
-    output.push_str(rest);
-    (output, replacements)
-}
 
-fn rust_declare_id_declarations(
-    root: &FsPath,
-    artifact_stem: Option<&str>,
-) -> Result<Vec<ProgramIdDeclaration>, ApiError> {
-    let mut declarations = Vec::new();
-    for path in rust_declare_id_candidate_paths(root, artifact_stem) {
-        let text = read_text_file_limited(&path, 512 * 1024, "Rust declare_id 源文件")?;
-        for program_id in parse_rust_declare_id_values(&text) {
-            declarations.push(ProgramIdDeclaration {
-                label: format!("{} declare_id!", path_string(&path)),
-                program_id,
-            });
-        }
+    #[test]
+    fn canonical_program_id_uses_generic_journal_rules() {
+        let program_id = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+        let buffer = Pubkey::new_unique();
+        let record = record(&program_id, &authority, &buffer);
+
+        assert_eq!(
+            validate_deployment_journal_binding(
+                &record,
+                "devnet-genesis",
+                &program_id,
+                &"11".repeat(32),
+                1024,
+                2048,
+                &authority,
+                Some(&buffer),
+            )
+            .unwrap(),
+            buffer
+        );
     }
-    Ok(declarations)
-}
 
-fn update_rust_declare_id_files(
-    root: &FsPath,
-    artifact_stem: Option<&str>,
-    program_id: &str,
-) -> Result<Vec<String>, ApiError> {
-    let mut updated = Vec::new();
-    for path in rust_declare_id_candidate_paths(root, artifact_stem) {
-        let text = read_text_file_limited(&path, 512 * 1024, "Rust declare_id 源文件")?;
-        let (next_text, replacements) = replace_rust_declare_id_values(&text, program_id);
-        if replacements == 0 || next_text == text {
-            continue;
-        }
-        fs::write(&path, next_text)
-            .map_err(|error| program_source_error(format!("更新 declare_id! 失败: {error}")))?;
-        updated.push(path_string(&path));
+    #[test]
+    fn deployment_capacity_errors_have_retryable_http_statuses() {
+        assert_eq!(
+            ApiError {
+                message: PROGRAM_DEPLOY_BUSY_MESSAGE.to_string(),
+            }
+            .status_code(),
+            StatusCode::CONFLICT
+        );
+        assert_eq!(
+            ApiError {
+                message: SBF_VERIFY_BUSY_MESSAGE.to_string(),
+            }
+            .status_code(),
+            StatusCode::TOO_MANY_REQUESTS
+        );
     }
-    Ok(updated)
 }
 
-fn update_anchor_program_ids(
-    root: &FsPath,
-    artifact_stem: Option<&str>,
-    program_id: &str,
-) -> Result<Vec<String>, ApiError> {
-    let anchor_path = root.join("Anchor.toml");
-    if !anchor_path.is_file() {
-        return Ok(Vec::new());
-    }
-    let text = read_text_file_limited(&anchor_path, 512 * 1024, "Anchor.toml")?;
-    let wanted_stem = artifact_stem.and_then(safe_artifact_stem);
-    let mut in_programs_section = false;
-    let mut replacements = 0usize;
-    let mut output = String::with_capacity(text.len());
-    for line in text.lines() {
-        let trimmed = line.trim();
-        if trimmed.starts_with('[') && trimmed.ends_with(']') {
-            let section = trimmed.trim_start_matches('[').trim_end_matches(']');
-            in_programs_section = section.starts_with("programs.");
-            output.push_str(line);
-            output.push('\n');
-            continue;
-        }
-        if !in_programs_section || trimmed.starts_with('#') || !trimmed.contains('=') {
-            output.push_str(line);
-            output.push('\n');
-            continue;
-        }
-        let Some((name, _value)) = trimmed.split_once('=') else {
-            output.push_str(line);
-            output.push('\n');
-            continue;
-        };
-        let Some(program_name) = safe_artifact_stem(name.trim()) else {
-            output.push_str(line);
-            output.push('\n');
-            continue;
-        };
-        if wanted_stem
-            .as_ref()
-            .is_some_and(|wanted_stem| wanted_stem != &program_name)
-        {
-            output.push_str(line);
-            output.push('\n');
-            continue;
-        }
-        let indent = line
-            .chars()
-            .take_while(|character| character.is_whitespace())
-            .collect::<String>();
-        output.push_str(&format!("{indent}{program_name} = \"{program_id}\"\n"));
-        replacements += 1;
+#[derive(Deserialize)]
+struct ProgramDeploySourceRequest {
+    source_dir: String,
+    #[serde(default)]
+    build: bool,
+    #[serde(default)]
+    confirm_untrusted_build: bool,
+    #[serde(default)]
+    network: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ProgramDeploySourceResponse {
+    source_dir: String,
+    built: bool,
+    build_status: Option<String>,
+    build_job_id: Option<String>,
+    build_error: Option<String>,
+    build_command: Option<String>,
+    build_template: Option<String>,
+    build_stdout: Option<String>,
+    build_stderr: Option<String>,
+    program_so_path: Option<String>,
+    program_so_name: Option<String>,
+    program_so_base64: Option<String>,
+    program_so_sha256: Option<String>,
+    program_so_size: Option<usize>,
+    program_keypair_path: Option<String>,
+    expected_program_id: Option<String>,
+    manifest_program_id: Option<String>,
+    manifest_network: Option<String>,
+    manifest_genesis_hash: Option<String>,
+    manifest_upgrade_authority: Option<String>,
+    manifest_owner_admin: Option<String>,
+    manifest_operational_admin: Option<String>,
+    build_available: bool,
+    build_blocked_reason: Option<String>,
+    source_validation_errors: Vec<String>,
+    warnings: Vec<String>,
+}
+
+struct ProgramSourceArtifacts {
+    program_so_path: Option<PathBuf>,
+    program_so_name: Option<String>,
+    program_so_base64: Option<String>,
+    program_so_sha256: Option<String>,
+    program_so_size: Option<usize>,
+    program_keypair_path: Option<PathBuf>,
+    expected_program_id: Option<String>,
+    manifest_program_id: Option<String>,
+    manifest_network: Option<String>,
+    manifest_genesis_hash: Option<String>,
+    manifest_upgrade_authority: Option<String>,
+    manifest_owner_admin: Option<String>,
+    manifest_operational_admin: Option<String>,
+    source_validation_errors: Vec<String>,
+    warnings: Vec<String>,
+}
+
+struct ProgramIdDeclaration {
+    label: String,
+    program_id: String,
+}
+
+#[derive(Clone)]
+struct ProgramSourceBuildJob {
+    source_dir: PathBuf,
+    status: String,
+    build_command: Option<String>,
+    build_template: Option<String>,
+    build_stdout: Option<String>,
+    build_stderr: Option<String>,
+    build_error: Option<String>,
+    warnings: Vec<String>,
+}
+
+fn program_source_build_jobs(
+) -> &'static Arc<tokio::sync::Mutex<HashMap<String, ProgramSourceBuildJob>>> {
+    PROGRAM_SOURCE_BUILD_JOBS.get_or_init(|| Arc::new(tokio::sync::Mutex::new(HashMap::new())))
+}
+
+fn program_source_error(message: impl Into<String>) -> ApiError {
+    ApiError {
+        message: message.into(),
     }
-    let output = if text.ends_with('\n') {
-        output
-    } else {
-        output.trim_end_matches('\n').to_string()
-    };
-    if replacements == 0 || output == text {
-        return Ok(Vec::new());
+}
+
+fn canonical_child_path(root: &FsPath, path: &FsPath, label: &str) -> Result<PathBuf, ApiError> {
+    let canonical = path
+        .canonicalize()
+        .map_err(|error| program_source_error(format!("读取 {label} 路径失败: {error}")))?;
+    if !canonical.starts_with(root) {
+        return Err(program_source_error(format!("{label} 必须位于源码目录内")));
     }
-    fs::write(&anchor_path, output)
-        .map_err(|error| program_source_error(format!("更新 Anchor.toml 失败: {error}")))?;
-    Ok(vec![path_string(&anchor_path)])
+    Ok(canonical)
 }
 
-fn push_program_id_validation_error(
-    errors: &mut Vec<String>,
+fn read_text_file_limited(
+    path: &FsPath,
+    max_bytes: usize,
     label: &str,
-    source_program_id: &str,
-    expected_program_id: &str,
-) {
-    if Pubkey::from_str(source_program_id).is_err() {
-        errors.push(format!(
-            "{label} 的 Program ID 不是有效 Solana 地址：{source_program_id}。请修正后重新编译。"
-        ));
-        return;
+) -> Result<String, ApiError> {
+    let metadata = fs::metadata(path)
+        .map_err(|error| program_source_error(format!("读取 {label} 元数据失败: {error}")))?;
+    if !metadata.is_file() {
+        return Err(program_source_error(format!("{label} 不是普通文件")));
     }
-    if source_program_id != expected_program_id {
-        errors.push(format!(
-            "源码/制品 Program ID 不一致：{label} 为 {source_program_id}，但当前 Program keypair 派生为 {expected_program_id}。请同步 declare_id!、Anchor.toml、IDL/编译产物后重新编译，或选择匹配的 Program keypair。"
-        ));
+    if metadata.len() > max_bytes as u64 {
+        return Err(program_source_error(format!("{label} 文件过大")));
     }
+    fs::read_to_string(path)
+        .map_err(|error| program_source_error(format!("读取 {label} 失败: {error}")))
 }
 
-fn push_program_so_declared_id_validation_error(
-    errors: &mut Vec<String>,
+fn read_bytes_file_limited(
+    path: &FsPath,
+    max_bytes: usize,
     label: &str,
-    program_bytes: &[u8],
-    expected_program_id: &str,
-) {
-    let Ok(expected_program_id) = Pubkey::from_str(expected_program_id) else {
-        return;
-    };
-    if let Err(error) =
-        program_deploy::require_anchor_declared_program_id(program_bytes, &expected_program_id)
-    {
-        errors.push(format!("{label}: {error}"));
+) -> Result<Vec<u8>, ApiError> {
+    let metadata = fs::metadata(path)
+        .map_err(|error| program_source_error(format!("读取 {label} 元数据失败: {error}")))?;
+    if !metadata.is_file() {
+        return Err(program_source_error(format!("{label} 不是普通文件")));
+    }
+    if metadata.len() == 0 || metadata.len() > max_bytes as u64 {
+        return Err(program_source_error(format!("{label} 大小无效")));
     }
+    fs::read(path).map_err(|error| program_source_error(format!("读取 {label} 失败: {error}")))
 }
 
-fn collect_source_validation_errors(
-    root: &FsPath,
-    artifact_stem: Option<&str>,
-    network: Option<&str>,
-    expected_program_id: Option<&str>,
-    program_so: Option<(&str, &[u8])>,
-) -> Result<Vec<String>, ApiError> {
-    let Some(expected_program_id) = expected_program_id else {
-        return Ok(Vec::new());
-    };
-    let mut errors = Vec::new();
-    for declaration in anchor_program_id_declarations(root, artifact_stem, network) {
-        push_program_id_validation_error(
-            &mut errors,
-            &declaration.label,
-            &declaration.program_id,
-            expected_program_id,
-        );
-    }
-    if let Some(declaration) = parse_idl_program_id(root, artifact_stem)? {
-        push_program_id_validation_error(
-            &mut errors,
-            &declaration.label,
-            &declaration.program_id,
-            expected_program_id,
-        );
-    }
-    for declaration in rust_declare_id_declarations(root, artifact_stem)? {
-        push_program_id_validation_error(
-            &mut errors,
-            &declaration.label,
-            &declaration.program_id,
-            expected_program_id,
-        );
-    }
-    if let Some((label, program_bytes)) = program_so {
-        push_program_so_declared_id_validation_error(
-            &mut errors,
-            label,
-            program_bytes,
-            expected_program_id,
-        );
-    }
-    errors.sort();
-    errors.dedup();
-    Ok(errors)
+fn first_existing_path_owned(root: &FsPath, candidates: &[String]) -> Option<PathBuf> {
+    candidates
+        .iter()
+        .map(|relative| root.join(relative))
+        .filter_map(|path| canonical_child_path(root, &path, "候选产物").ok())
+        .find(|path| path.is_file())
 }
 
-fn collect_prebuild_source_validation_errors(
-    root: &FsPath,
-    artifact_stem: Option<&str>,
-    network: Option<&str>,
-    expected_program_id: Option<&str>,
-) -> Result<Vec<String>, ApiError> {
-    let Some(expected_program_id) = expected_program_id else {
-        return Ok(Vec::new());
-    };
-    let mut errors = Vec::new();
-    for declaration in anchor_program_id_declarations(root, artifact_stem, network) {
-        push_program_id_validation_error(
-            &mut errors,
-            &declaration.label,
-            &declaration.program_id,
-            expected_program_id,
-        );
-    }
-    for declaration in rust_declare_id_declarations(root, artifact_stem)? {
-        push_program_id_validation_error(
-            &mut errors,
-            &declaration.label,
-            &declaration.program_id,
-            expected_program_id,
-        );
-    }
-    errors.sort();
-    errors.dedup();
-    Ok(errors)
+fn first_json_file(root: &FsPath, directory: &FsPath) -> Option<PathBuf> {
+    fs::read_dir(directory)
+        .ok()?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter_map(|path| canonical_child_path(root, &path, "JSON 产物").ok())
+        .filter(|path| {
+            path.is_file()
+                && path
+                    .extension()
+                    .and_then(|ext| ext.to_str())
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case("json"))
+        })
+        .min()
 }
 
-fn expected_program_id_from_keypair_path(path: &FsPath) -> Result<String, ApiError> {
-    let mut json = read_text_file_limited(
-        path,
-        program_deploy::MAX_PROGRAM_KEYPAIR_JSON_BYTES,
-        "Program keypair",
-    )?;
-    let keypair =
-        program_deploy::parse_program_keypair_json(&json).map_err(program_source_error)?;
-    json.zeroize();
-    Ok(keypair.pubkey().to_string())
+fn file_modified_time_key(path: &FsPath) -> SystemTime {
+    path.metadata()
+        .and_then(|metadata| metadata.modified())
+        .unwrap_or(SystemTime::UNIX_EPOCH)
 }
 
-fn expected_program_id_from_source_keypair(
-    root: &FsPath,
-    artifact_stem: Option<&str>,
-) -> Result<Option<String>, ApiError> {
-    find_program_keypair_path(root, artifact_stem)
-        .as_deref()
-        .map(expected_program_id_from_keypair_path)
-        .transpose()
+fn newest_file(paths: Vec<PathBuf>) -> Option<PathBuf> {
+    paths.into_iter().max_by(|left, right| {
+        file_modified_time_key(left)
+            .cmp(&file_modified_time_key(right))
+            .then_with(|| left.cmp(right))
+    })
 }
 
-fn source_validation_blocked_warning(error_count: usize) -> String {
-    format!(
-        "部署前一致性校验发现 {error_count} 个阻断问题，已停止部署。请复制错误信息修复后重新编译。"
+fn so_file_paths(root: &FsPath, directory: &FsPath) -> Vec<PathBuf> {
+    fs::read_dir(directory)
+        .ok()
+        .into_iter()
+        .flat_map(|entries| entries.filter_map(Result::ok))
+        .map(|entry| entry.path())
+        .filter_map(|path| canonical_child_path(root, &path, ".so 产物").ok())
+        .filter(|path| {
+            path.is_file()
+                && path
+                    .extension()
+                    .and_then(|ext| ext.to_str())
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case("so"))
+        })
+        .collect()
+}
+
+fn newest_existing_path_owned(root: &FsPath, candidates: &[String]) -> Option<PathBuf> {
+    newest_file(
+        candidates
+            .iter()
+            .map(|relative| root.join(relative))
+            .filter_map(|path| canonical_child_path(root, &path, "候选产物").ok())
+            .filter(|path| path.is_file())
+            .collect(),
     )
 }
 
-fn release_metadata_sha_and_len(
-    root: &FsPath,
-    artifact_stem: Option<&str>,
-) -> Result<(Option<String>, Option<usize>), ApiError> {
-    let mut candidates = Vec::new();
-    if let Some(stem) = artifact_stem.and_then(safe_artifact_stem) {
-        candidates.push(format!("target/verifiable/{stem}-build.json"));
-    }
-    let Some(metadata_path) = first_existing_path_owned(root, &candidates)
-        .or_else(|| newest_build_metadata_file(root, &root.join("target/verifiable")))
-    else {
-        return Ok((None, None));
-    };
-    let text = read_text_file_limited(&metadata_path, 128 * 1024, "build metadata")?;
-    let value: Value = serde_json::from_str(&text)
-        .map_err(|_| program_source_error("build metadata 不是有效 JSON"))?;
-    let sha = json_pointer_string(&value, "/artifacts/program/sha256")
-        .filter(|value| value.len() == 64 && value.bytes().all(|byte| byte.is_ascii_hexdigit()))
-        .map(|value| value.to_ascii_lowercase());
-    let len = value
-        .pointer("/artifacts/program/length")
-        .and_then(Value::as_u64)
-        .and_then(|value| usize::try_from(value).ok());
-    Ok((sha, len))
+fn newest_build_metadata_file(root: &FsPath, directory: &FsPath) -> Option<PathBuf> {
+    newest_file(
+        fs::read_dir(directory)
+            .ok()?
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter_map(|path| canonical_child_path(root, &path, "build metadata").ok())
+            .filter(|path| {
+                let Some(name) = path.file_name().and_then(|value| value.to_str()) else {
+                    return false;
+                };
+                path.is_file() && name.ends_with("-build.json")
+            })
+            .collect(),
+    )
 }
 
-type ReleaseManifestValues = (
-    Option<String>,
-    Option<String>,
-    Option<String>,
-    Option<String>,
-    Option<String>,
-);
+fn safe_artifact_stem(value: &str) -> Option<String> {
+    let stem = value.trim().replace('-', "_");
+    if stem.is_empty()
+        || stem.len() > 128
+        || !stem
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || byte == b'_')
+    {
+        return None;
+    }
+    Some(stem)
+}
 
-fn release_manifest_values(root: &FsPath) -> Result<ReleaseManifestValues, ApiError> {
-    let Some(manifest_path) = find_release_manifest_path(root) else {
-        return Ok((None, None, None, None, None));
-    };
-    let text = read_text_file_limited(&manifest_path, 512 * 1024, "release manifest")?;
-    let value: Value = serde_json::from_str(&text)
-        .map_err(|_| program_source_error("release manifest 不是有效 JSON"))?;
-    Ok((
-        json_pointer_string(&value, "/target/network"),
-        json_pointer_string(&value, "/target/genesis_hash"),
-        json_pointer_string(&value, "/target/upgrade_authority"),
-        json_pointer_string(&value, "/target/owner_admin"),
-        json_pointer_string(&value, "/target/admin"),
-    ))
+fn artifact_stem_from_so_name(value: &str) -> Option<String> {
+    let name = value.trim();
+    let stem = name
+        .strip_suffix(".so")
+        .or_else(|| name.strip_suffix(".SO"))
+        .or_else(|| {
+            name.rsplit_once('.').and_then(|(stem, ext)| {
+                if ext.eq_ignore_ascii_case("so") {
+                    Some(stem)
+                } else {
+                    None
+                }
+            })
+        })
+        .unwrap_or(name);
+    safe_artifact_stem(stem)
 }
 
-fn load_program_source_artifacts(
-    root: &FsPath,
-    artifact_stem: Option<&str>,
-    fallback_network: Option<&str>,
-    warn_missing_artifacts: bool,
-) -> Result<ProgramSourceArtifacts, ApiError> {
-    let program_so_path = find_program_so_path(root, artifact_stem);
-    let program_keypair_path = find_program_keypair_path(root, artifact_stem);
-    let (metadata_sha, metadata_len) = release_metadata_sha_and_len(root, artifact_stem)?;
-    let (
-        mut manifest_network,
-        manifest_genesis_hash,
-        manifest_upgrade_authority,
-        manifest_owner_admin,
-        manifest_operational_admin,
-    ) = release_manifest_values(root)?;
-    if manifest_network.is_none() {
-        manifest_network = fallback_network
-            .map(str::trim)
-            .filter(|value| matches!(*value, "mainnet" | "devnet" | "testnet"))
-            .map(ToOwned::to_owned);
+fn parse_quoted_toml_value(value: &str) -> Option<String> {
+    let trimmed = value.trim();
+    if let Some(rest) = trimmed.strip_prefix('"') {
+        return rest
+            .split_once('"')
+            .map(|(value, _)| value.trim().to_string())
+            .filter(|value| !value.is_empty());
     }
-    let manifest_program_id = parse_anchor_program_id(
-        root,
-        artifact_stem,
-        manifest_network.as_deref().or(fallback_network),
-    );
+    trimmed
+        .split('#')
+        .next()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::to_string)
+}
 
-    let mut warnings = Vec::new();
-    let mut expected_program_id = None;
-    if let Some(path) = program_keypair_path.as_ref() {
-        let mut json = read_text_file_limited(
-            path,
-            program_deploy::MAX_PROGRAM_KEYPAIR_JSON_BYTES,
-            "Program keypair",
-        )?;
-        let keypair =
-            program_deploy::parse_program_keypair_json(&json).map_err(program_source_error)?;
-        json.zeroize();
-        expected_program_id = Some(keypair.pubkey().to_string());
-    } else if warn_missing_artifacts {
-        warnings.push(
-            "未找到 target/deploy/*-keypair.json（也未找到可用的 .keys/*.json 回退）".to_string(),
-        );
+fn parse_anchor_program_name(root: &FsPath) -> Option<String> {
+    let anchor = fs::read_to_string(root.join("Anchor.toml")).ok()?;
+    let mut in_programs_section = false;
+    for line in anchor.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') && trimmed.ends_with(']') {
+            in_programs_section = trimmed.starts_with("[programs.");
+            continue;
+        }
+        if !in_programs_section || trimmed.starts_with('#') || !trimmed.contains('=') {
+            continue;
+        }
+        let Some((name, value)) = trimmed.split_once('=') else {
+            continue;
+        };
+        let Some(program_id) = parse_quoted_toml_value(value) else {
+            continue;
+        };
+        if Pubkey::from_str(&program_id).is_ok() {
+            if let Some(stem) = safe_artifact_stem(name) {
+                return Some(stem);
+            }
+        }
     }
+    None
+}
 
-    let mut program_so_base64 = None;
-    let mut program_so_sha256 = None;
-    let mut program_so_size = None;
-    let mut program_so_name = None;
-    let mut program_so_validation: Option<(String, Vec<u8>)> = None;
-    if let Some(path) = program_so_path.as_ref() {
-        let bytes = read_bytes_file_limited(path, MAX_PROGRAM_SO_BYTES, ".so 文件")?;
-        let actual_sha = program_deploy::sha256_hex(&bytes);
-        let actual_len = bytes.len();
-        if let Some(expected_sha) = metadata_sha.as_ref() {
-            if expected_sha != &actual_sha {
-                warnings.push(format!(
-                    "build metadata SHA-256 为 {expected_sha}，但 .so 实际为 {actual_sha}"
-                ));
-            }
+fn parse_cargo_package_name(root: &FsPath) -> Option<String> {
+    let cargo = fs::read_to_string(root.join("Cargo.toml")).ok()?;
+    let mut in_package_section = false;
+    for line in cargo.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') && trimmed.ends_with(']') {
+            in_package_section = trimmed == "[package]";
+            continue;
         }
-        if let Some(expected_len) = metadata_len {
-            if expected_len != actual_len {
-                warnings.push(format!(
-                    "build metadata 长度为 {expected_len}，但 .so 实际为 {actual_len}"
-                ));
-            }
+        if !in_package_section || trimmed.starts_with('#') {
+            continue;
         }
-        program_so_base64 = Some(BASE64.encode(&bytes));
-        program_so_sha256 = Some(actual_sha);
-        program_so_size = Some(actual_len);
-        program_so_validation = Some((path_string(path), bytes));
-        program_so_name = path
-            .file_name()
-            .and_then(|value| value.to_str())
-            .map(ToOwned::to_owned);
-    } else if warn_missing_artifacts {
-        warnings.push(
-            "未找到 target/deploy/<program>.so（也未找到 target/verifiable/*.so 回退）".to_string(),
-        );
+        let Some((name, value)) = trimmed.split_once('=') else {
+            continue;
+        };
+        if name.trim() != "name" {
+            continue;
+        }
+        let package_name = parse_quoted_toml_value(value)?;
+        return safe_artifact_stem(&package_name);
     }
+    None
+}
 
-    let source_validation_errors = collect_source_validation_errors(
-        root,
-        artifact_stem,
-        manifest_network.as_deref().or(fallback_network),
-        expected_program_id.as_deref(),
-        program_so_validation
-            .as_ref()
-            .map(|(label, bytes)| (label.as_str(), bytes.as_slice())),
-    )?;
-    if warn_missing_artifacts && !source_validation_errors.is_empty() {
-        warnings.push(source_validation_blocked_warning(
-            source_validation_errors.len(),
-        ));
+fn detect_program_artifact_stem(root: &FsPath) -> Option<String> {
+    parse_anchor_program_name(root).or_else(|| parse_cargo_package_name(root))
+}
+
+fn find_program_so_path(root: &FsPath, artifact_stem: Option<&str>) -> Option<PathBuf> {
+    // Anchor's canonical deploy artifacts live under target/deploy/:
+    //   target/deploy/<program_name>.so
+    //   target/deploy/<program_name>-keypair.json
+    // Prefer that pair so the .so and keypair stay in lockstep. Verifiable
+    // builds are only a fallback when deploy output is missing.
+    let mut deploy_candidates = Vec::new();
+    let mut verifiable_candidates = Vec::new();
+    if let Some(stem) = artifact_stem.and_then(safe_artifact_stem) {
+        deploy_candidates.push(format!("target/deploy/{stem}.so"));
+        verifiable_candidates.push(format!("target/verifiable/{stem}.so"));
     }
+    newest_existing_path_owned(root, &deploy_candidates)
+        .or_else(|| newest_file(so_file_paths(root, &root.join("target/deploy"))))
+        .or_else(|| newest_existing_path_owned(root, &verifiable_candidates))
+        .or_else(|| newest_file(so_file_paths(root, &root.join("target/verifiable"))))
+}
 
-    Ok(ProgramSourceArtifacts {
-        program_so_path,
-        program_so_name,
-        program_so_base64,
-        program_so_sha256: program_so_sha256.clone(),
-        program_so_size,
-        program_keypair_path,
-        expected_program_id,
-        manifest_program_id,
-        manifest_network,
-        manifest_genesis_hash,
-        manifest_upgrade_authority,
-        manifest_owner_admin,
-        manifest_operational_admin,
-        source_validation_errors,
-        warnings,
-    })
+fn find_verifiable_program_so_path(root: &FsPath, artifact_stem: Option<&str>) -> Option<PathBuf> {
+    // Verification compares against the reproducible build first; plain deploy
+    // output is only used when no verifiable artifact exists.
+    let mut candidates = Vec::new();
+    if let Some(stem) = artifact_stem.and_then(safe_artifact_stem) {
+        candidates.push(format!("target/verifiable/{stem}.so"));
+    }
+    newest_existing_path_owned(root, &candidates)
+        .or_else(|| newest_file(so_file_paths(root, &root.join("target/verifiable"))))
+        .or_else(|| find_program_so_path(root, artifact_stem))
 }
 
-fn next_program_source_build_job_id() -> String {
-    let millis = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .map(|value| value.as_millis())
-        .unwrap_or_default();
-    format!("build-{millis}")
+fn find_program_keypair_path(root: &FsPath, artifact_stem: Option<&str>) -> Option<PathBuf> {
+    // Prefer Anchor's build output under target/deploy/. Historical copies under
+    // .keys/ are often stale and must not override the keypair that matches the
+    // just-built .so / declare_id!.
+    let mut deploy_candidates = Vec::new();
+    let mut keys_candidates = Vec::new();
+    if let Some(stem) = artifact_stem.and_then(safe_artifact_stem) {
+        deploy_candidates.push(format!("target/deploy/{stem}-keypair.json"));
+        keys_candidates.push(format!(".keys/{stem}-program-keypair.json"));
+        keys_candidates.push(format!(".keys/{stem}-keypair.json"));
+    }
+    newest_existing_path_owned(root, &deploy_candidates)
+        .or_else(|| first_json_file(root, &root.join("target/deploy")))
+        .or_else(|| newest_existing_path_owned(root, &keys_candidates))
+        .or_else(|| first_json_file(root, &root.join(".keys")))
 }
 
-async fn start_program_source_build_job(
-    source_dir: PathBuf,
-    build_plans: Vec<ProgramSourceBuildPlan>,
-    build_blocked_reason: Option<String>,
-    artifact_stem: Option<String>,
-    initial_warnings: Vec<String>,
-) -> (String, Option<String>, Option<String>) {
-    let job_id = next_program_source_build_job_id();
-    let build_command = build_plans
-        .first()
-        .map(|plan| display_program_source_build_command(&source_dir, plan));
-    let build_template = build_plans.first().map(|plan| plan.template.clone());
-    let job = ProgramSourceBuildJob {
-        source_dir: source_dir.clone(),
-        status: "running".to_string(),
-        build_command: build_command.clone(),
-        build_template: build_template.clone(),
-        build_stdout: None,
-        build_stderr: None,
-        build_error: None,
-        warnings: initial_warnings,
-    };
-    program_source_build_jobs()
-        .lock()
-        .await
-        .insert(job_id.clone(), job);
+fn find_program_idl_path(root: &FsPath, artifact_stem: Option<&str>) -> Option<PathBuf> {
+    let mut candidates = Vec::new();
+    if let Some(stem) = artifact_stem.and_then(safe_artifact_stem) {
+        candidates.push(format!("target/idl/{stem}.json"));
+    }
+    first_existing_path_owned(root, &candidates)
+        .or_else(|| first_json_file(root, &root.join("target/idl")))
+}
 
-    let jobs = Arc::clone(program_source_build_jobs());
-    let task_job_id = job_id.clone();
-    tokio::spawn(async move {
-        let result = execute_program_source_build(
-            &source_dir,
-            &build_plans,
-            build_blocked_reason,
-            artifact_stem.as_deref(),
-            find_program_so_path,
-        )
-        .await;
-        let mut jobs = jobs.lock().await;
-        if let Some(job) = jobs.get_mut(&task_job_id) {
-            match result {
-                Ok(outcome) => {
-                    job.status = "completed".to_string();
-                    job.build_command = Some(outcome.command);
-                    job.build_template = Some(outcome.template);
-                    job.build_stdout = Some(outcome.stdout);
-                    job.build_stderr = Some(outcome.stderr);
-                    job.warnings.extend(outcome.warnings);
-                }
-                Err(error) => {
-                    job.status = "failed".to_string();
-                    job.build_error = Some(error.message);
-                }
-            }
-        }
-    });
+fn find_release_manifest_path(root: &FsPath) -> Option<PathBuf> {
+    fs::read_dir(root.join("docs"))
+        .ok()?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter_map(|path| canonical_child_path(root, &path, "release manifest").ok())
+        .filter(|path| {
+            let Some(name) = path.file_name().and_then(|value| value.to_str()) else {
+                return false;
+            };
+            path.is_file()
+                && name.contains("RELEASE_MANIFEST")
+                && name.ends_with(".json")
+                && !name.contains(".example.")
+        })
+        .min()
+}
 
-    (job_id, build_command, build_template)
+fn path_string(path: &FsPath) -> String {
+    path.to_string_lossy().to_string()
 }
 
-async fn program_deploy_source(
-    Json(req): Json<ProgramDeploySourceRequest>,
-) -> Result<Json<ProgramDeploySourceResponse>, ApiError> {
-    let source_dir_raw = req.source_dir.trim();
-    if source_dir_raw.is_empty() || source_dir_raw.len() > 4096 {
-        return Err(program_source_error("源码目录路径无效"));
-    }
-    let source_dir = PathBuf::from(source_dir_raw)
-        .canonicalize()
-        .map_err(|error| program_source_error(format!("源码目录不存在或不可访问: {error}")))?;
-    if !source_dir.is_dir() {
-        return Err(program_source_error("源码目录不是目录"));
-    }
+fn json_pointer_string(value: &Value, pointer: &str) -> Option<String> {
+    value
+        .pointer(pointer)
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .filter(|value| !value.is_empty() && !value.starts_with("REPLACE_WITH_"))
+        .map(ToOwned::to_owned)
+}
 
-    let (build_plans, build_blocked_reason) = program_source_build_plans(&source_dir);
-    let build_available = !build_plans.is_empty();
-    let mut build_command = build_plans.first().map(|plan| plan.display_command.clone());
-    let mut build_template = build_plans.first().map(|plan| plan.template.clone());
-    let artifact_stem = detect_program_artifact_stem(&source_dir);
-    let built = false;
-    let mut build_status = None;
-    let mut build_job_id = None;
-    let build_error = None;
-    let build_stdout = None;
-    let build_stderr = None;
-    let mut build_warnings = Vec::new();
-    if let Some(warning) = program_source_keys_build_warning(&source_dir) {
-        build_warnings.push(warning);
-    }
-    if req.build {
-        if !req.confirm_untrusted_build {
-            return Err(program_source_error(
-                "源码构建会执行所选目录中的项目脚本或 Makefile；请求必须显式确认 confirm_untrusted_build=true"
-                    .to_string(),
-            ));
-        }
-        build_warnings.push(
-            "已确认执行所选源码目录的构建命令；仅应对可信或已审计源码使用自动构建".to_string(),
-        );
-        if build_plans.is_empty() {
-            return Err(program_source_error(
-                build_blocked_reason
-                    .clone()
-                    .unwrap_or_else(|| "当前源码目录不可自动编译".to_string()),
-            ));
+fn anchor_program_id_declarations(
+    root: &FsPath,
+    artifact_stem: Option<&str>,
+    network: Option<&str>,
+) -> Vec<ProgramIdDeclaration> {
+    let Ok(anchor) = fs::read_to_string(root.join("Anchor.toml")) else {
+        return Vec::new();
+    };
+    let wanted_stem = artifact_stem.and_then(safe_artifact_stem);
+    let wanted_network = network
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(ToOwned::to_owned);
+    let mut current_programs_section: Option<String> = None;
+    let mut declarations = Vec::new();
+    for line in anchor.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') && trimmed.ends_with(']') {
+            let section = trimmed.trim_start_matches('[').trim_end_matches(']');
+            current_programs_section = section
+                .strip_prefix("programs.")
+                .map(str::trim)
+                .filter(|value| !value.is_empty())
+                .map(ToOwned::to_owned);
+            continue;
         }
-        let expected_program_id =
-            expected_program_id_from_source_keypair(&source_dir, artifact_stem.as_deref())?;
-        let prebuild_validation_errors = collect_prebuild_source_validation_errors(
-            &source_dir,
-            artifact_stem.as_deref(),
-            req.network.as_deref(),
-            expected_program_id.as_deref(),
-        )?;
-        if !prebuild_validation_errors.is_empty() {
-            let mut artifacts = load_program_source_artifacts(
-                &source_dir,
-                artifact_stem.as_deref(),
-                req.network.as_deref(),
-                true,
-            )?;
-            artifacts.source_validation_errors = prebuild_validation_errors;
-            build_warnings.append(&mut artifacts.warnings);
-            build_warnings.push(format!(
-                "编译未启动：{}",
-                source_validation_blocked_warning(artifacts.source_validation_errors.len())
-            ));
-            return Ok(Json(ProgramDeploySourceResponse {
-                source_dir: path_string(&source_dir),
-                built,
-                build_status: Some("blocked".to_string()),
-                build_job_id: None,
-                build_error: None,
-                build_command,
-                build_template,
-                build_stdout,
-                build_stderr,
-                program_so_path: artifacts
-                    .program_so_path
-                    .as_ref()
-                    .map(|path| path_string(path)),
-                program_so_name: artifacts.program_so_name,
-                program_so_base64: artifacts.program_so_base64,
-                program_so_sha256: artifacts.program_so_sha256,
-                program_so_size: artifacts.program_so_size,
-                program_keypair_path: artifacts
-                    .program_keypair_path
-                    .as_ref()
-                    .map(|path| path_string(path)),
-                expected_program_id: artifacts.expected_program_id.or(expected_program_id),
-                manifest_program_id: artifacts.manifest_program_id,
-                manifest_network: artifacts.manifest_network,
-                manifest_genesis_hash: artifacts.manifest_genesis_hash,
-                manifest_upgrade_authority: artifacts.manifest_upgrade_authority,
-                manifest_owner_admin: artifacts.manifest_owner_admin,
-                manifest_operational_admin: artifacts.manifest_operational_admin,
-                build_available,
-                build_blocked_reason,
-                source_validation_errors: artifacts.source_validation_errors,
-                warnings: build_warnings,
-            }));
+        let Some(section_network) = current_programs_section.as_ref() else {
+            continue;
+        };
+        if let Some(wanted_network) = wanted_network.as_ref() {
+            if section_network != wanted_network {
+                continue;
+            }
         }
-        let (job_id, job_command, job_template) = start_program_source_build_job(
-            source_dir.clone(),
-            build_plans.clone(),
-            build_blocked_reason.clone(),
-            artifact_stem.clone(),
-            build_warnings.clone(),
-        )
-        .await;
-        build_command = job_command;
-        build_template = job_template;
-        build_status = Some("running".to_string());
-        build_job_id = Some(job_id);
-        build_warnings.push("编译已在后台开始，完成后会自动重新读取部署信息".to_string());
+        if trimmed.starts_with('#') || !trimmed.contains('=') {
+            continue;
+        }
+        let Some((name, value)) = trimmed.split_once('=') else {
+            continue;
+        };
+        let Some(program_name) = safe_artifact_stem(name.trim()) else {
+            continue;
+        };
+        if let Some(wanted_stem) = wanted_stem.as_ref() {
+            if &program_name != wanted_stem {
+                continue;
+            }
+        }
+        let Some(value) = parse_quoted_toml_value(value) else {
+            continue;
+        };
+        declarations.push(ProgramIdDeclaration {
+            label: format!("Anchor.toml [programs.{section_network}] {program_name}"),
+            program_id: value,
+        });
     }
-
-    let mut artifacts = load_program_source_artifacts(
-        &source_dir,
-        artifact_stem.as_deref(),
-        req.network.as_deref(),
-        true,
-    )?;
-    build_warnings.append(&mut artifacts.warnings);
-
-    Ok(Json(ProgramDeploySourceResponse {
-        source_dir: path_string(&source_dir),
-        built,
-        build_status,
-        build_job_id,
-        build_error,
-        build_command,
-        build_template,
-        build_stdout,
-        build_stderr,
-        program_so_path: artifacts
-            .program_so_path
-            .as_ref()
-            .map(|path| path_string(path)),
-        program_so_name: artifacts.program_so_name,
-        program_so_base64: artifacts.program_so_base64,
-        program_so_sha256: artifacts.program_so_sha256,
-        program_so_size: artifacts.program_so_size,
-        program_keypair_path: artifacts
-            .program_keypair_path
-            .as_ref()
-            .map(|path| path_string(path)),
-        expected_program_id: artifacts.expected_program_id,
-        manifest_program_id: artifacts.manifest_program_id,
-        manifest_network: artifacts.manifest_network,
-        manifest_genesis_hash: artifacts.manifest_genesis_hash,
-        manifest_upgrade_authority: artifacts.manifest_upgrade_authority,
-        manifest_owner_admin: artifacts.manifest_owner_admin,
-        manifest_operational_admin: artifacts.manifest_operational_admin,
-        build_available,
-        build_blocked_reason,
-        source_validation_errors: artifacts.source_validation_errors,
-        warnings: build_warnings,
-    }))
+    if declarations.is_empty() && wanted_network.is_some() {
+        return anchor_program_id_declarations(root, artifact_stem, None);
+    }
+    declarations
 }
 
-#[derive(Deserialize)]
-struct ProgramKeypairArtifactRequest {
-    source_dir: String,
-    #[serde(default)]
-    artifact_stem: Option<String>,
-    #[serde(default)]
-    program_so_name: Option<String>,
+fn parse_anchor_program_id(
+    root: &FsPath,
+    artifact_stem: Option<&str>,
+    network: Option<&str>,
+) -> Option<String> {
+    anchor_program_id_declarations(root, artifact_stem, network)
+        .into_iter()
+        .next()
+        .map(|declaration| declaration.program_id)
 }
 
-#[derive(Serialize)]
-struct ProgramKeypairArtifactResponse {
-    source_dir: String,
-    artifact_stem: Option<String>,
-    program_keypair_path: Option<String>,
-    expected_program_id: Option<String>,
-    warnings: Vec<String>,
+fn parse_idl_program_id(
+    root: &FsPath,
+    artifact_stem: Option<&str>,
+) -> Result<Option<ProgramIdDeclaration>, ApiError> {
+    let Some(idl_path) = find_program_idl_path(root, artifact_stem) else {
+        return Ok(None);
+    };
+    let text = read_text_file_limited(&idl_path, MAX_PROGRAM_IDL_JSON_BYTES, "Anchor IDL")?;
+    let value: Value = serde_json::from_str(&text)
+        .map_err(|_| program_source_error("Anchor IDL 不是有效 JSON"))?;
+    let Some(program_id) = json_pointer_string(&value, "/address")
+        .or_else(|| json_pointer_string(&value, "/metadata/address"))
+    else {
+        return Ok(None);
+    };
+    Ok(Some(ProgramIdDeclaration {
+        label: format!("{} address", path_string(&idl_path)),
+        program_id,
+    }))
 }
 
-async fn program_keypair_artifact(
-    Json(req): Json<ProgramKeypairArtifactRequest>,
-) -> Result<Json<ProgramKeypairArtifactResponse>, ApiError> {
-    let source_dir_raw = req.source_dir.trim();
-    if source_dir_raw.is_empty() || source_dir_raw.len() > 4096 {
-        return Err(program_source_error("源码目录路径无效"));
+fn rust_declare_id_candidate_paths(root: &FsPath, artifact_stem: Option<&str>) -> Vec<PathBuf> {
+    let mut candidates = Vec::new();
+    if let Some(stem) = artifact_stem.and_then(safe_artifact_stem) {
+        candidates.push(root.join(format!("programs/{stem}/src/lib.rs")));
     }
-    let source_dir = PathBuf::from(source_dir_raw)
-        .canonicalize()
-        .map_err(|error| program_source_error(format!("源码目录不存在或不可访问: {error}")))?;
-    if !source_dir.is_dir() {
-        return Err(program_source_error("源码目录不是目录"));
+    candidates.push(root.join("src/lib.rs"));
+    let exact_candidates: Vec<PathBuf> = candidates
+        .into_iter()
+        .filter_map(|path| canonical_child_path(root, &path, "Rust declare_id 源文件").ok())
+        .filter(|path| path.is_file())
+        .collect();
+    if !exact_candidates.is_empty() {
+        return exact_candidates;
     }
-    let artifact_stem = req
-        .artifact_stem
-        .as_deref()
-        .and_then(safe_artifact_stem)
-        .or_else(|| {
-            req.program_so_name
-                .as_deref()
-                .and_then(artifact_stem_from_so_name)
-        })
-        .or_else(|| detect_program_artifact_stem(&source_dir));
-    let mut warnings = Vec::new();
-    let Some(program_keypair_path) =
-        find_program_keypair_path(&source_dir, artifact_stem.as_deref())
-    else {
-        warnings.push(
-            "未找到 target/deploy/<program>-keypair.json（也未找到可用的 .keys/*.json 回退）"
-                .to_string(),
-        );
-        return Ok(Json(ProgramKeypairArtifactResponse {
-            source_dir: path_string(&source_dir),
-            artifact_stem,
-            program_keypair_path: None,
-            expected_program_id: None,
-            warnings,
-        }));
-    };
-    let mut json = read_text_file_limited(
-        &program_keypair_path,
-        program_deploy::MAX_PROGRAM_KEYPAIR_JSON_BYTES,
-        "Program keypair",
-    )?;
-    let keypair =
-        program_deploy::parse_program_keypair_json(&json).map_err(program_source_error)?;
-    json.zeroize();
-    Ok(Json(ProgramKeypairArtifactResponse {
-        source_dir: path_string(&source_dir),
-        artifact_stem,
-        program_keypair_path: Some(path_string(&program_keypair_path)),
-        expected_program_id: Some(keypair.pubkey().to_string()),
-        warnings,
-    }))
-}
-
-#[derive(Deserialize)]
-struct ProgramGenerateKeypairRequest {
-    source_dir: String,
-    #[serde(default)]
-    artifact_stem: Option<String>,
-    #[serde(default)]
-    program_so_name: Option<String>,
-    #[serde(default)]
-    update_source: bool,
-}
-
-#[derive(Serialize)]
-struct ProgramGenerateKeypairResponse {
-    source_dir: String,
-    artifact_stem: String,
-    program_keypair_path: String,
-    backup_program_keypair_path: Option<String>,
-    expected_program_id: String,
-    updated_source_files: Vec<String>,
-    warnings: Vec<String>,
+    newest_file(
+        fs::read_dir(root.join("programs"))
+            .ok()
+            .into_iter()
+            .flat_map(|entries| entries.filter_map(Result::ok))
+            .map(|entry| entry.path().join("src/lib.rs"))
+            .filter_map(|path| canonical_child_path(root, &path, "Rust declare_id 源文件").ok())
+            .filter(|path| path.is_file())
+            .collect(),
+    )
+    .into_iter()
+    .collect()
 }
 
-async fn program_generate_keypair(
-    Json(req): Json<ProgramGenerateKeypairRequest>,
-) -> Result<Json<ProgramGenerateKeypairResponse>, ApiError> {
-    let source_dir_raw = req.source_dir.trim();
-    if source_dir_raw.is_empty() || source_dir_raw.len() > 4096 {
-        return Err(program_source_error("源码目录路径无效"));
-    }
-    let source_dir = PathBuf::from(source_dir_raw)
-        .canonicalize()
-        .map_err(|error| program_source_error(format!("源码目录不存在或不可访问: {error}")))?;
-    if !source_dir.is_dir() {
-        return Err(program_source_error("源码目录不是目录"));
-    }
-    let artifact_stem = req
-        .artifact_stem
-        .as_deref()
-        .and_then(safe_artifact_stem)
-        .or_else(|| {
-            req.program_so_name
-                .as_deref()
-                .and_then(artifact_stem_from_so_name)
-        })
-        .or_else(|| detect_program_artifact_stem(&source_dir))
-        .ok_or_else(|| program_source_error("无法识别程序名称，请先编译或选择 .so 文件"))?;
-
-    let keypair = Keypair::new();
-    let program_id = keypair.pubkey().to_string();
-    let deploy_dir = source_dir.join("target/deploy");
-    fs::create_dir_all(&deploy_dir)
-        .map_err(|error| program_source_error(format!("创建 target/deploy 失败: {error}")))?;
-    let keypair_path = deploy_dir.join(format!("{artifact_stem}-keypair.json"));
-    let backup_path = if keypair_path.exists() {
-        let timestamp = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
-        let backup_path = deploy_dir.join(format!("{artifact_stem}-keypair.json.bak-{timestamp}"));
-        fs::rename(&keypair_path, &backup_path).map_err(|error| {
-            program_source_error(format!("备份旧 Program keypair 失败: {error}"))
-        })?;
-        Some(backup_path)
-    } else {
-        None
-    };
-    let keypair_json = serde_json::to_string(&keypair.to_bytes().to_vec())
-        .map_err(|error| program_source_error(format!("序列化 Program keypair 失败: {error}")))?;
-    fs::write(&keypair_path, keypair_json)
-        .map_err(|error| program_source_error(format!("写入 Program keypair 失败: {error}")))?;
-
-    let mut warnings = Vec::new();
-    let mut updated_source_files = Vec::new();
-    if req.update_source {
-        let mut anchor_updates =
-            update_anchor_program_ids(&source_dir, Some(&artifact_stem), &program_id)?;
-        let mut rust_updates =
-            update_rust_declare_id_files(&source_dir, Some(&artifact_stem), &program_id)?;
-        updated_source_files.append(&mut anchor_updates);
-        updated_source_files.append(&mut rust_updates);
-        updated_source_files.sort();
-        updated_source_files.dedup();
-        if updated_source_files.is_empty() {
-            warnings.push(
-                "已生成新的 Program keypair，但未找到可自动更新的 Anchor.toml 或 declare_id!；请手动同步 Program ID 后重新编译。"
-                    .to_string(),
-            );
-        } else {
-            warnings.push(
-                "已生成新的 Program keypair，并同步 Anchor.toml / declare_id!；请重新编译后部署。"
-                    .to_string(),
-            );
+fn parse_rust_declare_id_values(text: &str) -> Vec<String> {
+    let mut values = Vec::new();
+    let mut rest = text;
+    while let Some(index) = rest.find("declare_id!") {
+        rest = &rest[index + "declare_id!".len()..];
+        let Some(open_quote) = rest.find('"') else {
+            break;
+        };
+        rest = &rest[open_quote + 1..];
+        let Some(close_quote) = rest.find('"') else {
+            break;
+        };
+        let value = rest[..close_quote].trim();
+        if !value.is_empty() {
+            values.push(value.to_string());
         }
-    } else {
-        warnings.push(
-            "已生成新的 Program keypair；请手动同步 Anchor.toml / declare_id! 后重新编译。"
-                .to_string(),
-        );
+        rest = &rest[close_quote + 1..];
     }
-
-    Ok(Json(ProgramGenerateKeypairResponse {
-        source_dir: path_string(&source_dir),
-        artifact_stem,
-        program_keypair_path: path_string(&keypair_path),
-        backup_program_keypair_path: backup_path.as_ref().map(|path| path_string(path)),
-        expected_program_id: program_id,
-        updated_source_files,
-        warnings,
-    }))
+    values
 }
 
-#[derive(Deserialize)]
-struct ProgramIdlRequest {
-    source_dir: String,
-    #[serde(default)]
-    artifact_stem: Option<String>,
+fn replace_rust_declare_id_values(text: &str, program_id: &str) -> (String, usize) {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+    let mut replacements = 0usize;
+    while let Some(index) = rest.find("declare_id!") {
+        output.push_str(&rest[..index + "declare_id!".len()]);
+        rest = &rest[index + "declare_id!".len()..];
+        let Some(open_quote) = rest.find('"') else {
+            output.push_str(rest);
+            return (output, replacements);
+        };
+        output.push_str(&rest[..open_quote + 1]);
+        rest = &rest[open_quote + 1..];
+        let Some(close_quote) = rest.find('"') else {
+            output.push_str(rest);
+            return (output, replacements);
+        };
+        output.push_str(program_id);
+        rest = &rest[close_quote..];
+        replacements += 1;
+    }
+    output.push_str(rest);
+    (output, replacements)
 }
 
-#[derive(Serialize)]
-struct ProgramIdlResponse {
-    source_dir: String,
-    idl_path: String,
-    idl_json: Value,
+fn rust_declare_id_declarations(
+    root: &FsPath,
+    artifact_stem: Option<&str>,
+) -> Result<Vec<ProgramIdDeclaration>, ApiError> {
+    let mut declarations = Vec::new();
+    for path in rust_declare_id_candidate_paths(root, artifact_stem) {
+        let text = read_text_file_limited(&path, 512 * 1024, "Rust declare_id 源文件")?;
+        for program_id in parse_rust_declare_id_values(&text) {
+            declarations.push(ProgramIdDeclaration {
+                label: format!("{} declare_id!", path_string(&path)),
+                program_id,
+            });
+        }
+    }
+    Ok(declarations)
 }
 
-async fn program_idl(
-    Json(req): Json<ProgramIdlRequest>,
-) -> Result<Json<ProgramIdlResponse>, ApiError> {
-    let source_dir_raw = req.source_dir.trim();
-    if source_dir_raw.is_empty() || source_dir_raw.len() > 4096 {
-        return Err(program_source_error("源码目录路径无效"));
-    }
-    let source_dir = PathBuf::from(source_dir_raw)
-        .canonicalize()
-        .map_err(|error| program_source_error(format!("源码目录不存在或不可访问: {error}")))?;
-    if !source_dir.is_dir() {
-        return Err(program_source_error("源码目录不是目录"));
-    }
-    let artifact_stem = req
-        .artifact_stem
-        .as_deref()
-        .and_then(safe_artifact_stem)
-        .or_else(|| detect_program_artifact_stem(&source_dir));
-    let idl_path = find_program_idl_path(&source_dir, artifact_stem.as_deref())
-        .ok_or_else(|| program_source_error("未找到 target/idl/*.json，请先编译项目"))?;
-    let text = read_text_file_limited(&idl_path, MAX_PROGRAM_IDL_JSON_BYTES, "Anchor IDL")?;
-    let idl_json: Value = serde_json::from_str(&text)
-        .map_err(|_| program_source_error("Anchor IDL 不是有效 JSON"))?;
-    if idl_json
-        .get("instructions")
-        .and_then(Value::as_array)
-        .is_none_or(|items| items.is_empty())
-    {
-        return Err(program_source_error("Anchor IDL 缺少 instructions"));
+fn update_rust_declare_id_files(
+    root: &FsPath,
+    artifact_stem: Option<&str>,
+    program_id: &str,
+) -> Result<Vec<String>, ApiError> {
+    let mut updated = Vec::new();
+    for path in rust_declare_id_candidate_paths(root, artifact_stem) {
+        let text = read_text_file_limited(&path, 512 * 1024, "Rust declare_id 源文件")?;
+        let (next_text, replacements) = replace_rust_declare_id_values(&text, program_id);
+        if replacements == 0 || next_text == text {
+            continue;
+        }
+        fs::write(&path, next_text)
+            .map_err(|error| program_source_error(format!("更新 declare_id! 失败: {error}")))?;
+        updated.push(path_string(&path));
     }
-    Ok(Json(ProgramIdlResponse {
-        source_dir: path_string(&source_dir),
-        idl_path: path_string(&idl_path),
-        idl_json,
-    }))
+    Ok(updated)
 }
 
-#[derive(Deserialize)]
-struct GenericProgramAccountMetaRequest {
-    pubkey: String,
-    is_signer: bool,
-    is_writable: bool,
+fn update_anchor_program_ids(
+    root: &FsPath,
+    artifact_stem: Option<&str>,
+    program_id: &str,
+) -> Result<Vec<String>, ApiError> {
+    let anchor_path = root.join("Anchor.toml");
+    if !anchor_path.is_file() {
+        return Ok(Vec::new());
+    }
+    let text = read_text_file_limited(&anchor_path, 512 * 1024, "Anchor.toml")?;
+    let wanted_stem = artifact_stem.and_then(safe_artifact_stem);
+    let mut in_programs_section = false;
+    let mut replacements = 0usize;
+    let mut output = String::with_capacity(text.len());
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') && trimmed.ends_with(']') {
+            let section = trimmed.trim_start_matches('[').trim_end_matches(']');
+            in_programs_section = section.starts_with("programs.");
+            output.push_str(line);
+            output.push('\n');
+            continue;
+        }
+        if !in_programs_section || trimmed.starts_with('#') || !trimmed.contains('=') {
+            output.push_str(line);
+            output.push('\n');
+            continue;
+        }
+        let Some((name, _value)) = trimmed.split_once('=') else {
+            output.push_str(line);
+            output.push('\n');
+            continue;
+        };
+        let Some(program_name) = safe_artifact_stem(name.trim()) else {
+            output.push_str(line);
+            output.push('\n');
+            continue;
+        };
+        if wanted_stem
+            .as_ref()
+            .is_some_and(|wanted_stem| wanted_stem != &program_name)
+        {
+            output.push_str(line);
+            output.push('\n');
+            continue;
+        }
+        let indent = line
+            .chars()
+            .take_while(|character| character.is_whitespace())
+            .collect::<String>();
+        output.push_str(&format!("{indent}{program_name} = \"{program_id}\"\n"));
+        replacements += 1;
+    }
+    let output = if text.ends_with('\n') {
+        output
+    } else {
+        output.trim_end_matches('\n').to_string()
+    };
+    if replacements == 0 || output == text {
+        return Ok(Vec::new());
+    }
+    fs::write(&anchor_path, output)
+        .map_err(|error| program_source_error(format!("更新 Anchor.toml 失败: {error}")))?;
+    Ok(vec![path_string(&anchor_path)])
 }
 
-#[derive(Deserialize)]
-struct ProgramInvokeRequest {
-    #[serde(flatten)]
-    wallet: WalletAuthRequest,
-    program_id: String,
-    instruction_name: String,
-    accounts: Vec<GenericProgramAccountMetaRequest>,
-    data_base64: String,
-    #[serde(default)]
-    network: Option<String>,
-    #[serde(default)]
-    mode: Option<String>,
-    #[serde(default)]
-    additional_signers: Vec<GenericProgramAdditionalSignerRequest>,
+fn push_program_id_validation_error(
+    errors: &mut Vec<String>,
+    label: &str,
+    source_program_id: &str,
+    expected_program_id: &str,
+) {
+    if Pubkey::from_str(source_program_id).is_err() {
+        errors.push(format!(
+            "{label} 的 Program ID 不是有效 Solana 地址：{source_program_id}。请修正后重新编译。"
+        ));
+        return;
+    }
+    if source_program_id != expected_program_id {
+        errors.push(format!(
+            "源码/制品 Program ID 不一致：{label} 为 {source_program_id}，但当前 Program keypair 派生为 {expected_program_id}。请同步 declare_id!、Anchor.toml、IDL/编译产物后重新编译，或选择匹配的 Program keypair。"
+        ));
+    }
 }
 
-#[derive(Serialize)]
-struct ProgramInvokeResponse {
-    status: String,
-    signature: Option<String>,
-    simulation_error: Option<String>,
-    logs: Vec<String>,
+fn push_program_so_declared_id_validation_error(
+    errors: &mut Vec<String>,
+    label: &str,
+    program_bytes: &[u8],
+    expected_program_id: &str,
+) {
+    let Ok(expected_program_id) = Pubkey::from_str(expected_program_id) else {
+        return;
+    };
+    if let Err(error) =
+        program_deploy::require_anchor_declared_program_id(program_bytes, &expected_program_id)
+    {
+        errors.push(format!("{label}: {error}"));
+    }
 }
 
-#[derive(Deserialize)]
-struct ExternalSignSubmitRequest {
-    #[serde(flatten)]
-    wallet: WalletAuthRequest,
-    #[serde(default, alias = "requiredSigner")]
-    required_signer: String,
-    #[serde(default, alias = "transactionBase64")]
-    transaction_base64: String,
-    #[serde(default, alias = "transactionFormat")]
-    transaction_format: Option<String>,
-    #[serde(default)]
-    network: Option<String>,
-    #[serde(default, alias = "requestId")]
-    request_id: Option<String>,
-    #[serde(default, alias = "expiresAt")]
-    expires_at: Option<u64>,
-    #[serde(default, alias = "expectedGenesisHash")]
-    expected_genesis_hash: Option<String>,
-    #[serde(default, alias = "recentBlockhash")]
-    recent_blockhash: Option<String>,
-    #[serde(default, alias = "lastValidBlockHeight")]
-    last_valid_block_height: Option<u64>,
+fn collect_source_validation_errors(
+    root: &FsPath,
+    artifact_stem: Option<&str>,
+    network: Option<&str>,
+    expected_program_id: Option<&str>,
+    program_so: Option<(&str, &[u8])>,
+) -> Result<Vec<String>, ApiError> {
+    let Some(expected_program_id) = expected_program_id else {
+        return Ok(Vec::new());
+    };
+    let mut errors = Vec::new();
+    for declaration in anchor_program_id_declarations(root, artifact_stem, network) {
+        push_program_id_validation_error(
+            &mut errors,
+            &declaration.label,
+            &declaration.program_id,
+            expected_program_id,
+        );
+    }
+    if let Some(declaration) = parse_idl_program_id(root, artifact_stem)? {
+        push_program_id_validation_error(
+            &mut errors,
+            &declaration.label,
+            &declaration.program_id,
+            expected_program_id,
+        );
+    }
+    for declaration in rust_declare_id_declarations(root, artifact_stem)? {
+        push_program_id_validation_error(
+            &mut errors,
+            &declaration.label,
+            &declaration.program_id,
+            expected_program_id,
+        );
+    }
+    if let Some((label, program_bytes)) = program_so {
+        push_program_so_declared_id_validation_error(
+            &mut errors,
+            label,
+            program_bytes,
+            expected_program_id,
+        );
+    }
+    errors.sort();
+    errors.dedup();
+    Ok(errors)
 }
 
-#[derive(Serialize)]
-struct ExternalSignSubmitResponse {
-    status: String,
-    signature: String,
-    slot: u64,
-    required_signer: String,
-    signed_by: String,
-    network: String,
-    request_id: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none", alias = "rawTransaction")]
-    raw_transaction: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none", alias = "recentBlockhash")]
-    recent_blockhash: Option<String>,
-    #[serde(
-        skip_serializing_if = "Option::is_none",
-        alias = "lastValidBlockHeight"
-    )]
-    last_valid_block_height: Option<u64>,
+fn collect_prebuild_source_validation_errors(
+    root: &FsPath,
+    artifact_stem: Option<&str>,
+    network: Option<&str>,
+    expected_program_id: Option<&str>,
+) -> Result<Vec<String>, ApiError> {
+    let Some(expected_program_id) = expected_program_id else {
+        return Ok(Vec::new());
+    };
+    let mut errors = Vec::new();
+    for declaration in anchor_program_id_declarations(root, artifact_stem, network) {
+        push_program_id_validation_error(
+            &mut errors,
+            &declaration.label,
+            &declaration.program_id,
+            expected_program_id,
+        );
+    }
+    for declaration in rust_declare_id_declarations(root, artifact_stem)? {
+        push_program_id_validation_error(
+            &mut errors,
+            &declaration.label,
+            &declaration.program_id,
+            expected_program_id,
+        );
+    }
+    errors.sort();
+    errors.dedup();
+    Ok(errors)
 }
 
-#[derive(Serialize)]
-struct ExternalSignOnlyResponse {
-    status: String,
-    required_signer: String,
-    signed_by: String,
-    request_id: Option<String>,
-    raw_transaction: String,
-    recent_blockhash: String,
+fn expected_program_id_from_keypair_path(path: &FsPath) -> Result<String, ApiError> {
+    let mut json = read_text_file_limited(
+        path,
+        program_deploy::MAX_PROGRAM_KEYPAIR_JSON_BYTES,
+        "Program keypair",
+    )?;
+    let keypair =
+        program_deploy::parse_program_keypair_json(&json).map_err(program_source_error)?;
+    json.zeroize();
+    Ok(keypair.pubkey().to_string())
 }
 
-#[derive(Deserialize)]
-struct ExternalSignMessageRequest {
-    #[serde(flatten)]
-    wallet: WalletAuthRequest,
-    #[serde(default, alias = "requiredSigner")]
-    required_signer: String,
-    #[serde(default, alias = "messageBase64")]
-    message_base64: String,
-    #[serde(default, alias = "requestId")]
-    request_id: Option<String>,
-    #[serde(default, alias = "expiresAt")]
-    expires_at: Option<u64>,
-}
-
-#[derive(Serialize)]
-struct ExternalSignMessageResponse {
-    status: String,
-    required_signer: String,
-    signed_by: String,
-    request_id: Option<String>,
-    signature: String,
-    message_base64: String,
+fn expected_program_id_from_source_keypair(
+    root: &FsPath,
+    artifact_stem: Option<&str>,
+) -> Result<Option<String>, ApiError> {
+    find_program_keypair_path(root, artifact_stem)
+        .as_deref()
+        .map(expected_program_id_from_keypair_path)
+        .transpose()
 }
 
-#[derive(Deserialize)]
-struct ExternalSignPreviewRequest {
-    #[serde(default, alias = "requiredSigner")]
-    required_signer: String,
-    transaction_base64: String,
-    #[serde(default, alias = "transactionFormat")]
-    transaction_format: Option<String>,
+fn source_validation_blocked_warning(error_count: usize) -> String {
+    format!(
+        "部署前一致性校验发现 {error_count} 个阻断问题，已停止部署。请复制错误信息修复后重新编译。"
+    )
 }
 
-#[derive(Serialize)]
-struct ExternalSignInstructionPreview {
-    index: usize,
-    program_id: String,
-    account_count: usize,
-    data_bytes: usize,
+fn release_metadata_sha_and_len(
+    root: &FsPath,
+    artifact_stem: Option<&str>,
+) -> Result<(Option<String>, Option<usize>), ApiError> {
+    let mut candidates = Vec::new();
+    if let Some(stem) = artifact_stem.and_then(safe_artifact_stem) {
+        candidates.push(format!("target/verifiable/{stem}-build.json"));
+    }
+    let Some(metadata_path) = first_existing_path_owned(root, &candidates)
+        .or_else(|| newest_build_metadata_file(root, &root.join("target/verifiable")))
+    else {
+        return Ok((None, None));
+    };
+    let text = read_text_file_limited(&metadata_path, 128 * 1024, "build metadata")?;
+    let value: Value = serde_json::from_str(&text)
+        .map_err(|_| program_source_error("build metadata 不是有效 JSON"))?;
+    let sha = json_pointer_string(&value, "/artifacts/program/sha256")
+        .filter(|value| value.len() == 64 && value.bytes().all(|byte| byte.is_ascii_hexdigit()))
+        .map(|value| value.to_ascii_lowercase());
+    let len = value
+        .pointer("/artifacts/program/length")
+        .and_then(Value::as_u64)
+        .and_then(|value| usize::try_from(value).ok());
+    Ok((sha, len))
 }
 
-#[derive(Serialize)]
-struct ExternalSignTransactionPreviewResponse {
-    status: String,
-    transaction_format: String,
-    recent_blockhash: String,
-    required_signatures: usize,
-    required_signer_present: bool,
-    signer_accounts: Vec<String>,
-    writable_accounts: Vec<String>,
-    readonly_accounts: Vec<String>,
-    programs: Vec<String>,
-    instructions: Vec<ExternalSignInstructionPreview>,
-    warnings: Vec<String>,
-}
+type ReleaseManifestValues = (
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+);
 
-#[derive(Deserialize)]
-struct GenericProgramAdditionalSignerRequest {
-    pubkey: String,
-    wallet_id: String,
-    password: String,
+fn release_manifest_values(root: &FsPath) -> Result<ReleaseManifestValues, ApiError> {
+    let Some(manifest_path) = find_release_manifest_path(root) else {
+        return Ok((None, None, None, None, None));
+    };
+    let text = read_text_file_limited(&manifest_path, 512 * 1024, "release manifest")?;
+    let value: Value = serde_json::from_str(&text)
+        .map_err(|_| program_source_error("release manifest 不是有效 JSON"))?;
+    Ok((
+        json_pointer_string(&value, "/target/network"),
+        json_pointer_string(&value, "/target/genesis_hash"),
+        json_pointer_string(&value, "/target/upgrade_authority"),
+        json_pointer_string(&value, "/target/owner_admin"),
+        json_pointer_string(&value, "/target/admin"),
+    ))
 }
 
-impl Drop for GenericProgramAdditionalSignerRequest {
-    fn drop(&mut self) {
-        self.password.zeroize();
+fn load_program_source_artifacts(
+    root: &FsPath,
+    artifact_stem: Option<&str>,
+    fallback_network: Option<&str>,
+    warn_missing_artifacts: bool,
+) -> Result<ProgramSourceArtifacts, ApiError> {
+    let program_so_path = find_program_so_path(root, artifact_stem);
+    let program_keypair_path = find_program_keypair_path(root, artifact_stem);
+    let (metadata_sha, metadata_len) = release_metadata_sha_and_len(root, artifact_stem)?;
+    let (
+        mut manifest_network,
+        manifest_genesis_hash,
+        manifest_upgrade_authority,
+        manifest_owner_admin,
+        manifest_operational_admin,
+    ) = release_manifest_values(root)?;
+    if manifest_network.is_none() {
+        manifest_network = fallback_network
+            .map(str::trim)
+            .filter(|value| matches!(*value, "mainnet" | "devnet" | "testnet"))
+            .map(ToOwned::to_owned);
     }
-}
+    let manifest_program_id = parse_anchor_program_id(
+        root,
+        artifact_stem,
+        manifest_network.as_deref().or(fallback_network),
+    );
 
-fn keypair_from_saved_wallet(
-    wallet_id: &str,
-    password: &str,
-    address_label: &str,
-) -> Result<Keypair, ApiError> {
-    let wallet_id = wallet_id.trim();
-    validate_wallet_id(wallet_id)?;
-    if password.is_empty() {
-        return Err(ApiError {
-            message: format!("{address_label} 需要提供钱包密码"),
-        });
-    }
-    let (keypair, wallet) =
-        keypair_from_saved_wallet_with_password(wallet_id, password, address_label)?;
-    let expected_pubkey = Pubkey::from_str(address_label).map_err(|_| ApiError {
-        message: format!("Signer 地址无效: {address_label}"),
-    })?;
-    if keypair.pubkey() != expected_pubkey || wallet.public_key != expected_pubkey.to_string() {
-        return Err(ApiError {
-            message: format!(
-                "Signer {} 选择的钱包不匹配，实际解锁为 {}",
-                expected_pubkey,
-                keypair.pubkey()
-            ),
-        });
+    let mut warnings = Vec::new();
+    let mut expected_program_id = None;
+    if let Some(path) = program_keypair_path.as_ref() {
+        let mut json = read_text_file_limited(
+            path,
+            program_deploy::MAX_PROGRAM_KEYPAIR_JSON_BYTES,
+            "Program keypair",
+        )?;
+        let keypair =
+            program_deploy::parse_program_keypair_json(&json).map_err(program_source_error)?;
+        json.zeroize();
+        expected_program_id = Some(keypair.pubkey().to_string());
+    } else if warn_missing_artifacts {
+        warnings.push(
+            "未找到 target/deploy/*-keypair.json（也未找到可用的 .keys/*.json 回退）".to_string(),
+        );
     }
-    Ok(keypair)
-}
 
-fn validate_external_sign_expiry(expires_at: Option<u64>) -> Result<(), ApiError> {
-    let Some(expires_at) = expires_at else {
-        return Ok(());
-    };
-    let now_ms = now_unix_ms_lossy();
-    let expires_at_ms = if expires_at < 10_000_000_000 {
-        expires_at.saturating_mul(1_000)
-    } else {
-        expires_at
-    };
-    if expires_at_ms <= now_ms {
-        return Err(ApiError {
-            message: "外部签名请求已过期".to_string(),
-        });
+    let mut program_so_base64 = None;
+    let mut program_so_sha256 = None;
+    let mut program_so_size = None;
+    let mut program_so_name = None;
+    let mut program_so_validation: Option<(String, Vec<u8>)> = None;
+    if let Some(path) = program_so_path.as_ref() {
+        let bytes = read_bytes_file_limited(path, MAX_PROGRAM_SO_BYTES, ".so 文件")?;
+        let actual_sha = program_deploy::sha256_hex(&bytes);
+        let actual_len = bytes.len();
+        if let Some(expected_sha) = metadata_sha.as_ref() {
+            if expected_sha != &actual_sha {
+                warnings.push(format!(
+                    "build metadata SHA-256 为 {expected_sha}，但 .so 实际为 {actual_sha}"
+                ));
+            }
+        }
+        if let Some(expected_len) = metadata_len {
+            if expected_len != actual_len {
+                warnings.push(format!(
+                    "build metadata 长度为 {expected_len}，但 .so 实际为 {actual_len}"
+                ));
+            }
+        }
+        program_so_base64 = Some(BASE64.encode(&bytes));
+        program_so_sha256 = Some(actual_sha);
+        program_so_size = Some(actual_len);
+        program_so_validation = Some((path_string(path), bytes));
+        program_so_name = path
+            .file_name()
+            .and_then(|value| value.to_str())
+            .map(ToOwned::to_owned);
+    } else if warn_missing_artifacts {
+        warnings.push(
+            "未找到 target/deploy/<program>.so（也未找到 target/verifiable/*.so 回退）".to_string(),
+        );
     }
-    Ok(())
-}
 
-fn decode_external_legacy_transaction(transaction_base64: &str) -> Result<Transaction, ApiError> {
-    let trimmed = transaction_base64.trim();
-    if trimmed.is_empty() {
-        return Err(ApiError {
-            message: "需要提供交易 base64".to_string(),
-        });
+    let source_validation_errors = collect_source_validation_errors(
+        root,
+        artifact_stem,
+        manifest_network.as_deref().or(fallback_network),
+        expected_program_id.as_deref(),
+        program_so_validation
+            .as_ref()
+            .map(|(label, bytes)| (label.as_str(), bytes.as_slice())),
+    )?;
+    if warn_missing_artifacts && !source_validation_errors.is_empty() {
+        warnings.push(source_validation_blocked_warning(
+            source_validation_errors.len(),
+        ));
     }
-    let bytes = BASE64.decode(trimmed).map_err(|error| ApiError {
-        message: format!("交易 base64 解码失败: {error}"),
-    })?;
-    if bytes.len() > SOLANA_TRANSACTION_PACKET_DATA_BYTES {
-        return Err(ApiError {
-            message: format!(
-                "交易数据过大: {} bytes，最大 {} bytes",
-                bytes.len(),
-                SOLANA_TRANSACTION_PACKET_DATA_BYTES
-            ),
-        });
-    }
-    bincode::deserialize::<Transaction>(&bytes).map_err(|error| ApiError {
-        message: format!("交易反序列化失败；当前仅支持 legacy Transaction base64: {error}"),
-    })
-}
 
-fn decode_external_versioned_transaction(
-    transaction_base64: &str,
-) -> Result<VersionedTransaction, ApiError> {
-    let trimmed = transaction_base64.trim();
-    if trimmed.is_empty() {
-        return Err(ApiError {
-            message: "需要提供交易 base64".to_string(),
-        });
-    }
-    let bytes = BASE64.decode(trimmed).map_err(|error| ApiError {
-        message: format!("交易 base64 解码失败: {error}"),
-    })?;
-    if bytes.len() > SOLANA_TRANSACTION_PACKET_DATA_BYTES {
-        return Err(ApiError {
-            message: format!(
-                "交易数据过大: {} bytes，最大 {} bytes",
-                bytes.len(),
-                SOLANA_TRANSACTION_PACKET_DATA_BYTES
-            ),
-        });
-    }
-    bincode::deserialize::<VersionedTransaction>(&bytes).map_err(|error| ApiError {
-        message: format!("versioned transaction 反序列化失败: {error}"),
+    Ok(ProgramSourceArtifacts {
+        program_so_path,
+        program_so_name,
+        program_so_base64,
+        program_so_sha256: program_so_sha256.clone(),
+        program_so_size,
+        program_keypair_path,
+        expected_program_id,
+        manifest_program_id,
+        manifest_network,
+        manifest_genesis_hash,
+        manifest_upgrade_authority,
+        manifest_owner_admin,
+        manifest_operational_admin,
+        source_validation_errors,
+        warnings,
     })
 }
 
-fn external_sign_program_label(program_id: &str) -> &'static str {
-    match program_id {
-        "11111111111111111111111111111111" => "System Program",
-        SPL_TOKEN_PROGRAM_ID => "SPL Token",
-        SPL_TOKEN_2022_PROGRAM_ID => "SPL Token 2022",
-        "ComputeBudget111111111111111111111111111111" => "Compute Budget",
-        "ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL" => "Associated Token Account",
-        UPGRADEABLE_LOADER_ID_STR => "Upgradeable Loader",
-        _ => "Unknown Program",
-    }
+fn next_program_source_build_job_id() -> String {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|value| value.as_millis())
+        .unwrap_or_default();
+    format!("build-{millis}")
 }
 
-fn external_sign_instruction_preview(
-    index: usize,
-    program_id: String,
-    account_count: usize,
-    data_bytes: usize,
-) -> ExternalSignInstructionPreview {
-    ExternalSignInstructionPreview {
-        index,
-        program_id: format!(
-            "{} ({})",
-            program_id,
-            external_sign_program_label(&program_id)
-        ),
-        account_count,
-        data_bytes,
-    }
-}
+async fn start_program_source_build_job(
+    source_dir: PathBuf,
+    build_plans: Vec<ProgramSourceBuildPlan>,
+    build_blocked_reason: Option<String>,
+    artifact_stem: Option<String>,
+    initial_warnings: Vec<String>,
+) -> (String, Option<String>, Option<String>) {
+    let job_id = next_program_source_build_job_id();
+    let build_command = build_plans
+        .first()
+        .map(|plan| display_program_source_build_command(&source_dir, plan));
+    let build_template = build_plans.first().map(|plan| plan.template.clone());
+    let job = ProgramSourceBuildJob {
+        source_dir: source_dir.clone(),
+        status: "running".to_string(),
+        build_command: build_command.clone(),
+        build_template: build_template.clone(),
+        build_stdout: None,
+        build_stderr: None,
+        build_error: None,
+        warnings: initial_warnings,
+    };
+    program_source_build_jobs()
+        .lock()
+        .await
+        .insert(job_id.clone(), job);
 
-fn preview_external_legacy_transaction(
-    transaction: &Transaction,
-    required_signer: Option<&Pubkey>,
-) -> ExternalSignTransactionPreviewResponse {
-    let message = &transaction.message;
-    let required_signatures = message.header.num_required_signatures as usize;
-    let signer_accounts = message
-        .account_keys
-        .iter()
-        .take(required_signatures)
-        .map(ToString::to_string)
-        .collect::<Vec<_>>();
-    let mut writable_accounts = Vec::new();
-    let mut readonly_accounts = Vec::new();
-    for (index, account) in message.account_keys.iter().enumerate() {
-        if message.is_maybe_writable(index, None) {
-            writable_accounts.push(account.to_string());
-        } else {
-            readonly_accounts.push(account.to_string());
-        }
-    }
-    let mut programs = Vec::new();
-    let instructions = message
-        .instructions
-        .iter()
-        .enumerate()
-        .map(|(index, instruction)| {
-            let program_id = message
-                .account_keys
-                .get(instruction.program_id_index as usize)
-                .map(ToString::to_string)
-                .unwrap_or_else(|| format!("account-index:{}", instruction.program_id_index));
-            if !programs.contains(&program_id) {
-                programs.push(program_id.clone());
+    let jobs = Arc::clone(program_source_build_jobs());
+    let task_job_id = job_id.clone();
+    tokio::spawn(async move {
+        let result = execute_program_source_build(
+            &source_dir,
+            &build_plans,
+            build_blocked_reason,
+            artifact_stem.as_deref(),
+            find_program_so_path,
+        )
+        .await;
+        let mut jobs = jobs.lock().await;
+        if let Some(job) = jobs.get_mut(&task_job_id) {
+            match result {
+                Ok(outcome) => {
+                    job.status = "completed".to_string();
+                    job.build_command = Some(outcome.command);
+                    job.build_template = Some(outcome.template);
+                    job.build_stdout = Some(outcome.stdout);
+                    job.build_stderr = Some(outcome.stderr);
+                    job.warnings.extend(outcome.warnings);
+                }
+                Err(error) => {
+                    job.status = "failed".to_string();
+                    job.build_error = Some(error.message);
+                }
             }
-            external_sign_instruction_preview(
-                index,
-                program_id,
-                instruction.accounts.len(),
-                instruction.data.len(),
-            )
-        })
-        .collect::<Vec<_>>();
-    let required_signer_present = required_signer
-        .map(|required| {
-            signer_accounts
-                .iter()
-                .any(|account| account == &required.to_string())
-        })
-        .unwrap_or(false);
-    let mut warnings = Vec::new();
-    if !required_signer_present {
-        warnings.push("requiredSigner 不在交易所需 signer 列表中；签名将被拒绝".to_string());
-    }
-    if programs
-        .iter()
-        .any(|program| external_sign_program_label(program) == "Unknown Program")
-    {
-        warnings.push("交易包含未识别 program，请确认 DApp 来源和指令意图".to_string());
-    }
+        }
+    });
 
-    ExternalSignTransactionPreviewResponse {
-        status: "success".to_string(),
-        transaction_format: "legacy".to_string(),
-        recent_blockhash: message.recent_blockhash.to_string(),
-        required_signatures,
-        required_signer_present,
-        signer_accounts,
-        writable_accounts,
-        readonly_accounts,
-        programs,
-        instructions,
-        warnings,
-    }
+    (job_id, build_command, build_template)
 }
 
-fn preview_external_versioned_transaction(
-    transaction: &VersionedTransaction,
-    required_signer: Option<&Pubkey>,
-) -> ExternalSignTransactionPreviewResponse {
-    let message = &transaction.message;
-    let required_signatures = message.header().num_required_signatures as usize;
-    let static_accounts = message.static_account_keys();
-    let signer_accounts = static_accounts
-        .iter()
-        .take(required_signatures)
-        .map(ToString::to_string)
-        .collect::<Vec<_>>();
-    let mut writable_accounts = Vec::new();
-    let mut readonly_accounts = Vec::new();
-    for (index, account) in static_accounts.iter().enumerate() {
-        if message.is_maybe_writable(index, None) {
-            writable_accounts.push(account.to_string());
-        } else {
-            readonly_accounts.push(account.to_string());
-        }
+async fn program_deploy_source(
+    Json(req): Json<ProgramDeploySourceRequest>,
+) -> Result<Json<ProgramDeploySourceResponse>, ApiError> {
+    let source_dir_raw = req.source_dir.trim();
+    if source_dir_raw.is_empty() || source_dir_raw.len() > 4096 {
+        return Err(program_source_error("源码目录路径无效"));
     }
-    let mut programs = Vec::new();
-    let instructions = message
-        .instructions()
-        .iter()
-        .enumerate()
-        .map(|(index, instruction)| {
-            let program_id = static_accounts
-                .get(instruction.program_id_index as usize)
-                .map(ToString::to_string)
-                .unwrap_or_else(|| format!("account-index:{}", instruction.program_id_index));
-            if !programs.contains(&program_id) {
-                programs.push(program_id.clone());
-            }
-            external_sign_instruction_preview(
-                index,
-                program_id,
-                instruction.accounts.len(),
-                instruction.data.len(),
-            )
-        })
-        .collect::<Vec<_>>();
-    let required_signer_present = required_signer
-        .map(|required| {
-            signer_accounts
-                .iter()
-                .any(|account| account == &required.to_string())
-        })
-        .unwrap_or(false);
-    let mut warnings = Vec::new();
-    if !required_signer_present {
-        warnings.push("requiredSigner 不在交易所需 signer 列表中；签名将被拒绝".to_string());
+    let source_dir = PathBuf::from(source_dir_raw)
+        .canonicalize()
+        .map_err(|error| program_source_error(format!("源码目录不存在或不可访问: {error}")))?;
+    if !source_dir.is_dir() {
+        return Err(program_source_error("源码目录不是目录"));
     }
-    if message
-        .address_table_lookups()
-        .is_some_and(|lookups| !lookups.is_empty())
-    {
-        warnings.push(
-            "v0 交易使用地址表；当前预览只展示静态账户，动态加载账户需在链上解析后核对".to_string(),
-        );
+
+    let (build_plans, build_blocked_reason) = program_source_build_plans(&source_dir);
+    let build_available = !build_plans.is_empty();
+    let mut build_command = build_plans.first().map(|plan| plan.display_command.clone());
+    let mut build_template = build_plans.first().map(|plan| plan.template.clone());
+    let artifact_stem = detect_program_artifact_stem(&source_dir);
+    let built = false;
+    let mut build_status = None;
+    let mut build_job_id = None;
+    let build_error = None;
+    let build_stdout = None;
+    let build_stderr = None;
+    let mut build_warnings = Vec::new();
+    if let Some(warning) = program_source_keys_build_warning(&source_dir) {
+        build_warnings.push(warning);
     }
-    if programs
-        .iter()
-        .any(|program| external_sign_program_label(program) == "Unknown Program")
-    {
-        warnings.push("交易包含未识别 program，请确认 DApp 来源和指令意图".to_string());
+    if req.build {
+        if !req.confirm_untrusted_build {
+            return Err(program_source_error(
+                "源码构建会执行所选目录中的项目脚本或 Makefile；请求必须显式确认 confirm_untrusted_build=true"
+                    .to_string(),
+            ));
+        }
+        build_warnings.push(
+            "已确认执行所选源码目录的构建命令；仅应对可信或已审计源码使用自动构建".to_string(),
+        );
+        if build_plans.is_empty() {
+            return Err(program_source_error(
+                build_blocked_reason
+                    .clone()
+                    .unwrap_or_else(|| "当前源码目录不可自动编译".to_string()),
+            ));
+        }
+        let expected_program_id =
+            expected_program_id_from_source_keypair(&source_dir, artifact_stem.as_deref())?;
+        let prebuild_validation_errors = collect_prebuild_source_validation_errors(
+            &source_dir,
+            artifact_stem.as_deref(),
+            req.network.as_deref(),
+            expected_program_id.as_deref(),
+        )?;
+        if !prebuild_validation_errors.is_empty() {
+            let mut artifacts = load_program_source_artifacts(
+                &source_dir,
+                artifact_stem.as_deref(),
+                req.network.as_deref(),
+                true,
+            )?;
+            artifacts.source_validation_errors = prebuild_validation_errors;
+            build_warnings.append(&mut artifacts.warnings);
+            build_warnings.push(format!(
+                "编译未启动：{}",
+                source_validation_blocked_warning(artifacts.source_validation_errors.len())
+            ));
+            return Ok(Json(ProgramDeploySourceResponse {
+                source_dir: path_string(&source_dir),
+                built,
+                build_status: Some("blocked".to_string()),
+                build_job_id: None,
+                build_error: None,
+                build_command,
+                build_template,
+                build_stdout,
+                build_stderr,
+                program_so_path: artifacts
+                    .program_so_path
+                    .as_ref()
+                    .map(|path| path_string(path)),
+                program_so_name: artifacts.program_so_name,
+                program_so_base64: artifacts.program_so_base64,
+                program_so_sha256: artifacts.program_so_sha256,
+                program_so_size: artifacts.program_so_size,
+                program_keypair_path: artifacts
+                    .program_keypair_path
+                    .as_ref()
+                    .map(|path| path_string(path)),
+                expected_program_id: artifacts.expected_program_id.or(expected_program_id),
+                manifest_program_id: artifacts.manifest_program_id,
+                manifest_network: artifacts.manifest_network,
+                manifest_genesis_hash: artifacts.manifest_genesis_hash,
+                manifest_upgrade_authority: artifacts.manifest_upgrade_authority,
+                manifest_owner_admin: artifacts.manifest_owner_admin,
+                manifest_operational_admin: artifacts.manifest_operational_admin,
+                build_available,
+                build_blocked_reason,
+                source_validation_errors: artifacts.source_validation_errors,
+                warnings: build_warnings,
+            }));
+        }
+        let (job_id, job_command, job_template) = start_program_source_build_job(
+            source_dir.clone(),
+            build_plans.clone(),
+            build_blocked_reason.clone(),
+            artifact_stem.clone(),
+            build_warnings.clone(),
+        )
+        .await;
+        build_command = job_command;
+        build_template = job_template;
+        build_status = Some("running".to_string());
+        build_job_id = Some(job_id);
+        build_warnings.push("编译已在后台开始，完成后会自动重新读取部署信息".to_string());
     }
 
-    ExternalSignTransactionPreviewResponse {
-        status: "success".to_string(),
-        transaction_format: "versioned".to_string(),
-        recent_blockhash: message.recent_blockhash().to_string(),
-        required_signatures,
-        required_signer_present,
-        signer_accounts,
-        writable_accounts,
-        readonly_accounts,
-        programs,
-        instructions,
-        warnings,
-    }
+    let mut artifacts = load_program_source_artifacts(
+        &source_dir,
+        artifact_stem.as_deref(),
+        req.network.as_deref(),
+        true,
+    )?;
+    build_warnings.append(&mut artifacts.warnings);
+
+    Ok(Json(ProgramDeploySourceResponse {
+        source_dir: path_string(&source_dir),
+        built,
+        build_status,
+        build_job_id,
+        build_error,
+        build_command,
+        build_template,
+        build_stdout,
+        build_stderr,
+        program_so_path: artifacts
+            .program_so_path
+            .as_ref()
+            .map(|path| path_string(path)),
+        program_so_name: artifacts.program_so_name,
+        program_so_base64: artifacts.program_so_base64,
+        program_so_sha256: artifacts.program_so_sha256,
+        program_so_size: artifacts.program_so_size,
+        program_keypair_path: artifacts
+            .program_keypair_path
+            .as_ref()
+            .map(|path| path_string(path)),
+        expected_program_id: artifacts.expected_program_id,
+        manifest_program_id: artifacts.manifest_program_id,
+        manifest_network: artifacts.manifest_network,
+        manifest_genesis_hash: artifacts.manifest_genesis_hash,
+        manifest_upgrade_authority: artifacts.manifest_upgrade_authority,
+        manifest_owner_admin: artifacts.manifest_owner_admin,
+        manifest_operational_admin: artifacts.manifest_operational_admin,
+        build_available,
+        build_blocked_reason,
+        source_validation_errors: artifacts.source_validation_errors,
+        warnings: build_warnings,
+    }))
 }
 
-fn preview_external_transaction_request(
-    req: &ExternalSignPreviewRequest,
-) -> Result<ExternalSignTransactionPreviewResponse, ApiError> {
-    let required_signer = req.required_signer.trim();
-    let required_signer = if required_signer.is_empty() {
-        None
-    } else {
-        Some(Pubkey::from_str(required_signer).map_err(|_| ApiError {
-            message: "requiredSigner 地址无效".to_string(),
-        })?)
-    };
-    let transaction_format = req
-        .transaction_format
-        .as_deref()
-        .map(str::trim)
-        .filter(|value| !value.is_empty())
-        .unwrap_or("auto")
-        .to_ascii_lowercase();
-    match transaction_format.as_str() {
-        "legacy" => Ok(preview_external_legacy_transaction(
-            &decode_external_legacy_transaction(&req.transaction_base64)?,
-            required_signer.as_ref(),
-        )),
-        "versioned" | "v0" => Ok(preview_external_versioned_transaction(
-            &decode_external_versioned_transaction(&req.transaction_base64)?,
-            required_signer.as_ref(),
-        )),
-        "auto" => match decode_external_versioned_transaction(&req.transaction_base64) {
-            Ok(transaction) => Ok(preview_external_versioned_transaction(
-                &transaction,
-                required_signer.as_ref(),
-            )),
-            Err(versioned_error) => {
-                let transaction = decode_external_legacy_transaction(&req.transaction_base64)
-                    .map_err(|legacy_error| ApiError {
-                        message: format!(
-                            "交易既不能按 versioned 也不能按 legacy 预览；versioned: {}；legacy: {}",
-                            versioned_error.message, legacy_error.message
-                        ),
-                    })?;
-                Ok(preview_external_legacy_transaction(
-                    &transaction,
-                    required_signer.as_ref(),
-                ))
-            }
-        },
-        _ => Err(ApiError {
-            message: "transactionFormat 必须是 auto、legacy 或 versioned".to_string(),
-        }),
-    }
+#[derive(Deserialize)]
+struct ProgramKeypairArtifactRequest {
+    source_dir: String,
+    #[serde(default)]
+    artifact_stem: Option<String>,
+    #[serde(default)]
+    program_so_name: Option<String>,
 }
 
-async fn external_sign_preview(
-    Json(req): Json<ExternalSignPreviewRequest>,
-) -> Result<Json<ExternalSignTransactionPreviewResponse>, ApiError> {
-    Ok(Json(preview_external_transaction_request(&req)?))
+#[derive(Serialize)]
+struct ProgramKeypairArtifactResponse {
+    source_dir: String,
+    artifact_stem: Option<String>,
+    program_keypair_path: Option<String>,
+    expected_program_id: Option<String>,
+    warnings: Vec<String>,
 }
 
-fn external_sign_legacy_transaction(
-    transaction_base64: &str,
-    required_signer: &Pubkey,
-    signing_keypair: &Keypair,
-) -> Result<Transaction, ApiError> {
-    let mut transaction = decode_external_legacy_transaction(transaction_base64)?;
-    transaction.sanitize().map_err(|error| ApiError {
-        message: format!("外部交易结构校验失败: {error:?}"),
-    })?;
-    let required_signature_count = transaction.message.header.num_required_signatures as usize;
-    let signing_position = transaction
-        .message
-        .account_keys
-        .get(0..required_signature_count)
-        .and_then(|signers| signers.iter().position(|pubkey| pubkey == required_signer))
-        .ok_or_else(|| ApiError {
-            message: format!("交易没有要求 {} 作为 signer", required_signer),
-        })?;
-    if signing_position >= transaction.signatures.len() {
-        return Err(ApiError {
-            message: "交易签名数组与 message signer 数量不匹配".to_string(),
-        });
+async fn program_keypair_artifact(
+    Json(req): Json<ProgramKeypairArtifactRequest>,
+) -> Result<Json<ProgramKeypairArtifactResponse>, ApiError> {
+    let source_dir_raw = req.source_dir.trim();
+    if source_dir_raw.is_empty() || source_dir_raw.len() > 4096 {
+        return Err(program_source_error("源码目录路径无效"));
     }
-
-    let recent_blockhash = transaction.message.recent_blockhash;
-    transaction
-        .try_partial_sign(&[signing_keypair], recent_blockhash)
-        .map_err(|error| ApiError {
-            message: format!("外部交易签名失败: {error}"),
-        })?;
-    let signer_signature = transaction
-        .signatures
-        .get(signing_position)
-        .ok_or_else(|| ApiError {
-            message: "交易签名数组与 message signer 数量不匹配".to_string(),
-        })?;
-    if !signer_signature.verify(required_signer.as_ref(), &transaction.message_data()) {
-        return Err(ApiError {
-            message: "外部交易当前钱包签名无效，已中止".to_string(),
-        });
+    let source_dir = PathBuf::from(source_dir_raw)
+        .canonicalize()
+        .map_err(|error| program_source_error(format!("源码目录不存在或不可访问: {error}")))?;
+    if !source_dir.is_dir() {
+        return Err(program_source_error("源码目录不是目录"));
     }
-    Ok(transaction)
+    let artifact_stem = req
+        .artifact_stem
+        .as_deref()
+        .and_then(safe_artifact_stem)
+        .or_else(|| {
+            req.program_so_name
+                .as_deref()
+                .and_then(artifact_stem_from_so_name)
+        })
+        .or_else(|| detect_program_artifact_stem(&source_dir));
+    let mut warnings = Vec::new();
+    let Some(program_keypair_path) =
+        find_program_keypair_path(&source_dir, artifact_stem.as_deref())
+    else {
+        warnings.push(
+            "未找到 target/deploy/<program>-keypair.json（也未找到可用的 .keys/*.json 回退）"
+                .to_string(),
+        );
+        return Ok(Json(ProgramKeypairArtifactResponse {
+            source_dir: path_string(&source_dir),
+            artifact_stem,
+            program_keypair_path: None,
+            expected_program_id: None,
+            warnings,
+        }));
+    };
+    let mut json = read_text_file_limited(
+        &program_keypair_path,
+        program_deploy::MAX_PROGRAM_KEYPAIR_JSON_BYTES,
+        "Program keypair",
+    )?;
+    let keypair =
+        program_deploy::parse_program_keypair_json(&json).map_err(program_source_error)?;
+    json.zeroize();
+    Ok(Json(ProgramKeypairArtifactResponse {
+        source_dir: path_string(&source_dir),
+        artifact_stem,
+        program_keypair_path: Some(path_string(&program_keypair_path)),
+        expected_program_id: Some(keypair.pubkey().to_string()),
+        warnings,
+    }))
 }
 
-fn external_sign_versioned_transaction(
-    transaction_base64: &str,
-    required_signer: &Pubkey,
-    signing_keypair: &Keypair,
-) -> Result<VersionedTransaction, ApiError> {
-    let mut transaction = decode_external_versioned_transaction(transaction_base64)?;
-    transaction.sanitize().map_err(|error| ApiError {
-        message: format!("外部 versioned 交易结构校验失败: {error:?}"),
-    })?;
-    let required_signature_count = transaction.message.header().num_required_signatures as usize;
-    let signing_position = transaction
-        .message
-        .static_account_keys()
-        .get(0..required_signature_count)
-        .and_then(|signers| signers.iter().position(|pubkey| pubkey == required_signer))
-        .ok_or_else(|| ApiError {
-            message: format!("交易没有要求 {} 作为 signer", required_signer),
-        })?;
-    if signing_position >= transaction.signatures.len() {
-        return Err(ApiError {
-            message: "交易签名数组与 message signer 数量不匹配".to_string(),
-        });
-    }
-
-    let signature = signing_keypair
-        .try_sign_message(&transaction.message.serialize())
-        .map_err(|error| ApiError {
-            message: format!("外部 versioned 交易签名失败: {error}"),
-        })?;
-    transaction.signatures[signing_position] = signature;
-    let signer_signature = transaction
-        .signatures
-        .get(signing_position)
-        .ok_or_else(|| ApiError {
-            message: "交易签名数组与 message signer 数量不匹配".to_string(),
-        })?;
-    if !signer_signature.verify(required_signer.as_ref(), &transaction.message.serialize()) {
-        return Err(ApiError {
-            message: "外部 versioned 交易当前钱包签名无效，已中止".to_string(),
-        });
-    }
-    Ok(transaction)
+#[derive(Deserialize)]
+struct ProgramGenerateKeypairRequest {
+    source_dir: String,
+    #[serde(default)]
+    artifact_stem: Option<String>,
+    #[serde(default)]
+    program_so_name: Option<String>,
+    #[serde(default)]
+    update_source: bool,
 }
 
-enum SignedExternalTransaction {
-    Legacy(Transaction),
-    Versioned(VersionedTransaction),
+#[derive(Serialize)]
+struct ProgramGenerateKeypairResponse {
+    source_dir: String,
+    artifact_stem: String,
+    program_keypair_path: String,
+    backup_program_keypair_path: Option<String>,
+    expected_program_id: String,
+    updated_source_files: Vec<String>,
+    warnings: Vec<String>,
 }
 
-impl SignedExternalTransaction {
-    fn raw_base64(&self) -> Result<String, ApiError> {
-        Ok(match self {
-            Self::Legacy(transaction) => {
-                BASE64.encode(bincode::serialize(transaction).map_err(|error| ApiError {
-                    message: format!("已签名交易序列化失败: {error}"),
-                })?)
-            }
-            Self::Versioned(transaction) => {
-                BASE64.encode(bincode::serialize(transaction).map_err(|error| ApiError {
-                    message: format!("已签名 versioned 交易序列化失败: {error}"),
-                })?)
-            }
-        })
+async fn program_generate_keypair(
+    Json(req): Json<ProgramGenerateKeypairRequest>,
+) -> Result<Json<ProgramGenerateKeypairResponse>, ApiError> {
+    let source_dir_raw = req.source_dir.trim();
+    if source_dir_raw.is_empty() || source_dir_raw.len() > 4096 {
+        return Err(program_source_error("源码目录路径无效"));
+    }
+    let source_dir = PathBuf::from(source_dir_raw)
+        .canonicalize()
+        .map_err(|error| program_source_error(format!("源码目录不存在或不可访问: {error}")))?;
+    if !source_dir.is_dir() {
+        return Err(program_source_error("源码目录不是目录"));
     }
+    let artifact_stem = req
+        .artifact_stem
+        .as_deref()
+        .and_then(safe_artifact_stem)
+        .or_else(|| {
+            req.program_so_name
+                .as_deref()
+                .and_then(artifact_stem_from_so_name)
+        })
+        .or_else(|| detect_program_artifact_stem(&source_dir))
+        .ok_or_else(|| program_source_error("无法识别程序名称，请先编译或选择 .so 文件"))?;
 
-    fn recent_blockhash(&self) -> solana_sdk::hash::Hash {
-        match self {
-            Self::Legacy(transaction) => transaction.message.recent_blockhash,
-            Self::Versioned(transaction) => *transaction.message.recent_blockhash(),
+    let keypair = Keypair::new();
+    let program_id = keypair.pubkey().to_string();
+    let deploy_dir = source_dir.join("target/deploy");
+    fs::create_dir_all(&deploy_dir)
+        .map_err(|error| program_source_error(format!("创建 target/deploy 失败: {error}")))?;
+    let keypair_path = deploy_dir.join(format!("{artifact_stem}-keypair.json"));
+    let backup_path = if keypair_path.exists() {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let backup_path = deploy_dir.join(format!("{artifact_stem}-keypair.json.bak-{timestamp}"));
+        fs::rename(&keypair_path, &backup_path).map_err(|error| {
+            program_source_error(format!("备份旧 Program keypair 失败: {error}"))
+        })?;
+        Some(backup_path)
+    } else {
+        None
+    };
+    let keypair_json = serde_json::to_string(&keypair.to_bytes().to_vec())
+        .map_err(|error| program_source_error(format!("序列化 Program keypair 失败: {error}")))?;
+    fs::write(&keypair_path, keypair_json)
+        .map_err(|error| program_source_error(format!("写入 Program keypair 失败: {error}")))?;
+
+    let mut warnings = Vec::new();
+    let mut updated_source_files = Vec::new();
+    if req.update_source {
+        let mut anchor_updates =
+            update_anchor_program_ids(&source_dir, Some(&artifact_stem), &program_id)?;
+        let mut rust_updates =
+            update_rust_declare_id_files(&source_dir, Some(&artifact_stem), &program_id)?;
+        updated_source_files.append(&mut anchor_updates);
+        updated_source_files.append(&mut rust_updates);
+        updated_source_files.sort();
+        updated_source_files.dedup();
+        if updated_source_files.is_empty() {
+            warnings.push(
+                "已生成新的 Program keypair，但未找到可自动更新的 Anchor.toml 或 declare_id!；请手动同步 Program ID 后重新编译。"
+                    .to_string(),
+            );
+        } else {
+            warnings.push(
+                "已生成新的 Program keypair，并同步 Anchor.toml / declare_id!；请重新编译后部署。"
+                    .to_string(),
+            );
         }
+    } else {
+        warnings.push(
+            "已生成新的 Program keypair；请手动同步 Anchor.toml / declare_id! 后重新编译。"
+                .to_string(),
+        );
     }
 
-    async fn submit_once(
-        &self,
-        client: &RpcClient,
-        action: &str,
-        context: &str,
-        commitment: CommitmentConfig,
-        commitment_label: &str,
-        timeout: Duration,
-    ) -> Result<(Signature, u64), ApiError> {
-        match self {
-            Self::Legacy(transaction) => {
-                submit_signed_transaction_once(
-                    client,
-                    transaction,
-                    action,
-                    context,
-                    commitment,
-                    commitment_label,
-                    timeout,
-                )
-                .await
-            }
-            Self::Versioned(transaction) => {
-                submit_signed_versioned_transaction_once(
-                    client,
-                    transaction,
-                    action,
-                    context,
-                    commitment,
-                    commitment_label,
-                    timeout,
-                )
-                .await
-            }
-        }
-    }
+    Ok(Json(ProgramGenerateKeypairResponse {
+        source_dir: path_string(&source_dir),
+        artifact_stem,
+        program_keypair_path: path_string(&keypair_path),
+        backup_program_keypair_path: backup_path.as_ref().map(|path| path_string(path)),
+        expected_program_id: program_id,
+        updated_source_files,
+        warnings,
+    }))
 }
 
-fn sign_external_transaction_request(
-    req: &ExternalSignSubmitRequest,
-) -> Result<(Pubkey, Pubkey, SignedExternalTransaction, String, String), ApiError> {
-    let required_signer = Pubkey::from_str(req.required_signer.trim()).map_err(|_| ApiError {
-        message: "requiredSigner 地址无效".to_string(),
-    })?;
-    let signing_keypair = req.wallet.keypair()?;
-    let signed_by = signing_keypair.pubkey();
-    if signed_by != required_signer {
-        return Err(ApiError {
-            message: format!(
-                "外部签名请求只允许 {} 签名，当前解锁钱包为 {}",
-                required_signer, signed_by
-            ),
-        });
-    }
+#[derive(Deserialize)]
+struct ProgramIdlRequest {
+    source_dir: String,
+    #[serde(default)]
+    artifact_stem: Option<String>,
+}
 
-    let transaction_format = req
-        .transaction_format
-        .as_deref()
-        .map(str::trim)
-        .filter(|value| !value.is_empty())
-        .unwrap_or("auto")
-        .to_ascii_lowercase();
-    let signed_transaction = match transaction_format.as_str() {
-        "legacy" => SignedExternalTransaction::Legacy(external_sign_legacy_transaction(
-            &req.transaction_base64,
-            &required_signer,
-            &signing_keypair,
-        )?),
-        "versioned" | "v0" => {
-            SignedExternalTransaction::Versioned(external_sign_versioned_transaction(
-                &req.transaction_base64,
-                &required_signer,
-                &signing_keypair,
-            )?)
-        }
-        "auto" => {
-            match external_sign_versioned_transaction(
-                &req.transaction_base64,
-                &required_signer,
-                &signing_keypair,
-            ) {
-                Ok(transaction) => SignedExternalTransaction::Versioned(transaction),
-                Err(versioned_error) => SignedExternalTransaction::Legacy(
-                    external_sign_legacy_transaction(
-                        &req.transaction_base64,
-                        &required_signer,
-                        &signing_keypair,
-                    )
-                    .map_err(|legacy_error| ApiError {
-                        message: format!(
-                            "交易既不能按 versioned 也不能按 legacy 签名；versioned: {}；legacy: {}",
-                            versioned_error.message, legacy_error.message
-                        ),
-                    })?,
-                ),
-            }
-        }
-        _ => {
-            return Err(ApiError {
-                message: "transactionFormat 必须是 auto、legacy 或 versioned".to_string(),
-            })
-        }
-    };
-    let signed_transaction_base64 = signed_transaction.raw_base64()?;
-    let signed_recent_blockhash_hash = signed_transaction.recent_blockhash();
-    let signed_recent_blockhash = signed_recent_blockhash_hash.to_string();
-    Ok((
-        required_signer,
-        signed_by,
-        signed_transaction,
-        signed_transaction_base64,
-        signed_recent_blockhash,
-    ))
+#[derive(Serialize)]
+struct ProgramIdlResponse {
+    source_dir: String,
+    idl_path: String,
+    idl_json: Value,
 }
 
-async fn external_sign_sign(
-    Json(req): Json<ExternalSignSubmitRequest>,
-) -> Result<Json<ExternalSignOnlyResponse>, ApiError> {
-    validate_external_sign_expiry(req.expires_at)?;
-    let (
-        required_signer,
-        signed_by,
-        _signed_transaction,
-        signed_transaction_base64,
-        signed_recent_blockhash,
-    ) = sign_external_transaction_request(&req)?;
-    if let Some(expected_recent_blockhash) = req
-        .recent_blockhash
+async fn program_idl(
+    Json(req): Json<ProgramIdlRequest>,
+) -> Result<Json<ProgramIdlResponse>, ApiError> {
+    let source_dir_raw = req.source_dir.trim();
+    if source_dir_raw.is_empty() || source_dir_raw.len() > 4096 {
+        return Err(program_source_error("源码目录路径无效"));
+    }
+    let source_dir = PathBuf::from(source_dir_raw)
+        .canonicalize()
+        .map_err(|error| program_source_error(format!("源码目录不存在或不可访问: {error}")))?;
+    if !source_dir.is_dir() {
+        return Err(program_source_error("源码目录不是目录"));
+    }
+    let artifact_stem = req
+        .artifact_stem
         .as_deref()
-        .map(str::trim)
-        .filter(|value| !value.is_empty())
+        .and_then(safe_artifact_stem)
+        .or_else(|| detect_program_artifact_stem(&source_dir));
+    let idl_path = find_program_idl_path(&source_dir, artifact_stem.as_deref())
+        .ok_or_else(|| program_source_error("未找到 target/idl/*.json，请先编译项目"))?;
+    let text = read_text_file_limited(&idl_path, MAX_PROGRAM_IDL_JSON_BYTES, "Anchor IDL")?;
+    let idl_json: Value = serde_json::from_str(&text)
+        .map_err(|_| program_source_error("Anchor IDL 不是有效 JSON"))?;
+    if idl_json
+        .get("instructions")
+        .and_then(Value::as_array)
+        .is_none_or(|items| items.is_empty())
     {
-        if expected_recent_blockhash != signed_recent_blockhash {
-            return Err(ApiError {
-                message: format!(
-                    "交易 blockhash 为 {}，与请求中的 {} 不一致；请重新导入签名请求",
-                    signed_recent_blockhash, expected_recent_blockhash
-                ),
-            });
-        }
+        return Err(program_source_error("Anchor IDL 缺少 instructions"));
     }
-
-    Ok(Json(ExternalSignOnlyResponse {
-        status: "success".to_string(),
-        required_signer: required_signer.to_string(),
-        signed_by: signed_by.to_string(),
-        request_id: req.request_id,
-        raw_transaction: signed_transaction_base64,
-        recent_blockhash: signed_recent_blockhash,
+    Ok(Json(ProgramIdlResponse {
+        source_dir: path_string(&source_dir),
+        idl_path: path_string(&idl_path),
+        idl_json,
     }))
 }
 
-async fn external_sign_message(
-    Json(req): Json<ExternalSignMessageRequest>,
-) -> Result<Json<ExternalSignMessageResponse>, ApiError> {
-    validate_external_sign_expiry(req.expires_at)?;
-    let required_signer = Pubkey::from_str(req.required_signer.trim()).map_err(|_| ApiError {
-        message: "requiredSigner 地址无效".to_string(),
-    })?;
-    let signing_keypair = req.wallet.keypair()?;
-    let signed_by = signing_keypair.pubkey();
-    if signed_by != required_signer {
-        return Err(ApiError {
-            message: format!(
-                "外部消息签名请求只允许 {} 签名，当前解锁钱包为 {}",
-                required_signer, signed_by
-            ),
-        });
-    }
-
-    let message_base64 = req.message_base64.trim();
-    require_nonempty(message_base64, "消息 base64")?;
-    let message_bytes = BASE64.decode(message_base64).map_err(|error| ApiError {
-        message: format!("消息 base64 解码失败: {error}"),
-    })?;
-    if message_bytes.is_empty() || message_bytes.len() > MAX_EXTERNAL_SIGN_MESSAGE_BYTES {
-        return Err(ApiError {
-            message: format!(
-                "消息长度无效，最大 {} bytes",
-                MAX_EXTERNAL_SIGN_MESSAGE_BYTES
-            ),
-        });
-    }
+#[derive(Deserialize)]
+struct GenericProgramAccountMetaRequest {
+    pubkey: String,
+    is_signer: bool,
+    is_writable: bool,
+}
 
-    let signature = signing_keypair
-        .try_sign_message(&message_bytes)
-        .map_err(|error| ApiError {
-            message: format!("外部消息签名失败: {error}"),
-        })?;
-    if !signature.verify(required_signer.as_ref(), &message_bytes) {
-        return Err(ApiError {
-            message: "外部消息当前钱包签名无效，已中止".to_string(),
-        });
-    }
+#[derive(Deserialize)]
+struct ProgramInvokeRequest {
+    #[serde(flatten)]
+    wallet: WalletAuthRequest,
+    program_id: String,
+    instruction_name: String,
+    accounts: Vec<GenericProgramAccountMetaRequest>,
+    data_base64: String,
+    #[serde(default)]
+    network: Option<String>,
+    #[serde(default)]
+    mode: Option<String>,
+    #[serde(default)]
+    additional_signers: Vec<GenericProgramAdditionalSignerRequest>,
+}
 
-    Ok(Json(ExternalSignMessageResponse {
-        status: "success".to_string(),
-        required_signer: required_signer.to_string(),
-        signed_by: signed_by.to_string(),
-        request_id: req.request_id,
-        signature: signature.to_string(),
-        message_base64: message_base64.to_string(),
-    }))
+#[derive(Serialize)]
+struct ProgramInvokeResponse {
+    status: String,
+    signature: Option<String>,
+    simulation_error: Option<String>,
+    logs: Vec<String>,
 }
 
-async fn external_sign_submit(
-    Json(req): Json<ExternalSignSubmitRequest>,
-) -> Result<Json<ExternalSignSubmitResponse>, ApiError> {
-    validate_external_sign_expiry(req.expires_at)?;
-    let (
-        required_signer,
-        signed_by,
-        signed_transaction,
-        signed_transaction_base64,
-        signed_recent_blockhash,
-    ) = sign_external_transaction_request(&req)?;
-    let signed_recent_blockhash_hash = signed_transaction.recent_blockhash();
-    if let Some(expected_recent_blockhash) = req
-        .recent_blockhash
-        .as_deref()
-        .map(str::trim)
-        .filter(|value| !value.is_empty())
-    {
-        if expected_recent_blockhash != signed_recent_blockhash {
-            return Err(ApiError {
-                message: format!(
-                    "交易 blockhash 为 {}，与请求中的 {} 不一致；请重新导入签名请求",
-                    signed_recent_blockhash, expected_recent_blockhash
-                ),
-            });
-        }
-    }
+#[derive(Deserialize)]
+struct ExternalSignSubmitRequest {
+    #[serde(flatten)]
+    wallet: WalletAuthRequest,
+    #[serde(default, alias = "requiredSigner")]
+    required_signer: String,
+    #[serde(default, alias = "transactionBase64")]
+    transaction_base64: String,
+    #[serde(default, alias = "transactionFormat")]
+    transaction_format: Option<String>,
+    #[serde(default)]
+    network: Option<String>,
+    #[serde(default, alias = "requestId")]
+    request_id: Option<String>,
+    #[serde(default, alias = "expiresAt")]
+    expires_at: Option<u64>,
+    #[serde(default, alias = "expectedGenesisHash")]
+    expected_genesis_hash: Option<String>,
+    #[serde(default, alias = "recentBlockhash")]
+    recent_blockhash: Option<String>,
+    #[serde(default, alias = "lastValidBlockHeight")]
+    last_valid_block_height: Option<u64>,
+}
 
-    let (client, network) = rpc_client_for(req.network.as_deref())?;
-    let signed_last_valid_block_height = match req.last_valid_block_height {
-        Some(last_valid_block_height) if last_valid_block_height > 0 => last_valid_block_height,
-        _ => {
-            let latest = client
-                .get_latest_blockhash_with_commitment(CommitmentConfig::confirmed())
-                .map_err(|error| ApiError {
-                    message: format!("读取 RPC latest blockhash 失败: {error}"),
-                })?;
-            if latest.0 != signed_recent_blockhash_hash {
-                return Err(ApiError {
-                    message: "签名请求缺少 lastValidBlockHeight，且当前 RPC latest blockhash 与交易不一致；请重新导入签名请求"
-                        .to_string(),
-                });
-            }
-            latest.1
-        }
-    };
-    if let Some(expected_genesis_hash) = req
-        .expected_genesis_hash
-        .as_deref()
-        .map(str::trim)
-        .filter(|value| !value.is_empty())
-    {
-        let expected_genesis_hash = solana_sdk::hash::Hash::from_str(expected_genesis_hash)
-            .map_err(|_| ApiError {
-                message: "expectedGenesisHash 无效".to_string(),
-            })?;
-        let actual_genesis_hash = client.get_genesis_hash().map_err(|error| ApiError {
-            message: format!("读取 RPC genesis hash 失败: {error}"),
-        })?;
-        if actual_genesis_hash != expected_genesis_hash {
-            return Err(ApiError {
-                message: format!(
-                    "RPC genesis hash 为 {}，预期为 {}；已在提交前中止",
-                    actual_genesis_hash, expected_genesis_hash
-                ),
-            });
-        }
-    }
+#[derive(Serialize)]
+struct ExternalSignSubmitResponse {
+    status: String,
+    signature: String,
+    slot: u64,
+    required_signer: String,
+    signed_by: String,
+    network: String,
+    request_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", alias = "rawTransaction")]
+    raw_transaction: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", alias = "recentBlockhash")]
+    recent_blockhash: Option<String>,
+    #[serde(
+        skip_serializing_if = "Option::is_none",
+        alias = "lastValidBlockHeight"
+    )]
+    last_valid_block_height: Option<u64>,
+}
 
-    let context = format!(
-        "external_sign request={} signer={} network={}",
-        req.request_id.as_deref().unwrap_or("-"),
-        required_signer,
-        network
-    );
-    let (signature, slot) = signed_transaction
-        .submit_once(
-            &client,
-            "外部签名",
-            &context,
-            CommitmentConfig::confirmed(),
-            "confirmed",
-            Duration::from_secs(20),
-        )
-        .await?;
+#[derive(Serialize)]
+struct ExternalSignOnlyResponse {
+    status: String,
+    required_signer: String,
+    signed_by: String,
+    request_id: Option<String>,
+    raw_transaction: String,
+    recent_blockhash: String,
+}
 
-    Ok(Json(ExternalSignSubmitResponse {
-        status: "success".to_string(),
-        signature: signature.to_string(),
-        slot,
-        required_signer: required_signer.to_string(),
-        signed_by: signed_by.to_string(),
-        network,
-        request_id: req.request_id,
-        raw_transaction: Some(signed_transaction_base64),
-        recent_blockhash: Some(signed_recent_blockhash),
-        last_valid_block_height: Some(signed_last_valid_block_height),
-    }))
+#[derive(Deserialize)]
+struct ExternalSignMessageRequest {
+    #[serde(flatten)]
+    wallet: WalletAuthRequest,
+    #[serde(default, alias = "requiredSigner")]
+    required_signer: String,
+    #[serde(default, alias = "messageBase64")]
+    message_base64: String,
+    #[serde(default, alias = "requestId")]
+    request_id: Option<String>,
+    #[serde(default, alias = "expiresAt")]
+    expires_at: Option<u64>,
 }
 
-fn decode_generic_instruction_data(value: &str) -> Result<Vec<u8>, ApiError> {
-    let trimmed = value.trim();
-    if trimmed.is_empty() {
-        return Err(ApiError {
-            message: "Instruction data 不能为空".to_string(),
-        });
+#[derive(Serialize)]
+struct ExternalSignMessageResponse {
+    status: String,
+    required_signer: String,
+    signed_by: String,
+    request_id: Option<String>,
+    signature: String,
+    message_base64: String,
+}
+
+#[derive(Deserialize)]
+struct ExternalSignPreviewRequest {
+    #[serde(default, alias = "requiredSigner")]
+    required_signer: String,
+    transaction_base64: String,
+    #[serde(default, alias = "transactionFormat")]
+    transaction_format: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ExternalSignInstructionPreview {
+    index: usize,
+    program_id: String,
+    account_count: usize,
+    data_bytes: usize,
+}
+
+#[derive(Serialize)]
+struct ExternalSignTransactionPreviewResponse {
+    status: String,
+    transaction_format: String,
+    recent_blockhash: String,
+    required_signatures: usize,
+    required_signer_present: bool,
+    signer_accounts: Vec<String>,
+    writable_accounts: Vec<String>,
+    readonly_accounts: Vec<String>,
+    programs: Vec<String>,
+    instructions: Vec<ExternalSignInstructionPreview>,
+    warnings: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct GenericProgramAdditionalSignerRequest {
+    pubkey: String,
+    wallet_id: String,
+    password: String,
+}
+
+impl Drop for GenericProgramAdditionalSignerRequest {
+    fn drop(&mut self) {
+        self.password.zeroize();
     }
-    let max_base64_len = MAX_GENERIC_PROGRAM_INSTRUCTION_DATA_BYTES.div_ceil(3) * 4;
-    if trimmed.len() > max_base64_len {
+}
+
+fn keypair_from_saved_wallet(
+    wallet_id: &str,
+    password: &str,
+    address_label: &str,
+) -> Result<Keypair, ApiError> {
+    let wallet_id = wallet_id.trim();
+    validate_wallet_id(wallet_id)?;
+    if password.is_empty() {
         return Err(ApiError {
-            message: "Instruction data 过大".to_string(),
+            message: format!("{address_label} 需要提供钱包密码"),
         });
     }
-    let data = BASE64.decode(trimmed.as_bytes()).map_err(|_| ApiError {
-        message: "Instruction data base64 无效".to_string(),
+    let (keypair, wallet) =
+        keypair_from_saved_wallet_with_password(wallet_id, password, address_label)?;
+    let expected_pubkey = Pubkey::from_str(address_label).map_err(|_| ApiError {
+        message: format!("Signer 地址无效: {address_label}"),
     })?;
-    if data.is_empty() || data.len() > MAX_GENERIC_PROGRAM_INSTRUCTION_DATA_BYTES {
+    if keypair.pubkey() != expected_pubkey || wallet.public_key != expected_pubkey.to_string() {
         return Err(ApiError {
-            message: "Instruction data 大小无效".to_string(),
+            message: format!(
+                "Signer {} 选择的钱包不匹配，实际解锁为 {}",
+                expected_pubkey,
+                keypair.pubkey()
+            ),
         });
     }
-    Ok(data)
+    Ok(keypair)
 }
 
-fn build_generic_program_instruction(
-    program_id: &str,
-    accounts: &[GenericProgramAccountMetaRequest],
-    data_base64: &str,
-) -> Result<Instruction, ApiError> {
-    let program_id = Pubkey::from_str(program_id.trim()).map_err(|_| ApiError {
-        message: "Program ID 无效".to_string(),
-    })?;
-    if accounts.len() > MAX_GENERIC_PROGRAM_INSTRUCTION_ACCOUNTS {
-        return Err(ApiError {
-            message: "Instruction 账户数量过多".to_string(),
-        });
+fn validate_external_sign_expiry(expires_at: Option<u64>) -> Result<(), ApiError> {
+    let Some(expires_at) = expires_at else {
+        return Ok(());
+    };
+    let now_ms = now_unix_ms_lossy();
+    let expires_at_ms = if expires_at < 10_000_000_000 {
+        expires_at.saturating_mul(1_000)
+    } else {
+        expires_at
+    };
+    if expires_at_ms <= now_ms {
+        return Err(ApiError {
+            message: "外部签名请求已过期".to_string(),
+        });
     }
-    let metas = accounts
-        .iter()
-        .map(|account| {
-            let pubkey = Pubkey::from_str(account.pubkey.trim()).map_err(|_| ApiError {
-                message: format!("账户地址无效: {}", account.pubkey),
-            })?;
-            Ok(if account.is_writable {
-                AccountMeta::new(pubkey, account.is_signer)
-            } else {
-                AccountMeta::new_readonly(pubkey, account.is_signer)
-            })
-        })
-        .collect::<Result<Vec<_>, ApiError>>()?;
-    Ok(Instruction {
-        program_id,
-        accounts: metas,
-        data: decode_generic_instruction_data(data_base64)?,
-    })
+    Ok(())
 }
 
-async fn program_invoke(
-    Json(mut req): Json<ProgramInvokeRequest>,
-) -> Result<Json<ProgramInvokeResponse>, ApiError> {
-    let signer = req.wallet.keypair()?;
-    let signer_pubkey = signer.pubkey();
-    let instruction =
-        build_generic_program_instruction(&req.program_id, &req.accounts, &req.data_base64)?;
-    let mode = req.mode.as_deref().unwrap_or("simulate");
-    if mode != "simulate" && mode != "send" {
+fn decode_external_legacy_transaction(transaction_base64: &str) -> Result<Transaction, ApiError> {
+    let trimmed = transaction_base64.trim();
+    if trimmed.is_empty() {
         return Err(ApiError {
-            message: "调用模式必须是 simulate 或 send".to_string(),
+            message: "需要提供交易 base64".to_string(),
         });
     }
-    if req.additional_signers.len() > MAX_GENERIC_PROGRAM_ADDITIONAL_SIGNERS {
+    let bytes = BASE64.decode(trimmed).map_err(|error| ApiError {
+        message: format!("交易 base64 解码失败: {error}"),
+    })?;
+    if bytes.len() > SOLANA_TRANSACTION_PACKET_DATA_BYTES {
         return Err(ApiError {
-            message: "额外 signer 数量过多".to_string(),
+            message: format!(
+                "交易数据过大: {} bytes，最大 {} bytes",
+                bytes.len(),
+                SOLANA_TRANSACTION_PACKET_DATA_BYTES
+            ),
         });
     }
-    let mut signers = vec![signer];
-    let mut signer_pubkeys = vec![signer_pubkey];
-    for additional in &req.additional_signers {
-        let requested_pubkey =
-            Pubkey::from_str(additional.pubkey.trim()).map_err(|_| ApiError {
-                message: format!("Signer 地址无效: {}", additional.pubkey),
-            })?;
-        if signer_pubkeys.contains(&requested_pubkey) {
-            continue;
-        }
-        let keypair = keypair_from_saved_wallet(
-            &additional.wallet_id,
-            &additional.password,
-            &requested_pubkey.to_string(),
-        )?;
-        signer_pubkeys.push(keypair.pubkey());
-        signers.push(keypair);
-    }
-    req.additional_signers.clear();
+    bincode::deserialize::<Transaction>(&bytes).map_err(|error| ApiError {
+        message: format!("交易反序列化失败；当前仅支持 legacy Transaction base64: {error}"),
+    })
+}
 
-    for account in &instruction.accounts {
-        if account.is_signer && !signer_pubkeys.contains(&account.pubkey) {
-            return Err(ApiError {
-                message: format!(
-                    "账户 {} 需要签名，请为这个 signer 选择已保存钱包并输入密码",
-                    account.pubkey
-                ),
-            });
-        }
+fn decode_external_versioned_transaction(
+    transaction_base64: &str,
+) -> Result<VersionedTransaction, ApiError> {
+    let trimmed = transaction_base64.trim();
+    if trimmed.is_empty() {
+        return Err(ApiError {
+            message: "需要提供交易 base64".to_string(),
+        });
     }
-    let (client, _) = rpc_client_for(req.network.as_deref())?;
-    let blockhash = client.get_latest_blockhash().map_err(|error| ApiError {
-        message: format!("获取 blockhash 失败: {error}"),
+    let bytes = BASE64.decode(trimmed).map_err(|error| ApiError {
+        message: format!("交易 base64 解码失败: {error}"),
     })?;
-    let signer_refs = signers
-        .iter()
-        .map(|keypair| keypair as &dyn Signer)
-        .collect::<Vec<_>>();
-    let transaction = Transaction::new_signed_with_payer(
-        &[instruction],
-        Some(&signer_pubkey),
-        &signer_refs,
-        blockhash,
-    );
-    if mode == "simulate" {
-        let simulation = client
-            .simulate_transaction_with_config(
-                &transaction,
-                RpcSimulateTransactionConfig {
-                    sig_verify: true,
-                    replace_recent_blockhash: false,
-                    commitment: Some(CommitmentConfig::confirmed()),
-                    encoding: Some(UiTransactionEncoding::Base64),
-                    accounts: None,
-                    min_context_slot: None,
-                    inner_instructions: false,
-                },
-            )
-            .map_err(|error| ApiError {
-                message: format!("模拟调用 RPC 失败: {error}"),
-            })?;
-        return Ok(Json(ProgramInvokeResponse {
-            status: if simulation.value.err.is_some() {
-                "simulation_failed".to_string()
-            } else {
-                "simulated".to_string()
-            },
-            signature: None,
-            simulation_error: simulation.value.err.map(|error| format!("{error}")),
-            logs: simulation.value.logs.unwrap_or_default(),
-        }));
-    }
-
-    let signature = client
-        .send_and_confirm_transaction(&transaction)
-        .map_err(|error| ApiError {
+    if bytes.len() > SOLANA_TRANSACTION_PACKET_DATA_BYTES {
+        return Err(ApiError {
             message: format!(
-                "{} 调用失败: {error}",
-                req.instruction_name
-                    .trim()
-                    .chars()
-                    .take(80)
-                    .collect::<String>()
+                "交易数据过大: {} bytes，最大 {} bytes",
+                bytes.len(),
+                SOLANA_TRANSACTION_PACKET_DATA_BYTES
             ),
-        })?;
-    Ok(Json(ProgramInvokeResponse {
-        status: "success".to_string(),
-        signature: Some(signature.to_string()),
-        simulation_error: None,
-        logs: Vec::new(),
-    }))
-}
-
-async fn program_deploy_source_build_status(
-    Path(job_id): Path<String>,
-) -> Result<Json<ProgramDeploySourceResponse>, ApiError> {
-    let job = {
-        let jobs = program_source_build_jobs().lock().await;
-        jobs.get(&job_id)
-            .cloned()
-            .ok_or_else(|| program_source_error("构建任务不存在或已过期"))?
-    };
-    let (build_plans, build_blocked_reason) = program_source_build_plans(&job.source_dir);
-    let build_available = !build_plans.is_empty();
-    let artifact_stem = detect_program_artifact_stem(&job.source_dir);
-    let mut warnings = job.warnings.clone();
-    if let Some(error) = job.build_error.as_ref() {
-        warnings.push(error.clone());
+        });
     }
-    let mut artifacts = load_program_source_artifacts(
-        &job.source_dir,
-        artifact_stem.as_deref(),
-        None,
-        job.status != "running",
-    )?;
-    warnings.append(&mut artifacts.warnings);
-
-    Ok(Json(ProgramDeploySourceResponse {
-        source_dir: path_string(&job.source_dir),
-        built: job.status == "completed",
-        build_status: Some(job.status),
-        build_job_id: Some(job_id),
-        build_error: job.build_error,
-        build_command: job.build_command,
-        build_template: job.build_template,
-        build_stdout: job.build_stdout,
-        build_stderr: job.build_stderr,
-        program_so_path: artifacts
-            .program_so_path
-            .as_ref()
-            .map(|path| path_string(path)),
-        program_so_name: artifacts.program_so_name,
-        program_so_base64: artifacts.program_so_base64,
-        program_so_sha256: artifacts.program_so_sha256,
-        program_so_size: artifacts.program_so_size,
-        program_keypair_path: artifacts
-            .program_keypair_path
-            .as_ref()
-            .map(|path| path_string(path)),
-        expected_program_id: artifacts.expected_program_id,
-        manifest_program_id: artifacts.manifest_program_id,
-        manifest_network: artifacts.manifest_network,
-        manifest_genesis_hash: artifacts.manifest_genesis_hash,
-        manifest_upgrade_authority: artifacts.manifest_upgrade_authority,
-        manifest_owner_admin: artifacts.manifest_owner_admin,
-        manifest_operational_admin: artifacts.manifest_operational_admin,
-        build_available,
-        build_blocked_reason,
-        source_validation_errors: artifacts.source_validation_errors,
-        warnings,
-    }))
+    bincode::deserialize::<VersionedTransaction>(&bytes).map_err(|error| ApiError {
+        message: format!("versioned transaction 反序列化失败: {error}"),
+    })
 }
 
-#[derive(Deserialize)]
-#[serde(deny_unknown_fields)]
-struct ProgramDeploymentJournalRequest {
-    network: String,
-    expected_genesis_hash: String,
-    expected_program_id: String,
-    expected_program_sha256: String,
-    program_len: usize,
-    max_data_len: usize,
-    expected_upgrade_authority: String,
+fn external_sign_program_label(program_id: &str) -> &'static str {
+    match program_id {
+        "11111111111111111111111111111111" => "System Program",
+        SPL_TOKEN_PROGRAM_ID => "SPL Token",
+        SPL_TOKEN_2022_PROGRAM_ID => "SPL Token 2022",
+        "ComputeBudget111111111111111111111111111111" => "Compute Budget",
+        "ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL" => "Associated Token Account",
+        UPGRADEABLE_LOADER_ID_STR => "Upgradeable Loader",
+        _ => "Unknown Program",
+    }
 }
 
-#[derive(Deserialize)]
-struct ProgramDeploymentJournalByProgramRequest {
-    network: String,
-    expected_genesis_hash: String,
+fn external_sign_instruction_preview(
+    index: usize,
     program_id: String,
+    account_count: usize,
+    data_bytes: usize,
+) -> ExternalSignInstructionPreview {
+    ExternalSignInstructionPreview {
+        index,
+        program_id: format!(
+            "{} ({})",
+            program_id,
+            external_sign_program_label(&program_id)
+        ),
+        account_count,
+        data_bytes,
+    }
 }
 
-#[derive(Serialize)]
-struct ProgramDeploymentJournalResponse {
-    network: String,
-    genesis_hash: String,
-    write_chunk_bytes: usize,
-    write_chunk_count: usize,
-    journal: Option<ProgramDeploymentJournalView>,
-    deployment_attempts: Vec<wallet_store::ProgramDeploymentAttemptRecord>,
-    conflicting_journal: Option<ProgramDeploymentJournalView>,
-    conflicting_deployment_attempts: Vec<wallet_store::ProgramDeploymentAttemptRecord>,
-}
-
-#[derive(Serialize)]
-struct ProgramDeploymentJournalView {
-    genesis_hash: String,
-    program_id: String,
-    program_sha256: String,
-    program_len: usize,
-    max_data_len: usize,
-    upgrade_authority: String,
-    buffer_address: String,
-    status: String,
-    create_signature: Option<String>,
-    create_last_valid_block_height: Option<u64>,
-    last_write_signature: Option<String>,
-    last_write_chunk_index: Option<usize>,
-    last_write_last_valid_block_height: Option<u64>,
-    completed_writes: usize,
-    deploy_signature: Option<String>,
-    deploy_last_valid_block_height: Option<u64>,
-    attempt_evidence_version: u32,
-    revision: u64,
-    created_at: u64,
-    updated_at: u64,
-}
-
-impl From<&wallet_store::ProgramDeploymentRecord> for ProgramDeploymentJournalView {
-    fn from(record: &wallet_store::ProgramDeploymentRecord) -> Self {
-        Self {
-            genesis_hash: record.genesis_hash.clone(),
-            program_id: record.program_id.clone(),
-            program_sha256: record.program_sha256.clone(),
-            program_len: record.program_len,
-            max_data_len: record.max_data_len,
-            upgrade_authority: record.upgrade_authority.clone(),
-            buffer_address: record.buffer_address.clone(),
-            status: record.status.clone(),
-            create_signature: record.create_signature.clone(),
-            create_last_valid_block_height: record.create_last_valid_block_height,
-            last_write_signature: record.last_write_signature.clone(),
-            last_write_chunk_index: record.last_write_chunk_index,
-            last_write_last_valid_block_height: record.last_write_last_valid_block_height,
-            completed_writes: record.completed_writes,
-            deploy_signature: record.deploy_signature.clone(),
-            deploy_last_valid_block_height: record.deploy_last_valid_block_height,
-            attempt_evidence_version: record.attempt_evidence_version,
-            revision: record.revision,
-            created_at: record.created_at,
-            updated_at: record.updated_at,
+fn preview_external_legacy_transaction(
+    transaction: &Transaction,
+    required_signer: Option<&Pubkey>,
+) -> ExternalSignTransactionPreviewResponse {
+    let message = &transaction.message;
+    let required_signatures = message.header.num_required_signatures as usize;
+    let signer_accounts = message
+        .account_keys
+        .iter()
+        .take(required_signatures)
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+    let mut writable_accounts = Vec::new();
+    let mut readonly_accounts = Vec::new();
+    for (index, account) in message.account_keys.iter().enumerate() {
+        if message.is_maybe_writable(index, None) {
+            writable_accounts.push(account.to_string());
+        } else {
+            readonly_accounts.push(account.to_string());
         }
     }
-}
-
-struct ProgramDeploymentJournalIntent {
-    expected_genesis_hash: solana_sdk::hash::Hash,
-    program_id: Pubkey,
-    program_sha256: String,
-    program_len: usize,
-    max_data_len: usize,
-    upgrade_authority: Pubkey,
-}
+    let mut programs = Vec::new();
+    let instructions = message
+        .instructions
+        .iter()
+        .enumerate()
+        .map(|(index, instruction)| {
+            let program_id = message
+                .account_keys
+                .get(instruction.program_id_index as usize)
+                .map(ToString::to_string)
+                .unwrap_or_else(|| format!("account-index:{}", instruction.program_id_index));
+            if !programs.contains(&program_id) {
+                programs.push(program_id.clone());
+            }
+            external_sign_instruction_preview(
+                index,
+                program_id,
+                instruction.accounts.len(),
+                instruction.data.len(),
+            )
+        })
+        .collect::<Vec<_>>();
+    let required_signer_present = required_signer
+        .map(|required| {
+            signer_accounts
+                .iter()
+                .any(|account| account == &required.to_string())
+        })
+        .unwrap_or(false);
+    let mut warnings = Vec::new();
+    if !required_signer_present {
+        warnings.push("requiredSigner 不在交易所需 signer 列表中；签名将被拒绝".to_string());
+    }
+    if programs
+        .iter()
+        .any(|program| external_sign_program_label(program) == "Unknown Program")
+    {
+        warnings.push("交易包含未识别 program，请确认 DApp 来源和指令意图".to_string());
+    }
 
-fn parse_canonical_deployment_pubkey(value: &str, label: &str) -> Result<Pubkey, ApiError> {
-    let trimmed = value.trim();
-    let pubkey = Pubkey::from_str(trimmed).map_err(|_| ApiError {
-        message: format!("无效的{label}"),
-    })?;
-    if pubkey == Pubkey::default() || pubkey.to_string() != trimmed {
-        return Err(ApiError {
-            message: format!("{label}必须是 canonical 非零公钥"),
-        });
+    ExternalSignTransactionPreviewResponse {
+        status: "success".to_string(),
+        transaction_format: "legacy".to_string(),
+        recent_blockhash: message.recent_blockhash.to_string(),
+        required_signatures,
+        required_signer_present,
+        signer_accounts,
+        writable_accounts,
+        readonly_accounts,
+        programs,
+        instructions,
+        warnings,
     }
-    Ok(pubkey)
 }
 
-fn validate_program_deployment_journal_request(
-    req: &ProgramDeploymentJournalRequest,
-) -> Result<ProgramDeploymentJournalIntent, ApiError> {
-    if req.program_len == 0
-        || req.program_len > MAX_PROGRAM_SO_BYTES
-        || req.max_data_len < req.program_len
-        || req.max_data_len > MAX_PROGRAM_SO_BYTES
-    {
-        return Err(ApiError {
-            message: format!(
-                "Program 长度必须满足 1 <= program_len <= max_data_len <= {}",
-                MAX_PROGRAM_SO_BYTES
-            ),
-        });
+fn preview_external_versioned_transaction(
+    transaction: &VersionedTransaction,
+    required_signer: Option<&Pubkey>,
+) -> ExternalSignTransactionPreviewResponse {
+    let message = &transaction.message;
+    let required_signatures = message.header().num_required_signatures as usize;
+    let static_accounts = message.static_account_keys();
+    let signer_accounts = static_accounts
+        .iter()
+        .take(required_signatures)
+        .map(ToString::to_string)
+        .collect::<Vec<_>>();
+    let mut writable_accounts = Vec::new();
+    let mut readonly_accounts = Vec::new();
+    for (index, account) in static_accounts.iter().enumerate() {
+        if message.is_maybe_writable(index, None) {
+            writable_accounts.push(account.to_string());
+        } else {
+            readonly_accounts.push(account.to_string());
+        }
     }
-    let program_sha256 = req.expected_program_sha256.trim().to_ascii_lowercase();
-    if program_sha256.len() != 64 || !program_sha256.as_bytes().iter().all(u8::is_ascii_hexdigit) {
-        return Err(ApiError {
-            message: "预期 Program SHA-256 必须是 64 位十六进制".to_string(),
-        });
+    let mut programs = Vec::new();
+    let instructions = message
+        .instructions()
+        .iter()
+        .enumerate()
+        .map(|(index, instruction)| {
+            let program_id = static_accounts
+                .get(instruction.program_id_index as usize)
+                .map(ToString::to_string)
+                .unwrap_or_else(|| format!("account-index:{}", instruction.program_id_index));
+            if !programs.contains(&program_id) {
+                programs.push(program_id.clone());
+            }
+            external_sign_instruction_preview(
+                index,
+                program_id,
+                instruction.accounts.len(),
+                instruction.data.len(),
+            )
+        })
+        .collect::<Vec<_>>();
+    let required_signer_present = required_signer
+        .map(|required| {
+            signer_accounts
+                .iter()
+                .any(|account| account == &required.to_string())
+        })
+        .unwrap_or(false);
+    let mut warnings = Vec::new();
+    if !required_signer_present {
+        warnings.push("requiredSigner 不在交易所需 signer 列表中；签名将被拒绝".to_string());
     }
-    let expected_genesis_text = req.expected_genesis_hash.trim();
-    let expected_genesis_hash =
-        solana_sdk::hash::Hash::from_str(expected_genesis_text).map_err(|_| ApiError {
-            message: "无效的预期 genesis hash".to_string(),
-        })?;
-    if expected_genesis_hash == solana_sdk::hash::Hash::default()
-        || expected_genesis_hash.to_string() != expected_genesis_text
+    if message
+        .address_table_lookups()
+        .is_some_and(|lookups| !lookups.is_empty())
     {
-        return Err(ApiError {
-            message: "预期 genesis hash 必须是 canonical 非零值".to_string(),
-        });
+        warnings.push(
+            "v0 交易使用地址表；当前预览只展示静态账户，动态加载账户需在链上解析后核对".to_string(),
+        );
+    }
+    if programs
+        .iter()
+        .any(|program| external_sign_program_label(program) == "Unknown Program")
+    {
+        warnings.push("交易包含未识别 program，请确认 DApp 来源和指令意图".to_string());
     }
-    Ok(ProgramDeploymentJournalIntent {
-        expected_genesis_hash,
-        program_id: parse_canonical_deployment_pubkey(&req.expected_program_id, "预期 Program ID")?,
-        program_sha256,
-        program_len: req.program_len,
-        max_data_len: req.max_data_len,
-        upgrade_authority: parse_canonical_deployment_pubkey(
-            &req.expected_upgrade_authority,
-            "预期 Upgrade Authority",
-        )?,
-    })
-}
 
-async fn program_deployment_journal(
-    Json(req): Json<ProgramDeploymentJournalRequest>,
-) -> Result<Json<ProgramDeploymentJournalResponse>, ApiError> {
-    if req.network.trim().is_empty() {
-        return Err(ApiError {
-            message: "部署网络不能为空".to_string(),
-        });
+    ExternalSignTransactionPreviewResponse {
+        status: "success".to_string(),
+        transaction_format: "versioned".to_string(),
+        recent_blockhash: message.recent_blockhash().to_string(),
+        required_signatures,
+        required_signer_present,
+        signer_accounts,
+        writable_accounts,
+        readonly_accounts,
+        programs,
+        instructions,
+        warnings,
     }
-    let intent = validate_program_deployment_journal_request(&req)?;
-    let selector = rpc_selector(Some(req.network.trim()))?;
-    let network = selector.network;
-    let genesis_hash = intent.expected_genesis_hash.to_string();
-    let program_id = intent.program_id.to_string();
-    let (journal, deployment_attempts) =
-        wallet_store::load_program_deployment_snapshot(&genesis_hash, &program_id)
-            .map_err(|message| ApiError { message })?;
-    let mut matching_journal = None;
-    let mut matching_attempts = Vec::new();
-    let mut conflicting_journal = None;
-    let mut conflicting_attempts = Vec::new();
-    if let Some(record) = journal.as_ref() {
-        if !deployment_status_is_known(&record.status)
-            || record.attempt_evidence_version
-                != wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_EVIDENCE_VERSION
-        {
-            return Err(deployment_journal_error(format!(
-                "Program {} 的部署记录状态或证据版本无效",
-                intent.program_id
-            )));
-        }
-        for attempt in &deployment_attempts {
-            validate_program_deployment_attempt_record(record, attempt)?;
-        }
-        let binding_matches = validate_deployment_journal_binding(
-            record,
-            &genesis_hash,
-            &intent.program_id,
-            &intent.program_sha256,
-            intent.program_len,
-            intent.max_data_len,
-            &intent.upgrade_authority,
-            None,
-        )
-        .is_ok();
-        if binding_matches {
-            matching_journal = Some(ProgramDeploymentJournalView::from(record));
-            matching_attempts = deployment_attempts;
-        } else {
-            conflicting_journal = Some(ProgramDeploymentJournalView::from(record));
-            conflicting_attempts = deployment_attempts;
-        }
-    } else if !deployment_attempts.is_empty() {
-        return Err(deployment_journal_error(
-            "部署 attempt 缺少对应 journal，拒绝返回撕裂快照",
-        ));
+}
+
+fn preview_external_transaction_request(
+    req: &ExternalSignPreviewRequest,
+) -> Result<ExternalSignTransactionPreviewResponse, ApiError> {
+    let required_signer = req.required_signer.trim();
+    let required_signer = if required_signer.is_empty() {
+        None
+    } else {
+        Some(Pubkey::from_str(required_signer).map_err(|_| ApiError {
+            message: "requiredSigner 地址无效".to_string(),
+        })?)
+    };
+    let transaction_format = req
+        .transaction_format
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .unwrap_or("auto")
+        .to_ascii_lowercase();
+    match transaction_format.as_str() {
+        "legacy" => Ok(preview_external_legacy_transaction(
+            &decode_external_legacy_transaction(&req.transaction_base64)?,
+            required_signer.as_ref(),
+        )),
+        "versioned" | "v0" => Ok(preview_external_versioned_transaction(
+            &decode_external_versioned_transaction(&req.transaction_base64)?,
+            required_signer.as_ref(),
+        )),
+        "auto" => match decode_external_versioned_transaction(&req.transaction_base64) {
+            Ok(transaction) => Ok(preview_external_versioned_transaction(
+                &transaction,
+                required_signer.as_ref(),
+            )),
+            Err(versioned_error) => {
+                let transaction = decode_external_legacy_transaction(&req.transaction_base64)
+                    .map_err(|legacy_error| ApiError {
+                        message: format!(
+                            "交易既不能按 versioned 也不能按 legacy 预览；versioned: {}；legacy: {}",
+                            versioned_error.message, legacy_error.message
+                        ),
+                    })?;
+                Ok(preview_external_legacy_transaction(
+                    &transaction,
+                    required_signer.as_ref(),
+                ))
+            }
+        },
+        _ => Err(ApiError {
+            message: "transactionFormat 必须是 auto、legacy 或 versioned".to_string(),
+        }),
     }
-    Ok(Json(ProgramDeploymentJournalResponse {
-        network,
-        genesis_hash,
-        write_chunk_bytes: PROGRAM_WRITE_CHUNK_BYTES,
-        write_chunk_count: intent.program_len.div_ceil(PROGRAM_WRITE_CHUNK_BYTES),
-        journal: matching_journal,
-        deployment_attempts: matching_attempts,
-        conflicting_journal,
-        conflicting_deployment_attempts: conflicting_attempts,
-    }))
 }
 
-async fn program_deployment_journal_by_program(
-    Json(req): Json<ProgramDeploymentJournalByProgramRequest>,
-) -> Result<Json<ProgramDeploymentJournalResponse>, ApiError> {
-    if req.network.trim().is_empty() {
+async fn external_sign_preview(
+    Json(req): Json<ExternalSignPreviewRequest>,
+) -> Result<Json<ExternalSignTransactionPreviewResponse>, ApiError> {
+    Ok(Json(preview_external_transaction_request(&req)?))
+}
+
+fn external_sign_legacy_transaction(
+    transaction_base64: &str,
+    required_signer: &Pubkey,
+    signing_keypair: &Keypair,
+) -> Result<Transaction, ApiError> {
+    let mut transaction = decode_external_legacy_transaction(transaction_base64)?;
+    transaction.sanitize().map_err(|error| ApiError {
+        message: format!("外部交易结构校验失败: {error:?}"),
+    })?;
+    let required_signature_count = transaction.message.header.num_required_signatures as usize;
+    let signing_position = transaction
+        .message
+        .account_keys
+        .get(0..required_signature_count)
+        .and_then(|signers| signers.iter().position(|pubkey| pubkey == required_signer))
+        .ok_or_else(|| ApiError {
+            message: format!("交易没有要求 {} 作为 signer", required_signer),
+        })?;
+    if signing_position >= transaction.signatures.len() {
         return Err(ApiError {
-            message: "部署网络不能为空".to_string(),
+            message: "交易签名数组与 message signer 数量不匹配".to_string(),
         });
     }
-    let selector = rpc_selector(Some(req.network.trim()))?;
-    let network = selector.network;
-    let expected_genesis_text = req.expected_genesis_hash.trim();
-    let expected_genesis_hash =
-        solana_sdk::hash::Hash::from_str(expected_genesis_text).map_err(|_| ApiError {
-            message: "无效的预期 genesis hash".to_string(),
+
+    let recent_blockhash = transaction.message.recent_blockhash;
+    transaction
+        .try_partial_sign(&[signing_keypair], recent_blockhash)
+        .map_err(|error| ApiError {
+            message: format!("外部交易签名失败: {error}"),
         })?;
-    if expected_genesis_hash == solana_sdk::hash::Hash::default()
-        || expected_genesis_hash.to_string() != expected_genesis_text
-    {
+    let signer_signature = transaction
+        .signatures
+        .get(signing_position)
+        .ok_or_else(|| ApiError {
+            message: "交易签名数组与 message signer 数量不匹配".to_string(),
+        })?;
+    if !signer_signature.verify(required_signer.as_ref(), &transaction.message_data()) {
         return Err(ApiError {
-            message: "预期 genesis hash 必须是 canonical 非零值".to_string(),
+            message: "外部交易当前钱包签名无效，已中止".to_string(),
         });
     }
-    let program_id = parse_canonical_deployment_pubkey(&req.program_id, "Program ID")?;
-    let genesis_hash = expected_genesis_hash.to_string();
-    let program_id = program_id.to_string();
-    let (journal, deployment_attempts) =
-        wallet_store::load_program_deployment_snapshot(&genesis_hash, &program_id)
-            .map_err(|message| ApiError { message })?;
-    let Some(record) = journal.as_ref() else {
-        return Ok(Json(ProgramDeploymentJournalResponse {
-            network,
-            genesis_hash,
-            write_chunk_bytes: PROGRAM_WRITE_CHUNK_BYTES,
-            write_chunk_count: 0,
-            journal: None,
-            deployment_attempts: Vec::new(),
-            conflicting_journal: None,
-            conflicting_deployment_attempts: Vec::new(),
-        }));
-    };
-    if !deployment_status_is_known(&record.status)
-        || record.attempt_evidence_version
-            != wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_EVIDENCE_VERSION
-    {
-        return Err(deployment_journal_error(format!(
-            "Program {} 的部署记录状态或证据版本无效",
-            program_id
-        )));
-    }
-    for attempt in &deployment_attempts {
-        validate_program_deployment_attempt_record(record, attempt)?;
-    }
-    Ok(Json(ProgramDeploymentJournalResponse {
-        network,
-        genesis_hash,
-        write_chunk_bytes: PROGRAM_WRITE_CHUNK_BYTES,
-        write_chunk_count: record.program_len.div_ceil(PROGRAM_WRITE_CHUNK_BYTES),
-        journal: Some(ProgramDeploymentJournalView::from(record)),
-        deployment_attempts,
-        conflicting_journal: None,
-        conflicting_deployment_attempts: Vec::new(),
-    }))
+    Ok(transaction)
 }
 
-#[cfg(test)]
-mod generic_program_deployment_policy_tests {
-    use super::*;
+fn external_sign_versioned_transaction(
+    transaction_base64: &str,
+    required_signer: &Pubkey,
+    signing_keypair: &Keypair,
+) -> Result<VersionedTransaction, ApiError> {
+    let mut transaction = decode_external_versioned_transaction(transaction_base64)?;
+    transaction.sanitize().map_err(|error| ApiError {
+        message: format!("外部 versioned 交易结构校验失败: {error:?}"),
+    })?;
+    let required_signature_count = transaction.message.header().num_required_signatures as usize;
+    let signing_position = transaction
+        .message
+        .static_account_keys()
+        .get(0..required_signature_count)
+        .and_then(|signers| signers.iter().position(|pubkey| pubkey == required_signer))
+        .ok_or_else(|| ApiError {
+            message: format!("交易没有要求 {} 作为 signer", required_signer),
+        })?;
+    if signing_position >= transaction.signatures.len() {
+        return Err(ApiError {
+            message: "交易签名数组与 message signer 数量不匹配".to_string(),
+        });
+    }
 
-    fn unique_temp_path(label: &str) -> PathBuf {
-        let nanos = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .map(|value| value.as_nanos())
-            .unwrap_or_default();
-        std::env::temp_dir().join(format!(
-            "fnzero-safe-{label}-{}-{nanos}",
-            std::process::id()
-        ))
-    }
-
-    fn valid_journal_request() -> ProgramDeploymentJournalRequest {
-        ProgramDeploymentJournalRequest {
-            network: "devnet".to_string(),
-            expected_genesis_hash: solana_sdk::hash::Hash::new_unique().to_string(),
-            expected_program_id: Pubkey::new_unique().to_string(),
-            expected_program_sha256: "AB".repeat(32),
-            program_len: 1_024,
-            max_data_len: 2_048,
-            expected_upgrade_authority: Pubkey::new_unique().to_string(),
-        }
-    }
-
-    #[test]
-    fn generic_journal_intent_normalizes_hash_without_hidden_policy() {
-        let request = valid_journal_request();
-        let intent = validate_program_deployment_journal_request(&request).unwrap();
-        assert_eq!(intent.program_sha256, "ab".repeat(32));
-        assert_eq!(intent.program_id.to_string(), request.expected_program_id);
-        assert_eq!(
-            intent.upgrade_authority.to_string(),
-            request.expected_upgrade_authority
-        );
+    let signature = signing_keypair
+        .try_sign_message(&transaction.message.serialize())
+        .map_err(|error| ApiError {
+            message: format!("外部 versioned 交易签名失败: {error}"),
+        })?;
+    transaction.signatures[signing_position] = signature;
+    let signer_signature = transaction
+        .signatures
+        .get(signing_position)
+        .ok_or_else(|| ApiError {
+            message: "交易签名数组与 message signer 数量不匹配".to_string(),
+        })?;
+    if !signer_signature.verify(required_signer.as_ref(), &transaction.message.serialize()) {
+        return Err(ApiError {
+            message: "外部 versioned 交易当前钱包签名无效，已中止".to_string(),
+        });
     }
+    Ok(transaction)
+}
 
-    #[test]
-    fn program_write_chunk_fits_solana_transaction_packet() {
-        let payer = Keypair::new();
-        let buffer = Pubkey::new_unique();
-        let chunk = vec![7_u8; PROGRAM_WRITE_CHUNK_BYTES];
-        let instruction = loader_v3_instruction::write(&buffer, &payer.pubkey(), 0, chunk);
-        let transaction = Transaction::new_signed_with_payer(
-            &[instruction],
-            Some(&payer.pubkey()),
-            &[&payer],
-            solana_sdk::hash::Hash::new_unique(),
-        );
-        let serialized = bincode::serialize(&transaction).unwrap();
-        assert!(
-            serialized.len() <= SOLANA_TRANSACTION_PACKET_DATA_BYTES,
-            "serialized write transaction is {} bytes, above packet limit {}",
-            serialized.len(),
-            SOLANA_TRANSACTION_PACKET_DATA_BYTES,
-        );
-    }
+enum SignedExternalTransaction {
+    Legacy(Transaction),
+    Versioned(VersionedTransaction),
+}
 
-    #[test]
-    fn external_sign_preview_summarizes_legacy_transaction() {
-        let payer = Keypair::new();
-        let recipient = Pubkey::new_unique();
-        let instruction = Instruction {
-            program_id: Pubkey::default(),
-            accounts: vec![
-                AccountMeta::new(payer.pubkey(), true),
-                AccountMeta::new(recipient, false),
-            ],
-            data: Vec::new(),
-        };
-        let transaction = Transaction::new_signed_with_payer(
-            &[instruction],
-            Some(&payer.pubkey()),
-            &[&payer],
-            solana_sdk::hash::Hash::new_unique(),
-        );
-        let transaction_base64 = BASE64.encode(bincode::serialize(&transaction).unwrap());
-        let preview = preview_external_transaction_request(&ExternalSignPreviewRequest {
-            required_signer: payer.pubkey().to_string(),
-            transaction_base64,
-            transaction_format: Some("legacy".to_string()),
+impl SignedExternalTransaction {
+    fn raw_base64(&self) -> Result<String, ApiError> {
+        Ok(match self {
+            Self::Legacy(transaction) => {
+                BASE64.encode(bincode::serialize(transaction).map_err(|error| ApiError {
+                    message: format!("已签名交易序列化失败: {error}"),
+                })?)
+            }
+            Self::Versioned(transaction) => {
+                BASE64.encode(bincode::serialize(transaction).map_err(|error| ApiError {
+                    message: format!("已签名 versioned 交易序列化失败: {error}"),
+                })?)
+            }
         })
-        .unwrap();
-
-        assert_eq!(preview.transaction_format, "legacy");
-        assert!(preview.required_signer_present);
-        assert_eq!(preview.required_signatures, 1);
-        assert_eq!(preview.instructions.len(), 1);
-        assert!(preview
-            .instructions
-            .first()
-            .unwrap()
-            .program_id
-            .contains("System Program"));
-        assert!(preview
-            .writable_accounts
-            .iter()
-            .any(|account| account == &recipient.to_string()));
     }
 
-    #[test]
-    fn generic_journal_intent_rejects_unsafe_boundaries() {
-        let mut request = valid_journal_request();
-        request.program_len = 0;
-        assert!(validate_program_deployment_journal_request(&request).is_err());
-
-        let mut request = valid_journal_request();
-        request.max_data_len = request.program_len - 1;
-        assert!(validate_program_deployment_journal_request(&request).is_err());
-
-        let mut request = valid_journal_request();
-        request.expected_program_sha256 = "xyz".to_string();
-        assert!(validate_program_deployment_journal_request(&request).is_err());
-
-        let mut request = valid_journal_request();
-        request.expected_program_id = Pubkey::default().to_string();
-        assert!(validate_program_deployment_journal_request(&request).is_err());
-
-        let mut request = valid_journal_request();
-        request.expected_genesis_hash = solana_sdk::hash::Hash::default().to_string();
-        assert!(validate_program_deployment_journal_request(&request).is_err());
+    fn recent_blockhash(&self) -> solana_sdk::hash::Hash {
+        match self {
+            Self::Legacy(transaction) => transaction.message.recent_blockhash,
+            Self::Versioned(transaction) => *transaction.message.recent_blockhash(),
+        }
     }
 
-    #[test]
-    fn unknown_network_labels_never_fall_back_to_mainnet() {
-        assert!(parse_rpc_selector("devnett").is_err());
-        assert!(parse_rpc_selector("rpc:devnett:https%3A%2F%2Fexample.invalid").is_err());
-        assert_eq!(parse_rpc_selector("mainnet").unwrap().network, "mainnet");
+    async fn submit_once(
+        &self,
+        client: &RpcClient,
+        action: &str,
+        context: &str,
+        commitment: CommitmentConfig,
+        commitment_label: &str,
+        timeout: Duration,
+    ) -> Result<(Signature, u64), ApiError> {
+        match self {
+            Self::Legacy(transaction) => {
+                submit_signed_transaction_once(
+                    client,
+                    transaction,
+                    action,
+                    context,
+                    commitment,
+                    commitment_label,
+                    timeout,
+                )
+                .await
+            }
+            Self::Versioned(transaction) => {
+                submit_signed_versioned_transaction_once(
+                    client,
+                    transaction,
+                    action,
+                    context,
+                    commitment,
+                    commitment_label,
+                    timeout,
+                )
+                .await
+            }
+        }
     }
+}
 
-    #[cfg(unix)]
-    #[test]
-    fn program_source_artifact_discovery_rejects_symlinks_outside_source_dir() {
-        use std::os::unix::fs::symlink;
-
-        let base = unique_temp_path("source-artifact-symlink");
-        let source = base.join("source");
-        let outside = base.join("outside");
-        fs::create_dir_all(source.join("target/deploy")).unwrap();
-        fs::create_dir_all(source.join(".keys")).unwrap();
-        fs::create_dir_all(&outside).unwrap();
-
-        let outside_so = outside.join("escaped.so");
-        let outside_keypair = outside.join("escaped-keypair.json");
-        fs::write(&outside_so, b"not-a-program").unwrap();
-        fs::write(&outside_keypair, b"[]").unwrap();
-        symlink(&outside_so, source.join("target/deploy/escaped.so")).unwrap();
-        symlink(&outside_keypair, source.join(".keys/escaped-keypair.json")).unwrap();
-
-        let canonical_source = source.canonicalize().unwrap();
-        assert!(find_program_so_path(&canonical_source, None).is_none());
-        assert!(find_program_keypair_path(&canonical_source, None).is_none());
+fn sign_external_transaction_request(
+    req: &ExternalSignSubmitRequest,
+) -> Result<(Pubkey, Pubkey, SignedExternalTransaction, String, String), ApiError> {
+    let required_signer = Pubkey::from_str(req.required_signer.trim()).map_err(|_| ApiError {
+        message: "requiredSigner 地址无效".to_string(),
+    })?;
+    let signing_keypair = req.wallet.keypair()?;
+    let signed_by = signing_keypair.pubkey();
+    if signed_by != required_signer {
+        return Err(ApiError {
+            message: format!(
+                "外部签名请求只允许 {} 签名，当前解锁钱包为 {}",
+                required_signer, signed_by
+            ),
+        });
     }
 
-    #[test]
-    fn program_source_artifact_discovery_prefers_target_deploy_so() {
-        let source = unique_temp_path("source-artifact-prefer-deploy-so");
-        fs::create_dir_all(source.join("target/verifiable")).unwrap();
-        fs::create_dir_all(source.join("target/deploy")).unwrap();
-
-        let deploy_so = source.join("target/deploy/fnzero.so");
-        let deploy_bytes = b"deploy-so";
-        fs::write(&deploy_so, deploy_bytes).unwrap();
-
-        // Even if verifiable is newer, target/deploy/<name>.so must win.
-        std::thread::sleep(Duration::from_millis(20));
-        fs::write(
-            source.join("target/verifiable/fnzero.so"),
-            b"newer-verifiable",
-        )
-        .unwrap();
-        fs::write(
-            source.join("target/verifiable/fnzero-build.json"),
-            format!(
-                r#"{{"artifacts":{{"program":{{"sha256":"{}","length":{}}}}}}}"#,
-                "0".repeat(64),
-                99
-            ),
-        )
-        .unwrap();
-
-        let canonical_source = source.canonicalize().unwrap();
-        let selected = find_program_so_path(&canonical_source, Some("fnzero")).unwrap();
-        assert_eq!(selected, deploy_so.canonicalize().unwrap());
-
-        let artifacts =
-            load_program_source_artifacts(&canonical_source, Some("fnzero"), Some("devnet"), true)
-                .unwrap();
-        let deploy_sha = program_deploy::sha256_hex(deploy_bytes);
-        assert_eq!(
-            artifacts.program_so_path,
-            Some(deploy_so.canonicalize().unwrap())
-        );
-        assert_eq!(
-            artifacts.program_so_sha256.as_deref(),
-            Some(deploy_sha.as_str())
-        );
-        assert!(artifacts
-            .warnings
-            .iter()
-            .any(|warning| warning.contains("build metadata SHA-256")));
+    let transaction_format = req
+        .transaction_format
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .unwrap_or("auto")
+        .to_ascii_lowercase();
+    let signed_transaction = match transaction_format.as_str() {
+        "legacy" => SignedExternalTransaction::Legacy(external_sign_legacy_transaction(
+            &req.transaction_base64,
+            &required_signer,
+            &signing_keypair,
+        )?),
+        "versioned" | "v0" => {
+            SignedExternalTransaction::Versioned(external_sign_versioned_transaction(
+                &req.transaction_base64,
+                &required_signer,
+                &signing_keypair,
+            )?)
+        }
+        "auto" => {
+            match external_sign_versioned_transaction(
+                &req.transaction_base64,
+                &required_signer,
+                &signing_keypair,
+            ) {
+                Ok(transaction) => SignedExternalTransaction::Versioned(transaction),
+                Err(versioned_error) => SignedExternalTransaction::Legacy(
+                    external_sign_legacy_transaction(
+                        &req.transaction_base64,
+                        &required_signer,
+                        &signing_keypair,
+                    )
+                    .map_err(|legacy_error| ApiError {
+                        message: format!(
+                            "交易既不能按 versioned 也不能按 legacy 签名；versioned: {}；legacy: {}",
+                            versioned_error.message, legacy_error.message
+                        ),
+                    })?,
+                ),
+            }
+        }
+        _ => {
+            return Err(ApiError {
+                message: "transactionFormat 必须是 auto、legacy 或 versioned".to_string(),
+            })
+        }
+    };
+    let signed_transaction_base64 = signed_transaction.raw_base64()?;
+    let signed_recent_blockhash_hash = signed_transaction.recent_blockhash();
+    let signed_recent_blockhash = signed_recent_blockhash_hash.to_string();
+    Ok((
+        required_signer,
+        signed_by,
+        signed_transaction,
+        signed_transaction_base64,
+        signed_recent_blockhash,
+    ))
+}
 
-        let _ = fs::remove_dir_all(source);
+async fn external_sign_sign(
+    Json(req): Json<ExternalSignSubmitRequest>,
+) -> Result<Json<ExternalSignOnlyResponse>, ApiError> {
+    validate_external_sign_expiry(req.expires_at)?;
+    let (
+        required_signer,
+        signed_by,
+        _signed_transaction,
+        signed_transaction_base64,
+        signed_recent_blockhash,
+    ) = sign_external_transaction_request(&req)?;
+    if let Some(expected_recent_blockhash) = req
+        .recent_blockhash
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+    {
+        if expected_recent_blockhash != signed_recent_blockhash {
+            return Err(ApiError {
+                message: format!(
+                    "交易 blockhash 为 {}，与请求中的 {} 不一致；请重新导入签名请求",
+                    signed_recent_blockhash, expected_recent_blockhash
+                ),
+            });
+        }
     }
 
-    #[test]
-    fn program_keypair_discovery_prefers_so_stem_specific_keypair() {
-        let source = unique_temp_path("source-artifact-keypair-stem");
-        fs::create_dir_all(source.join(".keys")).unwrap();
-        fs::create_dir_all(source.join("target/deploy")).unwrap();
-
-        let unrelated_keypair = Keypair::new();
-        let target_keypair = Keypair::new();
-        fs::write(
-            source.join(".keys/alpha-program-keypair.json"),
-            serde_json::to_string(&unrelated_keypair.to_bytes().to_vec()).unwrap(),
-        )
-        .unwrap();
-        fs::write(
-            source.join("target/deploy/beta-keypair.json"),
-            serde_json::to_string(&target_keypair.to_bytes().to_vec()).unwrap(),
-        )
-        .unwrap();
-
-        let canonical_source = source.canonicalize().unwrap();
-        let selected = find_program_keypair_path(&canonical_source, Some("beta")).unwrap();
-        assert_eq!(
-            selected,
-            source
-                .join("target/deploy/beta-keypair.json")
-                .canonicalize()
-                .unwrap()
-        );
-        let mut json = fs::read_to_string(selected).unwrap();
-        let parsed = program_deploy::parse_program_keypair_json(&json).unwrap();
-        json.zeroize();
-        assert_eq!(parsed.pubkey(), target_keypair.pubkey());
-        assert_eq!(
-            artifact_stem_from_so_name("beta.so").as_deref(),
-            Some("beta")
-        );
+    Ok(Json(ExternalSignOnlyResponse {
+        status: "success".to_string(),
+        required_signer: required_signer.to_string(),
+        signed_by: signed_by.to_string(),
+        request_id: req.request_id,
+        raw_transaction: signed_transaction_base64,
+        recent_blockhash: signed_recent_blockhash,
+    }))
+}
 
-        let _ = fs::remove_dir_all(source);
+async fn external_sign_message(
+    Json(req): Json<ExternalSignMessageRequest>,
+) -> Result<Json<ExternalSignMessageResponse>, ApiError> {
+    validate_external_sign_expiry(req.expires_at)?;
+    let required_signer = Pubkey::from_str(req.required_signer.trim()).map_err(|_| ApiError {
+        message: "requiredSigner 地址无效".to_string(),
+    })?;
+    let signing_keypair = req.wallet.keypair()?;
+    let signed_by = signing_keypair.pubkey();
+    if signed_by != required_signer {
+        return Err(ApiError {
+            message: format!(
+                "外部消息签名请求只允许 {} 签名，当前解锁钱包为 {}",
+                required_signer, signed_by
+            ),
+        });
     }
 
-    #[test]
-    fn program_keypair_discovery_prefers_target_deploy_over_keys() {
-        let source = unique_temp_path("source-artifact-keypair-prefer-deploy");
-        fs::create_dir_all(source.join(".keys")).unwrap();
-        fs::create_dir_all(source.join("target/deploy")).unwrap();
+    let message_base64 = req.message_base64.trim();
+    require_nonempty(message_base64, "消息 base64")?;
+    let message_bytes = BASE64.decode(message_base64).map_err(|error| ApiError {
+        message: format!("消息 base64 解码失败: {error}"),
+    })?;
+    if message_bytes.is_empty() || message_bytes.len() > MAX_EXTERNAL_SIGN_MESSAGE_BYTES {
+        return Err(ApiError {
+            message: format!(
+                "消息长度无效，最大 {} bytes",
+                MAX_EXTERNAL_SIGN_MESSAGE_BYTES
+            ),
+        });
+    }
 
-        let keys_keypair = Keypair::new();
-        let deploy_keypair = Keypair::new();
-        let keys_path = source.join(".keys/fnzero-program-keypair.json");
-        let deploy_path = source.join("target/deploy/fnzero-keypair.json");
-        fs::write(
-            &deploy_path,
-            serde_json::to_string(&deploy_keypair.to_bytes().to_vec()).unwrap(),
-        )
-        .unwrap();
-        // Even if .keys is newer, target/deploy must win.
-        std::thread::sleep(Duration::from_millis(20));
-        fs::write(
-            &keys_path,
-            serde_json::to_string(&keys_keypair.to_bytes().to_vec()).unwrap(),
-        )
-        .unwrap();
+    let signature = signing_keypair
+        .try_sign_message(&message_bytes)
+        .map_err(|error| ApiError {
+            message: format!("外部消息签名失败: {error}"),
+        })?;
+    if !signature.verify(required_signer.as_ref(), &message_bytes) {
+        return Err(ApiError {
+            message: "外部消息当前钱包签名无效，已中止".to_string(),
+        });
+    }
 
-        let canonical_source = source.canonicalize().unwrap();
-        let selected = find_program_keypair_path(&canonical_source, Some("fnzero")).unwrap();
-        assert_eq!(selected, deploy_path.canonicalize().unwrap());
-        let mut json = fs::read_to_string(selected).unwrap();
-        let parsed = program_deploy::parse_program_keypair_json(&json).unwrap();
-        json.zeroize();
-        assert_eq!(parsed.pubkey(), deploy_keypair.pubkey());
+    Ok(Json(ExternalSignMessageResponse {
+        status: "success".to_string(),
+        required_signer: required_signer.to_string(),
+        signed_by: signed_by.to_string(),
+        request_id: req.request_id,
+        signature: signature.to_string(),
+        message_base64: message_base64.to_string(),
+    }))
+}
 
-        let _ = fs::remove_dir_all(source);
+async fn external_sign_submit(
+    Json(req): Json<ExternalSignSubmitRequest>,
+) -> Result<Json<ExternalSignSubmitResponse>, ApiError> {
+    validate_external_sign_expiry(req.expires_at)?;
+    let (
+        required_signer,
+        signed_by,
+        signed_transaction,
+        signed_transaction_base64,
+        signed_recent_blockhash,
+    ) = sign_external_transaction_request(&req)?;
+    let signed_recent_blockhash_hash = signed_transaction.recent_blockhash();
+    if let Some(expected_recent_blockhash) = req
+        .recent_blockhash
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+    {
+        if expected_recent_blockhash != signed_recent_blockhash {
+            return Err(ApiError {
+                message: format!(
+                    "交易 blockhash 为 {}，与请求中的 {} 不一致；请重新导入签名请求",
+                    signed_recent_blockhash, expected_recent_blockhash
+                ),
+            });
+        }
     }
 
-    #[test]
-    fn program_keypair_discovery_falls_back_to_keys_when_deploy_missing() {
-        let source = unique_temp_path("source-artifact-keypair-keys-fallback");
-        fs::create_dir_all(source.join(".keys")).unwrap();
-        fs::create_dir_all(source.join("target/deploy")).unwrap();
+    let (client, network) = rpc_client_for(req.network.as_deref())?;
+    let signed_last_valid_block_height = match req.last_valid_block_height {
+        Some(last_valid_block_height) if last_valid_block_height > 0 => last_valid_block_height,
+        _ => {
+            let latest = client
+                .get_latest_blockhash_with_commitment(CommitmentConfig::confirmed())
+                .map_err(|error| ApiError {
+                    message: format!("读取 RPC latest blockhash 失败: {error}"),
+                })?;
+            if latest.0 != signed_recent_blockhash_hash {
+                return Err(ApiError {
+                    message: "签名请求缺少 lastValidBlockHeight，且当前 RPC latest blockhash 与交易不一致；请重新导入签名请求"
+                        .to_string(),
+                });
+            }
+            latest.1
+        }
+    };
+    if let Some(expected_genesis_hash) = req
+        .expected_genesis_hash
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+    {
+        let expected_genesis_hash = solana_sdk::hash::Hash::from_str(expected_genesis_hash)
+            .map_err(|_| ApiError {
+                message: "expectedGenesisHash 无效".to_string(),
+            })?;
+        let actual_genesis_hash = client.get_genesis_hash().map_err(|error| ApiError {
+            message: format!("读取 RPC genesis hash 失败: {error}"),
+        })?;
+        if actual_genesis_hash != expected_genesis_hash {
+            return Err(ApiError {
+                message: format!(
+                    "RPC genesis hash 为 {}，预期为 {}；已在提交前中止",
+                    actual_genesis_hash, expected_genesis_hash
+                ),
+            });
+        }
+    }
 
-        let keys_keypair = Keypair::new();
-        let keys_path = source.join(".keys/fnzero-program-keypair.json");
-        fs::write(
-            &keys_path,
-            serde_json::to_string(&keys_keypair.to_bytes().to_vec()).unwrap(),
+    let context = format!(
+        "external_sign request={} signer={} network={}",
+        req.request_id.as_deref().unwrap_or("-"),
+        required_signer,
+        network
+    );
+    let (signature, slot) = signed_transaction
+        .submit_once(
+            &client,
+            "外部签名",
+            &context,
+            CommitmentConfig::confirmed(),
+            "confirmed",
+            Duration::from_secs(20),
         )
-        .unwrap();
+        .await?;
 
-        let canonical_source = source.canonicalize().unwrap();
-        let selected = find_program_keypair_path(&canonical_source, Some("fnzero")).unwrap();
-        assert_eq!(selected, keys_path.canonicalize().unwrap());
-        let mut json = fs::read_to_string(selected).unwrap();
-        let parsed = program_deploy::parse_program_keypair_json(&json).unwrap();
-        json.zeroize();
-        assert_eq!(parsed.pubkey(), keys_keypair.pubkey());
+    Ok(Json(ExternalSignSubmitResponse {
+        status: "success".to_string(),
+        signature: signature.to_string(),
+        slot,
+        required_signer: required_signer.to_string(),
+        signed_by: signed_by.to_string(),
+        network,
+        request_id: req.request_id,
+        raw_transaction: Some(signed_transaction_base64),
+        recent_blockhash: Some(signed_recent_blockhash),
+        last_valid_block_height: Some(signed_last_valid_block_height),
+    }))
+}
 
-        let _ = fs::remove_dir_all(source);
-    }
+#[derive(Serialize)]
+struct RemoteSignPairingView {
+    #[serde(flatten)]
+    pairing: wallet_store::RemoteSignPairingRecord,
+    uri: String,
+    client_fingerprint: Option<String>,
+}
 
-    #[test]
-    fn program_source_artifacts_block_mismatched_program_ids_before_deploy() {
-        let source = unique_temp_path("source-artifact-program-id-validation");
-        fs::create_dir_all(source.join("target/deploy")).unwrap();
-        fs::create_dir_all(source.join("target/idl")).unwrap();
-        fs::create_dir_all(source.join("programs/fnzero/src")).unwrap();
+impl From<wallet_store::RemoteSignPairingRecord> for RemoteSignPairingView {
+    fn from(pairing: wallet_store::RemoteSignPairingRecord) -> Self {
+        Self {
+            uri: remote_sign::pairing_uri(&pairing),
+            client_fingerprint: pairing
+                .client_public_key
+                .as_deref()
+                .map(remote_sign_protocol::key_fingerprint),
+            pairing,
+        }
+    }
+}
 
-        let expected_keypair = Keypair::new();
-        let stale_program_pubkey = Pubkey::new_unique();
-        let stale_program_id = stale_program_pubkey.to_string();
-        let mut stale_so = b"\x7fELFAnchorError occurred".to_vec();
-        stale_so.extend_from_slice(&stale_program_pubkey.to_bytes());
-        fs::write(source.join("target/deploy/fnzero.so"), stale_so).unwrap();
-        fs::write(
-            source.join("target/deploy/fnzero-keypair.json"),
-            serde_json::to_string(&expected_keypair.to_bytes().to_vec()).unwrap(),
-        )
-        .unwrap();
-        fs::write(
-            source.join("Anchor.toml"),
-            format!(
-                r#"[programs.devnet]
-fnzero = "{stale_program_id}"
-"#
-            ),
-        )
-        .unwrap();
-        fs::write(
-            source.join("target/idl/fnzero.json"),
-            format!(r#"{{"address":"{stale_program_id}","instructions":[]}}"#),
-        )
-        .unwrap();
-        fs::write(
-            source.join("programs/fnzero/src/lib.rs"),
-            format!(r#"declare_id!("{stale_program_id}");"#),
-        )
-        .unwrap();
+#[derive(Serialize)]
+struct RemoteSignPairingsResponse {
+    pairings: Vec<RemoteSignPairingView>,
+    /// Relay prefilled for new pairings, from `[remote_sign] relay_url`
+    default_relay_url: Option<String>,
+}
 
-        let canonical_source = source.canonicalize().unwrap();
-        let artifacts =
-            load_program_source_artifacts(&canonical_source, Some("fnzero"), Some("devnet"), true)
-                .unwrap();
+async fn remote_sign_pairings() -> Result<Json<RemoteSignPairingsResponse>, ApiError> {
+    let pairings = wallet_store::list_remote_sign_pairings()
+        .map_err(|message| ApiError { message })?
+        .into_iter()
+        .map(RemoteSignPairingView::from)
+        .collect();
+    Ok(Json(RemoteSignPairingsResponse {
+        pairings,
+        default_relay_url: config::get().remote_sign_relay_url.clone(),
+    }))
+}
 
-        let expected_program_id = expected_keypair.pubkey().to_string();
-        assert_eq!(
-            artifacts.expected_program_id.as_deref(),
-            Some(expected_program_id.as_str())
-        );
-        assert!(artifacts
-            .source_validation_errors
-            .iter()
-            .any(|error| { error.contains("Anchor.toml") && error.contains(&stale_program_id) }));
-        assert!(artifacts
-            .source_validation_errors
-            .iter()
-            .any(|error| error.contains("target/idl/fnzero.json")));
-        assert!(artifacts
-            .source_validation_errors
-            .iter()
-            .any(|error| error.contains("declare_id!")));
-        assert!(
-            artifacts
-                .source_validation_errors
-                .iter()
-                .any(|error| error.contains("target/deploy/fnzero.so")
-                    && error.contains("Anchor .so"))
-        );
-        assert!(artifacts
-            .warnings
-            .iter()
-            .any(|warning| warning.contains("部署前一致性校验发现")));
+#[derive(Deserialize)]
+struct RemoteSignPairingCreateRequest {
+    name: String,
+    /// Falls back to `[remote_sign] relay_url` / `FNZERO_SAFE_RELAY_URL`
+    #[serde(default, alias = "relayUrl")]
+    relay_url: Option<String>,
+}
+
+async fn remote_sign_pairing_create(
+    Json(req): Json<RemoteSignPairingCreateRequest>,
+) -> Result<Json<RemoteSignPairingView>, ApiError> {
+    validate_text_len(&req.name, "name", MAX_TEXT_FIELD_CHARS)?;
+    let relay_url = req
+        .relay_url
+        .filter(|url| !url.trim().is_empty())
+        .or_else(|| config::get().remote_sign_relay_url.clone())
+        .ok_or_else(|| ApiError {
+            message: "中继地址不能为空".to_string(),
+        })?;
+    let pairing = remote_sign::create_pairing(&req.name, &relay_url)
+        .map_err(|message| ApiError { message })?;
+    tracing::info!("remote_sign pairing created id={}", pairing.id);
+    Ok(Json(pairing.into()))
+}
 
-        let _ = fs::remove_dir_all(source);
-    }
+#[derive(Deserialize)]
+struct RemoteSignIdRequest {
+    id: String,
+}
 
-    #[test]
-    fn prebuild_program_id_validation_blocks_only_source_declarations() {
-        let source = unique_temp_path("source-prebuild-program-id-validation");
-        fs::create_dir_all(source.join("target/idl")).unwrap();
-        fs::create_dir_all(source.join("programs/fnzero/src")).unwrap();
+async fn remote_sign_pairing_delete(
+    Json(req): Json<RemoteSignIdRequest>,
+) -> Result<Json<serde_json::Value>, ApiError> {
+    wallet_store::delete_remote_sign_pairing(&req.id).map_err(|message| ApiError { message })?;
+    Ok(Json(serde_json::json!({ "status": "success" })))
+}
 
-        let expected_program_id = Pubkey::new_unique().to_string();
-        let stale_idl_program_id = Pubkey::new_unique().to_string();
-        fs::write(
-            source.join("Anchor.toml"),
-            format!(
-                r#"[programs.devnet]
-fnzero = "{expected_program_id}"
-"#
-            ),
-        )
-        .unwrap();
-        fs::write(
-            source.join("target/idl/fnzero.json"),
-            format!(r#"{{"address":"{stale_idl_program_id}","instructions":[]}}"#),
-        )
-        .unwrap();
-        fs::write(
-            source.join("programs/fnzero/src/lib.rs"),
-            format!(r#"declare_id!("{expected_program_id}");"#),
-        )
-        .unwrap();
+#[derive(Serialize)]
+struct RemoteSignRequestView {
+    id: String,
+    pairing_id: String,
+    pairing_name: Option<String>,
+    client_fingerprint: Option<String>,
+    request_id: String,
+    status: String,
+    delivered: bool,
+    received_at: u64,
+    updated_at: u64,
+    created_at: u64,
+    expires_at: Option<u64>,
+    note: Option<String>,
+    #[serde(flatten)]
+    payload: remote_sign_protocol::SignPayload,
+    /// Same summary `/api/external-sign/preview` returns, for pending transactions
+    #[serde(skip_serializing_if = "Option::is_none")]
+    preview: Option<ExternalSignTransactionPreviewResponse>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    preview_error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response: Option<Value>,
+}
 
-        let canonical_source = source.canonicalize().unwrap();
-        let prebuild_errors = collect_prebuild_source_validation_errors(
-            &canonical_source,
-            Some("fnzero"),
-            Some("devnet"),
-            Some(&expected_program_id),
-        )
-        .unwrap();
-        let postbuild_errors = collect_source_validation_errors(
-            &canonical_source,
-            Some("fnzero"),
-            Some("devnet"),
-            Some(&expected_program_id),
-            None,
-        )
-        .unwrap();
+fn remote_sign_request_view(
+    record: wallet_store::RemoteSignRequestRecord,
+    pairings: &[wallet_store::RemoteSignPairingRecord],
+) -> Result<RemoteSignRequestView, ApiError> {
+    let request: remote_sign_protocol::SignRequest = serde_json::from_str(&record.request_json)
+        .map_err(|e| ApiError {
+            message: format!("远程签名请求记录无效: {}", e),
+        })?;
+    let pairing = pairings
+        .iter()
+        .find(|pairing| pairing.id == record.pairing_id);
+    let (preview, preview_error) = match &request.payload {
+        remote_sign_protocol::SignPayload::Transaction {
+            required_signer,
+            transaction_base64,
+            transaction_format,
+            ..
+        } if record.status == wallet_store::REMOTE_SIGN_STATUS_PENDING => {
+            match preview_external_transaction_request(&ExternalSignPreviewRequest {
+                required_signer: required_signer.clone(),
+                transaction_base64: transaction_base64.clone(),
+                transaction_format: transaction_format.clone(),
+            }) {
+                Ok(preview) => (Some(preview), None),
+                Err(error) => (None, Some(error.message)),
+            }
+        }
+        _ => (None, None),
+    };
+    Ok(RemoteSignRequestView {
+        id: record.id,
+        pairing_id: record.pairing_id,
+        pairing_name: pairing.map(|pairing| pairing.name.clone()),
+        client_fingerprint: pairing
+            .and_then(|pairing| pairing.client_public_key.as_deref())
+            .map(remote_sign_protocol::key_fingerprint),
+        request_id: request.request_id,
+        status: record.status,
+        delivered: record.delivered,
+        received_at: record.created_at,
+        updated_at: record.updated_at,
+        created_at: request.created_at,
+        expires_at: request.expires_at,
+        note: request.note,
+        payload: request.payload,
+        preview,
+        preview_error,
+        response: record
+            .response_json
+            .and_then(|response| serde_json::from_str(&response).ok()),
+    })
+}
 
-        assert!(prebuild_errors.is_empty());
-        assert!(postbuild_errors
-            .iter()
-            .any(|error| error.contains("target/idl/fnzero.json")));
+#[derive(Deserialize, Default)]
+struct RemoteSignRequestListRequest {
+    /// `pending`, `approved`, `rejected` or `expired`; all when omitted
+    #[serde(default)]
+    status: Option<String>,
+}
 
-        let _ = fs::remove_dir_all(source);
-    }
+async fn remote_sign_requests(
+    Json(req): Json<RemoteSignRequestListRequest>,
+) -> Result<Json<Vec<RemoteSignRequestView>>, ApiError> {
+    let pairings =
+        wallet_store::list_remote_sign_pairings().map_err(|message| ApiError { message })?;
+    wallet_store::list_remote_sign_requests(req.status.as_deref().filter(|s| !s.is_empty()))
+        .map_err(|message| ApiError { message })?
+        .into_iter()
+        .map(|record| remote_sign_request_view(record, &pairings))
+        .collect::<Result<Vec<_>, _>>()
+        .map(Json)
+}
 
-    #[test]
-    fn generated_program_keypair_helpers_sync_anchor_sources() {
-        let source = unique_temp_path("source-generate-program-keypair-sync");
-        fs::create_dir_all(source.join("programs/fnzero/src")).unwrap();
-
-        let old_program_id = Pubkey::new_unique().to_string();
-        let new_program_id = Pubkey::new_unique().to_string();
-        fs::write(
-            source.join("Anchor.toml"),
-            format!(
-                r#"[programs.devnet]
-fnzero = "{old_program_id}"
-"#
-            ),
-        )
-        .unwrap();
-        fs::write(
-            source.join("programs/fnzero/src/lib.rs"),
-            format!(
-                r#"use anchor_lang::prelude::*;
-declare_id!("{old_program_id}");
-"#
-            ),
-        )
-        .unwrap();
-
-        let canonical_source = source.canonicalize().unwrap();
-        let anchor_updates =
-            update_anchor_program_ids(&canonical_source, Some("fnzero"), &new_program_id).unwrap();
-        let rust_updates =
-            update_rust_declare_id_files(&canonical_source, Some("fnzero"), &new_program_id)
-                .unwrap();
-
-        assert_eq!(anchor_updates.len(), 1);
-        assert_eq!(rust_updates.len(), 1);
-        assert!(fs::read_to_string(source.join("Anchor.toml"))
-            .unwrap()
-            .contains(&new_program_id));
-        assert!(
-            fs::read_to_string(source.join("programs/fnzero/src/lib.rs"))
-                .unwrap()
-                .contains(&new_program_id)
-        );
-        assert!(
-            !fs::read_to_string(source.join("programs/fnzero/src/lib.rs"))
-                .unwrap()
-                .contains(&old_program_id)
-        );
+async fn remote_sign_finished_view(
+    record: wallet_store::RemoteSignRequestRecord,
+) -> Result<Json<RemoteSignRequestView>, ApiError> {
+    // The poller retries delivery when the relay is unreachable right now
+    let record = match remote_sign::deliver(&record).await {
+        Ok(()) => wallet_store::find_remote_sign_request(&record.id)
+            .map_err(|message| ApiError { message })?,
+        Err(error) => {
+            tracing::warn!(
+                "remote_sign response {} not delivered yet: {}",
+                record.id,
+                error
+            );
+            record
+        }
+    };
+    let pairings =
+        wallet_store::list_remote_sign_pairings().map_err(|message| ApiError { message })?;
+    remote_sign_request_view(record, &pairings).map(Json)
+}
 
-        let _ = fs::remove_dir_all(source);
+/// Sign a remote request with the same handlers `/api/external-sign/*` uses
+async fn sign_remote_request(
+    request: remote_sign_protocol::SignRequest,
+    wallet: WalletAuthRequest,
+) -> Result<Value, ApiError> {
+    let to_value = |value: Result<Value, serde_json::Error>| {
+        value.map_err(|e| ApiError {
+            message: format!("序列化签名结果失败: {}", e),
+        })
+    };
+    match request.payload {
+        remote_sign_protocol::SignPayload::Transaction {
+            required_signer,
+            transaction_base64,
+            transaction_format,
+            network,
+            submit,
+            expected_genesis_hash,
+            recent_blockhash,
+            last_valid_block_height,
+        } => {
+            let req = ExternalSignSubmitRequest {
+                wallet,
+                required_signer,
+                transaction_base64,
+                transaction_format,
+                network,
+                request_id: Some(request.request_id),
+                expires_at: request.expires_at,
+                expected_genesis_hash,
+                recent_blockhash,
+                last_valid_block_height,
+            };
+            if submit {
+                to_value(serde_json::to_value(
+                    external_sign_submit(Json(req)).await?.0,
+                ))
+            } else {
+                to_value(serde_json::to_value(external_sign_sign(Json(req)).await?.0))
+            }
+        }
+        remote_sign_protocol::SignPayload::Message {
+            required_signer,
+            message_base64,
+        } => {
+            let req = ExternalSignMessageRequest {
+                wallet,
+                required_signer,
+                message_base64,
+                request_id: Some(request.request_id),
+                expires_at: request.expires_at,
+            };
+            to_value(serde_json::to_value(
+                external_sign_message(Json(req)).await?.0,
+            ))
+        }
     }
 }
 
 #[derive(Deserialize)]
-#[serde(deny_unknown_fields)]
-struct DeployProgramRequest {
-    #[serde(default)]
-    wallet_id: Option<String>,
-    #[serde(default)]
-    private_key: Option<String>,
-    #[serde(default)]
-    secret_key: Option<String>,
-    #[serde(default)]
-    keystore_json: Option<String>,
-    #[serde(default)]
-    encrypted_key: Option<String>,
-    #[serde(default)]
-    password: Option<String>,
-    #[serde(default)]
-    program_keypair_json: Option<String>,
-    #[serde(default)]
-    program_keypair_path: Option<String>,
-    expected_program_id: String,
-    expected_upgrade_authority: String,
-    expected_genesis_hash: String,
-    expected_program_sha256: String,
-    program_so_base64: String,
-    network: String,
-    #[serde(default)]
-    max_data_len: Option<usize>,
-    #[serde(default)]
-    resume_buffer_address: Option<String>,
+struct RemoteSignApproveRequest {
+    id: String,
+    #[serde(flatten)]
+    wallet: WalletAuthRequest,
 }
 
-impl DeployProgramRequest {
-    fn take_wallet_auth(&mut self) -> WalletAuthRequest {
-        WalletAuthRequest {
-            wallet_id: self.wallet_id.take(),
-            private_key: self.private_key.take(),
-            secret_key: self.secret_key.take(),
-            keystore_json: self.keystore_json.take(),
-            encrypted_key: self.encrypted_key.take(),
-            password: self.password.take(),
+async fn remote_sign_approve(
+    Json(mut req): Json<RemoteSignApproveRequest>,
+) -> Result<Json<RemoteSignRequestView>, ApiError> {
+    let record = wallet_store::transition_remote_sign_request(
+        &req.id,
+        wallet_store::REMOTE_SIGN_STATUS_PENDING,
+        wallet_store::REMOTE_SIGN_STATUS_SIGNING,
+        None,
+    )
+    .map_err(|message| ApiError { message })?;
+    let request = serde_json::from_str(&record.request_json).map_err(|e| ApiError {
+        message: format!("远程签名请求记录无效: {}", e),
+    });
+    let signed = match request {
+        Ok(request) => sign_remote_request(request, std::mem::take(&mut req.wallet)).await,
+        Err(error) => Err(error),
+    };
+    let result = match signed {
+        Ok(result) => result,
+        Err(error) => {
+            // Leave the request pending so the user can retry or reject it
+            wallet_store::transition_remote_sign_request(
+                &record.id,
+                wallet_store::REMOTE_SIGN_STATUS_SIGNING,
+                wallet_store::REMOTE_SIGN_STATUS_PENDING,
+                None,
+            )
+            .map_err(|message| ApiError { message })?;
+            return Err(error);
         }
-    }
+    };
+    let record = remote_sign::finish_request(
+        &record.id,
+        wallet_store::REMOTE_SIGN_STATUS_SIGNING,
+        wallet_store::REMOTE_SIGN_STATUS_APPROVED,
+        remote_sign_protocol::SignResponseStatus::Approved,
+        Some(result),
+        None,
+    )
+    .map_err(|message| ApiError { message })?;
+    tracing::info!(
+        "remote_sign approved id={} request={}",
+        record.id,
+        record.request_id
+    );
+    remote_sign_finished_view(record).await
 }
 
-impl Drop for DeployProgramRequest {
-    fn drop(&mut self) {
-        if let Some(value) = self.program_keypair_json.as_mut() {
-            value.zeroize();
-        }
-        self.take_wallet_auth().clear_secrets();
-    }
+#[derive(Deserialize)]
+struct RemoteSignRejectRequest {
+    id: String,
+    #[serde(default)]
+    reason: Option<String>,
 }
 
-#[derive(Serialize)]
-struct DeployProgramResponse {
-    program_id: String,
-    programdata_address: String,
-    buffer_address: String,
-    authority: String,
-    network: String,
-    genesis_hash: String,
-    program_bytes: usize,
-    max_data_len: usize,
-    program_sha256: String,
-    temporary_buffer_rent_lamports: u64,
-    program_rent_lamports: u64,
-    programdata_rent_lamports: u64,
-    rent_lamports: u64,
-    estimated_transaction_fees_lamports: u64,
-    fee_rate_reserve_lamports: u64,
-    recovery_write_reserve_lamports: u64,
-    total_fee_budget_lamports: u64,
-    estimated_required_balance_lamports: u64,
-    create_buffer_signature: Option<String>,
-    skipped_write_chunks: usize,
-    write_signatures: Vec<String>,
-    deploy_signature: String,
-    finalized_slot: u64,
-    deployed_slot: u64,
-    readback_verified: bool,
-    journal_revision: Option<u64>,
-    attempt_evidence_version: Option<u32>,
-    deployment_attempts: Vec<wallet_store::ProgramDeploymentAttemptRecord>,
-    status: String,
+async fn remote_sign_reject(
+    Json(req): Json<RemoteSignRejectRequest>,
+) -> Result<Json<RemoteSignRequestView>, ApiError> {
+    let reason = req
+        .reason
+        .as_deref()
+        .map(str::trim)
+        .filter(|reason| !reason.is_empty())
+        .unwrap_or("rejected by the wallet owner");
+    validate_text_len(reason, "reason", MAX_TEXT_FIELD_CHARS)?;
+    let record = remote_sign::finish_request(
+        &req.id,
+        wallet_store::REMOTE_SIGN_STATUS_PENDING,
+        wallet_store::REMOTE_SIGN_STATUS_REJECTED,
+        remote_sign_protocol::SignResponseStatus::Rejected,
+        None,
+        Some(reason.to_string()),
+    )
+    .map_err(|message| ApiError { message })?;
+    tracing::info!(
+        "remote_sign rejected id={} request={}",
+        record.id,
+        record.request_id
+    );
+    remote_sign_finished_view(record).await
 }
 
-async fn deploy_generic_program(
-    Json(req): Json<DeployProgramRequest>,
-) -> Result<Json<DeployProgramResponse>, ApiError> {
-    deploy_program(Json(req)).await
+fn decode_generic_instruction_data(value: &str) -> Result<Vec<u8>, ApiError> {
+    let trimmed = value.trim();
+    if trimmed.is_empty() {
+        return Err(ApiError {
+            message: "Instruction data 不能为空".to_string(),
+        });
+    }
+    let max_base64_len = MAX_GENERIC_PROGRAM_INSTRUCTION_DATA_BYTES.div_ceil(3) * 4;
+    if trimmed.len() > max_base64_len {
+        return Err(ApiError {
+            message: "Instruction data 过大".to_string(),
+        });
+    }
+    let data = BASE64.decode(trimmed.as_bytes()).map_err(|_| ApiError {
+        message: "Instruction data base64 无效".to_string(),
+    })?;
+    if data.is_empty() || data.len() > MAX_GENERIC_PROGRAM_INSTRUCTION_DATA_BYTES {
+        return Err(ApiError {
+            message: "Instruction data 大小无效".to_string(),
+        });
+    }
+    Ok(data)
 }
 
-async fn deploy_program(
-    Json(mut req): Json<DeployProgramRequest>,
-) -> Result<Json<DeployProgramResponse>, ApiError> {
-    let _deploy_guard = PROGRAM_DEPLOY_LOCK
-        .get_or_init(|| tokio::sync::Mutex::new(()))
-        .try_lock()
-        .map_err(|_| ApiError {
-            message: PROGRAM_DEPLOY_BUSY_MESSAGE.to_string(),
-        })?;
-    let expected_upgrade_authority = Pubkey::from_str(req.expected_upgrade_authority.trim())
-        .map_err(|_| ApiError {
-            message: "无效的预期 Upgrade Authority".to_string(),
-        })?;
-
-    let program_bytes = decode_program_binary_base64(&req.program_so_base64)?;
+fn build_generic_program_instruction(
+    program_id: &str,
+    accounts: &[GenericProgramAccountMetaRequest],
+    data_base64: &str,
+) -> Result<Instruction, ApiError> {
+    let program_id = Pubkey::from_str(program_id.trim()).map_err(|_| ApiError {
+        message: "Program ID 无效".to_string(),
+    })?;
+    if accounts.len() > MAX_GENERIC_PROGRAM_INSTRUCTION_ACCOUNTS {
+        return Err(ApiError {
+            message: "Instruction 账户数量过多".to_string(),
+        });
+    }
+    let metas = accounts
+        .iter()
+        .map(|account| {
+            let pubkey = Pubkey::from_str(account.pubkey.trim()).map_err(|_| ApiError {
+                message: format!("账户地址无效: {}", account.pubkey),
+            })?;
+            Ok(if account.is_writable {
+                AccountMeta::new(pubkey, account.is_signer)
+            } else {
+                AccountMeta::new_readonly(pubkey, account.is_signer)
+            })
+        })
+        .collect::<Result<Vec<_>, ApiError>>()?;
+    Ok(Instruction {
+        program_id,
+        accounts: metas,
+        data: decode_generic_instruction_data(data_base64)?,
+    })
+}
 
-    let program_keypair_json = match (
-        req.program_keypair_json.take(),
-        req.program_keypair_path
-            .as_deref()
-            .map(str::trim)
-            .filter(|value| !value.is_empty()),
-    ) {
-        (Some(json), None) => json,
-        (None, Some(path)) => {
-            let path = PathBuf::from(path)
-                .canonicalize()
-                .map_err(|error| ApiError {
-                    message: format!("Program keypair 路径不可访问: {error}"),
-                })?;
-            read_text_file_limited(
-                &path,
-                program_deploy::MAX_PROGRAM_KEYPAIR_JSON_BYTES,
-                "Program keypair",
-            )?
-        }
-        (Some(mut json), Some(_)) => {
-            json.zeroize();
-            return Err(ApiError {
-                message: "Program keypair JSON 与路径只能提供一个".to_string(),
-            });
-        }
-        (None, None) => {
-            return Err(ApiError {
-                message: "必须提供 Program keypair JSON 或 Program keypair 路径".to_string(),
-            });
-        }
-    };
-    let mut program_keypair_json = Zeroizing::new(program_keypair_json);
-    let program_keypair_result = program_deploy::parse_program_keypair_json(&program_keypair_json);
-    program_keypair_json.zeroize();
-    let program_keypair = program_keypair_result.map_err(|message| ApiError { message })?;
-    let program_id =
-        program_deploy::require_program_id(&program_keypair, req.expected_program_id.trim())
-            .map_err(|message| ApiError { message })?;
-
-    let program_sha256 =
-        program_deploy::require_sha256(&program_bytes, &req.expected_program_sha256)
-            .map_err(|message| ApiError { message })?;
-    program_deploy::require_anchor_declared_program_id(&program_bytes, &program_id)
-        .map_err(|message| ApiError { message })?;
-    let program_bytes = verify_program_binary_offline(program_bytes).await?;
-
-    let requested_resume_buffer_address = req
-        .resume_buffer_address
-        .as_deref()
-        .map(str::trim)
-        .filter(|value| !value.is_empty())
-        .map(|value| {
-            Pubkey::from_str(value).map_err(|_| ApiError {
-                message: "无效的恢复 Buffer 地址".to_string(),
-            })
-        })
-        .transpose()?;
-    let max_data_len = req.max_data_len.unwrap_or(program_bytes.len());
-    if max_data_len < program_bytes.len() {
+async fn program_invoke(
+    Json(mut req): Json<ProgramInvokeRequest>,
+) -> Result<Json<ProgramInvokeResponse>, ApiError> {
+    let signer = req.wallet.keypair()?;
+    let signer_pubkey = signer.pubkey();
+    let instruction =
+        build_generic_program_instruction(&req.program_id, &req.accounts, &req.data_base64)?;
+    let mode = req.mode.as_deref().unwrap_or("simulate");
+    if mode != "simulate" && mode != "send" {
         return Err(ApiError {
-            message: "最大 Program 数据长度不能小于 .so 文件大小".to_string(),
+            message: "调用模式必须是 simulate 或 send".to_string(),
         });
     }
-    if max_data_len > MAX_PROGRAM_SO_BYTES {
+    if req.additional_signers.len() > MAX_GENERIC_PROGRAM_ADDITIONAL_SIGNERS {
         return Err(ApiError {
-            message: format!(
-                "最大 Program 数据长度不能超过 {} MB",
-                MAX_PROGRAM_SO_BYTES / 1024 / 1024
-            ),
+            message: "额外 signer 数量过多".to_string(),
         });
     }
+    let mut signers = vec![signer];
+    let mut signer_pubkeys = vec![signer_pubkey];
+    for additional in &req.additional_signers {
+        let requested_pubkey =
+            Pubkey::from_str(additional.pubkey.trim()).map_err(|_| ApiError {
+                message: format!("Signer 地址无效: {}", additional.pubkey),
+            })?;
+        if signer_pubkeys.contains(&requested_pubkey) {
+            continue;
+        }
+        let keypair = keypair_from_saved_wallet(
+            &additional.wallet_id,
+            &additional.password,
+            &requested_pubkey.to_string(),
+        )?;
+        signer_pubkeys.push(keypair.pubkey());
+        signers.push(keypair);
+    }
+    req.additional_signers.clear();
 
-    if req.network.trim().is_empty() {
-        return Err(ApiError {
-            message: "部署网络不能为空".to_string(),
-        });
+    for account in &instruction.accounts {
+        if account.is_signer && !signer_pubkeys.contains(&account.pubkey) {
+            return Err(ApiError {
+                message: format!(
+                    "账户 {} 需要签名，请为这个 signer 选择已保存钱包并输入密码",
+                    account.pubkey
+                ),
+            });
+        }
     }
-    let selector = rpc_selector(Some(req.network.trim()))?;
-    let rpc_url = selector.url;
-    let network = selector.network;
-    let client = RpcClient::new_with_timeout_and_commitment(
-        rpc_url.to_string(),
-        Duration::from_secs(PROGRAM_DEPLOY_RPC_TIMEOUT_SECS),
-        CommitmentConfig::confirmed(),
+    let (client, _) = rpc_client_for(req.network.as_deref())?;
+    let blockhash = client.get_latest_blockhash().map_err(|error| ApiError {
+        message: format!("获取 blockhash 失败: {error}"),
+    })?;
+    let signer_refs = signers
+        .iter()
+        .map(|keypair| keypair as &dyn Signer)
+        .collect::<Vec<_>>();
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction],
+        Some(&signer_pubkey),
+        &signer_refs,
+        blockhash,
     );
+    if mode == "simulate" {
+        let simulation = client
+            .simulate_transaction_with_config(
+                &transaction,
+                RpcSimulateTransactionConfig {
+                    sig_verify: true,
+                    replace_recent_blockhash: false,
+                    commitment: Some(CommitmentConfig::confirmed()),
+                    encoding: Some(UiTransactionEncoding::Base64),
+                    accounts: None,
+                    min_context_slot: None,
+                    inner_instructions: false,
+                },
+            )
+            .map_err(|error| ApiError {
+                message: format!("模拟调用 RPC 失败: {error}"),
+            })?;
+        return Ok(Json(ProgramInvokeResponse {
+            status: if simulation.value.err.is_some() {
+                "simulation_failed".to_string()
+            } else {
+                "simulated".to_string()
+            },
+            signature: None,
+            simulation_error: simulation.value.err.map(|error| format!("{error}")),
+            logs: simulation.value.logs.unwrap_or_default(),
+        }));
+    }
 
-    let expected_genesis_hash = solana_sdk::hash::Hash::from_str(req.expected_genesis_hash.trim())
-        .map_err(|_| ApiError {
-            message: "无效的预期 genesis hash".to_string(),
-        })?;
-    let actual_genesis_hash = client.get_genesis_hash().map_err(|error| ApiError {
-        message: format!("读取 RPC genesis hash 失败: {error}"),
-    })?;
-    if actual_genesis_hash != expected_genesis_hash {
-        return Err(ApiError {
+    let signature = client
+        .send_and_confirm_transaction(&transaction)
+        .map_err(|error| ApiError {
             message: format!(
-                "RPC genesis hash 为 {}，预期为 {}；已在花费前中止部署",
-                actual_genesis_hash, expected_genesis_hash
+                "{} 调用失败: {error}",
+                req.instruction_name
+                    .trim()
+                    .chars()
+                    .take(80)
+                    .collect::<String>()
             ),
-        });
-    }
+        })?;
+    Ok(Json(ProgramInvokeResponse {
+        status: "success".to_string(),
+        signature: Some(signature.to_string()),
+        simulation_error: None,
+        logs: Vec::new(),
+    }))
+}
 
-    let genesis_hash = actual_genesis_hash.to_string();
-    let mut deployment_journal =
-        wallet_store::find_program_deployment(&genesis_hash, &program_id.to_string())
-            .map_err(|message| ApiError { message })?;
-    let mut deployment_journal_binding_matches = true;
-    let mut finalized_journal_binding_conflicts = false;
-    let journal_buffer_address = if let Some(record) = deployment_journal.as_ref() {
-        if !deployment_status_is_known(&record.status) {
-            return Err(deployment_journal_error(format!(
-                "Program {} 的记录状态 {} 无法识别，拒绝继续",
-                program_id, record.status
-            )));
-        }
-        match validate_deployment_journal_binding(
-            record,
-            &genesis_hash,
-            &program_id,
-            &program_sha256,
-            program_bytes.len(),
-            max_data_len,
-            &expected_upgrade_authority,
-            requested_resume_buffer_address.as_ref(),
-        ) {
-            Ok(buffer) => Some(buffer),
-            Err(_)
-                if requested_resume_buffer_address.is_none()
-                    && record.status != DEPLOYMENT_STATUS_FINALIZED =>
-            {
-                deployment_journal_binding_matches = false;
-                None
-            }
-            Err(_)
-                if requested_resume_buffer_address.is_none()
-                    && record.status == DEPLOYMENT_STATUS_FINALIZED =>
-            {
-                deployment_journal_binding_matches = false;
-                finalized_journal_binding_conflicts = true;
-                None
-            }
-            Err(error) => return Err(error),
-        }
-    } else {
-        None
-    };
-    let deployment_attempts = if let Some(record) = deployment_journal.as_ref() {
-        if deployment_journal_binding_matches {
-            let attempts = wallet_store::load_program_deployment_attempts(
-                &record.genesis_hash,
-                &record.program_id,
-            )
-            .map_err(|message| ApiError { message })?;
-            for attempt in &attempts {
-                validate_program_deployment_attempt_record(record, attempt)?;
-            }
-            attempts
-        } else {
-            Vec::new()
-        }
-    } else {
-        Vec::new()
+async fn program_deploy_source_build_status(
+    Path(job_id): Path<String>,
+) -> Result<Json<ProgramDeploySourceResponse>, ApiError> {
+    let job = {
+        let jobs = program_source_build_jobs().lock().await;
+        jobs.get(&job_id)
+            .cloned()
+            .ok_or_else(|| program_source_error("构建任务不存在或已过期"))?
     };
-    if !deployment_journal_binding_matches {
-        deployment_journal = None;
+    let (build_plans, build_blocked_reason) = program_source_build_plans(&job.source_dir);
+    let build_available = !build_plans.is_empty();
+    let artifact_stem = detect_program_artifact_stem(&job.source_dir);
+    let mut warnings = job.warnings.clone();
+    if let Some(error) = job.build_error.as_ref() {
+        warnings.push(error.clone());
     }
-
-    let programdata_address = get_program_data_address(&program_id);
-    let deployment_state_addresses = vec![program_id, programdata_address];
-    let existing_accounts = client
-        .get_multiple_accounts_with_commitment(
-            &deployment_state_addresses,
-            CommitmentConfig::finalized(),
-        )
-        .map_err(|error| ApiError {
-            message: format!("部署前查询 Program 与 ProgramData 失败: {error}"),
-        })?;
-    let existing_finalized_slot = existing_accounts.context.slot;
-    if existing_accounts.value.len() != deployment_state_addresses.len() {
+    let mut artifacts = load_program_source_artifacts(
+        &job.source_dir,
+        artifact_stem.as_deref(),
+        None,
+        job.status != "running",
+    )?;
+    warnings.append(&mut artifacts.warnings);
+
+    Ok(Json(ProgramDeploySourceResponse {
+        source_dir: path_string(&job.source_dir),
+        built: job.status == "completed",
+        build_status: Some(job.status),
+        build_job_id: Some(job_id),
+        build_error: job.build_error,
+        build_command: job.build_command,
+        build_template: job.build_template,
+        build_stdout: job.build_stdout,
+        build_stderr: job.build_stderr,
+        program_so_path: artifacts
+            .program_so_path
+            .as_ref()
+            .map(|path| path_string(path)),
+        program_so_name: artifacts.program_so_name,
+        program_so_base64: artifacts.program_so_base64,
+        program_so_sha256: artifacts.program_so_sha256,
+        program_so_size: artifacts.program_so_size,
+        program_keypair_path: artifacts
+            .program_keypair_path
+            .as_ref()
+            .map(|path| path_string(path)),
+        expected_program_id: artifacts.expected_program_id,
+        manifest_program_id: artifacts.manifest_program_id,
+        manifest_network: artifacts.manifest_network,
+        manifest_genesis_hash: artifacts.manifest_genesis_hash,
+        manifest_upgrade_authority: artifacts.manifest_upgrade_authority,
+        manifest_owner_admin: artifacts.manifest_owner_admin,
+        manifest_operational_admin: artifacts.manifest_operational_admin,
+        build_available,
+        build_blocked_reason,
+        source_validation_errors: artifacts.source_validation_errors,
+        warnings,
+    }))
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ProgramDeploymentJournalRequest {
+    network: String,
+    expected_genesis_hash: String,
+    expected_program_id: String,
+    expected_program_sha256: String,
+    program_len: usize,
+    max_data_len: usize,
+    expected_upgrade_authority: String,
+}
+
+#[derive(Deserialize)]
+struct ProgramDeploymentJournalByProgramRequest {
+    network: String,
+    expected_genesis_hash: String,
+    program_id: String,
+}
+
+#[derive(Serialize)]
+struct ProgramDeploymentJournalResponse {
+    network: String,
+    genesis_hash: String,
+    write_chunk_bytes: usize,
+    write_chunk_count: usize,
+    journal: Option<ProgramDeploymentJournalView>,
+    deployment_attempts: Vec<wallet_store::ProgramDeploymentAttemptRecord>,
+    conflicting_journal: Option<ProgramDeploymentJournalView>,
+    conflicting_deployment_attempts: Vec<wallet_store::ProgramDeploymentAttemptRecord>,
+}
+
+#[derive(Serialize)]
+struct ProgramDeploymentJournalView {
+    genesis_hash: String,
+    program_id: String,
+    program_sha256: String,
+    program_len: usize,
+    max_data_len: usize,
+    upgrade_authority: String,
+    buffer_address: String,
+    status: String,
+    create_signature: Option<String>,
+    create_last_valid_block_height: Option<u64>,
+    last_write_signature: Option<String>,
+    last_write_chunk_index: Option<usize>,
+    last_write_last_valid_block_height: Option<u64>,
+    completed_writes: usize,
+    deploy_signature: Option<String>,
+    deploy_last_valid_block_height: Option<u64>,
+    attempt_evidence_version: u32,
+    revision: u64,
+    created_at: u64,
+    updated_at: u64,
+}
+
+impl From<&wallet_store::ProgramDeploymentRecord> for ProgramDeploymentJournalView {
+    fn from(record: &wallet_store::ProgramDeploymentRecord) -> Self {
+        Self {
+            genesis_hash: record.genesis_hash.clone(),
+            program_id: record.program_id.clone(),
+            program_sha256: record.program_sha256.clone(),
+            program_len: record.program_len,
+            max_data_len: record.max_data_len,
+            upgrade_authority: record.upgrade_authority.clone(),
+            buffer_address: record.buffer_address.clone(),
+            status: record.status.clone(),
+            create_signature: record.create_signature.clone(),
+            create_last_valid_block_height: record.create_last_valid_block_height,
+            last_write_signature: record.last_write_signature.clone(),
+            last_write_chunk_index: record.last_write_chunk_index,
+            last_write_last_valid_block_height: record.last_write_last_valid_block_height,
+            completed_writes: record.completed_writes,
+            deploy_signature: record.deploy_signature.clone(),
+            deploy_last_valid_block_height: record.deploy_last_valid_block_height,
+            attempt_evidence_version: record.attempt_evidence_version,
+            revision: record.revision,
+            created_at: record.created_at,
+            updated_at: record.updated_at,
+        }
+    }
+}
+
+struct ProgramDeploymentJournalIntent {
+    expected_genesis_hash: solana_sdk::hash::Hash,
+    program_id: Pubkey,
+    program_sha256: String,
+    program_len: usize,
+    max_data_len: usize,
+    upgrade_authority: Pubkey,
+}
+
+fn parse_canonical_deployment_pubkey(value: &str, label: &str) -> Result<Pubkey, ApiError> {
+    let trimmed = value.trim();
+    let pubkey = Pubkey::from_str(trimmed).map_err(|_| ApiError {
+        message: format!("无效的{label}"),
+    })?;
+    if pubkey == Pubkey::default() || pubkey.to_string() != trimmed {
         return Err(ApiError {
-            message: "部署前 finalized 账户查询结果不完整，拒绝部署".to_string(),
+            message: format!("{label}必须是 canonical 非零公钥"),
         });
     }
-    let mut existing_values = existing_accounts.value.into_iter();
-    let existing_program = existing_values.next().flatten();
-    let existing_programdata = existing_values.next().flatten();
-
-    let existing_verified_readback = match (existing_program, existing_programdata) {
-        (None, None) => None,
-        (Some(program), Some(programdata)) => Some(
-            program_deploy::verify_deployment_readback(
-                &program_id,
-                &expected_upgrade_authority,
-                &program_bytes,
-                max_data_len,
-                &program.owner,
-                program.executable,
-                &program.data,
-                &programdata.owner,
-                &programdata.data,
-            )
-            .map_err(|error| ApiError {
-                message: format!(
-                    "目标 Program 已存在，但链上 finalized 代码与本次 .so 不一致；同一个 Program ID 不能再次走“新部署”，请使用升级流程发布新 .so，或生成新的 Program keypair 后全新部署。链上校验详情: {error}"
-                ),
-            })?,
-        ),
-        _ => {
-            return Err(ApiError {
-                message: format!(
-                    "目标 Program 与 ProgramData 只有一个存在（Program ID: {}），拒绝继续部署",
-                    program_id
-                ),
-            })
-        }
-    };
+    Ok(pubkey)
+}
 
-    if finalized_journal_binding_conflicts && existing_verified_readback.is_none() {
+fn validate_program_deployment_journal_request(
+    req: &ProgramDeploymentJournalRequest,
+) -> Result<ProgramDeploymentJournalIntent, ApiError> {
+    if req.program_len == 0
+        || req.program_len > MAX_PROGRAM_SO_BYTES
+        || req.max_data_len < req.program_len
+        || req.max_data_len > MAX_PROGRAM_SO_BYTES
+    {
         return Err(ApiError {
             message: format!(
-                "Program {} 已有 finalized 部署记录，但当前 artifact 与记录不同，且链上 finalized Program 不可见；为避免覆盖历史或误复用 Program ID，请生成新的 Program keypair 后新部署，或确认链上 Program 存在后使用升级流程",
-                program_id
+                "Program 长度必须满足 1 <= program_len <= max_data_len <= {}",
+                MAX_PROGRAM_SO_BYTES
             ),
         });
     }
-
-    let buffer_lamports = client
-        .get_minimum_balance_for_rent_exemption(UpgradeableLoaderState::size_of_buffer(
-            program_bytes.len(),
-        ))
-        .map_err(|e| ApiError {
-            message: format!("计算 buffer 租金失败: {}", e),
-        })?;
-    let program_lamports = client
-        .get_minimum_balance_for_rent_exemption(UpgradeableLoaderState::size_of_program())
-        .map_err(|e| ApiError {
-            message: format!("计算 program 租金失败: {}", e),
-        })?;
-    let programdata_lamports = client
-        .get_minimum_balance_for_rent_exemption(UpgradeableLoaderState::size_of_programdata(
-            max_data_len,
-        ))
-        .map_err(|e| ApiError {
-            message: format!("计算 ProgramData 租金失败: {}", e),
-        })?;
-    let final_rent_lamports = program_lamports
-        .checked_add(programdata_lamports)
-        .ok_or_else(|| ApiError {
-            message: "Program 与 ProgramData 最终租金总额溢出".to_string(),
+    let program_sha256 = req.expected_program_sha256.trim().to_ascii_lowercase();
+    if program_sha256.len() != 64 || !program_sha256.as_bytes().iter().all(u8::is_ascii_hexdigit) {
+        return Err(ApiError {
+            message: "预期 Program SHA-256 必须是 64 位十六进制".to_string(),
+        });
+    }
+    let expected_genesis_text = req.expected_genesis_hash.trim();
+    let expected_genesis_hash =
+        solana_sdk::hash::Hash::from_str(expected_genesis_text).map_err(|_| ApiError {
+            message: "无效的预期 genesis hash".to_string(),
         })?;
-
-    let wallet_auth = req.take_wallet_auth();
-    let payer = run_keystore_task(move || wallet_auth.keypair()).await?;
-    let payer_pubkey = payer.pubkey();
-    if expected_upgrade_authority != payer_pubkey {
+    if expected_genesis_hash == solana_sdk::hash::Hash::default()
+        || expected_genesis_hash.to_string() != expected_genesis_text
+    {
         return Err(ApiError {
-            message: format!(
-                "预期 Upgrade Authority 为 {}，但付款钱包为 {}；首次部署必须由该 authority 付款并持有升级权限",
-                expected_upgrade_authority, payer_pubkey
-            ),
+            message: "预期 genesis hash 必须是 canonical 非零值".to_string(),
         });
     }
-    if let Some(readback) = existing_verified_readback {
-        if readback.program_sha256 != program_sha256 {
-            return Err(ApiError {
-                message: "finalized Program SHA-256 与本地已验证哈希不一致".to_string(),
-            });
-        }
-        for attempt in deployment_attempts.iter().filter(|attempt| {
-            attempt.stage == wallet_store::PROGRAM_DEPLOYMENT_STAGE_DEPLOY
-                && deployment_attempt_is_active(&attempt.status)
-        }) {
-            let completed_writes = deployment_journal
-                .as_ref()
-                .map(|record| record.completed_writes)
-                .unwrap_or_default();
-            transition_deployment_attempt(
-                &mut deployment_journal,
-                &attempt.signature,
-                &attempt.status,
-                wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_FINALIZED,
-                DEPLOYMENT_STATUS_FINALIZED,
-                completed_writes,
-            )?;
-        }
-        if deployment_journal
-            .as_ref()
-            .is_some_and(|record| record.status != DEPLOYMENT_STATUS_FINALIZED)
-        {
-            let completed_writes = deployment_journal
-                .as_ref()
-                .map(|record| record.completed_writes)
-                .unwrap_or_default();
-            transition_deployment_journal(
-                &mut deployment_journal,
-                DEPLOYMENT_STATUS_FINALIZED,
-                None,
-                None,
-                None,
-                None,
-                None,
-                completed_writes,
-                None,
-                None,
-            )?;
-        }
-        let (journal_revision, attempt_evidence_version, deployment_attempts) =
-            load_deployment_receipt_evidence(deployment_journal.as_ref())?;
-        let buffer_address = deployment_journal
-            .as_ref()
-            .map(|record| record.buffer_address.clone())
-            .unwrap_or_default();
-        let create_buffer_signature = deployment_journal
-            .as_ref()
-            .and_then(|record| record.create_signature.clone());
-        let deploy_signature = deployment_journal
-            .as_ref()
-            .and_then(|record| record.deploy_signature.clone())
-            .unwrap_or_default();
-        let write_signatures = deployment_attempts
-            .iter()
-            .filter(|attempt| attempt.stage == wallet_store::PROGRAM_DEPLOYMENT_STAGE_WRITE)
-            .map(|attempt| attempt.signature.clone())
-            .collect();
-        return Ok(Json(DeployProgramResponse {
-            program_id: program_id.to_string(),
-            programdata_address: programdata_address.to_string(),
-            buffer_address,
-            authority: readback.upgrade_authority.to_string(),
-            network,
-            genesis_hash,
-            program_bytes: program_bytes.len(),
-            max_data_len,
-            program_sha256,
-            temporary_buffer_rent_lamports: buffer_lamports,
-            program_rent_lamports: program_lamports,
-            programdata_rent_lamports: programdata_lamports,
-            rent_lamports: final_rent_lamports,
-            estimated_transaction_fees_lamports: 0,
-            fee_rate_reserve_lamports: 0,
-            recovery_write_reserve_lamports: 0,
-            total_fee_budget_lamports: 0,
-            estimated_required_balance_lamports: 0,
-            create_buffer_signature,
-            skipped_write_chunks: program_bytes.chunks(PROGRAM_WRITE_CHUNK_BYTES).count(),
-            write_signatures,
-            deploy_signature,
-            finalized_slot: existing_finalized_slot,
-            deployed_slot: readback.deployed_slot,
-            readback_verified: true,
-            journal_revision,
-            attempt_evidence_version,
-            deployment_attempts,
-            status: "already_deployed_verified".to_string(),
-        }));
-    }
+    Ok(ProgramDeploymentJournalIntent {
+        expected_genesis_hash,
+        program_id: parse_canonical_deployment_pubkey(&req.expected_program_id, "预期 Program ID")?,
+        program_sha256,
+        program_len: req.program_len,
+        max_data_len: req.max_data_len,
+        upgrade_authority: parse_canonical_deployment_pubkey(
+            &req.expected_upgrade_authority,
+            "预期 Upgrade Authority",
+        )?,
+    })
+}
 
-    let deploy_attempts = deployment_attempts
-        .iter()
-        .filter(|attempt| attempt.stage == wallet_store::PROGRAM_DEPLOYMENT_STAGE_DEPLOY)
-        .collect::<Vec<_>>();
-    for attempt in &deploy_attempts {
-        if deployment_attempt_is_blocking_terminal(&attempt.status) {
+async fn program_deployment_journal(
+    Json(req): Json<ProgramDeploymentJournalRequest>,
+) -> Result<Json<ProgramDeploymentJournalResponse>, ApiError> {
+    if req.network.trim().is_empty() {
+        return Err(ApiError {
+            message: "部署网络不能为空".to_string(),
+        });
+    }
+    let intent = validate_program_deployment_journal_request(&req)?;
+    let selector = rpc_selector(Some(req.network.trim()))?;
+    let network = selector.network;
+    let genesis_hash = intent.expected_genesis_hash.to_string();
+    let program_id = intent.program_id.to_string();
+    let (journal, deployment_attempts) =
+        wallet_store::load_program_deployment_snapshot(&genesis_hash, &program_id)
+            .map_err(|message| ApiError { message })?;
+    let mut matching_journal = None;
+    let mut matching_attempts = Vec::new();
+    let mut conflicting_journal = None;
+    let mut conflicting_attempts = Vec::new();
+    if let Some(record) = journal.as_ref() {
+        if !deployment_status_is_known(&record.status)
+            || record.attempt_evidence_version
+                != wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_EVIDENCE_VERSION
+        {
             return Err(deployment_journal_error(format!(
-                "deploy attempt {} 已处于终态 {}，但 finalized Program 不存在；禁止自动重签",
-                attempt.signature, attempt.status
+                "Program {} 的部署记录状态或证据版本无效",
+                intent.program_id
             )));
         }
-        if deployment_attempt_is_active(&attempt.status) {
-            let completed_writes = deployment_journal
-                .as_ref()
-                .map(|record| record.completed_writes)
-                .unwrap_or_default();
-            reconcile_absent_deployment_attempt(
-                &client,
-                &mut deployment_journal,
-                attempt,
-                DEPLOYMENT_STATUS_BUFFER_FINALIZED,
-                completed_writes,
-                |client, min_context_slot| {
-                    confirm_deploy_attempt_still_absent(
-                        client,
-                        &program_id,
-                        &programdata_address,
-                        min_context_slot,
-                    )
-                },
-            )?;
+        for attempt in &deployment_attempts {
+            validate_program_deployment_attempt_record(record, attempt)?;
         }
-    }
-    if deployment_journal.as_ref().is_some_and(|record| {
-        matches!(
-            record.status.as_str(),
-            DEPLOYMENT_STATUS_DEPLOY_SIGNED
-                | DEPLOYMENT_STATUS_DEPLOY_RECONCILE
-                | DEPLOYMENT_STATUS_DEPLOY_FINALIZED
-                | DEPLOYMENT_STATUS_FINALIZED
-        ) && deploy_attempts.is_empty()
-    }) {
+        let binding_matches = validate_deployment_journal_binding(
+            record,
+            &genesis_hash,
+            &intent.program_id,
+            &intent.program_sha256,
+            intent.program_len,
+            intent.max_data_len,
+            &intent.upgrade_authority,
+            None,
+        )
+        .is_ok();
+        if binding_matches {
+            matching_journal = Some(ProgramDeploymentJournalView::from(record));
+            matching_attempts = deployment_attempts;
+        } else {
+            conflicting_journal = Some(ProgramDeploymentJournalView::from(record));
+            conflicting_attempts = deployment_attempts;
+        }
+    } else if !deployment_attempts.is_empty() {
         return Err(deployment_journal_error(
-            "journal 处于 deploy 状态但缺少完整 signature/last-valid-block-height attempt 证据",
+            "部署 attempt 缺少对应 journal，拒绝返回撕裂快照",
         ));
     }
+    Ok(Json(ProgramDeploymentJournalResponse {
+        network,
+        genesis_hash,
+        write_chunk_bytes: PROGRAM_WRITE_CHUNK_BYTES,
+        write_chunk_count: intent.program_len.div_ceil(PROGRAM_WRITE_CHUNK_BYTES),
+        journal: matching_journal,
+        deployment_attempts: matching_attempts,
+        conflicting_journal,
+        conflicting_deployment_attempts: conflicting_attempts,
+    }))
+}
 
-    let mut buffer_keypair = (journal_buffer_address.is_none()
-        && requested_resume_buffer_address.is_none())
-    .then(Keypair::new);
-    let mut buffer_address = journal_buffer_address
-        .or(requested_resume_buffer_address)
-        .or_else(|| buffer_keypair.as_ref().map(|keypair| keypair.pubkey()))
-        .ok_or_else(|| ApiError {
-            message: "无法确定部署 Buffer 地址".to_string(),
+async fn program_deployment_journal_by_program(
+    Json(req): Json<ProgramDeploymentJournalByProgramRequest>,
+) -> Result<Json<ProgramDeploymentJournalResponse>, ApiError> {
+    if req.network.trim().is_empty() {
+        return Err(ApiError {
+            message: "部署网络不能为空".to_string(),
+        });
+    }
+    let selector = rpc_selector(Some(req.network.trim()))?;
+    let network = selector.network;
+    let expected_genesis_text = req.expected_genesis_hash.trim();
+    let expected_genesis_hash =
+        solana_sdk::hash::Hash::from_str(expected_genesis_text).map_err(|_| ApiError {
+            message: "无效的预期 genesis hash".to_string(),
         })?;
-    let mut rotating_create_buffer = false;
-
-    let (mut write_plan, resume_buffer_lamports) = if buffer_keypair.is_none() {
-        let response = client
-            .get_account_with_commitment(&buffer_address, CommitmentConfig::finalized())
-            .map_err(|error| ApiError {
-                message: format!("读取恢复 Buffer {} 失败: {error}", buffer_address),
-            })?;
-        if let Some(account) = response.value {
-            if account.lamports < buffer_lamports {
-                return Err(ApiError {
-                    message: format!(
-                        "恢复 Buffer {} 余额为 {} lamports，低于当前所需免租金额 {}",
-                        buffer_address, account.lamports, buffer_lamports
-                    ),
-                });
-            }
-            let plan = program_deploy::verify_resume_buffer(
-                &payer_pubkey,
-                &program_bytes,
-                PROGRAM_WRITE_CHUNK_BYTES,
-                &account.owner,
-                account.executable,
-                &account.data,
-            )
-            .map_err(|error| ApiError {
-                message: format!("恢复 Buffer {} 校验失败: {error}", buffer_address),
-            })?;
-            for attempt in deployment_attempts.iter().filter(|attempt| {
-                attempt.stage == wallet_store::PROGRAM_DEPLOYMENT_STAGE_CREATE_BUFFER
-                    && attempt.buffer_address == buffer_address.to_string()
-                    && deployment_attempt_is_active(&attempt.status)
-            }) {
-                transition_deployment_attempt(
-                    &mut deployment_journal,
-                    &attempt.signature,
-                    &attempt.status,
-                    wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_FINALIZED,
-                    DEPLOYMENT_STATUS_BUFFER_READY,
-                    plan.completed_chunks,
-                )?;
-            }
-            (plan, account.lamports)
-        } else {
-            let record = deployment_journal.as_ref().ok_or_else(|| ApiError {
-                message: format!(
-                    "恢复 Buffer {} 在 finalized 状态下不存在，且没有可审计的 create attempt；拒绝自动创建新 Buffer",
-                    buffer_address
-                ),
-            })?;
-            if record.completed_writes != 0
-                || deployment_attempts.iter().any(|attempt| {
-                    matches!(
-                        attempt.stage.as_str(),
-                        wallet_store::PROGRAM_DEPLOYMENT_STAGE_WRITE
-                            | wallet_store::PROGRAM_DEPLOYMENT_STAGE_DEPLOY
-                    )
-                })
-            {
-                return Err(deployment_journal_error(format!(
-                    "Buffer {} 不存在，但 journal 已包含 write/deploy 证据；拒绝轮换地址",
-                    buffer_address
-                )));
-            }
-            let create_attempts = deployment_attempts
-                .iter()
-                .filter(|attempt| {
-                    attempt.stage == wallet_store::PROGRAM_DEPLOYMENT_STAGE_CREATE_BUFFER
-                        && attempt.buffer_address == buffer_address.to_string()
-                })
-                .collect::<Vec<_>>();
-            if let Some(attempt) = create_attempts
-                .iter()
-                .find(|attempt| deployment_attempt_is_blocking_terminal(&attempt.status))
-            {
-                return Err(deployment_journal_error(format!(
-                    "create-buffer attempt {} 已处于终态 {}，但 finalized Buffer 不存在；禁止自动轮换",
-                    attempt.signature, attempt.status
-                )));
-            }
-            let active_create_attempts = create_attempts
-                .iter()
-                .filter(|attempt| deployment_attempt_is_active(&attempt.status))
-                .copied()
-                .collect::<Vec<_>>();
-            if active_create_attempts.len() > 1 {
-                return Err(deployment_journal_error(
-                    "同一 Buffer 存在多个活动 create attempt，拒绝自动恢复",
-                ));
-            }
-            if let Some(attempt) = active_create_attempts.first() {
-                reconcile_absent_deployment_attempt(
-                    &client,
-                    &mut deployment_journal,
-                    attempt,
-                    DEPLOYMENT_STATUS_CREATE_BUFFER_RECONCILE,
-                    0,
-                    |client, min_context_slot| {
-                        confirm_create_buffer_attempt_still_absent(
-                            client,
-                            &buffer_address,
-                            min_context_slot,
-                        )
-                    },
-                )?;
-            } else if !create_attempts.iter().any(|attempt| {
-                attempt.status == wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_EXPIRED_ABSENT
-            }) {
-                return Err(deployment_journal_error(format!(
-                    "Buffer {} 不存在且缺少完整 create signature/last-valid-block-height 证据",
-                    buffer_address
-                )));
-            }
-            let replacement = Keypair::new();
-            buffer_address = replacement.pubkey();
-            buffer_keypair = Some(replacement);
-            rotating_create_buffer = true;
-            (
-                program_deploy::BufferWritePlan {
-                    completed_chunks: 0,
-                    pending_chunk_indexes: (0..program_bytes
-                        .chunks(PROGRAM_WRITE_CHUNK_BYTES)
-                        .count())
-                        .collect(),
-                },
-                0,
-            )
-        }
-    } else {
-        (
-            program_deploy::BufferWritePlan {
-                completed_chunks: 0,
-                pending_chunk_indexes: (0..program_bytes.chunks(PROGRAM_WRITE_CHUNK_BYTES).count())
-                    .collect(),
-            },
-            0,
-        )
+    if expected_genesis_hash == solana_sdk::hash::Hash::default()
+        || expected_genesis_hash.to_string() != expected_genesis_text
+    {
+        return Err(ApiError {
+            message: "预期 genesis hash 必须是 canonical 非零值".to_string(),
+        });
+    }
+    let program_id = parse_canonical_deployment_pubkey(&req.program_id, "Program ID")?;
+    let genesis_hash = expected_genesis_hash.to_string();
+    let program_id = program_id.to_string();
+    let (journal, deployment_attempts) =
+        wallet_store::load_program_deployment_snapshot(&genesis_hash, &program_id)
+            .map_err(|message| ApiError { message })?;
+    let Some(record) = journal.as_ref() else {
+        return Ok(Json(ProgramDeploymentJournalResponse {
+            network,
+            genesis_hash,
+            write_chunk_bytes: PROGRAM_WRITE_CHUNK_BYTES,
+            write_chunk_count: 0,
+            journal: None,
+            deployment_attempts: Vec::new(),
+            conflicting_journal: None,
+            conflicting_deployment_attempts: Vec::new(),
+        }));
     };
+    if !deployment_status_is_known(&record.status)
+        || record.attempt_evidence_version
+            != wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_EVIDENCE_VERSION
+    {
+        return Err(deployment_journal_error(format!(
+            "Program {} 的部署记录状态或证据版本无效",
+            program_id
+        )));
+    }
+    for attempt in &deployment_attempts {
+        validate_program_deployment_attempt_record(record, attempt)?;
+    }
+    Ok(Json(ProgramDeploymentJournalResponse {
+        network,
+        genesis_hash,
+        write_chunk_bytes: PROGRAM_WRITE_CHUNK_BYTES,
+        write_chunk_count: record.program_len.div_ceil(PROGRAM_WRITE_CHUNK_BYTES),
+        journal: Some(ProgramDeploymentJournalView::from(record)),
+        deployment_attempts,
+        conflicting_journal: None,
+        conflicting_deployment_attempts: Vec::new(),
+    }))
+}
 
-    if buffer_keypair.is_none() {
-        let chain_completed = write_plan.completed_chunks;
-        let total_chunks = chain_completed
-            .checked_add(write_plan.pending_chunk_indexes.len())
-            .ok_or_else(|| deployment_journal_error("Buffer chunk 总数溢出"))?;
-        if let Some(record) = deployment_journal.as_ref() {
-            if record.attempt_evidence_version == 0 {
-                if record.completed_writes > chain_completed {
-                    return Err(deployment_journal_error(format!(
-                        "旧 journal 的完成计数 {} 大于 finalized Buffer 的 {}；该旧值可能来自 confirmed 状态，拒绝自动迁移",
-                        record.completed_writes, chain_completed
-                    )));
-                }
-                match record.status.as_str() {
-                    DEPLOYMENT_STATUS_WRITE_SIGNED
-                    | DEPLOYMENT_STATUS_WRITE_CONFIRMED
-                    | DEPLOYMENT_STATUS_WRITE_RECONCILE => {
-                        let last_index = record.last_write_chunk_index.ok_or_else(|| {
-                            deployment_journal_error(
-                                "旧 write journal 缺少 chunk index，无法证明此前尝试均已落地",
-                            )
-                        })?;
-                        if last_index >= total_chunks
-                            || write_plan
-                                .pending_chunk_indexes
-                                .iter()
-                                .any(|pending| *pending < last_index)
-                        {
-                            return Err(deployment_journal_error(
-                                "旧 write journal 存在无法还原的早期 pending chunk，拒绝自动重签",
-                            ));
-                        }
-                        if write_plan.pending_chunk_indexes.contains(&last_index)
-                            && !deployment_attempts.iter().any(|attempt| {
-                                attempt.stage == wallet_store::PROGRAM_DEPLOYMENT_STAGE_WRITE
-                                    && attempt.chunk_index == Some(last_index)
-                            })
-                        {
-                            return Err(deployment_journal_error(
-                                "旧 write journal 的最后一个 pending chunk 缺少完整签名证据",
-                            ));
-                        }
-                    }
-                    DEPLOYMENT_STATUS_BUFFER_READY => {
-                        if chain_completed < record.completed_writes {
-                            return Err(deployment_journal_error(format!(
-                                "finalized Buffer 仅完成 {} 块，少于旧 journal 的 {} 块",
-                                chain_completed, record.completed_writes
-                            )));
-                        }
-                    }
-                    DEPLOYMENT_STATUS_BUFFER_FINALIZED
-                    | DEPLOYMENT_STATUS_DEPLOY_SIGNED
-                    | DEPLOYMENT_STATUS_DEPLOY_RECONCILE
-                    | DEPLOYMENT_STATUS_DEPLOY_FINALIZED
-                    | DEPLOYMENT_STATUS_FINALIZED => {
-                        if !write_plan.pending_chunk_indexes.is_empty() {
-                            return Err(deployment_journal_error(
-                                "旧 journal 声称 Buffer 已 finalized，但回读仍存在 pending chunk",
-                            ));
-                        }
-                    }
-                    DEPLOYMENT_STATUS_CREATE_BUFFER_SIGNED
-                    | DEPLOYMENT_STATUS_CREATE_BUFFER_RECONCILE => {
-                        if record.completed_writes != 0 {
-                            return Err(deployment_journal_error(
-                                "旧 create-buffer journal 含非零写入进度，拒绝迁移",
-                            ));
-                        }
-                    }
-                    _ => {}
-                }
-            } else if chain_completed < record.completed_writes {
-                return Err(deployment_journal_error(format!(
-                    "finalized Buffer 仅完成 {} 块，少于 journal 的 {} 块",
-                    chain_completed, record.completed_writes
-                )));
-            }
+#[cfg(test)]
+mod generic_program_deployment_policy_tests {
+    use super::*;
+
+    fn unique_temp_path(label: &str) -> PathBuf {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|value| value.as_nanos())
+            .unwrap_or_default();
+        std::env::temp_dir().join(format!(
+            "fnzero-safe-{label}-{}-{nanos}",
+            std::process::id()
+        ))
+    }
+
+    fn valid_journal_request() -> ProgramDeploymentJournalRequest {
+        ProgramDeploymentJournalRequest {
+            network: "devnet".to_string(),
+            expected_genesis_hash: solana_sdk::hash::Hash::new_unique().to_string(),
+            expected_program_id: Pubkey::new_unique().to_string(),
+            expected_program_sha256: "AB".repeat(32),
+            program_len: 1_024,
+            max_data_len: 2_048,
+            expected_upgrade_authority: Pubkey::new_unique().to_string(),
         }
+    }
 
-        for attempt in deployment_attempts
-            .iter()
-            .filter(|attempt| attempt.stage == wallet_store::PROGRAM_DEPLOYMENT_STAGE_WRITE)
-        {
-            let index = attempt
-                .chunk_index
-                .ok_or_else(|| deployment_journal_error("write attempt 缺少 chunk index"))?;
-            if index >= total_chunks {
-                return Err(deployment_journal_error(format!(
-                    "write attempt {} 的 chunk index {} 超出总块数 {}",
-                    attempt.signature, index, total_chunks
-                )));
-            }
-            let pending = write_plan.pending_chunk_indexes.contains(&index);
-            if pending && deployment_attempt_is_blocking_terminal(&attempt.status) {
-                return Err(deployment_journal_error(format!(
-                    "write chunk {} 的 attempt {} 已处于终态 {}，但 finalized Buffer 仍未包含该块",
-                    index, attempt.signature, attempt.status
-                )));
-            }
-            if !deployment_attempt_is_active(&attempt.status) {
-                continue;
-            }
-            let completed_for_transition = deployment_journal
-                .as_ref()
-                .map(|_| chain_completed)
-                .unwrap_or(chain_completed);
-            if pending {
-                reconcile_absent_deployment_attempt(
-                    &client,
-                    &mut deployment_journal,
-                    attempt,
-                    DEPLOYMENT_STATUS_BUFFER_READY,
-                    completed_for_transition,
-                    |client, min_context_slot| {
-                        confirm_write_attempt_still_absent(
-                            client,
-                            &buffer_address,
-                            &payer_pubkey,
-                            &program_bytes,
-                            &write_plan,
-                            index,
-                            min_context_slot,
-                        )
-                    },
-                )?;
-            } else {
-                transition_deployment_attempt(
-                    &mut deployment_journal,
-                    &attempt.signature,
-                    &attempt.status,
-                    wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_FINALIZED,
-                    DEPLOYMENT_STATUS_BUFFER_READY,
-                    completed_for_transition,
-                )?;
-            }
-        }
-
-        if let Some(record) = deployment_journal.as_ref() {
-            if record.attempt_evidence_version == 0 {
-                let promoted = wallet_store::promote_program_deployment_attempt_evidence(
-                    record,
-                    DEPLOYMENT_STATUS_BUFFER_READY,
-                    chain_completed,
-                )
-                .map_err(|message| ApiError { message })?;
-                deployment_journal = Some(promoted);
-            } else if record.completed_writes != chain_completed
-                || matches!(
-                    record.status.as_str(),
-                    DEPLOYMENT_STATUS_CREATE_BUFFER_SIGNED
-                        | DEPLOYMENT_STATUS_CREATE_BUFFER_RECONCILE
-                        | DEPLOYMENT_STATUS_WRITE_SIGNED
-                        | DEPLOYMENT_STATUS_WRITE_CONFIRMED
-                        | DEPLOYMENT_STATUS_WRITE_RECONCILE
-                        | DEPLOYMENT_STATUS_DEPLOY_SIGNED
-                        | DEPLOYMENT_STATUS_DEPLOY_RECONCILE
-                        | DEPLOYMENT_STATUS_DEPLOY_FINALIZED
-                )
-            {
-                transition_deployment_journal(
-                    &mut deployment_journal,
-                    DEPLOYMENT_STATUS_BUFFER_READY,
-                    None,
-                    None,
-                    None,
-                    None,
-                    None,
-                    chain_completed,
-                    None,
-                    None,
-                )?;
-            }
-        }
+    #[test]
+    fn generic_journal_intent_normalizes_hash_without_hidden_policy() {
+        let request = valid_journal_request();
+        let intent = validate_program_deployment_journal_request(&request).unwrap();
+        assert_eq!(intent.program_sha256, "ab".repeat(32));
+        assert_eq!(intent.program_id.to_string(), request.expected_program_id);
+        assert_eq!(
+            intent.upgrade_authority.to_string(),
+            request.expected_upgrade_authority
+        );
     }
 
-    let create_buffer_ixs = if buffer_keypair.is_some() {
-        Some(
-            loader_v3_instruction::create_buffer(
-                &payer_pubkey,
-                &buffer_address,
-                &payer_pubkey,
-                buffer_lamports,
-                program_bytes.len(),
-            )
-            .map_err(|e| ApiError {
-                message: format!("创建 buffer 指令失败: {}", e),
-            })?,
-        )
-    } else {
-        None
-    };
-
-    #[allow(deprecated)]
-    let deploy_ixs = loader_v3_instruction::deploy_with_max_program_len(
-        &payer_pubkey,
-        &program_id,
-        &buffer_address,
-        &payer_pubkey,
-        program_lamports,
-        max_data_len,
-    )
-    .map_err(|e| ApiError {
-        message: format!("创建 deploy 指令失败: {}", e),
-    })?;
+    #[test]
+    fn program_write_chunk_fits_solana_transaction_packet() {
+        let payer = Keypair::new();
+        let buffer = Pubkey::new_unique();
+        let chunk = vec![7_u8; PROGRAM_WRITE_CHUNK_BYTES];
+        let instruction = loader_v3_instruction::write(&buffer, &payer.pubkey(), 0, chunk);
+        let transaction = Transaction::new_signed_with_payer(
+            &[instruction],
+            Some(&payer.pubkey()),
+            &[&payer],
+            solana_sdk::hash::Hash::new_unique(),
+        );
+        let serialized = bincode::serialize(&transaction).unwrap();
+        assert!(
+            serialized.len() <= SOLANA_TRANSACTION_PACKET_DATA_BYTES,
+            "serialized write transaction is {} bytes, above packet limit {}",
+            serialized.len(),
+            SOLANA_TRANSACTION_PACKET_DATA_BYTES,
+        );
+    }
 
-    let fee_blockhash = client.get_latest_blockhash().map_err(|error| ApiError {
-        message: format!("获取交易费估算 blockhash 失败: {error}"),
-    })?;
-    let create_buffer_fee = create_buffer_ixs
-        .as_deref()
-        .map(|instructions| {
-            estimate_instruction_fee(
-                &client,
-                instructions,
-                &payer_pubkey,
-                &fee_blockhash,
-                "create-buffer",
-            )
-        })
-        .transpose()?
-        .unwrap_or(0);
-    let mut write_fee_by_chunk_len = HashMap::<usize, u64>::new();
-    let mut write_fees = 0u64;
-    let mut estimated_write_fee_lamports = 0u64;
-    for index in &write_plan.pending_chunk_indexes {
-        let start = index
-            .checked_mul(PROGRAM_WRITE_CHUNK_BYTES)
-            .ok_or_else(|| ApiError {
-                message: "Program 写入偏移超出范围".to_string(),
-            })?;
-        let chunk = program_bytes
-            .get(
-                start
-                    ..start
-                        .saturating_add(PROGRAM_WRITE_CHUNK_BYTES)
-                        .min(program_bytes.len()),
-            )
-            .ok_or_else(|| ApiError {
-                message: "Program 写入块超出制品范围".to_string(),
-            })?;
-        let fee = if let Some(fee) = write_fee_by_chunk_len.get(&chunk.len()) {
-            *fee
-        } else {
-            let offset = u32::try_from(start).map_err(|_| ApiError {
-                message: "Program 写入偏移超出范围".to_string(),
-            })?;
-            let instruction = loader_v3_instruction::write(
-                &buffer_address,
-                &payer_pubkey,
-                offset,
-                chunk.to_vec(),
-            );
-            let fee = estimate_instruction_fee(
-                &client,
-                &[instruction],
-                &payer_pubkey,
-                &fee_blockhash,
-                "write-buffer",
-            )?;
-            write_fee_by_chunk_len.insert(chunk.len(), fee);
-            fee
+    #[test]
+    fn external_sign_preview_summarizes_legacy_transaction() {
+        let payer = Keypair::new();
+        let recipient = Pubkey::new_unique();
+        let instruction = Instruction {
+            program_id: Pubkey::default(),
+            accounts: vec![
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new(recipient, false),
+            ],
+            data: Vec::new(),
         };
-        estimated_write_fee_lamports = estimated_write_fee_lamports.max(fee);
-        write_fees = write_fees.checked_add(fee).ok_or_else(|| ApiError {
-            message: "Program 写入交易费总额溢出".to_string(),
-        })?;
+        let transaction = Transaction::new_signed_with_payer(
+            &[instruction],
+            Some(&payer.pubkey()),
+            &[&payer],
+            solana_sdk::hash::Hash::new_unique(),
+        );
+        let transaction_base64 = BASE64.encode(bincode::serialize(&transaction).unwrap());
+        let preview = preview_external_transaction_request(&ExternalSignPreviewRequest {
+            required_signer: payer.pubkey().to_string(),
+            transaction_base64,
+            transaction_format: Some("legacy".to_string()),
+        })
+        .unwrap();
+
+        assert_eq!(preview.transaction_format, "legacy");
+        assert!(preview.required_signer_present);
+        assert_eq!(preview.required_signatures, 1);
+        assert_eq!(preview.instructions.len(), 1);
+        assert!(preview
+            .instructions
+            .first()
+            .unwrap()
+            .program_id
+            .contains("System Program"));
+        assert!(preview
+            .writable_accounts
+            .iter()
+            .any(|account| account == &recipient.to_string()));
     }
-    let deploy_fee = estimate_instruction_fee(
-        &client,
-        &deploy_ixs,
-        &payer_pubkey,
-        &fee_blockhash,
-        "deploy",
-    )?;
-    let estimated_transaction_fees_lamports = create_buffer_fee
-        .checked_add(write_fees)
-        .and_then(|value| value.checked_add(deploy_fee))
-        .ok_or_else(|| ApiError {
-            message: "部署交易费总额溢出".to_string(),
-        })?;
-    let fee_budget = program_deploy::deployment_fee_budget(
-        estimated_transaction_fees_lamports,
-        estimated_write_fee_lamports,
-        write_plan.pending_chunk_indexes.len(),
-    )
-    .map_err(|message| ApiError { message })?;
-    let rent_budget = program_deploy::deployment_rent_budget(
-        buffer_lamports,
-        program_lamports,
-        programdata_lamports,
-        buffer_keypair.is_none().then_some(resume_buffer_lamports),
-    )
-    .map_err(|message| ApiError { message })?;
-    let required_rent_balance = rent_budget.required_rent_balance_lamports;
-    let estimated_required_balance_lamports = required_rent_balance
-        .checked_add(fee_budget.total_fee_budget_lamports)
-        .ok_or_else(|| ApiError {
-            message: "部署所需余额总额溢出".to_string(),
-        })?;
-    let payer_balance = client
-        .get_balance(&payer_pubkey)
-        .map_err(|error| ApiError {
-            message: format!("查询部署钱包余额失败: {error}"),
-        })?;
-    if payer_balance < estimated_required_balance_lamports {
-        return Err(ApiError {
-            message: format!(
-                "部署钱包余额不足：当前 {} lamports，至少需要 {} lamports（租金需求 {} + 当前估算交易费 {} + 费率预留 {} + 恢复写入预留 {}）",
-                payer_balance,
-                estimated_required_balance_lamports,
-                required_rent_balance,
-                estimated_transaction_fees_lamports,
-                fee_budget.fee_rate_reserve_lamports,
-                fee_budget.recovery_write_reserve_lamports
-            ),
-        });
+
+    #[test]
+    fn generic_journal_intent_rejects_unsafe_boundaries() {
+        let mut request = valid_journal_request();
+        request.program_len = 0;
+        assert!(validate_program_deployment_journal_request(&request).is_err());
+
+        let mut request = valid_journal_request();
+        request.max_data_len = request.program_len - 1;
+        assert!(validate_program_deployment_journal_request(&request).is_err());
+
+        let mut request = valid_journal_request();
+        request.expected_program_sha256 = "xyz".to_string();
+        assert!(validate_program_deployment_journal_request(&request).is_err());
+
+        let mut request = valid_journal_request();
+        request.expected_program_id = Pubkey::default().to_string();
+        assert!(validate_program_deployment_journal_request(&request).is_err());
+
+        let mut request = valid_journal_request();
+        request.expected_genesis_hash = solana_sdk::hash::Hash::default().to_string();
+        assert!(validate_program_deployment_journal_request(&request).is_err());
     }
 
-    if deployment_journal.is_none() && buffer_keypair.is_none() {
-        let record = wallet_store::ProgramDeploymentRecord {
-            genesis_hash: genesis_hash.clone(),
-            program_id: program_id.to_string(),
-            program_sha256: program_sha256.clone(),
-            program_len: program_bytes.len(),
-            max_data_len,
-            upgrade_authority: expected_upgrade_authority.to_string(),
-            buffer_address: buffer_address.to_string(),
-            status: DEPLOYMENT_STATUS_BUFFER_READY.to_string(),
-            create_signature: None,
-            create_last_valid_block_height: None,
-            last_write_signature: None,
-            last_write_chunk_index: None,
-            last_write_last_valid_block_height: None,
-            completed_writes: write_plan.completed_chunks,
-            deploy_signature: None,
-            deploy_last_valid_block_height: None,
-            attempt_evidence_version: wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_EVIDENCE_VERSION,
-            revision: 0,
-            created_at: 0,
-            updated_at: 0,
-        };
-        let (stored, inserted) = wallet_store::reserve_program_deployment(record)
-            .map_err(|message| ApiError { message })?;
-        if !inserted {
-            validate_deployment_journal_binding(
-                &stored,
-                &genesis_hash,
-                &program_id,
-                &program_sha256,
-                program_bytes.len(),
-                max_data_len,
-                &expected_upgrade_authority,
-                Some(&buffer_address),
-            )?;
-            return Err(deployment_journal_error(format!(
-                "另一部署请求已抢先预留 Buffer {}；当前请求未发送交易，请重新进入恢复流程",
-                stored.buffer_address
-            )));
-        }
-        deployment_journal = Some(stored);
+    #[test]
+    fn unknown_network_labels_never_fall_back_to_mainnet() {
+        assert!(parse_rpc_selector("devnett").is_err());
+        assert!(parse_rpc_selector("rpc:devnett:https%3A%2F%2Fexample.invalid").is_err());
+        assert_eq!(parse_rpc_selector("mainnet").unwrap().network, "mainnet");
     }
 
-    if let (Some(instructions), Some(buffer_signer)) =
-        (create_buffer_ixs.as_ref(), buffer_keypair.as_ref())
-    {
-        let (blockhash, last_valid_block_height) = client
-            .get_latest_blockhash_with_commitment(CommitmentConfig::confirmed())
-            .map_err(|error| ApiError {
-                message: format!("获取 create-buffer blockhash 失败: {error}"),
-            })?;
-        let transaction = Transaction::new_signed_with_payer(
-            instructions,
-            Some(&payer_pubkey),
-            &[&payer, buffer_signer],
-            blockhash,
+    #[cfg(unix)]
+    #[test]
+    fn program_source_artifact_discovery_rejects_symlinks_outside_source_dir() {
+        use std::os::unix::fs::symlink;
+
+        let base = unique_temp_path("source-artifact-symlink");
+        let source = base.join("source");
+        let outside = base.join("outside");
+        fs::create_dir_all(source.join("target/deploy")).unwrap();
+        fs::create_dir_all(source.join(".keys")).unwrap();
+        fs::create_dir_all(&outside).unwrap();
+
+        let outside_so = outside.join("escaped.so");
+        let outside_keypair = outside.join("escaped-keypair.json");
+        fs::write(&outside_so, b"not-a-program").unwrap();
+        fs::write(&outside_keypair, b"[]").unwrap();
+        symlink(&outside_so, source.join("target/deploy/escaped.so")).unwrap();
+        symlink(&outside_keypair, source.join(".keys/escaped-keypair.json")).unwrap();
+
+        let canonical_source = source.canonicalize().unwrap();
+        assert!(find_program_so_path(&canonical_source, None).is_none());
+        assert!(find_program_keypair_path(&canonical_source, None).is_none());
+    }
+
+    #[test]
+    fn program_source_artifact_discovery_prefers_target_deploy_so() {
+        let source = unique_temp_path("source-artifact-prefer-deploy-so");
+        fs::create_dir_all(source.join("target/verifiable")).unwrap();
+        fs::create_dir_all(source.join("target/deploy")).unwrap();
+
+        let deploy_so = source.join("target/deploy/fnzero.so");
+        let deploy_bytes = b"deploy-so";
+        fs::write(&deploy_so, deploy_bytes).unwrap();
+
+        // Even if verifiable is newer, target/deploy/<name>.so must win.
+        std::thread::sleep(Duration::from_millis(20));
+        fs::write(
+            source.join("target/verifiable/fnzero.so"),
+            b"newer-verifiable",
+        )
+        .unwrap();
+        fs::write(
+            source.join("target/verifiable/fnzero-build.json"),
+            format!(
+                r#"{{"artifacts":{{"program":{{"sha256":"{}","length":{}}}}}}}"#,
+                "0".repeat(64),
+                99
+            ),
+        )
+        .unwrap();
+
+        let canonical_source = source.canonicalize().unwrap();
+        let selected = find_program_so_path(&canonical_source, Some("fnzero")).unwrap();
+        assert_eq!(selected, deploy_so.canonicalize().unwrap());
+
+        let artifacts =
+            load_program_source_artifacts(&canonical_source, Some("fnzero"), Some("devnet"), true)
+                .unwrap();
+        let deploy_sha = program_deploy::sha256_hex(deploy_bytes);
+        assert_eq!(
+            artifacts.program_so_path,
+            Some(deploy_so.canonicalize().unwrap())
         );
-        let local_signature = transaction
-            .signatures
-            .first()
-            .copied()
-            .ok_or_else(|| ApiError {
-                message: "create-buffer 交易缺少本地签名".to_string(),
-            })?;
-        let record = wallet_store::ProgramDeploymentRecord {
-            genesis_hash: genesis_hash.clone(),
-            program_id: program_id.to_string(),
-            program_sha256: program_sha256.clone(),
-            program_len: program_bytes.len(),
-            max_data_len,
-            upgrade_authority: expected_upgrade_authority.to_string(),
-            buffer_address: buffer_address.to_string(),
-            status: DEPLOYMENT_STATUS_CREATE_BUFFER_SIGNED.to_string(),
-            create_signature: Some(local_signature.to_string()),
-            create_last_valid_block_height: Some(last_valid_block_height),
-            last_write_signature: None,
-            last_write_chunk_index: None,
-            last_write_last_valid_block_height: None,
-            completed_writes: 0,
-            deploy_signature: None,
-            deploy_last_valid_block_height: None,
-            attempt_evidence_version: wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_EVIDENCE_VERSION,
-            revision: 0,
-            created_at: 0,
-            updated_at: 0,
-        };
-        let create_attempt = new_program_deployment_attempt(
-            &record,
-            wallet_store::PROGRAM_DEPLOYMENT_STAGE_CREATE_BUFFER,
-            None,
-            &local_signature,
-            last_valid_block_height,
+        assert_eq!(
+            artifacts.program_so_sha256.as_deref(),
+            Some(deploy_sha.as_str())
         );
-        if rotating_create_buffer {
-            let current = deployment_journal
-                .as_ref()
-                .ok_or_else(|| deployment_journal_error("Buffer 轮换时部署 journal 不存在"))?;
-            let stored = wallet_store::rotate_program_deployment_create_attempt(
-                current,
-                create_attempt,
-                DEPLOYMENT_STATUS_CREATE_BUFFER_SIGNED,
-            )
-            .map_err(|message| ApiError { message })?;
-            deployment_journal = Some(stored);
-        } else {
-            let (stored, inserted) =
-                wallet_store::reserve_program_deployment_with_attempt(record, create_attempt)
-                    .map_err(|message| ApiError { message })?;
-            if !inserted {
-                let binding_matches = validate_deployment_journal_binding(
-                    &stored,
-                    &genesis_hash,
-                    &program_id,
-                    &program_sha256,
-                    program_bytes.len(),
-                    max_data_len,
-                    &expected_upgrade_authority,
-                    None,
-                )
-                .is_ok();
-                if !binding_matches {
-                    let record = wallet_store::ProgramDeploymentRecord {
-                        genesis_hash: genesis_hash.clone(),
-                        program_id: program_id.to_string(),
-                        program_sha256: program_sha256.clone(),
-                        program_len: program_bytes.len(),
+        assert!(artifacts
+            .warnings
+            .iter()
+            .any(|warning| warning.contains("build metadata SHA-256")));
+
+        let _ = fs::remove_dir_all(source);
+    }
+
+    #[test]
+    fn program_keypair_discovery_prefers_so_stem_specific_keypair() {
+        let source = unique_temp_path("source-artifact-keypair-stem");
+        fs::create_dir_all(source.join(".keys")).unwrap();
+        fs::create_dir_all(source.join("target/deploy")).unwrap();
+
+        let unrelated_keypair = Keypair::new();
+        let target_keypair = Keypair::new();
+        fs::write(
+            source.join(".keys/alpha-program-keypair.json"),
+            serde_json::to_string(&unrelated_keypair.to_bytes().to_vec()).unwrap(),
+        )
+        .unwrap();
+        fs::write(
+            source.join("target/deploy/beta-keypair.json"),
+            serde_json::to_string(&target_keypair.to_bytes().to_vec()).unwrap(),
+        )
+        .unwrap();
+
+        let canonical_source = source.canonicalize().unwrap();
+        let selected = find_program_keypair_path(&canonical_source, Some("beta")).unwrap();
+        assert_eq!(
+            selected,
+            source
+                .join("target/deploy/beta-keypair.json")
+                .canonicalize()
+                .unwrap()
+        );
+        let mut json = fs::read_to_string(selected).unwrap();
+        let parsed = program_deploy::parse_program_keypair_json(&json).unwrap();
+        json.zeroize();
+        assert_eq!(parsed.pubkey(), target_keypair.pubkey());
+        assert_eq!(
+            artifact_stem_from_so_name("beta.so").as_deref(),
+            Some("beta")
+        );
+
+        let _ = fs::remove_dir_all(source);
+    }
+
+    #[test]
+    fn program_keypair_discovery_prefers_target_deploy_over_keys() {
+        let source = unique_temp_path("source-artifact-keypair-prefer-deploy");
+        fs::create_dir_all(source.join(".keys")).unwrap();
+        fs::create_dir_all(source.join("target/deploy")).unwrap();
+
+        let keys_keypair = Keypair::new();
+        let deploy_keypair = Keypair::new();
+        let keys_path = source.join(".keys/fnzero-program-keypair.json");
+        let deploy_path = source.join("target/deploy/fnzero-keypair.json");
+        fs::write(
+            &deploy_path,
+            serde_json::to_string(&deploy_keypair.to_bytes().to_vec()).unwrap(),
+        )
+        .unwrap();
+        // Even if .keys is newer, target/deploy must win.
+        std::thread::sleep(Duration::from_millis(20));
+        fs::write(
+            &keys_path,
+            serde_json::to_string(&keys_keypair.to_bytes().to_vec()).unwrap(),
+        )
+        .unwrap();
+
+        let canonical_source = source.canonicalize().unwrap();
+        let selected = find_program_keypair_path(&canonical_source, Some("fnzero")).unwrap();
+        assert_eq!(selected, deploy_path.canonicalize().unwrap());
+        let mut json = fs::read_to_string(selected).unwrap();
+        let parsed = program_deploy::parse_program_keypair_json(&json).unwrap();
+        json.zeroize();
+        assert_eq!(parsed.pubkey(), deploy_keypair.pubkey());
+
+        let _ = fs::remove_dir_all(source);
+    }
+
+    #[test]
+    fn program_keypair_discovery_falls_back_to_keys_when_deploy_missing() {
+        let source = unique_temp_path("source-artifact-keypair-keys-fallback");
+        fs::create_dir_all(source.join(".keys")).unwrap();
+        fs::create_dir_all(source.join("target/deploy")).unwrap();
+
+        let keys_keypair = Keypair::new();
+        let keys_path = source.join(".keys/fnzero-program-keypair.json");
+        fs::write(
+            &keys_path,
+            serde_json::to_string(&keys_keypair.to_bytes().to_vec()).unwrap(),
+        )
+        .unwrap();
+
+        let canonical_source = source.canonicalize().unwrap();
+        let selected = find_program_keypair_path(&canonical_source, Some("fnzero")).unwrap();
+        assert_eq!(selected, keys_path.canonicalize().unwrap());
+        let mut json = fs::read_to_string(selected).unwrap();
+        let parsed = program_deploy::parse_program_keypair_json(&json).unwrap();
+        json.zeroize();
+        assert_eq!(parsed.pubkey(), keys_keypair.pubkey());
+
+        let _ = fs::remove_dir_all(source);
+    }
+
+    #[test]
+    fn program_source_artifacts_block_mismatched_program_ids_before_deploy() {
+        let source = unique_temp_path("source-artifact-program-id-validation");
+        fs::create_dir_all(source.join("target/deploy")).unwrap();
+        fs::create_dir_all(source.join("target/idl")).unwrap();
+        fs::create_dir_all(source.join("programs/fnzero/src")).unwrap();
+
+        let expected_keypair = Keypair::new();
+        let stale_program_pubkey = Pubkey::new_unique();
+        let stale_program_id = stale_program_pubkey.to_string();
+        let mut stale_so = b"\x7fELFAnchorError occurred".to_vec();
+        stale_so.extend_from_slice(&stale_program_pubkey.to_bytes());
+        fs::write(source.join("target/deploy/fnzero.so"), stale_so).unwrap();
+        fs::write(
+            source.join("target/deploy/fnzero-keypair.json"),
+            serde_json::to_string(&expected_keypair.to_bytes().to_vec()).unwrap(),
+        )
+        .unwrap();
+        fs::write(
+            source.join("Anchor.toml"),
+            format!(
+                r#"[programs.devnet]
+fnzero = "{stale_program_id}"
+"#
+            ),
+        )
+        .unwrap();
+        fs::write(
+            source.join("target/idl/fnzero.json"),
+            format!(r#"{{"address":"{stale_program_id}","instructions":[]}}"#),
+        )
+        .unwrap();
+        fs::write(
+            source.join("programs/fnzero/src/lib.rs"),
+            format!(r#"declare_id!("{stale_program_id}");"#),
+        )
+        .unwrap();
+
+        let canonical_source = source.canonicalize().unwrap();
+        let artifacts =
+            load_program_source_artifacts(&canonical_source, Some("fnzero"), Some("devnet"), true)
+                .unwrap();
+
+        let expected_program_id = expected_keypair.pubkey().to_string();
+        assert_eq!(
+            artifacts.expected_program_id.as_deref(),
+            Some(expected_program_id.as_str())
+        );
+        assert!(artifacts
+            .source_validation_errors
+            .iter()
+            .any(|error| { error.contains("Anchor.toml") && error.contains(&stale_program_id) }));
+        assert!(artifacts
+            .source_validation_errors
+            .iter()
+            .any(|error| error.contains("target/idl/fnzero.json")));
+        assert!(artifacts
+            .source_validation_errors
+            .iter()
+            .any(|error| error.contains("declare_id!")));
+        assert!(
+            artifacts
+                .source_validation_errors
+                .iter()
+                .any(|error| error.contains("target/deploy/fnzero.so")
+                    && error.contains("Anchor .so"))
+        );
+        assert!(artifacts
+            .warnings
+            .iter()
+            .any(|warning| warning.contains("部署前一致性校验发现")));
+
+        let _ = fs::remove_dir_all(source);
+    }
+
+    #[test]
+    fn prebuild_program_id_validation_blocks_only_source_declarations() {
+        let source = unique_temp_path("source-prebuild-program-id-validation");
+        fs::create_dir_all(source.join("target/idl")).unwrap();
+        fs::create_dir_all(source.join("programs/fnzero/src")).unwrap();
+
+        let expected_program_id = Pubkey::new_unique().to_string();
+        let stale_idl_program_id = Pubkey::new_unique().to_string();
+        fs::write(
+            source.join("Anchor.toml"),
+            format!(
+                r#"[programs.devnet]
+fnzero = "{expected_program_id}"
+"#
+            ),
+        )
+        .unwrap();
+        fs::write(
+            source.join("target/idl/fnzero.json"),
+            format!(r#"{{"address":"{stale_idl_program_id}","instructions":[]}}"#),
+        )
+        .unwrap();
+        fs::write(
+            source.join("programs/fnzero/src/lib.rs"),
+            format!(r#"declare_id!("{expected_program_id}");"#),
+        )
+        .unwrap();
+
+        let canonical_source = source.canonicalize().unwrap();
+        let prebuild_errors = collect_prebuild_source_validation_errors(
+            &canonical_source,
+            Some("fnzero"),
+            Some("devnet"),
+            Some(&expected_program_id),
+        )
+        .unwrap();
+        let postbuild_errors = collect_source_validation_errors(
+            &canonical_source,
+            Some("fnzero"),
+            Some("devnet"),
+            Some(&expected_program_id),
+            None,
+        )
+        .unwrap();
+
+        assert!(prebuild_errors.is_empty());
+        assert!(postbuild_errors
+            .iter()
+            .any(|error| error.contains("target/idl/fnzero.json")));
+
+        let _ = fs::remove_dir_all(source);
+    }
+
+    #[test]
+    fn generated_program_keypair_helpers_sync_anchor_sources() {
+        let source = unique_temp_path("source-generate-program-keypair-sync");
+        fs::create_dir_all(source.join("programs/fnzero/src")).unwrap();
+
+        let old_program_id = Pubkey::new_unique().to_string();
+        let new_program_id = Pubkey::new_unique().to_string();
+        fs::write(
+            source.join("Anchor.toml"),
+            format!(
+                r#"[programs.devnet]
+fnzero = "{old_program_id}"
+"#
+            ),
+        )
+        .unwrap();
+        fs::write(
+            source.join("programs/fnzero/src/lib.rs"),
+            format!(
+                r#"use anchor_lang::prelude::*;
+declare_id!("{old_program_id}");
+"#
+            ),
+        )
+        .unwrap();
+
+        let canonical_source = source.canonicalize().unwrap();
+        let anchor_updates =
+            update_anchor_program_ids(&canonical_source, Some("fnzero"), &new_program_id).unwrap();
+        let rust_updates =
+            update_rust_declare_id_files(&canonical_source, Some("fnzero"), &new_program_id)
+                .unwrap();
+
+        assert_eq!(anchor_updates.len(), 1);
+        assert_eq!(rust_updates.len(), 1);
+        assert!(fs::read_to_string(source.join("Anchor.toml"))
+            .unwrap()
+            .contains(&new_program_id));
+        assert!(
+            fs::read_to_string(source.join("programs/fnzero/src/lib.rs"))
+                .unwrap()
+                .contains(&new_program_id)
+        );
+        assert!(
+            !fs::read_to_string(source.join("programs/fnzero/src/lib.rs"))
+                .unwrap()
+                .contains(&old_program_id)
+        );
+
+        let _ = fs::remove_dir_all(source);
+    }
+
+    #[test]
+    fn stake_action_requests_parse_alongside_wallet_fields() {
+        let stake = Pubkey::new_unique();
+        let request = serde_json::from_value::<StakeActionExecuteRequest>(serde_json::json!({
+            "wallet_id": "w1",
+            "password": "secret",
+            "action": "withdraw",
+            "stake_account": stake.to_string(),
+            "amount": 1.5,
+            "network": "devnet"
+        }))
+        .unwrap();
+        assert_eq!(request.wallet.wallet_id.as_deref(), Some("w1"));
+        assert_eq!(
+            request.action.to_action().unwrap(),
+            StakeAction::Withdraw {
+                stake_account: stake,
+                to: None,
+                lamports: Some(1_500_000_000),
+            }
+        );
+
+        let request = serde_json::from_value::<StakeActionExecuteRequest>(serde_json::json!({
+            "action": "authorize",
+            "stake_account": stake.to_string(),
+            "new_authority": Pubkey::new_unique().to_string(),
+            "authority": "custodian"
+        }))
+        .unwrap();
+        assert!(request.action.to_action().is_err());
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DeployProgramRequest {
+    #[serde(default)]
+    wallet_id: Option<String>,
+    #[serde(default)]
+    private_key: Option<String>,
+    #[serde(default)]
+    secret_key: Option<String>,
+    #[serde(default)]
+    keystore_json: Option<String>,
+    #[serde(default)]
+    encrypted_key: Option<String>,
+    #[serde(default)]
+    password: Option<String>,
+    #[serde(default)]
+    program_keypair_json: Option<String>,
+    #[serde(default)]
+    program_keypair_path: Option<String>,
+    expected_program_id: String,
+    expected_upgrade_authority: String,
+    expected_genesis_hash: String,
+    expected_program_sha256: String,
+    program_so_base64: String,
+    network: String,
+    #[serde(default)]
+    max_data_len: Option<usize>,
+    #[serde(default)]
+    resume_buffer_address: Option<String>,
+}
+
+impl DeployProgramRequest {
+    fn take_wallet_auth(&mut self) -> WalletAuthRequest {
+        WalletAuthRequest {
+            wallet_id: self.wallet_id.take(),
+            private_key: self.private_key.take(),
+            secret_key: self.secret_key.take(),
+            keystore_json: self.keystore_json.take(),
+            encrypted_key: self.encrypted_key.take(),
+            password: self.password.take(),
+        }
+    }
+}
+
+impl Drop for DeployProgramRequest {
+    fn drop(&mut self) {
+        if let Some(value) = self.program_keypair_json.as_mut() {
+            value.zeroize();
+        }
+        self.take_wallet_auth().clear_secrets();
+    }
+}
+
+#[derive(Serialize)]
+struct DeployProgramResponse {
+    program_id: String,
+    programdata_address: String,
+    buffer_address: String,
+    authority: String,
+    network: String,
+    genesis_hash: String,
+    program_bytes: usize,
+    max_data_len: usize,
+    program_sha256: String,
+    temporary_buffer_rent_lamports: u64,
+    program_rent_lamports: u64,
+    programdata_rent_lamports: u64,
+    rent_lamports: u64,
+    estimated_transaction_fees_lamports: u64,
+    fee_rate_reserve_lamports: u64,
+    recovery_write_reserve_lamports: u64,
+    total_fee_budget_lamports: u64,
+    estimated_required_balance_lamports: u64,
+    create_buffer_signature: Option<String>,
+    skipped_write_chunks: usize,
+    write_signatures: Vec<String>,
+    deploy_signature: String,
+    finalized_slot: u64,
+    deployed_slot: u64,
+    readback_verified: bool,
+    journal_revision: Option<u64>,
+    attempt_evidence_version: Option<u32>,
+    deployment_attempts: Vec<wallet_store::ProgramDeploymentAttemptRecord>,
+    status: String,
+}
+
+async fn deploy_generic_program(
+    Json(req): Json<DeployProgramRequest>,
+) -> Result<Json<DeployProgramResponse>, ApiError> {
+    deploy_program(Json(req)).await
+}
+
+async fn deploy_program(
+    Json(mut req): Json<DeployProgramRequest>,
+) -> Result<Json<DeployProgramResponse>, ApiError> {
+    let _deploy_guard = PROGRAM_DEPLOY_LOCK
+        .get_or_init(|| tokio::sync::Mutex::new(()))
+        .try_lock()
+        .map_err(|_| ApiError {
+            message: PROGRAM_DEPLOY_BUSY_MESSAGE.to_string(),
+        })?;
+    let expected_upgrade_authority = Pubkey::from_str(req.expected_upgrade_authority.trim())
+        .map_err(|_| ApiError {
+            message: "无效的预期 Upgrade Authority".to_string(),
+        })?;
+
+    let program_bytes = decode_program_binary_base64(&req.program_so_base64)?;
+
+    let program_keypair_json = match (
+        req.program_keypair_json.take(),
+        req.program_keypair_path
+            .as_deref()
+            .map(str::trim)
+            .filter(|value| !value.is_empty()),
+    ) {
+        (Some(json), None) => json,
+        (None, Some(path)) => {
+            let path = PathBuf::from(path)
+                .canonicalize()
+                .map_err(|error| ApiError {
+                    message: format!("Program keypair 路径不可访问: {error}"),
+                })?;
+            read_text_file_limited(
+                &path,
+                program_deploy::MAX_PROGRAM_KEYPAIR_JSON_BYTES,
+                "Program keypair",
+            )?
+        }
+        (Some(mut json), Some(_)) => {
+            json.zeroize();
+            return Err(ApiError {
+                message: "Program keypair JSON 与路径只能提供一个".to_string(),
+            });
+        }
+        (None, None) => {
+            return Err(ApiError {
+                message: "必须提供 Program keypair JSON 或 Program keypair 路径".to_string(),
+            });
+        }
+    };
+    let mut program_keypair_json = Zeroizing::new(program_keypair_json);
+    let program_keypair_result = program_deploy::parse_program_keypair_json(&program_keypair_json);
+    program_keypair_json.zeroize();
+    let program_keypair = program_keypair_result.map_err(|message| ApiError { message })?;
+    let program_id =
+        program_deploy::require_program_id(&program_keypair, req.expected_program_id.trim())
+            .map_err(|message| ApiError { message })?;
+
+    let program_sha256 =
+        program_deploy::require_sha256(&program_bytes, &req.expected_program_sha256)
+            .map_err(|message| ApiError { message })?;
+    program_deploy::require_anchor_declared_program_id(&program_bytes, &program_id)
+        .map_err(|message| ApiError { message })?;
+    let program_bytes = verify_program_binary_offline(program_bytes).await?;
+
+    let requested_resume_buffer_address = req
+        .resume_buffer_address
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(|value| {
+            Pubkey::from_str(value).map_err(|_| ApiError {
+                message: "无效的恢复 Buffer 地址".to_string(),
+            })
+        })
+        .transpose()?;
+    let max_data_len = req.max_data_len.unwrap_or(program_bytes.len());
+    if max_data_len < program_bytes.len() {
+        return Err(ApiError {
+            message: "最大 Program 数据长度不能小于 .so 文件大小".to_string(),
+        });
+    }
+    if max_data_len > MAX_PROGRAM_SO_BYTES {
+        return Err(ApiError {
+            message: format!(
+                "最大 Program 数据长度不能超过 {} MB",
+                MAX_PROGRAM_SO_BYTES / 1024 / 1024
+            ),
+        });
+    }
+
+    if req.network.trim().is_empty() {
+        return Err(ApiError {
+            message: "部署网络不能为空".to_string(),
+        });
+    }
+    let selector = rpc_selector(Some(req.network.trim()))?;
+    let rpc_url = selector.url;
+    let network = selector.network;
+    let client = RpcClient::new_with_timeout_and_commitment(
+        rpc_url.to_string(),
+        Duration::from_secs(PROGRAM_DEPLOY_RPC_TIMEOUT_SECS),
+        CommitmentConfig::confirmed(),
+    );
+
+    let expected_genesis_hash = solana_sdk::hash::Hash::from_str(req.expected_genesis_hash.trim())
+        .map_err(|_| ApiError {
+            message: "无效的预期 genesis hash".to_string(),
+        })?;
+    let actual_genesis_hash = client.get_genesis_hash().map_err(|error| ApiError {
+        message: format!("读取 RPC genesis hash 失败: {error}"),
+    })?;
+    if actual_genesis_hash != expected_genesis_hash {
+        return Err(ApiError {
+            message: format!(
+                "RPC genesis hash 为 {}，预期为 {}；已在花费前中止部署",
+                actual_genesis_hash, expected_genesis_hash
+            ),
+        });
+    }
+
+    let genesis_hash = actual_genesis_hash.to_string();
+    let mut deployment_journal =
+        wallet_store::find_program_deployment(&genesis_hash, &program_id.to_string())
+            .map_err(|message| ApiError { message })?;
+    let mut deployment_journal_binding_matches = true;
+    let mut finalized_journal_binding_conflicts = false;
+    let journal_buffer_address = if let Some(record) = deployment_journal.as_ref() {
+        if !deployment_status_is_known(&record.status) {
+            return Err(deployment_journal_error(format!(
+                "Program {} 的记录状态 {} 无法识别，拒绝继续",
+                program_id, record.status
+            )));
+        }
+        match validate_deployment_journal_binding(
+            record,
+            &genesis_hash,
+            &program_id,
+            &program_sha256,
+            program_bytes.len(),
+            max_data_len,
+            &expected_upgrade_authority,
+            requested_resume_buffer_address.as_ref(),
+        ) {
+            Ok(buffer) => Some(buffer),
+            Err(_)
+                if requested_resume_buffer_address.is_none()
+                    && record.status != DEPLOYMENT_STATUS_FINALIZED =>
+            {
+                deployment_journal_binding_matches = false;
+                None
+            }
+            Err(_)
+                if requested_resume_buffer_address.is_none()
+                    && record.status == DEPLOYMENT_STATUS_FINALIZED =>
+            {
+                deployment_journal_binding_matches = false;
+                finalized_journal_binding_conflicts = true;
+                None
+            }
+            Err(error) => return Err(error),
+        }
+    } else {
+        None
+    };
+    let deployment_attempts = if let Some(record) = deployment_journal.as_ref() {
+        if deployment_journal_binding_matches {
+            let attempts = wallet_store::load_program_deployment_attempts(
+                &record.genesis_hash,
+                &record.program_id,
+            )
+            .map_err(|message| ApiError { message })?;
+            for attempt in &attempts {
+                validate_program_deployment_attempt_record(record, attempt)?;
+            }
+            attempts
+        } else {
+            Vec::new()
+        }
+    } else {
+        Vec::new()
+    };
+    if !deployment_journal_binding_matches {
+        deployment_journal = None;
+    }
+
+    let programdata_address = get_program_data_address(&program_id);
+    let deployment_state_addresses = vec![program_id, programdata_address];
+    let existing_accounts = client
+        .get_multiple_accounts_with_commitment(
+            &deployment_state_addresses,
+            CommitmentConfig::finalized(),
+        )
+        .map_err(|error| ApiError {
+            message: format!("部署前查询 Program 与 ProgramData 失败: {error}"),
+        })?;
+    let existing_finalized_slot = existing_accounts.context.slot;
+    if existing_accounts.value.len() != deployment_state_addresses.len() {
+        return Err(ApiError {
+            message: "部署前 finalized 账户查询结果不完整，拒绝部署".to_string(),
+        });
+    }
+    let mut existing_values = existing_accounts.value.into_iter();
+    let existing_program = existing_values.next().flatten();
+    let existing_programdata = existing_values.next().flatten();
+
+    let existing_verified_readback = match (existing_program, existing_programdata) {
+        (None, None) => None,
+        (Some(program), Some(programdata)) => Some(
+            program_deploy::verify_deployment_readback(
+                &program_id,
+                &expected_upgrade_authority,
+                &program_bytes,
+                max_data_len,
+                &program.owner,
+                program.executable,
+                &program.data,
+                &programdata.owner,
+                &programdata.data,
+            )
+            .map_err(|error| ApiError {
+                message: format!(
+                    "目标 Program 已存在，但链上 finalized 代码与本次 .so 不一致；同一个 Program ID 不能再次走“新部署”，请使用升级流程发布新 .so，或生成新的 Program keypair 后全新部署。链上校验详情: {error}"
+                ),
+            })?,
+        ),
+        _ => {
+            return Err(ApiError {
+                message: format!(
+                    "目标 Program 与 ProgramData 只有一个存在（Program ID: {}），拒绝继续部署",
+                    program_id
+                ),
+            })
+        }
+    };
+
+    if finalized_journal_binding_conflicts && existing_verified_readback.is_none() {
+        return Err(ApiError {
+            message: format!(
+                "Program {} 已有 finalized 部署记录，但当前 artifact 与记录不同，且链上 finalized Program 不可见；为避免覆盖历史或误复用 Program ID，请生成新的 Program keypair 后新部署，或确认链上 Program 存在后使用升级流程",
+                program_id
+            ),
+        });
+    }
+
+    let buffer_lamports = client
+        .get_minimum_balance_for_rent_exemption(UpgradeableLoaderState::size_of_buffer(
+            program_bytes.len(),
+        ))
+        .map_err(|e| ApiError {
+            message: format!("计算 buffer 租金失败: {}", e),
+        })?;
+    let program_lamports = client
+        .get_minimum_balance_for_rent_exemption(UpgradeableLoaderState::size_of_program())
+        .map_err(|e| ApiError {
+            message: format!("计算 program 租金失败: {}", e),
+        })?;
+    let programdata_lamports = client
+        .get_minimum_balance_for_rent_exemption(UpgradeableLoaderState::size_of_programdata(
+            max_data_len,
+        ))
+        .map_err(|e| ApiError {
+            message: format!("计算 ProgramData 租金失败: {}", e),
+        })?;
+    let final_rent_lamports = program_lamports
+        .checked_add(programdata_lamports)
+        .ok_or_else(|| ApiError {
+            message: "Program 与 ProgramData 最终租金总额溢出".to_string(),
+        })?;
+
+    let wallet_auth = req.take_wallet_auth();
+    let payer = run_keystore_task(move || wallet_auth.keypair()).await?;
+    let payer_pubkey = payer.pubkey();
+    if expected_upgrade_authority != payer_pubkey {
+        return Err(ApiError {
+            message: format!(
+                "预期 Upgrade Authority 为 {}，但付款钱包为 {}；首次部署必须由该 authority 付款并持有升级权限",
+                expected_upgrade_authority, payer_pubkey
+            ),
+        });
+    }
+    if let Some(readback) = existing_verified_readback {
+        if readback.program_sha256 != program_sha256 {
+            return Err(ApiError {
+                message: "finalized Program SHA-256 与本地已验证哈希不一致".to_string(),
+            });
+        }
+        for attempt in deployment_attempts.iter().filter(|attempt| {
+            attempt.stage == wallet_store::PROGRAM_DEPLOYMENT_STAGE_DEPLOY
+                && deployment_attempt_is_active(&attempt.status)
+        }) {
+            let completed_writes = deployment_journal
+                .as_ref()
+                .map(|record| record.completed_writes)
+                .unwrap_or_default();
+            transition_deployment_attempt(
+                &mut deployment_journal,
+                &attempt.signature,
+                &attempt.status,
+                wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_FINALIZED,
+                DEPLOYMENT_STATUS_FINALIZED,
+                completed_writes,
+            )?;
+        }
+        if deployment_journal
+            .as_ref()
+            .is_some_and(|record| record.status != DEPLOYMENT_STATUS_FINALIZED)
+        {
+            let completed_writes = deployment_journal
+                .as_ref()
+                .map(|record| record.completed_writes)
+                .unwrap_or_default();
+            transition_deployment_journal(
+                &mut deployment_journal,
+                DEPLOYMENT_STATUS_FINALIZED,
+                None,
+                None,
+                None,
+                None,
+                None,
+                completed_writes,
+                None,
+                None,
+            )?;
+        }
+        let (journal_revision, attempt_evidence_version, deployment_attempts) =
+            load_deployment_receipt_evidence(deployment_journal.as_ref())?;
+        let buffer_address = deployment_journal
+            .as_ref()
+            .map(|record| record.buffer_address.clone())
+            .unwrap_or_default();
+        let create_buffer_signature = deployment_journal
+            .as_ref()
+            .and_then(|record| record.create_signature.clone());
+        let deploy_signature = deployment_journal
+            .as_ref()
+            .and_then(|record| record.deploy_signature.clone())
+            .unwrap_or_default();
+        let write_signatures = deployment_attempts
+            .iter()
+            .filter(|attempt| attempt.stage == wallet_store::PROGRAM_DEPLOYMENT_STAGE_WRITE)
+            .map(|attempt| attempt.signature.clone())
+            .collect();
+        return Ok(Json(DeployProgramResponse {
+            program_id: program_id.to_string(),
+            programdata_address: programdata_address.to_string(),
+            buffer_address,
+            authority: readback.upgrade_authority.to_string(),
+            network,
+            genesis_hash,
+            program_bytes: program_bytes.len(),
+            max_data_len,
+            program_sha256,
+            temporary_buffer_rent_lamports: buffer_lamports,
+            program_rent_lamports: program_lamports,
+            programdata_rent_lamports: programdata_lamports,
+            rent_lamports: final_rent_lamports,
+            estimated_transaction_fees_lamports: 0,
+            fee_rate_reserve_lamports: 0,
+            recovery_write_reserve_lamports: 0,
+            total_fee_budget_lamports: 0,
+            estimated_required_balance_lamports: 0,
+            create_buffer_signature,
+            skipped_write_chunks: program_bytes.chunks(PROGRAM_WRITE_CHUNK_BYTES).count(),
+            write_signatures,
+            deploy_signature,
+            finalized_slot: existing_finalized_slot,
+            deployed_slot: readback.deployed_slot,
+            readback_verified: true,
+            journal_revision,
+            attempt_evidence_version,
+            deployment_attempts,
+            status: "already_deployed_verified".to_string(),
+        }));
+    }
+
+    let deploy_attempts = deployment_attempts
+        .iter()
+        .filter(|attempt| attempt.stage == wallet_store::PROGRAM_DEPLOYMENT_STAGE_DEPLOY)
+        .collect::<Vec<_>>();
+    for attempt in &deploy_attempts {
+        if deployment_attempt_is_blocking_terminal(&attempt.status) {
+            return Err(deployment_journal_error(format!(
+                "deploy attempt {} 已处于终态 {}，但 finalized Program 不存在；禁止自动重签",
+                attempt.signature, attempt.status
+            )));
+        }
+        if deployment_attempt_is_active(&attempt.status) {
+            let completed_writes = deployment_journal
+                .as_ref()
+                .map(|record| record.completed_writes)
+                .unwrap_or_default();
+            reconcile_absent_deployment_attempt(
+                &client,
+                &mut deployment_journal,
+                attempt,
+                DEPLOYMENT_STATUS_BUFFER_FINALIZED,
+                completed_writes,
+                |client, min_context_slot| {
+                    confirm_deploy_attempt_still_absent(
+                        client,
+                        &program_id,
+                        &programdata_address,
+                        min_context_slot,
+                    )
+                },
+            )?;
+        }
+    }
+    if deployment_journal.as_ref().is_some_and(|record| {
+        matches!(
+            record.status.as_str(),
+            DEPLOYMENT_STATUS_DEPLOY_SIGNED
+                | DEPLOYMENT_STATUS_DEPLOY_RECONCILE
+                | DEPLOYMENT_STATUS_DEPLOY_FINALIZED
+                | DEPLOYMENT_STATUS_FINALIZED
+        ) && deploy_attempts.is_empty()
+    }) {
+        return Err(deployment_journal_error(
+            "journal 处于 deploy 状态但缺少完整 signature/last-valid-block-height attempt 证据",
+        ));
+    }
+
+    let mut buffer_keypair = (journal_buffer_address.is_none()
+        && requested_resume_buffer_address.is_none())
+    .then(Keypair::new);
+    let mut buffer_address = journal_buffer_address
+        .or(requested_resume_buffer_address)
+        .or_else(|| buffer_keypair.as_ref().map(|keypair| keypair.pubkey()))
+        .ok_or_else(|| ApiError {
+            message: "无法确定部署 Buffer 地址".to_string(),
+        })?;
+    let mut rotating_create_buffer = false;
+
+    let (mut write_plan, resume_buffer_lamports) = if buffer_keypair.is_none() {
+        let response = client
+            .get_account_with_commitment(&buffer_address, CommitmentConfig::finalized())
+            .map_err(|error| ApiError {
+                message: format!("读取恢复 Buffer {} 失败: {error}", buffer_address),
+            })?;
+        if let Some(account) = response.value {
+            if account.lamports < buffer_lamports {
+                return Err(ApiError {
+                    message: format!(
+                        "恢复 Buffer {} 余额为 {} lamports，低于当前所需免租金额 {}",
+                        buffer_address, account.lamports, buffer_lamports
+                    ),
+                });
+            }
+            let plan = program_deploy::verify_resume_buffer(
+                &payer_pubkey,
+                &program_bytes,
+                PROGRAM_WRITE_CHUNK_BYTES,
+                &account.owner,
+                account.executable,
+                &account.data,
+            )
+            .map_err(|error| ApiError {
+                message: format!("恢复 Buffer {} 校验失败: {error}", buffer_address),
+            })?;
+            for attempt in deployment_attempts.iter().filter(|attempt| {
+                attempt.stage == wallet_store::PROGRAM_DEPLOYMENT_STAGE_CREATE_BUFFER
+                    && attempt.buffer_address == buffer_address.to_string()
+                    && deployment_attempt_is_active(&attempt.status)
+            }) {
+                transition_deployment_attempt(
+                    &mut deployment_journal,
+                    &attempt.signature,
+                    &attempt.status,
+                    wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_FINALIZED,
+                    DEPLOYMENT_STATUS_BUFFER_READY,
+                    plan.completed_chunks,
+                )?;
+            }
+            (plan, account.lamports)
+        } else {
+            let record = deployment_journal.as_ref().ok_or_else(|| ApiError {
+                message: format!(
+                    "恢复 Buffer {} 在 finalized 状态下不存在，且没有可审计的 create attempt；拒绝自动创建新 Buffer",
+                    buffer_address
+                ),
+            })?;
+            if record.completed_writes != 0
+                || deployment_attempts.iter().any(|attempt| {
+                    matches!(
+                        attempt.stage.as_str(),
+                        wallet_store::PROGRAM_DEPLOYMENT_STAGE_WRITE
+                            | wallet_store::PROGRAM_DEPLOYMENT_STAGE_DEPLOY
+                    )
+                })
+            {
+                return Err(deployment_journal_error(format!(
+                    "Buffer {} 不存在，但 journal 已包含 write/deploy 证据；拒绝轮换地址",
+                    buffer_address
+                )));
+            }
+            let create_attempts = deployment_attempts
+                .iter()
+                .filter(|attempt| {
+                    attempt.stage == wallet_store::PROGRAM_DEPLOYMENT_STAGE_CREATE_BUFFER
+                        && attempt.buffer_address == buffer_address.to_string()
+                })
+                .collect::<Vec<_>>();
+            if let Some(attempt) = create_attempts
+                .iter()
+                .find(|attempt| deployment_attempt_is_blocking_terminal(&attempt.status))
+            {
+                return Err(deployment_journal_error(format!(
+                    "create-buffer attempt {} 已处于终态 {}，但 finalized Buffer 不存在；禁止自动轮换",
+                    attempt.signature, attempt.status
+                )));
+            }
+            let active_create_attempts = create_attempts
+                .iter()
+                .filter(|attempt| deployment_attempt_is_active(&attempt.status))
+                .copied()
+                .collect::<Vec<_>>();
+            if active_create_attempts.len() > 1 {
+                return Err(deployment_journal_error(
+                    "同一 Buffer 存在多个活动 create attempt，拒绝自动恢复",
+                ));
+            }
+            if let Some(attempt) = active_create_attempts.first() {
+                reconcile_absent_deployment_attempt(
+                    &client,
+                    &mut deployment_journal,
+                    attempt,
+                    DEPLOYMENT_STATUS_CREATE_BUFFER_RECONCILE,
+                    0,
+                    |client, min_context_slot| {
+                        confirm_create_buffer_attempt_still_absent(
+                            client,
+                            &buffer_address,
+                            min_context_slot,
+                        )
+                    },
+                )?;
+            } else if !create_attempts.iter().any(|attempt| {
+                attempt.status == wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_EXPIRED_ABSENT
+            }) {
+                return Err(deployment_journal_error(format!(
+                    "Buffer {} 不存在且缺少完整 create signature/last-valid-block-height 证据",
+                    buffer_address
+                )));
+            }
+            let replacement = Keypair::new();
+            buffer_address = replacement.pubkey();
+            buffer_keypair = Some(replacement);
+            rotating_create_buffer = true;
+            (
+                program_deploy::BufferWritePlan {
+                    completed_chunks: 0,
+                    pending_chunk_indexes: (0..program_bytes
+                        .chunks(PROGRAM_WRITE_CHUNK_BYTES)
+                        .count())
+                        .collect(),
+                },
+                0,
+            )
+        }
+    } else {
+        (
+            program_deploy::BufferWritePlan {
+                completed_chunks: 0,
+                pending_chunk_indexes: (0..program_bytes.chunks(PROGRAM_WRITE_CHUNK_BYTES).count())
+                    .collect(),
+            },
+            0,
+        )
+    };
+
+    if buffer_keypair.is_none() {
+        let chain_completed = write_plan.completed_chunks;
+        let total_chunks = chain_completed
+            .checked_add(write_plan.pending_chunk_indexes.len())
+            .ok_or_else(|| deployment_journal_error("Buffer chunk 总数溢出"))?;
+        if let Some(record) = deployment_journal.as_ref() {
+            if record.attempt_evidence_version == 0 {
+                if record.completed_writes > chain_completed {
+                    return Err(deployment_journal_error(format!(
+                        "旧 journal 的完成计数 {} 大于 finalized Buffer 的 {}；该旧值可能来自 confirmed 状态，拒绝自动迁移",
+                        record.completed_writes, chain_completed
+                    )));
+                }
+                match record.status.as_str() {
+                    DEPLOYMENT_STATUS_WRITE_SIGNED
+                    | DEPLOYMENT_STATUS_WRITE_CONFIRMED
+                    | DEPLOYMENT_STATUS_WRITE_RECONCILE => {
+                        let last_index = record.last_write_chunk_index.ok_or_else(|| {
+                            deployment_journal_error(
+                                "旧 write journal 缺少 chunk index，无法证明此前尝试均已落地",
+                            )
+                        })?;
+                        if last_index >= total_chunks
+                            || write_plan
+                                .pending_chunk_indexes
+                                .iter()
+                                .any(|pending| *pending < last_index)
+                        {
+                            return Err(deployment_journal_error(
+                                "旧 write journal 存在无法还原的早期 pending chunk，拒绝自动重签",
+                            ));
+                        }
+                        if write_plan.pending_chunk_indexes.contains(&last_index)
+                            && !deployment_attempts.iter().any(|attempt| {
+                                attempt.stage == wallet_store::PROGRAM_DEPLOYMENT_STAGE_WRITE
+                                    && attempt.chunk_index == Some(last_index)
+                            })
+                        {
+                            return Err(deployment_journal_error(
+                                "旧 write journal 的最后一个 pending chunk 缺少完整签名证据",
+                            ));
+                        }
+                    }
+                    DEPLOYMENT_STATUS_BUFFER_READY => {
+                        if chain_completed < record.completed_writes {
+                            return Err(deployment_journal_error(format!(
+                                "finalized Buffer 仅完成 {} 块，少于旧 journal 的 {} 块",
+                                chain_completed, record.completed_writes
+                            )));
+                        }
+                    }
+                    DEPLOYMENT_STATUS_BUFFER_FINALIZED
+                    | DEPLOYMENT_STATUS_DEPLOY_SIGNED
+                    | DEPLOYMENT_STATUS_DEPLOY_RECONCILE
+                    | DEPLOYMENT_STATUS_DEPLOY_FINALIZED
+                    | DEPLOYMENT_STATUS_FINALIZED => {
+                        if !write_plan.pending_chunk_indexes.is_empty() {
+                            return Err(deployment_journal_error(
+                                "旧 journal 声称 Buffer 已 finalized，但回读仍存在 pending chunk",
+                            ));
+                        }
+                    }
+                    DEPLOYMENT_STATUS_CREATE_BUFFER_SIGNED
+                    | DEPLOYMENT_STATUS_CREATE_BUFFER_RECONCILE => {
+                        if record.completed_writes != 0 {
+                            return Err(deployment_journal_error(
+                                "旧 create-buffer journal 含非零写入进度，拒绝迁移",
+                            ));
+                        }
+                    }
+                    _ => {}
+                }
+            } else if chain_completed < record.completed_writes {
+                return Err(deployment_journal_error(format!(
+                    "finalized Buffer 仅完成 {} 块，少于 journal 的 {} 块",
+                    chain_completed, record.completed_writes
+                )));
+            }
+        }
+
+        for attempt in deployment_attempts
+            .iter()
+            .filter(|attempt| attempt.stage == wallet_store::PROGRAM_DEPLOYMENT_STAGE_WRITE)
+        {
+            let index = attempt
+                .chunk_index
+                .ok_or_else(|| deployment_journal_error("write attempt 缺少 chunk index"))?;
+            if index >= total_chunks {
+                return Err(deployment_journal_error(format!(
+                    "write attempt {} 的 chunk index {} 超出总块数 {}",
+                    attempt.signature, index, total_chunks
+                )));
+            }
+            let pending = write_plan.pending_chunk_indexes.contains(&index);
+            if pending && deployment_attempt_is_blocking_terminal(&attempt.status) {
+                return Err(deployment_journal_error(format!(
+                    "write chunk {} 的 attempt {} 已处于终态 {}，但 finalized Buffer 仍未包含该块",
+                    index, attempt.signature, attempt.status
+                )));
+            }
+            if !deployment_attempt_is_active(&attempt.status) {
+                continue;
+            }
+            let completed_for_transition = deployment_journal
+                .as_ref()
+                .map(|_| chain_completed)
+                .unwrap_or(chain_completed);
+            if pending {
+                reconcile_absent_deployment_attempt(
+                    &client,
+                    &mut deployment_journal,
+                    attempt,
+                    DEPLOYMENT_STATUS_BUFFER_READY,
+                    completed_for_transition,
+                    |client, min_context_slot| {
+                        confirm_write_attempt_still_absent(
+                            client,
+                            &buffer_address,
+                            &payer_pubkey,
+                            &program_bytes,
+                            &write_plan,
+                            index,
+                            min_context_slot,
+                        )
+                    },
+                )?;
+            } else {
+                transition_deployment_attempt(
+                    &mut deployment_journal,
+                    &attempt.signature,
+                    &attempt.status,
+                    wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_FINALIZED,
+                    DEPLOYMENT_STATUS_BUFFER_READY,
+                    completed_for_transition,
+                )?;
+            }
+        }
+
+        if let Some(record) = deployment_journal.as_ref() {
+            if record.attempt_evidence_version == 0 {
+                let promoted = wallet_store::promote_program_deployment_attempt_evidence(
+                    record,
+                    DEPLOYMENT_STATUS_BUFFER_READY,
+                    chain_completed,
+                )
+                .map_err(|message| ApiError { message })?;
+                deployment_journal = Some(promoted);
+            } else if record.completed_writes != chain_completed
+                || matches!(
+                    record.status.as_str(),
+                    DEPLOYMENT_STATUS_CREATE_BUFFER_SIGNED
+                        | DEPLOYMENT_STATUS_CREATE_BUFFER_RECONCILE
+                        | DEPLOYMENT_STATUS_WRITE_SIGNED
+                        | DEPLOYMENT_STATUS_WRITE_CONFIRMED
+                        | DEPLOYMENT_STATUS_WRITE_RECONCILE
+                        | DEPLOYMENT_STATUS_DEPLOY_SIGNED
+                        | DEPLOYMENT_STATUS_DEPLOY_RECONCILE
+                        | DEPLOYMENT_STATUS_DEPLOY_FINALIZED
+                )
+            {
+                transition_deployment_journal(
+                    &mut deployment_journal,
+                    DEPLOYMENT_STATUS_BUFFER_READY,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                    chain_completed,
+                    None,
+                    None,
+                )?;
+            }
+        }
+    }
+
+    let create_buffer_ixs = if buffer_keypair.is_some() {
+        Some(
+            loader_v3_instruction::create_buffer(
+                &payer_pubkey,
+                &buffer_address,
+                &payer_pubkey,
+                buffer_lamports,
+                program_bytes.len(),
+            )
+            .map_err(|e| ApiError {
+                message: format!("创建 buffer 指令失败: {}", e),
+            })?,
+        )
+    } else {
+        None
+    };
+
+    #[allow(deprecated)]
+    let deploy_ixs = loader_v3_instruction::deploy_with_max_program_len(
+        &payer_pubkey,
+        &program_id,
+        &buffer_address,
+        &payer_pubkey,
+        program_lamports,
+        max_data_len,
+    )
+    .map_err(|e| ApiError {
+        message: format!("创建 deploy 指令失败: {}", e),
+    })?;
+
+    let fee_blockhash = client.get_latest_blockhash().map_err(|error| ApiError {
+        message: format!("获取交易费估算 blockhash 失败: {error}"),
+    })?;
+    let create_buffer_fee = create_buffer_ixs
+        .as_deref()
+        .map(|instructions| {
+            estimate_instruction_fee(
+                &client,
+                instructions,
+                &payer_pubkey,
+                &fee_blockhash,
+                "create-buffer",
+            )
+        })
+        .transpose()?
+        .unwrap_or(0);
+    let mut write_fee_by_chunk_len = HashMap::<usize, u64>::new();
+    let mut write_fees = 0u64;
+    let mut estimated_write_fee_lamports = 0u64;
+    for index in &write_plan.pending_chunk_indexes {
+        let start = index
+            .checked_mul(PROGRAM_WRITE_CHUNK_BYTES)
+            .ok_or_else(|| ApiError {
+                message: "Program 写入偏移超出范围".to_string(),
+            })?;
+        let chunk = program_bytes
+            .get(
+                start
+                    ..start
+                        .saturating_add(PROGRAM_WRITE_CHUNK_BYTES)
+                        .min(program_bytes.len()),
+            )
+            .ok_or_else(|| ApiError {
+                message: "Program 写入块超出制品范围".to_string(),
+            })?;
+        let fee = if let Some(fee) = write_fee_by_chunk_len.get(&chunk.len()) {
+            *fee
+        } else {
+            let offset = u32::try_from(start).map_err(|_| ApiError {
+                message: "Program 写入偏移超出范围".to_string(),
+            })?;
+            let instruction = loader_v3_instruction::write(
+                &buffer_address,
+                &payer_pubkey,
+                offset,
+                chunk.to_vec(),
+            );
+            let fee = estimate_instruction_fee(
+                &client,
+                &[instruction],
+                &payer_pubkey,
+                &fee_blockhash,
+                "write-buffer",
+            )?;
+            write_fee_by_chunk_len.insert(chunk.len(), fee);
+            fee
+        };
+        estimated_write_fee_lamports = estimated_write_fee_lamports.max(fee);
+        write_fees = write_fees.checked_add(fee).ok_or_else(|| ApiError {
+            message: "Program 写入交易费总额溢出".to_string(),
+        })?;
+    }
+    let deploy_fee = estimate_instruction_fee(
+        &client,
+        &deploy_ixs,
+        &payer_pubkey,
+        &fee_blockhash,
+        "deploy",
+    )?;
+    let estimated_transaction_fees_lamports = create_buffer_fee
+        .checked_add(write_fees)
+        .and_then(|value| value.checked_add(deploy_fee))
+        .ok_or_else(|| ApiError {
+            message: "部署交易费总额溢出".to_string(),
+        })?;
+    let fee_budget = program_deploy::deployment_fee_budget(
+        estimated_transaction_fees_lamports,
+        estimated_write_fee_lamports,
+        write_plan.pending_chunk_indexes.len(),
+    )
+    .map_err(|message| ApiError { message })?;
+    let rent_budget = program_deploy::deployment_rent_budget(
+        buffer_lamports,
+        program_lamports,
+        programdata_lamports,
+        buffer_keypair.is_none().then_some(resume_buffer_lamports),
+    )
+    .map_err(|message| ApiError { message })?;
+    let required_rent_balance = rent_budget.required_rent_balance_lamports;
+    let estimated_required_balance_lamports = required_rent_balance
+        .checked_add(fee_budget.total_fee_budget_lamports)
+        .ok_or_else(|| ApiError {
+            message: "部署所需余额总额溢出".to_string(),
+        })?;
+    let payer_balance = client
+        .get_balance(&payer_pubkey)
+        .map_err(|error| ApiError {
+            message: format!("查询部署钱包余额失败: {error}"),
+        })?;
+    if payer_balance < estimated_required_balance_lamports {
+        return Err(ApiError {
+            message: format!(
+                "部署钱包余额不足：当前 {} lamports，至少需要 {} lamports（租金需求 {} + 当前估算交易费 {} + 费率预留 {} + 恢复写入预留 {}）",
+                payer_balance,
+                estimated_required_balance_lamports,
+                required_rent_balance,
+                estimated_transaction_fees_lamports,
+                fee_budget.fee_rate_reserve_lamports,
+                fee_budget.recovery_write_reserve_lamports
+            ),
+        });
+    }
+
+    if deployment_journal.is_none() && buffer_keypair.is_none() {
+        let record = wallet_store::ProgramDeploymentRecord {
+            genesis_hash: genesis_hash.clone(),
+            program_id: program_id.to_string(),
+            program_sha256: program_sha256.clone(),
+            program_len: program_bytes.len(),
+            max_data_len,
+            upgrade_authority: expected_upgrade_authority.to_string(),
+            buffer_address: buffer_address.to_string(),
+            status: DEPLOYMENT_STATUS_BUFFER_READY.to_string(),
+            create_signature: None,
+            create_last_valid_block_height: None,
+            last_write_signature: None,
+            last_write_chunk_index: None,
+            last_write_last_valid_block_height: None,
+            completed_writes: write_plan.completed_chunks,
+            deploy_signature: None,
+            deploy_last_valid_block_height: None,
+            attempt_evidence_version: wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_EVIDENCE_VERSION,
+            revision: 0,
+            created_at: 0,
+            updated_at: 0,
+        };
+        let (stored, inserted) = wallet_store::reserve_program_deployment(record)
+            .map_err(|message| ApiError { message })?;
+        if !inserted {
+            validate_deployment_journal_binding(
+                &stored,
+                &genesis_hash,
+                &program_id,
+                &program_sha256,
+                program_bytes.len(),
+                max_data_len,
+                &expected_upgrade_authority,
+                Some(&buffer_address),
+            )?;
+            return Err(deployment_journal_error(format!(
+                "另一部署请求已抢先预留 Buffer {}；当前请求未发送交易，请重新进入恢复流程",
+                stored.buffer_address
+            )));
+        }
+        deployment_journal = Some(stored);
+    }
+
+    if let (Some(instructions), Some(buffer_signer)) =
+        (create_buffer_ixs.as_ref(), buffer_keypair.as_ref())
+    {
+        let (blockhash, last_valid_block_height) = client
+            .get_latest_blockhash_with_commitment(CommitmentConfig::confirmed())
+            .map_err(|error| ApiError {
+                message: format!("获取 create-buffer blockhash 失败: {error}"),
+            })?;
+        let transaction = Transaction::new_signed_with_payer(
+            instructions,
+            Some(&payer_pubkey),
+            &[&payer, buffer_signer],
+            blockhash,
+        );
+        let local_signature = transaction
+            .signatures
+            .first()
+            .copied()
+            .ok_or_else(|| ApiError {
+                message: "create-buffer 交易缺少本地签名".to_string(),
+            })?;
+        let record = wallet_store::ProgramDeploymentRecord {
+            genesis_hash: genesis_hash.clone(),
+            program_id: program_id.to_string(),
+            program_sha256: program_sha256.clone(),
+            program_len: program_bytes.len(),
+            max_data_len,
+            upgrade_authority: expected_upgrade_authority.to_string(),
+            buffer_address: buffer_address.to_string(),
+            status: DEPLOYMENT_STATUS_CREATE_BUFFER_SIGNED.to_string(),
+            create_signature: Some(local_signature.to_string()),
+            create_last_valid_block_height: Some(last_valid_block_height),
+            last_write_signature: None,
+            last_write_chunk_index: None,
+            last_write_last_valid_block_height: None,
+            completed_writes: 0,
+            deploy_signature: None,
+            deploy_last_valid_block_height: None,
+            attempt_evidence_version: wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_EVIDENCE_VERSION,
+            revision: 0,
+            created_at: 0,
+            updated_at: 0,
+        };
+        let create_attempt = new_program_deployment_attempt(
+            &record,
+            wallet_store::PROGRAM_DEPLOYMENT_STAGE_CREATE_BUFFER,
+            None,
+            &local_signature,
+            last_valid_block_height,
+        );
+        if rotating_create_buffer {
+            let current = deployment_journal
+                .as_ref()
+                .ok_or_else(|| deployment_journal_error("Buffer 轮换时部署 journal 不存在"))?;
+            let stored = wallet_store::rotate_program_deployment_create_attempt(
+                current,
+                create_attempt,
+                DEPLOYMENT_STATUS_CREATE_BUFFER_SIGNED,
+            )
+            .map_err(|message| ApiError { message })?;
+            deployment_journal = Some(stored);
+        } else {
+            let (stored, inserted) =
+                wallet_store::reserve_program_deployment_with_attempt(record, create_attempt)
+                    .map_err(|message| ApiError { message })?;
+            if !inserted {
+                let binding_matches = validate_deployment_journal_binding(
+                    &stored,
+                    &genesis_hash,
+                    &program_id,
+                    &program_sha256,
+                    program_bytes.len(),
+                    max_data_len,
+                    &expected_upgrade_authority,
+                    None,
+                )
+                .is_ok();
+                if !binding_matches {
+                    let record = wallet_store::ProgramDeploymentRecord {
+                        genesis_hash: genesis_hash.clone(),
+                        program_id: program_id.to_string(),
+                        program_sha256: program_sha256.clone(),
+                        program_len: program_bytes.len(),
                         max_data_len,
                         upgrade_authority: expected_upgrade_authority.to_string(),
                         buffer_address: buffer_address.to_string(),
@@ -11344,1174 +13424,1897 @@ async fn deploy_program(
         }
     }
 
-    let create_buffer_signature = deployment_journal
-        .as_ref()
-        .and_then(|record| record.create_signature.clone());
+    let create_buffer_signature = deployment_journal
+        .as_ref()
+        .and_then(|record| record.create_signature.clone());
+
+    let mut write_signatures = Vec::with_capacity(write_plan.pending_chunk_indexes.len());
+    let completed_writes = write_plan.completed_chunks;
+    let total_pending_writes = write_plan.pending_chunk_indexes.len();
+    for (position, index) in write_plan.pending_chunk_indexes.iter().copied().enumerate() {
+        let start = index
+            .checked_mul(PROGRAM_WRITE_CHUNK_BYTES)
+            .ok_or_else(|| ApiError {
+                message: "Program 写入偏移超出范围".to_string(),
+            })?;
+        let end = start
+            .saturating_add(PROGRAM_WRITE_CHUNK_BYTES)
+            .min(program_bytes.len());
+        let chunk = program_bytes.get(start..end).ok_or_else(|| ApiError {
+            message: "Program 写入块超出制品范围".to_string(),
+        })?;
+        let offset = u32::try_from(start).map_err(|_| ApiError {
+            message: "Program 写入偏移超出范围".to_string(),
+        })?;
+        let write_ix =
+            loader_v3_instruction::write(&buffer_address, &payer_pubkey, offset, chunk.to_vec());
+        let (blockhash, write_last_valid_block_height) = client
+            .get_latest_blockhash_with_commitment(CommitmentConfig::confirmed())
+            .map_err(|error| ApiError {
+                message: format!("获取 write-buffer blockhash 失败: {error}"),
+            })?;
+        let transaction = Transaction::new_signed_with_payer(
+            &[write_ix],
+            Some(&payer_pubkey),
+            &[&payer],
+            blockhash,
+        );
+        let local_signature = transaction
+            .signatures
+            .first()
+            .copied()
+            .ok_or_else(|| ApiError {
+                message: "write-buffer 交易缺少本地签名".to_string(),
+            })?;
+        let attempt = new_program_deployment_attempt(
+            deployment_journal.as_ref().ok_or_else(|| ApiError {
+                message: "部署 journal 不存在，拒绝写入 Buffer".to_string(),
+            })?,
+            wallet_store::PROGRAM_DEPLOYMENT_STAGE_WRITE,
+            Some(index),
+            &local_signature,
+            write_last_valid_block_height,
+        );
+        begin_deployment_attempt(
+            &mut deployment_journal,
+            attempt,
+            DEPLOYMENT_STATUS_WRITE_SIGNED,
+            completed_writes,
+        )?;
+        let context = format!(
+            "Program ID: {program_id}；Buffer: {buffer_address}；写入进度: {}/{}；chunk index: {}",
+            position + 1,
+            total_pending_writes,
+            index
+        );
+        let submission = submit_signed_transaction_once(
+            &client,
+            &transaction,
+            "写入 Buffer",
+            &context,
+            CommitmentConfig::confirmed(),
+            "confirmed",
+            Duration::from_secs(120),
+        )
+        .await;
+        match submission {
+            Ok((signature, _)) => {
+                if signature != local_signature {
+                    return Err(deployment_journal_error(format!(
+                        "write chunk {} 的 RPC 签名与本地已持久化签名不一致",
+                        index
+                    )));
+                }
+                transition_deployment_attempt(
+                    &mut deployment_journal,
+                    &local_signature.to_string(),
+                    wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_SIGNED,
+                    wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_CONFIRMED,
+                    DEPLOYMENT_STATUS_WRITE_CONFIRMED,
+                    completed_writes,
+                )?;
+                write_signatures.push(signature.to_string());
+            }
+            Err(error) => {
+                let journal_error = transition_deployment_attempt(
+                    &mut deployment_journal,
+                    &local_signature.to_string(),
+                    wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_SIGNED,
+                    wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_REQUIRES_RECONCILIATION,
+                    DEPLOYMENT_STATUS_WRITE_RECONCILE,
+                    completed_writes,
+                )
+                .err()
+                .map(|journal_error| format!("；journal 更新失败: {}", journal_error.message))
+                .unwrap_or_default();
+                return Err(ApiError {
+                    message: format!(
+                        "{}；Buffer {} 的 chunk {} 已记录本地签名 {}{}",
+                        error.message, buffer_address, index, local_signature, journal_error
+                    ),
+                });
+            }
+        }
+    }
+
+    let buffer_finalized_deadline = Instant::now() + Duration::from_secs(120);
+    let finalized_completed_writes = loop {
+        let response = client
+            .get_account_with_commitment(&buffer_address, CommitmentConfig::finalized())
+            .map_err(|error| ApiError {
+                message: format!(
+                    "部署前 finalized 回读 Buffer {} 失败: {error}",
+                    buffer_address
+                ),
+            })?;
+        let account = response.value.ok_or_else(|| ApiError {
+            message: format!("部署前 Buffer {} 不存在", buffer_address),
+        })?;
+        let finalized_plan = program_deploy::verify_resume_buffer(
+            &payer_pubkey,
+            &program_bytes,
+            PROGRAM_WRITE_CHUNK_BYTES,
+            &account.owner,
+            account.executable,
+            &account.data,
+        )
+        .map_err(|error| ApiError {
+            message: format!("部署前 Buffer {} 回读校验失败: {error}", buffer_address),
+        })?;
+        if finalized_plan.pending_chunk_indexes.is_empty() {
+            break finalized_plan.completed_chunks;
+        }
+        if Instant::now() >= buffer_finalized_deadline {
+            return Err(ApiError {
+                message: format!(
+                    "Buffer {} 仍有 {} 个块未 finalized；请保留该地址并使用恢复部署，不能盲目创建新 Buffer",
+                    buffer_address,
+                    finalized_plan.pending_chunk_indexes.len()
+                ),
+            });
+        }
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    };
+    let current_journal = deployment_journal.as_ref().ok_or_else(|| ApiError {
+        message: "部署 journal 不存在，拒绝完成 Buffer 回读".to_string(),
+    })?;
+    let active_attempts = wallet_store::load_active_program_deployment_attempts(
+        &current_journal.genesis_hash,
+        &current_journal.program_id,
+    )
+    .map_err(|message| ApiError { message })?;
+    for attempt in active_attempts
+        .iter()
+        .filter(|attempt| attempt.stage == wallet_store::PROGRAM_DEPLOYMENT_STAGE_WRITE)
+    {
+        transition_deployment_attempt(
+            &mut deployment_journal,
+            &attempt.signature,
+            &attempt.status,
+            wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_FINALIZED,
+            DEPLOYMENT_STATUS_WRITE_CONFIRMED,
+            finalized_completed_writes,
+        )?;
+    }
+    transition_deployment_journal(
+        &mut deployment_journal,
+        DEPLOYMENT_STATUS_BUFFER_FINALIZED,
+        None,
+        None,
+        None,
+        None,
+        None,
+        finalized_completed_writes,
+        None,
+        None,
+    )?;
+
+    let (blockhash, deploy_last_valid_block_height) = client
+        .get_latest_blockhash_with_commitment(CommitmentConfig::confirmed())
+        .map_err(|error| ApiError {
+            message: format!("获取 deploy blockhash 失败: {error}"),
+        })?;
+    let deploy_transaction = Transaction::new_signed_with_payer(
+        &deploy_ixs,
+        Some(&payer_pubkey),
+        &[&payer, &program_keypair],
+        blockhash,
+    );
+    let local_deploy_signature =
+        deploy_transaction
+            .signatures
+            .first()
+            .copied()
+            .ok_or_else(|| ApiError {
+                message: "deploy 交易缺少本地签名".to_string(),
+            })?;
+    simulate_program_deployment_transaction(&client, &deploy_transaction, "deploy")?;
+    let deploy_attempt = new_program_deployment_attempt(
+        deployment_journal.as_ref().ok_or_else(|| ApiError {
+            message: "部署 journal 不存在，拒绝记录 deploy attempt".to_string(),
+        })?,
+        wallet_store::PROGRAM_DEPLOYMENT_STAGE_DEPLOY,
+        None,
+        &local_deploy_signature,
+        deploy_last_valid_block_height,
+    );
+    begin_deployment_attempt(
+        &mut deployment_journal,
+        deploy_attempt,
+        DEPLOYMENT_STATUS_DEPLOY_SIGNED,
+        finalized_completed_writes,
+    )?;
+    let deploy_context = format!("Program ID: {program_id}；Buffer: {buffer_address}");
+    let deploy_submission = submit_signed_transaction_once(
+        &client,
+        &deploy_transaction,
+        "部署",
+        &deploy_context,
+        CommitmentConfig::finalized(),
+        "finalized",
+        Duration::from_secs(90),
+    )
+    .await;
+    let (deploy_signature, finalized_slot) = match deploy_submission {
+        Ok(result) => result,
+        Err(error) => {
+            let journal_error = transition_deployment_attempt(
+                &mut deployment_journal,
+                &local_deploy_signature.to_string(),
+                wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_SIGNED,
+                wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_REQUIRES_RECONCILIATION,
+                DEPLOYMENT_STATUS_DEPLOY_RECONCILE,
+                finalized_completed_writes,
+            )
+            .err()
+            .map(|journal_error| format!("；journal 更新失败: {}", journal_error.message))
+            .unwrap_or_default();
+            return Err(ApiError {
+                message: format!(
+                    "{}；Program {} 已记录本地部署签名 {}，last valid block height {}{}",
+                    error.message,
+                    program_id,
+                    local_deploy_signature,
+                    deploy_last_valid_block_height,
+                    journal_error
+                ),
+            });
+        }
+    };
+    if deploy_signature != local_deploy_signature {
+        return Err(deployment_journal_error(
+            "RPC 返回的 deploy 签名与本地已持久化签名不一致",
+        ));
+    }
+    transition_deployment_attempt(
+        &mut deployment_journal,
+        &local_deploy_signature.to_string(),
+        wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_SIGNED,
+        wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_CONFIRMED,
+        DEPLOYMENT_STATUS_DEPLOY_FINALIZED,
+        finalized_completed_writes,
+    )?;
+    let readback = wait_for_finalized_deployment_readback(
+        &client,
+        &program_id,
+        &programdata_address,
+        &expected_upgrade_authority,
+        &program_bytes,
+        max_data_len,
+        finalized_slot,
+    )
+    .await
+    .map_err(|error| ApiError {
+        message: format!(
+            "{}；Program ID: {}，部署签名: {}",
+            error.message, program_id, deploy_signature
+        ),
+    })?;
+    if readback.program_sha256 != program_sha256 {
+        return Err(ApiError {
+            message: "finalized 回读 SHA-256 与本地已验证哈希不一致".to_string(),
+        });
+    }
+    transition_deployment_attempt(
+        &mut deployment_journal,
+        &local_deploy_signature.to_string(),
+        wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_CONFIRMED,
+        wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_FINALIZED,
+        DEPLOYMENT_STATUS_FINALIZED,
+        finalized_completed_writes,
+    )?;
+    let (journal_revision, attempt_evidence_version, deployment_attempts) =
+        load_deployment_receipt_evidence(deployment_journal.as_ref())?;
+
+    Ok(Json(DeployProgramResponse {
+        program_id: program_id.to_string(),
+        programdata_address: programdata_address.to_string(),
+        buffer_address: buffer_address.to_string(),
+        authority: readback.upgrade_authority.to_string(),
+        network,
+        genesis_hash,
+        program_bytes: program_bytes.len(),
+        max_data_len,
+        program_sha256,
+        temporary_buffer_rent_lamports: buffer_lamports,
+        program_rent_lamports: program_lamports,
+        programdata_rent_lamports: programdata_lamports,
+        rent_lamports: final_rent_lamports,
+        estimated_transaction_fees_lamports,
+        fee_rate_reserve_lamports: fee_budget.fee_rate_reserve_lamports,
+        recovery_write_reserve_lamports: fee_budget.recovery_write_reserve_lamports,
+        total_fee_budget_lamports: fee_budget.total_fee_budget_lamports,
+        estimated_required_balance_lamports,
+        create_buffer_signature,
+        skipped_write_chunks: write_plan.completed_chunks,
+        write_signatures,
+        deploy_signature: deploy_signature.to_string(),
+        finalized_slot,
+        deployed_slot: readback.deployed_slot,
+        readback_verified: true,
+        journal_revision,
+        attempt_evidence_version,
+        deployment_attempts,
+        status: "finalized".to_string(),
+    }))
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct UpgradeProgramRequest {
+    #[serde(default)]
+    wallet_id: Option<String>,
+    #[serde(default)]
+    private_key: Option<String>,
+    #[serde(default)]
+    secret_key: Option<String>,
+    #[serde(default)]
+    keystore_json: Option<String>,
+    #[serde(default)]
+    encrypted_key: Option<String>,
+    #[serde(default)]
+    password: Option<String>,
+    program_id: String,
+    expected_upgrade_authority: String,
+    expected_genesis_hash: String,
+    expected_program_sha256: String,
+    program_so_base64: String,
+    network: String,
+    #[serde(default)]
+    spill_address: Option<String>,
+}
+
+impl UpgradeProgramRequest {
+    fn take_wallet_auth(&mut self) -> WalletAuthRequest {
+        WalletAuthRequest {
+            wallet_id: self.wallet_id.take(),
+            private_key: self.private_key.take(),
+            secret_key: self.secret_key.take(),
+            keystore_json: self.keystore_json.take(),
+            encrypted_key: self.encrypted_key.take(),
+            password: self.password.take(),
+        }
+    }
+}
+
+impl Drop for UpgradeProgramRequest {
+    fn drop(&mut self) {
+        self.take_wallet_auth().clear_secrets();
+    }
+}
+
+#[derive(Serialize)]
+struct UpgradeProgramResponse {
+    program_id: String,
+    programdata_address: String,
+    buffer_address: String,
+    authority: String,
+    network: String,
+    genesis_hash: String,
+    program_bytes: usize,
+    program_sha256: String,
+    rent_lamports: u64,
+    create_buffer_signature: String,
+    write_signatures: Vec<String>,
+    upgrade_signature: String,
+    deployed_slot: u64,
+    readback_verified: bool,
+    status: String,
+}
+
+async fn upgrade_generic_program(
+    Json(req): Json<UpgradeProgramRequest>,
+) -> Result<Json<UpgradeProgramResponse>, ApiError> {
+    upgrade_program(Json(req)).await
+}
+
+async fn program_upgrade_progress() -> Json<ProgramUpgradeProgress> {
+    let store = program_upgrade_progress_store();
+    let progress = store.lock().await.clone();
+    Json(progress)
+}
+
+async fn upgrade_program(
+    Json(mut req): Json<UpgradeProgramRequest>,
+) -> Result<Json<UpgradeProgramResponse>, ApiError> {
+    let _deploy_guard = PROGRAM_DEPLOY_LOCK
+        .get_or_init(|| tokio::sync::Mutex::new(()))
+        .try_lock()
+        .map_err(|_| ApiError {
+            message: PROGRAM_DEPLOY_BUSY_MESSAGE.to_string(),
+        })?;
+
+    let result = upgrade_program_inner(&mut req).await;
+    finish_program_upgrade_progress(&result).await;
+    result
+}
+
+async fn upgrade_program_inner(
+    req: &mut UpgradeProgramRequest,
+) -> Result<Json<UpgradeProgramResponse>, ApiError> {
+    let program_id = Pubkey::from_str(req.program_id.trim()).map_err(|_| ApiError {
+        message: "无效的 Program ID".to_string(),
+    })?;
+    let expected_upgrade_authority = Pubkey::from_str(req.expected_upgrade_authority.trim())
+        .map_err(|_| ApiError {
+            message: "无效的预期 Upgrade Authority".to_string(),
+        })?;
+
+    let program_bytes = decode_program_binary_base64(&req.program_so_base64)?;
+    let write_chunk_count = program_bytes.chunks(PROGRAM_WRITE_CHUNK_BYTES).count();
+    begin_program_upgrade_progress(
+        &program_id.to_string(),
+        req.network.trim(),
+        program_bytes.len(),
+        write_chunk_count,
+    )
+    .await;
+
+    let program_sha256 =
+        program_deploy::require_sha256(&program_bytes, &req.expected_program_sha256)
+            .map_err(|message| ApiError { message })?;
+    program_deploy::require_anchor_declared_program_id(&program_bytes, &program_id)
+        .map_err(|message| ApiError { message })?;
+    publish_program_upgrade_progress(|progress| {
+        progress.stage = "verifying".to_string();
+        progress.message = "正在离线校验 SBF 程序制品".to_string();
+    })
+    .await;
+    let program_bytes = verify_program_binary_offline(program_bytes).await?;
 
-    let mut write_signatures = Vec::with_capacity(write_plan.pending_chunk_indexes.len());
-    let completed_writes = write_plan.completed_chunks;
-    let total_pending_writes = write_plan.pending_chunk_indexes.len();
-    for (position, index) in write_plan.pending_chunk_indexes.iter().copied().enumerate() {
-        let start = index
-            .checked_mul(PROGRAM_WRITE_CHUNK_BYTES)
-            .ok_or_else(|| ApiError {
-                message: "Program 写入偏移超出范围".to_string(),
-            })?;
-        let end = start
-            .saturating_add(PROGRAM_WRITE_CHUNK_BYTES)
-            .min(program_bytes.len());
-        let chunk = program_bytes.get(start..end).ok_or_else(|| ApiError {
-            message: "Program 写入块超出制品范围".to_string(),
-        })?;
-        let offset = u32::try_from(start).map_err(|_| ApiError {
-            message: "Program 写入偏移超出范围".to_string(),
-        })?;
-        let write_ix =
-            loader_v3_instruction::write(&buffer_address, &payer_pubkey, offset, chunk.to_vec());
-        let (blockhash, write_last_valid_block_height) = client
-            .get_latest_blockhash_with_commitment(CommitmentConfig::confirmed())
-            .map_err(|error| ApiError {
-                message: format!("获取 write-buffer blockhash 失败: {error}"),
-            })?;
-        let transaction = Transaction::new_signed_with_payer(
-            &[write_ix],
-            Some(&payer_pubkey),
-            &[&payer],
-            blockhash,
-        );
-        let local_signature = transaction
-            .signatures
-            .first()
-            .copied()
-            .ok_or_else(|| ApiError {
-                message: "write-buffer 交易缺少本地签名".to_string(),
-            })?;
-        let attempt = new_program_deployment_attempt(
-            deployment_journal.as_ref().ok_or_else(|| ApiError {
-                message: "部署 journal 不存在，拒绝写入 Buffer".to_string(),
-            })?,
-            wallet_store::PROGRAM_DEPLOYMENT_STAGE_WRITE,
-            Some(index),
-            &local_signature,
-            write_last_valid_block_height,
-        );
-        begin_deployment_attempt(
-            &mut deployment_journal,
-            attempt,
-            DEPLOYMENT_STATUS_WRITE_SIGNED,
-            completed_writes,
-        )?;
-        let context = format!(
-            "Program ID: {program_id}；Buffer: {buffer_address}；写入进度: {}/{}；chunk index: {}",
-            position + 1,
-            total_pending_writes,
-            index
-        );
-        let submission = submit_signed_transaction_once(
-            &client,
-            &transaction,
-            "写入 Buffer",
-            &context,
-            CommitmentConfig::confirmed(),
-            "confirmed",
-            Duration::from_secs(120),
-        )
-        .await;
-        match submission {
-            Ok((signature, _)) => {
-                if signature != local_signature {
-                    return Err(deployment_journal_error(format!(
-                        "write chunk {} 的 RPC 签名与本地已持久化签名不一致",
-                        index
-                    )));
-                }
-                transition_deployment_attempt(
-                    &mut deployment_journal,
-                    &local_signature.to_string(),
-                    wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_SIGNED,
-                    wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_CONFIRMED,
-                    DEPLOYMENT_STATUS_WRITE_CONFIRMED,
-                    completed_writes,
-                )?;
-                write_signatures.push(signature.to_string());
-            }
-            Err(error) => {
-                let journal_error = transition_deployment_attempt(
-                    &mut deployment_journal,
-                    &local_signature.to_string(),
-                    wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_SIGNED,
-                    wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_REQUIRES_RECONCILIATION,
-                    DEPLOYMENT_STATUS_WRITE_RECONCILE,
-                    completed_writes,
-                )
-                .err()
-                .map(|journal_error| format!("；journal 更新失败: {}", journal_error.message))
-                .unwrap_or_default();
-                return Err(ApiError {
-                    message: format!(
-                        "{}；Buffer {} 的 chunk {} 已记录本地签名 {}{}",
-                        error.message, buffer_address, index, local_signature, journal_error
-                    ),
-                });
-            }
-        }
+    if req.network.trim().is_empty() {
+        return Err(ApiError {
+            message: "升级网络不能为空".to_string(),
+        });
     }
+    let selector = rpc_selector(Some(req.network.trim()))?;
+    let rpc_url = selector.url;
+    let network = selector.network;
+    publish_program_upgrade_progress(|progress| {
+        progress.network = network.clone();
+        progress.message = format!("正在连接 RPC 并校验网络（{network}）");
+    })
+    .await;
+    let client = RpcClient::new_with_timeout_and_commitment(
+        rpc_url.to_string(),
+        Duration::from_secs(PROGRAM_DEPLOY_RPC_TIMEOUT_SECS),
+        CommitmentConfig::confirmed(),
+    );
 
-    let buffer_finalized_deadline = Instant::now() + Duration::from_secs(120);
-    let finalized_completed_writes = loop {
-        let response = client
-            .get_account_with_commitment(&buffer_address, CommitmentConfig::finalized())
-            .map_err(|error| ApiError {
-                message: format!(
-                    "部署前 finalized 回读 Buffer {} 失败: {error}",
-                    buffer_address
-                ),
-            })?;
-        let account = response.value.ok_or_else(|| ApiError {
-            message: format!("部署前 Buffer {} 不存在", buffer_address),
+    let expected_genesis_hash = solana_sdk::hash::Hash::from_str(req.expected_genesis_hash.trim())
+        .map_err(|_| ApiError {
+            message: "无效的预期 genesis hash".to_string(),
         })?;
-        let finalized_plan = program_deploy::verify_resume_buffer(
-            &payer_pubkey,
-            &program_bytes,
-            PROGRAM_WRITE_CHUNK_BYTES,
-            &account.owner,
-            account.executable,
-            &account.data,
-        )
+    let actual_genesis_hash = client.get_genesis_hash().map_err(|error| ApiError {
+        message: format!("读取 RPC genesis hash 失败: {error}"),
+    })?;
+    if actual_genesis_hash != expected_genesis_hash {
+        return Err(ApiError {
+            message: format!(
+                "RPC genesis hash 为 {}，预期为 {}；已在花费前中止升级",
+                actual_genesis_hash, expected_genesis_hash
+            ),
+        });
+    }
+    let genesis_hash = actual_genesis_hash.to_string();
+
+    let wallet_auth = req.take_wallet_auth();
+    let payer = wallet_auth.keypair()?;
+    let payer_pubkey = payer.pubkey();
+    if payer_pubkey != expected_upgrade_authority {
+        return Err(ApiError {
+            message: format!(
+                "签名钱包 {} 与预期 Upgrade Authority {} 不一致",
+                payer_pubkey, expected_upgrade_authority
+            ),
+        });
+    }
+
+    let programdata_address =
+        require_program_upgrade_authority(&client, &program_id, &payer_pubkey)?;
+    let programdata_account = client
+        .get_account_with_commitment(&programdata_address, CommitmentConfig::confirmed())
         .map_err(|error| ApiError {
-            message: format!("部署前 Buffer {} 回读校验失败: {error}", buffer_address),
+            message: format!("读取 ProgramData 失败: {error}"),
+        })?
+        .value
+        .ok_or_else(|| ApiError {
+            message: format!("ProgramData {} 不存在", programdata_address),
         })?;
-        if finalized_plan.pending_chunk_indexes.is_empty() {
-            break finalized_plan.completed_chunks;
-        }
-        if Instant::now() >= buffer_finalized_deadline {
-            return Err(ApiError {
-                message: format!(
-                    "Buffer {} 仍有 {} 个块未 finalized；请保留该地址并使用恢复部署，不能盲目创建新 Buffer",
-                    buffer_address,
-                    finalized_plan.pending_chunk_indexes.len()
-                ),
-            });
-        }
-        tokio::time::sleep(Duration::from_millis(500)).await;
-    };
-    let current_journal = deployment_journal.as_ref().ok_or_else(|| ApiError {
-        message: "部署 journal 不存在，拒绝完成 Buffer 回读".to_string(),
-    })?;
-    let active_attempts = wallet_store::load_active_program_deployment_attempts(
-        &current_journal.genesis_hash,
-        &current_journal.program_id,
-    )
-    .map_err(|message| ApiError { message })?;
-    for attempt in active_attempts
-        .iter()
-        .filter(|attempt| attempt.stage == wallet_store::PROGRAM_DEPLOYMENT_STAGE_WRITE)
-    {
-        transition_deployment_attempt(
-            &mut deployment_journal,
-            &attempt.signature,
-            &attempt.status,
-            wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_FINALIZED,
-            DEPLOYMENT_STATUS_WRITE_CONFIRMED,
-            finalized_completed_writes,
-        )?;
+    let metadata_len = UpgradeableLoaderState::size_of_programdata_metadata();
+    if programdata_account.data.len() < metadata_len {
+        return Err(ApiError {
+            message: "ProgramData 账户长度不足".to_string(),
+        });
+    }
+    let max_program_len = programdata_account.data.len() - metadata_len;
+    if program_bytes.len() > max_program_len {
+        return Err(ApiError {
+            message: format!(
+                "新 .so 长度为 {} bytes，超过 ProgramData 容量 {} bytes；无法用当前 Program ID 升级，请重新部署更大 max_data_len 的 Program",
+                program_bytes.len(),
+                max_program_len
+            ),
+        });
     }
-    transition_deployment_journal(
-        &mut deployment_journal,
-        DEPLOYMENT_STATUS_BUFFER_FINALIZED,
-        None,
-        None,
-        None,
-        None,
-        None,
-        finalized_completed_writes,
-        None,
-        None,
-    )?;
 
-    let (blockhash, deploy_last_valid_block_height) = client
-        .get_latest_blockhash_with_commitment(CommitmentConfig::confirmed())
+    let spill = if let Some(spill) = req
+        .spill_address
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+    {
+        Pubkey::from_str(spill).map_err(|_| ApiError {
+            message: "无效的 spill 地址".to_string(),
+        })?
+    } else {
+        payer_pubkey
+    };
+
+    let buffer_keypair = Keypair::new();
+    let buffer_lamports = client
+        .get_minimum_balance_for_rent_exemption(UpgradeableLoaderState::size_of_buffer(
+            program_bytes.len(),
+        ))
         .map_err(|error| ApiError {
-            message: format!("获取 deploy blockhash 失败: {error}"),
+            message: format!("计算 buffer 租金失败: {error}"),
         })?;
-    let deploy_transaction = Transaction::new_signed_with_payer(
-        &deploy_ixs,
-        Some(&payer_pubkey),
-        &[&payer, &program_keypair],
-        blockhash,
-    );
-    let local_deploy_signature =
-        deploy_transaction
-            .signatures
-            .first()
-            .copied()
-            .ok_or_else(|| ApiError {
-                message: "deploy 交易缺少本地签名".to_string(),
-            })?;
-    simulate_program_deployment_transaction(&client, &deploy_transaction, "deploy")?;
-    let deploy_attempt = new_program_deployment_attempt(
-        deployment_journal.as_ref().ok_or_else(|| ApiError {
-            message: "部署 journal 不存在，拒绝记录 deploy attempt".to_string(),
-        })?,
-        wallet_store::PROGRAM_DEPLOYMENT_STAGE_DEPLOY,
-        None,
-        &local_deploy_signature,
-        deploy_last_valid_block_height,
-    );
-    begin_deployment_attempt(
-        &mut deployment_journal,
-        deploy_attempt,
-        DEPLOYMENT_STATUS_DEPLOY_SIGNED,
-        finalized_completed_writes,
-    )?;
-    let deploy_context = format!("Program ID: {program_id}；Buffer: {buffer_address}");
-    let deploy_submission = submit_signed_transaction_once(
+    // create buffer + write chunks + upgrade; keep a small SOL reserve for fee spikes
+    let estimated_fee_lamports = (write_chunk_count.saturating_add(2).saturating_mul(10_000))
+        .saturating_add(50_000_000) as u64;
+    let estimated_required_balance_lamports = buffer_lamports
+        .checked_add(estimated_fee_lamports)
+        .ok_or_else(|| ApiError {
+        message: "升级所需余额总额溢出".to_string(),
+    })?;
+    let payer_balance = client
+        .get_balance(&payer_pubkey)
+        .map_err(|error| ApiError {
+            message: format!("查询升级钱包余额失败: {error}"),
+        })?;
+    if payer_balance < estimated_required_balance_lamports {
+        return Err(ApiError {
+            message: format!(
+                "升级钱包余额不足：当前 {:.4} SOL（{} lamports），至少需要约 {:.4} SOL（{} lamports）。其中升级 Buffer 租金约 {:.4} SOL，交易费预留约 {:.4} SOL。Buffer 租金升级成功后会退回 spill 地址，但创建 Buffer 时必须先备足余额。",
+                payer_balance as f64 / 1_000_000_000.0,
+                payer_balance,
+                estimated_required_balance_lamports as f64 / 1_000_000_000.0,
+                estimated_required_balance_lamports,
+                buffer_lamports as f64 / 1_000_000_000.0,
+                estimated_fee_lamports as f64 / 1_000_000_000.0
+            ),
+        });
+    }
+    publish_program_upgrade_progress(|progress| {
+        progress.stage = "creating_buffer".to_string();
+        progress.message = format!("正在创建升级 Buffer（{}）", buffer_keypair.pubkey());
+        progress.buffer_address = Some(buffer_keypair.pubkey().to_string());
+        progress.write_total = write_chunk_count;
+        progress.program_bytes = program_bytes.len();
+    })
+    .await;
+    let create_buffer_ixs = loader_v3_instruction::create_buffer(
+        &payer_pubkey,
+        &buffer_keypair.pubkey(),
+        &payer_pubkey,
+        buffer_lamports,
+        program_bytes.len(),
+    )
+    .map_err(|error| ApiError {
+        message: format!("创建 buffer 指令失败: {error}"),
+    })?;
+    let create_buffer_signature = sign_and_send_with_commitment(
         &client,
-        &deploy_transaction,
-        "部署",
-        &deploy_context,
+        create_buffer_ixs,
+        &[&payer, &buffer_keypair],
+        &payer_pubkey,
+        "创建升级 Buffer",
         CommitmentConfig::finalized(),
         "finalized",
         Duration::from_secs(90),
     )
-    .await;
-    let (deploy_signature, finalized_slot) = match deploy_submission {
-        Ok(result) => result,
-        Err(error) => {
-            let journal_error = transition_deployment_attempt(
-                &mut deployment_journal,
-                &local_deploy_signature.to_string(),
-                wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_SIGNED,
-                wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_REQUIRES_RECONCILIATION,
-                DEPLOYMENT_STATUS_DEPLOY_RECONCILE,
-                finalized_completed_writes,
-            )
-            .err()
-            .map(|journal_error| format!("；journal 更新失败: {}", journal_error.message))
-            .unwrap_or_default();
-            return Err(ApiError {
-                message: format!(
-                    "{}；Program {} 已记录本地部署签名 {}，last valid block height {}{}",
-                    error.message,
-                    program_id,
-                    local_deploy_signature,
-                    deploy_last_valid_block_height,
-                    journal_error
-                ),
-            });
-        }
-    };
-    if deploy_signature != local_deploy_signature {
-        return Err(deployment_journal_error(
-            "RPC 返回的 deploy 签名与本地已持久化签名不一致",
-        ));
+    .await?
+    .0;
+
+    let mut write_signatures = Vec::with_capacity(write_chunk_count);
+    for (index, chunk) in program_bytes.chunks(PROGRAM_WRITE_CHUNK_BYTES).enumerate() {
+        let offset = index
+            .checked_mul(PROGRAM_WRITE_CHUNK_BYTES)
+            .and_then(|value| u32::try_from(value).ok())
+            .ok_or_else(|| ApiError {
+                message: "Program 写入偏移超出范围".to_string(),
+            })?;
+        let write_ix = loader_v3_instruction::write(
+            &buffer_keypair.pubkey(),
+            &payer_pubkey,
+            offset,
+            chunk.to_vec(),
+        );
+        publish_program_upgrade_progress(|progress| {
+            progress.stage = "writing".to_string();
+            progress.write_completed = index;
+            progress.write_total = write_chunk_count;
+            progress.message = format!(
+                "正在写入升级 Buffer：{}/{}（约 {:.1}%）",
+                index + 1,
+                write_chunk_count,
+                ((index as f64) / (write_chunk_count.max(1) as f64)) * 100.0
+            );
+            progress.buffer_address = Some(buffer_keypair.pubkey().to_string());
+        })
+        .await;
+        tracing::info!(
+            "program upgrade write chunk {}/{} for {} ({} bytes)",
+            index + 1,
+            write_chunk_count,
+            program_id,
+            chunk.len()
+        );
+        let write_signature = sign_and_send_with_commitment(
+            &client,
+            vec![write_ix],
+            &[&payer],
+            &payer_pubkey,
+            &format!("写入升级 Buffer ({}/{})", index + 1, write_chunk_count),
+            CommitmentConfig::confirmed(),
+            "confirmed",
+            Duration::from_secs(120),
+        )
+        .await?
+        .0;
+        publish_program_upgrade_progress(|progress| {
+            progress.write_completed = index + 1;
+            progress.last_signature = Some(write_signature.clone());
+            progress.message = format!(
+                "已写入升级 Buffer：{}/{}（约 {:.1}%）",
+                index + 1,
+                write_chunk_count,
+                ((index + 1) as f64 / (write_chunk_count.max(1) as f64)) * 100.0
+            );
+        })
+        .await;
+        write_signatures.push(write_signature);
     }
-    transition_deployment_attempt(
-        &mut deployment_journal,
-        &local_deploy_signature.to_string(),
-        wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_SIGNED,
-        wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_CONFIRMED,
-        DEPLOYMENT_STATUS_DEPLOY_FINALIZED,
-        finalized_completed_writes,
-    )?;
-    let readback = wait_for_finalized_deployment_readback(
+
+    publish_program_upgrade_progress(|progress| {
+        progress.stage = "upgrading".to_string();
+        progress.message = "正在提交 upgradeable-loader 升级交易".to_string();
+        progress.write_completed = write_chunk_count;
+    })
+    .await;
+    let upgrade_ix =
+        squads_v4::upgrade_program_ix(&program_id, &buffer_keypair.pubkey(), &payer_pubkey, &spill);
+    let (upgrade_signature, upgrade_slot) = sign_and_send_with_commitment(
         &client,
-        &program_id,
-        &programdata_address,
-        &expected_upgrade_authority,
-        &program_bytes,
-        max_data_len,
-        finalized_slot,
-    )
-    .await
-    .map_err(|error| ApiError {
-        message: format!(
-            "{}；Program ID: {}，部署签名: {}",
-            error.message, program_id, deploy_signature
-        ),
-    })?;
-    if readback.program_sha256 != program_sha256 {
-        return Err(ApiError {
-            message: "finalized 回读 SHA-256 与本地已验证哈希不一致".to_string(),
-        });
-    }
-    transition_deployment_attempt(
-        &mut deployment_journal,
-        &local_deploy_signature.to_string(),
-        wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_CONFIRMED,
-        wallet_store::PROGRAM_DEPLOYMENT_ATTEMPT_FINALIZED,
-        DEPLOYMENT_STATUS_FINALIZED,
-        finalized_completed_writes,
-    )?;
-    let (journal_revision, attempt_evidence_version, deployment_attempts) =
-        load_deployment_receipt_evidence(deployment_journal.as_ref())?;
+        vec![upgrade_ix],
+        &[&payer],
+        &payer_pubkey,
+        "提交 Program 升级",
+        CommitmentConfig::finalized(),
+        "finalized",
+        Duration::from_secs(90),
+    )
+    .await?;
 
-    Ok(Json(DeployProgramResponse {
+    publish_program_upgrade_progress(|progress| {
+        progress.stage = "verifying_readback".to_string();
+        progress.message = "正在 finalized 回读并校验链上 Program 数据".to_string();
+        progress.last_signature = Some(upgrade_signature.clone());
+    })
+    .await;
+    let readback = wait_for_finalized_deployment_readback(
+        &client,
+        &program_id,
+        &programdata_address,
+        &payer_pubkey,
+        &program_bytes,
+        max_program_len,
+        upgrade_slot,
+    )
+    .await?;
+
+    Ok(Json(UpgradeProgramResponse {
         program_id: program_id.to_string(),
         programdata_address: programdata_address.to_string(),
-        buffer_address: buffer_address.to_string(),
+        buffer_address: buffer_keypair.pubkey().to_string(),
         authority: readback.upgrade_authority.to_string(),
         network,
         genesis_hash,
         program_bytes: program_bytes.len(),
-        max_data_len,
         program_sha256,
-        temporary_buffer_rent_lamports: buffer_lamports,
-        program_rent_lamports: program_lamports,
-        programdata_rent_lamports: programdata_lamports,
-        rent_lamports: final_rent_lamports,
-        estimated_transaction_fees_lamports,
-        fee_rate_reserve_lamports: fee_budget.fee_rate_reserve_lamports,
-        recovery_write_reserve_lamports: fee_budget.recovery_write_reserve_lamports,
-        total_fee_budget_lamports: fee_budget.total_fee_budget_lamports,
-        estimated_required_balance_lamports,
+        rent_lamports: buffer_lamports,
         create_buffer_signature,
-        skipped_write_chunks: write_plan.completed_chunks,
         write_signatures,
-        deploy_signature: deploy_signature.to_string(),
-        finalized_slot,
+        upgrade_signature,
         deployed_slot: readback.deployed_slot,
         readback_verified: true,
-        journal_revision,
-        attempt_evidence_version,
-        deployment_attempts,
         status: "finalized".to_string(),
     }))
 }
 
 #[derive(Deserialize)]
-#[serde(deny_unknown_fields)]
-struct UpgradeProgramRequest {
-    #[serde(default)]
-    wallet_id: Option<String>,
-    #[serde(default)]
-    private_key: Option<String>,
-    #[serde(default)]
-    secret_key: Option<String>,
-    #[serde(default)]
-    keystore_json: Option<String>,
-    #[serde(default)]
-    encrypted_key: Option<String>,
-    #[serde(default)]
-    password: Option<String>,
+struct ProgramInfoRequest {
     program_id: String,
-    expected_upgrade_authority: String,
-    expected_genesis_hash: String,
-    expected_program_sha256: String,
-    program_so_base64: String,
-    network: String,
     #[serde(default)]
-    spill_address: Option<String>,
-}
-
-impl UpgradeProgramRequest {
-    fn take_wallet_auth(&mut self) -> WalletAuthRequest {
-        WalletAuthRequest {
-            wallet_id: self.wallet_id.take(),
-            private_key: self.private_key.take(),
-            secret_key: self.secret_key.take(),
-            keystore_json: self.keystore_json.take(),
-            encrypted_key: self.encrypted_key.take(),
-            password: self.password.take(),
-        }
-    }
-}
-
-impl Drop for UpgradeProgramRequest {
-    fn drop(&mut self) {
-        self.take_wallet_auth().clear_secrets();
-    }
+    network: Option<String>,
 }
 
 #[derive(Serialize)]
-struct UpgradeProgramResponse {
+struct ProgramInfoResponse {
     program_id: String,
     programdata_address: String,
-    buffer_address: String,
-    authority: String,
+    exists: bool,
+    executable: bool,
+    owner: String,
+    lamports: u64,
+    data_len: usize,
     network: String,
-    genesis_hash: String,
-    program_bytes: usize,
-    program_sha256: String,
-    rent_lamports: u64,
-    create_buffer_signature: String,
-    write_signatures: Vec<String>,
-    upgrade_signature: String,
-    deployed_slot: u64,
-    readback_verified: bool,
-    status: String,
-}
-
-async fn upgrade_generic_program(
-    Json(req): Json<UpgradeProgramRequest>,
-) -> Result<Json<UpgradeProgramResponse>, ApiError> {
-    upgrade_program(Json(req)).await
-}
-
-async fn program_upgrade_progress() -> Json<ProgramUpgradeProgress> {
-    let store = program_upgrade_progress_store();
-    let progress = store.lock().await.clone();
-    Json(progress)
-}
-
-async fn upgrade_program(
-    Json(mut req): Json<UpgradeProgramRequest>,
-) -> Result<Json<UpgradeProgramResponse>, ApiError> {
-    let _deploy_guard = PROGRAM_DEPLOY_LOCK
-        .get_or_init(|| tokio::sync::Mutex::new(()))
-        .try_lock()
-        .map_err(|_| ApiError {
-            message: PROGRAM_DEPLOY_BUSY_MESSAGE.to_string(),
-        })?;
-
-    let result = upgrade_program_inner(&mut req).await;
-    finish_program_upgrade_progress(&result).await;
-    result
 }
 
-async fn upgrade_program_inner(
-    req: &mut UpgradeProgramRequest,
-) -> Result<Json<UpgradeProgramResponse>, ApiError> {
+async fn program_info(
+    Json(req): Json<ProgramInfoRequest>,
+) -> Result<Json<ProgramInfoResponse>, ApiError> {
     let program_id = Pubkey::from_str(req.program_id.trim()).map_err(|_| ApiError {
         message: "无效的 Program ID".to_string(),
     })?;
-    let expected_upgrade_authority = Pubkey::from_str(req.expected_upgrade_authority.trim())
-        .map_err(|_| ApiError {
-            message: "无效的预期 Upgrade Authority".to_string(),
-        })?;
-
-    let program_bytes = decode_program_binary_base64(&req.program_so_base64)?;
-    let write_chunk_count = program_bytes.chunks(PROGRAM_WRITE_CHUNK_BYTES).count();
-    begin_program_upgrade_progress(
-        &program_id.to_string(),
-        req.network.trim(),
-        program_bytes.len(),
-        write_chunk_count,
-    )
-    .await;
-
-    let program_sha256 =
-        program_deploy::require_sha256(&program_bytes, &req.expected_program_sha256)
-            .map_err(|message| ApiError { message })?;
-    program_deploy::require_anchor_declared_program_id(&program_bytes, &program_id)
-        .map_err(|message| ApiError { message })?;
-    publish_program_upgrade_progress(|progress| {
-        progress.stage = "verifying".to_string();
-        progress.message = "正在离线校验 SBF 程序制品".to_string();
-    })
-    .await;
-    let program_bytes = verify_program_binary_offline(program_bytes).await?;
+    let rpc_url = get_rpc_url(req.network.as_deref())?;
+    let network = network_name(req.network.as_deref());
+    let client = RpcClient::new_with_commitment(rpc_url.to_string(), CommitmentConfig::confirmed());
+    let programdata_address = get_program_data_address(&program_id);
+    let Some(account) = client
+        .get_account_with_commitment(&program_id, CommitmentConfig::confirmed())
+        .map_err(|e| ApiError {
+            message: format!("查询 Program 失败: {}", e),
+        })?
+        .value
+    else {
+        return Ok(Json(ProgramInfoResponse {
+            program_id: program_id.to_string(),
+            programdata_address: programdata_address.to_string(),
+            exists: false,
+            executable: false,
+            owner: "".to_string(),
+            lamports: 0,
+            data_len: 0,
+            network,
+        }));
+    };
 
-    if req.network.trim().is_empty() {
-        return Err(ApiError {
-            message: "升级网络不能为空".to_string(),
-        });
-    }
-    let selector = rpc_selector(Some(req.network.trim()))?;
-    let rpc_url = selector.url;
-    let network = selector.network;
-    publish_program_upgrade_progress(|progress| {
-        progress.network = network.clone();
-        progress.message = format!("正在连接 RPC 并校验网络（{network}）");
-    })
-    .await;
-    let client = RpcClient::new_with_timeout_and_commitment(
-        rpc_url.to_string(),
-        Duration::from_secs(PROGRAM_DEPLOY_RPC_TIMEOUT_SECS),
-        CommitmentConfig::confirmed(),
-    );
+    Ok(Json(ProgramInfoResponse {
+        program_id: program_id.to_string(),
+        programdata_address: programdata_address.to_string(),
+        exists: true,
+        executable: account.executable,
+        owner: account.owner.to_string(),
+        lamports: account.lamports,
+        data_len: account.data.len(),
+        network,
+    }))
+}
 
-    let expected_genesis_hash = solana_sdk::hash::Hash::from_str(req.expected_genesis_hash.trim())
-        .map_err(|_| ApiError {
-            message: "无效的预期 genesis hash".to_string(),
-        })?;
-    let actual_genesis_hash = client.get_genesis_hash().map_err(|error| ApiError {
-        message: format!("读取 RPC genesis hash 失败: {error}"),
-    })?;
-    if actual_genesis_hash != expected_genesis_hash {
-        return Err(ApiError {
-            message: format!(
-                "RPC genesis hash 为 {}，预期为 {}；已在花费前中止升级",
-                actual_genesis_hash, expected_genesis_hash
-            ),
-        });
-    }
-    let genesis_hash = actual_genesis_hash.to_string();
+#[derive(Deserialize)]
+struct ProgramVerifyRequest {
+    program_id: String,
+    source_dir: String,
+    #[serde(default)]
+    artifact_stem: Option<String>,
+    #[serde(default)]
+    expected_program_sha256: Option<String>,
+    #[serde(default)]
+    network: Option<String>,
+}
 
-    let wallet_auth = req.take_wallet_auth();
-    let payer = wallet_auth.keypair()?;
-    let payer_pubkey = payer.pubkey();
-    if payer_pubkey != expected_upgrade_authority {
-        return Err(ApiError {
-            message: format!(
-                "签名钱包 {} 与预期 Upgrade Authority {} 不一致",
-                payer_pubkey, expected_upgrade_authority
-            ),
-        });
-    }
+#[derive(Serialize)]
+struct ProgramVerifyResponse {
+    program_id: String,
+    programdata_address: String,
+    artifact_path: String,
+    #[serde(flatten)]
+    comparison: fnzero_safe::program_verify::ProgramComparison,
+    expected_program_sha256: Option<String>,
+    expected_sha256_matches: Option<bool>,
+    network: String,
+}
 
-    let programdata_address =
-        require_program_upgrade_authority(&client, &program_id, &payer_pubkey)?;
-    let programdata_account = client
-        .get_account_with_commitment(&programdata_address, CommitmentConfig::confirmed())
-        .map_err(|error| ApiError {
-            message: format!("读取 ProgramData 失败: {error}"),
-        })?
-        .value
-        .ok_or_else(|| ApiError {
-            message: format!("ProgramData {} 不存在", programdata_address),
-        })?;
-    let metadata_len = UpgradeableLoaderState::size_of_programdata_metadata();
-    if programdata_account.data.len() < metadata_len {
-        return Err(ApiError {
-            message: "ProgramData 账户长度不足".to_string(),
-        });
+async fn program_verify(
+    Json(req): Json<ProgramVerifyRequest>,
+) -> Result<Json<ProgramVerifyResponse>, ApiError> {
+    use fnzero_safe::program_verify::{compare_program_bytes, fetch_program_executable};
+
+    let program_id = Pubkey::from_str(req.program_id.trim()).map_err(|_| ApiError {
+        message: "无效的 Program ID".to_string(),
+    })?;
+    let source_dir_raw = req.source_dir.trim();
+    if source_dir_raw.is_empty() || source_dir_raw.len() > 4096 {
+        return Err(program_source_error("源码目录路径无效"));
     }
-    let max_program_len = programdata_account.data.len() - metadata_len;
-    if program_bytes.len() > max_program_len {
-        return Err(ApiError {
-            message: format!(
-                "新 .so 长度为 {} bytes，超过 ProgramData 容量 {} bytes；无法用当前 Program ID 升级，请重新部署更大 max_data_len 的 Program",
-                program_bytes.len(),
-                max_program_len
-            ),
-        });
+    let source_dir = PathBuf::from(source_dir_raw)
+        .canonicalize()
+        .map_err(|error| program_source_error(format!("源码目录不存在或不可访问: {error}")))?;
+    if !source_dir.is_dir() {
+        return Err(program_source_error("源码目录不是目录"));
     }
+    let expected_program_sha256 =
+        normalize_expected_program_sha256(req.expected_program_sha256.as_deref())?;
 
-    let spill = if let Some(spill) = req
-        .spill_address
+    let artifact_stem = req
+        .artifact_stem
         .as_deref()
         .map(str::trim)
         .filter(|value| !value.is_empty())
-    {
-        Pubkey::from_str(spill).map_err(|_| ApiError {
-            message: "无效的 spill 地址".to_string(),
-        })?
-    } else {
-        payer_pubkey
-    };
-
-    let buffer_keypair = Keypair::new();
-    let buffer_lamports = client
-        .get_minimum_balance_for_rent_exemption(UpgradeableLoaderState::size_of_buffer(
-            program_bytes.len(),
-        ))
-        .map_err(|error| ApiError {
-            message: format!("计算 buffer 租金失败: {error}"),
+        .map(ToOwned::to_owned)
+        .or_else(|| detect_program_artifact_stem(&source_dir));
+    let artifact_path = find_verifiable_program_so_path(&source_dir, artifact_stem.as_deref())
+        .ok_or_else(|| {
+            program_source_error("未找到 target/verifiable/*.so 或 target/deploy/*.so，请先构建")
         })?;
-    // create buffer + write chunks + upgrade; keep a small SOL reserve for fee spikes
-    let estimated_fee_lamports = (write_chunk_count.saturating_add(2).saturating_mul(10_000))
-        .saturating_add(50_000_000) as u64;
-    let estimated_required_balance_lamports = buffer_lamports
-        .checked_add(estimated_fee_lamports)
-        .ok_or_else(|| ApiError {
-        message: "升级所需余额总额溢出".to_string(),
+    let artifact_path = canonical_child_path(&source_dir, &artifact_path, ".so 文件")?;
+    let local = read_bytes_file_limited(&artifact_path, MAX_PROGRAM_SO_BYTES, ".so 文件")?;
+
+    let rpc_url = get_rpc_url(req.network.as_deref())?;
+    let network = network_name(req.network.as_deref());
+    let client = RpcClient::new_with_commitment(rpc_url.to_string(), CommitmentConfig::confirmed());
+    let onchain = fetch_program_executable(&client, &program_id).map_err(|e| ApiError {
+        message: format!("读取链上程序失败: {}", e),
     })?;
-    let payer_balance = client
-        .get_balance(&payer_pubkey)
-        .map_err(|error| ApiError {
-            message: format!("查询升级钱包余额失败: {error}"),
-        })?;
-    if payer_balance < estimated_required_balance_lamports {
+    let comparison = compare_program_bytes(&onchain, &local);
+    let expected_sha256_matches = expected_program_sha256
+        .as_ref()
+        .map(|expected| expected == &comparison.local_sha256);
+
+    Ok(Json(ProgramVerifyResponse {
+        program_id: program_id.to_string(),
+        programdata_address: get_program_data_address(&program_id).to_string(),
+        artifact_path: path_string(&artifact_path),
+        comparison,
+        expected_program_sha256,
+        expected_sha256_matches,
+        network,
+    }))
+}
+
+#[derive(Deserialize)]
+struct ProgramAddressSeedRequest {
+    kind: String,
+    value: String,
+}
+
+#[derive(Deserialize)]
+struct ProgramDeriveAddressRequest {
+    program_id: String,
+    seeds: Vec<ProgramAddressSeedRequest>,
+}
+
+#[derive(Serialize)]
+struct ProgramDeriveAddressResponse {
+    address: String,
+    bump: u8,
+}
+
+async fn program_derive_address(
+    Json(req): Json<ProgramDeriveAddressRequest>,
+) -> Result<Json<ProgramDeriveAddressResponse>, ApiError> {
+    let program_id = Pubkey::from_str(req.program_id.trim()).map_err(|_| ApiError {
+        message: "无效的 PDA Program ID".to_string(),
+    })?;
+    if req.seeds.is_empty() || req.seeds.len() > 16 {
         return Err(ApiError {
-            message: format!(
-                "升级钱包余额不足：当前 {:.4} SOL（{} lamports），至少需要约 {:.4} SOL（{} lamports）。其中升级 Buffer 租金约 {:.4} SOL，交易费预留约 {:.4} SOL。Buffer 租金升级成功后会退回 spill 地址，但创建 Buffer 时必须先备足余额。",
-                payer_balance as f64 / 1_000_000_000.0,
-                payer_balance,
-                estimated_required_balance_lamports as f64 / 1_000_000_000.0,
-                estimated_required_balance_lamports,
-                buffer_lamports as f64 / 1_000_000_000.0,
-                estimated_fee_lamports as f64 / 1_000_000_000.0
-            ),
+            message: "PDA seeds 数量必须在 1 到 16 之间".to_string(),
         });
     }
-    publish_program_upgrade_progress(|progress| {
-        progress.stage = "creating_buffer".to_string();
-        progress.message = format!("正在创建升级 Buffer（{}）", buffer_keypair.pubkey());
-        progress.buffer_address = Some(buffer_keypair.pubkey().to_string());
-        progress.write_total = write_chunk_count;
-        progress.program_bytes = program_bytes.len();
-    })
-    .await;
-    let create_buffer_ixs = loader_v3_instruction::create_buffer(
-        &payer_pubkey,
-        &buffer_keypair.pubkey(),
-        &payer_pubkey,
-        buffer_lamports,
-        program_bytes.len(),
-    )
-    .map_err(|error| ApiError {
-        message: format!("创建 buffer 指令失败: {error}"),
-    })?;
-    let create_buffer_signature = sign_and_send_with_commitment(
-        &client,
-        create_buffer_ixs,
-        &[&payer, &buffer_keypair],
-        &payer_pubkey,
-        "创建升级 Buffer",
-        CommitmentConfig::finalized(),
-        "finalized",
-        Duration::from_secs(90),
-    )
-    .await?
-    .0;
 
-    let mut write_signatures = Vec::with_capacity(write_chunk_count);
-    for (index, chunk) in program_bytes.chunks(PROGRAM_WRITE_CHUNK_BYTES).enumerate() {
-        let offset = index
-            .checked_mul(PROGRAM_WRITE_CHUNK_BYTES)
-            .and_then(|value| u32::try_from(value).ok())
-            .ok_or_else(|| ApiError {
-                message: "Program 写入偏移超出范围".to_string(),
-            })?;
-        let write_ix = loader_v3_instruction::write(
-            &buffer_keypair.pubkey(),
-            &payer_pubkey,
-            offset,
-            chunk.to_vec(),
-        );
-        publish_program_upgrade_progress(|progress| {
-            progress.stage = "writing".to_string();
-            progress.write_completed = index;
-            progress.write_total = write_chunk_count;
-            progress.message = format!(
-                "正在写入升级 Buffer：{}/{}（约 {:.1}%）",
-                index + 1,
-                write_chunk_count,
-                ((index as f64) / (write_chunk_count.max(1) as f64)) * 100.0
-            );
-            progress.buffer_address = Some(buffer_keypair.pubkey().to_string());
-        })
-        .await;
-        tracing::info!(
-            "program upgrade write chunk {}/{} for {} ({} bytes)",
-            index + 1,
-            write_chunk_count,
-            program_id,
-            chunk.len()
-        );
-        let write_signature = sign_and_send_with_commitment(
-            &client,
-            vec![write_ix],
-            &[&payer],
-            &payer_pubkey,
-            &format!("写入升级 Buffer ({}/{})", index + 1, write_chunk_count),
-            CommitmentConfig::confirmed(),
-            "confirmed",
-            Duration::from_secs(120),
-        )
-        .await?
-        .0;
-        publish_program_upgrade_progress(|progress| {
-            progress.write_completed = index + 1;
-            progress.last_signature = Some(write_signature.clone());
-            progress.message = format!(
-                "已写入升级 Buffer：{}/{}（约 {:.1}%）",
-                index + 1,
-                write_chunk_count,
-                ((index + 1) as f64 / (write_chunk_count.max(1) as f64)) * 100.0
-            );
+    let mut seed_bytes = Vec::with_capacity(req.seeds.len());
+    for seed in req.seeds {
+        let bytes = match seed.kind.as_str() {
+            "bytes_base64" => BASE64.decode(seed.value.trim()).map_err(|_| ApiError {
+                message: "PDA seed base64 无效".to_string(),
+            })?,
+            "pubkey" => Pubkey::from_str(seed.value.trim())
+                .map_err(|_| ApiError {
+                    message: "PDA seed pubkey 无效".to_string(),
+                })?
+                .to_bytes()
+                .to_vec(),
+            _ => {
+                return Err(ApiError {
+                    message: "不支持的 PDA seed 类型".to_string(),
+                });
+            }
+        };
+        if bytes.is_empty() || bytes.len() > 32 {
+            return Err(ApiError {
+                message: "PDA 单个 seed 长度必须在 1 到 32 bytes 之间".to_string(),
+            });
+        }
+        seed_bytes.push(bytes);
+    }
+
+    let seed_refs: Vec<&[u8]> = seed_bytes.iter().map(Vec::as_slice).collect();
+    let (address, bump) = Pubkey::find_program_address(&seed_refs, &program_id);
+    Ok(Json(ProgramDeriveAddressResponse {
+        address: address.to_string(),
+        bump,
+    }))
+}
+
+// ============= Squads v4 Multisig =============
+
+#[derive(Deserialize)]
+struct SquadsCreateRequest {
+    #[serde(flatten)]
+    wallet: WalletAuthRequest,
+    members: Vec<String>,
+    threshold: u16,
+    #[serde(default)]
+    time_lock: Option<u32>,
+    #[serde(default)]
+    memo: Option<String>,
+    #[serde(default)]
+    network: Option<String>,
+}
+
+#[derive(Serialize)]
+struct SquadsMemberResponse {
+    key: String,
+    permissions: u8,
+}
+
+#[derive(Serialize)]
+struct SquadsCreateResponse {
+    multisig: String,
+    vault: String,
+    create_key: String,
+    signature: String,
+    threshold: u16,
+    members: Vec<SquadsMemberResponse>,
+    creation_fee_lamports: u64,
+    network: String,
+    status: String,
+}
+
+async fn squads_create(
+    Json(req): Json<SquadsCreateRequest>,
+) -> Result<Json<SquadsCreateResponse>, ApiError> {
+    if req.members.is_empty() {
+        return Err(ApiError {
+            message: "至少需要一个多签成员".to_string(),
+        });
+    }
+    let mut members = req
+        .members
+        .iter()
+        .map(|member| {
+            parse_squads_pubkey(member, "成员地址").map(|key| SquadsMember {
+                key,
+                permissions: SquadsPermissions::all(),
+            })
         })
-        .await;
-        write_signatures.push(write_signature);
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|message| ApiError { message })?;
+    members.sort_by_key(|member| member.key);
+    members.dedup_by_key(|member| member.key);
+
+    if req.threshold == 0 || usize::from(req.threshold) > members.len() {
+        return Err(ApiError {
+            message: "阈值必须大于 0 且不能超过成员数量".to_string(),
+        });
     }
 
-    publish_program_upgrade_progress(|progress| {
-        progress.stage = "upgrading".to_string();
-        progress.message = "正在提交 upgradeable-loader 升级交易".to_string();
-        progress.write_completed = write_chunk_count;
-    })
-    .await;
-    let upgrade_ix =
-        squads_v4::upgrade_program_ix(&program_id, &buffer_keypair.pubkey(), &payer_pubkey, &spill);
-    let (upgrade_signature, upgrade_slot) = sign_and_send_with_commitment(
-        &client,
-        vec![upgrade_ix],
-        &[&payer],
-        &payer_pubkey,
-        "提交 Program 升级",
-        CommitmentConfig::finalized(),
-        "finalized",
-        Duration::from_secs(90),
-    )
-    .await?;
+    if let Some(memo) = &req.memo {
+        validate_text_len(memo, "memo", MAX_TEXT_FIELD_CHARS)?;
+    }
 
-    publish_program_upgrade_progress(|progress| {
-        progress.stage = "verifying_readback".to_string();
-        progress.message = "正在 finalized 回读并校验链上 Program 数据".to_string();
-        progress.last_signature = Some(upgrade_signature.clone());
-    })
-    .await;
-    let readback = wait_for_finalized_deployment_readback(
-        &client,
-        &program_id,
-        &programdata_address,
-        &payer_pubkey,
-        &program_bytes,
-        max_program_len,
-        upgrade_slot,
+    let payer = req.wallet.keypair()?;
+    if !members.iter().any(|member| member.key == payer.pubkey()) {
+        return Err(ApiError {
+            message: "当前签名钱包必须是多签成员".to_string(),
+        });
+    }
+    let create_key = Keypair::new();
+    let (client, network) = rpc_client_for(req.network.as_deref())?;
+    let program_config = load_squads_program_config(&client)?;
+    let (ix, multisig) = squads_v4::multisig_create_ix(
+        &create_key.pubkey(),
+        &payer.pubkey(),
+        &program_config.treasury,
+        req.threshold,
+        members.clone(),
+        req.time_lock.unwrap_or(0),
+        req.memo,
     )
-    .await?;
+    .map_err(|message| ApiError { message })?;
+    let signature = sign_and_send(&client, vec![ix], &[&payer, &create_key], &payer.pubkey())?;
+    let vault = squads_v4::vault_pda(&multisig, 0);
 
-    Ok(Json(UpgradeProgramResponse {
-        program_id: program_id.to_string(),
-        programdata_address: programdata_address.to_string(),
-        buffer_address: buffer_keypair.pubkey().to_string(),
-        authority: readback.upgrade_authority.to_string(),
+    Ok(Json(SquadsCreateResponse {
+        multisig: multisig.to_string(),
+        vault: vault.to_string(),
+        create_key: create_key.pubkey().to_string(),
+        signature,
+        threshold: req.threshold,
+        members: members
+            .into_iter()
+            .map(|member| SquadsMemberResponse {
+                key: member.key.to_string(),
+                permissions: member.permissions.mask,
+            })
+            .collect(),
+        creation_fee_lamports: program_config.multisig_creation_fee,
         network,
-        genesis_hash,
-        program_bytes: program_bytes.len(),
-        program_sha256,
-        rent_lamports: buffer_lamports,
-        create_buffer_signature,
-        write_signatures,
-        upgrade_signature,
-        deployed_slot: readback.deployed_slot,
-        readback_verified: true,
-        status: "finalized".to_string(),
+        status: "success".to_string(),
     }))
 }
 
 #[derive(Deserialize)]
-struct ProgramInfoRequest {
-    program_id: String,
+struct SquadsInfoRequest {
+    multisig: String,
+    #[serde(default)]
+    proposal: Option<String>,
     #[serde(default)]
     network: Option<String>,
+    #[serde(flatten)]
+    known_build: SquadsKnownBuildRequest,
 }
 
 #[derive(Serialize)]
-struct ProgramInfoResponse {
-    program_id: String,
-    programdata_address: String,
-    exists: bool,
-    executable: bool,
-    owner: String,
-    lamports: u64,
-    data_len: usize,
+struct SquadsInfoResponse {
+    multisig: String,
+    vault: String,
+    create_key: String,
+    threshold: u16,
+    time_lock: u32,
+    transaction_index: u64,
+    stale_transaction_index: u64,
+    members: Vec<SquadsMemberResponse>,
+    proposal: Option<SquadsProposalResponse>,
     network: String,
 }
 
-async fn program_info(
-    Json(req): Json<ProgramInfoRequest>,
-) -> Result<Json<ProgramInfoResponse>, ApiError> {
-    let program_id = Pubkey::from_str(req.program_id.trim()).map_err(|_| ApiError {
-        message: "无效的 Program ID".to_string(),
-    })?;
-    let rpc_url = get_rpc_url(req.network.as_deref())?;
-    let network = network_name(req.network.as_deref());
-    let client = RpcClient::new_with_commitment(rpc_url.to_string(), CommitmentConfig::confirmed());
-    let programdata_address = get_program_data_address(&program_id);
-    let Some(account) = client
-        .get_account_with_commitment(&program_id, CommitmentConfig::confirmed())
-        .map_err(|e| ApiError {
-            message: format!("查询 Program 失败: {}", e),
-        })?
-        .value
-    else {
-        return Ok(Json(ProgramInfoResponse {
-            program_id: program_id.to_string(),
-            programdata_address: programdata_address.to_string(),
-            exists: false,
-            executable: false,
-            owner: "".to_string(),
-            lamports: 0,
-            data_len: 0,
-            network,
-        }));
+#[derive(Serialize)]
+struct SquadsProposalResponse {
+    address: String,
+    transaction_index: u64,
+    status: String,
+    approved: Vec<String>,
+    rejected: Vec<String>,
+    cancelled: Vec<String>,
+    decoded: Option<SquadsDecodedTransactionResponse>,
+    decode_error: Option<String>,
+}
+
+/// Locally known program build used to check the buffer of an upgrade
+/// proposal. Either a source directory with build artifacts or an explicit
+/// SHA-256 may be given.
+#[derive(Deserialize, Default)]
+struct SquadsKnownBuildRequest {
+    #[serde(default)]
+    program_source_dir: Option<String>,
+    #[serde(default)]
+    expected_program_sha256: Option<String>,
+}
+
+#[derive(Serialize)]
+struct SquadsDecodedTransactionResponse {
+    transaction: String,
+    vault: String,
+    accounts: Vec<SquadsMessageAccountResponse>,
+    instructions: Vec<SquadsDecodedInstructionResponse>,
+    outflows: Vec<SquadsVaultOutflowResponse>,
+    program_upgrades: Vec<SquadsProgramUpgradeResponse>,
+}
+
+#[derive(Serialize)]
+struct SquadsMessageAccountResponse {
+    pubkey: String,
+    signer: bool,
+    writable: bool,
+    lookup_table: Option<String>,
+    lookup_index: Option<u8>,
+}
+
+#[derive(Serialize)]
+struct SquadsDecodedInstructionResponse {
+    program_id: String,
+    program: String,
+    name: String,
+    fields: Vec<SquadsInstructionFieldResponse>,
+    accounts: Vec<String>,
+    data_base64: String,
+}
+
+#[derive(Serialize)]
+struct SquadsInstructionFieldResponse {
+    name: String,
+    value: String,
+}
+
+#[derive(Serialize)]
+struct SquadsVaultOutflowResponse {
+    asset: String,
+    mint: Option<String>,
+    token_program: Option<String>,
+    source: String,
+    destination: String,
+    amount: String,
+    decimals: Option<u8>,
+    ui_amount: Option<String>,
+}
+
+#[derive(Serialize)]
+struct SquadsProgramUpgradeResponse {
+    program_id: String,
+    buffer: String,
+    spill: String,
+    buffer_len: Option<usize>,
+    buffer_sha256: Option<String>,
+    local_sha256: Option<String>,
+    matches_local_build: Option<bool>,
+    warning: Option<String>,
+}
+
+async fn squads_info(
+    Json(req): Json<SquadsInfoRequest>,
+) -> Result<Json<SquadsInfoResponse>, ApiError> {
+    let multisig_key =
+        parse_squads_pubkey(&req.multisig, "多签地址").map_err(|message| ApiError { message })?;
+    let local_sha256 = squads_known_build_sha256(&req.known_build)?;
+    let (client, network) = rpc_client_for(req.network.as_deref())?;
+    let multisig = load_squads_multisig(&client, &multisig_key)?;
+    let proposal = if let Some(proposal) = req
+        .proposal
+        .as_deref()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+    {
+        let proposal_key =
+            parse_squads_pubkey(proposal, "提案地址").map_err(|message| ApiError { message })?;
+        let proposal = load_squads_proposal(&client, &proposal_key)?;
+        Some(squads_proposal_response(
+            &client,
+            &multisig_key,
+            proposal_key,
+            proposal,
+            local_sha256.as_deref(),
+        ))
+    } else {
+        None
     };
 
-    Ok(Json(ProgramInfoResponse {
-        program_id: program_id.to_string(),
-        programdata_address: programdata_address.to_string(),
-        exists: true,
-        executable: account.executable,
-        owner: account.owner.to_string(),
-        lamports: account.lamports,
-        data_len: account.data.len(),
+    Ok(Json(SquadsInfoResponse {
+        multisig: multisig_key.to_string(),
+        vault: squads_v4::vault_pda(&multisig_key, 0).to_string(),
+        create_key: multisig.create_key.to_string(),
+        threshold: multisig.threshold,
+        time_lock: multisig.time_lock,
+        transaction_index: multisig.transaction_index,
+        stale_transaction_index: multisig.stale_transaction_index,
+        members: multisig
+            .members
+            .into_iter()
+            .map(|member| SquadsMemberResponse {
+                key: member.key.to_string(),
+                permissions: member.permissions.mask,
+            })
+            .collect(),
+        proposal,
         network,
     }))
 }
 
 #[derive(Deserialize)]
-struct ProgramAddressSeedRequest {
-    kind: String,
-    value: String,
+struct SquadsProposalsRequest {
+    multisig: String,
+    #[serde(default)]
+    network: Option<String>,
+    #[serde(default)]
+    limit: Option<u64>,
+    #[serde(flatten)]
+    known_build: SquadsKnownBuildRequest,
 }
 
-#[derive(Deserialize)]
-struct ProgramDeriveAddressRequest {
-    program_id: String,
-    seeds: Vec<ProgramAddressSeedRequest>,
+#[derive(Serialize)]
+struct SquadsProposalsResponse {
+    multisig: String,
+    vault: String,
+    proposals: Vec<SquadsProposalResponse>,
+    latest_transaction_index: u64,
+    network: String,
 }
 
-#[derive(Serialize)]
-struct ProgramDeriveAddressResponse {
-    address: String,
-    bump: u8,
+async fn squads_proposals(
+    Json(req): Json<SquadsProposalsRequest>,
+) -> Result<Json<SquadsProposalsResponse>, ApiError> {
+    let multisig_key =
+        parse_squads_pubkey(&req.multisig, "多签地址").map_err(|message| ApiError { message })?;
+    let local_sha256 = squads_known_build_sha256(&req.known_build)?;
+    let (client, network) = rpc_client_for(req.network.as_deref())?;
+    let multisig = load_squads_multisig(&client, &multisig_key)?;
+    let limit = req.limit.unwrap_or(20).clamp(1, 50);
+    let start = multisig.transaction_index;
+    let end = start.saturating_sub(limit.saturating_sub(1));
+    let mut proposals = Vec::new();
+
+    for index in (end..=start).rev() {
+        let proposal_key = squads_v4::proposal_pda(&multisig_key, index);
+        if let Ok(proposal) = load_squads_proposal(&client, &proposal_key) {
+            proposals.push(squads_proposal_response(
+                &client,
+                &multisig_key,
+                proposal_key,
+                proposal,
+                local_sha256.as_deref(),
+            ));
+        }
+    }
+
+    Ok(Json(SquadsProposalsResponse {
+        multisig: multisig_key.to_string(),
+        vault: squads_v4::vault_pda(&multisig_key, 0).to_string(),
+        proposals,
+        latest_transaction_index: multisig.transaction_index,
+        network,
+    }))
 }
 
-async fn program_derive_address(
-    Json(req): Json<ProgramDeriveAddressRequest>,
-) -> Result<Json<ProgramDeriveAddressResponse>, ApiError> {
-    let program_id = Pubkey::from_str(req.program_id.trim()).map_err(|_| ApiError {
-        message: "无效的 PDA Program ID".to_string(),
-    })?;
-    if req.seeds.is_empty() || req.seeds.len() > 16 {
+fn squads_proposal_response(
+    client: &RpcClient,
+    multisig: &Pubkey,
+    address: Pubkey,
+    proposal: SquadsProposal,
+    local_sha256: Option<&str>,
+) -> SquadsProposalResponse {
+    // Finished proposals are not decoded: their outcome is already on chain
+    // and their transaction accounts may have been closed.
+    let (decoded, decode_error) = if proposal.status.is_pending() {
+        match decode_squads_vault_transaction(
+            client,
+            multisig,
+            proposal.transaction_index,
+            local_sha256,
+        ) {
+            Ok(decoded) => (Some(decoded), None),
+            Err(error) => (None, Some(error.message)),
+        }
+    } else {
+        (None, None)
+    };
+    SquadsProposalResponse {
+        address: address.to_string(),
+        transaction_index: proposal.transaction_index,
+        status: proposal.status.label().to_string(),
+        approved: proposal
+            .approved
+            .into_iter()
+            .map(|key| key.to_string())
+            .collect(),
+        rejected: proposal
+            .rejected
+            .into_iter()
+            .map(|key| key.to_string())
+            .collect(),
+        cancelled: proposal
+            .cancelled
+            .into_iter()
+            .map(|key| key.to_string())
+            .collect(),
+        decoded,
+        decode_error,
+    }
+}
+
+fn normalize_expected_program_sha256(value: Option<&str>) -> Result<Option<String>, ApiError> {
+    let Some(expected) = value.map(str::trim).filter(|value| !value.is_empty()) else {
+        return Ok(None);
+    };
+    let expected = expected.to_ascii_lowercase();
+    if expected.len() != 64 || !expected.bytes().all(|byte| byte.is_ascii_hexdigit()) {
         return Err(ApiError {
-            message: "PDA seeds 数量必须在 1 到 16 之间".to_string(),
+            message: "expected_program_sha256 必须是 64 位十六进制".to_string(),
         });
     }
+    Ok(Some(expected))
+}
 
-    let mut seed_bytes = Vec::with_capacity(req.seeds.len());
-    for seed in req.seeds {
-        let bytes = match seed.kind.as_str() {
-            "bytes_base64" => BASE64.decode(seed.value.trim()).map_err(|_| ApiError {
-                message: "PDA seed base64 无效".to_string(),
-            })?,
-            "pubkey" => Pubkey::from_str(seed.value.trim())
-                .map_err(|_| ApiError {
-                    message: "PDA seed pubkey 无效".to_string(),
-                })?
-                .to_bytes()
-                .to_vec(),
-            _ => {
-                return Err(ApiError {
-                    message: "不支持的 PDA seed 类型".to_string(),
-                });
-            }
-        };
-        if bytes.is_empty() || bytes.len() > 32 {
-            return Err(ApiError {
-                message: "PDA 单个 seed 长度必须在 1 到 32 bytes 之间".to_string(),
-            });
-        }
-        seed_bytes.push(bytes);
+fn squads_known_build_sha256(req: &SquadsKnownBuildRequest) -> Result<Option<String>, ApiError> {
+    if let Some(expected) =
+        normalize_expected_program_sha256(req.expected_program_sha256.as_deref())?
+    {
+        return Ok(Some(expected));
     }
-
-    let seed_refs: Vec<&[u8]> = seed_bytes.iter().map(Vec::as_slice).collect();
-    let (address, bump) = Pubkey::find_program_address(&seed_refs, &program_id);
-    Ok(Json(ProgramDeriveAddressResponse {
-        address: address.to_string(),
-        bump,
-    }))
+    let Some(source_dir) = req
+        .program_source_dir
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+    else {
+        return Ok(None);
+    };
+    if source_dir.len() > 4096 {
+        return Err(program_source_error("源码目录路径无效"));
+    }
+    let source_dir = PathBuf::from(source_dir)
+        .canonicalize()
+        .map_err(|error| program_source_error(format!("源码目录不存在或不可访问: {error}")))?;
+    if !source_dir.is_dir() {
+        return Err(program_source_error("源码目录不是目录"));
+    }
+    let artifact_stem = detect_program_artifact_stem(&source_dir);
+    let artifacts =
+        load_program_source_artifacts(&source_dir, artifact_stem.as_deref(), None, false)?;
+    Ok(artifacts.program_so_sha256)
 }
 
-// ============= Squads v4 Multisig =============
+fn decode_squads_vault_transaction(
+    client: &RpcClient,
+    multisig: &Pubkey,
+    transaction_index: u64,
+    local_sha256: Option<&str>,
+) -> Result<SquadsDecodedTransactionResponse, ApiError> {
+    let transaction_key = squads_v4::transaction_pda(multisig, transaction_index);
+    let transaction = load_squads_vault_transaction(client, &transaction_key)?;
+    let vault = squads_v4::vault_pda(multisig, transaction.vault_index);
+    let lookup_tables = load_squads_message_lookup_tables(client, &transaction.message)?;
+    let message = squads_v4::decode_transaction_message(&transaction.message, &lookup_tables)
+        .map_err(|message| ApiError { message })?;
 
-#[derive(Deserialize)]
-struct SquadsCreateRequest {
-    #[serde(flatten)]
-    wallet: WalletAuthRequest,
-    members: Vec<String>,
-    threshold: u16,
-    #[serde(default)]
-    time_lock: Option<u32>,
-    #[serde(default)]
-    memo: Option<String>,
-    #[serde(default)]
-    network: Option<String>,
+    let accounts = message
+        .accounts
+        .iter()
+        .map(|account| {
+            let (lookup_table, lookup_index) = match account.source {
+                squads_v4::MessageAccountSource::Static => (None, None),
+                squads_v4::MessageAccountSource::Lookup { table, index } => {
+                    (Some(table.to_string()), Some(index))
+                }
+            };
+            SquadsMessageAccountResponse {
+                pubkey: account.pubkey.to_string(),
+                signer: account.is_signer,
+                writable: account.is_writable,
+                lookup_table,
+                lookup_index,
+            }
+        })
+        .collect();
+    let instructions = message
+        .instructions
+        .iter()
+        .map(|ix| {
+            let summary = squads_v4::summarize_instruction(ix);
+            SquadsDecodedInstructionResponse {
+                program_id: ix.program_id.to_string(),
+                program: summary.program,
+                name: summary.name,
+                fields: summary
+                    .fields
+                    .into_iter()
+                    .map(|(name, value)| SquadsInstructionFieldResponse { name, value })
+                    .collect(),
+                accounts: ix
+                    .accounts
+                    .iter()
+                    .map(|meta| meta.pubkey.to_string())
+                    .collect(),
+                data_base64: BASE64.encode(&ix.data),
+            }
+        })
+        .collect();
+    let outflows = squads_v4::vault_outflows(&vault, &message.instructions)
+        .into_iter()
+        .map(|outflow| squads_vault_outflow_response(client, outflow))
+        .collect();
+    let program_upgrades = squads_v4::program_upgrade_targets(&message.instructions)
+        .into_iter()
+        .map(|target| squads_program_upgrade_response(client, target, local_sha256))
+        .collect();
+
+    Ok(SquadsDecodedTransactionResponse {
+        transaction: transaction_key.to_string(),
+        vault: vault.to_string(),
+        accounts,
+        instructions,
+        outflows,
+        program_upgrades,
+    })
 }
 
-#[derive(Serialize)]
-struct SquadsMemberResponse {
-    key: String,
-    permissions: u8,
+fn squads_vault_outflow_response(
+    client: &RpcClient,
+    outflow: squads_v4::VaultOutflow,
+) -> SquadsVaultOutflowResponse {
+    let (asset, mut mint, token_program, mut decimals) = match outflow.asset {
+        squads_v4::OutflowAsset::Sol => ("SOL".to_string(), None, None, Some(9)),
+        squads_v4::OutflowAsset::Token {
+            token_program,
+            mint,
+            decimals,
+        } => ("token".to_string(), mint, Some(token_program), decimals),
+    };
+    // A plain token transfer names neither the mint nor its decimals; read
+    // them from the source account so the amount can still be shown.
+    if token_program.is_some() && mint.is_none() {
+        mint = client
+            .get_account(&outflow.source)
+            .ok()
+            .and_then(|account| Pubkey::try_from(account.data.get(..32)?).ok());
+    }
+    if let (Some(mint), None) = (mint.as_ref(), decimals) {
+        decimals = client
+            .get_account(mint)
+            .ok()
+            .and_then(|account| account.data.get(44).copied());
+    }
+    SquadsVaultOutflowResponse {
+        asset,
+        mint: mint.map(|mint| mint.to_string()),
+        token_program: token_program.map(|program| program.to_string()),
+        source: outflow.source.to_string(),
+        destination: outflow.destination.to_string(),
+        amount: outflow.amount.to_string(),
+        decimals,
+        ui_amount: decimals
+            .map(|decimals| ui_amount_from_raw_amount(u128::from(outflow.amount), decimals)),
+    }
+}
+
+fn squads_program_upgrade_response(
+    client: &RpcClient,
+    target: squads_v4::ProgramUpgradeTarget,
+    local_sha256: Option<&str>,
+) -> SquadsProgramUpgradeResponse {
+    let buffer = client
+        .get_account(&target.buffer)
+        .map_err(|e| format!("读取升级 Buffer 失败: {}", e))
+        .and_then(|account| {
+            squads_v4::upgrade_buffer_program_bytes(&account.data)
+                .map(|bytes| (bytes.len(), program_deploy::sha256_hex(bytes)))
+        });
+    let (buffer_len, buffer_sha256, warning) = match buffer {
+        Ok((len, sha256)) => (Some(len), Some(sha256), None),
+        Err(error) => (None, None, Some(error)),
+    };
+    let matches_local_build = buffer_sha256
+        .as_deref()
+        .zip(local_sha256)
+        .map(|(buffer, local)| buffer == local);
+    let warning = warning.or_else(|| {
+        (matches_local_build == Some(false))
+            .then(|| "Buffer 内容与本地已知构建的 SHA-256 不一致".to_string())
+    });
+    SquadsProgramUpgradeResponse {
+        program_id: target.program.to_string(),
+        buffer: target.buffer.to_string(),
+        spill: target.spill.to_string(),
+        buffer_len,
+        buffer_sha256,
+        local_sha256: local_sha256.map(ToOwned::to_owned),
+        matches_local_build,
+        warning,
+    }
 }
 
 #[derive(Serialize)]
-struct SquadsCreateResponse {
+struct SquadsProposalCreateResponse {
     multisig: String,
     vault: String,
-    create_key: String,
+    transaction: String,
+    proposal: String,
+    transaction_index: u64,
     signature: String,
-    threshold: u16,
-    members: Vec<SquadsMemberResponse>,
-    creation_fee_lamports: u64,
     network: String,
     status: String,
 }
 
-async fn squads_create(
-    Json(req): Json<SquadsCreateRequest>,
-) -> Result<Json<SquadsCreateResponse>, ApiError> {
-    if req.members.is_empty() {
-        return Err(ApiError {
-            message: "至少需要一个多签成员".to_string(),
-        });
-    }
-    let mut members = req
-        .members
-        .iter()
-        .map(|member| {
-            squads_v4::parse_pubkey(member, "成员地址").map(|key| SquadsMember {
-                key,
-                permissions: SquadsPermissions::all(),
-            })
-        })
-        .collect::<Result<Vec<_>, _>>()
-        .map_err(|message| ApiError { message })?;
-    members.sort_by_key(|member| member.key);
-    members.dedup_by_key(|member| member.key);
-
-    if req.threshold == 0 || usize::from(req.threshold) > members.len() {
-        return Err(ApiError {
-            message: "阈值必须大于 0 且不能超过成员数量".to_string(),
-        });
-    }
+#[derive(Deserialize)]
+struct SquadsSolTransferProposalRequest {
+    #[serde(flatten)]
+    wallet: WalletAuthRequest,
+    multisig: String,
+    to_address: String,
+    amount: DecimalAmount,
+    #[serde(default)]
+    memo: Option<String>,
+    #[serde(default)]
+    network: Option<String>,
+}
 
+async fn squads_sol_transfer_proposal(
+    Json(req): Json<SquadsSolTransferProposalRequest>,
+) -> Result<Json<SquadsProposalCreateResponse>, ApiError> {
+    let signer = req.wallet.keypair()?;
+    let multisig_key =
+        parse_squads_pubkey(&req.multisig, "多签地址").map_err(|message| ApiError { message })?;
+    let to_pubkey =
+        parse_squads_pubkey(&req.to_address, "接收地址").map_err(|message| ApiError { message })?;
+    let amount_lamports = sol_to_lamports(&req.amount)?;
     if let Some(memo) = &req.memo {
-        validate_text_len(memo, "memo", MAX_TEXT_FIELD_CHARS)?;
-    }
-
-    let payer = req.wallet.keypair()?;
-    if !members.iter().any(|member| member.key == payer.pubkey()) {
-        return Err(ApiError {
-            message: "当前签名钱包必须是多签成员".to_string(),
-        });
+        validate_text_len(memo, "memo", MAX_TEXT_FIELD_CHARS)?;
     }
-    let create_key = Keypair::new();
+
     let (client, network) = rpc_client_for(req.network.as_deref())?;
-    let program_config = load_squads_program_config(&client)?;
-    let (ix, multisig) = squads_v4::multisig_create_ix(
-        &create_key.pubkey(),
-        &payer.pubkey(),
-        &program_config.treasury,
-        req.threshold,
-        members.clone(),
-        req.time_lock.unwrap_or(0),
+    let multisig = load_squads_multisig(&client, &multisig_key)?;
+    let transaction_index = next_squads_transaction_index(&multisig)?;
+    let vault = squads_v4::vault_pda(&multisig_key, 0);
+    let transfer_ix = squads_v4::sol_transfer_ix(&vault, &to_pubkey, amount_lamports);
+    let (tx_create_ix, transaction, vault, _) = squads_v4::vault_transaction_create_ix(
+        &multisig_key,
+        &signer.pubkey(),
+        transaction_index,
+        0,
+        &[transfer_ix],
+        &[],
         req.memo,
     )
     .map_err(|message| ApiError { message })?;
-    let signature = sign_and_send(&client, vec![ix], &[&payer, &create_key], &payer.pubkey())?;
-    let vault = squads_v4::vault_pda(&multisig, 0);
+    let (proposal_ix, proposal) =
+        squads_v4::proposal_create_ix(&multisig_key, &signer.pubkey(), transaction_index, false)
+            .map_err(|message| ApiError { message })?;
+    let signature = sign_and_send(
+        &client,
+        vec![tx_create_ix, proposal_ix],
+        &[&signer],
+        &signer.pubkey(),
+    )?;
 
-    Ok(Json(SquadsCreateResponse {
-        multisig: multisig.to_string(),
+    Ok(Json(SquadsProposalCreateResponse {
+        multisig: multisig_key.to_string(),
         vault: vault.to_string(),
-        create_key: create_key.pubkey().to_string(),
+        transaction: transaction.to_string(),
+        proposal: proposal.to_string(),
+        transaction_index,
         signature,
-        threshold: req.threshold,
-        members: members
-            .into_iter()
-            .map(|member| SquadsMemberResponse {
-                key: member.key.to_string(),
-                permissions: member.permissions.mask,
-            })
-            .collect(),
-        creation_fee_lamports: program_config.multisig_creation_fee,
         network,
         status: "success".to_string(),
     }))
 }
 
 #[derive(Deserialize)]
-struct SquadsInfoRequest {
+struct SquadsTokenTransferProposalRequest {
+    #[serde(flatten)]
+    wallet: WalletAuthRequest,
     multisig: String,
+    mint: String,
     #[serde(default)]
-    proposal: Option<String>,
+    recipient: Option<String>,
+    amount: DecimalAmount,
+    #[allow(dead_code)]
+    #[serde(default)]
+    decimals: Option<u8>,
+    #[serde(default)]
+    source_token_account: Option<String>,
+    #[serde(default)]
+    destination_token_account: Option<String>,
+    #[serde(default)]
+    memo: Option<String>,
     #[serde(default)]
     network: Option<String>,
 }
 
-#[derive(Serialize)]
-struct SquadsInfoResponse {
-    multisig: String,
-    vault: String,
-    create_key: String,
-    threshold: u16,
-    time_lock: u32,
-    transaction_index: u64,
-    stale_transaction_index: u64,
-    members: Vec<SquadsMemberResponse>,
-    proposal: Option<SquadsProposalResponse>,
-    network: String,
-}
-
-#[derive(Serialize)]
-struct SquadsProposalResponse {
-    address: String,
-    transaction_index: u64,
-    status: String,
-    approved: Vec<String>,
-    rejected: Vec<String>,
-    cancelled: Vec<String>,
-}
+async fn squads_token_transfer_proposal(
+    Json(req): Json<SquadsTokenTransferProposalRequest>,
+) -> Result<Json<SquadsProposalCreateResponse>, ApiError> {
+    let signer = req.wallet.keypair()?;
+    let multisig_key =
+        parse_squads_pubkey(&req.multisig, "多签地址").map_err(|message| ApiError { message })?;
+    let mint =
+        parse_squads_pubkey(&req.mint, "Token Mint").map_err(|message| ApiError { message })?;
+    let recipient = if let Some(recipient) = req
+        .recipient
+        .as_deref()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+    {
+        Some(
+            parse_squads_pubkey(recipient, "接收钱包地址")
+                .map_err(|message| ApiError { message })?,
+        )
+    } else {
+        None
+    };
+    if let Some(memo) = &req.memo {
+        validate_text_len(memo, "memo", MAX_TEXT_FIELD_CHARS)?;
+    }
 
-async fn squads_info(
-    Json(req): Json<SquadsInfoRequest>,
-) -> Result<Json<SquadsInfoResponse>, ApiError> {
-    let multisig_key = squads_v4::parse_pubkey(&req.multisig, "多签地址")
-        .map_err(|message| ApiError { message })?;
     let (client, network) = rpc_client_for(req.network.as_deref())?;
+    let mint_info = token_mint_info_from_rpc(&client, &mint)?;
+    let decimals = mint_info.decimals;
+    let token_amount = token_amount_to_raw(&req.amount, decimals)?;
     let multisig = load_squads_multisig(&client, &multisig_key)?;
-    let proposal = if let Some(proposal) = req
-        .proposal
+    let transaction_index = next_squads_transaction_index(&multisig)?;
+    let vault = squads_v4::vault_pda(&multisig_key, 0);
+    let source = if let Some(source) = req
+        .source_token_account
         .as_deref()
         .map(str::trim)
         .filter(|s| !s.is_empty())
     {
-        let proposal_key = squads_v4::parse_pubkey(proposal, "提案地址")
-            .map_err(|message| ApiError { message })?;
-        let proposal = load_squads_proposal(&client, &proposal_key)?;
-        Some(SquadsProposalResponse {
-            address: proposal_key.to_string(),
-            transaction_index: proposal.transaction_index,
-            status: proposal.status.label().to_string(),
-            approved: proposal
-                .approved
-                .into_iter()
-                .map(|key| key.to_string())
-                .collect(),
-            rejected: proposal
-                .rejected
-                .into_iter()
-                .map(|key| key.to_string())
-                .collect(),
-            cancelled: proposal
-                .cancelled
-                .into_iter()
-                .map(|key| key.to_string())
-                .collect(),
-        })
+        parse_squads_pubkey(source, "来源 Token 账户").map_err(|message| ApiError { message })?
     } else {
-        None
+        squads_v4::associated_token_address(&vault, &mint)
+    };
+    let destination_override = req
+        .destination_token_account
+        .as_deref()
+        .map(str::trim)
+        .filter(|s| !s.is_empty());
+    let destination = if let Some(destination) = destination_override {
+        parse_squads_pubkey(destination, "接收 Token 账户")
+            .map_err(|message| ApiError { message })?
+    } else if let Some(recipient) = recipient {
+        squads_v4::associated_token_address(&recipient, &mint)
+    } else {
+        return Err(ApiError {
+            message: "接收钱包地址或接收 Token 账户不能为空".to_string(),
+        });
     };
+    let transfer_ix = squads_v4::token_transfer_checked_ix(
+        &source,
+        &mint,
+        &destination,
+        &vault,
+        token_amount,
+        decimals,
+    );
+    let mut inner_instructions = Vec::new();
+    if destination_override.is_none() {
+        if let Some(recipient) = recipient {
+            inner_instructions.push(squads_v4::create_associated_token_account_idempotent_ix(
+                &vault, &recipient, &mint,
+            ));
+        }
+    }
+    inner_instructions.push(transfer_ix);
+    let (tx_create_ix, transaction, vault, _) = squads_v4::vault_transaction_create_ix(
+        &multisig_key,
+        &signer.pubkey(),
+        transaction_index,
+        0,
+        &inner_instructions,
+        &[],
+        req.memo,
+    )
+    .map_err(|message| ApiError { message })?;
+    let (proposal_ix, proposal) =
+        squads_v4::proposal_create_ix(&multisig_key, &signer.pubkey(), transaction_index, false)
+            .map_err(|message| ApiError { message })?;
+    let signature = sign_and_send(
+        &client,
+        vec![tx_create_ix, proposal_ix],
+        &[&signer],
+        &signer.pubkey(),
+    )?;
 
-    Ok(Json(SquadsInfoResponse {
+    Ok(Json(SquadsProposalCreateResponse {
         multisig: multisig_key.to_string(),
-        vault: squads_v4::vault_pda(&multisig_key, 0).to_string(),
-        create_key: multisig.create_key.to_string(),
-        threshold: multisig.threshold,
-        time_lock: multisig.time_lock,
-        transaction_index: multisig.transaction_index,
-        stale_transaction_index: multisig.stale_transaction_index,
-        members: multisig
-            .members
-            .into_iter()
-            .map(|member| SquadsMemberResponse {
-                key: member.key.to_string(),
-                permissions: member.permissions.mask,
-            })
-            .collect(),
-        proposal,
+        vault: vault.to_string(),
+        transaction: transaction.to_string(),
+        proposal: proposal.to_string(),
+        transaction_index,
+        signature,
         network,
+        status: "success".to_string(),
     }))
 }
 
 #[derive(Deserialize)]
-struct SquadsProposalsRequest {
+struct SquadsPrepareUpgradeBufferRequest {
+    #[serde(flatten)]
+    wallet: WalletAuthRequest,
     multisig: String,
+    program_so_base64: String,
     #[serde(default)]
-    network: Option<String>,
+    expected_program_id: Option<String>,
     #[serde(default)]
-    limit: Option<u64>,
-}
-
-#[derive(Serialize)]
-struct SquadsProposalsResponse {
-    multisig: String,
-    vault: String,
-    proposals: Vec<SquadsProposalResponse>,
-    latest_transaction_index: u64,
-    network: String,
-}
-
-async fn squads_proposals(
-    Json(req): Json<SquadsProposalsRequest>,
-) -> Result<Json<SquadsProposalsResponse>, ApiError> {
-    let multisig_key = squads_v4::parse_pubkey(&req.multisig, "多签地址")
-        .map_err(|message| ApiError { message })?;
-    let (client, network) = rpc_client_for(req.network.as_deref())?;
-    let multisig = load_squads_multisig(&client, &multisig_key)?;
-    let limit = req.limit.unwrap_or(20).clamp(1, 50);
-    let start = multisig.transaction_index;
-    let end = start.saturating_sub(limit.saturating_sub(1));
-    let mut proposals = Vec::new();
-
-    for index in (end..=start).rev() {
-        let proposal_key = squads_v4::proposal_pda(&multisig_key, index);
-        if let Ok(proposal) = load_squads_proposal(&client, &proposal_key) {
-            proposals.push(SquadsProposalResponse {
-                address: proposal_key.to_string(),
-                transaction_index: proposal.transaction_index,
-                status: proposal.status.label().to_string(),
-                approved: proposal
-                    .approved
-                    .into_iter()
-                    .map(|key| key.to_string())
-                    .collect(),
-                rejected: proposal
-                    .rejected
-                    .into_iter()
-                    .map(|key| key.to_string())
-                    .collect(),
-                cancelled: proposal
-                    .cancelled
-                    .into_iter()
-                    .map(|key| key.to_string())
-                    .collect(),
-            });
-        }
-    }
-
-    Ok(Json(SquadsProposalsResponse {
-        multisig: multisig_key.to_string(),
-        vault: squads_v4::vault_pda(&multisig_key, 0).to_string(),
-        proposals,
-        latest_transaction_index: multisig.transaction_index,
-        network,
-    }))
+    network: Option<String>,
 }
 
 #[derive(Serialize)]
-struct SquadsProposalCreateResponse {
+struct SquadsPrepareUpgradeBufferResponse {
     multisig: String,
     vault: String,
-    transaction: String,
-    proposal: String,
-    transaction_index: u64,
-    signature: String,
+    buffer_address: String,
+    create_signature: String,
+    authority_signature: String,
     network: String,
+    program_bytes: usize,
+    rent_lamports: u64,
+    write_signatures: Vec<String>,
     status: String,
 }
 
+async fn squads_prepare_upgrade_buffer(
+    Json(req): Json<SquadsPrepareUpgradeBufferRequest>,
+) -> Result<Json<SquadsPrepareUpgradeBufferResponse>, ApiError> {
+    let program_bytes = decode_program_binary_base64(&req.program_so_base64)?;
+    if let Some(expected_program_id) = req
+        .expected_program_id
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+    {
+        let expected_program_id = Pubkey::from_str(expected_program_id).map_err(|_| ApiError {
+            message: "无效的目标 Program ID".to_string(),
+        })?;
+        program_deploy::require_anchor_declared_program_id(&program_bytes, &expected_program_id)
+            .map_err(|message| ApiError { message })?;
+    }
+    let program_bytes = verify_program_binary_offline(program_bytes).await?;
+
+    let payer = req.wallet.keypair()?;
+    let payer_pubkey = payer.pubkey();
+    let multisig =
+        parse_squads_pubkey(&req.multisig, "多签地址").map_err(|message| ApiError { message })?;
+    let vault = squads_v4::vault_pda(&multisig, 0);
+    let buffer_keypair = Keypair::new();
+    let (client, network) = rpc_client_for(req.network.as_deref())?;
+    let multisig_state = load_squads_multisig(&client, &multisig)?;
+    require_squads_member(&multisig_state, &payer_pubkey)?;
+    let buffer_lamports = client
+        .get_minimum_balance_for_rent_exemption(UpgradeableLoaderState::size_of_buffer(
+            program_bytes.len(),
+        ))
+        .map_err(|e| ApiError {
+            message: format!("计算 buffer 租金失败: {}", e),
+        })?;
+
+    let create_buffer_ixs = loader_v3_instruction::create_buffer(
+        &payer_pubkey,
+        &buffer_keypair.pubkey(),
+        &payer_pubkey,
+        buffer_lamports,
+        program_bytes.len(),
+    )
+    .map_err(|e| ApiError {
+        message: format!("创建 buffer 指令失败: {}", e),
+    })?;
+    let create_signature = sign_and_send_with_commitment(
+        &client,
+        create_buffer_ixs,
+        &[&payer, &buffer_keypair],
+        &payer_pubkey,
+        "创建多签升级 Buffer",
+        CommitmentConfig::finalized(),
+        "finalized",
+        Duration::from_secs(90),
+    )
+    .await?
+    .0;
+
+    let write_chunk_count = program_bytes.chunks(PROGRAM_WRITE_CHUNK_BYTES).count();
+    let mut write_signatures = Vec::with_capacity(write_chunk_count);
+    for (index, chunk) in program_bytes.chunks(PROGRAM_WRITE_CHUNK_BYTES).enumerate() {
+        let offset = index
+            .checked_mul(PROGRAM_WRITE_CHUNK_BYTES)
+            .and_then(|value| u32::try_from(value).ok())
+            .ok_or_else(|| ApiError {
+                message: "Program 写入偏移超出范围".to_string(),
+            })?;
+        let write_ix = loader_v3_instruction::write(
+            &buffer_keypair.pubkey(),
+            &payer_pubkey,
+            offset,
+            chunk.to_vec(),
+        );
+        write_signatures.push(
+            sign_and_send_with_commitment(
+                &client,
+                vec![write_ix],
+                &[&payer],
+                &payer_pubkey,
+                &format!("写入多签升级 Buffer ({}/{})", index + 1, write_chunk_count),
+                CommitmentConfig::confirmed(),
+                "confirmed",
+                Duration::from_secs(120),
+            )
+            .await?
+            .0,
+        );
+    }
+
+    let set_authority_ix =
+        squads_v4::set_buffer_authority_ix(&buffer_keypair.pubkey(), &payer_pubkey, &vault);
+    let authority_signature = sign_and_send_with_commitment(
+        &client,
+        vec![set_authority_ix],
+        &[&payer],
+        &payer_pubkey,
+        "移交升级 Buffer 给 Squads vault",
+        CommitmentConfig::confirmed(),
+        "confirmed",
+        Duration::from_secs(90),
+    )
+    .await?
+    .0;
+
+    Ok(Json(SquadsPrepareUpgradeBufferResponse {
+        multisig: multisig.to_string(),
+        vault: vault.to_string(),
+        buffer_address: buffer_keypair.pubkey().to_string(),
+        create_signature,
+        authority_signature,
+        network,
+        program_bytes: program_bytes.len(),
+        rent_lamports: buffer_lamports,
+        write_signatures,
+        status: "success".to_string(),
+    }))
+}
+
 #[derive(Deserialize)]
-struct SquadsSolTransferProposalRequest {
+struct SquadsProgramUpgradeProposalRequest {
     #[serde(flatten)]
     wallet: WalletAuthRequest,
     multisig: String,
-    to_address: String,
-    amount: DecimalAmount,
+    program_id: String,
+    buffer_address: String,
+    #[serde(default)]
+    spill_address: Option<String>,
     #[serde(default)]
     memo: Option<String>,
     #[serde(default)]
     network: Option<String>,
 }
 
-async fn squads_sol_transfer_proposal(
-    Json(req): Json<SquadsSolTransferProposalRequest>,
+async fn squads_program_upgrade_proposal(
+    Json(req): Json<SquadsProgramUpgradeProposalRequest>,
 ) -> Result<Json<SquadsProposalCreateResponse>, ApiError> {
-    let signer = req.wallet.keypair()?;
-    let multisig_key = squads_v4::parse_pubkey(&req.multisig, "多签地址")
+    let program_id = parse_squads_pubkey(&req.program_id, "Program ID")
         .map_err(|message| ApiError { message })?;
-    let to_pubkey = squads_v4::parse_pubkey(&req.to_address, "接收地址")
+
+    let signer = req.wallet.keypair()?;
+    let multisig_key =
+        parse_squads_pubkey(&req.multisig, "多签地址").map_err(|message| ApiError { message })?;
+    let buffer = parse_squads_pubkey(&req.buffer_address, "buffer 地址")
         .map_err(|message| ApiError { message })?;
-    let amount_lamports = sol_to_lamports(&req.amount)?;
+    let spill = if let Some(spill) = req
+        .spill_address
+        .as_deref()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+    {
+        parse_squads_pubkey(spill, "spill 地址").map_err(|message| ApiError { message })?
+    } else {
+        signer.pubkey()
+    };
     if let Some(memo) = &req.memo {
         validate_text_len(memo, "memo", MAX_TEXT_FIELD_CHARS)?;
     }
 
     let (client, network) = rpc_client_for(req.network.as_deref())?;
     let multisig = load_squads_multisig(&client, &multisig_key)?;
+    require_squads_member(&multisig, &signer.pubkey())?;
     let transaction_index = next_squads_transaction_index(&multisig)?;
     let vault = squads_v4::vault_pda(&multisig_key, 0);
-    let transfer_ix = squads_v4::sol_transfer_ix(&vault, &to_pubkey, amount_lamports);
+    require_program_upgrade_authority(&client, &program_id, &vault)?;
+    require_upgrade_buffer_authority(&client, &buffer, &vault)?;
+    let upgrade_ix = squads_v4::upgrade_program_ix(&program_id, &buffer, &vault, &spill);
     let (tx_create_ix, transaction, vault, _) = squads_v4::vault_transaction_create_ix(
         &multisig_key,
         &signer.pubkey(),
         transaction_index,
         0,
-        &[transfer_ix],
+        &[upgrade_ix],
+        &[],
         req.memo,
     )
     .map_err(|message| ApiError { message })?;
@@ -12538,120 +15341,153 @@ async fn squads_sol_transfer_proposal(
 }
 
 #[derive(Deserialize)]
-struct SquadsTokenTransferProposalRequest {
+struct SquadsInstructionRequest {
+    program_id: String,
+    #[serde(default)]
+    instruction_name: Option<String>,
+    accounts: Vec<GenericProgramAccountMetaRequest>,
+    data_base64: String,
+}
+
+#[derive(Deserialize)]
+struct SquadsInstructionsProposalRequest {
     #[serde(flatten)]
     wallet: WalletAuthRequest,
     multisig: String,
-    mint: String,
-    #[serde(default)]
-    recipient: Option<String>,
-    amount: DecimalAmount,
-    #[allow(dead_code)]
-    #[serde(default)]
-    decimals: Option<u8>,
+    instructions: Vec<SquadsInstructionRequest>,
     #[serde(default)]
-    source_token_account: Option<String>,
+    address_lookup_tables: Vec<String>,
     #[serde(default)]
-    destination_token_account: Option<String>,
+    vault_index: Option<u8>,
     #[serde(default)]
     memo: Option<String>,
     #[serde(default)]
     network: Option<String>,
 }
 
-async fn squads_token_transfer_proposal(
-    Json(req): Json<SquadsTokenTransferProposalRequest>,
-) -> Result<Json<SquadsProposalCreateResponse>, ApiError> {
-    let signer = req.wallet.keypair()?;
-    let multisig_key = squads_v4::parse_pubkey(&req.multisig, "多签地址")
-        .map_err(|message| ApiError { message })?;
-    let mint =
-        squads_v4::parse_pubkey(&req.mint, "Token Mint").map_err(|message| ApiError { message })?;
-    let recipient = if let Some(recipient) = req
-        .recipient
-        .as_deref()
-        .map(str::trim)
-        .filter(|s| !s.is_empty())
-    {
-        Some(
-            squads_v4::parse_pubkey(recipient, "接收钱包地址")
-                .map_err(|message| ApiError { message })?,
-        )
-    } else {
-        None
-    };
-    if let Some(memo) = &req.memo {
-        validate_text_len(memo, "memo", MAX_TEXT_FIELD_CHARS)?;
+fn build_squads_inner_instructions(
+    instructions: &[SquadsInstructionRequest],
+    vault: &Pubkey,
+) -> Result<Vec<Instruction>, ApiError> {
+    if instructions.is_empty() {
+        return Err(ApiError {
+            message: "Squads 提案至少需要一条指令".to_string(),
+        });
+    }
+    if instructions.len() > MAX_SQUADS_PROPOSAL_INSTRUCTIONS {
+        return Err(ApiError {
+            message: format!(
+                "单个 Squads 交易最多包含 {} 条指令",
+                MAX_SQUADS_PROPOSAL_INSTRUCTIONS
+            ),
+        });
     }
+    instructions
+        .iter()
+        .map(|request| {
+            if let Some(name) = &request.instruction_name {
+                validate_text_len(name, "指令名称", MAX_LABEL_CHARS)?;
+            }
+            let instruction = build_generic_program_instruction(
+                &request.program_id,
+                &request.accounts,
+                &request.data_base64,
+            )?;
+            if let Some(account) = instruction
+                .accounts
+                .iter()
+                .find(|account| account.is_signer && account.pubkey != *vault)
+            {
+                return Err(ApiError {
+                    message: format!(
+                        "Squads 内部指令只能由 vault {} 签名，账户 {} 不能作为 signer",
+                        vault, account.pubkey
+                    ),
+                });
+            }
+            Ok(instruction)
+        })
+        .collect()
+}
 
-    let (client, network) = rpc_client_for(req.network.as_deref())?;
-    let mint_info = token_mint_info_from_rpc(&client, &mint)?;
-    let decimals = mint_info.decimals;
-    let token_amount = token_amount_to_raw(&req.amount, decimals)?;
-    let multisig = load_squads_multisig(&client, &multisig_key)?;
-    let transaction_index = next_squads_transaction_index(&multisig)?;
-    let vault = squads_v4::vault_pda(&multisig_key, 0);
-    let source = if let Some(source) = req
-        .source_token_account
-        .as_deref()
-        .map(str::trim)
-        .filter(|s| !s.is_empty())
-    {
-        squads_v4::parse_pubkey(source, "来源 Token 账户")
-            .map_err(|message| ApiError { message })?
-    } else {
-        squads_v4::associated_token_address(&vault, &mint)
-    };
-    let destination_override = req
-        .destination_token_account
-        .as_deref()
-        .map(str::trim)
-        .filter(|s| !s.is_empty());
-    let destination = if let Some(destination) = destination_override {
-        squads_v4::parse_pubkey(destination, "接收 Token 账户")
-            .map_err(|message| ApiError { message })?
-    } else if let Some(recipient) = recipient {
-        squads_v4::associated_token_address(&recipient, &mint)
-    } else {
+fn parse_squads_pubkey(value: &str, field: &str) -> Result<Pubkey, String> {
+    Pubkey::from_str(value.trim()).map_err(|_| format!("无效的 {}", field))
+}
+
+fn parse_squads_lookup_table_keys(keys: &[String]) -> Result<Vec<Pubkey>, ApiError> {
+    if keys.len() > MAX_SQUADS_ADDRESS_LOOKUP_TABLES {
+        return Err(ApiError {
+            message: format!(
+                "最多使用 {} 个 Address Lookup Table",
+                MAX_SQUADS_ADDRESS_LOOKUP_TABLES
+            ),
+        });
+    }
+    let mut parsed = Vec::with_capacity(keys.len());
+    for key in keys {
+        let key = parse_squads_pubkey(key, "Address Lookup Table")
+            .map_err(|message| ApiError { message })?;
+        if !parsed.contains(&key) {
+            parsed.push(key);
+        }
+    }
+    Ok(parsed)
+}
+
+fn require_squads_transaction_fits(
+    instructions: &[Instruction],
+    payer: &Pubkey,
+    label: &str,
+) -> Result<(), ApiError> {
+    let transaction = Transaction::new_with_payer(instructions, Some(payer));
+    let size = bincode::serialized_size(&transaction).map_err(|e| ApiError {
+        message: format!("计算交易大小失败: {}", e),
+    })?;
+    if size > SOLANA_TRANSACTION_PACKET_DATA_BYTES as u64 {
         return Err(ApiError {
-            message: "接收钱包地址或接收 Token 账户不能为空".to_string(),
+            message: format!(
+                "{label}交易为 {size} bytes，超过 {SOLANA_TRANSACTION_PACKET_DATA_BYTES} bytes；请使用 Address Lookup Table 或拆分为 Squads batch"
+            ),
         });
-    };
-    let transfer_ix = squads_v4::token_transfer_checked_ix(
-        &source,
-        &mint,
-        &destination,
-        &vault,
-        token_amount,
-        decimals,
-    );
-    let mut inner_instructions = Vec::new();
-    if destination_override.is_none() {
-        if let Some(recipient) = recipient {
-            inner_instructions.push(squads_v4::create_associated_token_account_idempotent_ix(
-                &vault, &recipient, &mint,
-            ));
-        }
     }
-    inner_instructions.push(transfer_ix);
+    Ok(())
+}
+
+async fn squads_instructions_proposal(
+    Json(req): Json<SquadsInstructionsProposalRequest>,
+) -> Result<Json<SquadsProposalCreateResponse>, ApiError> {
+    let signer = req.wallet.keypair()?;
+    let multisig_key =
+        parse_squads_pubkey(&req.multisig, "多签地址").map_err(|message| ApiError { message })?;
+    let vault_index = req.vault_index.unwrap_or(0);
+    let vault = squads_v4::vault_pda(&multisig_key, vault_index);
+    let inner_instructions = build_squads_inner_instructions(&req.instructions, &vault)?;
+    let lookup_table_keys = parse_squads_lookup_table_keys(&req.address_lookup_tables)?;
+    if let Some(memo) = &req.memo {
+        validate_text_len(memo, "memo", MAX_TEXT_FIELD_CHARS)?;
+    }
+
+    let (client, network) = rpc_client_for(req.network.as_deref())?;
+    let multisig = load_squads_multisig(&client, &multisig_key)?;
+    require_squads_member(&multisig, &signer.pubkey())?;
+    let transaction_index = next_squads_transaction_index(&multisig)?;
+    let address_lookup_tables = load_address_lookup_tables(&client, &lookup_table_keys)?;
     let (tx_create_ix, transaction, vault, _) = squads_v4::vault_transaction_create_ix(
         &multisig_key,
         &signer.pubkey(),
         transaction_index,
-        0,
+        vault_index,
         &inner_instructions,
+        &address_lookup_tables,
         req.memo,
     )
     .map_err(|message| ApiError { message })?;
     let (proposal_ix, proposal) =
         squads_v4::proposal_create_ix(&multisig_key, &signer.pubkey(), transaction_index, false)
             .map_err(|message| ApiError { message })?;
-    let signature = sign_and_send(
-        &client,
-        vec![tx_create_ix, proposal_ix],
-        &[&signer],
-        &signer.pubkey(),
-    )?;
+    let instructions = vec![tx_create_ix, proposal_ix];
+    require_squads_transaction_fits(&instructions, &signer.pubkey(), "Squads 提案")?;
+    let signature = sign_and_send(&client, instructions, &[&signer], &signer.pubkey())?;
 
     Ok(Json(SquadsProposalCreateResponse {
         multisig: multisig_key.to_string(),
@@ -12666,223 +15502,439 @@ async fn squads_token_transfer_proposal(
 }
 
 #[derive(Deserialize)]
-struct SquadsPrepareUpgradeBufferRequest {
+struct SquadsBatchTransactionRequest {
+    instructions: Vec<SquadsInstructionRequest>,
+}
+
+#[derive(Deserialize)]
+struct SquadsBatchCreateRequest {
     #[serde(flatten)]
     wallet: WalletAuthRequest,
     multisig: String,
-    program_so_base64: String,
+    transactions: Vec<SquadsBatchTransactionRequest>,
     #[serde(default)]
-    expected_program_id: Option<String>,
+    address_lookup_tables: Vec<String>,
+    #[serde(default)]
+    vault_index: Option<u8>,
+    #[serde(default)]
+    memo: Option<String>,
     #[serde(default)]
     network: Option<String>,
 }
 
 #[derive(Serialize)]
-struct SquadsPrepareUpgradeBufferResponse {
+struct SquadsBatchCreateResponse {
     multisig: String,
     vault: String,
-    buffer_address: String,
-    create_signature: String,
-    authority_signature: String,
+    batch: String,
+    proposal: String,
+    transaction_index: u64,
+    batch_transactions: Vec<String>,
+    signatures: Vec<String>,
     network: String,
-    program_bytes: usize,
-    rent_lamports: u64,
-    write_signatures: Vec<String>,
     status: String,
 }
 
-async fn squads_prepare_upgrade_buffer(
-    Json(req): Json<SquadsPrepareUpgradeBufferRequest>,
-) -> Result<Json<SquadsPrepareUpgradeBufferResponse>, ApiError> {
-    let program_bytes = decode_program_binary_base64(&req.program_so_base64)?;
-    if let Some(expected_program_id) = req
-        .expected_program_id
-        .as_deref()
-        .map(str::trim)
-        .filter(|value| !value.is_empty())
-    {
-        let expected_program_id = Pubkey::from_str(expected_program_id).map_err(|_| ApiError {
-            message: "无效的目标 Program ID".to_string(),
-        })?;
-        program_deploy::require_anchor_declared_program_id(&program_bytes, &expected_program_id)
-            .map_err(|message| ApiError { message })?;
+async fn squads_batch_create(
+    Json(req): Json<SquadsBatchCreateRequest>,
+) -> Result<Json<SquadsBatchCreateResponse>, ApiError> {
+    let signer = req.wallet.keypair()?;
+    let signer_pubkey = signer.pubkey();
+    let multisig_key =
+        parse_squads_pubkey(&req.multisig, "多签地址").map_err(|message| ApiError { message })?;
+    if req.transactions.is_empty() || req.transactions.len() > MAX_SQUADS_BATCH_TRANSACTIONS {
+        return Err(ApiError {
+            message: format!(
+                "Squads batch 需要 1 到 {} 笔交易",
+                MAX_SQUADS_BATCH_TRANSACTIONS
+            ),
+        });
+    }
+    let vault_index = req.vault_index.unwrap_or(0);
+    let vault = squads_v4::vault_pda(&multisig_key, vault_index);
+    let batch_instructions = req
+        .transactions
+        .iter()
+        .map(|transaction| build_squads_inner_instructions(&transaction.instructions, &vault))
+        .collect::<Result<Vec<_>, _>>()?;
+    let lookup_table_keys = parse_squads_lookup_table_keys(&req.address_lookup_tables)?;
+    if let Some(memo) = &req.memo {
+        validate_text_len(memo, "memo", MAX_TEXT_FIELD_CHARS)?;
     }
-    let program_bytes = verify_program_binary_offline(program_bytes).await?;
 
-    let payer = req.wallet.keypair()?;
-    let payer_pubkey = payer.pubkey();
-    let multisig = squads_v4::parse_pubkey(&req.multisig, "多签地址")
-        .map_err(|message| ApiError { message })?;
-    let vault = squads_v4::vault_pda(&multisig, 0);
-    let buffer_keypair = Keypair::new();
     let (client, network) = rpc_client_for(req.network.as_deref())?;
-    let multisig_state = load_squads_multisig(&client, &multisig)?;
-    require_squads_member(&multisig_state, &payer_pubkey)?;
-    let buffer_lamports = client
-        .get_minimum_balance_for_rent_exemption(UpgradeableLoaderState::size_of_buffer(
-            program_bytes.len(),
-        ))
-        .map_err(|e| ApiError {
-            message: format!("计算 buffer 租金失败: {}", e),
+    let multisig = load_squads_multisig(&client, &multisig_key)?;
+    require_squads_member(&multisig, &signer_pubkey)?;
+    let batch_index = next_squads_transaction_index(&multisig)?;
+    let address_lookup_tables = load_address_lookup_tables(&client, &lookup_table_keys)?;
+
+    let mut add_instructions = Vec::with_capacity(batch_instructions.len());
+    let mut batch_transactions = Vec::with_capacity(batch_instructions.len());
+    for (position, instructions) in batch_instructions.iter().enumerate() {
+        let transaction_index = u32::try_from(position + 1).map_err(|_| ApiError {
+            message: "Squads batch 交易数量超出范围".to_string(),
         })?;
+        let (ix, transaction, _) = squads_v4::batch_add_transaction_ix(
+            &multisig_key,
+            &signer_pubkey,
+            batch_index,
+            transaction_index,
+            vault_index,
+            instructions,
+            &address_lookup_tables,
+        )
+        .map_err(|message| ApiError { message })?;
+        require_squads_transaction_fits(
+            std::slice::from_ref(&ix),
+            &signer_pubkey,
+            &format!("Squads batch 第 {} 笔", position + 1),
+        )?;
+        add_instructions.push(ix);
+        batch_transactions.push(transaction.to_string());
+    }
 
-    let create_buffer_ixs = loader_v3_instruction::create_buffer(
-        &payer_pubkey,
-        &buffer_keypair.pubkey(),
-        &payer_pubkey,
-        buffer_lamports,
-        program_bytes.len(),
+    let (batch_ix, batch) = squads_v4::batch_create_ix(
+        &multisig_key,
+        &signer_pubkey,
+        batch_index,
+        vault_index,
+        req.memo,
     )
-    .map_err(|e| ApiError {
-        message: format!("创建 buffer 指令失败: {}", e),
-    })?;
-    let create_signature = sign_and_send_with_commitment(
+    .map_err(|message| ApiError { message })?;
+    let (proposal_ix, proposal) =
+        squads_v4::proposal_create_ix(&multisig_key, &signer_pubkey, batch_index, true)
+            .map_err(|message| ApiError { message })?;
+    let mut signatures = Vec::with_capacity(add_instructions.len() + 2);
+    signatures.push(sign_and_send(
         &client,
-        create_buffer_ixs,
-        &[&payer, &buffer_keypair],
-        &payer_pubkey,
-        "创建多签升级 Buffer",
-        CommitmentConfig::finalized(),
-        "finalized",
-        Duration::from_secs(90),
-    )
-    .await?
-    .0;
-
-    let write_chunk_count = program_bytes.chunks(PROGRAM_WRITE_CHUNK_BYTES).count();
-    let mut write_signatures = Vec::with_capacity(write_chunk_count);
-    for (index, chunk) in program_bytes.chunks(PROGRAM_WRITE_CHUNK_BYTES).enumerate() {
-        let offset = index
-            .checked_mul(PROGRAM_WRITE_CHUNK_BYTES)
-            .and_then(|value| u32::try_from(value).ok())
-            .ok_or_else(|| ApiError {
-                message: "Program 写入偏移超出范围".to_string(),
-            })?;
-        let write_ix = loader_v3_instruction::write(
-            &buffer_keypair.pubkey(),
-            &payer_pubkey,
-            offset,
-            chunk.to_vec(),
-        );
-        write_signatures.push(
-            sign_and_send_with_commitment(
-                &client,
-                vec![write_ix],
-                &[&payer],
-                &payer_pubkey,
-                &format!("写入多签升级 Buffer ({}/{})", index + 1, write_chunk_count),
-                CommitmentConfig::confirmed(),
-                "confirmed",
-                Duration::from_secs(120),
-            )
-            .await?
-            .0,
-        );
+        vec![batch_ix, proposal_ix],
+        &[&signer],
+        &signer_pubkey,
+    )?);
+    for ix in add_instructions {
+        signatures.push(sign_and_send_single(&client, ix, &signer)?);
     }
+    let activate_ix = squads_v4::proposal_activate_ix(&multisig_key, &proposal, &signer_pubkey);
+    signatures.push(sign_and_send_single(&client, activate_ix, &signer)?);
 
-    let set_authority_ix =
-        squads_v4::set_buffer_authority_ix(&buffer_keypair.pubkey(), &payer_pubkey, &vault);
-    let authority_signature = sign_and_send_with_commitment(
-        &client,
-        vec![set_authority_ix],
-        &[&payer],
-        &payer_pubkey,
-        "移交升级 Buffer 给 Squads vault",
-        CommitmentConfig::confirmed(),
-        "confirmed",
-        Duration::from_secs(90),
-    )
-    .await?
-    .0;
-
-    Ok(Json(SquadsPrepareUpgradeBufferResponse {
-        multisig: multisig.to_string(),
+    Ok(Json(SquadsBatchCreateResponse {
+        multisig: multisig_key.to_string(),
         vault: vault.to_string(),
-        buffer_address: buffer_keypair.pubkey().to_string(),
-        create_signature,
-        authority_signature,
+        batch: batch.to_string(),
+        proposal: proposal.to_string(),
+        transaction_index: batch_index,
+        batch_transactions,
+        signatures,
         network,
-        program_bytes: program_bytes.len(),
-        rent_lamports: buffer_lamports,
-        write_signatures,
         status: "success".to_string(),
     }))
 }
 
 #[derive(Deserialize)]
-struct SquadsProgramUpgradeProposalRequest {
+struct SquadsPayoutsRequest {
     #[serde(flatten)]
     wallet: WalletAuthRequest,
     multisig: String,
-    program_id: String,
-    buffer_address: String,
+    #[serde(flatten)]
+    input: PayoutInputRequest,
     #[serde(default)]
-    spill_address: Option<String>,
+    vault_index: Option<u8>,
     #[serde(default)]
     memo: Option<String>,
     #[serde(default)]
     network: Option<String>,
 }
 
-async fn squads_program_upgrade_proposal(
-    Json(req): Json<SquadsProgramUpgradeProposalRequest>,
-) -> Result<Json<SquadsProposalCreateResponse>, ApiError> {
-    let program_id = squads_v4::parse_pubkey(&req.program_id, "Program ID")
+#[derive(Serialize)]
+struct SquadsPayoutsResponse {
+    #[serde(flatten)]
+    batch: SquadsBatchCreateResponse,
+    preview: PayoutPreviewResponse,
+}
+
+/// Propose a payout list from a Squads vault as one batch proposal, one batch transaction
+/// per packed payout transaction
+async fn squads_payouts(
+    Json(req): Json<SquadsPayoutsRequest>,
+) -> Result<Json<SquadsPayoutsResponse>, ApiError> {
+    let signer = req.wallet.keypair()?;
+    let signer_pubkey = signer.pubkey();
+    let multisig_key =
+        parse_squads_pubkey(&req.multisig, "多签地址").map_err(|message| ApiError { message })?;
+    let entries = req.input.entries()?;
+    if let Some(memo) = &req.memo {
+        validate_text_len(memo, "memo", MAX_TEXT_FIELD_CHARS)?;
+    }
+    let vault_index = req.vault_index.unwrap_or(0);
+    let vault = squads_v4::vault_pda(&multisig_key, vault_index);
+
+    let (client, network) = rpc_client_for(req.network.as_deref())?;
+    let multisig = load_squads_multisig(&client, &multisig_key)?;
+    require_squads_member(&multisig, &signer_pubkey)?;
+    let batch_index = next_squads_transaction_index(&multisig)?;
+
+    let mut plan = SolanaClient::new(client.url())
+        .plan_payouts(&vault, &entries, &HashSet::new())
+        .map_err(|e| ApiError {
+            message: format!("生成批量转账预览失败: {}", e),
+        })?;
+    if !plan.is_executable() {
+        let issues: Vec<String> = plan.errors.iter().map(ToString::to_string).collect();
+        return Err(ApiError {
+            message: format!("批量转账校验未通过:\n{}", issues.join("\n")),
+        });
+    }
+    // Each packed group must fit as a batch transaction message, which is larger than a plain transaction
+    plan.repack(|instructions| {
+        squads_v4::batch_add_transaction_ix(
+            &multisig_key,
+            &signer_pubkey,
+            batch_index,
+            u32::MAX,
+            vault_index,
+            instructions,
+            &[],
+        )
+        .is_ok_and(|(ix, _, _)| {
+            require_squads_transaction_fits(std::slice::from_ref(&ix), &signer_pubkey, "").is_ok()
+        })
+    })
+    .map_err(|e| ApiError {
+        message: format!("批量转账无法打包为 Squads batch 交易: {}", e),
+    })?;
+    if plan.transactions.len() > MAX_SQUADS_BATCH_TRANSACTIONS {
+        return Err(ApiError {
+            message: format!(
+                "批量转账需要 {} 笔 batch 交易，超过上限 {}；请拆分列表",
+                plan.transactions.len(),
+                MAX_SQUADS_BATCH_TRANSACTIONS
+            ),
+        });
+    }
+
+    let mut add_instructions = Vec::with_capacity(plan.transactions.len());
+    let mut batch_transactions = Vec::with_capacity(plan.transactions.len());
+    for (position, transaction) in plan.transactions.iter().enumerate() {
+        let transaction_index = u32::try_from(position + 1).map_err(|_| ApiError {
+            message: "Squads batch 交易数量超出范围".to_string(),
+        })?;
+        let (ix, transaction, _) = squads_v4::batch_add_transaction_ix(
+            &multisig_key,
+            &signer_pubkey,
+            batch_index,
+            transaction_index,
+            vault_index,
+            &transaction.instructions,
+            &[],
+        )
         .map_err(|message| ApiError { message })?;
+        add_instructions.push(ix);
+        batch_transactions.push(transaction.to_string());
+    }
+
+    let (batch_ix, batch) = squads_v4::batch_create_ix(
+        &multisig_key,
+        &signer_pubkey,
+        batch_index,
+        vault_index,
+        req.memo,
+    )
+    .map_err(|message| ApiError { message })?;
+    let (proposal_ix, proposal) =
+        squads_v4::proposal_create_ix(&multisig_key, &signer_pubkey, batch_index, true)
+            .map_err(|message| ApiError { message })?;
+    let mut signatures = Vec::with_capacity(add_instructions.len() + 2);
+    signatures.push(sign_and_send(
+        &client,
+        vec![batch_ix, proposal_ix],
+        &[&signer],
+        &signer_pubkey,
+    )?);
+    for ix in add_instructions {
+        signatures.push(sign_and_send_single(&client, ix, &signer)?);
+    }
+    let activate_ix = squads_v4::proposal_activate_ix(&multisig_key, &proposal, &signer_pubkey);
+    signatures.push(sign_and_send_single(&client, activate_ix, &signer)?);
+
+    Ok(Json(SquadsPayoutsResponse {
+        batch: SquadsBatchCreateResponse {
+            multisig: multisig_key.to_string(),
+            vault: vault.to_string(),
+            batch: batch.to_string(),
+            proposal: proposal.to_string(),
+            transaction_index: batch_index,
+            batch_transactions,
+            signatures,
+            network: network.clone(),
+            status: "success".to_string(),
+        },
+        preview: PayoutPreviewResponse::from_plan(&plan, network),
+    }))
+}
+
+#[derive(Deserialize)]
+struct SquadsStakeProposalRequest {
+    #[serde(flatten)]
+    wallet: WalletAuthRequest,
+    multisig: String,
+    #[serde(flatten)]
+    action: StakeActionRequest,
+    #[serde(default)]
+    memo: Option<String>,
+    #[serde(default)]
+    network: Option<String>,
+}
+
+#[derive(Serialize)]
+struct SquadsStakeProposalResponse {
+    #[serde(flatten)]
+    proposal: SquadsProposalCreateResponse,
+    description: String,
+    stake_account: Option<String>,
+    warnings: Vec<String>,
+}
 
+/// Stake operation proposed as a vault transaction with the vault as authority and payer
+async fn squads_stake_proposal(
+    Json(req): Json<SquadsStakeProposalRequest>,
+) -> Result<Json<SquadsStakeProposalResponse>, ApiError> {
     let signer = req.wallet.keypair()?;
-    let multisig_key = squads_v4::parse_pubkey(&req.multisig, "多签地址")
-        .map_err(|message| ApiError { message })?;
-    let buffer = squads_v4::parse_pubkey(&req.buffer_address, "buffer 地址")
-        .map_err(|message| ApiError { message })?;
-    let spill = if let Some(spill) = req
-        .spill_address
-        .as_deref()
-        .map(str::trim)
-        .filter(|s| !s.is_empty())
-    {
-        squads_v4::parse_pubkey(spill, "spill 地址").map_err(|message| ApiError { message })?
-    } else {
-        signer.pubkey()
-    };
+    let signer_pubkey = signer.pubkey();
+    let multisig_key =
+        parse_squads_pubkey(&req.multisig, "多签地址").map_err(|message| ApiError { message })?;
+    let action = req.action.to_action()?;
+    // The vault cannot co-sign with a fresh keypair, so new accounts must be seed-derived
+    if matches!(
+        &action,
+        StakeAction::Create { seed: None, .. } | StakeAction::Split { seed: None, .. }
+    ) {
+        return Err(ApiError {
+            message: "通过 Squads 金库创建或拆分质押账户时必须提供 seed".to_string(),
+        });
+    }
     if let Some(memo) = &req.memo {
         validate_text_len(memo, "memo", MAX_TEXT_FIELD_CHARS)?;
     }
 
     let (client, network) = rpc_client_for(req.network.as_deref())?;
     let multisig = load_squads_multisig(&client, &multisig_key)?;
-    require_squads_member(&multisig, &signer.pubkey())?;
+    require_squads_member(&multisig, &signer_pubkey)?;
     let transaction_index = next_squads_transaction_index(&multisig)?;
     let vault = squads_v4::vault_pda(&multisig_key, 0);
-    require_program_upgrade_authority(&client, &program_id, &vault)?;
-    require_upgrade_buffer_authority(&client, &buffer, &vault)?;
-    let upgrade_ix = squads_v4::upgrade_program_ix(&program_id, &buffer, &vault, &spill);
+    let plan = SolanaClient::new(client.url())
+        .plan_stake_action(&vault, &action)
+        .map_err(|e| ApiError {
+            message: format!("质押操作校验失败: {}", e),
+        })?;
     let (tx_create_ix, transaction, vault, _) = squads_v4::vault_transaction_create_ix(
         &multisig_key,
-        &signer.pubkey(),
+        &signer_pubkey,
         transaction_index,
         0,
-        &[upgrade_ix],
+        &plan.instructions,
+        &[],
         req.memo,
     )
     .map_err(|message| ApiError { message })?;
     let (proposal_ix, proposal) =
-        squads_v4::proposal_create_ix(&multisig_key, &signer.pubkey(), transaction_index, false)
+        squads_v4::proposal_create_ix(&multisig_key, &signer_pubkey, transaction_index, false)
             .map_err(|message| ApiError { message })?;
     let signature = sign_and_send(
         &client,
         vec![tx_create_ix, proposal_ix],
         &[&signer],
-        &signer.pubkey(),
+        &signer_pubkey,
     )?;
 
-    Ok(Json(SquadsProposalCreateResponse {
-        multisig: multisig_key.to_string(),
-        vault: vault.to_string(),
-        transaction: transaction.to_string(),
-        proposal: proposal.to_string(),
-        transaction_index,
-        signature,
+    Ok(Json(SquadsStakeProposalResponse {
+        proposal: SquadsProposalCreateResponse {
+            multisig: multisig_key.to_string(),
+            vault: vault.to_string(),
+            transaction: transaction.to_string(),
+            proposal: proposal.to_string(),
+            transaction_index,
+            signature,
+            network,
+            status: "success".to_string(),
+        },
+        description: plan.description,
+        stake_account: plan.new_stake_account.map(|stake| stake.to_string()),
+        warnings: plan.warnings,
+    }))
+}
+
+#[derive(Deserialize)]
+struct SquadsBatchExecuteRequest {
+    #[serde(flatten)]
+    wallet: WalletAuthRequest,
+    multisig: String,
+    transaction_index: u64,
+    #[serde(default)]
+    execute_all: bool,
+    #[serde(default)]
+    network: Option<String>,
+}
+
+#[derive(Serialize)]
+struct SquadsBatchExecuteResponse {
+    batch: String,
+    executed_transaction_index: u32,
+    size: u32,
+    signatures: Vec<String>,
+    network: String,
+    status: String,
+}
+
+async fn squads_batch_execute(
+    Json(req): Json<SquadsBatchExecuteRequest>,
+) -> Result<Json<SquadsBatchExecuteResponse>, ApiError> {
+    let signer = req.wallet.keypair()?;
+    let multisig_key =
+        parse_squads_pubkey(&req.multisig, "多签地址").map_err(|message| ApiError { message })?;
+    let batch_key = squads_v4::transaction_pda(&multisig_key, req.transaction_index);
+    let (client, network) = rpc_client_for(req.network.as_deref())?;
+    let multisig = load_squads_multisig(&client, &multisig_key)?;
+    require_squads_member(&multisig, &signer.pubkey())?;
+    let mut batch = load_squads_batch(&client, &batch_key)?;
+    if batch.multisig != multisig_key || batch.index != req.transaction_index {
+        return Err(ApiError {
+            message: "Squads batch 账户与请求参数不匹配".to_string(),
+        });
+    }
+
+    let mut signatures = Vec::new();
+    while batch.executed_transaction_index < batch.size {
+        let next_index = batch.executed_transaction_index + 1;
+        let transaction_key =
+            squads_v4::batch_transaction_pda(&multisig_key, batch.index, next_index);
+        let batch_transaction = load_squads_batch_transaction(&client, &transaction_key)?;
+        let address_lookup_tables =
+            load_squads_message_lookup_tables(&client, &batch_transaction.message)?;
+        let ix = squads_v4::batch_execute_transaction_ix(
+            &multisig_key,
+            &signer.pubkey(),
+            &batch,
+            &batch_transaction,
+            &address_lookup_tables,
+        )
+        .map_err(|message| ApiError { message })?;
+        signatures.push(sign_and_send_single(&client, ix, &signer)?);
+        batch.executed_transaction_index = next_index;
+        if !req.execute_all {
+            break;
+        }
+    }
+    if signatures.is_empty() {
+        return Err(ApiError {
+            message: "Squads batch 中的交易已全部执行".to_string(),
+        });
+    }
+
+    Ok(Json(SquadsBatchExecuteResponse {
+        batch: batch_key.to_string(),
+        executed_transaction_index: batch.executed_transaction_index,
+        size: batch.size,
+        signatures,
         network,
         status: "success".to_string(),
     }))
@@ -12911,10 +15963,10 @@ async fn squads_proposal_approve(
     Json(req): Json<SquadsVoteRequest>,
 ) -> Result<Json<SquadsActionResponse>, ApiError> {
     let signer = req.wallet.keypair()?;
-    let multisig = squads_v4::parse_pubkey(&req.multisig, "多签地址")
-        .map_err(|message| ApiError { message })?;
-    let proposal = squads_v4::parse_pubkey(&req.proposal, "提案地址")
-        .map_err(|message| ApiError { message })?;
+    let multisig =
+        parse_squads_pubkey(&req.multisig, "多签地址").map_err(|message| ApiError { message })?;
+    let proposal =
+        parse_squads_pubkey(&req.proposal, "提案地址").map_err(|message| ApiError { message })?;
     if let Some(memo) = &req.memo {
         validate_text_len(memo, "memo", MAX_TEXT_FIELD_CHARS)?;
     }
@@ -12933,10 +15985,10 @@ async fn squads_proposal_reject(
     Json(req): Json<SquadsVoteRequest>,
 ) -> Result<Json<SquadsActionResponse>, ApiError> {
     let signer = req.wallet.keypair()?;
-    let multisig = squads_v4::parse_pubkey(&req.multisig, "多签地址")
-        .map_err(|message| ApiError { message })?;
-    let proposal = squads_v4::parse_pubkey(&req.proposal, "提案地址")
-        .map_err(|message| ApiError { message })?;
+    let multisig =
+        parse_squads_pubkey(&req.multisig, "多签地址").map_err(|message| ApiError { message })?;
+    let proposal =
+        parse_squads_pubkey(&req.proposal, "提案地址").map_err(|message| ApiError { message })?;
     if let Some(memo) = &req.memo {
         validate_text_len(memo, "memo", MAX_TEXT_FIELD_CHARS)?;
     }
@@ -12951,6 +16003,279 @@ async fn squads_proposal_reject(
     }))
 }
 
+async fn squads_proposal_cancel(
+    Json(req): Json<SquadsVoteRequest>,
+) -> Result<Json<SquadsActionResponse>, ApiError> {
+    let signer = req.wallet.keypair()?;
+    let multisig =
+        parse_squads_pubkey(&req.multisig, "多签地址").map_err(|message| ApiError { message })?;
+    let proposal =
+        parse_squads_pubkey(&req.proposal, "提案地址").map_err(|message| ApiError { message })?;
+    if let Some(memo) = &req.memo {
+        validate_text_len(memo, "memo", MAX_TEXT_FIELD_CHARS)?;
+    }
+    let (client, network) = rpc_client_for(req.network.as_deref())?;
+    let ix = squads_v4::proposal_cancel_ix(&multisig, &proposal, &signer.pubkey(), req.memo)
+        .map_err(|message| ApiError { message })?;
+    let signature = sign_and_send_single(&client, ix, &signer)?;
+    Ok(Json(SquadsActionResponse {
+        signature,
+        status: "success".to_string(),
+        network,
+    }))
+}
+
+async fn squads_proposal_activate(
+    Json(req): Json<SquadsVoteRequest>,
+) -> Result<Json<SquadsActionResponse>, ApiError> {
+    let signer = req.wallet.keypair()?;
+    let multisig =
+        parse_squads_pubkey(&req.multisig, "多签地址").map_err(|message| ApiError { message })?;
+    let proposal =
+        parse_squads_pubkey(&req.proposal, "提案地址").map_err(|message| ApiError { message })?;
+    let (client, network) = rpc_client_for(req.network.as_deref())?;
+    let state = load_squads_proposal(&client, &proposal)?;
+    if !matches!(state.status, squads_v4::ProposalStatus::Draft { .. }) {
+        return Err(ApiError {
+            message: format!("只能激活草稿提案，当前状态为 {}", state.status.label()),
+        });
+    }
+    let ix = squads_v4::proposal_activate_ix(&multisig, &proposal, &signer.pubkey());
+    let signature = sign_and_send_single(&client, ix, &signer)?;
+    Ok(Json(SquadsActionResponse {
+        signature,
+        status: "success".to_string(),
+        network,
+    }))
+}
+
+#[derive(Deserialize)]
+struct SquadsTransactionCloseRequest {
+    #[serde(flatten)]
+    wallet: WalletAuthRequest,
+    multisig: String,
+    transaction_index: u64,
+    #[serde(default)]
+    network: Option<String>,
+}
+
+async fn squads_transaction_close(
+    Json(req): Json<SquadsTransactionCloseRequest>,
+) -> Result<Json<SquadsActionResponse>, ApiError> {
+    let signer = req.wallet.keypair()?;
+    let multisig_key =
+        parse_squads_pubkey(&req.multisig, "多签地址").map_err(|message| ApiError { message })?;
+    let (client, network) = rpc_client_for(req.network.as_deref())?;
+    let multisig = load_squads_multisig(&client, &multisig_key)?;
+    let candidate =
+        load_squads_close_candidate(&client, &multisig_key, &multisig, req.transaction_index)?
+            .ok_or_else(|| ApiError {
+                message: format!(
+                    "Squads 交易 #{} 的账户已关闭或不存在",
+                    req.transaction_index
+                ),
+            })?
+            .map_err(|message| ApiError { message })?;
+    let signature = sign_and_send_single(&client, candidate.instruction, &signer)?;
+    Ok(Json(SquadsActionResponse {
+        signature,
+        status: "success".to_string(),
+        network,
+    }))
+}
+
+#[derive(Deserialize)]
+struct SquadsCleanupRequest {
+    #[serde(flatten)]
+    wallet: WalletAuthRequest,
+    multisig: String,
+    #[serde(default)]
+    dry_run: bool,
+    #[serde(default)]
+    network: Option<String>,
+}
+
+#[derive(Serialize)]
+struct SquadsCleanupItemResponse {
+    transaction_index: u64,
+    kind: Option<String>,
+    lamports: u64,
+    reason: Option<String>,
+}
+
+#[derive(Serialize)]
+struct SquadsCleanupResponse {
+    multisig: String,
+    rent_collector: Option<String>,
+    closable: Vec<SquadsCleanupItemResponse>,
+    skipped: Vec<SquadsCleanupItemResponse>,
+    signatures: Vec<String>,
+    reclaimed_lamports: u64,
+    remaining: usize,
+    network: String,
+    status: String,
+}
+
+struct SquadsCloseCandidate {
+    transaction_index: u64,
+    kind: squads_v4::TransactionAccountKind,
+    lamports: u64,
+    instruction: solana_sdk::instruction::Instruction,
+}
+
+/// Reads the transaction and proposal accounts at `transaction_index`.
+/// Returns `None` when they are already closed, otherwise the close
+/// instruction or the reason the program would refuse it.
+fn load_squads_close_candidate(
+    client: &RpcClient,
+    multisig_key: &Pubkey,
+    multisig: &SquadsMultisig,
+    transaction_index: u64,
+) -> Result<Option<Result<SquadsCloseCandidate, String>>, ApiError> {
+    let transaction = squads_v4::transaction_pda(multisig_key, transaction_index);
+    let proposal = squads_v4::proposal_pda(multisig_key, transaction_index);
+    let accounts = client
+        .get_multiple_accounts_with_commitment(
+            &[transaction, proposal],
+            CommitmentConfig::confirmed(),
+        )
+        .map_err(|e| ApiError {
+            message: format!("读取 Squads 交易账户失败: {}", e),
+        })?
+        .value;
+    Ok(squads_close_candidate(
+        multisig_key,
+        multisig,
+        transaction_index,
+        accounts.first().cloned().flatten(),
+        accounts.get(1).cloned().flatten(),
+    ))
+}
+
+fn squads_close_candidate(
+    multisig_key: &Pubkey,
+    multisig: &SquadsMultisig,
+    transaction_index: u64,
+    transaction: Option<Account>,
+    proposal: Option<Account>,
+) -> Option<Result<SquadsCloseCandidate, String>> {
+    let transaction = transaction?;
+    let Some(proposal) = proposal else {
+        return Some(Err(format!(
+            "Squads 交易 #{} 没有对应的提案账户",
+            transaction_index
+        )));
+    };
+    Some(
+        squads_v4::transaction_accounts_close_ix(
+            multisig_key,
+            multisig,
+            transaction_index,
+            &transaction.data,
+            &proposal.data,
+        )
+        .map(|(kind, instruction)| SquadsCloseCandidate {
+            transaction_index,
+            kind,
+            lamports: transaction.lamports.saturating_add(proposal.lamports),
+            instruction,
+        }),
+    )
+}
+
+async fn squads_cleanup(
+    Json(req): Json<SquadsCleanupRequest>,
+) -> Result<Json<SquadsCleanupResponse>, ApiError> {
+    let signer = req.wallet.keypair()?;
+    let multisig_key =
+        parse_squads_pubkey(&req.multisig, "多签地址").map_err(|message| ApiError { message })?;
+    let (client, network) = rpc_client_for(req.network.as_deref())?;
+    let multisig = load_squads_multisig(&client, &multisig_key)?;
+    if multisig.rent_collector.is_none() {
+        return Err(ApiError {
+            message: "该多签未设置 rent collector，无法回收租金".to_string(),
+        });
+    }
+
+    let mut candidates = Vec::new();
+    let mut skipped = Vec::new();
+    let indexes = (1..=multisig.transaction_index).collect::<Vec<_>>();
+    for chunk in indexes.chunks(50) {
+        let keys = chunk
+            .iter()
+            .flat_map(|index| {
+                [
+                    squads_v4::transaction_pda(&multisig_key, *index),
+                    squads_v4::proposal_pda(&multisig_key, *index),
+                ]
+            })
+            .collect::<Vec<_>>();
+        let accounts = client
+            .get_multiple_accounts_with_commitment(&keys, CommitmentConfig::confirmed())
+            .map_err(|e| ApiError {
+                message: format!("读取 Squads 交易账户失败: {}", e),
+            })?
+            .value;
+        let mut accounts = accounts.into_iter();
+        for index in chunk {
+            let transaction = accounts.next().flatten();
+            let proposal = accounts.next().flatten();
+            match squads_close_candidate(&multisig_key, &multisig, *index, transaction, proposal) {
+                Some(Ok(candidate)) => candidates.push(candidate),
+                Some(Err(reason)) => skipped.push(SquadsCleanupItemResponse {
+                    transaction_index: *index,
+                    kind: None,
+                    lamports: 0,
+                    reason: Some(reason),
+                }),
+                None => {}
+            }
+        }
+    }
+
+    let remaining = candidates.len().saturating_sub(MAX_SQUADS_CLEANUP_CLOSES);
+    candidates.truncate(MAX_SQUADS_CLEANUP_CLOSES);
+    let mut signatures = Vec::new();
+    let mut reclaimed_lamports = 0_u64;
+    if !req.dry_run {
+        for batch in candidates.chunks(SQUADS_CLOSES_PER_TRANSACTION) {
+            let instructions = batch
+                .iter()
+                .map(|candidate| candidate.instruction.clone())
+                .collect();
+            signatures.push(sign_and_send(
+                &client,
+                instructions,
+                &[&signer],
+                &signer.pubkey(),
+            )?);
+            reclaimed_lamports = batch.iter().fold(reclaimed_lamports, |sum, candidate| {
+                sum.saturating_add(candidate.lamports)
+            });
+        }
+    }
+
+    Ok(Json(SquadsCleanupResponse {
+        multisig: multisig_key.to_string(),
+        rent_collector: multisig.rent_collector.map(|key| key.to_string()),
+        closable: candidates
+            .iter()
+            .map(|candidate| SquadsCleanupItemResponse {
+                transaction_index: candidate.transaction_index,
+                kind: Some(candidate.kind.label().to_string()),
+                lamports: candidate.lamports,
+                reason: None,
+            })
+            .collect(),
+        skipped,
+        signatures,
+        reclaimed_lamports,
+        remaining,
+        network,
+        status: if req.dry_run { "dry_run" } else { "success" }.to_string(),
+    }))
+}
+
 #[derive(Deserialize)]
 struct SquadsExecuteRequest {
     #[serde(flatten)]
@@ -12966,10 +16291,10 @@ async fn squads_proposal_execute(
     Json(req): Json<SquadsExecuteRequest>,
 ) -> Result<Json<SquadsActionResponse>, ApiError> {
     let signer = req.wallet.keypair()?;
-    let multisig = squads_v4::parse_pubkey(&req.multisig, "多签地址")
-        .map_err(|message| ApiError { message })?;
-    let proposal = squads_v4::parse_pubkey(&req.proposal, "提案地址")
-        .map_err(|message| ApiError { message })?;
+    let multisig =
+        parse_squads_pubkey(&req.multisig, "多签地址").map_err(|message| ApiError { message })?;
+    let proposal =
+        parse_squads_pubkey(&req.proposal, "提案地址").map_err(|message| ApiError { message })?;
     let transaction = squads_v4::transaction_pda(&multisig, req.transaction_index);
     let (client, network) = rpc_client_for(req.network.as_deref())?;
     let vault_transaction = load_squads_vault_transaction(&client, &transaction)?;
@@ -12978,6 +16303,8 @@ async fn squads_proposal_execute(
             message: "Squads 交易账户与请求参数不匹配".to_string(),
         });
     }
+    let address_lookup_tables =
+        load_squads_message_lookup_tables(&client, &vault_transaction.message)?;
     let ix = squads_v4::vault_transaction_execute_ix(
         &multisig,
         &transaction,
@@ -12988,6 +16315,7 @@ async fn squads_proposal_execute(
         u8::try_from(vault_transaction.ephemeral_signer_bumps.len()).map_err(|_| ApiError {
             message: "Squads ephemeral signer 数量超出范围".to_string(),
         })?,
+        &address_lookup_tables,
     )
     .map_err(|message| ApiError { message })?;
     let signature = sign_and_send_single(&client, ix, &signer)?;
@@ -13022,9 +16350,9 @@ async fn squads_set_program_authority(
     Json(req): Json<SquadsSetProgramAuthorityRequest>,
 ) -> Result<Json<SquadsSetProgramAuthorityResponse>, ApiError> {
     let signer = req.wallet.keypair()?;
-    let multisig = squads_v4::parse_pubkey(&req.multisig, "多签地址")
-        .map_err(|message| ApiError { message })?;
-    let program_id = squads_v4::parse_pubkey(&req.program_id, "Program ID")
+    let multisig =
+        parse_squads_pubkey(&req.multisig, "多签地址").map_err(|message| ApiError { message })?;
+    let program_id = parse_squads_pubkey(&req.program_id, "Program ID")
         .map_err(|message| ApiError { message })?;
     let vault = squads_v4::vault_pda(&multisig, 0);
     let (client, network) = rpc_client_for(req.network.as_deref())?;
@@ -13049,6 +16377,8 @@ async fn squads_set_program_authority(
 // 4. Setup 2FA
 #[derive(Deserialize)]
 struct Setup2faRequest {
+    /// Defaults to this machine's combined hardware fingerprint
+    #[serde(default)]
     hardware_fingerprint: String,
     master_password: String,
     #[serde(default)]
@@ -13063,7 +16393,6 @@ struct Setup2faResponse {
 }
 
 async fn setup_2fa(Json(req): Json<Setup2faRequest>) -> Result<Json<Setup2faResponse>, ApiError> {
-    require_nonempty(req.hardware_fingerprint.trim(), "硬件指纹")?;
     require_nonempty(req.master_password.as_str(), "主密码")?;
     validate_text_len(&req.hardware_fingerprint, "硬件指纹", MAX_TEXT_FIELD_CHARS)?;
     validate_text_len(&req.master_password, "主密码", MAX_TEXT_FIELD_CHARS)?;
@@ -13072,8 +16401,19 @@ async fn setup_2fa(Json(req): Json<Setup2faRequest>) -> Result<Json<Setup2faResp
     let issuer =
         validate_optional_label(req.issuer, "发行者")?.unwrap_or_else(|| "FnzeroSafe".to_string());
 
+    let hardware_fingerprint = if req.hardware_fingerprint.trim().is_empty() {
+        tokio::task::spawn_blocking(HardwareFingerprint::collect)
+            .await
+            .map_err(|error| ApiError {
+                message: format!("收集硬件指纹任务异常终止: {error}"),
+            })?
+            .map_err(|e| ApiError { message: e })?
+            .fingerprint
+    } else {
+        req.hardware_fingerprint
+    };
     let totp_secret = fnzero_safe::derive_totp_secret_from_hardware_and_password(
-        &req.hardware_fingerprint,
+        &hardware_fingerprint,
         &req.master_password,
         &account,
         &issuer,
@@ -13097,114 +16437,523 @@ async fn setup_2fa(Json(req): Json<Setup2faRequest>) -> Result<Json<Setup2faResp
     }))
 }
 
+// 4b. Built-in Security Questions
+#[derive(Serialize)]
+struct BuiltinSecurityQuestion {
+    index: usize,
+    zh: &'static str,
+    en: &'static str,
+}
+#[derive(Serialize)]
+struct SecurityQuestionsResponse {
+    questions: Vec<BuiltinSecurityQuestion>,
+    max_questions: usize,
+}
+
+async fn security_questions() -> Json<SecurityQuestionsResponse> {
+    let questions = (0..SECURITY_QUESTIONS.len())
+        .map(|index| BuiltinSecurityQuestion {
+            index,
+            zh: SECURITY_QUESTIONS[index],
+            en: SECURITY_QUESTIONS_EN[index],
+        })
+        .collect();
+    Json(SecurityQuestionsResponse {
+        questions,
+        max_questions: MAX_SECURITY_QUESTIONS,
+    })
+}
+
 // 5. Create Triple-Factor Wallet
 #[derive(Deserialize)]
 struct CreateTripleFactorRequest {
     #[serde(flatten)]
     wallet: WalletAuthRequest,
     totp_secret: String,
-    hardware_fingerprint: String,
     master_password: String,
+    /// Device factor provider: `fingerprint` (default), `device-key` or `tpm`
+    #[serde(default)]
+    device_factor: Option<String>,
+    /// Legacy single built-in question; ignored when `security_questions` is set
+    #[serde(default)]
     question_index: usize,
+    #[serde(default)]
     security_answer: String,
+    /// Built-in or custom questions with one answer each in `security_answers`
+    #[serde(default)]
+    security_questions: Vec<QuestionPrompt>,
+    #[serde(default)]
+    security_answers: Vec<String>,
+    /// Answers needed to unlock; defaults to all of them
+    #[serde(default)]
+    answer_threshold: Option<usize>,
 }
 #[derive(Serialize)]
 struct CreateTripleFactorResponse {
     encrypted_wallet: String,
     public_key: String,
+    /// One-time recovery codes, returned only here; the wallet keeps their hashes
+    backup_codes: Vec<String>,
+    /// Store with the wallet and send back with the answers to unlock it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    security_questions: Option<SecurityQuestionSet>,
+    /// Store with the wallet and send back to unlock it
+    device_factor: Value,
+}
+
+/// Security answers for an unlock: a question set with its answers, or a legacy answer
+#[derive(Deserialize)]
+struct SecurityAnswerInput {
+    #[serde(default)]
+    security_answer: String,
+    #[serde(default)]
+    security_questions: Option<SecurityQuestionSet>,
+    /// One entry per question; `null` skips it
+    #[serde(default)]
+    security_answers: Vec<Option<String>>,
+}
+
+impl SecurityAnswerInput {
+    fn validate(&self) -> Result<(), ApiError> {
+        if self.security_questions.is_none() {
+            require_nonempty(self.security_answer.trim(), "安全答案")?;
+            return validate_text_len(&self.security_answer, "安全答案", MAX_TEXT_FIELD_CHARS);
+        }
+        if self.security_answers.len() > MAX_SECURITY_QUESTIONS {
+            return Err(ApiError {
+                message: format!("安全答案不能超过 {} 个", MAX_SECURITY_QUESTIONS),
+            });
+        }
+        for answer in self.security_answers.iter().flatten() {
+            validate_text_len(answer, "安全答案", MAX_TEXT_FIELD_CHARS)?;
+        }
+        Ok(())
+    }
+
+    /// The value the triple-factor key is derived from; runs PBKDF2 for a question set
+    fn resolve(&self, master_password: &str) -> Result<Zeroizing<String>, String> {
+        match &self.security_questions {
+            Some(set) => set.unlock(&self.security_answers, master_password),
+            None => Ok(Zeroizing::new(self.security_answer.clone())),
+        }
+    }
 }
 
 async fn create_triple_factor_wallet(
     Json(req): Json<CreateTripleFactorRequest>,
 ) -> Result<Json<CreateTripleFactorResponse>, ApiError> {
     require_nonempty(req.totp_secret.trim(), "TOTP secret")?;
-    require_nonempty(req.hardware_fingerprint.trim(), "硬件指纹")?;
     require_nonempty(req.master_password.as_str(), "主密码")?;
-    require_nonempty(req.security_answer.trim(), "安全答案")?;
-    if req.question_index > MAX_SECURITY_QUESTION_INDEX {
-        return Err(ApiError {
-            message: format!("安全问题索引不能超过 {}", MAX_SECURITY_QUESTION_INDEX),
-        });
-    }
     validate_text_len(&req.totp_secret, "TOTP secret", MAX_TEXT_FIELD_CHARS)?;
-    validate_text_len(&req.hardware_fingerprint, "硬件指纹", MAX_TEXT_FIELD_CHARS)?;
     validate_text_len(&req.master_password, "主密码", MAX_TEXT_FIELD_CHARS)?;
-    validate_text_len(&req.security_answer, "安全答案", MAX_TEXT_FIELD_CHARS)?;
+    let factor_kind = req
+        .device_factor
+        .clone()
+        .unwrap_or_else(|| device_factor::FingerprintFactor::KIND.to_string());
+    validate_text_len(&factor_kind, "设备因子类型", MAX_LABEL_CHARS)?;
+    // The legacy single built-in question's answer, unless a question set is given
+    let legacy_answer = if req.security_questions.is_empty() {
+        require_nonempty(req.security_answer.trim(), "安全答案")?;
+        if req.question_index > MAX_SECURITY_QUESTION_INDEX {
+            return Err(ApiError {
+                message: format!("安全问题索引不能超过 {}", MAX_SECURITY_QUESTION_INDEX),
+            });
+        }
+        validate_text_len(&req.security_answer, "安全答案", MAX_TEXT_FIELD_CHARS)?;
+        Some(Zeroizing::new(req.security_answer.clone()))
+    } else {
+        for answer in &req.security_answers {
+            validate_text_len(answer, "安全答案", MAX_TEXT_FIELD_CHARS)?;
+        }
+        None
+    };
     let keypair = req.wallet.keypair()?;
-    let private_key = keypair.to_base58_string();
-
-    let encrypted = fnzero_safe::encrypt_with_triple_factor(
-        &private_key,
-        &req.totp_secret,
-        &req.hardware_fingerprint,
-        &req.master_password,
-        req.question_index,
-        &req.security_answer,
-    )
-    .map_err(|e| ApiError {
-        message: format!("加密失败: {}", e),
-    })?;
-
-    // Get public key from private key
+    let private_key = Zeroizing::new(keypair.to_base58_string());
     let public_key = KeyManager::get_public_key(&private_key).map_err(|e| ApiError {
         message: format!("获取公钥失败: {}", e),
     })?;
 
+    // Hardware binding, TPM sealing and answer stretching are slow or block on I/O
+    let (encrypted, backup_codes, security_questions, device_factor) =
+        tokio::task::spawn_blocking(move || {
+            let (question_index, security_answer, security_questions) = match legacy_answer {
+                Some(answer) => (req.question_index, answer, None),
+                None => {
+                    let threshold = req.answer_threshold.unwrap_or(req.security_questions.len());
+                    let (set, answer_key) = SecurityQuestionSet::create(
+                        req.security_questions,
+                        &req.security_answers,
+                        threshold,
+                        &req.master_password,
+                    )?;
+                    // The set travels with the wallet; the envelope's index only matters for
+                    // legacy wallets
+                    (0, answer_key, Some(set))
+                }
+            };
+            let factor = device_factor::device_factor_by_kind(&factor_kind)?;
+            let (device, device_factor) = device_factor::enroll_device_factor(factor.as_ref())?;
+            let backup_codes = fnzero_safe::backup_codes::generate_backup_codes()
+                .map_err(|e| format!("生成备用恢复码失败: {}", e))?;
+            let encrypted = fnzero_safe::encrypt_with_triple_factor(
+                &private_key,
+                &req.totp_secret,
+                &device.secret,
+                &req.master_password,
+                question_index,
+                &security_answer,
+                &backup_codes.hashes,
+            )
+            .map_err(|e| format!("加密失败: {}", e))?;
+            Ok::<_, String>((encrypted, backup_codes, security_questions, device_factor))
+        })
+        .await
+        .map_err(|error| ApiError {
+            message: format!("三因素钱包任务异常终止: {error}"),
+        })?
+        .map_err(|message| ApiError { message })?;
+
     Ok(Json(CreateTripleFactorResponse {
         encrypted_wallet: encrypted,
         public_key,
+        backup_codes: backup_codes.codes,
+        security_questions,
+        device_factor,
     }))
 }
 
 // 6. Unlock Triple-Factor Wallet
+/// The wallet and the factors every triple-factor request carries
 #[derive(Deserialize)]
-struct UnlockTripleFactorRequest {
+struct TripleFactorWalletInput {
     encrypted_wallet: String,
+    /// `device_factor` returned when the wallet was created or re-bound
+    #[serde(default)]
+    device_factor: Option<Value>,
+    /// Fingerprint string typed in for wallets created before device factors
+    #[serde(default)]
     hardware_fingerprint: String,
     master_password: String,
-    security_answer: String,
+    #[serde(flatten)]
+    answers: SecurityAnswerInput,
+}
+
+impl TripleFactorWalletInput {
+    fn validate(&self) -> Result<(), ApiError> {
+        require_nonempty(self.encrypted_wallet.trim(), "三因素钱包")?;
+        require_nonempty(&self.master_password, "主密码")?;
+        validate_text_len(&self.encrypted_wallet, "三因素钱包", MAX_JSON_BODY_BYTES)?;
+        validate_text_len(&self.master_password, "主密码", MAX_TEXT_FIELD_CHARS)?;
+        match &self.device_factor {
+            Some(stored) if !stored["kind"].is_string() => {
+                return Err(ApiError {
+                    message: "设备因子缺少类型".to_string(),
+                })
+            }
+            Some(_) => {}
+            None => {
+                require_nonempty(self.hardware_fingerprint.trim(), "设备因子或硬件指纹")?;
+                validate_text_len(&self.hardware_fingerprint, "硬件指纹", MAX_TEXT_FIELD_CHARS)?;
+            }
+        }
+        self.answers.validate()
+    }
+
+    /// Recover the device secret on this machine
+    fn device_secret(&self) -> Result<DeviceSecret, String> {
+        let Some(stored) = &self.device_factor else {
+            return Ok(DeviceSecret {
+                secret: Zeroizing::new(self.hardware_fingerprint.clone()),
+                notice: Some("该钱包使用手动填写的硬件指纹，建议重新绑定到本机设备".to_string()),
+            });
+        };
+        let kind = stored["kind"].as_str().ok_or("设备因子缺少类型")?;
+        device_factor::device_factor_by_kind(kind)?.unlock(stored)
+    }
+}
+
+/// Factors of a triple-factor wallet once recovered on this machine
+struct TripleFactorSecrets {
+    encrypted_wallet: String,
+    device: DeviceSecret,
+    master_password: Zeroizing<String>,
+    security_answer: Zeroizing<String>,
+}
+
+#[derive(Deserialize)]
+struct UnlockTripleFactorRequest {
+    #[serde(flatten)]
+    wallet: TripleFactorWalletInput,
     totp_code: String,
 }
 #[derive(Serialize)]
 struct UnlockTripleFactorResponse {
     public_key: String,
     unlocked: bool,
+    backup_codes_remaining: usize,
+    /// Present when a backup code was spent: store it in place of the submitted wallet,
+    /// which is refused from now on
+    #[serde(skip_serializing_if = "Option::is_none")]
+    encrypted_wallet: Option<String>,
+    /// Hardware drift or a legacy fingerprint; re-bind before it locks the wallet out
+    #[serde(skip_serializing_if = "Option::is_none")]
+    device_notice: Option<String>,
+}
+
+/// Run a triple-factor operation under the unlock lock on a blocking thread
+///
+/// The security answers and device secret are recovered first, inside the wallet's TOTP guard,
+/// so wrong answers count toward the same lockout as wrong codes.
+async fn run_triple_factor<T, F>(wallet: TripleFactorWalletInput, work: F) -> Result<T, ApiError>
+where
+    T: Send + 'static,
+    F: FnOnce(&mut wallet_store::TotpStateDb, &TripleFactorSecrets) -> Result<T, String>
+        + Send
+        + 'static,
+{
+    let state_key =
+        fnzero_safe::triple_factor_state_key(&wallet.encrypted_wallet).map_err(|e| ApiError {
+            message: format!("解密失败: {}", e),
+        })?;
+    // Serialize unlocks so two requests cannot both spend the same code
+    let _unlock_guard = TOTP_UNLOCK_LOCK
+        .get_or_init(|| tokio::sync::Mutex::new(()))
+        .lock()
+        .await;
+    tokio::task::spawn_blocking(move || {
+        let mut store = wallet_store::TotpStateDb;
+        let master_password = Zeroizing::new(wallet.master_password.clone());
+        let (security_answer, device) =
+            fnzero_safe::totp_guard::guarded_unlock(&mut store, &state_key, || {
+                Ok((
+                    wallet.answers.resolve(&master_password)?,
+                    wallet.device_secret()?,
+                ))
+            })
+            .map_err(|e| e.to_string())?;
+        let secrets = TripleFactorSecrets {
+            encrypted_wallet: wallet.encrypted_wallet,
+            device,
+            master_password,
+            security_answer,
+        };
+        work(&mut store, &secrets)
+    })
+    .await
+    .map_err(|error| ApiError {
+        message: format!("三因素钱包任务异常终止: {error}"),
+    })?
+    .map_err(|e| ApiError {
+        message: format!("解密失败: {}", e),
+    })
 }
 
 async fn unlock_triple_factor_wallet(
     Json(req): Json<UnlockTripleFactorRequest>,
 ) -> Result<Json<UnlockTripleFactorResponse>, ApiError> {
-    require_nonempty(req.encrypted_wallet.trim(), "三因素钱包")?;
-    require_nonempty(req.hardware_fingerprint.trim(), "硬件指纹")?;
-    require_nonempty(req.master_password.as_str(), "主密码")?;
-    require_nonempty(req.security_answer.trim(), "安全答案")?;
+    req.wallet.validate()?;
     require_nonempty(req.totp_code.trim(), "TOTP 验证码")?;
-    validate_text_len(&req.encrypted_wallet, "三因素钱包", MAX_JSON_BODY_BYTES)?;
-    validate_text_len(&req.hardware_fingerprint, "硬件指纹", MAX_TEXT_FIELD_CHARS)?;
-    validate_text_len(&req.master_password, "主密码", MAX_TEXT_FIELD_CHARS)?;
-    validate_text_len(&req.security_answer, "安全答案", MAX_TEXT_FIELD_CHARS)?;
     validate_text_len(&req.totp_code, "TOTP 验证码", MAX_LABEL_CHARS)?;
-    let (decrypted, _question, _index) = fnzero_safe::decrypt_with_triple_factor_and_2fa(
-        &req.encrypted_wallet,
-        &req.hardware_fingerprint,
-        &req.master_password,
-        &req.security_answer,
-        &req.totp_code,
-    )
-    .map_err(|e| ApiError {
-        message: format!("解密失败: {}", e),
-    })?;
+    let UnlockTripleFactorRequest { wallet, totp_code } = req;
+    let (unlocked, encrypted_wallet, device_notice) =
+        run_triple_factor(wallet, move |store, secrets| {
+            let mut unlocked = fnzero_safe::decrypt_with_triple_factor_and_2fa(
+                store,
+                &secrets.encrypted_wallet,
+                &secrets.device.secret,
+                &secrets.master_password,
+                &secrets.security_answer,
+                &totp_code,
+            )?;
+            // The client stores the returned envelope; nothing is committed if this fails
+            let encrypted_wallet = unlocked.reseal.take().map(|reseal| {
+                let encrypted_wallet = reseal.encrypted_data.clone();
+                reseal.commit(store).map(|()| encrypted_wallet)
+            });
+            Ok((
+                unlocked,
+                encrypted_wallet.transpose()?,
+                secrets.device.notice.clone(),
+            ))
+        })
+        .await?;
 
-    let public_key = KeyManager::get_public_key(&decrypted).map_err(|e| ApiError {
+    let public_key = KeyManager::get_public_key(&unlocked.private_key).map_err(|e| ApiError {
         message: format!("获取公钥失败: {}", e),
     })?;
 
     Ok(Json(UnlockTripleFactorResponse {
         public_key,
         unlocked: true,
+        backup_codes_remaining: unlocked.backup_codes_remaining,
+        encrypted_wallet,
+        device_notice,
+    }))
+}
+
+// 6b. Triple-Factor Backup Codes
+#[derive(Serialize)]
+struct TripleFactorBackupCodesResponse {
+    backup_codes_remaining: usize,
+}
+
+async fn triple_factor_backup_codes(
+    Json(req): Json<TripleFactorWalletInput>,
+) -> Result<Json<TripleFactorBackupCodesResponse>, ApiError> {
+    req.validate()?;
+    let backup_codes_remaining = run_triple_factor(req, |store, secrets| {
+        fnzero_safe::triple_factor_backup_codes_remaining(
+            store,
+            &secrets.encrypted_wallet,
+            &secrets.device.secret,
+            &secrets.master_password,
+            &secrets.security_answer,
+        )
+    })
+    .await?;
+    Ok(Json(TripleFactorBackupCodesResponse {
+        backup_codes_remaining,
+    }))
+}
+
+#[derive(Serialize)]
+struct RegenerateBackupCodesResponse {
+    /// Replaces the submitted wallet; the submitted wallet and every earlier code stop working
+    encrypted_wallet: String,
+    backup_codes: Vec<String>,
+}
+
+async fn regenerate_triple_factor_backup_codes(
+    Json(req): Json<UnlockTripleFactorRequest>,
+) -> Result<Json<RegenerateBackupCodesResponse>, ApiError> {
+    req.wallet.validate()?;
+    require_nonempty(req.totp_code.trim(), "TOTP 验证码")?;
+    validate_text_len(&req.totp_code, "TOTP 验证码", MAX_LABEL_CHARS)?;
+    let UnlockTripleFactorRequest { wallet, totp_code } = req;
+    let (encrypted_wallet, backup_codes) = run_triple_factor(wallet, move |store, secrets| {
+        let (reseal, backup_codes) = fnzero_safe::regenerate_triple_factor_backup_codes(
+            store,
+            &secrets.encrypted_wallet,
+            &secrets.device.secret,
+            &secrets.master_password,
+            &secrets.security_answer,
+            &totp_code,
+        )?;
+        let encrypted_wallet = reseal.encrypted_data.clone();
+        reseal.commit(store)?;
+        Ok((encrypted_wallet, backup_codes))
+    })
+    .await?;
+    Ok(Json(RegenerateBackupCodesResponse {
+        encrypted_wallet,
+        backup_codes,
+    }))
+}
+
+// 6c. Re-bind Triple-Factor Wallet to a device factor on this machine
+#[derive(Deserialize)]
+struct RebindTripleFactorRequest {
+    #[serde(flatten)]
+    unlock: UnlockTripleFactorRequest,
+    /// Provider to enroll; defaults to the wallet's current one
+    #[serde(default)]
+    new_device_factor: Option<String>,
+}
+#[derive(Serialize)]
+struct RebindTripleFactorResponse {
+    /// Replaces the submitted wallet, which is refused from now on
+    encrypted_wallet: String,
+    /// Replaces the submitted `device_factor`
+    device_factor: Value,
+}
+
+async fn rebind_triple_factor_wallet(
+    Json(req): Json<RebindTripleFactorRequest>,
+) -> Result<Json<RebindTripleFactorResponse>, ApiError> {
+    let RebindTripleFactorRequest {
+        unlock: req,
+        new_device_factor,
+    } = req;
+    req.wallet.validate()?;
+    require_nonempty(req.totp_code.trim(), "TOTP 验证码")?;
+    validate_text_len(&req.totp_code, "TOTP 验证码", MAX_LABEL_CHARS)?;
+    let factor_kind = new_device_factor
+        .or_else(|| {
+            req.wallet
+                .device_factor
+                .as_ref()
+                .and_then(|stored| stored["kind"].as_str().map(str::to_string))
+        })
+        .unwrap_or_else(|| device_factor::FingerprintFactor::KIND.to_string());
+    validate_text_len(&factor_kind, "设备因子类型", MAX_LABEL_CHARS)?;
+    let UnlockTripleFactorRequest { wallet, totp_code } = req;
+    let (encrypted_wallet, device_factor) = run_triple_factor(wallet, move |store, secrets| {
+        let factor = device_factor::device_factor_by_kind(&factor_kind)?;
+        let (device, device_factor) = device_factor::enroll_device_factor(factor.as_ref())?;
+        let reseal = fnzero_safe::rebind_triple_factor_hardware(
+            store,
+            &secrets.encrypted_wallet,
+            &secrets.device.secret,
+            &secrets.master_password,
+            &secrets.security_answer,
+            &totp_code,
+            &device.secret,
+        )?;
+        let encrypted_wallet = reseal.encrypted_data.clone();
+        reseal.commit(store)?;
+        Ok((encrypted_wallet, device_factor))
+    })
+    .await?;
+    Ok(Json(RebindTripleFactorResponse {
+        encrypted_wallet,
+        device_factor,
     }))
 }
 
+#[cfg(test)]
+mod triple_factor_request_tests {
+    use super::*;
+
+    #[test]
+    fn unlock_request_carries_device_factor_and_answers() {
+        let req: UnlockTripleFactorRequest = serde_json::from_value(json!({
+            "encrypted_wallet": "tfa2:00:AAAA",
+            "device_factor": { "kind": "fingerprint", "binding": {} },
+            "master_password": "correct horse battery",
+            "security_answer": "paris",
+            "totp_code": "123456",
+        }))
+        .unwrap();
+        assert!(req.wallet.validate().is_ok());
+        assert_eq!(
+            req.wallet.device_factor.as_ref().unwrap()["kind"],
+            "fingerprint"
+        );
+        assert_eq!(req.wallet.answers.security_answer, "paris");
+        assert_eq!(req.totp_code, "123456");
+    }
+
+    #[test]
+    fn legacy_fingerprint_is_only_used_without_a_device_factor() {
+        let mut wallet: TripleFactorWalletInput = serde_json::from_value(json!({
+            "encrypted_wallet": "AAAA",
+            "master_password": "correct horse battery",
+            "security_answer": "paris",
+        }))
+        .unwrap();
+        assert!(wallet.validate().is_err());
+
+        wallet.hardware_fingerprint = "typed-in fingerprint".to_string();
+        assert!(wallet.validate().is_ok());
+        let device = wallet.device_secret().unwrap();
+        assert_eq!(device.secret.as_str(), "typed-in fingerprint");
+        assert!(device.notice.is_some());
+
+        wallet.device_factor = Some(json!({ "binding": {} }));
+        assert!(wallet.validate().is_err());
+    }
+}
+
 // ============= Pump.fun Operations (15-18) =============
 
 // 15. Pump.fun Sell Token
@@ -13264,6 +17013,276 @@ async fn pumpfun_sell(
     }))
 }
 
+// 15b. Pump.fun / PumpSwap Buy Token
+#[derive(Deserialize)]
+struct PumpBuyRequest {
+    #[serde(flatten)]
+    wallet: WalletAuthRequest,
+    mint: String,
+    /// SOL/WSOL amount to spend.
+    amount: DecimalAmount,
+    #[serde(default)]
+    input_token: Option<String>,
+    #[serde(default)]
+    slippage: Option<u64>,
+    #[serde(default)]
+    create_ata: Option<bool>,
+    #[serde(default)]
+    network: Option<String>,
+}
+#[derive(Serialize)]
+struct PumpBuyResponse {
+    status: String,
+    signature: String,
+    dex: String,
+    market: String,
+    input_token: String,
+    spent_lamports: String,
+}
+
+async fn pump_buy_handler(
+    req: PumpBuyRequest,
+    dex_type: DexType,
+) -> Result<Json<PumpBuyResponse>, ApiError> {
+    let keypair = req.wallet.keypair()?;
+
+    let rpc_url = get_rpc_url(req.network.as_deref())?;
+    let slippage = normalize_slippage_bps(req.slippage)?;
+    let amount_lamports = sol_to_lamports(&req.amount)?;
+    let input_token_type = pump_buy_input_token(req.input_token.as_deref())?;
+
+    let execution = execute_pump_buy(
+        keypair,
+        req.mint,
+        &rpc_url,
+        amount_lamports,
+        input_token_type,
+        slippage,
+        req.create_ata.unwrap_or(true),
+        dex_type,
+    )
+    .await?;
+
+    Ok(Json(PumpBuyResponse {
+        status: "success".to_string(),
+        signature: execution.signature,
+        dex: pump_sell_dex_value(execution.dex_type).to_string(),
+        market: pump_sell_market_value(execution.dex_type).to_string(),
+        input_token: pump_buy_input_value(&execution.input_token_type).to_string(),
+        spent_lamports: execution.spent_lamports.to_string(),
+    }))
+}
+
+async fn pumpfun_buy(Json(req): Json<PumpBuyRequest>) -> Result<Json<PumpBuyResponse>, ApiError> {
+    pump_buy_handler(req, DexType::PumpFun).await
+}
+
+async fn pumpswap_buy(Json(req): Json<PumpBuyRequest>) -> Result<Json<PumpBuyResponse>, ApiError> {
+    pump_buy_handler(req, DexType::PumpSwap).await
+}
+
+// 16b. SWQoS Settings
+#[derive(Serialize)]
+struct SwqosProviderView {
+    provider: SwqosProvider,
+    enabled: bool,
+    region: String,
+    /// "settings" / "env" / null
+    token_source: Option<&'static str>,
+}
+
+#[derive(Serialize)]
+struct SwqosSettingsResponse {
+    saved: bool,
+    providers: Vec<SwqosProviderView>,
+    tip: SwqosTipStrategy,
+    sender_concurrency: Option<usize>,
+    swqos_only: bool,
+}
+
+#[derive(Deserialize)]
+struct SwqosProviderUpdate {
+    provider: SwqosProvider,
+    #[serde(default = "default_true")]
+    enabled: bool,
+    region: String,
+    /// Omitted keeps the stored token; an empty string removes it.
+    #[serde(default)]
+    api_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct SwqosSettingsRequest {
+    providers: Vec<SwqosProviderUpdate>,
+    tip: SwqosTipStrategy,
+    #[serde(default)]
+    sender_concurrency: Option<usize>,
+    swqos_only: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn swqos_settings_response(settings: &SwqosSettings, saved: bool) -> SwqosSettingsResponse {
+    let providers = settings
+        .providers
+        .iter()
+        .map(|entry| {
+            let stored = entry
+                .api_token
+                .as_deref()
+                .is_some_and(|token| !token.trim().is_empty());
+            let token_source = if stored {
+                Some("settings")
+            } else if settings.provider_token(entry).is_some() {
+                Some("env")
+            } else if config::get().swqos_token(entry.provider).is_some() {
+                Some("config")
+            } else {
+                None
+            };
+            SwqosProviderView {
+                provider: entry.provider,
+                enabled: entry.enabled,
+                region: entry.region.clone(),
+                token_source,
+            }
+        })
+        .collect();
+    SwqosSettingsResponse {
+        saved,
+        providers,
+        tip: settings.tip.clone(),
+        sender_concurrency: settings.sender_concurrency,
+        swqos_only: settings.swqos_only,
+    }
+}
+
+async fn get_swqos_settings() -> Result<Json<SwqosSettingsResponse>, ApiError> {
+    let stored = wallet_store::get_swqos_settings().map_err(|message| ApiError { message })?;
+    let saved = stored.is_some();
+    Ok(Json(swqos_settings_response(
+        &stored.unwrap_or_default(),
+        saved,
+    )))
+}
+
+async fn save_swqos_settings(
+    Json(req): Json<SwqosSettingsRequest>,
+) -> Result<Json<SwqosSettingsResponse>, ApiError> {
+    let current = load_swqos_settings()?;
+    let providers = req
+        .providers
+        .into_iter()
+        .map(|update| {
+            let api_token = match update.api_token.map(|token| token.trim().to_string()) {
+                Some(token) if !token.is_empty() => Some(token),
+                Some(_) => None,
+                None => current
+                    .providers
+                    .iter()
+                    .find(|entry| entry.provider == update.provider)
+                    .and_then(|entry| entry.api_token.clone()),
+            };
+            SwqosProviderSettings {
+                provider: update.provider,
+                enabled: update.enabled,
+                region: update.region.trim().to_string(),
+                api_token,
+            }
+        })
+        .collect();
+    let settings = SwqosSettings {
+        providers,
+        tip: req.tip,
+        sender_concurrency: req.sender_concurrency,
+        swqos_only: req.swqos_only,
+    };
+    wallet_store::save_swqos_settings(&settings).map_err(|message| ApiError { message })?;
+    export_swqos_settings(&settings);
+    Ok(Json(swqos_settings_response(&settings, true)))
+}
+
+// 16c. Pump Batch Sell / Dust Sweep
+#[derive(Deserialize)]
+struct PumpBatchSellRequest {
+    #[serde(flatten)]
+    wallet: WalletAuthRequest,
+    #[serde(default)]
+    slippage: Option<u64>,
+    /// Per-mint slippage in basis points.
+    #[serde(default)]
+    slippage_overrides: HashMap<String, u64>,
+    #[serde(default)]
+    venues: Option<Vec<PumpVenue>>,
+    #[serde(default)]
+    min_value_sol: Option<f64>,
+    #[serde(default)]
+    max_value_sol: Option<f64>,
+    #[serde(default)]
+    min_age_secs: Option<u64>,
+    #[serde(default)]
+    exclude_mints: Vec<String>,
+    #[serde(default)]
+    max_concurrency: Option<usize>,
+    #[serde(default = "default_true")]
+    close_empty_accounts: bool,
+    #[serde(default)]
+    dry_run: bool,
+    #[serde(default)]
+    network: Option<String>,
+}
+
+async fn pump_batch_sell(
+    Json(req): Json<PumpBatchSellRequest>,
+) -> Result<Json<BatchSellReport>, ApiError> {
+    let keypair = req.wallet.keypair()?;
+    let rpc_url = get_rpc_url(req.network.as_deref())?;
+    let options = BatchSellOptions {
+        slippage: normalize_slippage_bps(req.slippage)?,
+        slippage_overrides: req.slippage_overrides,
+        venues: req.venues,
+        min_value_sol: req.min_value_sol,
+        max_value_sol: req.max_value_sol,
+        min_age_secs: req.min_age_secs,
+        exclude_mints: req.exclude_mints,
+        max_concurrency: req.max_concurrency,
+        close_empty_accounts: req.close_empty_accounts,
+        dry_run: req.dry_run,
+    };
+    options.validate().map_err(|message| ApiError { message })?;
+
+    let query_client = rpc_query_client_for_url(rpc_url.clone(), RPC_QUERY_TIMEOUT_SECS);
+    let holdings = all_owner_token_accounts(&query_client, &keypair.pubkey())?
+        .into_iter()
+        .filter_map(|(account, token_program, token_account)| {
+            Some(TokenHolding {
+                account: Pubkey::from_str(&account).ok()?,
+                mint: Pubkey::from_str(&token_account.mint).ok()?,
+                token_program,
+                raw_amount: token_account.token_amount.amount.parse().ok()?,
+                decimals: token_account.token_amount.decimals,
+            })
+        })
+        .collect();
+
+    let swqos_settings = load_sending_swqos_settings()?;
+    let client = swqos_trade_client(&swqos_settings, Arc::new(keypair), &rpc_url, false).await?;
+    let tip_sol = swqos_settings.resolve_tip_sol().await;
+    let report = batch_sell(&client, holdings, &options, tip_sol)
+        .await
+        .map_err(|message| ApiError { message })?;
+    tracing::info!(
+        "Pump batch sell: sold={}, failed={}, closed={}, reclaimed={} lamports",
+        report.sold,
+        report.failed,
+        report.closed_accounts,
+        report.reclaimed_lamports
+    );
+    Ok(Json(report))
+}
+
 // 17. Pump.fun Cashback
 #[derive(Deserialize)]
 struct CashbackInfoRequest {
@@ -13563,8 +17582,17 @@ impl IntoResponse for ApiError {
 impl ApiError {
     fn status_code(&self) -> StatusCode {
         let message = self.message.as_str();
-        if message == SBF_VERIFY_BUSY_MESSAGE {
+        if message == SBF_VERIFY_BUSY_MESSAGE || message.contains("钱包已锁定") {
             StatusCode::TOO_MANY_REQUESTS
+        } else if message == vault::VAULT_LOCKED_MESSAGE {
+            StatusCode::LOCKED
+        } else if message.contains("保险库已启用") || message.contains("远程签名请求已处理")
+        {
+            StatusCode::CONFLICT
+        } else if message.contains("保险库解锁失败") {
+            StatusCode::UNAUTHORIZED
+        } else if message.contains("保险库未启用") {
+            StatusCode::BAD_REQUEST
         } else if message == PROGRAM_DEPLOY_BUSY_MESSAGE
             || message.contains("目标 Program 或 ProgramData 已存在")
             || message.contains("部署 journal 冲突")
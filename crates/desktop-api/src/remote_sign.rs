@@ -0,0 +1,460 @@
+//! Remote signing through a pairing relay
+//!
+//! Pairings and received requests live in SQLite (see `wallet_store`). A background
+//! poller pulls sealed requests from each pairing's relay mailbox, stores them as
+//! pending and delivers the responses the approve/reject handlers write. Nothing is
+//! signed here: approval goes through the same code as `/api/external-sign/*`.
+
+use crate::wallet_store::{
+    self, RemoteSignPairingRecord, RemoteSignRequestRecord, RemoteSignStoreOutcome,
+    REMOTE_SIGN_STATUS_EXPIRED, REMOTE_SIGN_STATUS_PENDING,
+};
+use fnzero_safe_remote_sign::{
+    new_topic, now_unix_secs, relay::RelayMessage, response_topic, Direction, Envelope, PairingUri,
+    RelayClient, SignRequest, SignResponse, SignResponseStatus, StaticKey,
+};
+use serde_json::Value;
+use std::time::Duration;
+use uuid::Uuid;
+
+const MAX_PENDING_PER_PAIRING: usize = 50;
+const POLL_INTERVAL: Duration = Duration::from_secs(3);
+const MAX_REQUEST_ID_CHARS: usize = 128;
+const MAX_NOTE_CHARS: usize = 500;
+/// Requests without an expiry, or with a later one, expire this long after they arrive
+const MAX_REQUEST_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+fn normalize_relay_url(relay_url: &str) -> Result<String, String> {
+    let relay_url = relay_url.trim().trim_end_matches('/');
+    if relay_url.is_empty() {
+        return Err("中继地址不能为空".to_string());
+    }
+    if !(relay_url.starts_with("http://") || relay_url.starts_with("https://"))
+        || relay_url.chars().any(char::is_whitespace)
+    {
+        return Err("中继地址无效，请使用 http:// 或 https:// 地址".to_string());
+    }
+    Ok(relay_url.to_string())
+}
+
+pub fn create_pairing(name: &str, relay_url: &str) -> Result<RemoteSignPairingRecord, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("配对名称不能为空".to_string());
+    }
+    let key = StaticKey::generate();
+    let record = RemoteSignPairingRecord {
+        id: Uuid::new_v4().simple().to_string(),
+        name: name.to_string(),
+        relay_url: normalize_relay_url(relay_url)?,
+        topic: new_topic(),
+        public_key: key.public_key(),
+        secret_key: key.to_base64().to_string(),
+        client_public_key: None,
+        created_at: now_unix_secs(),
+        last_seen_at: None,
+    };
+    wallet_store::insert_remote_sign_pairing(&record)?;
+    Ok(record)
+}
+
+/// `fnzero-safe://pair` URI shown as a QR code for the remote client
+pub fn pairing_uri(pairing: &RemoteSignPairingRecord) -> String {
+    PairingUri {
+        topic: pairing.topic.clone(),
+        public_key: pairing.public_key.clone(),
+        relay_url: pairing.relay_url.clone(),
+        name: Some(pairing.name.clone()),
+    }
+    .to_string()
+}
+
+fn pairing_key(pairing: &RemoteSignPairingRecord) -> Result<StaticKey, String> {
+    StaticKey::from_base64(&pairing.secret_key).map_err(|e| format!("配对密钥无效: {}", e))
+}
+
+/// Decrypt and validate one relay envelope addressed to `pairing`
+fn open_request(
+    pairing: &RemoteSignPairingRecord,
+    envelope: &Envelope,
+    now: u64,
+) -> Result<SignRequest, String> {
+    if pairing
+        .client_public_key
+        .as_deref()
+        .is_some_and(|client| client != envelope.sender)
+    {
+        return Err("远程签名客户端与配对绑定的客户端不一致".to_string());
+    }
+    let plaintext = envelope
+        .open(&pairing_key(pairing)?, &pairing.topic, Direction::Request)
+        .map_err(|e| format!("远程签名请求无法打开: {}", e))?;
+    let request: SignRequest =
+        serde_json::from_slice(&plaintext).map_err(|e| format!("远程签名请求格式无效: {}", e))?;
+    let request_id = request.request_id.trim();
+    if request_id.is_empty()
+        || request_id.chars().count() > MAX_REQUEST_ID_CHARS
+        || request_id.chars().any(char::is_control)
+    {
+        return Err("远程签名请求 ID 无效".to_string());
+    }
+    if request
+        .note
+        .as_deref()
+        .is_some_and(|note| note.chars().count() > MAX_NOTE_CHARS)
+    {
+        return Err(format!("远程签名请求备注不能超过 {MAX_NOTE_CHARS} 个字符"));
+    }
+    if request
+        .expires_at
+        .is_some_and(|expires_at| expires_at <= now)
+    {
+        return Err("远程签名请求已过期".to_string());
+    }
+    Ok(request)
+}
+
+/// Expiry stored with a request: its own, capped at [`MAX_REQUEST_AGE`] from `now`
+fn request_expiry(expires_at: Option<u64>, now: u64) -> u64 {
+    let latest = now.saturating_add(MAX_REQUEST_AGE.as_secs());
+    expires_at.map_or(latest, |expires_at| expires_at.min(latest))
+}
+
+/// Store newly arrived requests for `pairing` and acknowledge them on the relay
+///
+/// Messages are handled in `seq` order and the ack stops before the first one that
+/// could not be stored, so it and everything after it are fetched again next poll.
+async fn sync_pairing(pairing: &RemoteSignPairingRecord) -> Result<usize, String> {
+    pairing_key(pairing)?;
+    let relay = RelayClient::new(&pairing.relay_url).map_err(|e| e.to_string())?;
+    let mut messages = relay
+        .fetch(&pairing.topic, 0, Duration::ZERO)
+        .await
+        .map_err(|e| e.to_string())?;
+    messages.sort_by_key(|message| message.seq);
+    let now = now_unix_secs();
+    let mut stored = 0;
+    let mut handled_through = None;
+    for message in &messages {
+        match receive_message(&relay, pairing, message, now).await {
+            Ok(true) => stored += 1,
+            Ok(false) => {}
+            Err(error) => {
+                tracing::debug!(
+                    "Relay message {} for pairing {} will be retried: {}",
+                    message.seq,
+                    pairing.id,
+                    error
+                );
+                break;
+            }
+        }
+        handled_through = Some(message.seq);
+    }
+    if let Some(through) = handled_through {
+        relay
+            .ack(&pairing.topic, through)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(stored)
+}
+
+/// Store one relay message
+///
+/// `Ok(false)` covers duplicates and requests that were dropped or rejected for good;
+/// `Err` means the message must stay on the relay.
+async fn receive_message(
+    relay: &RelayClient,
+    pairing: &RemoteSignPairingRecord,
+    message: &RelayMessage,
+    now: u64,
+) -> Result<bool, String> {
+    let dropped = |reason: &str| {
+        tracing::warn!(
+            "Dropped relay message {} for pairing {}: {}",
+            message.seq,
+            pairing.id,
+            reason
+        )
+    };
+    let request = match open_request(pairing, &message.envelope, now) {
+        Ok(request) => request,
+        Err(error) => {
+            dropped(&error);
+            return Ok(false);
+        }
+    };
+    let request_json =
+        serde_json::to_string(&request).map_err(|e| format!("序列化远程签名请求失败: {}", e))?;
+    let sender = &message.envelope.sender;
+    let outcome = wallet_store::store_remote_sign_request(
+        &pairing.id,
+        sender,
+        request.request_id.trim(),
+        &request_json,
+        Some(request_expiry(request.expires_at, now)),
+        MAX_PENDING_PER_PAIRING,
+        now,
+    )?;
+    match outcome {
+        RemoteSignStoreOutcome::Stored => Ok(true),
+        RemoteSignStoreOutcome::Duplicate => Ok(false),
+        RemoteSignStoreOutcome::ClientMismatch => {
+            dropped("远程签名客户端与配对绑定的客户端不一致");
+            Ok(false)
+        }
+        RemoteSignStoreOutcome::PendingLimit => {
+            let error = format!("待处理的远程签名请求不能超过 {MAX_PENDING_PER_PAIRING} 条");
+            let response = SignResponse {
+                request_id: request.request_id,
+                status: SignResponseStatus::Rejected,
+                result: None,
+                error: Some(error.clone()),
+                responded_at: now,
+            };
+            let response_json = serde_json::to_string(&response)
+                .map_err(|e| format!("序列化远程签名回执失败: {}", e))?;
+            post_response(relay, pairing, sender, &response_json).await?;
+            dropped(&error);
+            Ok(false)
+        }
+    }
+}
+
+/// Record the answer to a request; the poller delivers it if [`deliver`] is not reached
+pub fn finish_request(
+    id: &str,
+    from: &str,
+    to: &str,
+    status: SignResponseStatus,
+    result: Option<Value>,
+    error: Option<String>,
+) -> Result<RemoteSignRequestRecord, String> {
+    let request_id = wallet_store::find_remote_sign_request(id)?.request_id;
+    let response = SignResponse {
+        request_id,
+        status,
+        result,
+        error,
+        responded_at: now_unix_secs(),
+    };
+    let response_json =
+        serde_json::to_string(&response).map_err(|e| format!("序列化远程签名回执失败: {}", e))?;
+    wallet_store::transition_remote_sign_request(id, from, to, Some(&response_json))
+}
+
+/// Seal the stored response of `record` to its client and post it to the relay
+pub async fn deliver(record: &RemoteSignRequestRecord) -> Result<(), String> {
+    let response_json = record
+        .response_json
+        .as_deref()
+        .ok_or_else(|| "远程签名请求还没有回执".to_string())?;
+    let pairing = wallet_store::find_remote_sign_pairing(&record.pairing_id)?;
+    let client = pairing
+        .client_public_key
+        .as_deref()
+        .ok_or_else(|| "远程签名配对尚未绑定客户端".to_string())?;
+    let relay = RelayClient::new(&pairing.relay_url).map_err(|e| e.to_string())?;
+    post_response(&relay, &pairing, client, response_json).await?;
+    wallet_store::mark_remote_sign_response_delivered(&record.id)
+}
+
+async fn post_response(
+    relay: &RelayClient,
+    pairing: &RemoteSignPairingRecord,
+    client: &str,
+    response_json: &str,
+) -> Result<(), String> {
+    let envelope = Envelope::seal(
+        &pairing_key(pairing)?,
+        client,
+        &pairing.topic,
+        Direction::Response,
+        response_json.as_bytes(),
+    )
+    .map_err(|e| format!("加密远程签名回执失败: {}", e))?;
+    relay
+        .post(&response_topic(&pairing.topic, client), &envelope)
+        .await
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+fn expire_requests(now: u64) -> Result<(), String> {
+    for record in wallet_store::list_remote_sign_requests(Some(REMOTE_SIGN_STATUS_PENDING))? {
+        if record
+            .expires_at
+            .is_some_and(|expires_at| expires_at <= now)
+        {
+            finish_request(
+                &record.id,
+                REMOTE_SIGN_STATUS_PENDING,
+                REMOTE_SIGN_STATUS_EXPIRED,
+                SignResponseStatus::Expired,
+                None,
+                Some("request expired before it was approved".to_string()),
+            )?;
+        }
+    }
+    Ok(())
+}
+
+async fn sync_once() -> Result<(), String> {
+    expire_requests(now_unix_secs())?;
+    for pairing in wallet_store::list_remote_sign_pairings()? {
+        match sync_pairing(&pairing).await {
+            Ok(0) => {}
+            Ok(stored) => tracing::info!(
+                "Received {} remote sign request(s) for pairing {}",
+                stored,
+                pairing.id
+            ),
+            Err(error) => {
+                tracing::debug!("Relay sync for pairing {} failed: {}", pairing.id, error)
+            }
+        }
+    }
+    for record in wallet_store::undelivered_remote_sign_responses()? {
+        if let Err(error) = deliver(&record).await {
+            tracing::debug!(
+                "Delivering remote sign response {} failed: {}",
+                record.id,
+                error
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Start the background relay poller
+pub fn spawn_poller() {
+    if let Err(error) = wallet_store::reset_interrupted_remote_sign_requests() {
+        tracing::warn!(
+            "Failed to reset interrupted remote sign requests: {}",
+            error
+        );
+    }
+    tokio::spawn(async {
+        let mut interval = tokio::time::interval(POLL_INTERVAL);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            if let Err(error) = sync_once().await {
+                tracing::warn!("Remote sign relay sync failed: {}", error);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fnzero_safe_remote_sign::{
+        relay::{self, RelayConfig},
+        RemoteSigner, SignPayload,
+    };
+
+    fn test_pairing(relay_url: &str) -> (RemoteSignPairingRecord, StaticKey) {
+        let key = StaticKey::generate();
+        let record = RemoteSignPairingRecord {
+            id: "pairing".to_string(),
+            name: "withdrawal bot".to_string(),
+            relay_url: relay_url.to_string(),
+            topic: new_topic(),
+            public_key: key.public_key(),
+            secret_key: key.to_base64().to_string(),
+            client_public_key: None,
+            created_at: 1,
+            last_seen_at: None,
+        };
+        (record, key)
+    }
+
+    #[test]
+    fn stored_requests_always_expire() {
+        let max_age = MAX_REQUEST_AGE.as_secs();
+        assert_eq!(request_expiry(None, 100), 100 + max_age);
+        assert_eq!(request_expiry(Some(160), 100), 160);
+        assert_eq!(request_expiry(Some(u64::MAX), 100), 100 + max_age);
+    }
+
+    #[tokio::test]
+    async fn requests_and_responses_round_trip_through_a_local_relay() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let relay_url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(relay::serve(listener, RelayConfig::default()));
+
+        let (mut pairing, desktop_key) = test_pairing(&relay_url);
+        let uri = PairingUri::parse(&pairing_uri(&pairing)).unwrap();
+        let mut bot = RemoteSigner::new(uri, StaticKey::generate()).unwrap();
+        let request = SignRequest::new(
+            SignPayload::Message {
+                required_signer: "11111111111111111111111111111111".to_string(),
+                message_base64: "aGVsbG8=".to_string(),
+            },
+            Some(Duration::from_secs(600)),
+        )
+        .with_note("withdraw 500 SOL");
+        bot.send(&request).await.unwrap();
+
+        let relay = RelayClient::new(&relay_url).unwrap();
+        let messages = relay
+            .fetch(&pairing.topic, 0, Duration::ZERO)
+            .await
+            .unwrap();
+        assert_eq!(messages.len(), 1);
+        let envelope = &messages[0].envelope;
+        let now = now_unix_secs();
+        assert_eq!(open_request(&pairing, envelope, now).unwrap(), request);
+
+        // Once pinned, envelopes from any other client are dropped
+        pairing.client_public_key = Some(envelope.sender.clone());
+        let intruder = Envelope::seal(
+            &StaticKey::generate(),
+            &pairing.public_key,
+            &pairing.topic,
+            Direction::Request,
+            &serde_json::to_vec(&request).unwrap(),
+        )
+        .unwrap();
+        assert!(open_request(&pairing, &intruder, now).is_err());
+        let mut expired = request.clone();
+        expired.expires_at = Some(now);
+        let stale = Envelope::seal(
+            &StaticKey::generate(),
+            &pairing.public_key,
+            &pairing.topic,
+            Direction::Request,
+            &serde_json::to_vec(&expired).unwrap(),
+        )
+        .unwrap();
+        pairing.client_public_key = None;
+        assert!(open_request(&pairing, &stale, now).is_err());
+
+        let response = SignResponse {
+            request_id: request.request_id.clone(),
+            status: SignResponseStatus::Approved,
+            result: Some(serde_json::json!({ "signature": "sig" })),
+            error: None,
+            responded_at: now,
+        };
+        let sealed = Envelope::seal(
+            &desktop_key,
+            &envelope.sender,
+            &pairing.topic,
+            Direction::Response,
+            &serde_json::to_vec(&response).unwrap(),
+        )
+        .unwrap();
+        relay
+            .post(&response_topic(&pairing.topic, &envelope.sender), &sealed)
+            .await
+            .unwrap();
+        let received = bot
+            .wait_for_response(&request.request_id, Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert_eq!(received, response);
+    }
+}
@@ -0,0 +1,318 @@
+//! Optional at-rest vault for the wallet table
+//!
+//! Keystores are already password-encrypted, but the `wallets` rows also carry wallet
+//! names, public keys and keystore metadata such as `encrypted_mnemonic` in clear. Once
+//! the vault is enabled each row is sealed with AES-256-GCM under a vault key:
+//!
+//! - `keystore_json` holds `vault1:<base64(nonce || ciphertext)>` of the row's name,
+//!   public key and keystore JSON, with the wallet id as associated data
+//! - `public_key` holds an HMAC-SHA256 blind index so lookups and the UNIQUE
+//!   constraint keep working without storing the address
+//! - `name` is left empty
+//!
+//! The key is either derived from a vault passphrase (PBKDF2-HMAC-SHA256) or a random
+//! key kept in the OS keyring (Keychain, Windows Credential Manager, Secret Service).
+//! The [`VaultHeader`] in `app_settings` records which, plus a sealed check value used
+//! to reject wrong passphrases. The unlocked key only lives in process memory.
+
+use aes_gcm::{
+    aead::{Aead, KeyInit, Payload},
+    Aes256Gcm, Nonce,
+};
+use aws_lc_rs::{hmac, pbkdf2};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::{
+    num::NonZeroU32,
+    sync::{Arc, Mutex, OnceLock},
+};
+use uuid::Uuid;
+use zeroize::Zeroizing;
+
+/// `app_settings` key holding the JSON [`VaultHeader`]
+pub const VAULT_SETTINGS_KEY: &str = "vault";
+pub const VAULT_LOCKED_MESSAGE: &str = "钱包保险库已锁定，请先解锁";
+const VAULT_VERSION: u32 = 1;
+const SEALED_PREFIX: &str = "vault1:";
+const PASSPHRASE_ITERATIONS: u32 = 600_000;
+const KEYRING_SERVICE: &str = "fnzero-safe";
+const KEY_CHECK_PLAINTEXT: &[u8] = b"fnzero-safe wallet vault";
+const KEY_CHECK_AAD: &[u8] = b"vault-key-check";
+const BLIND_INDEX_CONTEXT: &[u8] = b"wallet-public-key:";
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum VaultKeySource {
+    Passphrase,
+    Keyring,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct VaultHeader {
+    pub version: u32,
+    /// Also the keyring account name, so a rotation never overwrites the live key
+    pub vault_id: String,
+    pub source: VaultKeySource,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub salt: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iterations: Option<u32>,
+    pub key_check: String,
+    pub created_at: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rotated_at: Option<u64>,
+}
+
+pub struct VaultKey(Zeroizing<[u8; 32]>);
+
+impl VaultKey {
+    fn random() -> Self {
+        let mut key = Zeroizing::new([0u8; 32]);
+        OsRng.fill_bytes(key.as_mut());
+        Self(key)
+    }
+
+    fn from_passphrase(passphrase: &str, salt: &[u8], iterations: u32) -> Result<Self, String> {
+        let iterations =
+            NonZeroU32::new(iterations).ok_or_else(|| "保险库迭代次数无效".to_string())?;
+        let mut key = Zeroizing::new([0u8; 32]);
+        pbkdf2::derive(
+            pbkdf2::PBKDF2_HMAC_SHA256,
+            iterations,
+            salt,
+            passphrase.as_bytes(),
+            key.as_mut(),
+        );
+        Ok(Self(key))
+    }
+
+    fn cipher(&self) -> Aes256Gcm {
+        Aes256Gcm::new_from_slice(self.0.as_ref()).expect("vault key is 32 bytes")
+    }
+
+    pub fn seal(&self, plaintext: &[u8], aad: &[u8]) -> Result<String, String> {
+        let mut nonce = [0u8; 12];
+        OsRng.fill_bytes(&mut nonce);
+        let ciphertext = self
+            .cipher()
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: plaintext,
+                    aad,
+                },
+            )
+            .map_err(|_| "保险库加密失败".to_string())?;
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&ciphertext);
+        Ok(format!("{SEALED_PREFIX}{}", BASE64.encode(sealed)))
+    }
+
+    pub fn open(&self, sealed: &str, aad: &[u8]) -> Result<Zeroizing<Vec<u8>>, String> {
+        let encoded = sealed
+            .strip_prefix(SEALED_PREFIX)
+            .ok_or_else(|| "保险库数据格式无效".to_string())?;
+        let bytes = BASE64
+            .decode(encoded)
+            .map_err(|_| "保险库数据格式无效".to_string())?;
+        if bytes.len() < 12 {
+            return Err("保险库数据格式无效".to_string());
+        }
+        let (nonce, ciphertext) = bytes.split_at(12);
+        self.cipher()
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad,
+                },
+            )
+            .map(Zeroizing::new)
+            .map_err(|_| "保险库数据解密失败".to_string())
+    }
+
+    /// Deterministic stand-in for a public key in the `public_key` column
+    pub fn blind_index(&self, public_key: &str) -> String {
+        let key = hmac::Key::new(hmac::HMAC_SHA256, self.0.as_ref());
+        let mut context = hmac::Context::with_key(&key);
+        context.update(BLIND_INDEX_CONTEXT);
+        context.update(public_key.as_bytes());
+        let tag = context.sign();
+        format!("{SEALED_PREFIX}{}", BASE64.encode(tag.as_ref()))
+    }
+}
+
+fn keyring_entry(vault_id: &str) -> Result<keyring::Entry, String> {
+    keyring::Entry::new(KEYRING_SERVICE, &format!("wallet-vault-{vault_id}"))
+        .map_err(|e| format!("打开系统钥匙串失败: {}", e))
+}
+
+/// Create a header and key for a new vault, storing keyring keys before returning
+///
+/// Call [`discard_key`] if the header is not committed afterwards.
+pub fn create(
+    source: VaultKeySource,
+    passphrase: Option<&str>,
+    now: u64,
+) -> Result<(VaultHeader, VaultKey), String> {
+    create_with_iterations(source, passphrase, now, PASSPHRASE_ITERATIONS)
+}
+
+fn create_with_iterations(
+    source: VaultKeySource,
+    passphrase: Option<&str>,
+    now: u64,
+    iterations: u32,
+) -> Result<(VaultHeader, VaultKey), String> {
+    let vault_id = Uuid::new_v4().simple().to_string();
+    let (key, salt, iterations) = match source {
+        VaultKeySource::Passphrase => {
+            let passphrase = passphrase
+                .filter(|value| !value.is_empty())
+                .ok_or_else(|| "保险库口令不能为空".to_string())?;
+            let mut salt = [0u8; 16];
+            OsRng.fill_bytes(&mut salt);
+            let key = VaultKey::from_passphrase(passphrase, &salt, iterations)?;
+            (key, Some(BASE64.encode(salt)), Some(iterations))
+        }
+        VaultKeySource::Keyring => {
+            let key = VaultKey::random();
+            keyring_entry(&vault_id)?
+                .set_secret(key.0.as_ref())
+                .map_err(|e| format!("写入系统钥匙串失败: {}", e))?;
+            (key, None, None)
+        }
+    };
+    let header = VaultHeader {
+        version: VAULT_VERSION,
+        vault_id,
+        source,
+        salt,
+        iterations,
+        key_check: key.seal(KEY_CHECK_PLAINTEXT, KEY_CHECK_AAD)?,
+        created_at: now,
+        rotated_at: None,
+    };
+    Ok((header, key))
+}
+
+/// Recover the key for `header`, failing on a wrong passphrase or missing keyring entry
+pub fn derive_key(header: &VaultHeader, passphrase: Option<&str>) -> Result<VaultKey, String> {
+    if header.version != VAULT_VERSION {
+        return Err(format!("不支持的保险库版本: {}", header.version));
+    }
+    let key = match header.source {
+        VaultKeySource::Passphrase => {
+            let passphrase = passphrase
+                .filter(|value| !value.is_empty())
+                .ok_or_else(|| "保险库口令不能为空".to_string())?;
+            let salt = header
+                .salt
+                .as_deref()
+                .and_then(|salt| BASE64.decode(salt).ok())
+                .ok_or_else(|| "保险库盐值无效".to_string())?;
+            let iterations = header
+                .iterations
+                .ok_or_else(|| "保险库迭代次数无效".to_string())?;
+            VaultKey::from_passphrase(passphrase, &salt, iterations)?
+        }
+        VaultKeySource::Keyring => {
+            let secret = Zeroizing::new(
+                keyring_entry(&header.vault_id)?
+                    .get_secret()
+                    .map_err(|e| format!("读取系统钥匙串失败: {}", e))?,
+            );
+            let bytes: [u8; 32] = secret
+                .as_slice()
+                .try_into()
+                .map_err(|_| "系统钥匙串中的保险库密钥无效".to_string())?;
+            VaultKey(Zeroizing::new(bytes))
+        }
+    };
+    match key.open(&header.key_check, KEY_CHECK_AAD) {
+        Ok(check) if check.as_slice() == KEY_CHECK_PLAINTEXT => Ok(key),
+        _ => Err("保险库解锁失败：口令或密钥不正确".to_string()),
+    }
+}
+
+/// Remove the keyring entry of a vault that was rotated away or never committed
+pub fn discard_key(header: &VaultHeader) {
+    if header.source != VaultKeySource::Keyring {
+        return;
+    }
+    if let Err(error) = keyring_entry(&header.vault_id)
+        .and_then(|entry| entry.delete_credential().map_err(|e| e.to_string()))
+    {
+        tracing::warn!(
+            "Failed to remove vault key {} from the OS keyring: {}",
+            header.vault_id,
+            error
+        );
+    }
+}
+
+struct UnlockedVault {
+    vault_id: String,
+    key: Arc<VaultKey>,
+}
+
+fn unlocked() -> &'static Mutex<Option<UnlockedVault>> {
+    static UNLOCKED: OnceLock<Mutex<Option<UnlockedVault>>> = OnceLock::new();
+    UNLOCKED.get_or_init(|| Mutex::new(None))
+}
+
+/// The in-memory key for `header`, if that vault is unlocked
+pub fn unlocked_key(header: &VaultHeader) -> Option<Arc<VaultKey>> {
+    let guard = unlocked().lock().ok()?;
+    guard
+        .as_ref()
+        .filter(|vault| vault.vault_id == header.vault_id)
+        .map(|vault| vault.key.clone())
+}
+
+pub fn set_unlocked(header: &VaultHeader, key: VaultKey) -> Result<(), String> {
+    let mut guard = unlocked()
+        .lock()
+        .map_err(|_| "保险库状态锁已损坏".to_string())?;
+    *guard = Some(UnlockedVault {
+        vault_id: header.vault_id.clone(),
+        key: Arc::new(key),
+    });
+    Ok(())
+}
+
+pub fn lock() -> Result<(), String> {
+    let mut guard = unlocked()
+        .lock()
+        .map_err(|_| "保险库状态锁已损坏".to_string())?;
+    *guard = None;
+    Ok(())
+}
+
+#[cfg(test)]
+pub(crate) fn test_passphrase_vault(passphrase: &str) -> (VaultHeader, VaultKey) {
+    create_with_iterations(VaultKeySource::Passphrase, Some(passphrase), 1, 1_000).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn passphrase_key_is_checked_and_rows_are_bound_to_their_id() {
+        let (header, key) = test_passphrase_vault("correct horse");
+        assert!(derive_key(&header, Some("wrong horse")).is_err());
+        let derived = derive_key(&header, Some("correct horse")).unwrap();
+        assert_eq!(derived.blind_index("addr"), key.blind_index("addr"));
+        assert_ne!(key.blind_index("addr"), key.blind_index("addr2"));
+
+        let sealed = key.seal(b"keystore", b"wallet-a").unwrap();
+        assert!(sealed.starts_with(SEALED_PREFIX));
+        assert_eq!(
+            derived.open(&sealed, b"wallet-a").unwrap().as_slice(),
+            b"keystore"
+        );
+        assert!(derived.open(&sealed, b"wallet-b").is_err());
+    }
+}
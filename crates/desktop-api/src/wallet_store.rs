@@ -1,21 +1,27 @@
+use crate::vault::{self, VaultHeader, VaultKey, VaultKeySource, VAULT_SETTINGS_KEY};
+use fnzero_safe::solana_utils::payouts::{PayoutLineStatus, PayoutProgress, PayoutProgressStore};
+use fnzero_safe::solana_utils::swqos::SwqosSettings;
+use fnzero_safe::totp_guard::{TotpGuardState, TotpStateStore};
 use fnzero_safe::{KeyManager, KeystoreVersion};
 use rusqlite::{params, Connection, OptionalExtension};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::{
-    path::PathBuf,
-    sync::{Mutex, OnceLock},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, OnceLock},
     time::{SystemTime, UNIX_EPOCH},
 };
 use uuid::Uuid;
+use zeroize::Zeroizing;
 
 #[cfg(unix)]
 use std::os::unix::fs::DirBuilderExt;
 
-const DEFAULT_DATABASE_PATH: &str = "data/fnzero-safe.sqlite3";
-const LEGACY_DATABASE_PATH: &str = "data/sol-safekey.sqlite3";
-const DATABASE_PATH_ENV: &str = "FNZERO_SAFE_DB_PATH";
-const LEGACY_DATABASE_PATH_ENV: &str = "SOL_SAFEKEY_DB_PATH";
+/// Databases that builds before the config file kept under the crate's manifest dir
+const MANIFEST_DIR_DATABASE_PATHS: [&str; 2] =
+    ["data/fnzero-safe.sqlite3", "data/sol-safekey.sqlite3"];
 const MAX_NONCE_ACCOUNTS_PER_OWNER_NETWORK: usize = 100;
+const SWQOS_SETTINGS_KEY: &str = "swqos";
 
 fn store_lock() -> &'static Mutex<()> {
     static LOCK: OnceLock<Mutex<()>> = OnceLock::new();
@@ -125,6 +131,126 @@ pub struct ProgramDeploymentAttemptRecord {
     pub updated_at: u64,
 }
 
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct WalletTransactionChange {
+    pub asset: String,
+    pub mint: Option<String>,
+    pub amount: String,
+    pub ui_amount: String,
+    pub direction: String,
+    pub decimals: u8,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct WalletTransactionRecord {
+    pub signature: String,
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    pub confirmation_status: Option<String>,
+    pub err: Option<Value>,
+    pub memo: Option<String>,
+    pub action: String,
+    pub summary: String,
+    pub counterparty: Option<String>,
+    pub programs: Vec<String>,
+    pub changes: Vec<WalletTransactionChange>,
+    /// `getTransaction` succeeded; otherwise the indexer retries the details later
+    pub details_loaded: bool,
+}
+
+/// 某个钱包在某个网络上的交易索引进度
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct WalletHistoryState {
+    pub newest_signature: Option<String>,
+    pub oldest_signature: Option<String>,
+    pub backfill_complete: bool,
+    pub transaction_count: u64,
+    pub updated_at: Option<u64>,
+}
+
+/// 本地交易记录筛选条件，均为可选
+#[derive(Clone, Debug, Default)]
+pub struct WalletTransactionFilter {
+    /// Token mint, or `SOL` for native SOL changes
+    pub mint: Option<String>,
+    pub counterparty: Option<String>,
+    pub action: Option<String>,
+    /// Substring of the signature, counterparty, memo, summary, programs or assets
+    pub search: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct AddressBookRecord {
+    pub id: String,
+    pub network: String,
+    pub address: String,
+    pub label: String,
+    pub notes: Option<String>,
+    pub expected_mint: Option<String>,
+    pub send_count: u64,
+    pub last_sent_at: Option<u64>,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+/// 本机向某个地址发送过的记录
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct RecipientHistory {
+    pub send_count: u64,
+    pub first_sent_at: Option<u64>,
+    pub last_sent_at: Option<u64>,
+}
+
+/// 远程签名配对：桌面端的 X25519 密钥与中继上的 mailbox topic
+///
+/// `secret_key` 只用于解密请求和加密回执，不能签名交易。
+#[derive(Clone, Debug, Serialize)]
+pub struct RemoteSignPairingRecord {
+    pub id: String,
+    pub name: String,
+    pub relay_url: String,
+    pub topic: String,
+    pub public_key: String,
+    #[serde(skip_serializing)]
+    pub secret_key: String,
+    /// 首个有效请求的发送方公钥，之后只接受该客户端
+    pub client_public_key: Option<String>,
+    pub created_at: u64,
+    pub last_seen_at: Option<u64>,
+}
+
+#[derive(Clone, Debug)]
+pub struct RemoteSignRequestRecord {
+    pub id: String,
+    pub pairing_id: String,
+    pub request_id: String,
+    pub request_json: String,
+    pub status: String,
+    pub response_json: Option<String>,
+    pub delivered: bool,
+    pub expires_at: Option<u64>,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+pub const REMOTE_SIGN_STATUS_PENDING: &str = "pending";
+pub const REMOTE_SIGN_STATUS_SIGNING: &str = "signing";
+pub const REMOTE_SIGN_STATUS_APPROVED: &str = "approved";
+pub const REMOTE_SIGN_STATUS_REJECTED: &str = "rejected";
+pub const REMOTE_SIGN_STATUS_EXPIRED: &str = "expired";
+
+/// 保存远程签名请求的结果；数据库错误以 `Err` 返回，由调用方稍后重试
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RemoteSignStoreOutcome {
+    Stored,
+    /// 同一配对内的 `request_id` 已存在
+    Duplicate,
+    /// 发送方不是配对已绑定的客户端
+    ClientMismatch,
+    /// 待处理请求已达上限
+    PendingLimit,
+}
+
 pub const PROGRAM_DEPLOYMENT_STAGE_CREATE_BUFFER: &str = "create_buffer";
 pub const PROGRAM_DEPLOYMENT_STAGE_WRITE: &str = "write";
 pub const PROGRAM_DEPLOYMENT_STAGE_DEPLOY: &str = "deploy";
@@ -186,46 +312,131 @@ fn normalize_wallet_name(name: Option<String>, public_key: &str) -> String {
 fn ensure_data_dir() -> Result<(), String> {
     let database_path = database_path();
     if let Some(parent) = database_path.parent() {
-        #[cfg(unix)]
-        {
-            std::fs::DirBuilder::new()
-                .recursive(true)
-                .mode(0o700)
-                .create(parent)
-                .map_err(|e| format!("创建数据目录失败: {}", e))?;
+        create_private_dir(parent)?;
+    }
+    Ok(())
+}
+
+fn create_private_dir(dir: &Path) -> Result<(), String> {
+    #[cfg(unix)]
+    {
+        std::fs::DirBuilder::new()
+            .recursive(true)
+            .mode(0o700)
+            .create(dir)
+            .map_err(|e| format!("创建数据目录失败: {}", e))
+    }
+    #[cfg(not(unix))]
+    std::fs::create_dir_all(dir).map_err(|e| format!("创建数据目录失败: {}", e))
+}
+
+fn database_path() -> &'static Path {
+    &crate::config::get().database_path
+}
+
+/// Move a database left in the build tree by older builds to the platform data dir, once
+///
+/// Only success is remembered: a failed attempt is retried on the next open, and until it
+/// succeeds opens fail rather than start over with an empty database.
+fn migrate_manifest_dir_database() -> Result<(), String> {
+    static MIGRATED: OnceLock<()> = OnceLock::new();
+    if MIGRATED.get().is_some() {
+        return Ok(());
+    }
+    if crate::config::get().database_path_is_default {
+        let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let legacy = MANIFEST_DIR_DATABASE_PATHS.map(|path| manifest_dir.join(path));
+        if let Some(from) = migrate_legacy_database(database_path(), &legacy)? {
+            tracing::info!(
+                "Migrated wallet database from {} to {} and removed the old file",
+                from.display(),
+                database_path().display()
+            );
         }
-        #[cfg(not(unix))]
-        std::fs::create_dir_all(parent).map_err(|e| format!("创建数据目录失败: {}", e))?;
     }
+    let _ = MIGRATED.set(());
     Ok(())
 }
 
-fn database_path() -> PathBuf {
-    if let Ok(path) = std::env::var(DATABASE_PATH_ENV) {
-        return PathBuf::from(path);
+/// Copy the first existing `legacy` database to `target` unless `target` already exists
+///
+/// `VACUUM INTO` writes a consistent copy including WAL contents. The source is only deleted
+/// once the copy passes an integrity check and holds the same rows; otherwise the copy is
+/// removed and the source is left alone for the next start.
+fn migrate_legacy_database(target: &Path, legacy: &[PathBuf]) -> Result<Option<PathBuf>, String> {
+    if target.exists() {
+        return Ok(None);
+    }
+    let Some(source) = legacy.iter().find(|path| path.is_file() && *path != target) else {
+        return Ok(None);
+    };
+    if let Some(parent) = target.parent() {
+        create_private_dir(parent)?;
     }
-    if let Ok(path) = std::env::var(LEGACY_DATABASE_PATH_ENV) {
-        return PathBuf::from(path);
+    let target_str = target
+        .to_str()
+        .ok_or_else(|| format!("数据库路径无效: {}", target.display()))?;
+    let source_conn = Connection::open(source)
+        .map_err(|e| format!("迁移旧数据库 {} 失败: {}", source.display(), e))?;
+    source_conn
+        .execute("VACUUM INTO ?1", params![target_str])
+        .map_err(|e| format!("迁移旧数据库 {} 失败: {}", source.display(), e))?;
+    if let Err(error) = verify_database_copy(&source_conn, target) {
+        let _ = std::fs::remove_file(target);
+        return Err(format!("迁移旧数据库 {} 失败: {}", source.display(), error));
     }
+    drop(source_conn);
+    for suffix in ["", "-wal", "-shm"] {
+        let file = PathBuf::from(format!("{}{}", source.display(), suffix));
+        if file.exists() {
+            std::fs::remove_file(&file)
+                .map_err(|e| format!("删除旧数据库 {} 失败: {}", file.display(), e))?;
+        }
+    }
+    Ok(Some(source.clone()))
+}
 
-    let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
-    let default_path = manifest_dir.join(DEFAULT_DATABASE_PATH);
-    let legacy_path = manifest_dir.join(LEGACY_DATABASE_PATH);
-    if !default_path.exists() && legacy_path.exists() {
-        legacy_path
-    } else {
-        default_path
+/// Check that `target` is intact and has as many rows as `source` in every table
+fn verify_database_copy(source: &Connection, target: &Path) -> Result<(), String> {
+    let copy = Connection::open(target).map_err(|e| format!("打开迁移副本失败: {}", e))?;
+    let integrity: String = copy
+        .query_row("PRAGMA integrity_check", [], |row| row.get(0))
+        .map_err(|e| format!("检查迁移副本失败: {}", e))?;
+    if integrity != "ok" {
+        return Err(format!("迁移副本校验失败: {}", integrity));
     }
+    let tables = source
+        .prepare("SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%'")
+        .and_then(|mut stmt| {
+            stmt.query_map([], |row| row.get::<_, String>(0))?
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .map_err(|e| format!("读取旧数据库表失败: {}", e))?;
+    for table in tables {
+        let sql = format!("SELECT COUNT(*) FROM \"{}\"", table.replace('"', "\"\""));
+        let count = |conn: &Connection| conn.query_row(&sql, [], |row| row.get::<_, i64>(0));
+        let expected =
+            count(source).map_err(|e| format!("读取旧数据库表 {} 失败: {}", table, e))?;
+        let copied = count(&copy).map_err(|e| format!("读取迁移副本表 {} 失败: {}", table, e))?;
+        if copied != expected {
+            return Err(format!(
+                "迁移副本表 {} 行数不一致: {} != {}",
+                table, copied, expected
+            ));
+        }
+    }
+    Ok(())
 }
 
 fn open_connection() -> Result<Connection, String> {
     ensure_data_dir()?;
+    migrate_manifest_dir_database()?;
     let database_path = database_path();
-    let conn = Connection::open(&database_path).map_err(|e| format!("打开数据库失败: {}", e))?;
+    let conn = Connection::open(database_path).map_err(|e| format!("打开数据库失败: {}", e))?;
     #[cfg(unix)]
     {
         use std::os::unix::fs::PermissionsExt;
-        std::fs::set_permissions(&database_path, std::fs::Permissions::from_mode(0o600))
+        std::fs::set_permissions(database_path, std::fs::Permissions::from_mode(0o600))
             .map_err(|e| format!("设置数据库权限失败: {}", e))?;
     }
     conn.pragma_update(None, "journal_mode", "WAL")
@@ -243,6 +454,10 @@ pub fn checkpoint_sensitive_rewrite() -> Result<(), String> {
         .lock()
         .map_err(|_| "数据库写锁已损坏".to_string())?;
     let conn = open_connection()?;
+    checkpoint_with_connection(&conn)
+}
+
+fn checkpoint_with_connection(conn: &Connection) -> Result<(), String> {
     let (busy, log_frames, checkpointed_frames) = conn
         .query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |row| {
             Ok((
@@ -347,6 +562,12 @@ fn init_schema(conn: &Connection) -> Result<(), String> {
             PRIMARY KEY(network, mint)
         );
 
+        CREATE TABLE IF NOT EXISTS app_settings (
+            key TEXT PRIMARY KEY NOT NULL,
+            value TEXT NOT NULL,
+            updated_at INTEGER NOT NULL
+        );
+
         CREATE TABLE IF NOT EXISTS program_deployment_journal (
             genesis_hash TEXT NOT NULL,
             program_id TEXT NOT NULL,
@@ -388,6 +609,126 @@ fn init_schema(conn: &Connection) -> Result<(), String> {
                 ON DELETE CASCADE
         );
 
+        CREATE TABLE IF NOT EXISTS payout_progress (
+            network TEXT NOT NULL,
+            batch_id TEXT NOT NULL,
+            line_index INTEGER NOT NULL,
+            status TEXT NOT NULL,
+            signature TEXT,
+            last_valid_block_height INTEGER,
+            error TEXT,
+            updated_at INTEGER NOT NULL,
+            PRIMARY KEY(network, batch_id, line_index)
+        );
+
+        CREATE TABLE IF NOT EXISTS wallet_transactions (
+            owner TEXT NOT NULL,
+            network TEXT NOT NULL,
+            signature TEXT NOT NULL,
+            slot INTEGER NOT NULL,
+            block_time INTEGER,
+            confirmation_status TEXT,
+            err TEXT,
+            memo TEXT,
+            action TEXT NOT NULL,
+            summary TEXT NOT NULL,
+            counterparty TEXT,
+            programs TEXT NOT NULL,
+            details_loaded INTEGER NOT NULL,
+            indexed_at INTEGER NOT NULL,
+            PRIMARY KEY(owner, network, signature)
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_wallet_transactions_order
+            ON wallet_transactions(owner, network, slot DESC, signature DESC);
+
+        CREATE TABLE IF NOT EXISTS wallet_transaction_changes (
+            owner TEXT NOT NULL,
+            network TEXT NOT NULL,
+            signature TEXT NOT NULL,
+            position INTEGER NOT NULL,
+            asset TEXT NOT NULL,
+            mint TEXT,
+            amount TEXT NOT NULL,
+            ui_amount TEXT NOT NULL,
+            direction TEXT NOT NULL,
+            decimals INTEGER NOT NULL,
+            PRIMARY KEY(owner, network, signature, position),
+            FOREIGN KEY(owner, network, signature)
+                REFERENCES wallet_transactions(owner, network, signature)
+                ON DELETE CASCADE
+        );
+
+        CREATE INDEX IF NOT EXISTS idx_wallet_transaction_changes_mint
+            ON wallet_transaction_changes(owner, network, mint);
+
+        CREATE TABLE IF NOT EXISTS wallet_history_state (
+            owner TEXT NOT NULL,
+            network TEXT NOT NULL,
+            newest_signature TEXT,
+            oldest_signature TEXT,
+            backfill_complete INTEGER NOT NULL,
+            updated_at INTEGER NOT NULL,
+            PRIMARY KEY(owner, network)
+        );
+
+        CREATE TABLE IF NOT EXISTS address_book (
+            id TEXT PRIMARY KEY,
+            network TEXT NOT NULL,
+            address TEXT NOT NULL,
+            label TEXT NOT NULL,
+            notes TEXT,
+            expected_mint TEXT,
+            created_at INTEGER NOT NULL,
+            updated_at INTEGER NOT NULL,
+            UNIQUE(network, address)
+        );
+
+        CREATE TABLE IF NOT EXISTS sent_recipients (
+            network TEXT NOT NULL,
+            address TEXT NOT NULL,
+            send_count INTEGER NOT NULL,
+            first_sent_at INTEGER NOT NULL,
+            last_sent_at INTEGER NOT NULL,
+            PRIMARY KEY(network, address)
+        );
+
+        CREATE TABLE IF NOT EXISTS totp_guard_state (
+            wallet_key TEXT PRIMARY KEY,
+            last_accepted_step INTEGER,
+            failed_attempts INTEGER NOT NULL,
+            locked_until INTEGER NOT NULL,
+            generation INTEGER NOT NULL DEFAULT 0,
+            spent_backup_codes TEXT NOT NULL DEFAULT '[]',
+            updated_at INTEGER NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS remote_sign_pairings (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            relay_url TEXT NOT NULL,
+            topic TEXT NOT NULL UNIQUE,
+            public_key TEXT NOT NULL,
+            secret_key TEXT NOT NULL,
+            client_public_key TEXT,
+            created_at INTEGER NOT NULL,
+            last_seen_at INTEGER
+        );
+
+        CREATE TABLE IF NOT EXISTS remote_sign_requests (
+            id TEXT PRIMARY KEY,
+            pairing_id TEXT NOT NULL,
+            request_id TEXT NOT NULL,
+            request_json TEXT NOT NULL,
+            status TEXT NOT NULL,
+            response_json TEXT,
+            delivered INTEGER NOT NULL DEFAULT 0,
+            expires_at INTEGER,
+            created_at INTEGER NOT NULL,
+            updated_at INTEGER NOT NULL,
+            UNIQUE(pairing_id, request_id)
+        );
+
         CREATE INDEX IF NOT EXISTS idx_program_deployment_attempts_active
             ON program_deployment_attempts(genesis_hash, program_id, status, stage, chunk_index);
 
@@ -401,6 +742,18 @@ fn init_schema(conn: &Connection) -> Result<(), String> {
     )
     .map_err(|e| format!("初始化数据库表失败: {}", e))?;
     ensure_column(conn, "wallet_token_assets", "name", "TEXT")?;
+    ensure_column(
+        conn,
+        "totp_guard_state",
+        "generation",
+        "INTEGER NOT NULL DEFAULT 0",
+    )?;
+    ensure_column(
+        conn,
+        "totp_guard_state",
+        "spent_backup_codes",
+        "TEXT NOT NULL DEFAULT '[]'",
+    )?;
     ensure_column(conn, "wallet_token_assets", "symbol", "TEXT")?;
     ensure_column(conn, "wallet_token_assets", "logo_uri", "TEXT")?;
     ensure_column(
@@ -1575,78 +1928,189 @@ fn rotate_program_deployment_create_attempt_with_connection(
     Ok(stored)
 }
 
-pub fn load() -> Result<Vec<SavedWallet>, String> {
-    let _guard = store_lock()
-        .lock()
-        .map_err(|_| "数据库读锁已损坏".to_string())?;
-    let conn = open_connection()?;
+/// Name, public key and keystore of a wallet row, sealed together while the vault is on
+#[derive(Deserialize, Serialize)]
+struct SealedWalletFields {
+    name: String,
+    public_key: String,
+    keystore_json: String,
+}
+
+fn vault_header_with_connection(conn: &Connection) -> Result<Option<VaultHeader>, String> {
+    get_setting_with_connection(conn, VAULT_SETTINGS_KEY)?
+        .map(|value| serde_json::from_str(&value).map_err(|e| format!("解析保险库设置失败: {}", e)))
+        .transpose()
+}
+
+/// The key wallet rows are sealed with, `None` while the vault is disabled
+fn wallet_vault_key(conn: &Connection) -> Result<Option<Arc<VaultKey>>, String> {
+    match vault_header_with_connection(conn)? {
+        Some(header) => vault::unlocked_key(&header)
+            .map(Some)
+            .ok_or_else(|| vault::VAULT_LOCKED_MESSAGE.to_string()),
+        None => Ok(None),
+    }
+}
+
+fn open_wallet_row(wallet: SavedWallet, key: Option<&VaultKey>) -> Result<SavedWallet, String> {
+    let Some(key) = key else {
+        return Ok(wallet);
+    };
+    let plaintext = key.open(&wallet.keystore_json, wallet.id.as_bytes())?;
+    let fields: SealedWalletFields =
+        serde_json::from_slice(&plaintext).map_err(|e| format!("解析保险库钱包记录失败: {}", e))?;
+    Ok(SavedWallet {
+        name: fields.name,
+        public_key: fields.public_key,
+        keystore_json: fields.keystore_json,
+        ..wallet
+    })
+}
+
+/// Stored `(name, public_key, keystore_json)` column values for `wallet`
+fn seal_wallet_row(
+    wallet: &SavedWallet,
+    key: Option<&VaultKey>,
+) -> Result<(String, String, String), String> {
+    let Some(key) = key else {
+        return Ok((
+            wallet.name.clone(),
+            wallet.public_key.clone(),
+            wallet.keystore_json.clone(),
+        ));
+    };
+    let fields = Zeroizing::new(
+        serde_json::to_vec(&SealedWalletFields {
+            name: wallet.name.clone(),
+            public_key: wallet.public_key.clone(),
+            keystore_json: wallet.keystore_json.clone(),
+        })
+        .map_err(|e| format!("序列化钱包记录失败: {}", e))?,
+    );
+    Ok((
+        String::new(),
+        key.blind_index(&wallet.public_key),
+        key.seal(&fields, wallet.id.as_bytes())?,
+    ))
+}
+
+fn stored_public_key(public_key: &str, key: Option<&VaultKey>) -> String {
+    key.map_or_else(|| public_key.to_string(), |key| key.blind_index(public_key))
+}
+
+fn query_wallet_rows(
+    conn: &Connection,
+    sql: &str,
+    params: impl rusqlite::Params,
+) -> Result<Vec<SavedWallet>, String> {
     let mut stmt = conn
-        .prepare(
-            "SELECT id, name, public_key, keystore_json, created_at, updated_at \
-             FROM wallets ORDER BY updated_at DESC, created_at DESC",
-        )
-        .map_err(|e| format!("读取钱包列表失败: {}", e))?;
+        .prepare(sql)
+        .map_err(|e| format!("读取钱包失败: {}", e))?;
     let rows = stmt
-        .query_map([], row_to_wallet)
-        .map_err(|e| format!("读取钱包列表失败: {}", e))?;
+        .query_map(params, row_to_wallet)
+        .map_err(|e| format!("读取钱包失败: {}", e))?;
     rows.collect::<Result<Vec<_>, _>>()
-        .map_err(|e| format!("读取钱包列表失败: {}", e))
+        .map_err(|e| format!("读取钱包失败: {}", e))
 }
 
-pub fn list_summaries() -> Result<Vec<WalletSummary>, String> {
-    Ok(load()?.into_iter().map(WalletSummary::from).collect())
+fn insert_wallet_row(
+    conn: &Connection,
+    wallet: &SavedWallet,
+    key: Option<&VaultKey>,
+) -> Result<(), String> {
+    let (name, public_key, keystore_json) = seal_wallet_row(wallet, key)?;
+    conn.execute(
+        "INSERT INTO wallets (id, name, public_key, keystore_json, created_at, updated_at) \
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            wallet.id,
+            name,
+            public_key,
+            keystore_json,
+            wallet.created_at,
+            wallet.updated_at,
+        ],
+    )
+    .map(|_| ())
+    .map_err(|e| format!("保存钱包失败: {}", e))
 }
 
-pub fn find(wallet_id: &str) -> Result<SavedWallet, String> {
-    let _guard = store_lock()
-        .lock()
-        .map_err(|_| "数据库读锁已损坏".to_string())?;
-    let conn = open_connection()?;
-    conn.query_row(
+fn update_wallet_row(
+    conn: &Connection,
+    wallet: &SavedWallet,
+    key: Option<&VaultKey>,
+) -> Result<(), String> {
+    let (name, public_key, keystore_json) = seal_wallet_row(wallet, key)?;
+    conn.execute(
+        "UPDATE wallets SET name = ?1, public_key = ?2, keystore_json = ?3, updated_at = ?4 \
+         WHERE id = ?5",
+        params![
+            name,
+            public_key,
+            keystore_json,
+            wallet.updated_at,
+            wallet.id
+        ],
+    )
+    .map(|_| ())
+    .map_err(|e| format!("保存钱包失败: {}", e))
+}
+
+fn load_with_connection(
+    conn: &Connection,
+    key: Option<&VaultKey>,
+) -> Result<Vec<SavedWallet>, String> {
+    let wallets = query_wallet_rows(
+        conn,
+        "SELECT id, name, public_key, keystore_json, created_at, updated_at \
+         FROM wallets ORDER BY updated_at DESC, created_at DESC",
+        [],
+    )
+    .map_err(|_| "读取钱包列表失败".to_string())?;
+    wallets
+        .into_iter()
+        .map(|wallet| open_wallet_row(wallet, key))
+        .collect()
+}
+
+fn find_with_connection(
+    conn: &Connection,
+    key: Option<&VaultKey>,
+    wallet_id: &str,
+) -> Result<SavedWallet, String> {
+    let wallet = query_wallet_rows(
+        conn,
         "SELECT id, name, public_key, keystore_json, created_at, updated_at FROM wallets WHERE id = ?1",
         params![wallet_id],
-        row_to_wallet,
-    )
-    .optional()
-    .map_err(|e| format!("读取钱包失败: {}", e))?
-    .ok_or_else(|| "未找到已保存钱包".to_string())
+    )?
+    .pop()
+    .ok_or_else(|| "未找到已保存钱包".to_string())?;
+    open_wallet_row(wallet, key)
 }
 
-pub fn upsert(
+fn upsert_with_connection(
+    conn: &Connection,
+    key: Option<&VaultKey>,
     keystore_json: String,
     public_key: String,
     name: Option<String>,
 ) -> Result<SavedWallet, String> {
-    let _guard = store_lock()
-        .lock()
-        .map_err(|_| "数据库写锁已损坏".to_string())?;
-    let conn = open_connection()?;
     let now = now_unix_secs()?;
     let name = normalize_wallet_name(name, &public_key);
-    let existing: Option<SavedWallet> = conn
-        .query_row(
-            "SELECT id, name, public_key, keystore_json, created_at, updated_at FROM wallets WHERE public_key = ?1",
-            params![public_key],
-            row_to_wallet,
-        )
-        .optional()
-        .map_err(|e| format!("读取钱包失败: {}", e))?;
+    let existing = query_wallet_rows(
+        conn,
+        "SELECT id, name, public_key, keystore_json, created_at, updated_at FROM wallets WHERE public_key = ?1",
+        params![stored_public_key(&public_key, key)],
+    )?
+    .pop();
 
-    let wallet = if let Some(mut wallet) = existing {
+    if let Some(existing) = existing {
+        let mut wallet = open_wallet_row(existing, key)?;
         wallet.name = name;
         wallet.keystore_json = keystore_json;
         wallet.updated_at = now;
-        conn.execute(
-            "UPDATE wallets SET name = ?1, keystore_json = ?2, updated_at = ?3 WHERE id = ?4",
-            params![
-                wallet.name,
-                wallet.keystore_json,
-                wallet.updated_at,
-                wallet.id
-            ],
-        )
-        .map_err(|e| format!("保存钱包失败: {}", e))?;
-        wallet
+        update_wallet_row(conn, &wallet, key)?;
+        Ok(wallet)
     } else {
         let wallet = SavedWallet {
             id: Uuid::new_v4().simple().to_string(),
@@ -1656,23 +2120,44 @@ pub fn upsert(
             created_at: now,
             updated_at: now,
         };
-        conn.execute(
-            "INSERT INTO wallets (id, name, public_key, keystore_json, created_at, updated_at) \
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-            params![
-                wallet.id,
-                wallet.name,
-                wallet.public_key,
-                wallet.keystore_json,
-                wallet.created_at,
-                wallet.updated_at,
-            ],
-        )
-        .map_err(|e| format!("保存钱包失败: {}", e))?;
-        wallet
-    };
+        insert_wallet_row(conn, &wallet, key)?;
+        Ok(wallet)
+    }
+}
 
-    Ok(wallet)
+pub fn load() -> Result<Vec<SavedWallet>, String> {
+    let _guard = store_lock()
+        .lock()
+        .map_err(|_| "数据库读锁已损坏".to_string())?;
+    let conn = open_connection()?;
+    let key = wallet_vault_key(&conn)?;
+    load_with_connection(&conn, key.as_deref())
+}
+
+pub fn list_summaries() -> Result<Vec<WalletSummary>, String> {
+    Ok(load()?.into_iter().map(WalletSummary::from).collect())
+}
+
+pub fn find(wallet_id: &str) -> Result<SavedWallet, String> {
+    let _guard = store_lock()
+        .lock()
+        .map_err(|_| "数据库读锁已损坏".to_string())?;
+    let conn = open_connection()?;
+    let key = wallet_vault_key(&conn)?;
+    find_with_connection(&conn, key.as_deref(), wallet_id)
+}
+
+pub fn upsert(
+    keystore_json: String,
+    public_key: String,
+    name: Option<String>,
+) -> Result<SavedWallet, String> {
+    let _guard = store_lock()
+        .lock()
+        .map_err(|_| "数据库写锁已损坏".to_string())?;
+    let conn = open_connection()?;
+    let key = wallet_vault_key(&conn)?;
+    upsert_with_connection(&conn, key.as_deref(), keystore_json, public_key, name)
 }
 
 pub fn update_metadata(
@@ -1689,26 +2174,145 @@ pub fn update_metadata(
     }
 
     let conn = open_connection()?;
-    let now = now_unix_secs()?;
-    let updated = conn
-        .execute(
-            "UPDATE wallets SET name = ?1, keystore_json = ?2, updated_at = ?3 WHERE id = ?4",
-            params![name, keystore_json, now, wallet_id],
-        )
-        .map_err(|e| format!("更新钱包失败: {}", e))?;
-    if updated == 0 {
-        return Err("未找到已保存钱包".to_string());
-    }
-    conn.query_row(
-        "SELECT id, name, public_key, keystore_json, created_at, updated_at FROM wallets WHERE id = ?1",
-        params![wallet_id],
-        row_to_wallet,
-    )
-    .map_err(|e| format!("读取钱包失败: {}", e))
+    let key = wallet_vault_key(&conn)?;
+    let mut wallet = find_with_connection(&conn, key.as_deref(), wallet_id)?;
+    wallet.name = name.to_string();
+    wallet.keystore_json = keystore_json;
+    wallet.updated_at = now_unix_secs()?;
+    update_wallet_row(&conn, &wallet, key.as_deref())?;
+    Ok(wallet)
 }
 
-pub fn delete(wallet_id: &str) -> Result<(), String> {
-    let _guard = store_lock()
+#[derive(Serialize)]
+pub struct VaultStatus {
+    pub enabled: bool,
+    pub unlocked: bool,
+    pub source: Option<VaultKeySource>,
+    pub created_at: Option<u64>,
+    pub rotated_at: Option<u64>,
+}
+
+fn vault_status_with_connection(conn: &Connection) -> Result<VaultStatus, String> {
+    let header = vault_header_with_connection(conn)?;
+    Ok(VaultStatus {
+        enabled: header.is_some(),
+        unlocked: header
+            .as_ref()
+            .is_none_or(|header| vault::unlocked_key(header).is_some()),
+        source: header.as_ref().map(|header| header.source),
+        created_at: header.as_ref().map(|header| header.created_at),
+        rotated_at: header.as_ref().and_then(|header| header.rotated_at),
+    })
+}
+
+/// Re-seal every wallet row from `from` (plaintext when `None`) to `to` and store `header`
+///
+/// Runs in one transaction, so a row that fails to open leaves the old vault untouched.
+fn rewrap_wallets_with_connection(
+    conn: &mut Connection,
+    from: Option<&VaultKey>,
+    header: &VaultHeader,
+    to: &VaultKey,
+) -> Result<usize, String> {
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("开始保险库重加密失败: {}", e))?;
+    let wallets = load_with_connection(&tx, from)?;
+    for wallet in &wallets {
+        update_wallet_row(&tx, wallet, Some(to))?;
+    }
+    let header_json =
+        serde_json::to_string(header).map_err(|e| format!("序列化保险库设置失败: {}", e))?;
+    save_setting_with_connection(&tx, VAULT_SETTINGS_KEY, &header_json)?;
+    tx.commit()
+        .map_err(|e| format!("提交保险库重加密失败: {}", e))?;
+    Ok(wallets.len())
+}
+
+pub fn vault_status() -> Result<VaultStatus, String> {
+    let _guard = store_lock()
+        .lock()
+        .map_err(|_| "数据库读锁已损坏".to_string())?;
+    let conn = open_connection()?;
+    vault_status_with_connection(&conn)
+}
+
+/// Turn the vault on, sealing all existing wallet rows, and leave it unlocked
+pub fn enable_vault(
+    source: VaultKeySource,
+    passphrase: Option<&str>,
+) -> Result<VaultStatus, String> {
+    let _guard = store_lock()
+        .lock()
+        .map_err(|_| "数据库写锁已损坏".to_string())?;
+    let mut conn = open_connection()?;
+    if vault_header_with_connection(&conn)?.is_some() {
+        return Err("钱包保险库已启用".to_string());
+    }
+    let (header, key) = vault::create(source, passphrase, now_unix_secs()?)?;
+    if let Err(error) = rewrap_wallets_with_connection(&mut conn, None, &header, &key) {
+        vault::discard_key(&header);
+        return Err(error);
+    }
+    vault::set_unlocked(&header, key)?;
+    checkpoint_with_connection(&conn)?;
+    vault_status_with_connection(&conn)
+}
+
+pub fn unlock_vault(passphrase: Option<&str>) -> Result<VaultStatus, String> {
+    let _guard = store_lock()
+        .lock()
+        .map_err(|_| "数据库读锁已损坏".to_string())?;
+    let conn = open_connection()?;
+    let header =
+        vault_header_with_connection(&conn)?.ok_or_else(|| "钱包保险库未启用".to_string())?;
+    let key = vault::derive_key(&header, passphrase)?;
+    vault::set_unlocked(&header, key)?;
+    vault_status_with_connection(&conn)
+}
+
+pub fn lock_vault() -> Result<VaultStatus, String> {
+    let _guard = store_lock()
+        .lock()
+        .map_err(|_| "数据库读锁已损坏".to_string())?;
+    vault::lock()?;
+    let conn = open_connection()?;
+    vault_status_with_connection(&conn)
+}
+
+/// Re-wrap all wallet rows under a fresh key, optionally switching the key source
+///
+/// The current passphrase is required even while unlocked. A keyring key that is
+/// rotated away is removed from the keyring only after the new rows are committed.
+pub fn rotate_vault(
+    current_passphrase: Option<&str>,
+    source: VaultKeySource,
+    new_passphrase: Option<&str>,
+) -> Result<VaultStatus, String> {
+    let _guard = store_lock()
+        .lock()
+        .map_err(|_| "数据库写锁已损坏".to_string())?;
+    let mut conn = open_connection()?;
+    let current =
+        vault_header_with_connection(&conn)?.ok_or_else(|| "钱包保险库未启用".to_string())?;
+    let current_key = vault::derive_key(&current, current_passphrase)?;
+    let now = now_unix_secs()?;
+    let (mut header, key) = vault::create(source, new_passphrase, now)?;
+    header.created_at = current.created_at;
+    header.rotated_at = Some(now);
+    if let Err(error) = rewrap_wallets_with_connection(&mut conn, Some(&current_key), &header, &key)
+    {
+        vault::discard_key(&header);
+        return Err(error);
+    }
+    vault::discard_key(&current);
+    vault::set_unlocked(&header, key)?;
+    checkpoint_with_connection(&conn)?;
+    vault_status_with_connection(&conn)
+}
+
+pub fn delete(wallet_id: &str) -> Result<(), String> {
+    let _guard = store_lock()
         .lock()
         .map_err(|_| "数据库写锁已损坏".to_string())?;
     let conn = open_connection()?;
@@ -1965,9 +2569,1713 @@ pub fn save_token_metadata(network: &str, records: &[TokenMetadataRecord]) -> Re
         .map_err(|e| format!("提交 Token 元数据缓存失败: {}", e))
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+fn get_setting_with_connection(conn: &Connection, key: &str) -> Result<Option<String>, String> {
+    conn.query_row(
+        "SELECT value FROM app_settings WHERE key = ?1",
+        params![key],
+        |row| row.get::<_, String>(0),
+    )
+    .optional()
+    .map_err(|e| format!("读取设置失败: {}", e))
+}
+
+fn save_setting_with_connection(conn: &Connection, key: &str, value: &str) -> Result<(), String> {
+    let now = now_unix_secs()?;
+    conn.execute(
+        "INSERT INTO app_settings (key, value, updated_at) VALUES (?1, ?2, ?3) \
+         ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at",
+        params![key, value, now],
+    )
+    .map(|_| ())
+    .map_err(|e| format!("保存设置失败: {}", e))
+}
+
+fn get_swqos_settings_with_connection(conn: &Connection) -> Result<Option<SwqosSettings>, String> {
+    get_setting_with_connection(conn, SWQOS_SETTINGS_KEY)?
+        .map(|value| {
+            serde_json::from_str(&value).map_err(|e| format!("解析 SWQoS 设置失败: {}", e))
+        })
+        .transpose()
+}
+
+fn save_swqos_settings_with_connection(
+    conn: &Connection,
+    settings: &SwqosSettings,
+) -> Result<(), String> {
+    settings.validate()?;
+    let value =
+        serde_json::to_string(settings).map_err(|e| format!("序列化 SWQoS 设置失败: {}", e))?;
+    save_setting_with_connection(conn, SWQOS_SETTINGS_KEY, &value)
+}
+
+/// 读取已保存的 SWQoS 设置；从未保存时返回 `None`
+pub fn get_swqos_settings() -> Result<Option<SwqosSettings>, String> {
+    let _guard = store_lock()
+        .lock()
+        .map_err(|_| "数据库读锁已损坏".to_string())?;
+    let conn = open_connection()?;
+    get_swqos_settings_with_connection(&conn)
+}
+
+pub fn save_swqos_settings(settings: &SwqosSettings) -> Result<(), String> {
+    let _guard = store_lock()
+        .lock()
+        .map_err(|_| "数据库写锁已损坏".to_string())?;
+    let conn = open_connection()?;
+    save_swqos_settings_with_connection(&conn, settings)
+}
+
+fn load_payout_progress_with_connection(
+    conn: &Connection,
+    network: &str,
+    batch_id: &str,
+) -> Result<Vec<PayoutProgress>, String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT line_index, status, signature, last_valid_block_height, error \
+             FROM payout_progress WHERE network = ?1 AND batch_id = ?2 ORDER BY line_index",
+        )
+        .map_err(|e| format!("读取批量转账进度失败: {}", e))?;
+    let rows = stmt
+        .query_map(params![network, batch_id], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, Option<String>>(2)?,
+                row.get::<_, Option<i64>>(3)?,
+                row.get::<_, Option<String>>(4)?,
+            ))
+        })
+        .map_err(|e| format!("读取批量转账进度失败: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("读取批量转账进度失败: {}", e))?;
+    rows.into_iter()
+        .map(
+            |(line, status, signature, last_valid_block_height, error)| {
+                Ok(PayoutProgress {
+                    line: line as usize,
+                    status: PayoutLineStatus::parse(&status)
+                        .ok_or_else(|| format!("未知的批量转账状态: {}", status))?,
+                    signature,
+                    last_valid_block_height: last_valid_block_height.map(|height| height as u64),
+                    error,
+                })
+            },
+        )
+        .collect()
+}
+
+fn record_payout_progress_with_connection(
+    conn: &mut Connection,
+    network: &str,
+    batch_id: &str,
+    progress: &[PayoutProgress],
+) -> Result<(), String> {
+    let now = now_unix_secs()?;
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("保存批量转账进度失败: {}", e))?;
+    for entry in progress {
+        tx.execute(
+            "INSERT INTO payout_progress \
+             (network, batch_id, line_index, status, signature, last_valid_block_height, error, updated_at) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8) \
+             ON CONFLICT(network, batch_id, line_index) DO UPDATE SET \
+             status = excluded.status, signature = excluded.signature, \
+             last_valid_block_height = excluded.last_valid_block_height, \
+             error = excluded.error, updated_at = excluded.updated_at",
+            params![
+                network,
+                batch_id,
+                entry.line as i64,
+                entry.status.as_str(),
+                entry.signature,
+                entry.last_valid_block_height.map(|height| height as i64),
+                entry.error,
+                now as i64,
+            ],
+        )
+        .map_err(|e| format!("保存批量转账进度失败: {}", e))?;
+    }
+    tx.commit()
+        .map_err(|e| format!("保存批量转账进度失败: {}", e))
+}
+
+/// 读取某次批量转账在指定网络上的逐行进度
+pub fn load_payout_progress(network: &str, batch_id: &str) -> Result<Vec<PayoutProgress>, String> {
+    let _guard = store_lock()
+        .lock()
+        .map_err(|_| "数据库读锁已损坏".to_string())?;
+    let conn = open_connection()?;
+    load_payout_progress_with_connection(&conn, network, batch_id)
+}
+
+/// 批量转账进度的 SQLite 存储，按网络隔离
+pub struct PayoutProgressDb {
+    network: String,
+}
+
+impl PayoutProgressDb {
+    pub fn new(network: &str) -> Self {
+        Self {
+            network: network.to_string(),
+        }
+    }
+}
+
+impl PayoutProgressStore for PayoutProgressDb {
+    fn load(&mut self, batch_id: &str) -> anyhow::Result<Vec<PayoutProgress>> {
+        load_payout_progress(&self.network, batch_id).map_err(anyhow::Error::msg)
+    }
+
+    fn record(&mut self, batch_id: &str, progress: &[PayoutProgress]) -> anyhow::Result<()> {
+        let _guard = store_lock()
+            .lock()
+            .map_err(|_| anyhow::anyhow!("数据库写锁已损坏"))?;
+        let mut conn = open_connection().map_err(anyhow::Error::msg)?;
+        record_payout_progress_with_connection(&mut conn, &self.network, batch_id, progress)
+            .map_err(anyhow::Error::msg)
+    }
+}
+
+const WALLET_TRANSACTION_SELECT: &str =
+    "SELECT t.signature, t.slot, t.block_time, t.confirmation_status, t.err, t.memo, t.action, \
+     t.summary, t.counterparty, t.programs, t.details_loaded FROM wallet_transactions t";
+
+fn row_to_wallet_transaction(row: &rusqlite::Row<'_>) -> rusqlite::Result<WalletTransactionRecord> {
+    let err: Option<String> = row.get(4)?;
+    let programs: String = row.get(9)?;
+    Ok(WalletTransactionRecord {
+        signature: row.get(0)?,
+        slot: row.get::<_, i64>(1)? as u64,
+        block_time: row.get(2)?,
+        confirmation_status: row.get(3)?,
+        err: err.and_then(|err| serde_json::from_str(&err).ok()),
+        memo: row.get(5)?,
+        action: row.get(6)?,
+        summary: row.get(7)?,
+        counterparty: row.get(8)?,
+        programs: serde_json::from_str(&programs).unwrap_or_default(),
+        changes: Vec::new(),
+        details_loaded: row.get(10)?,
+    })
+}
+
+fn load_wallet_transaction_changes(
+    conn: &Connection,
+    owner: &str,
+    network: &str,
+    records: &mut [WalletTransactionRecord],
+) -> Result<(), String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT asset, mint, amount, ui_amount, direction, decimals \
+             FROM wallet_transaction_changes \
+             WHERE owner = ?1 AND network = ?2 AND signature = ?3 ORDER BY position",
+        )
+        .map_err(|e| format!("读取交易记录缓存失败: {}", e))?;
+    for record in records.iter_mut() {
+        record.changes = stmt
+            .query_map(params![owner, network, record.signature], |row| {
+                Ok(WalletTransactionChange {
+                    asset: row.get(0)?,
+                    mint: row.get(1)?,
+                    amount: row.get(2)?,
+                    ui_amount: row.get(3)?,
+                    direction: row.get(4)?,
+                    decimals: row.get(5)?,
+                })
+            })
+            .map_err(|e| format!("读取交易记录缓存失败: {}", e))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("读取交易记录缓存失败: {}", e))?;
+    }
+    Ok(())
+}
+
+fn wallet_history_state_with_connection(
+    conn: &Connection,
+    owner: &str,
+    network: &str,
+) -> Result<WalletHistoryState, String> {
+    let transaction_count = conn
+        .query_row(
+            "SELECT COUNT(*) FROM wallet_transactions WHERE owner = ?1 AND network = ?2",
+            params![owner, network],
+            |row| row.get::<_, i64>(0),
+        )
+        .map_err(|e| format!("读取交易索引状态失败: {}", e))? as u64;
+    let state = conn
+        .query_row(
+            "SELECT newest_signature, oldest_signature, backfill_complete, updated_at \
+             FROM wallet_history_state WHERE owner = ?1 AND network = ?2",
+            params![owner, network],
+            |row| {
+                Ok(WalletHistoryState {
+                    newest_signature: row.get(0)?,
+                    oldest_signature: row.get(1)?,
+                    backfill_complete: row.get(2)?,
+                    transaction_count,
+                    updated_at: Some(row.get::<_, i64>(3)? as u64),
+                })
+            },
+        )
+        .optional()
+        .map_err(|e| format!("读取交易索引状态失败: {}", e))?;
+    Ok(state.unwrap_or(WalletHistoryState {
+        transaction_count,
+        ..WalletHistoryState::default()
+    }))
+}
+
+fn save_wallet_transactions_with_connection(
+    conn: &mut Connection,
+    owner: &str,
+    network: &str,
+    records: &[WalletTransactionRecord],
+    state: &WalletHistoryState,
+) -> Result<(), String> {
+    let now = now_unix_secs()?;
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("保存交易记录缓存失败: {}", e))?;
+    for record in records {
+        let err = record.err.as_ref().map(Value::to_string);
+        let programs = serde_json::to_string(&record.programs)
+            .map_err(|e| format!("保存交易记录缓存失败: {}", e))?;
+        tx.execute(
+            "INSERT INTO wallet_transactions \
+             (owner, network, signature, slot, block_time, confirmation_status, err, memo, action, \
+              summary, counterparty, programs, details_loaded, indexed_at) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14) \
+             ON CONFLICT(owner, network, signature) DO UPDATE SET \
+             slot = excluded.slot, block_time = excluded.block_time, \
+             confirmation_status = excluded.confirmation_status, err = excluded.err, \
+             memo = excluded.memo, action = excluded.action, summary = excluded.summary, \
+             counterparty = excluded.counterparty, programs = excluded.programs, \
+             details_loaded = excluded.details_loaded, indexed_at = excluded.indexed_at",
+            params![
+                owner,
+                network,
+                record.signature,
+                record.slot as i64,
+                record.block_time,
+                record.confirmation_status,
+                err,
+                record.memo,
+                record.action,
+                record.summary,
+                record.counterparty,
+                programs,
+                record.details_loaded,
+                now as i64,
+            ],
+        )
+        .map_err(|e| format!("保存交易记录缓存失败: {}", e))?;
+        tx.execute(
+            "DELETE FROM wallet_transaction_changes \
+             WHERE owner = ?1 AND network = ?2 AND signature = ?3",
+            params![owner, network, record.signature],
+        )
+        .map_err(|e| format!("保存交易记录缓存失败: {}", e))?;
+        for (position, change) in record.changes.iter().enumerate() {
+            tx.execute(
+                "INSERT INTO wallet_transaction_changes \
+                 (owner, network, signature, position, asset, mint, amount, ui_amount, direction, decimals) \
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                params![
+                    owner,
+                    network,
+                    record.signature,
+                    position as i64,
+                    change.asset,
+                    change.mint,
+                    change.amount,
+                    change.ui_amount,
+                    change.direction,
+                    change.decimals,
+                ],
+            )
+            .map_err(|e| format!("保存交易记录缓存失败: {}", e))?;
+        }
+    }
+    tx.execute(
+        "INSERT INTO wallet_history_state \
+         (owner, network, newest_signature, oldest_signature, backfill_complete, updated_at) \
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6) \
+         ON CONFLICT(owner, network) DO UPDATE SET \
+         newest_signature = excluded.newest_signature, \
+         oldest_signature = excluded.oldest_signature, \
+         backfill_complete = excluded.backfill_complete, updated_at = excluded.updated_at",
+        params![
+            owner,
+            network,
+            state.newest_signature,
+            state.oldest_signature,
+            state.backfill_complete,
+            now as i64,
+        ],
+    )
+    .map_err(|e| format!("保存交易索引状态失败: {}", e))?;
+    tx.commit()
+        .map_err(|e| format!("保存交易记录缓存失败: {}", e))
+}
+
+fn like_pattern(value: &str) -> String {
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{escaped}%")
+}
+
+fn query_wallet_transactions_with_connection(
+    conn: &Connection,
+    owner: &str,
+    network: &str,
+    filter: &WalletTransactionFilter,
+    before: Option<&str>,
+    limit: usize,
+) -> Result<Vec<WalletTransactionRecord>, String> {
+    let cursor = match before {
+        Some(before) => Some(
+            conn.query_row(
+                "SELECT slot FROM wallet_transactions \
+                 WHERE owner = ?1 AND network = ?2 AND signature = ?3",
+                params![owner, network, before],
+                |row| row.get::<_, i64>(0),
+            )
+            .optional()
+            .map_err(|e| format!("读取交易记录缓存失败: {}", e))?
+            .ok_or_else(|| "分页交易签名不在本地交易索引中".to_string())?,
+        ),
+        None => None,
+    };
+    let search = filter.search.as_deref().map(like_pattern);
+    let mut stmt = conn
+        .prepare(&format!(
+            "{WALLET_TRANSACTION_SELECT} \
+             WHERE t.owner = ?1 AND t.network = ?2 \
+               AND (?3 IS NULL OR t.slot < ?3 OR (t.slot = ?3 AND t.signature < ?4)) \
+               AND (?5 IS NULL OR EXISTS (SELECT 1 FROM wallet_transaction_changes c \
+                    WHERE c.owner = t.owner AND c.network = t.network AND c.signature = t.signature \
+                      AND (c.mint = ?5 OR (?5 = 'SOL' AND c.mint IS NULL)))) \
+               AND (?6 IS NULL OR t.counterparty = ?6) \
+               AND (?7 IS NULL OR t.action = ?7) \
+               AND (?8 IS NULL OR t.signature LIKE ?8 ESCAPE '\\' \
+                    OR t.counterparty LIKE ?8 ESCAPE '\\' OR t.memo LIKE ?8 ESCAPE '\\' \
+                    OR t.summary LIKE ?8 ESCAPE '\\' OR t.programs LIKE ?8 ESCAPE '\\' \
+                    OR EXISTS (SELECT 1 FROM wallet_transaction_changes c \
+                        WHERE c.owner = t.owner AND c.network = t.network \
+                          AND c.signature = t.signature \
+                          AND (c.asset LIKE ?8 ESCAPE '\\' OR c.mint LIKE ?8 ESCAPE '\\'))) \
+             ORDER BY t.slot DESC, t.signature DESC LIMIT ?9"
+        ))
+        .map_err(|e| format!("读取交易记录缓存失败: {}", e))?;
+    let mut records = stmt
+        .query_map(
+            params![
+                owner,
+                network,
+                cursor,
+                before,
+                filter.mint,
+                filter.counterparty,
+                filter.action,
+                search,
+                limit as i64,
+            ],
+            row_to_wallet_transaction,
+        )
+        .map_err(|e| format!("读取交易记录缓存失败: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("读取交易记录缓存失败: {}", e))?;
+    load_wallet_transaction_changes(conn, owner, network, &mut records)?;
+    Ok(records)
+}
+
+fn undetailed_wallet_transactions_with_connection(
+    conn: &Connection,
+    owner: &str,
+    network: &str,
+    limit: usize,
+) -> Result<Vec<WalletTransactionRecord>, String> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "{WALLET_TRANSACTION_SELECT} \
+             WHERE t.owner = ?1 AND t.network = ?2 AND t.details_loaded = 0 \
+             ORDER BY t.slot DESC, t.signature DESC LIMIT ?3"
+        ))
+        .map_err(|e| format!("读取交易记录缓存失败: {}", e))?;
+    let records = stmt
+        .query_map(
+            params![owner, network, limit as i64],
+            row_to_wallet_transaction,
+        )
+        .map_err(|e| format!("读取交易记录缓存失败: {}", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("读取交易记录缓存失败: {}", e))?;
+    Ok(records)
+}
+
+pub fn wallet_history_state(owner: &str, network: &str) -> Result<WalletHistoryState, String> {
+    let _guard = store_lock()
+        .lock()
+        .map_err(|_| "数据库读锁已损坏".to_string())?;
+    let conn = open_connection()?;
+    wallet_history_state_with_connection(&conn, owner, network)
+}
+
+/// 写入一批已解析的交易并更新索引进度
+pub fn save_wallet_transactions(
+    owner: &str,
+    network: &str,
+    records: &[WalletTransactionRecord],
+    state: &WalletHistoryState,
+) -> Result<(), String> {
+    let _guard = store_lock()
+        .lock()
+        .map_err(|_| "数据库写锁已损坏".to_string())?;
+    let mut conn = open_connection()?;
+    save_wallet_transactions_with_connection(&mut conn, owner, network, records, state)
+}
+
+/// 按时间倒序分页读取本地交易索引，`before` 为上一页最后一条签名
+pub fn query_wallet_transactions(
+    owner: &str,
+    network: &str,
+    filter: &WalletTransactionFilter,
+    before: Option<&str>,
+    limit: usize,
+) -> Result<Vec<WalletTransactionRecord>, String> {
+    let _guard = store_lock()
+        .lock()
+        .map_err(|_| "数据库读锁已损坏".to_string())?;
+    let conn = open_connection()?;
+    query_wallet_transactions_with_connection(&conn, owner, network, filter, before, limit)
+}
+
+pub fn undetailed_wallet_transactions(
+    owner: &str,
+    network: &str,
+    limit: usize,
+) -> Result<Vec<WalletTransactionRecord>, String> {
+    let _guard = store_lock()
+        .lock()
+        .map_err(|_| "数据库读锁已损坏".to_string())?;
+    let conn = open_connection()?;
+    undetailed_wallet_transactions_with_connection(&conn, owner, network, limit)
+}
+
+const ADDRESS_BOOK_SELECT: &str =
+    "SELECT b.id, b.network, b.address, b.label, b.notes, b.expected_mint, \
+     COALESCE(r.send_count, 0), r.last_sent_at, b.created_at, b.updated_at \
+     FROM address_book b \
+     LEFT JOIN sent_recipients r ON r.network = b.network AND r.address = b.address";
+
+fn row_to_address_book(row: &rusqlite::Row<'_>) -> rusqlite::Result<AddressBookRecord> {
+    Ok(AddressBookRecord {
+        id: row.get(0)?,
+        network: row.get(1)?,
+        address: row.get(2)?,
+        label: row.get(3)?,
+        notes: row.get(4)?,
+        expected_mint: row.get(5)?,
+        send_count: row.get::<_, i64>(6)? as u64,
+        last_sent_at: row.get::<_, Option<i64>>(7)?.map(|at| at as u64),
+        created_at: row.get(8)?,
+        updated_at: row.get(9)?,
+    })
+}
+
+fn list_address_book_with_connection(
+    conn: &Connection,
+    network: Option<&str>,
+) -> Result<Vec<AddressBookRecord>, String> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "{ADDRESS_BOOK_SELECT} WHERE ?1 IS NULL OR b.network = ?1 \
+             ORDER BY b.network, b.label COLLATE NOCASE, b.address"
+        ))
+        .map_err(|e| format!("读取地址簿失败: {}", e))?;
+    let rows = stmt
+        .query_map(params![network], row_to_address_book)
+        .map_err(|e| format!("读取地址簿失败: {}", e))?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("读取地址簿失败: {}", e))
+}
+
+fn find_address_book_entry_with_connection(
+    conn: &Connection,
+    id: &str,
+) -> Result<AddressBookRecord, String> {
+    conn.query_row(
+        &format!("{ADDRESS_BOOK_SELECT} WHERE b.id = ?1"),
+        params![id],
+        row_to_address_book,
+    )
+    .optional()
+    .map_err(|e| format!("读取地址簿失败: {}", e))?
+    .ok_or_else(|| "未找到地址簿条目".to_string())
+}
+
+fn normalize_optional_text(value: Option<&str>) -> Option<String> {
+    value
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(ToOwned::to_owned)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn save_address_book_entry_with_connection(
+    conn: &Connection,
+    id: Option<&str>,
+    network: &str,
+    address: &str,
+    label: &str,
+    notes: Option<&str>,
+    expected_mint: Option<&str>,
+) -> Result<AddressBookRecord, String> {
+    let label = label.trim();
+    if label.is_empty() {
+        return Err("地址标签不能为空".to_string());
+    }
+    let address = address.trim();
+    let notes = normalize_optional_text(notes);
+    let expected_mint = normalize_optional_text(expected_mint);
+    let now = now_unix_secs()?;
+    let map_save_error = |error: rusqlite::Error| match error {
+        rusqlite::Error::SqliteFailure(failure, _)
+            if failure.code == rusqlite::ErrorCode::ConstraintViolation =>
+        {
+            "该网络的地址簿中已有此地址".to_string()
+        }
+        error => format!("保存地址簿失败: {}", error),
+    };
+
+    let id = match id {
+        Some(id) => {
+            let updated = conn
+                .execute(
+                    "UPDATE address_book SET network = ?1, address = ?2, label = ?3, notes = ?4, \
+                     expected_mint = ?5, updated_at = ?6 WHERE id = ?7",
+                    params![network, address, label, notes, expected_mint, now, id],
+                )
+                .map_err(map_save_error)?;
+            if updated == 0 {
+                return Err("未找到地址簿条目".to_string());
+            }
+            id.to_string()
+        }
+        None => {
+            let id = Uuid::new_v4().simple().to_string();
+            conn.execute(
+                "INSERT INTO address_book \
+                 (id, network, address, label, notes, expected_mint, created_at, updated_at) \
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?7)",
+                params![id, network, address, label, notes, expected_mint, now],
+            )
+            .map_err(map_save_error)?;
+            id
+        }
+    };
+    find_address_book_entry_with_connection(conn, &id)
+}
+
+fn delete_address_book_entry_with_connection(conn: &Connection, id: &str) -> Result<(), String> {
+    let deleted = conn
+        .execute("DELETE FROM address_book WHERE id = ?1", params![id])
+        .map_err(|e| format!("删除地址簿条目失败: {}", e))?;
+    if deleted == 0 {
+        return Err("未找到地址簿条目".to_string());
+    }
+    Ok(())
+}
+
+fn recipient_history_with_connection(
+    conn: &Connection,
+    network: &str,
+    address: &str,
+) -> Result<RecipientHistory, String> {
+    conn.query_row(
+        "SELECT send_count, first_sent_at, last_sent_at FROM sent_recipients \
+         WHERE network = ?1 AND address = ?2",
+        params![network, address],
+        |row| {
+            Ok(RecipientHistory {
+                send_count: row.get::<_, i64>(0)? as u64,
+                first_sent_at: Some(row.get::<_, i64>(1)? as u64),
+                last_sent_at: Some(row.get::<_, i64>(2)? as u64),
+            })
+        },
+    )
+    .optional()
+    .map(Option::unwrap_or_default)
+    .map_err(|e| format!("读取发送记录失败: {}", e))
+}
+
+fn record_sent_recipient_with_connection(
+    conn: &Connection,
+    network: &str,
+    address: &str,
+) -> Result<(), String> {
+    let now = now_unix_secs()?;
+    conn.execute(
+        "INSERT INTO sent_recipients (network, address, send_count, first_sent_at, last_sent_at) \
+         VALUES (?1, ?2, 1, ?3, ?3) \
+         ON CONFLICT(network, address) DO UPDATE SET \
+         send_count = send_count + 1, last_sent_at = excluded.last_sent_at",
+        params![network, address, now as i64],
+    )
+    .map_err(|e| format!("保存发送记录失败: {}", e))?;
+    Ok(())
+}
+
+/// 列出地址簿，`network` 为空时返回所有网络
+pub fn list_address_book(network: Option<&str>) -> Result<Vec<AddressBookRecord>, String> {
+    let _guard = store_lock()
+        .lock()
+        .map_err(|_| "数据库读锁已损坏".to_string())?;
+    let conn = open_connection()?;
+    list_address_book_with_connection(&conn, network)
+}
+
+/// 新增（`id` 为空）或更新地址簿条目
+pub fn save_address_book_entry(
+    id: Option<&str>,
+    network: &str,
+    address: &str,
+    label: &str,
+    notes: Option<&str>,
+    expected_mint: Option<&str>,
+) -> Result<AddressBookRecord, String> {
+    let _guard = store_lock()
+        .lock()
+        .map_err(|_| "数据库写锁已损坏".to_string())?;
+    let conn = open_connection()?;
+    save_address_book_entry_with_connection(
+        &conn,
+        id,
+        network,
+        address,
+        label,
+        notes,
+        expected_mint,
+    )
+}
+
+pub fn delete_address_book_entry(id: &str) -> Result<(), String> {
+    let _guard = store_lock()
+        .lock()
+        .map_err(|_| "数据库写锁已损坏".to_string())?;
+    let conn = open_connection()?;
+    delete_address_book_entry_with_connection(&conn, id)
+}
+
+pub fn recipient_history(network: &str, address: &str) -> Result<RecipientHistory, String> {
+    let _guard = store_lock()
+        .lock()
+        .map_err(|_| "数据库读锁已损坏".to_string())?;
+    let conn = open_connection()?;
+    recipient_history_with_connection(&conn, network, address)
+}
+
+/// 记录一次成功发送，用于首次转账提醒
+pub fn record_sent_recipient(network: &str, address: &str) -> Result<(), String> {
+    let _guard = store_lock()
+        .lock()
+        .map_err(|_| "数据库写锁已损坏".to_string())?;
+    let conn = open_connection()?;
+    record_sent_recipient_with_connection(&conn, network, address)
+}
+
+const REMOTE_SIGN_PAIRING_SELECT: &str =
+    "SELECT id, name, relay_url, topic, public_key, secret_key, client_public_key, \
+     created_at, last_seen_at FROM remote_sign_pairings";
+
+const REMOTE_SIGN_REQUEST_SELECT: &str =
+    "SELECT id, pairing_id, request_id, request_json, status, response_json, delivered, \
+     expires_at, created_at, updated_at FROM remote_sign_requests";
+
+fn row_to_remote_sign_pairing(
+    row: &rusqlite::Row<'_>,
+) -> rusqlite::Result<RemoteSignPairingRecord> {
+    Ok(RemoteSignPairingRecord {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        relay_url: row.get(2)?,
+        topic: row.get(3)?,
+        public_key: row.get(4)?,
+        secret_key: row.get(5)?,
+        client_public_key: row.get(6)?,
+        created_at: row.get(7)?,
+        last_seen_at: row.get(8)?,
+    })
+}
+
+fn row_to_remote_sign_request(
+    row: &rusqlite::Row<'_>,
+) -> rusqlite::Result<RemoteSignRequestRecord> {
+    Ok(RemoteSignRequestRecord {
+        id: row.get(0)?,
+        pairing_id: row.get(1)?,
+        request_id: row.get(2)?,
+        request_json: row.get(3)?,
+        status: row.get(4)?,
+        response_json: row.get(5)?,
+        delivered: row.get::<_, i64>(6)? != 0,
+        expires_at: row.get(7)?,
+        created_at: row.get(8)?,
+        updated_at: row.get(9)?,
+    })
+}
+
+fn insert_remote_sign_pairing_with_connection(
+    conn: &Connection,
+    record: &RemoteSignPairingRecord,
+) -> Result<(), String> {
+    conn.execute(
+        "INSERT INTO remote_sign_pairings \
+         (id, name, relay_url, topic, public_key, secret_key, client_public_key, created_at, \
+          last_seen_at) \
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![
+            record.id,
+            record.name,
+            record.relay_url,
+            record.topic,
+            record.public_key,
+            record.secret_key,
+            record.client_public_key,
+            record.created_at,
+            record.last_seen_at,
+        ],
+    )
+    .map(|_| ())
+    .map_err(|e| format!("保存远程签名配对失败: {}", e))
+}
+
+fn list_remote_sign_pairings_with_connection(
+    conn: &Connection,
+) -> Result<Vec<RemoteSignPairingRecord>, String> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "{REMOTE_SIGN_PAIRING_SELECT} ORDER BY created_at DESC"
+        ))
+        .map_err(|e| format!("读取远程签名配对失败: {}", e))?;
+    let rows = stmt
+        .query_map([], row_to_remote_sign_pairing)
+        .map_err(|e| format!("读取远程签名配对失败: {}", e))?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("读取远程签名配对失败: {}", e))
+}
+
+fn find_remote_sign_pairing_with_connection(
+    conn: &Connection,
+    id: &str,
+) -> Result<RemoteSignPairingRecord, String> {
+    conn.query_row(
+        &format!("{REMOTE_SIGN_PAIRING_SELECT} WHERE id = ?1"),
+        params![id],
+        row_to_remote_sign_pairing,
+    )
+    .optional()
+    .map_err(|e| format!("读取远程签名配对失败: {}", e))?
+    .ok_or_else(|| "未找到远程签名配对".to_string())
+}
+
+fn delete_remote_sign_pairing_with_connection(
+    conn: &mut Connection,
+    id: &str,
+) -> Result<(), String> {
+    let tx = conn
+        .transaction()
+        .map_err(|e| format!("删除远程签名配对失败: {}", e))?;
+    let deleted = tx
+        .execute(
+            "DELETE FROM remote_sign_pairings WHERE id = ?1",
+            params![id],
+        )
+        .map_err(|e| format!("删除远程签名配对失败: {}", e))?;
+    if deleted == 0 {
+        return Err("未找到远程签名配对".to_string());
+    }
+    tx.execute(
+        "DELETE FROM remote_sign_requests WHERE pairing_id = ?1",
+        params![id],
+    )
+    .map_err(|e| format!("删除远程签名配对失败: {}", e))?;
+    tx.commit()
+        .map_err(|e| format!("删除远程签名配对失败: {}", e))
+}
+
+/// 保存一条解密后的请求；同一配对内重复的 `request_id` 会被忽略
+///
+/// 配对尚未绑定客户端时，`client_public_key` 会在同一事务内被绑定。
+#[allow(clippy::too_many_arguments)]
+fn store_remote_sign_request_with_connection(
+    conn: &mut Connection,
+    pairing_id: &str,
+    client_public_key: &str,
+    request_id: &str,
+    request_json: &str,
+    expires_at: Option<u64>,
+    max_pending: usize,
+    now: u64,
+) -> Result<RemoteSignStoreOutcome, String> {
+    let map_error = |e: rusqlite::Error| format!("保存远程签名请求失败: {}", e);
+    let tx = conn.transaction().map_err(map_error)?;
+    let pinned: Option<String> = tx
+        .query_row(
+            "UPDATE remote_sign_pairings \
+             SET client_public_key = COALESCE(client_public_key, ?2), last_seen_at = ?3 \
+             WHERE id = ?1 RETURNING client_public_key",
+            params![pairing_id, client_public_key, now],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(map_error)?
+        .ok_or_else(|| "未找到远程签名配对".to_string())?;
+    if pinned.as_deref() != Some(client_public_key) {
+        return Ok(RemoteSignStoreOutcome::ClientMismatch);
+    }
+    let pending: i64 = tx
+        .query_row(
+            "SELECT COUNT(*) FROM remote_sign_requests WHERE pairing_id = ?1 AND status = ?2",
+            params![pairing_id, REMOTE_SIGN_STATUS_PENDING],
+            |row| row.get(0),
+        )
+        .map_err(map_error)?;
+    if pending as usize >= max_pending {
+        return Ok(RemoteSignStoreOutcome::PendingLimit);
+    }
+    let inserted = tx
+        .execute(
+            "INSERT OR IGNORE INTO remote_sign_requests \
+             (id, pairing_id, request_id, request_json, status, response_json, delivered, \
+              expires_at, created_at, updated_at) \
+             VALUES (?1, ?2, ?3, ?4, ?5, NULL, 0, ?6, ?7, ?7)",
+            params![
+                Uuid::new_v4().simple().to_string(),
+                pairing_id,
+                request_id,
+                request_json,
+                REMOTE_SIGN_STATUS_PENDING,
+                expires_at,
+                now,
+            ],
+        )
+        .map_err(map_error)?;
+    tx.commit().map_err(map_error)?;
+    Ok(if inserted > 0 {
+        RemoteSignStoreOutcome::Stored
+    } else {
+        RemoteSignStoreOutcome::Duplicate
+    })
+}
+
+fn list_remote_sign_requests_with_connection(
+    conn: &Connection,
+    status: Option<&str>,
+) -> Result<Vec<RemoteSignRequestRecord>, String> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "{REMOTE_SIGN_REQUEST_SELECT} WHERE ?1 IS NULL OR status = ?1 \
+             ORDER BY created_at DESC LIMIT 200"
+        ))
+        .map_err(|e| format!("读取远程签名请求失败: {}", e))?;
+    let rows = stmt
+        .query_map(params![status], row_to_remote_sign_request)
+        .map_err(|e| format!("读取远程签名请求失败: {}", e))?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("读取远程签名请求失败: {}", e))
+}
+
+fn find_remote_sign_request_with_connection(
+    conn: &Connection,
+    id: &str,
+) -> Result<RemoteSignRequestRecord, String> {
+    conn.query_row(
+        &format!("{REMOTE_SIGN_REQUEST_SELECT} WHERE id = ?1"),
+        params![id],
+        row_to_remote_sign_request,
+    )
+    .optional()
+    .map_err(|e| format!("读取远程签名请求失败: {}", e))?
+    .ok_or_else(|| "未找到远程签名请求".to_string())
+}
+
+/// 把请求从 `from` 状态改为 `to`；状态已变化时报错，防止同一请求被处理两次
+fn transition_remote_sign_request_with_connection(
+    conn: &Connection,
+    id: &str,
+    from: &str,
+    to: &str,
+    response_json: Option<&str>,
+) -> Result<RemoteSignRequestRecord, String> {
+    let updated = conn
+        .execute(
+            "UPDATE remote_sign_requests \
+             SET status = ?3, response_json = COALESCE(?4, response_json), delivered = 0, \
+             updated_at = ?5 WHERE id = ?1 AND status = ?2",
+            params![id, from, to, response_json, now_unix_secs()?],
+        )
+        .map_err(|e| format!("更新远程签名请求失败: {}", e))?;
+    let record = find_remote_sign_request_with_connection(conn, id)?;
+    if updated == 0 {
+        return Err(format!("远程签名请求已处理，当前状态: {}", record.status));
+    }
+    Ok(record)
+}
+
+fn undelivered_remote_sign_responses_with_connection(
+    conn: &Connection,
+) -> Result<Vec<RemoteSignRequestRecord>, String> {
+    let mut stmt = conn
+        .prepare(&format!(
+            "{REMOTE_SIGN_REQUEST_SELECT} WHERE response_json IS NOT NULL AND delivered = 0 \
+             ORDER BY updated_at"
+        ))
+        .map_err(|e| format!("读取远程签名回执失败: {}", e))?;
+    let rows = stmt
+        .query_map([], row_to_remote_sign_request)
+        .map_err(|e| format!("读取远程签名回执失败: {}", e))?;
+    rows.collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("读取远程签名回执失败: {}", e))
+}
+
+pub fn insert_remote_sign_pairing(record: &RemoteSignPairingRecord) -> Result<(), String> {
+    let _guard = store_lock()
+        .lock()
+        .map_err(|_| "数据库写锁已损坏".to_string())?;
+    let conn = open_connection()?;
+    insert_remote_sign_pairing_with_connection(&conn, record)
+}
+
+pub fn list_remote_sign_pairings() -> Result<Vec<RemoteSignPairingRecord>, String> {
+    let _guard = store_lock()
+        .lock()
+        .map_err(|_| "数据库读锁已损坏".to_string())?;
+    let conn = open_connection()?;
+    list_remote_sign_pairings_with_connection(&conn)
+}
+
+pub fn find_remote_sign_pairing(id: &str) -> Result<RemoteSignPairingRecord, String> {
+    let _guard = store_lock()
+        .lock()
+        .map_err(|_| "数据库读锁已损坏".to_string())?;
+    let conn = open_connection()?;
+    find_remote_sign_pairing_with_connection(&conn, id)
+}
+
+/// 删除配对及其全部请求记录
+pub fn delete_remote_sign_pairing(id: &str) -> Result<(), String> {
+    let _guard = store_lock()
+        .lock()
+        .map_err(|_| "数据库写锁已损坏".to_string())?;
+    let mut conn = open_connection()?;
+    delete_remote_sign_pairing_with_connection(&mut conn, id)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn store_remote_sign_request(
+    pairing_id: &str,
+    client_public_key: &str,
+    request_id: &str,
+    request_json: &str,
+    expires_at: Option<u64>,
+    max_pending: usize,
+    now: u64,
+) -> Result<RemoteSignStoreOutcome, String> {
+    let _guard = store_lock()
+        .lock()
+        .map_err(|_| "数据库写锁已损坏".to_string())?;
+    let mut conn = open_connection()?;
+    store_remote_sign_request_with_connection(
+        &mut conn,
+        pairing_id,
+        client_public_key,
+        request_id,
+        request_json,
+        expires_at,
+        max_pending,
+        now,
+    )
+}
+
+/// 列出远程签名请求，`status` 为空时返回所有状态
+pub fn list_remote_sign_requests(
+    status: Option<&str>,
+) -> Result<Vec<RemoteSignRequestRecord>, String> {
+    let _guard = store_lock()
+        .lock()
+        .map_err(|_| "数据库读锁已损坏".to_string())?;
+    let conn = open_connection()?;
+    list_remote_sign_requests_with_connection(&conn, status)
+}
+
+pub fn find_remote_sign_request(id: &str) -> Result<RemoteSignRequestRecord, String> {
+    let _guard = store_lock()
+        .lock()
+        .map_err(|_| "数据库读锁已损坏".to_string())?;
+    let conn = open_connection()?;
+    find_remote_sign_request_with_connection(&conn, id)
+}
+
+pub fn transition_remote_sign_request(
+    id: &str,
+    from: &str,
+    to: &str,
+    response_json: Option<&str>,
+) -> Result<RemoteSignRequestRecord, String> {
+    let _guard = store_lock()
+        .lock()
+        .map_err(|_| "数据库写锁已损坏".to_string())?;
+    let conn = open_connection()?;
+    transition_remote_sign_request_with_connection(&conn, id, from, to, response_json)
+}
+
+/// 签名过程中进程退出会留下 `signing` 状态，启动时退回待审批
+pub fn reset_interrupted_remote_sign_requests() -> Result<usize, String> {
+    let _guard = store_lock()
+        .lock()
+        .map_err(|_| "数据库写锁已损坏".to_string())?;
+    let conn = open_connection()?;
+    conn.execute(
+        "UPDATE remote_sign_requests SET status = ?1 WHERE status = ?2",
+        params![REMOTE_SIGN_STATUS_PENDING, REMOTE_SIGN_STATUS_SIGNING],
+    )
+    .map_err(|e| format!("更新远程签名请求失败: {}", e))
+}
+
+pub fn undelivered_remote_sign_responses() -> Result<Vec<RemoteSignRequestRecord>, String> {
+    let _guard = store_lock()
+        .lock()
+        .map_err(|_| "数据库读锁已损坏".to_string())?;
+    let conn = open_connection()?;
+    undelivered_remote_sign_responses_with_connection(&conn)
+}
+
+pub fn mark_remote_sign_response_delivered(id: &str) -> Result<(), String> {
+    let _guard = store_lock()
+        .lock()
+        .map_err(|_| "数据库写锁已损坏".to_string())?;
+    let conn = open_connection()?;
+    conn.execute(
+        "UPDATE remote_sign_requests SET delivered = 1 WHERE id = ?1",
+        params![id],
+    )
+    .map(|_| ())
+    .map_err(|e| format!("更新远程签名请求失败: {}", e))
+}
+
+fn load_totp_guard_state_with_connection(
+    conn: &Connection,
+    wallet_key: &str,
+) -> Result<TotpGuardState, String> {
+    conn.query_row(
+        "SELECT last_accepted_step, failed_attempts, locked_until, generation, \
+         spent_backup_codes FROM totp_guard_state WHERE wallet_key = ?1",
+        params![wallet_key],
+        |row| {
+            Ok((
+                TotpGuardState {
+                    last_accepted_step: row.get::<_, Option<i64>>(0)?.map(|step| step as u64),
+                    failed_attempts: row.get::<_, i64>(1)? as u32,
+                    locked_until: row.get::<_, i64>(2)? as u64,
+                    // Stored as i64; `u64::MAX` round-trips through the cast
+                    generation: row.get::<_, i64>(3)? as u64,
+                    spent_backup_codes: Vec::new(),
+                },
+                row.get::<_, String>(4)?,
+            ))
+        },
+    )
+    .optional()
+    .map_err(|e| format!("读取 2FA 状态失败: {}", e))?
+    .map(|(mut state, spent)| {
+        state.spent_backup_codes = serde_json::from_str(&spent)
+            .map_err(|e| format!("2FA 状态中的已用恢复码无效: {}", e))?;
+        Ok(state)
+    })
+    .unwrap_or_else(|| Ok(TotpGuardState::default()))
+}
+
+fn save_totp_guard_state_with_connection(
+    conn: &Connection,
+    wallet_key: &str,
+    state: &TotpGuardState,
+) -> Result<(), String> {
+    conn.execute(
+        "INSERT INTO totp_guard_state \
+         (wallet_key, last_accepted_step, failed_attempts, locked_until, generation, \
+         spent_backup_codes, updated_at) \
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7) \
+         ON CONFLICT(wallet_key) DO UPDATE SET \
+         last_accepted_step = excluded.last_accepted_step, \
+         failed_attempts = excluded.failed_attempts, \
+         locked_until = excluded.locked_until, generation = excluded.generation, \
+         spent_backup_codes = excluded.spent_backup_codes, updated_at = excluded.updated_at",
+        params![
+            wallet_key,
+            state.last_accepted_step.map(|step| step as i64),
+            state.failed_attempts as i64,
+            state.locked_until as i64,
+            state.generation as i64,
+            serde_json::to_string(&state.spent_backup_codes)
+                .map_err(|e| format!("保存 2FA 状态失败: {}", e))?,
+            now_unix_secs()? as i64,
+        ],
+    )
+    .map(|_| ())
+    .map_err(|e| format!("保存 2FA 状态失败: {}", e))
+}
+
+/// 2FA 防重放与失败锁定状态的 SQLite 存储
+pub struct TotpStateDb;
+
+impl TotpStateStore for TotpStateDb {
+    fn load(&mut self, wallet_key: &str) -> Result<TotpGuardState, String> {
+        let _guard = store_lock()
+            .lock()
+            .map_err(|_| "数据库读锁已损坏".to_string())?;
+        let conn = open_connection()?;
+        load_totp_guard_state_with_connection(&conn, wallet_key)
+    }
+
+    fn save(&mut self, wallet_key: &str, state: &TotpGuardState) -> Result<(), String> {
+        let _guard = store_lock()
+            .lock()
+            .map_err(|_| "数据库写锁已损坏".to_string())?;
+        let conn = open_connection()?;
+        save_totp_guard_state_with_connection(&conn, wallet_key, state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn legacy_database_is_copied_once_and_removed() {
+        let dir = std::env::temp_dir().join(format!("fnzero-db-migrate-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let legacy = dir.join("legacy.sqlite3");
+        let conn = Connection::open(&legacy).unwrap();
+        init_schema(&conn).unwrap();
+        let state = TotpGuardState {
+            failed_attempts: 2,
+            ..TotpGuardState::default()
+        };
+        save_totp_guard_state_with_connection(&conn, "wallet", &state).unwrap();
+        drop(conn);
+
+        let target = dir.join("data").join("fnzero-safe.sqlite3");
+        let candidates = [dir.join("missing.sqlite3"), legacy.clone()];
+        assert_eq!(
+            migrate_legacy_database(&target, &candidates).unwrap(),
+            Some(legacy.clone())
+        );
+        assert!(!legacy.exists());
+        let conn = Connection::open(&target).unwrap();
+        assert_eq!(
+            load_totp_guard_state_with_connection(&conn, "wallet").unwrap(),
+            state
+        );
+        assert_eq!(migrate_legacy_database(&target, &candidates).unwrap(), None);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn remote_sign_requests_pin_the_client_and_are_handled_once() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        let pairing = RemoteSignPairingRecord {
+            id: "pairing".to_string(),
+            name: "bot".to_string(),
+            relay_url: "http://127.0.0.1:8787".to_string(),
+            topic: "00112233445566778899aabbccddeeff".to_string(),
+            public_key: "desktop-public".to_string(),
+            secret_key: "desktop-secret".to_string(),
+            client_public_key: None,
+            created_at: 1,
+            last_seen_at: None,
+        };
+        insert_remote_sign_pairing_with_connection(&conn, &pairing).unwrap();
+
+        let store = |conn: &mut Connection, client: &str, request_id: &str| {
+            store_remote_sign_request_with_connection(
+                conn, "pairing", client, request_id, "{}", None, 2, 10,
+            )
+        };
+        assert_eq!(
+            store(&mut conn, "client-a", "r1").unwrap(),
+            RemoteSignStoreOutcome::Stored
+        );
+        assert_eq!(
+            store(&mut conn, "client-a", "r1").unwrap(),
+            RemoteSignStoreOutcome::Duplicate
+        );
+        assert_eq!(
+            store(&mut conn, "client-b", "r2").unwrap(),
+            RemoteSignStoreOutcome::ClientMismatch
+        );
+        assert_eq!(
+            find_remote_sign_pairing_with_connection(&conn, "pairing")
+                .unwrap()
+                .client_public_key
+                .as_deref(),
+            Some("client-a")
+        );
+        assert_eq!(
+            store(&mut conn, "client-a", "r2").unwrap(),
+            RemoteSignStoreOutcome::Stored
+        );
+        assert_eq!(
+            store(&mut conn, "client-a", "r3").unwrap(),
+            RemoteSignStoreOutcome::PendingLimit
+        );
+
+        let pending =
+            list_remote_sign_requests_with_connection(&conn, Some(REMOTE_SIGN_STATUS_PENDING))
+                .unwrap();
+        assert_eq!(pending.len(), 2);
+        let id = pending
+            .iter()
+            .find(|request| request.request_id == "r1")
+            .unwrap()
+            .id
+            .clone();
+        transition_remote_sign_request_with_connection(
+            &conn,
+            &id,
+            REMOTE_SIGN_STATUS_PENDING,
+            REMOTE_SIGN_STATUS_SIGNING,
+            None,
+        )
+        .unwrap();
+        assert!(transition_remote_sign_request_with_connection(
+            &conn,
+            &id,
+            REMOTE_SIGN_STATUS_PENDING,
+            REMOTE_SIGN_STATUS_REJECTED,
+            Some("{}"),
+        )
+        .is_err());
+        let approved = transition_remote_sign_request_with_connection(
+            &conn,
+            &id,
+            REMOTE_SIGN_STATUS_SIGNING,
+            REMOTE_SIGN_STATUS_APPROVED,
+            Some("{\"status\":\"approved\"}"),
+        )
+        .unwrap();
+        assert!(!approved.delivered);
+        let undelivered = undelivered_remote_sign_responses_with_connection(&conn).unwrap();
+        assert_eq!(undelivered.len(), 1);
+        assert_eq!(undelivered[0].id, id);
+
+        delete_remote_sign_pairing_with_connection(&mut conn, "pairing").unwrap();
+        assert!(list_remote_sign_requests_with_connection(&conn, None)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn vault_seals_wallet_rows_and_keeps_public_key_lookup() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        let saved = upsert_with_connection(
+            &conn,
+            None,
+            "{\"version\":2}".to_string(),
+            "PubKeyA".to_string(),
+            Some("Treasury".to_string()),
+        )
+        .unwrap();
+
+        let (header, key) = vault::test_passphrase_vault("office vault");
+        assert_eq!(
+            rewrap_wallets_with_connection(&mut conn, None, &header, &key).unwrap(),
+            1
+        );
+        let (name, public_key, keystore_json): (String, String, String) = conn
+            .query_row(
+                "SELECT name, public_key, keystore_json FROM wallets WHERE id = ?1",
+                params![saved.id],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        assert!(name.is_empty());
+        assert!(!public_key.contains("PubKeyA"));
+        assert!(!keystore_json.contains("version"));
+        assert!(vault_header_with_connection(&conn).unwrap().is_some());
+
+        let updated = upsert_with_connection(
+            &conn,
+            Some(&key),
+            "{\"version\":3}".to_string(),
+            "PubKeyA".to_string(),
+            None,
+        )
+        .unwrap();
+        assert_eq!(updated.id, saved.id);
+        let wallets = load_with_connection(&conn, Some(&key)).unwrap();
+        assert_eq!(wallets.len(), 1);
+        assert_eq!(wallets[0].public_key, "PubKeyA");
+        assert_eq!(wallets[0].keystore_json, "{\"version\":3}");
+        assert_eq!(
+            wallet_vault_key(&conn).err().as_deref(),
+            Some(vault::VAULT_LOCKED_MESSAGE)
+        );
+    }
+
+    #[test]
+    fn vault_rotation_is_all_or_nothing() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        let (old_header, old_key) = vault::test_passphrase_vault("old");
+        rewrap_wallets_with_connection(&mut conn, None, &old_header, &old_key).unwrap();
+        for public_key in ["PubKeyA", "PubKeyB"] {
+            upsert_with_connection(
+                &conn,
+                Some(&old_key),
+                "{}".to_string(),
+                public_key.to_string(),
+                None,
+            )
+            .unwrap();
+        }
+
+        let (new_header, new_key) = vault::test_passphrase_vault("new");
+        rewrap_wallets_with_connection(&mut conn, Some(&old_key), &new_header, &new_key).unwrap();
+        assert_eq!(
+            load_with_connection(&conn, Some(&new_key)).unwrap().len(),
+            2
+        );
+        assert!(load_with_connection(&conn, Some(&old_key)).is_err());
+
+        conn.execute(
+            "UPDATE wallets SET keystore_json = 'vault1:AAAA' WHERE id = \
+             (SELECT id FROM wallets ORDER BY id LIMIT 1)",
+            [],
+        )
+        .unwrap();
+        let (next_header, next_key) = vault::test_passphrase_vault("next");
+        assert!(
+            rewrap_wallets_with_connection(&mut conn, Some(&new_key), &next_header, &next_key)
+                .is_err()
+        );
+        let stored = vault_header_with_connection(&conn).unwrap().unwrap();
+        assert_eq!(stored.vault_id, new_header.vault_id);
+        let intact = conn
+            .query_row(
+                "SELECT id, name, public_key, keystore_json, created_at, updated_at \
+                 FROM wallets ORDER BY id DESC LIMIT 1",
+                [],
+                row_to_wallet,
+            )
+            .unwrap();
+        assert!(open_wallet_row(intact, Some(&new_key)).is_ok());
+    }
+
+    #[test]
+    fn totp_guard_state_round_trips() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        assert_eq!(
+            load_totp_guard_state_with_connection(&conn, "wallet").unwrap(),
+            TotpGuardState::default()
+        );
+        let state = TotpGuardState {
+            last_accepted_step: Some(56_666_667),
+            failed_attempts: 2,
+            locked_until: 1_700_000_060,
+            generation: fnzero_safe::totp_guard::RETIRED_GENERATION,
+            spent_backup_codes: vec!["$argon2id$hash".to_string()],
+        };
+        save_totp_guard_state_with_connection(&conn, "wallet", &state).unwrap();
+        assert_eq!(
+            load_totp_guard_state_with_connection(&conn, "wallet").unwrap(),
+            state
+        );
+    }
+
+    #[test]
+    fn wallet_transactions_page_and_filter_from_cache() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        let record =
+            |signature: &str, slot, action: &str, mint: Option<&str>| WalletTransactionRecord {
+                signature: signature.to_string(),
+                slot,
+                block_time: Some(slot as i64),
+                confirmation_status: Some("Finalized".to_string()),
+                err: None,
+                memo: None,
+                action: action.to_string(),
+                summary: "Sent".to_string(),
+                counterparty: Some("Bob".to_string()),
+                programs: vec!["spl-token".to_string()],
+                changes: vec![WalletTransactionChange {
+                    asset: mint.unwrap_or("SOL").to_string(),
+                    mint: mint.map(ToOwned::to_owned),
+                    amount: "-5".to_string(),
+                    ui_amount: "-5".to_string(),
+                    direction: "out".to_string(),
+                    decimals: 0,
+                }],
+                details_loaded: slot != 10,
+            };
+        let records = vec![
+            record("c", 30, "send", Some("USDC")),
+            record("b", 20, "receive", None),
+            record("a", 10, "send", None),
+        ];
+        let state = WalletHistoryState {
+            newest_signature: Some("c".to_string()),
+            oldest_signature: Some("a".to_string()),
+            backfill_complete: true,
+            ..WalletHistoryState::default()
+        };
+        save_wallet_transactions_with_connection(&mut conn, "owner", "mainnet", &records, &state)
+            .unwrap();
+        // Re-indexing the same signature replaces it instead of duplicating changes
+        save_wallet_transactions_with_connection(
+            &mut conn,
+            "owner",
+            "mainnet",
+            &records[..1],
+            &state,
+        )
+        .unwrap();
+
+        let loaded = wallet_history_state_with_connection(&conn, "owner", "mainnet").unwrap();
+        assert_eq!(loaded.transaction_count, 3);
+        assert!(loaded.backfill_complete);
+        assert_eq!(loaded.newest_signature.as_deref(), Some("c"));
+
+        let all = WalletTransactionFilter::default();
+        let page =
+            query_wallet_transactions_with_connection(&conn, "owner", "mainnet", &all, None, 2)
+                .unwrap();
+        assert_eq!(page, records[..2].to_vec());
+        let page = query_wallet_transactions_with_connection(
+            &conn,
+            "owner",
+            "mainnet",
+            &all,
+            Some("b"),
+            2,
+        )
+        .unwrap();
+        assert_eq!(page, records[2..].to_vec());
+        assert!(query_wallet_transactions_with_connection(
+            &conn,
+            "owner",
+            "mainnet",
+            &all,
+            Some("missing"),
+            2
+        )
+        .is_err());
+
+        let signatures = |filter: WalletTransactionFilter| {
+            query_wallet_transactions_with_connection(&conn, "owner", "mainnet", &filter, None, 10)
+                .unwrap()
+                .into_iter()
+                .map(|record| record.signature)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            signatures(WalletTransactionFilter {
+                mint: Some("SOL".to_string()),
+                ..WalletTransactionFilter::default()
+            }),
+            ["b", "a"]
+        );
+        assert_eq!(
+            signatures(WalletTransactionFilter {
+                action: Some("send".to_string()),
+                ..WalletTransactionFilter::default()
+            }),
+            ["c", "a"]
+        );
+        assert_eq!(
+            signatures(WalletTransactionFilter {
+                search: Some("usd".to_string()),
+                ..WalletTransactionFilter::default()
+            }),
+            ["c"]
+        );
+        assert!(signatures(WalletTransactionFilter {
+            search: Some("%".to_string()),
+            ..WalletTransactionFilter::default()
+        })
+        .is_empty());
+        assert_eq!(
+            undetailed_wallet_transactions_with_connection(&conn, "owner", "mainnet", 10)
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn address_book_entries_are_unique_per_network_and_track_sends() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        let entry = save_address_book_entry_with_connection(
+            &conn,
+            None,
+            "mainnet",
+            " Treasury111 ",
+            " Treasury ",
+            Some("  "),
+            None,
+        )
+        .unwrap();
+        assert_eq!(entry.address, "Treasury111");
+        assert_eq!(entry.label, "Treasury");
+        assert_eq!(entry.notes, None);
+        assert_eq!(entry.send_count, 0);
+
+        assert_eq!(
+            save_address_book_entry_with_connection(
+                &conn,
+                None,
+                "mainnet",
+                "Treasury111",
+                "Again",
+                None,
+                None
+            )
+            .unwrap_err(),
+            "该网络的地址簿中已有此地址"
+        );
+        save_address_book_entry_with_connection(
+            &conn,
+            None,
+            "devnet",
+            "Treasury111",
+            "Devnet treasury",
+            None,
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(
+            recipient_history_with_connection(&conn, "mainnet", "Treasury111").unwrap(),
+            RecipientHistory::default()
+        );
+        record_sent_recipient_with_connection(&conn, "mainnet", "Treasury111").unwrap();
+        record_sent_recipient_with_connection(&conn, "mainnet", "Treasury111").unwrap();
+        assert_eq!(
+            recipient_history_with_connection(&conn, "mainnet", "Treasury111")
+                .unwrap()
+                .send_count,
+            2
+        );
+
+        let updated = save_address_book_entry_with_connection(
+            &conn,
+            Some(&entry.id),
+            "mainnet",
+            "Treasury111",
+            "Cold treasury",
+            Some("multisig vault"),
+            None,
+        )
+        .unwrap();
+        assert_eq!(updated.label, "Cold treasury");
+        assert_eq!(updated.send_count, 2);
+        assert_eq!(
+            list_address_book_with_connection(&conn, Some("mainnet"))
+                .unwrap()
+                .len(),
+            1
+        );
+        assert_eq!(
+            list_address_book_with_connection(&conn, None)
+                .unwrap()
+                .len(),
+            2
+        );
+
+        delete_address_book_entry_with_connection(&conn, &entry.id).unwrap();
+        assert!(delete_address_book_entry_with_connection(&conn, &entry.id).is_err());
+    }
+
+    #[test]
+    fn payout_progress_upserts_per_line_and_network() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        let progress = |line, status| PayoutProgress {
+            line,
+            status,
+            signature: Some(format!("sig-{line}")),
+            last_valid_block_height: Some(42),
+            error: None,
+        };
+        record_payout_progress_with_connection(
+            &mut conn,
+            "mainnet",
+            "batch",
+            &[
+                progress(0, PayoutLineStatus::Submitted),
+                progress(1, PayoutLineStatus::Submitted),
+            ],
+        )
+        .unwrap();
+        let mut failed = progress(1, PayoutLineStatus::Failed);
+        failed.error = Some("expired".to_string());
+        record_payout_progress_with_connection(
+            &mut conn,
+            "mainnet",
+            "batch",
+            &[progress(0, PayoutLineStatus::Confirmed), failed.clone()],
+        )
+        .unwrap();
+
+        let loaded = load_payout_progress_with_connection(&conn, "mainnet", "batch").unwrap();
+        assert_eq!(
+            loaded,
+            vec![progress(0, PayoutLineStatus::Confirmed), failed]
+        );
+        assert!(
+            load_payout_progress_with_connection(&conn, "devnet", "batch")
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn swqos_settings_round_trip() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_schema(&conn).unwrap();
+        assert!(get_swqos_settings_with_connection(&conn).unwrap().is_none());
+
+        let mut settings = SwqosSettings::default();
+        settings.providers[0].region = "tokyo".to_string();
+        settings.sender_concurrency = Some(3);
+        save_swqos_settings_with_connection(&conn, &settings).unwrap();
+        assert_eq!(
+            get_swqos_settings_with_connection(&conn).unwrap(),
+            Some(settings.clone())
+        );
+
+        settings.providers[1].region = "atlantis".to_string();
+        assert!(save_swqos_settings_with_connection(&conn, &settings).is_err());
+        assert_eq!(
+            get_swqos_settings_with_connection(&conn)
+                .unwrap()
+                .unwrap()
+                .providers[1]
+                .region,
+            "frankfurt"
+        );
+    }
 
     fn deployment_record(buffer_address: &str, program_sha256: &str) -> ProgramDeploymentRecord {
         ProgramDeploymentRecord {
@@ -3,10 +3,12 @@ use serde::{Deserialize, Serialize};
 use svc::{
     biometric_policy_stub, create_wallet, empty_asset_snapshot, export_private_key,
     import_keystore, import_mnemonic, import_private_key, load_asset_snapshot, mobile_capabilities,
-    preview_dapp_signing, preview_payment, preview_pump_trade, preview_squads_action, setup_totp,
-    squads_approve_submit, squads_create_submit, squads_execute_submit, squads_info,
-    squads_proposals, squads_reject_submit, squads_transfer_proposal_submit, submit_dapp_signing,
-    submit_payment, unlock_wallet, unsupported_mobile_program_workflow, verify_totp,
+    preview_dapp_signing, preview_payment, preview_pump_buy, preview_pump_trade,
+    preview_squads_action, setup_totp, squads_activate_submit, squads_approve_submit,
+    squads_cancel_submit, squads_cleanup_submit, squads_close_submit, squads_create_submit,
+    squads_execute_submit, squads_info, squads_proposals, squads_reject_submit,
+    squads_transfer_proposal_submit, submit_dapp_signing, submit_payment, unlock_wallet,
+    unsupported_mobile_program_workflow, verify_totp, verify_totp_with_state,
 };
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -159,6 +161,29 @@ pub struct PaymentPreviewRequest {
     pub memo: Option<String>,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AddressBookEntry {
+    pub id: String,
+    pub network: AppNetwork,
+    pub address: String,
+    pub label: String,
+    pub notes: Option<String>,
+    pub expected_mint: Option<String>,
+    pub send_count: u64,
+    pub last_sent_at_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecipientCheck {
+    pub label: Option<String>,
+    pub notes: Option<String>,
+    pub expected_mint: Option<String>,
+    pub mint_mismatch: bool,
+    pub first_send: bool,
+    pub lookalikes: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct SigningPreview {
     pub id: String,
@@ -213,6 +238,18 @@ pub struct PumpPreviewRequest {
     pub venue: String,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PumpBuyPreviewRequest {
+    pub network: AppNetwork,
+    pub wallet_public_key: String,
+    pub mint: String,
+    pub amount_lamports: u64,
+    pub input_token: String,
+    pub slippage_bps: u32,
+    pub create_ata: bool,
+    pub venue: String,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct DappSignPreviewRequest {
     pub network: AppNetwork,
@@ -397,6 +434,45 @@ pub struct SquadsExecuteSubmitRequest {
     pub transaction_index: u64,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SquadsCloseSubmitRequest {
+    pub approved: bool,
+    pub network: AppNetwork,
+    pub rpc_url: Option<String>,
+    pub keystore_json: String,
+    pub password: String,
+    pub multisig: String,
+    pub transaction_index: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SquadsCleanupSubmitRequest {
+    pub approved: bool,
+    pub network: AppNetwork,
+    pub rpc_url: Option<String>,
+    pub keystore_json: String,
+    pub password: String,
+    pub multisig: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SquadsCleanupSkipped {
+    pub transaction_index: u64,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SquadsCleanupSubmitResult {
+    pub multisig: String,
+    pub rent_collector: String,
+    pub closed: Vec<u64>,
+    pub skipped: Vec<SquadsCleanupSkipped>,
+    pub transactions: Vec<TransactionSubmitResult>,
+    pub reclaimed_lamports: u64,
+    pub remaining: u64,
+    pub network: AppNetwork,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct TotpSetup {
     pub secret: String,
@@ -410,6 +486,21 @@ pub struct TotpVerifyRequest {
     pub code: String,
 }
 
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TotpAttemptState {
+    pub last_accepted_step: Option<u64>,
+    pub failed_attempts: u32,
+    pub locked_until: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TotpVerification {
+    pub verified: bool,
+    pub state: TotpAttemptState,
+    pub error: Option<MobileError>,
+    pub retry_after_secs: Option<u64>,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct BiometricPolicy {
     pub supported: bool,
@@ -635,6 +726,52 @@ impl From<PaymentPreviewRequest> for svc::PaymentPreviewRequest {
             mint: value.mint,
             amount: value.amount,
             memo: value.memo,
+            address_book: Vec::new(),
+            previous_recipients: Vec::new(),
+        }
+    }
+}
+
+impl From<AddressBookEntry> for svc::AddressBookEntry {
+    fn from(value: AddressBookEntry) -> Self {
+        Self {
+            id: value.id,
+            network: value.network.into(),
+            address: value.address,
+            label: value.label,
+            notes: value.notes,
+            expected_mint: value.expected_mint,
+            send_count: value.send_count,
+            last_sent_at_ms: value.last_sent_at_ms,
+        }
+    }
+}
+
+impl From<svc::AddressBookEntry> for AddressBookEntry {
+    fn from(value: svc::AddressBookEntry) -> Self {
+        Self {
+            id: value.id,
+            network: value.network.into(),
+            address: value.address,
+            label: value.label,
+            notes: value.notes,
+            expected_mint: value.expected_mint,
+            send_count: value.send_count,
+            last_sent_at_ms: value.last_sent_at_ms,
+        }
+    }
+}
+
+impl From<svc::RecipientCheck> for RecipientCheck {
+    fn from(value: svc::RecipientCheck) -> Self {
+        Self {
+            label: value.label,
+            notes: value.notes,
+            expected_mint: value.expected_mint,
+            mint_mismatch: value.mint_mismatch,
+            first_send: value.first_send,
+            lookalikes: value.lookalikes,
+            warnings: value.warnings,
         }
     }
 }
@@ -679,6 +816,7 @@ impl From<PaymentSubmitRequest> for svc::PaymentSubmitRequest {
             recipient: value.recipient,
             mint: value.mint,
             amount_base_units: value.amount_base_units,
+            memo: None,
         }
     }
 }
@@ -708,6 +846,21 @@ impl From<PumpPreviewRequest> for svc::PumpPreviewRequest {
     }
 }
 
+impl From<PumpBuyPreviewRequest> for svc::PumpBuyPreviewRequest {
+    fn from(value: PumpBuyPreviewRequest) -> Self {
+        Self {
+            network: value.network.into(),
+            wallet_public_key: value.wallet_public_key,
+            mint: value.mint,
+            amount_lamports: value.amount_lamports,
+            input_token: value.input_token,
+            slippage_bps: value.slippage_bps,
+            create_ata: value.create_ata,
+            venue: value.venue,
+        }
+    }
+}
+
 impl From<DappSignPreviewRequest> for svc::DappSignPreviewRequest {
     fn from(value: DappSignPreviewRequest) -> Self {
         Self {
@@ -943,6 +1096,55 @@ impl From<SquadsExecuteSubmitRequest> for svc::SquadsExecuteSubmitRequest {
     }
 }
 
+impl From<SquadsCloseSubmitRequest> for svc::SquadsCloseSubmitRequest {
+    fn from(value: SquadsCloseSubmitRequest) -> Self {
+        Self {
+            approved: value.approved,
+            network: value.network.into(),
+            rpc_url: value.rpc_url,
+            keystore_json: value.keystore_json,
+            password: value.password,
+            multisig: value.multisig,
+            transaction_index: value.transaction_index,
+        }
+    }
+}
+
+impl From<SquadsCleanupSubmitRequest> for svc::SquadsCleanupSubmitRequest {
+    fn from(value: SquadsCleanupSubmitRequest) -> Self {
+        Self {
+            approved: value.approved,
+            network: value.network.into(),
+            rpc_url: value.rpc_url,
+            keystore_json: value.keystore_json,
+            password: value.password,
+            multisig: value.multisig,
+        }
+    }
+}
+
+impl From<svc::SquadsCleanupSubmitResult> for SquadsCleanupSubmitResult {
+    fn from(value: svc::SquadsCleanupSubmitResult) -> Self {
+        Self {
+            multisig: value.multisig,
+            rent_collector: value.rent_collector,
+            closed: value.closed,
+            skipped: value
+                .skipped
+                .into_iter()
+                .map(|skipped| SquadsCleanupSkipped {
+                    transaction_index: skipped.transaction_index,
+                    reason: skipped.reason,
+                })
+                .collect(),
+            transactions: value.transactions.into_iter().map(Into::into).collect(),
+            reclaimed_lamports: value.reclaimed_lamports,
+            remaining: value.remaining,
+            network: value.network.into(),
+        }
+    }
+}
+
 impl From<svc::TotpSetup> for TotpSetup {
     fn from(value: svc::TotpSetup) -> Self {
         Self {
@@ -962,6 +1164,37 @@ impl From<TotpVerifyRequest> for svc::TotpVerifyRequest {
     }
 }
 
+impl From<TotpAttemptState> for svc::TotpAttemptState {
+    fn from(value: TotpAttemptState) -> Self {
+        Self {
+            last_accepted_step: value.last_accepted_step,
+            failed_attempts: value.failed_attempts,
+            locked_until: value.locked_until,
+        }
+    }
+}
+
+impl From<svc::TotpAttemptState> for TotpAttemptState {
+    fn from(value: svc::TotpAttemptState) -> Self {
+        Self {
+            last_accepted_step: value.last_accepted_step,
+            failed_attempts: value.failed_attempts,
+            locked_until: value.locked_until,
+        }
+    }
+}
+
+impl From<svc::TotpVerification> for TotpVerification {
+    fn from(value: svc::TotpVerification) -> Self {
+        Self {
+            verified: value.verified,
+            state: value.state.into(),
+            error: value.error.map(Into::into),
+            retry_after_secs: value.retry_after_secs,
+        }
+    }
+}
+
 impl From<svc::BiometricPolicy> for BiometricPolicy {
     fn from(value: svc::BiometricPolicy) -> Self {
         Self {
@@ -1071,6 +1304,84 @@ pub fn payment_confirm(req: PaymentSubmitRequest) -> Result<TransactionSubmitRes
         .map_err(bridge_error)
 }
 
+/// Same as `payment_preview`, labelling the recipient and flagging lookalikes and first sends
+pub fn payment_preview_with_address_book(
+    req: PaymentPreviewRequest,
+    address_book: Vec<AddressBookEntry>,
+    previous_recipients: Vec<String>,
+) -> Result<SigningPreview, MobileError> {
+    let mut req: svc::PaymentPreviewRequest = req.into();
+    req.address_book = address_book.into_iter().map(Into::into).collect();
+    req.previous_recipients = previous_recipients;
+    preview_payment(req).map(Into::into).map_err(bridge_error)
+}
+
+fn address_book_from_bridge(book: Vec<AddressBookEntry>) -> Vec<svc::AddressBookEntry> {
+    book.into_iter().map(Into::into).collect()
+}
+
+fn address_book_to_bridge(book: Vec<svc::AddressBookEntry>) -> Vec<AddressBookEntry> {
+    book.into_iter().map(Into::into).collect()
+}
+
+pub fn address_book_save(
+    book: Vec<AddressBookEntry>,
+    entry: AddressBookEntry,
+) -> Result<Vec<AddressBookEntry>, MobileError> {
+    svc::address_book_save(address_book_from_bridge(book), entry.into())
+        .map(address_book_to_bridge)
+        .map_err(bridge_error)
+}
+
+pub fn address_book_delete(
+    book: Vec<AddressBookEntry>,
+    id: String,
+) -> Result<Vec<AddressBookEntry>, MobileError> {
+    svc::address_book_delete(address_book_from_bridge(book), &id)
+        .map(address_book_to_bridge)
+        .map_err(bridge_error)
+}
+
+pub fn address_book_record_send(
+    book: Vec<AddressBookEntry>,
+    network: AppNetwork,
+    recipient: String,
+) -> Vec<AddressBookEntry> {
+    address_book_to_bridge(svc::address_book_record_send(
+        address_book_from_bridge(book),
+        network.into(),
+        &recipient,
+    ))
+}
+
+pub fn address_book_check(
+    network: AppNetwork,
+    recipient: String,
+    mint: Option<String>,
+    address_book: Vec<AddressBookEntry>,
+    previous_recipients: Vec<String>,
+) -> Result<RecipientCheck, MobileError> {
+    svc::check_recipient(svc::RecipientCheckRequest {
+        network: network.into(),
+        recipient,
+        mint,
+        address_book: address_book_from_bridge(address_book),
+        previous_recipients,
+    })
+    .map(Into::into)
+    .map_err(bridge_error)
+}
+
+/// Same as `payment_confirm`, attaching an SPL Memo to the SOL or token transfer
+pub fn payment_confirm_with_memo(
+    req: PaymentSubmitRequest,
+    memo: Option<String>,
+) -> Result<TransactionSubmitResult, MobileError> {
+    let mut req: svc::PaymentSubmitRequest = req.into();
+    req.memo = memo;
+    submit_payment(req).map(Into::into).map_err(bridge_error)
+}
+
 pub fn security_setup_totp(account: String) -> Result<TotpSetup, MobileError> {
     setup_totp(account).map(Into::into).map_err(bridge_error)
 }
@@ -1079,6 +1390,20 @@ pub fn security_verify_totp(req: TotpVerifyRequest) -> Result<bool, MobileError>
     verify_totp(req.into()).map_err(bridge_error)
 }
 
+/// Verify with the replay/lockout state from the previous call; persist the returned state
+pub fn security_verify_totp_with_state(
+    req: TotpVerifyRequest,
+    state: Option<TotpAttemptState>,
+) -> Result<TotpVerification, MobileError> {
+    verify_totp_with_state(svc::TotpStatefulVerifyRequest {
+        secret: req.secret,
+        code: req.code,
+        state: state.map(Into::into),
+    })
+    .map(Into::into)
+    .map_err(bridge_error)
+}
+
 pub fn security_biometric_policy() -> BiometricPolicy {
     biometric_policy_stub().into()
 }
@@ -1089,6 +1414,12 @@ pub fn pump_preview(req: PumpPreviewRequest) -> Result<SigningPreview, MobileErr
         .map_err(bridge_error)
 }
 
+pub fn pump_buy_preview(req: PumpBuyPreviewRequest) -> Result<SigningPreview, MobileError> {
+    preview_pump_buy(req.into())
+        .map(Into::into)
+        .map_err(bridge_error)
+}
+
 pub fn dapp_sign_preview(req: DappSignPreviewRequest) -> Result<SigningPreview, MobileError> {
     preview_dapp_signing(req.into())
         .map(Into::into)
@@ -1161,6 +1492,38 @@ pub fn squads_execute_confirm(
         .map_err(bridge_error)
 }
 
+pub fn squads_cancel_confirm(
+    req: SquadsVoteSubmitRequest,
+) -> Result<TransactionSubmitResult, MobileError> {
+    squads_cancel_submit(req.into())
+        .map(Into::into)
+        .map_err(bridge_error)
+}
+
+pub fn squads_activate_confirm(
+    req: SquadsVoteSubmitRequest,
+) -> Result<TransactionSubmitResult, MobileError> {
+    squads_activate_submit(req.into())
+        .map(Into::into)
+        .map_err(bridge_error)
+}
+
+pub fn squads_close_confirm(
+    req: SquadsCloseSubmitRequest,
+) -> Result<TransactionSubmitResult, MobileError> {
+    squads_close_submit(req.into())
+        .map(Into::into)
+        .map_err(bridge_error)
+}
+
+pub fn squads_cleanup_confirm(
+    req: SquadsCleanupSubmitRequest,
+) -> Result<SquadsCleanupSubmitResult, MobileError> {
+    squads_cleanup_submit(req.into())
+        .map(Into::into)
+        .map_err(bridge_error)
+}
+
 pub fn mobile_program_deploy() -> Result<(), MobileError> {
     Err(bridge_error(unsupported_mobile_program_workflow(
         "program_deploy",
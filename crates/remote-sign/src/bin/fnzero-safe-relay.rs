@@ -0,0 +1,45 @@
+//! Self-hostable remote signing relay
+//!
+//! `fnzero-safe-relay [--listen <addr>] [--ttl-secs <secs>]`; both can also be set
+//! through `FNZERO_SAFE_RELAY_LISTEN` and `FNZERO_SAFE_RELAY_TTL_SECS`.
+
+use fnzero_safe_remote_sign::relay::{self, RelayConfig, DEFAULT_LISTEN_ADDR};
+use std::time::Duration;
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+
+fn usage() -> ! {
+    eprintln!("usage: fnzero-safe-relay [--listen <addr>] [--ttl-secs <secs>]");
+    std::process::exit(2);
+}
+
+#[tokio::main]
+async fn main() -> std::io::Result<()> {
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| "fnzero_safe_remote_sign=info".into()),
+        )
+        .with(tracing_subscriber::fmt::layer())
+        .init();
+
+    let mut listen =
+        std::env::var("FNZERO_SAFE_RELAY_LISTEN").unwrap_or_else(|_| DEFAULT_LISTEN_ADDR.into());
+    let mut ttl_secs = std::env::var("FNZERO_SAFE_RELAY_TTL_SECS").ok();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--listen" => listen = args.next().unwrap_or_else(|| usage()),
+            "--ttl-secs" => ttl_secs = Some(args.next().unwrap_or_else(|| usage())),
+            _ => usage(),
+        }
+    }
+    let mut config = RelayConfig::default();
+    if let Some(ttl_secs) = ttl_secs {
+        let ttl_secs = ttl_secs.parse().unwrap_or_else(|_| usage());
+        config.message_ttl = Duration::from_secs(ttl_secs);
+    }
+
+    let listener = tokio::net::TcpListener::bind(&listen).await?;
+    tracing::info!("Relay listening on http://{}", listener.local_addr()?);
+    relay::serve(listener, config).await
+}
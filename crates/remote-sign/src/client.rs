@@ -0,0 +1,183 @@
+//! HTTP client for the relay and the client side of a pairing
+
+use crate::{
+    relay::{RelayMessage, RelayMessages, RelayPosted},
+    response_topic, Direction, Envelope, PairingUri, RemoteSignError, Result, SignRequest,
+    SignResponse, StaticKey,
+};
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+const HTTP_TIMEOUT: Duration = Duration::from_secs(45);
+const MAX_LONG_POLL: Duration = Duration::from_secs(25);
+
+#[derive(Clone)]
+pub struct RelayClient {
+    http: reqwest::Client,
+    base_url: String,
+}
+
+fn relay_error(error: impl std::fmt::Display) -> RemoteSignError {
+    RemoteSignError::Relay(error.to_string())
+}
+
+impl RelayClient {
+    pub fn new(base_url: &str) -> Result<Self> {
+        let http = reqwest::Client::builder()
+            .timeout(HTTP_TIMEOUT)
+            .build()
+            .map_err(relay_error)?;
+        Ok(Self {
+            http,
+            base_url: base_url.trim().trim_end_matches('/').to_string(),
+        })
+    }
+
+    fn mailbox_url(&self, topic: &str) -> String {
+        format!("{}/v1/mailboxes/{topic}", self.base_url)
+    }
+
+    pub async fn post(&self, topic: &str, envelope: &Envelope) -> Result<u64> {
+        let posted: RelayPosted = self
+            .http
+            .post(self.mailbox_url(topic))
+            .json(envelope)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(relay_error)?
+            .json()
+            .await
+            .map_err(relay_error)?;
+        Ok(posted.seq)
+    }
+
+    /// Envelopes newer than `after`, long-polling up to `wait` when there are none
+    pub async fn fetch(
+        &self,
+        topic: &str,
+        after: u64,
+        wait: Duration,
+    ) -> Result<Vec<RelayMessage>> {
+        let fetched: RelayMessages = self
+            .http
+            .get(self.mailbox_url(topic))
+            .query(&[("after", after), ("wait", wait.as_secs())])
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(relay_error)?
+            .json()
+            .await
+            .map_err(relay_error)?;
+        Ok(fetched.messages)
+    }
+
+    pub async fn ack(&self, topic: &str, through: u64) -> Result<()> {
+        self.http
+            .delete(self.mailbox_url(topic))
+            .query(&[("through", through)])
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(relay_error)?;
+        Ok(())
+    }
+}
+
+/// Client side of a pairing: sends sign requests and waits for the desktop's answer
+pub struct RemoteSigner {
+    pairing: PairingUri,
+    identity: StaticKey,
+    relay: RelayClient,
+    response_topic: String,
+    /// Responses read while waiting for a different request
+    received: HashMap<String, SignResponse>,
+}
+
+impl RemoteSigner {
+    pub fn new(pairing: PairingUri, identity: StaticKey) -> Result<Self> {
+        let relay = RelayClient::new(&pairing.relay_url)?;
+        let response_topic = response_topic(&pairing.topic, &identity.public_key());
+        Ok(Self {
+            pairing,
+            identity,
+            relay,
+            response_topic,
+            received: HashMap::new(),
+        })
+    }
+
+    /// Public key the desktop pins for this client on its first request
+    pub fn public_key(&self) -> String {
+        self.identity.public_key()
+    }
+
+    pub async fn send(&self, request: &SignRequest) -> Result<()> {
+        let plaintext = serde_json::to_vec(request)
+            .map_err(|error| RemoteSignError::InvalidEnvelope(error.to_string()))?;
+        let envelope = Envelope::seal(
+            &self.identity,
+            &self.pairing.public_key,
+            &self.pairing.topic,
+            Direction::Request,
+            &plaintext,
+        )?;
+        self.relay.post(&self.pairing.topic, &envelope).await?;
+        Ok(())
+    }
+
+    pub async fn wait_for_response(
+        &mut self,
+        request_id: &str,
+        timeout: Duration,
+    ) -> Result<SignResponse> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if let Some(response) = self.received.remove(request_id) {
+                return Ok(response);
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(RemoteSignError::Timeout(request_id.to_string()));
+            }
+            let wait = remaining.min(MAX_LONG_POLL).max(Duration::from_secs(1));
+            let messages = self.relay.fetch(&self.response_topic, 0, wait).await?;
+            let Some(through) = messages.iter().map(|message| message.seq).max() else {
+                continue;
+            };
+            for message in messages {
+                if let Some(response) = self.open_response(&message.envelope) {
+                    self.received.insert(response.request_id.clone(), response);
+                }
+            }
+            self.relay.ack(&self.response_topic, through).await?;
+        }
+    }
+
+    fn open_response(&self, envelope: &Envelope) -> Option<SignResponse> {
+        if envelope.sender != self.pairing.public_key {
+            tracing::warn!("Ignoring relay response from an unknown sender");
+            return None;
+        }
+        let plaintext = envelope
+            .open(&self.identity, &self.pairing.topic, Direction::Response)
+            .map_err(|error| tracing::warn!("Ignoring unreadable relay response: {}", error))
+            .ok()?;
+        serde_json::from_slice(&plaintext)
+            .map_err(|error| tracing::warn!("Ignoring malformed relay response: {}", error))
+            .ok()
+    }
+
+    /// Send `request` and wait until the desktop approves, rejects or expires it
+    pub async fn request(
+        &mut self,
+        request: &SignRequest,
+        timeout: Duration,
+    ) -> Result<SignResponse> {
+        self.send(request).await?;
+        self.wait_for_response(&request.request_id, timeout).await
+    }
+}
@@ -0,0 +1,33 @@
+//! Pairing-based remote signing for FnzeroSafe.
+//!
+//! The desktop wallet creates a pairing: a random relay topic plus a static X25519
+//! key, handed to a remote client (a bot, a phone) as a `fnzero-safe://pair` URI or
+//! QR code. The client seals sign requests to that key and drops them into the
+//! topic's mailbox on a relay; the desktop fetches them, asks a human to approve and
+//! answers through a per-client response mailbox. The relay only ever sees opaque
+//! envelopes, so a self-hosted [`relay`] needs no trust beyond availability.
+
+use thiserror::Error;
+
+mod client;
+mod protocol;
+pub mod relay;
+
+pub use client::*;
+pub use protocol::*;
+
+#[derive(Debug, Error)]
+pub enum RemoteSignError {
+    #[error("invalid pairing URI: {0}")]
+    InvalidPairing(String),
+    #[error("invalid envelope: {0}")]
+    InvalidEnvelope(String),
+    #[error("envelope could not be decrypted")]
+    Decrypt,
+    #[error("relay request failed: {0}")]
+    Relay(String),
+    #[error("timed out waiting for a response to request {0}")]
+    Timeout(String),
+}
+
+pub type Result<T> = std::result::Result<T, RemoteSignError>;
@@ -0,0 +1,484 @@
+//! Pairing URIs, sealed envelopes and the sign request/response messages
+
+use crate::{RemoteSignError, Result};
+use aes_gcm::{
+    aead::{Aead, KeyInit, Payload},
+    Aes256Gcm, Nonce,
+};
+use base64::{
+    engine::general_purpose::{STANDARD as BASE64, URL_SAFE_NO_PAD as BASE64_URL},
+    Engine as _,
+};
+use hkdf::Hkdf;
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::{
+    fmt,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use uuid::Uuid;
+use x25519_dalek::{PublicKey, StaticSecret};
+use zeroize::Zeroizing;
+
+pub const PROTOCOL_VERSION: u32 = 1;
+pub const PAIRING_URI_PREFIX: &str = "fnzero-safe://pair?";
+const HKDF_INFO: &str = "fnzero-safe remote-sign v1";
+const RESPONSE_TOPIC_CONTEXT: &[u8] = b"fnzero-safe remote-sign response";
+const TOPIC_BYTES: usize = 16;
+
+pub fn now_unix_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Static X25519 key of one side of a pairing
+///
+/// The desktop keeps one per pairing; a client should persist its own so the
+/// desktop keeps recognising it after a restart.
+pub struct StaticKey(StaticSecret);
+
+impl StaticKey {
+    pub fn generate() -> Self {
+        Self(StaticSecret::random_from_rng(OsRng))
+    }
+
+    pub fn from_base64(encoded: &str) -> Result<Self> {
+        let bytes = Zeroizing::new(
+            BASE64_URL
+                .decode(encoded.trim())
+                .map_err(|_| RemoteSignError::InvalidPairing("secret key is not base64".into()))?,
+        );
+        let bytes: [u8; 32] = bytes
+            .as_slice()
+            .try_into()
+            .map_err(|_| RemoteSignError::InvalidPairing("secret key must be 32 bytes".into()))?;
+        Ok(Self(StaticSecret::from(bytes)))
+    }
+
+    pub fn to_base64(&self) -> Zeroizing<String> {
+        Zeroizing::new(BASE64_URL.encode(self.0.as_bytes()))
+    }
+
+    /// URL-safe base64 public key, as carried in pairing URIs and envelopes
+    pub fn public_key(&self) -> String {
+        BASE64_URL.encode(PublicKey::from(&self.0).as_bytes())
+    }
+
+    fn cipher(
+        &self,
+        peer_public_key: &str,
+        topic: &str,
+        direction: Direction,
+    ) -> Result<Aes256Gcm> {
+        let peer = decode_public_key(peer_public_key)?;
+        let shared = self.0.diffie_hellman(&peer);
+        if !shared.was_contributory() {
+            return Err(RemoteSignError::InvalidEnvelope(
+                "peer public key is a low-order point".into(),
+            ));
+        }
+        let mut key = Zeroizing::new([0u8; 32]);
+        let info = format!("{HKDF_INFO}:{}", direction.as_str());
+        Hkdf::<Sha256>::new(Some(topic.as_bytes()), shared.as_bytes())
+            .expand(info.as_bytes(), key.as_mut())
+            .expect("32 bytes is a valid HKDF-SHA256 output length");
+        Ok(Aes256Gcm::new_from_slice(key.as_ref()).expect("AES-256 key is 32 bytes"))
+    }
+}
+
+fn decode_public_key(encoded: &str) -> Result<PublicKey> {
+    let bytes: [u8; 32] = BASE64_URL
+        .decode(encoded)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| RemoteSignError::InvalidEnvelope("public key must be 32 bytes".into()))?;
+    Ok(PublicKey::from(bytes))
+}
+
+/// Short fingerprint of a public key for showing next to a pairing
+pub fn key_fingerprint(public_key: &str) -> String {
+    let digest = Sha256::digest(public_key.as_bytes());
+    hex(&digest[..6])
+        .as_bytes()
+        .chunks(4)
+        .map(|chunk| String::from_utf8_lossy(chunk).into_owned())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Fresh random mailbox topic for a new pairing
+pub fn new_topic() -> String {
+    let mut bytes = [0u8; TOPIC_BYTES];
+    OsRng.fill_bytes(&mut bytes);
+    hex(&bytes)
+}
+
+pub fn is_valid_topic(topic: &str) -> bool {
+    topic.len() == TOPIC_BYTES * 2
+        && topic
+            .bytes()
+            .all(|byte| byte.is_ascii_digit() || (b'a'..=b'f').contains(&byte))
+}
+
+/// Mailbox the desktop answers a given client in
+pub fn response_topic(topic: &str, client_public_key: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(RESPONSE_TOPIC_CONTEXT);
+    hasher.update(topic.as_bytes());
+    hasher.update(client_public_key.as_bytes());
+    hex(&hasher.finalize()[..TOPIC_BYTES])
+}
+
+/// What the desktop shows as a QR code and a client imports
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PairingUri {
+    pub topic: String,
+    /// Desktop's X25519 public key, URL-safe base64
+    pub public_key: String,
+    pub relay_url: String,
+    pub name: Option<String>,
+}
+
+impl PairingUri {
+    pub fn parse(uri: &str) -> Result<Self> {
+        let query = uri.trim().strip_prefix(PAIRING_URI_PREFIX).ok_or_else(|| {
+            RemoteSignError::InvalidPairing(format!("expected {PAIRING_URI_PREFIX}"))
+        })?;
+        let (mut version, mut topic, mut public_key, mut relay_url, mut name) =
+            (None, None, None, None, None);
+        for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let value = urlencoding::decode(value)
+                .map_err(|_| RemoteSignError::InvalidPairing(format!("{key} is not UTF-8")))?
+                .into_owned();
+            match key {
+                "v" => version = Some(value),
+                "topic" => topic = Some(value),
+                "key" => public_key = Some(value),
+                "relay" => relay_url = Some(value),
+                "name" => name = Some(value).filter(|name| !name.is_empty()),
+                _ => {}
+            }
+        }
+        if version.as_deref() != Some("1") {
+            return Err(RemoteSignError::InvalidPairing(
+                "unsupported pairing version".into(),
+            ));
+        }
+        let topic = topic
+            .filter(|topic| is_valid_topic(topic))
+            .ok_or_else(|| RemoteSignError::InvalidPairing("missing or invalid topic".into()))?;
+        let public_key =
+            public_key.ok_or_else(|| RemoteSignError::InvalidPairing("missing key".into()))?;
+        decode_public_key(&public_key)
+            .map_err(|_| RemoteSignError::InvalidPairing("key must be 32 bytes".into()))?;
+        let relay_url = relay_url
+            .filter(|url| url.starts_with("http://") || url.starts_with("https://"))
+            .ok_or_else(|| {
+                RemoteSignError::InvalidPairing("relay must be an http(s) URL".into())
+            })?;
+        Ok(Self {
+            topic,
+            public_key,
+            relay_url,
+            name,
+        })
+    }
+}
+
+impl fmt::Display for PairingUri {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{PAIRING_URI_PREFIX}v={PROTOCOL_VERSION}&topic={}&key={}&relay={}",
+            self.topic,
+            self.public_key,
+            urlencoding::encode(&self.relay_url)
+        )?;
+        if let Some(name) = &self.name {
+            write!(f, "&name={}", urlencoding::encode(name))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    Request,
+    Response,
+}
+
+impl Direction {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Request => "request",
+            Self::Response => "response",
+        }
+    }
+}
+
+/// Sealed message as stored on the relay
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Envelope {
+    pub version: u32,
+    pub direction: Direction,
+    /// Sender's X25519 public key, URL-safe base64
+    pub sender: String,
+    pub nonce: String,
+    pub ciphertext: String,
+}
+
+fn envelope_aad(topic: &str, direction: Direction, sender: &str) -> Vec<u8> {
+    format!("{PROTOCOL_VERSION}|{}|{topic}|{sender}", direction.as_str()).into_bytes()
+}
+
+impl Envelope {
+    /// Seal `plaintext` from `sender` to `recipient_public_key` for `topic`
+    pub fn seal(
+        sender: &StaticKey,
+        recipient_public_key: &str,
+        topic: &str,
+        direction: Direction,
+        plaintext: &[u8],
+    ) -> Result<Self> {
+        let sender_public_key = sender.public_key();
+        let mut nonce = [0u8; 12];
+        OsRng.fill_bytes(&mut nonce);
+        let ciphertext = sender
+            .cipher(recipient_public_key, topic, direction)?
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: plaintext,
+                    aad: &envelope_aad(topic, direction, &sender_public_key),
+                },
+            )
+            .map_err(|_| RemoteSignError::InvalidEnvelope("encryption failed".into()))?;
+        Ok(Self {
+            version: PROTOCOL_VERSION,
+            direction,
+            sender: sender_public_key,
+            nonce: BASE64.encode(nonce),
+            ciphertext: BASE64.encode(ciphertext),
+        })
+    }
+
+    /// Open an envelope addressed to `recipient`; callers decide whether to trust `sender`
+    pub fn open(
+        &self,
+        recipient: &StaticKey,
+        topic: &str,
+        direction: Direction,
+    ) -> Result<Zeroizing<Vec<u8>>> {
+        if self.version != PROTOCOL_VERSION {
+            return Err(RemoteSignError::InvalidEnvelope(format!(
+                "unsupported version {}",
+                self.version
+            )));
+        }
+        if self.direction != direction {
+            return Err(RemoteSignError::InvalidEnvelope(
+                "unexpected direction".into(),
+            ));
+        }
+        let nonce = BASE64
+            .decode(&self.nonce)
+            .ok()
+            .filter(|nonce| nonce.len() == 12)
+            .ok_or_else(|| RemoteSignError::InvalidEnvelope("nonce must be 12 bytes".into()))?;
+        let ciphertext = BASE64
+            .decode(&self.ciphertext)
+            .map_err(|_| RemoteSignError::InvalidEnvelope("ciphertext is not base64".into()))?;
+        recipient
+            .cipher(&self.sender, topic, direction)?
+            .decrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &ciphertext,
+                    aad: &envelope_aad(topic, direction, &self.sender),
+                },
+            )
+            .map(Zeroizing::new)
+            .map_err(|_| RemoteSignError::Decrypt)
+    }
+}
+
+/// What the remote client wants signed
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SignPayload {
+    /// Same fields as `/api/external-sign/sign`; `submit` asks the desktop to also send it
+    Transaction {
+        required_signer: String,
+        transaction_base64: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        transaction_format: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        network: Option<String>,
+        #[serde(default)]
+        submit: bool,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        expected_genesis_hash: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        recent_blockhash: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        last_valid_block_height: Option<u64>,
+    },
+    /// Same fields as `/api/external-sign/message`
+    Message {
+        required_signer: String,
+        message_base64: String,
+    },
+}
+
+impl SignPayload {
+    pub fn required_signer(&self) -> &str {
+        match self {
+            Self::Transaction {
+                required_signer, ..
+            }
+            | Self::Message {
+                required_signer, ..
+            } => required_signer,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignRequest {
+    pub request_id: String,
+    pub created_at: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<u64>,
+    /// Free text shown to the approver, e.g. what a withdrawal is for
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    #[serde(flatten)]
+    pub payload: SignPayload,
+}
+
+impl SignRequest {
+    pub fn new(payload: SignPayload, ttl: Option<Duration>) -> Self {
+        let created_at = now_unix_secs();
+        Self {
+            request_id: Uuid::new_v4().to_string(),
+            created_at,
+            expires_at: ttl.map(|ttl| created_at + ttl.as_secs()),
+            note: None,
+            payload,
+        }
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.note = Some(note.into());
+        self
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SignResponseStatus {
+    Approved,
+    Rejected,
+    Expired,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SignResponse {
+    pub request_id: String,
+    pub status: SignResponseStatus,
+    /// The matching `/api/external-sign/*` response body when approved
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub responded_at: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pairing_uri_round_trips() {
+        let desktop = StaticKey::generate();
+        let pairing = PairingUri {
+            topic: new_topic(),
+            public_key: desktop.public_key(),
+            relay_url: "http://127.0.0.1:8787/relay".to_string(),
+            name: Some("Withdrawal bot & co".to_string()),
+        };
+        let uri = pairing.to_string();
+        assert!(uri.starts_with(PAIRING_URI_PREFIX));
+        assert_eq!(PairingUri::parse(&uri).unwrap(), pairing);
+        assert!(PairingUri::parse(&uri.replace("v=1", "v=2")).is_err());
+        assert!(PairingUri::parse(&uri.replace("http%3A", "ftp%3A")).is_err());
+    }
+
+    #[test]
+    fn envelopes_are_bound_to_topic_and_direction() {
+        let desktop = StaticKey::generate();
+        let client = StaticKey::generate();
+        let topic = new_topic();
+        let envelope = Envelope::seal(
+            &client,
+            &desktop.public_key(),
+            &topic,
+            Direction::Request,
+            b"sign me",
+        )
+        .unwrap();
+        assert_eq!(envelope.sender, client.public_key());
+        assert_eq!(
+            envelope
+                .open(&desktop, &topic, Direction::Request)
+                .unwrap()
+                .as_slice(),
+            b"sign me"
+        );
+        assert!(envelope
+            .open(&desktop, &new_topic(), Direction::Request)
+            .is_err());
+        assert!(envelope
+            .open(&desktop, &topic, Direction::Response)
+            .is_err());
+        assert!(envelope
+            .open(&StaticKey::generate(), &topic, Direction::Request)
+            .is_err());
+
+        let restored = StaticKey::from_base64(&client.to_base64()).unwrap();
+        assert_eq!(restored.public_key(), client.public_key());
+        assert_ne!(
+            response_topic(&topic, &client.public_key()),
+            response_topic(&topic, &desktop.public_key())
+        );
+    }
+
+    #[test]
+    fn sign_request_is_tagged_by_kind() {
+        let request = SignRequest::new(
+            SignPayload::Message {
+                required_signer: "signer".to_string(),
+                message_base64: "aGk=".to_string(),
+            },
+            Some(Duration::from_secs(60)),
+        );
+        let value = serde_json::to_value(&request).unwrap();
+        assert_eq!(value["kind"], "message");
+        assert_eq!(value["required_signer"], "signer");
+        assert_eq!(request.expires_at, Some(request.created_at + 60));
+        assert_eq!(
+            serde_json::from_value::<SignRequest>(value).unwrap(),
+            request
+        );
+    }
+}
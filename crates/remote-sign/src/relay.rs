@@ -0,0 +1,302 @@
+//! Self-hostable store-and-forward relay
+//!
+//! Mailboxes are keyed by topic and hold sealed [`Envelope`]s in memory until the
+//! reader acknowledges them or they expire. Knowing a topic is the only credential;
+//! topics are 128-bit random values handed out inside pairing URIs.
+//!
+//! - `POST /v1/mailboxes/{topic}` appends an envelope and returns its `seq`
+//! - `GET /v1/mailboxes/{topic}?after=<seq>&wait=<secs>` lists newer envelopes,
+//!   long-polling up to 30 seconds when there are none yet
+//! - `DELETE /v1/mailboxes/{topic}?through=<seq>` drops acknowledged envelopes
+
+use crate::{is_valid_topic, Envelope};
+use axum::{
+    extract::{DefaultBodyLimit, Path, Query, State},
+    http::StatusCode,
+    routing::get,
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::{net::TcpListener, sync::watch};
+
+pub const DEFAULT_LISTEN_ADDR: &str = "127.0.0.1:8787";
+const MAX_ENVELOPE_BYTES: usize = 256 * 1024;
+const MAX_WAIT_SECS: u64 = 30;
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Debug)]
+pub struct RelayConfig {
+    pub message_ttl: Duration,
+    pub max_messages_per_mailbox: usize,
+    pub max_mailboxes: usize,
+}
+
+impl Default for RelayConfig {
+    fn default() -> Self {
+        Self {
+            message_ttl: Duration::from_secs(24 * 60 * 60),
+            max_messages_per_mailbox: 256,
+            max_mailboxes: 10_000,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RelayMessage {
+    pub seq: u64,
+    pub envelope: Envelope,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RelayMessages {
+    pub messages: Vec<RelayMessage>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RelayPosted {
+    pub seq: u64,
+}
+
+struct StoredMessage {
+    seq: u64,
+    stored_at: Instant,
+    envelope: Envelope,
+}
+
+struct Mailbox {
+    messages: VecDeque<StoredMessage>,
+    /// Latest assigned sequence number; readers subscribe to wait for new messages
+    latest: watch::Sender<u64>,
+}
+
+impl Mailbox {
+    fn new() -> Self {
+        Self {
+            messages: VecDeque::new(),
+            latest: watch::channel(0).0,
+        }
+    }
+
+    fn after(&self, after: u64) -> Vec<RelayMessage> {
+        self.messages
+            .iter()
+            .filter(|message| message.seq > after)
+            .map(|message| RelayMessage {
+                seq: message.seq,
+                envelope: message.envelope.clone(),
+            })
+            .collect()
+    }
+}
+
+struct RelayState {
+    config: RelayConfig,
+    mailboxes: Mutex<HashMap<String, Mailbox>>,
+}
+
+type RelayError = (StatusCode, String);
+
+impl RelayState {
+    fn with_mailbox<T>(
+        &self,
+        topic: &str,
+        create: bool,
+        f: impl FnOnce(&mut Mailbox) -> Result<T, RelayError>,
+    ) -> Result<Option<T>, RelayError> {
+        if !is_valid_topic(topic) {
+            return Err((StatusCode::BAD_REQUEST, "invalid topic".to_string()));
+        }
+        let mut mailboxes = self.mailboxes.lock().map_err(|_| {
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "relay state poisoned".to_string(),
+            )
+        })?;
+        if !mailboxes.contains_key(topic) {
+            if !create {
+                return Ok(None);
+            }
+            if mailboxes.len() >= self.config.max_mailboxes {
+                return Err((
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    "relay mailbox limit reached".to_string(),
+                ));
+            }
+            mailboxes.insert(topic.to_string(), Mailbox::new());
+        }
+        let mailbox = mailboxes.get_mut(topic).expect("mailbox was just inserted");
+        let ttl = self.config.message_ttl;
+        mailbox
+            .messages
+            .retain(|message| message.stored_at.elapsed() < ttl);
+        f(mailbox).map(Some)
+    }
+
+    fn sweep(&self) {
+        let Ok(mut mailboxes) = self.mailboxes.lock() else {
+            return;
+        };
+        let ttl = self.config.message_ttl;
+        mailboxes.retain(|_, mailbox| {
+            mailbox
+                .messages
+                .retain(|message| message.stored_at.elapsed() < ttl);
+            !mailbox.messages.is_empty() || mailbox.latest.receiver_count() > 0
+        });
+    }
+}
+
+async fn post_message(
+    State(state): State<Arc<RelayState>>,
+    Path(topic): Path<String>,
+    Json(envelope): Json<Envelope>,
+) -> Result<Json<RelayPosted>, RelayError> {
+    let limit = state.config.max_messages_per_mailbox;
+    let seq = state.with_mailbox(&topic, true, |mailbox| {
+        if mailbox.messages.len() >= limit {
+            return Err((StatusCode::TOO_MANY_REQUESTS, "mailbox is full".to_string()));
+        }
+        let seq = *mailbox.latest.borrow() + 1;
+        mailbox.messages.push_back(StoredMessage {
+            seq,
+            stored_at: Instant::now(),
+            envelope,
+        });
+        mailbox.latest.send_replace(seq);
+        Ok(seq)
+    })?;
+    Ok(Json(RelayPosted {
+        seq: seq.unwrap_or_default(),
+    }))
+}
+
+#[derive(Deserialize)]
+struct FetchQuery {
+    #[serde(default)]
+    after: u64,
+    #[serde(default)]
+    wait: u64,
+}
+
+async fn fetch_messages(
+    State(state): State<Arc<RelayState>>,
+    Path(topic): Path<String>,
+    Query(query): Query<FetchQuery>,
+) -> Result<Json<RelayMessages>, RelayError> {
+    let wait = query.wait.min(MAX_WAIT_SECS);
+    let ready = state.with_mailbox(&topic, wait > 0, |mailbox| {
+        let messages = mailbox.after(query.after);
+        Ok(if messages.is_empty() && wait > 0 {
+            Err(mailbox.latest.subscribe())
+        } else {
+            Ok(messages)
+        })
+    })?;
+    let mut latest = match ready {
+        None => return Ok(Json(RelayMessages::default())),
+        Some(Ok(messages)) => return Ok(Json(RelayMessages { messages })),
+        Some(Err(latest)) => latest,
+    };
+    let _ = tokio::time::timeout(Duration::from_secs(wait), latest.changed()).await;
+    drop(latest);
+    let messages = state
+        .with_mailbox(&topic, false, |mailbox| Ok(mailbox.after(query.after)))?
+        .unwrap_or_default();
+    Ok(Json(RelayMessages { messages }))
+}
+
+#[derive(Deserialize)]
+struct AckQuery {
+    through: u64,
+}
+
+async fn ack_messages(
+    State(state): State<Arc<RelayState>>,
+    Path(topic): Path<String>,
+    Query(query): Query<AckQuery>,
+) -> Result<StatusCode, RelayError> {
+    state.with_mailbox(&topic, false, |mailbox| {
+        mailbox
+            .messages
+            .retain(|message| message.seq > query.through);
+        Ok(())
+    })?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn health() -> &'static str {
+    "ok"
+}
+
+fn router(state: Arc<RelayState>) -> Router {
+    Router::new()
+        .route("/health", get(health))
+        .route(
+            "/v1/mailboxes/{topic}",
+            get(fetch_messages).post(post_message).delete(ack_messages),
+        )
+        .layer(DefaultBodyLimit::max(MAX_ENVELOPE_BYTES))
+        .with_state(state)
+}
+
+/// Serve the relay on `listener` until the process exits, sweeping expired mailboxes
+pub async fn serve(listener: TcpListener, config: RelayConfig) -> std::io::Result<()> {
+    let state = Arc::new(RelayState {
+        config,
+        mailboxes: Mutex::new(HashMap::new()),
+    });
+    let sweeper = state.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(SWEEP_INTERVAL);
+        loop {
+            interval.tick().await;
+            sweeper.sweep();
+        }
+    });
+    axum::serve(listener, router(state)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{new_topic, Direction, RelayClient, StaticKey};
+
+    #[tokio::test]
+    async fn long_poll_wakes_on_post_and_ack_drops_messages() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let client =
+            RelayClient::new(&format!("http://{}", listener.local_addr().unwrap())).unwrap();
+        tokio::spawn(serve(listener, RelayConfig::default()));
+
+        let topic = new_topic();
+        let key = StaticKey::generate();
+        let envelope =
+            Envelope::seal(&key, &key.public_key(), &topic, Direction::Request, b"x").unwrap();
+        let waiting = {
+            let client = client.clone();
+            let topic = topic.clone();
+            tokio::spawn(async move { client.fetch(&topic, 0, Duration::from_secs(10)).await })
+        };
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(client.post(&topic, &envelope).await.unwrap(), 1);
+        let started = Instant::now();
+        let messages = waiting.await.unwrap().unwrap();
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].envelope, envelope);
+
+        assert_eq!(client.post(&topic, &envelope).await.unwrap(), 2);
+        client.ack(&topic, 1).await.unwrap();
+        let remaining = client.fetch(&topic, 0, Duration::ZERO).await.unwrap();
+        assert_eq!(remaining.iter().map(|m| m.seq).collect::<Vec<_>>(), vec![2]);
+        assert!(client
+            .fetch("not-a-topic", 0, Duration::ZERO)
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,66 @@
+//! Rent reclamation for finished and stale multisig transactions.
+
+use crate::decode_account;
+use crate::instructions::{
+    config_transaction_accounts_close_ix, vault_transaction_accounts_close_ix,
+};
+use crate::state::{
+    ConfigTransaction, Multisig, Proposal, TransactionAccountKind, VaultTransaction,
+};
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey};
+
+/// Validates that the transaction at `transaction_index` can be closed and
+/// builds the matching close instruction. `transaction_data` and
+/// `proposal_data` are the raw account data at the transaction and proposal
+/// PDAs.
+pub fn transaction_accounts_close_ix(
+    multisig_key: &Pubkey,
+    multisig: &Multisig,
+    transaction_index: u64,
+    transaction_data: &[u8],
+    proposal_data: &[u8],
+) -> Result<(TransactionAccountKind, Instruction), String> {
+    let rent_collector = multisig.rent_collector.ok_or_else(|| {
+        "Squads multisig has no rent collector; rent cannot be reclaimed".to_string()
+    })?;
+    let kind = TransactionAccountKind::from_account_data(transaction_data).ok_or_else(|| {
+        format!("Squads transaction #{transaction_index} has an unknown account type")
+    })?;
+    let transaction_multisig = match kind {
+        TransactionAccountKind::Vault => {
+            decode_account::<VaultTransaction>(transaction_data, "VaultTransaction")?.multisig
+        }
+        TransactionAccountKind::Config => {
+            decode_account::<ConfigTransaction>(transaction_data, "ConfigTransaction")?.multisig
+        }
+        TransactionAccountKind::Batch => {
+            return Err(format!(
+                "Squads transaction #{transaction_index} is a batch; close its batch transactions first"
+            ))
+        }
+    };
+    if transaction_multisig != *multisig_key {
+        return Err(format!(
+            "Squads transaction #{transaction_index} belongs to another multisig"
+        ));
+    }
+    let proposal = decode_account::<Proposal>(proposal_data, "Proposal")?;
+    let is_stale = transaction_index <= multisig.stale_transaction_index;
+    let closable = match kind {
+        TransactionAccountKind::Vault => proposal.status.allows_vault_transaction_close(is_stale),
+        _ => proposal.status.allows_config_transaction_close(is_stale),
+    };
+    if !closable {
+        return Err(format!(
+            "Squads transaction #{transaction_index} is {} and cannot be closed yet",
+            proposal.status.label()
+        ));
+    }
+    let ix = match kind {
+        TransactionAccountKind::Vault => {
+            vault_transaction_accounts_close_ix(multisig_key, transaction_index, &rent_collector)
+        }
+        _ => config_transaction_accounts_close_ix(multisig_key, transaction_index, &rent_collector),
+    };
+    Ok((kind, ix))
+}
@@ -0,0 +1,428 @@
+//! Human-readable summaries of the instructions a vault transaction will run.
+//!
+//! Only the programs a treasury typically touches are decoded: System, SPL
+//! Token / Token-2022, Associated Token and the upgradeable BPF loader.
+//! Everything else is reported by program id and raw data length.
+
+use crate::{
+    ASSOCIATED_TOKEN_PROGRAM_ID, SYSTEM_PROGRAM_ID, TOKEN_2022_PROGRAM_ID, TOKEN_PROGRAM_ID,
+};
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey};
+
+pub const BPF_LOADER_UPGRADEABLE_PROGRAM_ID: Pubkey =
+    Pubkey::from_str_const("BPFLoaderUpgradeab1e11111111111111111111111");
+
+/// Size of the `UpgradeableLoaderState::Buffer` header that precedes the
+/// program bytes in a buffer account.
+const LOADER_BUFFER_METADATA_SIZE: usize = 37;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InstructionSummary {
+    pub program: String,
+    pub name: String,
+    pub fields: Vec<(String, String)>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum OutflowAsset {
+    Sol,
+    /// `mint`/`decimals` are only known for the checked token instructions;
+    /// a plain `transfer` leaves them to be resolved from the source account.
+    Token {
+        token_program: Pubkey,
+        mint: Option<Pubkey>,
+        decimals: Option<u8>,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VaultOutflow {
+    pub asset: OutflowAsset,
+    pub source: Pubkey,
+    pub destination: Pubkey,
+    pub amount: u64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProgramUpgradeTarget {
+    pub program: Pubkey,
+    pub buffer: Pubkey,
+    pub spill: Pubkey,
+}
+
+pub fn summarize_instruction(ix: &Instruction) -> InstructionSummary {
+    let summary = if ix.program_id == SYSTEM_PROGRAM_ID {
+        summarize_system(ix)
+    } else if is_token_program(&ix.program_id) {
+        summarize_token(ix)
+    } else if ix.program_id == ASSOCIATED_TOKEN_PROGRAM_ID {
+        summarize_associated_token(ix)
+    } else if ix.program_id == BPF_LOADER_UPGRADEABLE_PROGRAM_ID {
+        summarize_loader(ix)
+    } else {
+        None
+    };
+    summary.unwrap_or_else(|| InstructionSummary {
+        program: program_label(&ix.program_id),
+        name: "unknown".to_string(),
+        fields: vec![
+            ("program_id".to_string(), ix.program_id.to_string()),
+            ("data_len".to_string(), ix.data.len().to_string()),
+        ],
+    })
+}
+
+/// Lists the SOL and token amounts that leave `vault` when the instructions
+/// run. Only direct transfers signed by the vault are counted.
+pub fn vault_outflows(vault: &Pubkey, instructions: &[Instruction]) -> Vec<VaultOutflow> {
+    let mut outflows = Vec::new();
+    for ix in instructions {
+        if ix.program_id == SYSTEM_PROGRAM_ID {
+            let (Some(tag), Some(from), Some(to)) =
+                (read_u32(&ix.data, 0), account_at(ix, 0), account_at(ix, 1))
+            else {
+                continue;
+            };
+            // CreateAccount and Transfer both carry the lamports right after the tag.
+            if (tag == 0 || tag == 2) && from == *vault {
+                if let Some(lamports) = read_u64(&ix.data, 4) {
+                    outflows.push(VaultOutflow {
+                        asset: OutflowAsset::Sol,
+                        source: from,
+                        destination: to,
+                        amount: lamports,
+                    });
+                }
+            }
+        } else if is_token_program(&ix.program_id) {
+            let (source, mint, destination, authority, decimals) = match ix.data.first() {
+                Some(3) => (
+                    account_at(ix, 0),
+                    None,
+                    account_at(ix, 1),
+                    account_at(ix, 2),
+                    None,
+                ),
+                Some(12) => (
+                    account_at(ix, 0),
+                    account_at(ix, 1),
+                    account_at(ix, 2),
+                    account_at(ix, 3),
+                    ix.data.get(9).copied(),
+                ),
+                _ => continue,
+            };
+            let (Some(source), Some(destination), Some(amount)) =
+                (source, destination, read_u64(&ix.data, 1))
+            else {
+                continue;
+            };
+            if authority == Some(*vault) {
+                outflows.push(VaultOutflow {
+                    asset: OutflowAsset::Token {
+                        token_program: ix.program_id,
+                        mint,
+                        decimals,
+                    },
+                    source,
+                    destination,
+                    amount,
+                });
+            }
+        }
+    }
+    outflows
+}
+
+pub fn program_upgrade_targets(instructions: &[Instruction]) -> Vec<ProgramUpgradeTarget> {
+    instructions
+        .iter()
+        .filter(|ix| {
+            ix.program_id == BPF_LOADER_UPGRADEABLE_PROGRAM_ID && read_u32(&ix.data, 0) == Some(3)
+        })
+        .filter_map(|ix| {
+            Some(ProgramUpgradeTarget {
+                program: account_at(ix, 1)?,
+                buffer: account_at(ix, 2)?,
+                spill: account_at(ix, 3)?,
+            })
+        })
+        .collect()
+}
+
+/// Returns the program bytes held by an upgradeable loader buffer account.
+pub fn upgrade_buffer_program_bytes(data: &[u8]) -> Result<&[u8], String> {
+    if data.len() < LOADER_BUFFER_METADATA_SIZE || read_u32(data, 0) != Some(1) {
+        return Err("Account is not an upgradeable loader buffer".to_string());
+    }
+    Ok(&data[LOADER_BUFFER_METADATA_SIZE..])
+}
+
+fn summarize_system(ix: &Instruction) -> Option<InstructionSummary> {
+    let tag = read_u32(&ix.data, 0)?;
+    let summary = match tag {
+        0 => summary(
+            ix,
+            "createAccount",
+            vec![
+                account_field(ix, "from", 0),
+                account_field(ix, "new_account", 1),
+                ("lamports".to_string(), read_u64(&ix.data, 4)?.to_string()),
+                ("space".to_string(), read_u64(&ix.data, 12)?.to_string()),
+                ("owner".to_string(), read_pubkey(&ix.data, 20)?.to_string()),
+            ],
+        ),
+        1 => summary(
+            ix,
+            "assign",
+            vec![
+                account_field(ix, "account", 0),
+                ("owner".to_string(), read_pubkey(&ix.data, 4)?.to_string()),
+            ],
+        ),
+        2 => summary(
+            ix,
+            "transfer",
+            vec![
+                account_field(ix, "from", 0),
+                account_field(ix, "to", 1),
+                ("lamports".to_string(), read_u64(&ix.data, 4)?.to_string()),
+            ],
+        ),
+        8 => summary(
+            ix,
+            "allocate",
+            vec![
+                account_field(ix, "account", 0),
+                ("space".to_string(), read_u64(&ix.data, 4)?.to_string()),
+            ],
+        ),
+        _ => summary(ix, &format!("instruction {tag}"), Vec::new()),
+    };
+    Some(summary)
+}
+
+fn summarize_token(ix: &Instruction) -> Option<InstructionSummary> {
+    const NAMES: [&str; 21] = [
+        "initializeMint",
+        "initializeAccount",
+        "initializeMultisig",
+        "transfer",
+        "approve",
+        "revoke",
+        "setAuthority",
+        "mintTo",
+        "burn",
+        "closeAccount",
+        "freezeAccount",
+        "thawAccount",
+        "transferChecked",
+        "approveChecked",
+        "mintToChecked",
+        "burnChecked",
+        "initializeAccount2",
+        "syncNative",
+        "initializeAccount3",
+        "initializeMultisig2",
+        "initializeMint2",
+    ];
+    let tag = *ix.data.first()?;
+    let name = NAMES
+        .get(usize::from(tag))
+        .map(|name| name.to_string())
+        .unwrap_or_else(|| format!("instruction {tag}"));
+    let amount = || -> Option<(String, String)> {
+        Some(("amount".to_string(), read_u64(&ix.data, 1)?.to_string()))
+    };
+    let decimals = || -> Option<(String, String)> {
+        Some(("decimals".to_string(), ix.data.get(9)?.to_string()))
+    };
+    let fields = match tag {
+        3 => vec![
+            account_field(ix, "source", 0),
+            account_field(ix, "destination", 1),
+            account_field(ix, "authority", 2),
+            amount()?,
+        ],
+        4 => vec![
+            account_field(ix, "source", 0),
+            account_field(ix, "delegate", 1),
+            account_field(ix, "owner", 2),
+            amount()?,
+        ],
+        7 => vec![
+            account_field(ix, "mint", 0),
+            account_field(ix, "destination", 1),
+            account_field(ix, "authority", 2),
+            amount()?,
+        ],
+        8 => vec![
+            account_field(ix, "account", 0),
+            account_field(ix, "mint", 1),
+            account_field(ix, "authority", 2),
+            amount()?,
+        ],
+        9 => vec![
+            account_field(ix, "account", 0),
+            account_field(ix, "destination", 1),
+            account_field(ix, "authority", 2),
+        ],
+        12 => vec![
+            account_field(ix, "source", 0),
+            account_field(ix, "mint", 1),
+            account_field(ix, "destination", 2),
+            account_field(ix, "authority", 3),
+            amount()?,
+            decimals()?,
+        ],
+        13 => vec![
+            account_field(ix, "source", 0),
+            account_field(ix, "mint", 1),
+            account_field(ix, "delegate", 2),
+            account_field(ix, "owner", 3),
+            amount()?,
+            decimals()?,
+        ],
+        14 | 15 => vec![
+            account_field(ix, if tag == 14 { "mint" } else { "account" }, 0),
+            account_field(ix, if tag == 14 { "destination" } else { "mint" }, 1),
+            account_field(ix, "authority", 2),
+            amount()?,
+            decimals()?,
+        ],
+        _ => Vec::new(),
+    };
+    Some(summary(ix, &name, fields))
+}
+
+fn summarize_associated_token(ix: &Instruction) -> Option<InstructionSummary> {
+    let name = match ix.data.first() {
+        None | Some(0) => "create",
+        Some(1) => "createIdempotent",
+        Some(2) => "recoverNested",
+        Some(_) => return None,
+    };
+    Some(summary(
+        ix,
+        name,
+        vec![
+            account_field(ix, "payer", 0),
+            account_field(ix, "associated_account", 1),
+            account_field(ix, "owner", 2),
+            account_field(ix, "mint", 3),
+        ],
+    ))
+}
+
+fn summarize_loader(ix: &Instruction) -> Option<InstructionSummary> {
+    let tag = read_u32(&ix.data, 0)?;
+    let summary = match tag {
+        3 => summary(
+            ix,
+            "upgrade",
+            vec![
+                account_field(ix, "program_data", 0),
+                account_field(ix, "program", 1),
+                account_field(ix, "buffer", 2),
+                account_field(ix, "spill", 3),
+                account_field(ix, "authority", 6),
+            ],
+        ),
+        4 | 7 => summary(
+            ix,
+            if tag == 4 {
+                "setAuthority"
+            } else {
+                "setAuthorityChecked"
+            },
+            vec![
+                account_field(ix, "account", 0),
+                account_field(ix, "current_authority", 1),
+                account_field(ix, "new_authority", 2),
+            ],
+        ),
+        5 => summary(
+            ix,
+            "close",
+            vec![
+                account_field(ix, "account", 0),
+                account_field(ix, "recipient", 1),
+                account_field(ix, "authority", 2),
+            ],
+        ),
+        6 => summary(
+            ix,
+            "extendProgram",
+            vec![
+                account_field(ix, "program_data", 0),
+                account_field(ix, "program", 1),
+                (
+                    "additional_bytes".to_string(),
+                    read_u32(&ix.data, 4)?.to_string(),
+                ),
+            ],
+        ),
+        _ => summary(ix, &format!("instruction {tag}"), Vec::new()),
+    };
+    Some(summary)
+}
+
+fn summary(ix: &Instruction, name: &str, fields: Vec<(String, String)>) -> InstructionSummary {
+    InstructionSummary {
+        program: program_label(&ix.program_id),
+        name: name.to_string(),
+        fields: fields
+            .into_iter()
+            .filter(|(_, value)| !value.is_empty())
+            .collect(),
+    }
+}
+
+fn program_label(program_id: &Pubkey) -> String {
+    if *program_id == SYSTEM_PROGRAM_ID {
+        "System Program".to_string()
+    } else if *program_id == TOKEN_PROGRAM_ID {
+        "SPL Token".to_string()
+    } else if *program_id == TOKEN_2022_PROGRAM_ID {
+        "Token-2022".to_string()
+    } else if *program_id == ASSOCIATED_TOKEN_PROGRAM_ID {
+        "Associated Token".to_string()
+    } else if *program_id == BPF_LOADER_UPGRADEABLE_PROGRAM_ID {
+        "BPF Upgradeable Loader".to_string()
+    } else {
+        program_id.to_string()
+    }
+}
+
+fn is_token_program(program_id: &Pubkey) -> bool {
+    *program_id == TOKEN_PROGRAM_ID || *program_id == TOKEN_2022_PROGRAM_ID
+}
+
+fn account_at(ix: &Instruction, index: usize) -> Option<Pubkey> {
+    ix.accounts.get(index).map(|meta| meta.pubkey)
+}
+
+fn account_field(ix: &Instruction, name: &str, index: usize) -> (String, String) {
+    (
+        name.to_string(),
+        account_at(ix, index)
+            .map(|key| key.to_string())
+            .unwrap_or_default(),
+    )
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        data.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(
+        data.get(offset..offset + 8)?.try_into().ok()?,
+    ))
+}
+
+fn read_pubkey(data: &[u8], offset: usize) -> Option<Pubkey> {
+    Pubkey::try_from(data.get(offset..offset + 32)?).ok()
+}
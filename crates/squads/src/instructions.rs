@@ -0,0 +1,509 @@
+use crate::message::{
+    compile_transaction_message, message_accounts_for_execute, serialize_transaction_message_arg,
+    TransactionMessage,
+};
+use crate::pda::{
+    batch_transaction_pda, multisig_pda, program_config_pda, proposal_pda, transaction_pda,
+    vault_pda,
+};
+use crate::state::{Batch, Member, VaultBatchTransaction};
+use crate::{
+    anchor_data, anchor_data_empty, ASSOCIATED_TOKEN_PROGRAM_ID, SQUADS_PROGRAM_ID,
+    SYSTEM_PROGRAM_ID, TOKEN_PROGRAM_ID,
+};
+use borsh::BorshSerialize;
+use solana_loader_v3_interface::instruction as loader_v3_instruction;
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    message::AddressLookupTableAccount,
+    pubkey::Pubkey,
+};
+
+#[derive(BorshSerialize)]
+struct MultisigCreateArgsV2 {
+    config_authority: Option<Pubkey>,
+    threshold: u16,
+    members: Vec<Member>,
+    time_lock: u32,
+    rent_collector: Option<Pubkey>,
+    memo: Option<String>,
+}
+
+#[derive(BorshSerialize)]
+struct ProposalCreateArgs {
+    transaction_index: u64,
+    draft: bool,
+}
+
+#[derive(BorshSerialize)]
+struct ProposalVoteArgs {
+    memo: Option<String>,
+}
+
+#[derive(BorshSerialize)]
+struct VaultTransactionCreateArgs {
+    vault_index: u8,
+    ephemeral_signers: u8,
+    transaction_message: Vec<u8>,
+    memo: Option<String>,
+}
+
+#[derive(BorshSerialize)]
+struct BatchCreateArgs {
+    vault_index: u8,
+    memo: Option<String>,
+}
+
+#[derive(BorshSerialize)]
+struct BatchAddTransactionArgs {
+    ephemeral_signers: u8,
+    transaction_message: Vec<u8>,
+}
+
+pub fn multisig_create_ix(
+    create_key: &Pubkey,
+    creator: &Pubkey,
+    treasury: &Pubkey,
+    threshold: u16,
+    members: Vec<Member>,
+    time_lock: u32,
+    memo: Option<String>,
+) -> Result<(Instruction, Pubkey), String> {
+    let multisig = multisig_pda(create_key);
+    let accounts = vec![
+        AccountMeta::new_readonly(program_config_pda(), false),
+        AccountMeta::new(*treasury, false),
+        AccountMeta::new(multisig, false),
+        AccountMeta::new_readonly(*create_key, true),
+        AccountMeta::new(*creator, true),
+        AccountMeta::new_readonly(SYSTEM_PROGRAM_ID, false),
+    ];
+    let data = anchor_data(
+        "multisig_create_v2",
+        &MultisigCreateArgsV2 {
+            config_authority: None,
+            threshold,
+            members,
+            time_lock,
+            rent_collector: Some(*creator),
+            memo,
+        },
+    )?;
+    Ok((
+        Instruction {
+            program_id: SQUADS_PROGRAM_ID,
+            accounts,
+            data,
+        },
+        multisig,
+    ))
+}
+
+pub fn vault_transaction_create_ix(
+    multisig: &Pubkey,
+    creator: &Pubkey,
+    transaction_index: u64,
+    vault_index: u8,
+    inner_instructions: &[Instruction],
+    address_lookup_tables: &[AddressLookupTableAccount],
+    memo: Option<String>,
+) -> Result<(Instruction, Pubkey, Pubkey, TransactionMessage), String> {
+    let vault = vault_pda(multisig, vault_index);
+    let transaction = transaction_pda(multisig, transaction_index);
+    let message = compile_transaction_message(&vault, inner_instructions, address_lookup_tables)?;
+    let message_data = serialize_transaction_message_arg(&message)?;
+    let data = anchor_data(
+        "vault_transaction_create",
+        &VaultTransactionCreateArgs {
+            vault_index,
+            ephemeral_signers: 0,
+            transaction_message: message_data,
+            memo,
+        },
+    )?;
+    let accounts = vec![
+        AccountMeta::new(*multisig, false),
+        AccountMeta::new(transaction, false),
+        AccountMeta::new_readonly(*creator, true),
+        AccountMeta::new(*creator, true),
+        AccountMeta::new_readonly(SYSTEM_PROGRAM_ID, false),
+    ];
+    Ok((
+        Instruction {
+            program_id: SQUADS_PROGRAM_ID,
+            accounts,
+            data,
+        },
+        transaction,
+        vault,
+        message,
+    ))
+}
+
+pub fn proposal_create_ix(
+    multisig: &Pubkey,
+    creator: &Pubkey,
+    transaction_index: u64,
+    draft: bool,
+) -> Result<(Instruction, Pubkey), String> {
+    let proposal = proposal_pda(multisig, transaction_index);
+    let data = anchor_data(
+        "proposal_create",
+        &ProposalCreateArgs {
+            transaction_index,
+            draft,
+        },
+    )?;
+    let accounts = vec![
+        AccountMeta::new(*multisig, false),
+        AccountMeta::new(proposal, false),
+        AccountMeta::new_readonly(*creator, true),
+        AccountMeta::new(*creator, true),
+        AccountMeta::new_readonly(SYSTEM_PROGRAM_ID, false),
+    ];
+    Ok((
+        Instruction {
+            program_id: SQUADS_PROGRAM_ID,
+            accounts,
+            data,
+        },
+        proposal,
+    ))
+}
+
+pub fn proposal_approve_ix(
+    multisig: &Pubkey,
+    proposal: &Pubkey,
+    member: &Pubkey,
+    memo: Option<String>,
+) -> Result<Instruction, String> {
+    proposal_vote_ix("proposal_approve", multisig, proposal, member, memo)
+}
+
+pub fn proposal_reject_ix(
+    multisig: &Pubkey,
+    proposal: &Pubkey,
+    member: &Pubkey,
+    memo: Option<String>,
+) -> Result<Instruction, String> {
+    proposal_vote_ix("proposal_reject", multisig, proposal, member, memo)
+}
+
+fn proposal_vote_ix(
+    name: &str,
+    multisig: &Pubkey,
+    proposal: &Pubkey,
+    member: &Pubkey,
+    memo: Option<String>,
+) -> Result<Instruction, String> {
+    let accounts = vec![
+        AccountMeta::new(*multisig, false),
+        AccountMeta::new(*member, true),
+        AccountMeta::new(*proposal, false),
+    ];
+    Ok(Instruction {
+        program_id: SQUADS_PROGRAM_ID,
+        accounts,
+        data: anchor_data(name, &ProposalVoteArgs { memo })?,
+    })
+}
+
+pub fn proposal_activate_ix(multisig: &Pubkey, proposal: &Pubkey, member: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: SQUADS_PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new_readonly(*multisig, false),
+            AccountMeta::new(*member, true),
+            AccountMeta::new(*proposal, false),
+        ],
+        data: anchor_data_empty("proposal_activate"),
+    }
+}
+
+/// Cancels an approved proposal. Uses the v2 instruction, which can realloc
+/// the proposal when the cancel list grows.
+pub fn proposal_cancel_ix(
+    multisig: &Pubkey,
+    proposal: &Pubkey,
+    member: &Pubkey,
+    memo: Option<String>,
+) -> Result<Instruction, String> {
+    let mut ix = proposal_vote_ix("proposal_cancel_v2", multisig, proposal, member, memo)?;
+    ix.accounts
+        .push(AccountMeta::new_readonly(SYSTEM_PROGRAM_ID, false));
+    Ok(ix)
+}
+
+/// Closes the proposal and vault transaction accounts of a finished or stale
+/// transaction, returning the rent to the multisig's rent collector.
+pub fn vault_transaction_accounts_close_ix(
+    multisig: &Pubkey,
+    transaction_index: u64,
+    rent_collector: &Pubkey,
+) -> Instruction {
+    transaction_accounts_close_ix(
+        "vault_transaction_accounts_close",
+        multisig,
+        transaction_index,
+        rent_collector,
+    )
+}
+
+pub fn config_transaction_accounts_close_ix(
+    multisig: &Pubkey,
+    transaction_index: u64,
+    rent_collector: &Pubkey,
+) -> Instruction {
+    transaction_accounts_close_ix(
+        "config_transaction_accounts_close",
+        multisig,
+        transaction_index,
+        rent_collector,
+    )
+}
+
+fn transaction_accounts_close_ix(
+    name: &str,
+    multisig: &Pubkey,
+    transaction_index: u64,
+    rent_collector: &Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: SQUADS_PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new_readonly(*multisig, false),
+            AccountMeta::new(proposal_pda(multisig, transaction_index), false),
+            AccountMeta::new(transaction_pda(multisig, transaction_index), false),
+            AccountMeta::new(*rent_collector, false),
+            AccountMeta::new_readonly(SYSTEM_PROGRAM_ID, false),
+        ],
+        data: anchor_data_empty(name),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn vault_transaction_execute_ix(
+    multisig: &Pubkey,
+    transaction: &Pubkey,
+    proposal: &Pubkey,
+    member: &Pubkey,
+    message: &TransactionMessage,
+    vault_index: u8,
+    ephemeral_signers: u8,
+    address_lookup_tables: &[AddressLookupTableAccount],
+) -> Result<Instruction, String> {
+    let mut accounts = vec![
+        AccountMeta::new(*multisig, false),
+        AccountMeta::new(*proposal, false),
+        AccountMeta::new_readonly(*transaction, false),
+        AccountMeta::new_readonly(*member, true),
+    ];
+    accounts.extend(message_accounts_for_execute(
+        message,
+        multisig,
+        transaction,
+        vault_index,
+        ephemeral_signers,
+        address_lookup_tables,
+    )?);
+    Ok(Instruction {
+        program_id: SQUADS_PROGRAM_ID,
+        accounts,
+        data: anchor_data_empty("vault_transaction_execute"),
+    })
+}
+
+pub fn batch_create_ix(
+    multisig: &Pubkey,
+    creator: &Pubkey,
+    batch_index: u64,
+    vault_index: u8,
+    memo: Option<String>,
+) -> Result<(Instruction, Pubkey), String> {
+    let batch = transaction_pda(multisig, batch_index);
+    let data = anchor_data("batch_create", &BatchCreateArgs { vault_index, memo })?;
+    Ok((
+        Instruction {
+            program_id: SQUADS_PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new(*multisig, false),
+                AccountMeta::new(batch, false),
+                AccountMeta::new_readonly(*creator, true),
+                AccountMeta::new(*creator, true),
+                AccountMeta::new_readonly(SYSTEM_PROGRAM_ID, false),
+            ],
+            data,
+        },
+        batch,
+    ))
+}
+
+/// Adds the next transaction to a draft batch. `transaction_index` is the
+/// 1-based position inside the batch, i.e. the batch size after this call.
+#[allow(clippy::too_many_arguments)]
+pub fn batch_add_transaction_ix(
+    multisig: &Pubkey,
+    member: &Pubkey,
+    batch_index: u64,
+    transaction_index: u32,
+    vault_index: u8,
+    inner_instructions: &[Instruction],
+    address_lookup_tables: &[AddressLookupTableAccount],
+) -> Result<(Instruction, Pubkey, TransactionMessage), String> {
+    let vault = vault_pda(multisig, vault_index);
+    let batch = transaction_pda(multisig, batch_index);
+    let proposal = proposal_pda(multisig, batch_index);
+    let transaction = batch_transaction_pda(multisig, batch_index, transaction_index);
+    let message = compile_transaction_message(&vault, inner_instructions, address_lookup_tables)?;
+    let data = anchor_data(
+        "batch_add_transaction",
+        &BatchAddTransactionArgs {
+            ephemeral_signers: 0,
+            transaction_message: serialize_transaction_message_arg(&message)?,
+        },
+    )?;
+    Ok((
+        Instruction {
+            program_id: SQUADS_PROGRAM_ID,
+            accounts: vec![
+                AccountMeta::new_readonly(*multisig, false),
+                AccountMeta::new_readonly(proposal, false),
+                AccountMeta::new(batch, false),
+                AccountMeta::new(transaction, false),
+                AccountMeta::new_readonly(*member, true),
+                AccountMeta::new(*member, true),
+                AccountMeta::new_readonly(SYSTEM_PROGRAM_ID, false),
+            ],
+            data,
+        },
+        transaction,
+        message,
+    ))
+}
+
+/// Executes the next pending transaction of an approved batch. The caller
+/// passes the batch account as read from chain so the PDA of the
+/// transaction at `executed_transaction_index + 1` can be derived.
+pub fn batch_execute_transaction_ix(
+    multisig: &Pubkey,
+    member: &Pubkey,
+    batch: &Batch,
+    batch_transaction: &VaultBatchTransaction,
+    address_lookup_tables: &[AddressLookupTableAccount],
+) -> Result<Instruction, String> {
+    let batch_key = transaction_pda(multisig, batch.index);
+    let proposal = proposal_pda(multisig, batch.index);
+    let next_index = batch
+        .executed_transaction_index
+        .checked_add(1)
+        .filter(|index| *index <= batch.size)
+        .ok_or_else(|| "All transactions in this Squads batch have been executed".to_string())?;
+    let transaction = batch_transaction_pda(multisig, batch.index, next_index);
+    let mut accounts = vec![
+        AccountMeta::new_readonly(*multisig, false),
+        AccountMeta::new_readonly(*member, true),
+        AccountMeta::new(proposal, false),
+        AccountMeta::new(batch_key, false),
+        AccountMeta::new_readonly(transaction, false),
+    ];
+    let ephemeral_signers = u8::try_from(batch_transaction.ephemeral_signer_bumps.len())
+        .map_err(|_| "Squads ephemeral signer count is out of range".to_string())?;
+    accounts.extend(message_accounts_for_execute(
+        &batch_transaction.message,
+        multisig,
+        &transaction,
+        batch.vault_index,
+        ephemeral_signers,
+        address_lookup_tables,
+    )?);
+    Ok(Instruction {
+        program_id: SQUADS_PROGRAM_ID,
+        accounts,
+        data: anchor_data_empty("batch_execute_transaction"),
+    })
+}
+
+pub fn sol_transfer_ix(from: &Pubkey, to: &Pubkey, lamports: u64) -> Instruction {
+    let mut data = vec![2, 0, 0, 0];
+    data.extend_from_slice(&lamports.to_le_bytes());
+    Instruction {
+        program_id: SYSTEM_PROGRAM_ID,
+        accounts: vec![AccountMeta::new(*from, true), AccountMeta::new(*to, false)],
+        data,
+    }
+}
+
+pub fn token_transfer_checked_ix(
+    source: &Pubkey,
+    mint: &Pubkey,
+    destination: &Pubkey,
+    authority: &Pubkey,
+    amount: u64,
+    decimals: u8,
+) -> Instruction {
+    let mut data = vec![12];
+    data.extend_from_slice(&amount.to_le_bytes());
+    data.push(decimals);
+    Instruction {
+        program_id: TOKEN_PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(*source, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new(*destination, false),
+            AccountMeta::new_readonly(*authority, true),
+        ],
+        data,
+    }
+}
+
+pub fn associated_token_address(owner: &Pubkey, mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[owner.as_ref(), TOKEN_PROGRAM_ID.as_ref(), mint.as_ref()],
+        &ASSOCIATED_TOKEN_PROGRAM_ID,
+    )
+    .0
+}
+
+pub fn create_associated_token_account_idempotent_ix(
+    payer: &Pubkey,
+    owner: &Pubkey,
+    mint: &Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: ASSOCIATED_TOKEN_PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(*payer, true),
+            AccountMeta::new(associated_token_address(owner, mint), false),
+            AccountMeta::new_readonly(*owner, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new_readonly(SYSTEM_PROGRAM_ID, false),
+            AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+        ],
+        data: vec![1],
+    }
+}
+
+pub fn upgrade_program_ix(
+    program_id: &Pubkey,
+    buffer: &Pubkey,
+    authority: &Pubkey,
+    spill: &Pubkey,
+) -> Instruction {
+    loader_v3_instruction::upgrade(program_id, buffer, authority, spill)
+}
+
+pub fn set_program_upgrade_authority_ix(
+    program_id: &Pubkey,
+    current_authority: &Pubkey,
+    new_authority: &Pubkey,
+) -> Instruction {
+    loader_v3_instruction::set_upgrade_authority(program_id, current_authority, Some(new_authority))
+}
+
+pub fn set_buffer_authority_ix(
+    buffer: &Pubkey,
+    current_authority: &Pubkey,
+    new_authority: &Pubkey,
+) -> Instruction {
+    loader_v3_instruction::set_buffer_authority(buffer, current_authority, new_authority)
+}
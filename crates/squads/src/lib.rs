@@ -0,0 +1,584 @@
+//! Squads v4 support shared by the desktop API, app services and mobile bridge.
+//!
+//! The crate holds the on-chain account layouts, PDA derivations, instruction
+//! builders and the vault transaction message codec. It only builds and decodes
+//! data; RPC access and signing stay with the calling surface.
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use sha2::{Digest, Sha256};
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+
+mod close;
+mod inspect;
+mod instructions;
+mod message;
+mod pda;
+mod state;
+
+pub use close::*;
+pub use inspect::*;
+pub use instructions::*;
+pub use message::*;
+pub use pda::*;
+pub use state::*;
+
+pub const SQUADS_PROGRAM_ID: Pubkey =
+    Pubkey::from_str_const("SQDS4ep65T869zMMBKyuUq6aD6EgTu8psMjkvj52pCf");
+pub const SYSTEM_PROGRAM_ID: Pubkey = Pubkey::from_str_const("11111111111111111111111111111111");
+pub const TOKEN_PROGRAM_ID: Pubkey =
+    Pubkey::from_str_const("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+pub const TOKEN_2022_PROGRAM_ID: Pubkey =
+    Pubkey::from_str_const("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+pub const ASSOCIATED_TOKEN_PROGRAM_ID: Pubkey =
+    Pubkey::from_str_const("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL");
+
+pub fn parse_pubkey(value: &str, field: &str) -> Result<Pubkey, String> {
+    Pubkey::from_str(value.trim()).map_err(|_| format!("Invalid {field}"))
+}
+
+pub fn account_discriminator(name: &str) -> [u8; 8] {
+    anchor_discriminator("account", name)
+}
+
+pub fn instruction_discriminator(name: &str) -> [u8; 8] {
+    anchor_discriminator("global", name)
+}
+
+fn anchor_discriminator(namespace: &str, name: &str) -> [u8; 8] {
+    let mut hasher = Sha256::new();
+    hasher.update(format!("{namespace}:{name}").as_bytes());
+    let hash = hasher.finalize();
+    let mut out = [0_u8; 8];
+    out.copy_from_slice(&hash[..8]);
+    out
+}
+
+pub(crate) fn anchor_data<T: BorshSerialize>(name: &str, args: &T) -> Result<Vec<u8>, String> {
+    let mut data = instruction_discriminator(name).to_vec();
+    let mut args_data =
+        borsh::to_vec(args).map_err(|e| format!("Serialize Squads instruction failed: {e}"))?;
+    data.append(&mut args_data);
+    Ok(data)
+}
+
+pub(crate) fn anchor_data_empty(name: &str) -> Vec<u8> {
+    instruction_discriminator(name).to_vec()
+}
+
+pub fn decode_account<T: BorshDeserialize>(data: &[u8], name: &str) -> Result<T, String> {
+    let discriminator = account_discriminator(name);
+    if data.len() < 8 || data[..8] != discriminator {
+        return Err(format!("Not a valid Squads {name} account"));
+    }
+    // Squads only grows accounts on realloc, so a multisig that dropped members
+    // keeps trailing bytes that must not fail the decode.
+    T::deserialize(&mut &data[8..]).map_err(|e| format!("Decode Squads {name} account failed: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::instruction::{AccountMeta, Instruction};
+    use solana_sdk::message::AddressLookupTableAccount;
+
+    // Discriminators as published in the Squads v4 IDL and TypeScript SDK.
+    #[test]
+    fn anchor_discriminators_match_squads_idl() {
+        assert_eq!(
+            account_discriminator("Multisig"),
+            [224, 116, 121, 186, 68, 161, 79, 236]
+        );
+        assert_eq!(
+            account_discriminator("Proposal"),
+            [26, 94, 189, 187, 116, 136, 53, 33]
+        );
+        assert_eq!(
+            account_discriminator("VaultTransaction"),
+            [168, 250, 162, 100, 81, 14, 162, 207]
+        );
+        assert_eq!(
+            account_discriminator("Batch"),
+            [156, 194, 70, 44, 22, 88, 137, 44]
+        );
+        assert_eq!(
+            instruction_discriminator("vault_transaction_create"),
+            [48, 250, 78, 168, 208, 226, 218, 211]
+        );
+        assert_eq!(
+            instruction_discriminator("proposal_approve"),
+            [144, 37, 164, 136, 188, 216, 42, 248]
+        );
+        assert_eq!(
+            instruction_discriminator("vault_transaction_execute"),
+            [194, 8, 161, 87, 153, 164, 25, 171]
+        );
+    }
+
+    #[test]
+    fn program_config_pda_matches_mainnet() {
+        assert_eq!(
+            program_config_pda().to_string(),
+            "BSTq9w3kZwNwpBXJEvTZz2G9ZTNyKBvoSeXMvwb4cNZr"
+        );
+    }
+
+    #[test]
+    fn decodes_multisig_account_golden_bytes() {
+        let create_key = Pubkey::new_from_array([1; 32]);
+        let member = Pubkey::new_from_array([2; 32]);
+        let rent_collector = Pubkey::new_from_array([3; 32]);
+        let mut data = account_discriminator("Multisig").to_vec();
+        data.extend_from_slice(create_key.as_ref());
+        data.extend_from_slice(Pubkey::default().as_ref());
+        data.extend_from_slice(&2_u16.to_le_bytes());
+        data.extend_from_slice(&0_u32.to_le_bytes());
+        data.extend_from_slice(&7_u64.to_le_bytes());
+        data.extend_from_slice(&5_u64.to_le_bytes());
+        data.push(1);
+        data.extend_from_slice(rent_collector.as_ref());
+        data.push(254);
+        data.extend_from_slice(&1_u32.to_le_bytes());
+        data.extend_from_slice(member.as_ref());
+        data.push(0b111);
+        // Reserved space left by the account allocation.
+        data.extend_from_slice(&[0; 32]);
+
+        let multisig = decode_account::<Multisig>(&data, "Multisig").unwrap();
+        assert_eq!(multisig.create_key, create_key);
+        assert_eq!(multisig.threshold, 2);
+        assert_eq!(multisig.transaction_index, 7);
+        assert_eq!(multisig.stale_transaction_index, 5);
+        assert_eq!(multisig.rent_collector, Some(rent_collector));
+        assert_eq!(multisig.bump, 254);
+        assert_eq!(multisig.members.len(), 1);
+        assert_eq!(multisig.members[0].key, member);
+        assert_eq!(multisig.members[0].permissions.mask, 0b111);
+        assert!(decode_account::<Multisig>(&data, "Proposal").is_err());
+    }
+
+    #[test]
+    fn serializes_transaction_message_in_compact_layout() {
+        let vault = Pubkey::new_from_array([9; 32]);
+        let to = Pubkey::new_from_array([4; 32]);
+        let message =
+            compile_transaction_message(&vault, &[sol_transfer_ix(&vault, &to, 5)], &[]).unwrap();
+        let bytes = serialize_transaction_message_arg(&message).unwrap();
+
+        let mut expected = vec![1, 1, 1, 3];
+        expected.extend_from_slice(vault.as_ref());
+        expected.extend_from_slice(to.as_ref());
+        expected.extend_from_slice(SYSTEM_PROGRAM_ID.as_ref());
+        expected.extend_from_slice(&[1, 2, 2, 0, 1, 12, 0]);
+        expected.extend_from_slice(&[2, 0, 0, 0]);
+        expected.extend_from_slice(&5_u64.to_le_bytes());
+        expected.push(0);
+        assert_eq!(bytes, expected);
+
+        let decoded = deserialize_transaction_message_arg(&bytes).unwrap();
+        assert_eq!(decoded.account_keys, message.account_keys);
+        assert_eq!(decoded.instructions.len(), 1);
+        assert_eq!(decoded.instructions[0].data, message.instructions[0].data);
+    }
+
+    #[test]
+    fn lookup_table_accounts_round_trip_through_decoder() {
+        let vault = Pubkey::new_from_array([9; 32]);
+        let program = Pubkey::new_from_array([5; 32]);
+        let writable = Pubkey::new_from_array([6; 32]);
+        let readonly = Pubkey::new_from_array([7; 32]);
+        let table = AddressLookupTableAccount {
+            key: Pubkey::new_from_array([8; 32]),
+            addresses: vec![readonly, program, writable],
+        };
+        let instruction = Instruction {
+            program_id: program,
+            accounts: vec![
+                AccountMeta::new(vault, true),
+                AccountMeta::new(writable, false),
+                AccountMeta::new_readonly(readonly, false),
+            ],
+            data: vec![1, 2, 3],
+        };
+        let message = compile_transaction_message(
+            &vault,
+            std::slice::from_ref(&instruction),
+            std::slice::from_ref(&table),
+        )
+        .unwrap();
+
+        assert_eq!(message.account_keys, vec![vault, program]);
+        assert_eq!(message.address_table_lookups.len(), 1);
+        assert_eq!(message.address_table_lookups[0].writable_indexes, vec![2]);
+        assert_eq!(message.address_table_lookups[0].readonly_indexes, vec![0]);
+
+        let decoded = decode_transaction_message(&message, std::slice::from_ref(&table)).unwrap();
+        assert_eq!(decoded.instructions, vec![instruction]);
+        assert_eq!(
+            decoded.accounts[2].source,
+            MessageAccountSource::Lookup {
+                table: table.key,
+                index: 2
+            }
+        );
+        assert!(decode_transaction_message(&message, &[]).is_err());
+    }
+
+    #[test]
+    fn compile_splits_accounts_between_static_keys_and_lookup_tables() {
+        let key = |byte: u8| Pubkey::new_from_array([byte; 32]);
+        let (vault, signer, static_readonly) = (key(9), key(10), key(11));
+        let (program, other_program) = (key(12), key(13));
+        let (writable, readonly, second_writable) = (key(14), key(15), key(16));
+        let first = AddressLookupTableAccount {
+            key: key(20),
+            // Programs and signers must stay static even when a table holds them.
+            addresses: vec![program, readonly, writable, signer],
+        };
+        let second = AddressLookupTableAccount {
+            key: key(21),
+            // `writable` was already taken from the first table.
+            addresses: vec![writable, second_writable],
+        };
+        let instructions = vec![
+            Instruction {
+                program_id: program,
+                accounts: vec![
+                    AccountMeta::new(vault, true),
+                    AccountMeta::new_readonly(signer, true),
+                    AccountMeta::new(writable, false),
+                    AccountMeta::new_readonly(readonly, false),
+                    AccountMeta::new_readonly(static_readonly, false),
+                ],
+                data: vec![7],
+            },
+            Instruction {
+                program_id: other_program,
+                accounts: vec![
+                    AccountMeta::new(second_writable, false),
+                    AccountMeta::new(writable, false),
+                ],
+                data: vec![8, 9],
+            },
+        ];
+        let tables = [first.clone(), second.clone()];
+        let message = compile_transaction_message(&vault, &instructions, &tables).unwrap();
+
+        assert_eq!(
+            message.account_keys,
+            vec![vault, signer, static_readonly, program, other_program]
+        );
+        assert_eq!(
+            (
+                message.num_signers,
+                message.num_writable_signers,
+                message.num_writable_non_signers
+            ),
+            (2, 1, 0)
+        );
+        let lookups: Vec<_> = message
+            .address_table_lookups
+            .iter()
+            .map(|lookup| {
+                (
+                    lookup.account_key,
+                    lookup.writable_indexes.clone(),
+                    lookup.readonly_indexes.clone(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            lookups,
+            vec![(first.key, vec![2], vec![1]), (second.key, vec![1], vec![])]
+        );
+        // Loaded writable accounts follow the static keys, then loaded readonly ones.
+        assert_eq!(message.instructions[0].program_id_index, 3);
+        assert_eq!(message.instructions[0].account_indexes, vec![0, 1, 5, 7, 2]);
+        assert_eq!(message.instructions[1].program_id_index, 4);
+        assert_eq!(message.instructions[1].account_indexes, vec![6, 5]);
+
+        let decoded = decode_transaction_message(&message, &tables).unwrap();
+        assert_eq!(decoded.instructions, instructions);
+    }
+
+    #[test]
+    fn compile_rejects_lookup_indexes_beyond_u8() {
+        let vault = Pubkey::new_from_array([9; 32]);
+        let target = Pubkey::new_unique();
+        let mut addresses: Vec<_> = (0..256).map(|_| Pubkey::new_unique()).collect();
+        addresses.push(target);
+        let table = AddressLookupTableAccount {
+            key: Pubkey::new_unique(),
+            addresses,
+        };
+        assert!(compile_transaction_message(
+            &vault,
+            &[sol_transfer_ix(&vault, &target, 1)],
+            &[table]
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn batch_create_encodes_vault_index_and_memo() {
+        let multisig = Pubkey::new_from_array([1; 32]);
+        let creator = Pubkey::new_from_array([2; 32]);
+        let (ix, batch) = batch_create_ix(&multisig, &creator, 4, 1, Some("memo".into())).unwrap();
+
+        assert_eq!(batch, transaction_pda(&multisig, 4));
+        assert_eq!(
+            ix.accounts,
+            vec![
+                AccountMeta::new(multisig, false),
+                AccountMeta::new(batch, false),
+                AccountMeta::new_readonly(creator, true),
+                AccountMeta::new(creator, true),
+                AccountMeta::new_readonly(SYSTEM_PROGRAM_ID, false),
+            ]
+        );
+        let mut data = instruction_discriminator("batch_create").to_vec();
+        data.extend_from_slice(&[1, 1]);
+        data.extend_from_slice(&4_u32.to_le_bytes());
+        data.extend_from_slice(b"memo");
+        assert_eq!(ix.data, data);
+    }
+
+    #[test]
+    fn batch_add_transaction_wraps_compact_message() {
+        let multisig = Pubkey::new_from_array([1; 32]);
+        let member = Pubkey::new_from_array([2; 32]);
+        let to = Pubkey::new_from_array([4; 32]);
+        let vault = vault_pda(&multisig, 1);
+        let (ix, transaction, message) = batch_add_transaction_ix(
+            &multisig,
+            &member,
+            4,
+            2,
+            1,
+            &[sol_transfer_ix(&vault, &to, 5)],
+            &[],
+        )
+        .unwrap();
+
+        assert_eq!(transaction, batch_transaction_pda(&multisig, 4, 2));
+        assert_eq!(message.account_keys, vec![vault, to, SYSTEM_PROGRAM_ID]);
+        assert_eq!(
+            ix.accounts,
+            vec![
+                AccountMeta::new_readonly(multisig, false),
+                AccountMeta::new_readonly(proposal_pda(&multisig, 4), false),
+                AccountMeta::new(transaction_pda(&multisig, 4), false),
+                AccountMeta::new(transaction, false),
+                AccountMeta::new_readonly(member, true),
+                AccountMeta::new(member, true),
+                AccountMeta::new_readonly(SYSTEM_PROGRAM_ID, false),
+            ]
+        );
+        let compact = serialize_transaction_message_arg(&message).unwrap();
+        let mut data = instruction_discriminator("batch_add_transaction").to_vec();
+        data.push(0);
+        data.extend_from_slice(&(compact.len() as u32).to_le_bytes());
+        data.extend_from_slice(&compact);
+        assert_eq!(ix.data, data);
+    }
+
+    #[test]
+    fn batch_execute_targets_next_transaction() {
+        let multisig = Pubkey::new_from_array([1; 32]);
+        let member = Pubkey::new_from_array([2; 32]);
+        let to = Pubkey::new_from_array([4; 32]);
+        let vault = vault_pda(&multisig, 1);
+        let mut batch = Batch {
+            multisig,
+            creator: member,
+            index: 4,
+            bump: 255,
+            vault_index: 1,
+            vault_bump: 254,
+            size: 2,
+            executed_transaction_index: 1,
+        };
+        let batch_transaction = VaultBatchTransaction {
+            bump: 253,
+            ephemeral_signer_bumps: Vec::new(),
+            message: compile_transaction_message(&vault, &[sol_transfer_ix(&vault, &to, 5)], &[])
+                .unwrap(),
+        };
+        let ix = batch_execute_transaction_ix(&multisig, &member, &batch, &batch_transaction, &[])
+            .unwrap();
+
+        assert_eq!(
+            ix.accounts,
+            vec![
+                AccountMeta::new_readonly(multisig, false),
+                AccountMeta::new_readonly(member, true),
+                AccountMeta::new(proposal_pda(&multisig, 4), false),
+                AccountMeta::new(transaction_pda(&multisig, 4), false),
+                AccountMeta::new_readonly(batch_transaction_pda(&multisig, 4, 2), false),
+                AccountMeta::new(vault, false),
+                AccountMeta::new(to, false),
+                AccountMeta::new_readonly(SYSTEM_PROGRAM_ID, false),
+            ]
+        );
+        assert_eq!(
+            ix.data,
+            instruction_discriminator("batch_execute_transaction")
+        );
+
+        batch.executed_transaction_index = 2;
+        assert!(
+            batch_execute_transaction_ix(&multisig, &member, &batch, &batch_transaction, &[])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn execute_accounts_put_lookup_tables_first() {
+        let multisig = Pubkey::new_from_array([1; 32]);
+        let vault = vault_pda(&multisig, 0);
+        let writable = Pubkey::new_from_array([6; 32]);
+        let table = AddressLookupTableAccount {
+            key: Pubkey::new_from_array([8; 32]),
+            addresses: vec![writable],
+        };
+        let message = compile_transaction_message(
+            &vault,
+            &[sol_transfer_ix(&vault, &writable, 1)],
+            std::slice::from_ref(&table),
+        )
+        .unwrap();
+        let ix = vault_transaction_execute_ix(
+            &multisig,
+            &transaction_pda(&multisig, 1),
+            &proposal_pda(&multisig, 1),
+            &Pubkey::new_from_array([2; 32]),
+            &message,
+            0,
+            0,
+            std::slice::from_ref(&table),
+        )
+        .unwrap();
+        let remaining = &ix.accounts[4..];
+        assert_eq!(remaining[0], AccountMeta::new_readonly(table.key, false));
+        assert_eq!(remaining[1], AccountMeta::new(vault, false));
+        assert_eq!(
+            remaining[2],
+            AccountMeta::new_readonly(SYSTEM_PROGRAM_ID, false)
+        );
+        assert_eq!(remaining[3], AccountMeta::new(writable, false));
+    }
+    #[test]
+    fn reports_vault_outflows_and_upgrade_targets() {
+        let vault = Pubkey::new_from_array([9; 32]);
+        let other = Pubkey::new_from_array([3; 32]);
+        let to = Pubkey::new_from_array([4; 32]);
+        let mint = Pubkey::new_from_array([5; 32]);
+        let program = Pubkey::new_from_array([6; 32]);
+        let buffer = Pubkey::new_from_array([7; 32]);
+        let instructions = vec![
+            sol_transfer_ix(&vault, &to, 5),
+            sol_transfer_ix(&other, &to, 7),
+            token_transfer_checked_ix(&other, &mint, &to, &vault, 42, 6),
+            upgrade_program_ix(&program, &buffer, &vault, &vault),
+        ];
+
+        let outflows = vault_outflows(&vault, &instructions);
+        assert_eq!(outflows.len(), 2);
+        assert_eq!(outflows[0].asset, OutflowAsset::Sol);
+        assert_eq!(outflows[0].amount, 5);
+        assert_eq!(
+            outflows[1].asset,
+            OutflowAsset::Token {
+                token_program: TOKEN_PROGRAM_ID,
+                mint: Some(mint),
+                decimals: Some(6),
+            }
+        );
+        assert_eq!(outflows[1].amount, 42);
+
+        let summary = summarize_instruction(&instructions[2]);
+        assert_eq!(summary.program, "SPL Token");
+        assert_eq!(summary.name, "transferChecked");
+        assert!(summary
+            .fields
+            .contains(&("amount".to_string(), "42".to_string())));
+
+        assert_eq!(
+            program_upgrade_targets(&instructions),
+            vec![ProgramUpgradeTarget {
+                program,
+                buffer,
+                spill: vault,
+            }]
+        );
+        let mut buffer_data = vec![1, 0, 0, 0, 1];
+        buffer_data.extend_from_slice(vault.as_ref());
+        buffer_data.extend_from_slice(&[0x7f, b'E', b'L', b'F']);
+        assert_eq!(
+            upgrade_buffer_program_bytes(&buffer_data).unwrap(),
+            b"\x7fELF"
+        );
+        assert!(upgrade_buffer_program_bytes(&[2, 0, 0, 0]).is_err());
+    }
+    #[test]
+    fn close_requires_finished_or_stale_transactions() {
+        let multisig_key = Pubkey::new_from_array([1; 32]);
+        let rent_collector = Pubkey::new_from_array([2; 32]);
+        let multisig = Multisig {
+            create_key: Pubkey::new_from_array([3; 32]),
+            config_authority: Pubkey::default(),
+            threshold: 1,
+            time_lock: 0,
+            transaction_index: 3,
+            stale_transaction_index: 1,
+            rent_collector: Some(rent_collector),
+            bump: 255,
+            members: Vec::new(),
+        };
+        let mut transaction = account_discriminator("VaultTransaction").to_vec();
+        transaction.extend_from_slice(multisig_key.as_ref());
+        transaction.extend_from_slice(&[0; 32 + 8 + 3]);
+        // Empty ephemeral signers, then an empty message.
+        transaction.extend_from_slice(&[0; 4 + 3 + 4 * 3]);
+        let proposal = |status: u8| {
+            let mut data = account_discriminator("Proposal").to_vec();
+            data.extend_from_slice(multisig_key.as_ref());
+            data.extend_from_slice(&2_u64.to_le_bytes());
+            data.push(status);
+            data.extend_from_slice(&0_i64.to_le_bytes());
+            data.push(255);
+            data.extend_from_slice(&[0; 4 * 3]);
+            data
+        };
+
+        // Active and not stale: still votable.
+        assert!(transaction_accounts_close_ix(
+            &multisig_key,
+            &multisig,
+            2,
+            &transaction,
+            &proposal(1)
+        )
+        .is_err());
+        // Active but stale, and executed: both closable.
+        assert!(transaction_accounts_close_ix(
+            &multisig_key,
+            &multisig,
+            1,
+            &transaction,
+            &proposal(1)
+        )
+        .is_ok());
+        let (kind, ix) =
+            transaction_accounts_close_ix(&multisig_key, &multisig, 2, &transaction, &proposal(5))
+                .unwrap();
+        assert_eq!(kind, TransactionAccountKind::Vault);
+        assert_eq!(ix.accounts[1].pubkey, proposal_pda(&multisig_key, 2));
+        assert_eq!(ix.accounts[2].pubkey, transaction_pda(&multisig_key, 2));
+        assert_eq!(ix.accounts[3], AccountMeta::new(rent_collector, false));
+        assert_eq!(
+            ix.data,
+            instruction_discriminator("vault_transaction_accounts_close")
+        );
+    }
+}
@@ -0,0 +1,542 @@
+use crate::pda::{ephemeral_signer_pda, vault_pda};
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    message::AddressLookupTableAccount,
+    pubkey::Pubkey,
+};
+use std::collections::BTreeMap;
+
+const ADDRESS_LOOKUP_TABLE_META_SIZE: usize = 56;
+
+#[derive(Clone, Debug, BorshDeserialize, BorshSerialize)]
+pub struct TransactionMessage {
+    pub num_signers: u8,
+    pub num_writable_signers: u8,
+    pub num_writable_non_signers: u8,
+    pub account_keys: Vec<Pubkey>,
+    pub instructions: Vec<CompiledInstruction>,
+    pub address_table_lookups: Vec<MessageAddressTableLookup>,
+}
+
+#[derive(Clone, Debug, BorshDeserialize, BorshSerialize)]
+pub struct CompiledInstruction {
+    pub program_id_index: u8,
+    pub account_indexes: Vec<u8>,
+    pub data: Vec<u8>,
+}
+
+#[derive(Clone, Debug, BorshDeserialize, BorshSerialize)]
+pub struct MessageAddressTableLookup {
+    pub account_key: Pubkey,
+    pub writable_indexes: Vec<u8>,
+    pub readonly_indexes: Vec<u8>,
+}
+
+/// Where an account of a vault transaction message comes from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MessageAccountSource {
+    Static,
+    Lookup { table: Pubkey, index: u8 },
+}
+
+#[derive(Clone, Debug)]
+pub struct MessageAccount {
+    pub pubkey: Pubkey,
+    pub is_signer: bool,
+    pub is_writable: bool,
+    pub source: MessageAccountSource,
+}
+
+/// A vault transaction message with every account resolved, in the same
+/// order the compiled instruction indexes refer to.
+#[derive(Clone, Debug)]
+pub struct DecodedTransactionMessage {
+    pub accounts: Vec<MessageAccount>,
+    pub instructions: Vec<Instruction>,
+}
+
+#[derive(Default, Debug, Clone)]
+struct CompiledKeyMeta {
+    is_signer: bool,
+    is_writable: bool,
+    is_invoked: bool,
+}
+
+pub fn compile_transaction_message(
+    vault_key: &Pubkey,
+    instructions: &[Instruction],
+    address_lookup_tables: &[AddressLookupTableAccount],
+) -> Result<TransactionMessage, String> {
+    let mut key_meta_map = BTreeMap::<Pubkey, CompiledKeyMeta>::new();
+
+    for ix in instructions {
+        key_meta_map.entry(ix.program_id).or_default().is_invoked = true;
+        for account in &ix.accounts {
+            let meta = key_meta_map.entry(account.pubkey).or_default();
+            meta.is_signer |= account.is_signer;
+            meta.is_writable |= account.is_writable;
+        }
+    }
+
+    key_meta_map.remove(vault_key);
+
+    let mut address_table_lookups = Vec::new();
+    let mut loaded_writable = Vec::new();
+    let mut loaded_readonly = Vec::new();
+    for table in address_lookup_tables {
+        let mut writable_indexes = Vec::new();
+        let mut readonly_indexes = Vec::new();
+        for (index, address) in table.addresses.iter().enumerate() {
+            let Some(meta) = key_meta_map.get(address) else {
+                continue;
+            };
+            if meta.is_signer || meta.is_invoked {
+                continue;
+            }
+            let index = checked_u8(index)?;
+            if meta.is_writable {
+                writable_indexes.push(index);
+                loaded_writable.push(*address);
+            } else {
+                readonly_indexes.push(index);
+                loaded_readonly.push(*address);
+            }
+            key_meta_map.remove(address);
+        }
+        if !writable_indexes.is_empty() || !readonly_indexes.is_empty() {
+            address_table_lookups.push(MessageAddressTableLookup {
+                account_key: table.key,
+                writable_indexes,
+                readonly_indexes,
+            });
+        }
+    }
+
+    let writable_signers = std::iter::once(*vault_key)
+        .chain(
+            key_meta_map
+                .iter()
+                .filter_map(|(key, meta)| (meta.is_signer && meta.is_writable).then_some(*key)),
+        )
+        .collect::<Vec<_>>();
+    let readonly_signers = key_meta_map
+        .iter()
+        .filter_map(|(key, meta)| (meta.is_signer && !meta.is_writable).then_some(*key))
+        .collect::<Vec<_>>();
+    let writable_non_signers = key_meta_map
+        .iter()
+        .filter_map(|(key, meta)| (!meta.is_signer && meta.is_writable).then_some(*key))
+        .collect::<Vec<_>>();
+    let readonly_non_signers = key_meta_map
+        .iter()
+        .filter_map(|(key, meta)| (!meta.is_signer && !meta.is_writable).then_some(*key))
+        .collect::<Vec<_>>();
+
+    let num_signers = checked_u8(writable_signers.len() + readonly_signers.len())?;
+    let num_writable_signers = checked_u8(writable_signers.len())?;
+    let num_writable_non_signers = checked_u8(writable_non_signers.len())?;
+
+    let account_keys = writable_signers
+        .into_iter()
+        .chain(readonly_signers)
+        .chain(writable_non_signers)
+        .chain(readonly_non_signers)
+        .collect::<Vec<_>>();
+    let all_keys = account_keys
+        .iter()
+        .chain(&loaded_writable)
+        .chain(&loaded_readonly)
+        .copied()
+        .collect::<Vec<_>>();
+
+    let mut compiled_instructions = Vec::with_capacity(instructions.len());
+    for ix in instructions {
+        let program_id_index = account_index(&account_keys, &ix.program_id)?;
+        let account_indexes = ix
+            .accounts
+            .iter()
+            .map(|account| account_index(&all_keys, &account.pubkey))
+            .collect::<Result<Vec<_>, _>>()?;
+        compiled_instructions.push(CompiledInstruction {
+            program_id_index,
+            account_indexes,
+            data: ix.data.clone(),
+        });
+    }
+
+    Ok(TransactionMessage {
+        num_signers,
+        num_writable_signers,
+        num_writable_non_signers,
+        account_keys,
+        instructions: compiled_instructions,
+        address_table_lookups,
+    })
+}
+
+/// Serializes a message in the compact layout Squads expects as the
+/// `transaction_message` argument (u8 vector lengths, u16 data length).
+pub fn serialize_transaction_message_arg(message: &TransactionMessage) -> Result<Vec<u8>, String> {
+    let mut out = vec![
+        message.num_signers,
+        message.num_writable_signers,
+        message.num_writable_non_signers,
+    ];
+
+    write_small_vec_u8(
+        &mut out,
+        &message.account_keys,
+        "account_keys",
+        |out, key| {
+            out.extend_from_slice(key.as_ref());
+            Ok(())
+        },
+    )?;
+    write_small_vec_u8(
+        &mut out,
+        &message.instructions,
+        "instructions",
+        |out, ix| {
+            out.push(ix.program_id_index);
+            write_small_bytes_u8(out, &ix.account_indexes, "instruction account_indexes")?;
+            write_small_bytes_u16(out, &ix.data, "instruction data")
+        },
+    )?;
+    write_small_vec_u8(
+        &mut out,
+        &message.address_table_lookups,
+        "address_table_lookups",
+        |out, lookup| {
+            out.extend_from_slice(lookup.account_key.as_ref());
+            write_small_bytes_u8(out, &lookup.writable_indexes, "lookup writable_indexes")?;
+            write_small_bytes_u8(out, &lookup.readonly_indexes, "lookup readonly_indexes")
+        },
+    )?;
+
+    Ok(out)
+}
+
+/// Inverse of [`serialize_transaction_message_arg`].
+pub fn deserialize_transaction_message_arg(bytes: &[u8]) -> Result<TransactionMessage, String> {
+    let mut reader = CompactReader { bytes, offset: 0 };
+    let num_signers = reader.u8()?;
+    let num_writable_signers = reader.u8()?;
+    let num_writable_non_signers = reader.u8()?;
+    let account_keys = (0..reader.u8()?)
+        .map(|_| reader.pubkey())
+        .collect::<Result<Vec<_>, _>>()?;
+    let instructions = (0..reader.u8()?)
+        .map(|_| {
+            let program_id_index = reader.u8()?;
+            let len = usize::from(reader.u8()?);
+            let account_indexes = reader.take(len)?.to_vec();
+            let len = usize::from(reader.u16()?);
+            let data = reader.take(len)?.to_vec();
+            Ok(CompiledInstruction {
+                program_id_index,
+                account_indexes,
+                data,
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    let address_table_lookups = (0..reader.u8()?)
+        .map(|_| {
+            let account_key = reader.pubkey()?;
+            let len = usize::from(reader.u8()?);
+            let writable_indexes = reader.take(len)?.to_vec();
+            let len = usize::from(reader.u8()?);
+            let readonly_indexes = reader.take(len)?.to_vec();
+            Ok(MessageAddressTableLookup {
+                account_key,
+                writable_indexes,
+                readonly_indexes,
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    if reader.offset != bytes.len() {
+        return Err("Squads transaction message has trailing bytes".to_string());
+    }
+    Ok(TransactionMessage {
+        num_signers,
+        num_writable_signers,
+        num_writable_non_signers,
+        account_keys,
+        instructions,
+        address_table_lookups,
+    })
+}
+
+/// Expands a stored vault transaction message back into plain instructions.
+/// Every lookup table referenced by the message must be supplied.
+pub fn decode_transaction_message(
+    message: &TransactionMessage,
+    address_lookup_tables: &[AddressLookupTableAccount],
+) -> Result<DecodedTransactionMessage, String> {
+    let mut accounts = message
+        .account_keys
+        .iter()
+        .enumerate()
+        .map(|(index, pubkey)| MessageAccount {
+            pubkey: *pubkey,
+            is_signer: is_signer_index(message, index),
+            is_writable: is_static_writable_index(message, index),
+            source: MessageAccountSource::Static,
+        })
+        .collect::<Vec<_>>();
+    let mut loaded_readonly = Vec::new();
+    for lookup in &message.address_table_lookups {
+        let table = find_lookup_table(address_lookup_tables, &lookup.account_key)?;
+        for (indexes, is_writable) in [
+            (&lookup.writable_indexes, true),
+            (&lookup.readonly_indexes, false),
+        ] {
+            for index in indexes {
+                let account = MessageAccount {
+                    pubkey: resolve_lookup_address(table, *index)?,
+                    is_signer: false,
+                    is_writable,
+                    source: MessageAccountSource::Lookup {
+                        table: table.key,
+                        index: *index,
+                    },
+                };
+                if is_writable {
+                    accounts.push(account);
+                } else {
+                    loaded_readonly.push(account);
+                }
+            }
+        }
+    }
+    accounts.extend(loaded_readonly);
+
+    let instructions = message
+        .instructions
+        .iter()
+        .map(|ix| {
+            let account = |index: u8| {
+                accounts.get(usize::from(index)).ok_or_else(|| {
+                    format!("Squads instruction account index {index} is out of range")
+                })
+            };
+            let program_id = account(ix.program_id_index)?.pubkey;
+            let metas = ix
+                .account_indexes
+                .iter()
+                .map(|index| {
+                    account(*index).map(|account| AccountMeta {
+                        pubkey: account.pubkey,
+                        is_signer: account.is_signer,
+                        is_writable: account.is_writable,
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(Instruction {
+                program_id,
+                accounts: metas,
+                data: ix.data.clone(),
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    Ok(DecodedTransactionMessage {
+        accounts,
+        instructions,
+    })
+}
+
+/// Reads the addresses stored in an Address Lookup Table account without
+/// pulling in the lookup-table program crate.
+pub fn parse_address_lookup_table(
+    key: &Pubkey,
+    data: &[u8],
+) -> Result<AddressLookupTableAccount, String> {
+    if data.len() < ADDRESS_LOOKUP_TABLE_META_SIZE
+        || data[..4] != 1_u32.to_le_bytes()
+        || (data.len() - ADDRESS_LOOKUP_TABLE_META_SIZE) % 32 != 0
+    {
+        return Err(format!("{key} is not a valid Address Lookup Table account"));
+    }
+    let addresses = data[ADDRESS_LOOKUP_TABLE_META_SIZE..]
+        .chunks_exact(32)
+        .map(|chunk| {
+            Pubkey::try_from(chunk).map_err(|_| format!("{key} has invalid lookup table data"))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(AddressLookupTableAccount {
+        key: *key,
+        addresses,
+    })
+}
+
+pub(crate) fn message_accounts_for_execute(
+    message: &TransactionMessage,
+    multisig: &Pubkey,
+    transaction: &Pubkey,
+    vault_index: u8,
+    ephemeral_signers: u8,
+    address_lookup_tables: &[AddressLookupTableAccount],
+) -> Result<Vec<AccountMeta>, String> {
+    let vault = vault_pda(multisig, vault_index);
+    let ephemeral_signer_pdas = (0..ephemeral_signers)
+        .map(|index| ephemeral_signer_pda(transaction, index))
+        .collect::<Vec<_>>();
+
+    let mut accounts = message
+        .address_table_lookups
+        .iter()
+        .map(|lookup| AccountMeta::new_readonly(lookup.account_key, false))
+        .collect::<Vec<_>>();
+    accounts.extend(
+        message
+            .account_keys
+            .iter()
+            .enumerate()
+            .map(|(index, pubkey)| {
+                let is_writable = is_static_writable_index(message, index);
+                let is_signer = is_signer_index(message, index)
+                    && pubkey != &vault
+                    && !ephemeral_signer_pdas.contains(pubkey);
+                AccountMeta {
+                    pubkey: *pubkey,
+                    is_writable,
+                    is_signer,
+                }
+            }),
+    );
+    for lookup in &message.address_table_lookups {
+        let table = find_lookup_table(address_lookup_tables, &lookup.account_key)?;
+        for index in &lookup.writable_indexes {
+            accounts.push(AccountMeta::new(
+                resolve_lookup_address(table, *index)?,
+                false,
+            ));
+        }
+        for index in &lookup.readonly_indexes {
+            accounts.push(AccountMeta::new_readonly(
+                resolve_lookup_address(table, *index)?,
+                false,
+            ));
+        }
+    }
+    Ok(accounts)
+}
+
+fn find_lookup_table<'a>(
+    address_lookup_tables: &'a [AddressLookupTableAccount],
+    key: &Pubkey,
+) -> Result<&'a AddressLookupTableAccount, String> {
+    address_lookup_tables
+        .iter()
+        .find(|table| table.key == *key)
+        .ok_or_else(|| format!("Missing Address Lookup Table {key}"))
+}
+
+fn resolve_lookup_address(table: &AddressLookupTableAccount, index: u8) -> Result<Pubkey, String> {
+    table
+        .addresses
+        .get(usize::from(index))
+        .copied()
+        .ok_or_else(|| {
+            format!(
+                "Address Lookup Table {} index {index} is out of range",
+                table.key
+            )
+        })
+}
+
+struct CompactReader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> CompactReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let end = self
+            .offset
+            .checked_add(len)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or_else(|| "Squads transaction message is truncated".to_string())?;
+        let slice = &self.bytes[self.offset..end];
+        self.offset = end;
+        Ok(slice)
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn pubkey(&mut self) -> Result<Pubkey, String> {
+        Pubkey::try_from(self.take(32)?)
+            .map_err(|_| "Squads transaction message has an invalid account key".to_string())
+    }
+}
+
+fn checked_u8(value: usize) -> Result<u8, String> {
+    u8::try_from(value).map_err(|_| "Squads transaction references too many accounts".to_string())
+}
+
+fn checked_u16(value: usize, field: &str) -> Result<u16, String> {
+    u16::try_from(value).map_err(|_| format!("Squads {field} is too large"))
+}
+
+fn write_small_vec_u8<T, F>(
+    out: &mut Vec<u8>,
+    values: &[T],
+    field: &str,
+    mut write_item: F,
+) -> Result<(), String>
+where
+    F: FnMut(&mut Vec<u8>, &T) -> Result<(), String>,
+{
+    out.push(checked_u8(values.len()).map_err(|_| format!("Too many Squads {field}"))?);
+    for value in values {
+        write_item(out, value)?;
+    }
+    Ok(())
+}
+
+fn write_small_bytes_u8(out: &mut Vec<u8>, values: &[u8], field: &str) -> Result<(), String> {
+    out.push(checked_u8(values.len()).map_err(|_| format!("Too many Squads {field}"))?);
+    out.extend_from_slice(values);
+    Ok(())
+}
+
+fn write_small_bytes_u16(out: &mut Vec<u8>, values: &[u8], field: &str) -> Result<(), String> {
+    out.extend_from_slice(&checked_u16(values.len(), field)?.to_le_bytes());
+    out.extend_from_slice(values);
+    Ok(())
+}
+
+fn account_index(account_keys: &[Pubkey], pubkey: &Pubkey) -> Result<u8, String> {
+    account_keys
+        .iter()
+        .position(|key| key == pubkey)
+        .ok_or_else(|| "Squads transaction message is missing an account".to_string())
+        .and_then(checked_u8)
+}
+
+fn is_static_writable_index(message: &TransactionMessage, index: usize) -> bool {
+    let num_signers = usize::from(message.num_signers);
+    let num_writable_signers = usize::from(message.num_writable_signers);
+    let num_writable_non_signers = usize::from(message.num_writable_non_signers);
+
+    if index < num_writable_signers {
+        return true;
+    }
+    if index >= num_signers {
+        let non_signer_index = index.saturating_sub(num_signers);
+        return non_signer_index < num_writable_non_signers;
+    }
+    false
+}
+
+fn is_signer_index(message: &TransactionMessage, index: usize) -> bool {
+    index < usize::from(message.num_signers)
+}
@@ -0,0 +1,93 @@
+use crate::SQUADS_PROGRAM_ID;
+use solana_sdk::pubkey::Pubkey;
+
+const SEED_PREFIX: &[u8] = b"multisig";
+const SEED_PROGRAM_CONFIG: &[u8] = b"program_config";
+const SEED_MULTISIG: &[u8] = b"multisig";
+const SEED_TRANSACTION: &[u8] = b"transaction";
+const SEED_PROPOSAL: &[u8] = b"proposal";
+const SEED_VAULT: &[u8] = b"vault";
+const SEED_BATCH_TRANSACTION: &[u8] = b"batch_transaction";
+const SEED_EPHEMERAL_SIGNER: &[u8] = b"ephemeral_signer";
+
+pub fn program_config_pda() -> Pubkey {
+    Pubkey::find_program_address(&[SEED_PREFIX, SEED_PROGRAM_CONFIG], &SQUADS_PROGRAM_ID).0
+}
+
+pub fn multisig_pda(create_key: &Pubkey) -> Pubkey {
+    multisig_pda_with_bump(create_key).0
+}
+
+pub fn multisig_pda_with_bump(create_key: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[SEED_PREFIX, SEED_MULTISIG, create_key.as_ref()],
+        &SQUADS_PROGRAM_ID,
+    )
+}
+
+pub fn vault_pda(multisig: &Pubkey, vault_index: u8) -> Pubkey {
+    Pubkey::find_program_address(
+        &[SEED_PREFIX, multisig.as_ref(), SEED_VAULT, &[vault_index]],
+        &SQUADS_PROGRAM_ID,
+    )
+    .0
+}
+
+pub fn transaction_pda(multisig: &Pubkey, transaction_index: u64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            SEED_PREFIX,
+            multisig.as_ref(),
+            SEED_TRANSACTION,
+            transaction_index.to_le_bytes().as_ref(),
+        ],
+        &SQUADS_PROGRAM_ID,
+    )
+    .0
+}
+
+pub fn proposal_pda(multisig: &Pubkey, transaction_index: u64) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            SEED_PREFIX,
+            multisig.as_ref(),
+            SEED_TRANSACTION,
+            transaction_index.to_le_bytes().as_ref(),
+            SEED_PROPOSAL,
+        ],
+        &SQUADS_PROGRAM_ID,
+    )
+    .0
+}
+
+pub fn batch_transaction_pda(
+    multisig: &Pubkey,
+    batch_index: u64,
+    transaction_index: u32,
+) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            SEED_PREFIX,
+            multisig.as_ref(),
+            SEED_TRANSACTION,
+            batch_index.to_le_bytes().as_ref(),
+            SEED_BATCH_TRANSACTION,
+            transaction_index.to_le_bytes().as_ref(),
+        ],
+        &SQUADS_PROGRAM_ID,
+    )
+    .0
+}
+
+pub fn ephemeral_signer_pda(transaction: &Pubkey, index: u8) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            SEED_PREFIX,
+            transaction.as_ref(),
+            SEED_EPHEMERAL_SIGNER,
+            index.to_le_bytes().as_ref(),
+        ],
+        &SQUADS_PROGRAM_ID,
+    )
+    .0
+}
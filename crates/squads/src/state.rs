@@ -0,0 +1,184 @@
+use crate::message::TransactionMessage;
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_sdk::pubkey::Pubkey;
+
+#[derive(Clone, Debug, BorshDeserialize, BorshSerialize)]
+pub struct Member {
+    pub key: Pubkey,
+    pub permissions: Permissions,
+}
+
+#[derive(Clone, Copy, Debug, Default, BorshDeserialize, BorshSerialize)]
+pub struct Permissions {
+    pub mask: u8,
+}
+
+impl Permissions {
+    pub const INITIATE: u8 = 0b001;
+    pub const VOTE: u8 = 0b010;
+    pub const EXECUTE: u8 = 0b100;
+
+    pub fn all() -> Self {
+        Self { mask: 0b111 }
+    }
+
+    pub fn has(&self, permission: u8) -> bool {
+        self.mask & permission == permission
+    }
+}
+
+#[derive(Clone, Debug, BorshDeserialize)]
+pub struct ProgramConfig {
+    pub authority: Pubkey,
+    pub multisig_creation_fee: u64,
+    pub treasury: Pubkey,
+    pub _reserved: [u8; 64],
+}
+
+#[derive(Clone, Debug, BorshDeserialize)]
+pub struct Multisig {
+    pub create_key: Pubkey,
+    pub config_authority: Pubkey,
+    pub threshold: u16,
+    pub time_lock: u32,
+    pub transaction_index: u64,
+    pub stale_transaction_index: u64,
+    pub rent_collector: Option<Pubkey>,
+    pub bump: u8,
+    pub members: Vec<Member>,
+}
+
+#[derive(Clone, Debug, BorshDeserialize)]
+pub struct Proposal {
+    pub multisig: Pubkey,
+    pub transaction_index: u64,
+    pub status: ProposalStatus,
+    pub bump: u8,
+    pub approved: Vec<Pubkey>,
+    pub rejected: Vec<Pubkey>,
+    pub cancelled: Vec<Pubkey>,
+}
+
+#[derive(Clone, Debug, BorshDeserialize)]
+pub struct VaultTransaction {
+    pub multisig: Pubkey,
+    pub creator: Pubkey,
+    pub index: u64,
+    pub bump: u8,
+    pub vault_index: u8,
+    pub vault_bump: u8,
+    pub ephemeral_signer_bumps: Vec<u8>,
+    pub message: TransactionMessage,
+}
+
+/// Leading fields of a `ConfigTransaction`. The config actions that follow
+/// are not decoded; `decode_account` ignores the remaining bytes.
+#[derive(Clone, Debug, BorshDeserialize)]
+pub struct ConfigTransaction {
+    pub multisig: Pubkey,
+    pub creator: Pubkey,
+    pub index: u64,
+    pub bump: u8,
+}
+
+#[derive(Clone, Debug, BorshDeserialize)]
+pub struct Batch {
+    pub multisig: Pubkey,
+    pub creator: Pubkey,
+    pub index: u64,
+    pub bump: u8,
+    pub vault_index: u8,
+    pub vault_bump: u8,
+    pub size: u32,
+    pub executed_transaction_index: u32,
+}
+
+#[derive(Clone, Debug, BorshDeserialize)]
+pub struct VaultBatchTransaction {
+    pub bump: u8,
+    pub ephemeral_signer_bumps: Vec<u8>,
+    pub message: TransactionMessage,
+}
+
+#[derive(Clone, Debug, BorshDeserialize)]
+pub enum ProposalStatus {
+    Draft { timestamp: i64 },
+    Active { timestamp: i64 },
+    Rejected { timestamp: i64 },
+    Approved { timestamp: i64 },
+    Executing,
+    Executed { timestamp: i64 },
+    Cancelled { timestamp: i64 },
+}
+
+impl ProposalStatus {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Draft { .. } => "draft",
+            Self::Active { .. } => "active",
+            Self::Rejected { .. } => "rejected",
+            Self::Approved { .. } => "approved",
+            Self::Executing => "executing",
+            Self::Executed { .. } => "executed",
+            Self::Cancelled { .. } => "cancelled",
+        }
+    }
+
+    /// Mirrors the program's `vault_transaction_accounts_close` check. An
+    /// approved vault transaction stays executable even once stale.
+    pub fn allows_vault_transaction_close(&self, is_stale: bool) -> bool {
+        match self {
+            Self::Draft { .. } | Self::Active { .. } => is_stale,
+            Self::Approved { .. } | Self::Executing => false,
+            Self::Rejected { .. } | Self::Executed { .. } | Self::Cancelled { .. } => true,
+        }
+    }
+
+    /// Mirrors the program's `config_transaction_accounts_close` check. Stale
+    /// config transactions can never execute, whatever their approval state.
+    pub fn allows_config_transaction_close(&self, is_stale: bool) -> bool {
+        match self {
+            Self::Draft { .. } | Self::Active { .. } | Self::Approved { .. } => is_stale,
+            Self::Executing => false,
+            Self::Rejected { .. } | Self::Executed { .. } | Self::Cancelled { .. } => true,
+        }
+    }
+
+    /// Whether the proposal can still be voted on or executed.
+    pub fn is_pending(&self) -> bool {
+        matches!(
+            self,
+            Self::Draft { .. } | Self::Active { .. } | Self::Approved { .. } | Self::Executing
+        )
+    }
+}
+
+/// Kind of account stored at a multisig's transaction PDA.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransactionAccountKind {
+    Vault,
+    Config,
+    Batch,
+}
+
+impl TransactionAccountKind {
+    pub fn from_account_data(data: &[u8]) -> Option<Self> {
+        let discriminator = data.get(..8)?;
+        [
+            (Self::Vault, "VaultTransaction"),
+            (Self::Config, "ConfigTransaction"),
+            (Self::Batch, "Batch"),
+        ]
+        .into_iter()
+        .find(|(_, name)| discriminator == crate::account_discriminator(name))
+        .map(|(kind, _)| kind)
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Vault => "vault",
+            Self::Config => "config",
+            Self::Batch => "batch",
+        }
+    }
+}
@@ -0,0 +1,35 @@
+#!/usr/bin/env bash
+# Capture a Squads v4 account from an RPC node into <name>.base64 and print the slot
+# it was read at, for the provenance table in README.md.
+#
+#   ./capture.sh multisig <ADDRESS> [RPC_URL]
+set -euo pipefail
+
+name="${1:?usage: capture.sh <multisig|proposal|vault_transaction> <address> [rpc_url]}"
+address="${2:?missing account address}"
+rpc_url="${3:-https://api.mainnet-beta.solana.com}"
+
+case "$name" in
+  multisig | proposal | vault_transaction) ;;
+  *)
+    echo "unknown fixture: $name" >&2
+    exit 1
+    ;;
+esac
+
+response="$(curl -sS "$rpc_url" -X POST -H 'content-type: application/json' -d "{
+  \"jsonrpc\": \"2.0\",
+  \"id\": 1,
+  \"method\": \"getAccountInfo\",
+  \"params\": [\"$address\", {\"encoding\": \"base64\", \"commitment\": \"finalized\"}]
+}")"
+
+data="$(jq -er '.result.value.data[0]' <<<"$response")" || {
+  echo "no account data for $address: $response" >&2
+  exit 1
+}
+slot="$(jq -r '.result.context.slot' <<<"$response")"
+
+cd "$(dirname "$0")"
+printf '%s\n' "$data" >"$name.base64"
+echo "| \`$name.base64\` | \`$address\` | $slot |"
@@ -0,0 +1,114 @@
+//! Decodes checked-in account data; see `fixtures/README.md` for where it comes from.
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use fnzero_safe_squads::{
+    decode_account, Multisig, Permissions, Proposal, ProposalStatus, TransactionAccountKind,
+    VaultTransaction, SYSTEM_PROGRAM_ID,
+};
+use sha2::{Digest, Sha256};
+use solana_sdk::pubkey::Pubkey;
+
+fn fixture(name: &str) -> Vec<u8> {
+    let path = format!(
+        "{}/tests/fixtures/{name}.base64",
+        env!("CARGO_MANIFEST_DIR")
+    );
+    let encoded = std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("{path}: {e}"));
+    STANDARD.decode(encoded.trim()).unwrap()
+}
+
+fn fixture_key(name: &str) -> Pubkey {
+    Pubkey::new_from_array(Sha256::digest(format!("fixture-{name}")).into())
+}
+
+#[test]
+fn decodes_multisig_fixture() {
+    let multisig = decode_account::<Multisig>(&fixture("multisig"), "Multisig").unwrap();
+    assert_eq!(multisig.create_key, fixture_key("create-key"));
+    assert_eq!(multisig.config_authority, Pubkey::default());
+    assert_eq!(multisig.threshold, 2);
+    assert_eq!(multisig.time_lock, 0);
+    assert_eq!(multisig.transaction_index, 12);
+    assert_eq!(multisig.stale_transaction_index, 9);
+    assert_eq!(multisig.rent_collector, Some(fixture_key("rent-collector")));
+    assert_eq!(multisig.bump, 255);
+    let members: Vec<_> = multisig
+        .members
+        .iter()
+        .map(|member| (member.key, member.permissions.mask))
+        .collect();
+    assert_eq!(
+        members,
+        vec![
+            (fixture_key("member-1"), Permissions::all().mask),
+            (
+                fixture_key("member-2"),
+                Permissions::INITIATE | Permissions::VOTE
+            ),
+            (fixture_key("member-3"), Permissions::VOTE),
+        ]
+    );
+}
+
+#[test]
+fn decodes_proposal_fixture() {
+    let proposal = decode_account::<Proposal>(&fixture("proposal"), "Proposal").unwrap();
+    assert_eq!(proposal.multisig, fixture_key("multisig"));
+    assert_eq!(proposal.transaction_index, 12);
+    assert!(matches!(
+        proposal.status,
+        ProposalStatus::Approved {
+            timestamp: 1_718_000_000
+        }
+    ));
+    assert_eq!(proposal.bump, 254);
+    assert_eq!(
+        proposal.approved,
+        vec![fixture_key("member-1"), fixture_key("member-2")]
+    );
+    assert!(proposal.rejected.is_empty());
+    assert!(proposal.cancelled.is_empty());
+}
+
+#[test]
+fn decodes_vault_transaction_fixture() {
+    let data = fixture("vault_transaction");
+    assert_eq!(
+        TransactionAccountKind::from_account_data(&data),
+        Some(TransactionAccountKind::Vault)
+    );
+    let transaction = decode_account::<VaultTransaction>(&data, "VaultTransaction").unwrap();
+    assert_eq!(transaction.multisig, fixture_key("multisig"));
+    assert_eq!(transaction.creator, fixture_key("member-1"));
+    assert_eq!(transaction.index, 12);
+    assert_eq!(transaction.bump, 253);
+    assert_eq!(transaction.vault_index, 0);
+    assert_eq!(transaction.vault_bump, 252);
+    assert!(transaction.ephemeral_signer_bumps.is_empty());
+
+    let message = &transaction.message;
+    assert_eq!(
+        (
+            message.num_signers,
+            message.num_writable_signers,
+            message.num_writable_non_signers
+        ),
+        (1, 1, 1)
+    );
+    assert_eq!(
+        message.account_keys,
+        vec![
+            fixture_key("vault"),
+            fixture_key("recipient"),
+            SYSTEM_PROGRAM_ID
+        ]
+    );
+    assert_eq!(message.instructions.len(), 1);
+    let transfer = &message.instructions[0];
+    assert_eq!(transfer.program_id_index, 2);
+    assert_eq!(transfer.account_indexes, vec![0, 1]);
+    let mut data = 2_u32.to_le_bytes().to_vec();
+    data.extend_from_slice(&250_000_000_u64.to_le_bytes());
+    assert_eq!(transfer.data, data);
+    assert!(message.address_table_lookups.is_empty());
+}